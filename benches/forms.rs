@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+
+use racoon::core::forms::{Files, FormConstraints, FormData};
+use racoon::core::headers::{HeaderValue, Headers};
+use racoon::core::parser::multipart::MultipartParser;
+use racoon::core::parser::urlencoded::UrlEncodedParser;
+use racoon::core::stream::{Stream, TestStreamWrapper};
+use racoon::core::testing::request::TestRequest;
+use racoon::forms::fields::input_field::InputField;
+use racoon::forms::{FormFields, FormValidator};
+
+const BOUNDARY: &str = "RacoonBenchBoundary";
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+fn multipart_body(file_size: usize) -> Vec<u8> {
+    let mut body = vec![];
+
+    for (name, value) in [("title", "Quarterly report"), ("folder", "documents")] {
+        body.extend(format!("--{}\r\n", BOUNDARY).as_bytes());
+        body.extend(
+            format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).as_bytes(),
+        );
+        body.extend(value.as_bytes());
+        body.extend(b"\r\n");
+    }
+
+    body.extend(format!("--{}\r\n", BOUNDARY).as_bytes());
+    body.extend(b"Content-Disposition: form-data; name=\"file\"; filename=\"report.bin\"\r\n");
+    body.extend(b"Content-Type: application/octet-stream\r\n\r\n");
+    // Bytes resembling the boundary make the parser scan for the delimiter more often.
+    body.extend(b"--RacoonBench\r\n".iter().cycle().take(file_size));
+    body.extend(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+fn bench_multipart(c: &mut Criterion) {
+    let runtime = runtime();
+    let form_constraints = Arc::new(FormConstraints::default());
+    let mut group = c.benchmark_group("multipart");
+    group.sample_size(20);
+
+    for file_size in [16 * 1024, 256 * 1024, 4 * 1024 * 1024] {
+        let body = multipart_body(file_size);
+        let mut headers = Headers::new();
+        headers.set(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        );
+        headers.set("Content-Length", body.len().to_string());
+
+        group.throughput(Throughput::Bytes(body.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(file_size), &body, |b, body| {
+            b.iter_batched(
+                || {
+                    let stream: Stream = Box::new(TestStreamWrapper::new(body.clone(), 8096));
+                    Arc::new(stream)
+                },
+                |stream| {
+                    let result = runtime.block_on(MultipartParser::parse(
+                        stream,
+                        form_constraints.clone(),
+                        &headers,
+                    ));
+                    black_box(result.unwrap());
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_urlencoded(c: &mut Criterion) {
+    let runtime = runtime();
+    let form_constraints = Arc::new(FormConstraints::default());
+
+    let body = (0..50)
+        .map(|i| format!("field{}=value%20{}%26more", i, i))
+        .collect::<Vec<String>>()
+        .join("&")
+        .into_bytes();
+    let mut headers = Headers::new();
+    headers.set("Content-Type", "application/x-www-form-urlencoded");
+    headers.set("Content-Length", body.len().to_string());
+
+    c.bench_function("urlencoded", |b| {
+        b.iter_batched(
+            || {
+                let stream: Stream = Box::new(TestStreamWrapper::new(body.clone(), 8096));
+                Arc::new(stream)
+            },
+            |stream| {
+                let result = runtime.block_on(UrlEncodedParser::parse(
+                    stream,
+                    &headers,
+                    form_constraints.clone(),
+                ));
+                black_box(result.unwrap());
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+struct SignupForm {
+    username: InputField<String>,
+    email: InputField<String>,
+    bio: InputField<Option<String>>,
+    tags: InputField<Vec<String>>,
+}
+
+impl FormValidator for SignupForm {
+    fn new() -> Self {
+        Self {
+            username: InputField::new("username").min_length(3).max_length(30),
+            email: InputField::new("email").max_length(254),
+            bio: InputField::new("bio").max_length(500),
+            tags: InputField::new("tags").max_length(20),
+        }
+    }
+
+    fn form_fields(&mut self) -> FormFields {
+        vec![
+            Box::new(self.username.clone()),
+            Box::new(self.email.clone()),
+            Box::new(self.bio.clone()),
+            Box::new(self.tags.clone()),
+        ]
+    }
+}
+
+fn signup_form_data() -> FormData {
+    let mut form_data = FormData::new();
+    form_data.insert("username".to_string(), vec!["john".to_string()]);
+    form_data.insert("email".to_string(), vec!["john@example.com".to_string()]);
+    form_data.insert("bio".to_string(), vec!["Hello ".repeat(50)]);
+    form_data.insert(
+        "tags".to_string(),
+        (0..10).map(|i| format!("tag{}", i)).collect(),
+    );
+    form_data
+}
+
+fn bench_form_validation(c: &mut Criterion) {
+    let runtime = runtime();
+    let mut group = c.benchmark_group("form_validation");
+
+    group.bench_function("fields", |b| {
+        b.iter_batched(
+            || (SignupForm::new(), signup_form_data()),
+            |(mut form, mut form_data)| {
+                runtime.block_on(async {
+                    let mut files = Files::new();
+                    for mut field in form.form_fields() {
+                        field.validate(&mut form_data, &mut files).await.unwrap();
+                    }
+                    black_box(form_data);
+                })
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    // Includes reading and decoding the urlencoded body.
+    group.bench_function("request", |b| {
+        b.iter_batched(
+            || {
+                let mut request = TestRequest::post("/signup");
+                for (name, values) in signup_form_data() {
+                    for value in values {
+                        request = request.field(name.clone(), value);
+                    }
+                }
+                runtime.block_on(request.build())
+            },
+            |request| {
+                let form = runtime.block_on(SignupForm::new().validate(&request));
+                assert!(form.is_ok());
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_multipart,
+    bench_urlencoded,
+    bench_form_validation
+);
+criterion_main!(benches);
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+use racoon::core::headers::{HeaderValue, Headers};
+use racoon::core::parser::headers::read_request_headers;
+use racoon::core::response::status::ResponseStatus;
+use racoon::core::response::{response_to_bytes, AbstractResponse, HttpResponse};
+use racoon::core::server::RequestConstraints;
+use racoon::core::stream::{AbstractStream, Stream, TcpStreamWrapper, TestStreamWrapper};
+
+const REQUEST: &[u8] = b"GET /api/items?page=2 HTTP/1.1\r\n\
+Host: example.com\r\n\
+User-Agent: Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0\r\n\
+Accept: text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8\r\n\
+Accept-Language: en-US,en;q=0.5\r\n\
+Accept-Encoding: gzip, deflate, br\r\n\
+Connection: keep-alive\r\n\
+Cookie: sessionid=5f2b7c1e9a; csrftoken=d41d8cd98f00b204e9800998ecf8427e\r\n\
+Upgrade-Insecure-Requests: 1\r\n\
+Cache-Control: max-age=0\r\n\r\n";
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+fn bench_parse_headers(c: &mut Criterion) {
+    let runtime = runtime();
+    let request_constraints = Arc::new(RequestConstraints {
+        max_request_header_size: 5 * 1024 * 1024,
+        max_header_count: 100,
+        header_read_timeout: None,
+    });
+
+    c.bench_function("parse_headers", |b| {
+        b.iter(|| {
+            let stream: Stream = Box::new(TestStreamWrapper::new(REQUEST.to_vec(), 8096));
+            let result = runtime.block_on(read_request_headers(
+                Arc::new(stream),
+                request_constraints.clone(),
+            ));
+            black_box(result.unwrap());
+        })
+    });
+}
+
+fn bench_read_chunk(c: &mut Criterion) {
+    let runtime = runtime();
+    let (mut client, server) = runtime.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (client, TcpStreamWrapper::from(server, 8096).unwrap())
+    });
+
+    c.bench_function("read_chunk", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                client.write_all(REQUEST).await.unwrap();
+
+                let mut read_size = 0;
+                while read_size < REQUEST.len() {
+                    read_size += server.read_chunk().await.unwrap().len();
+                }
+            })
+        })
+    });
+}
+
+fn bench_response_to_bytes(c: &mut Criterion) {
+    c.bench_function("response_to_bytes", |b| {
+        b.iter(|| {
+            let mut response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Hello World");
+            let headers = response.get_headers();
+            headers.set("Content-Type", "text/html; charset=utf-8");
+            headers.set("Cache-Control", "no-cache");
+            headers.set("Set-Cookie", "sessionid=5f2b7c1e9a; HttpOnly; Path=/");
+            black_box(response_to_bytes(&mut response));
+        })
+    });
+}
+
+fn bench_header_lookup(c: &mut Criterion) {
+    let mut headers = Headers::new();
+    for line in REQUEST.split(|byte| *byte == b'\n').skip(1) {
+        let line = String::from_utf8_lossy(line);
+        if let Some((name, value)) = line.trim().split_once(": ") {
+            headers.set_multiple(name, value);
+        }
+    }
+
+    c.bench_function("header_lookup", |b| {
+        b.iter(|| {
+            black_box(headers.value(black_box("cookie")));
+            black_box(headers.value(black_box("content-length")));
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_parse_headers,
+    bench_read_chunk,
+    bench_response_to_bytes,
+    bench_header_lookup
+);
+criterion_main!(benches);
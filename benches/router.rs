@@ -0,0 +1,87 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use racoon::core::path::{Path, Paths};
+use racoon::core::request::Request;
+use racoon::core::response::status::ResponseStatus;
+use racoon::core::response::{HttpResponse, Response};
+use racoon::core::router::{RouteResult, Router, TrailingSlash};
+
+async fn view(_: Request) -> Response {
+    HttpResponse::ok().empty()
+}
+
+fn paths(count: usize) -> Paths {
+    let mut paths = vec![];
+
+    for i in 0..count {
+        paths.push(Path::get(
+            format!("/resource{}/{{id:int}}/items", i),
+            |request| Box::pin(view(request)),
+        ));
+        paths.push(Path::get(format!("/page{}/about", i), |request| {
+            Box::pin(view(request))
+        }));
+    }
+    paths
+}
+
+///
+/// Matches each route one by one, which is how a router without prefix tree would behave.
+///
+fn linear_resolve<'a>(
+    routes: &'a [(matchit::Router<()>, Path)],
+    request_path: &str,
+) -> Option<&'a Path> {
+    for (route, path) in routes {
+        if let Ok(matched) = route.at(request_path) {
+            if path.is_match(matched.params.iter()) {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+fn bench_resolve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resolve");
+
+    for count in [10, 100, 1000] {
+        let paths = paths(count);
+        let request_path = format!("/resource{}/42/items", count - 1);
+
+        let router = Router::from_paths(&paths, TrailingSlash::Strict);
+        group.bench_with_input(
+            BenchmarkId::new("radix", count),
+            &request_path,
+            |b, request_path| {
+                b.iter(|| {
+                    let result = router.resolve("GET", None, black_box(request_path));
+                    assert!(matches!(result, RouteResult::Found { .. }));
+                })
+            },
+        );
+
+        let routes: Vec<(matchit::Router<()>, Path)> = paths
+            .into_iter()
+            .map(|path| {
+                let mut route = matchit::Router::new();
+                route.insert(path.pattern(), ()).unwrap();
+                (route, path)
+            })
+            .collect();
+        group.bench_with_input(
+            BenchmarkId::new("linear", count),
+            &request_path,
+            |b, request_path| {
+                b.iter(|| {
+                    assert!(linear_resolve(&routes, black_box(request_path)).is_some());
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_resolve);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use racoon::core::parser::chunked::{decode_chunked, ChunkedDecoder};
+
+fuzz_target!(|data: &[u8]| {
+    // First byte picks where the body is split between two reads.
+    let Some((split, body)) = data.split_first() else {
+        return;
+    };
+    let split = (*split as usize).min(body.len());
+
+    let whole = decode_chunked(body);
+
+    let mut decoder = ChunkedDecoder::new();
+    let split_result = decoder.feed(&body[..split]).and_then(|mut decoded| {
+        decoded.extend(decoder.feed(&body[split..])?);
+        Ok(decoded)
+    });
+
+    // Decoding must not depend on how the bytes were received.
+    if let Ok(decoded) = whole {
+        assert_eq!(decoded, split_result.unwrap());
+        assert!(decoder.is_finished());
+    }
+});
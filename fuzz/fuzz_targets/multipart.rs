@@ -0,0 +1,38 @@
+#![no_main]
+
+use std::sync::{Arc, OnceLock};
+
+use libfuzzer_sys::fuzz_target;
+
+use racoon::core::forms::FormConstraints;
+use racoon::core::headers::{HeaderValue, Headers};
+use racoon::core::parser::multipart::MultipartParser;
+
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    // First byte picks the read size, so the boundaries are split at different positions.
+    let Some((chunk_size, body)) = data.split_first() else {
+        return;
+    };
+
+    let mut headers = Headers::new();
+    headers.set("Content-Type", "multipart/form-data; boundary=fuzz");
+    headers.set("Content-Length", body.len().to_string());
+
+    let form_constraints = Arc::new(FormConstraints::default());
+    let _ = runtime().block_on(MultipartParser::parse_bytes(
+        body,
+        *chunk_size as usize,
+        form_constraints,
+        &headers,
+    ));
+});
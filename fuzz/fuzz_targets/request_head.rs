@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use racoon::core::parser::headers::parse_request_head;
+use racoon::core::parser::params::query_params_from_raw;
+use racoon::core::parser::path::path_and_raw_query;
+
+fuzz_target!(|data: &[u8]| {
+    let (request_header, head_size) = match parse_request_head(data, 100) {
+        Ok(Some(result)) => result,
+        _ => return,
+    };
+    assert!(head_size <= data.len());
+
+    // Truncated head must not be reported complete, otherwise the rest of it is read as body.
+    let split = data.len() / 2;
+    if split < head_size {
+        assert!(!matches!(
+            parse_request_head(&data[..split], 100),
+            Ok(Some(_))
+        ));
+    }
+
+    if let Some(raw_path) = request_header.raw_path {
+        let _ = path_and_raw_query(&raw_path);
+        let _ = query_params_from_raw(&raw_path);
+    }
+});
@@ -0,0 +1,252 @@
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+
+use crate::core::auth::Principal;
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::transfer::TransferSize;
+
+///
+/// Details of the handled request passed to the access log writer.
+///
+#[derive(Debug, Clone)]
+pub struct AccessLogEntry {
+    pub time: DateTime<Local>,
+    pub client_ip: Option<String>,
+    /// Id of the principal if the request is authenticated.
+    pub user: Option<String>,
+    pub method: String,
+    /// Path including the query string.
+    pub path: String,
+    pub http_version: String,
+    pub status_code: u32,
+    /// Size of the response body. Streamed responses use the `Content-Length` header if present,
+    /// otherwise the bytes written to the connection.
+    pub bytes: usize,
+    /// Bytes of the request read from the connection including the head.
+    pub bytes_received: u64,
+    pub latency: Duration,
+    /// Value of `X-Request-Id` header of the request or the response.
+    pub request_id: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AccessLogEntry {
+    ///
+    /// Formats the entry in Common Log Format.
+    ///
+    /// Example: `127.0.0.1 - john [10/Oct/2024:13:55:36 +0000] "GET /users HTTP/1.1" 200 2326`
+    ///
+    pub fn common(&self) -> String {
+        format!(
+            "{} - {} [{}] \"{} {} {}\" {} {}",
+            self.client_ip.as_deref().unwrap_or("-"),
+            self.user.as_deref().unwrap_or("-"),
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            self.path,
+            self.http_version,
+            self.status_code,
+            self.bytes
+        )
+    }
+
+    pub fn json(&self) -> String {
+        serde_json::json!({
+            "time": self.time.to_rfc3339(),
+            "client_ip": self.client_ip,
+            "user": self.user,
+            "method": self.method,
+            "path": self.path,
+            "http_version": self.http_version,
+            "status": self.status_code,
+            "bytes": self.bytes,
+            "bytes_received": self.bytes_received,
+            "latency_ms": self.latency.as_secs_f64() * 1000.0,
+            "request_id": self.request_id,
+            "user_agent": self.user_agent,
+        })
+        .to_string()
+    }
+}
+
+#[derive(Clone)]
+pub enum AccessLogFormat {
+    /// Common Log Format used by Apache and Nginx.
+    Common,
+    /// Single line JSON object including latency, request id and user agent.
+    Json,
+    Custom(fn(&AccessLogEntry) -> String),
+}
+
+///
+/// Middleware logging every handled request. Lines are written with `log::info!` under the
+/// `racoon::access` target by default, so they can be filtered separately from the server logs.
+/// Custom writer can forward the entries elsewhere such as `tracing`.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::access_log::{AccessLog, AccessLogFormat};
+/// use racoon::core::server::Server;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(AccessLog::new().format(AccessLogFormat::Json));
+///
+/// let access_log = AccessLog::new().writer(|entry| {
+///     eprintln!("{} {} {} {:?}", entry.method, entry.path, entry.status_code, entry.latency);
+/// });
+/// ```
+///
+#[derive(Clone)]
+pub struct AccessLog {
+    format: AccessLogFormat,
+    writer: Option<fn(&AccessLogEntry)>,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessLog {
+    pub fn new() -> Self {
+        Self {
+            format: AccessLogFormat::Common,
+            writer: None,
+        }
+    }
+
+    pub fn format(mut self, format: AccessLogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    ///
+    /// Receives the entries instead of the default log output. Format is ignored.
+    ///
+    pub fn writer(mut self, writer: fn(&AccessLogEntry)) -> Self {
+        self.writer = Some(writer);
+        self
+    }
+
+    fn write(&self, entry: &AccessLogEntry) {
+        if let Some(writer) = self.writer {
+            return writer(entry);
+        }
+
+        let line = match self.format {
+            AccessLogFormat::Common => entry.common(),
+            AccessLogFormat::Json => entry.json(),
+            AccessLogFormat::Custom(formatter) => formatter(entry),
+        };
+        log::info!(target: "racoon::access", "{}", line);
+    }
+}
+
+impl AbstractMiddleware for AccessLog {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let access_log = self.clone();
+
+        Box::new(Box::pin(async move {
+            let started_at = Instant::now();
+            let time = Local::now();
+
+            let client_ip = match request.remote_addr().await {
+                Some(remote_addr) => match SocketAddr::from_str(&remote_addr) {
+                    Ok(socket_addr) => Some(socket_addr.ip().to_string()),
+                    Err(_) => Some(remote_addr),
+                },
+                None => None,
+            };
+            let method = request.method.clone();
+            let path = request.path.clone();
+            let http_version = format!("HTTP/1.{}", request.http_version);
+            let request_id = request.headers.value("X-Request-Id");
+            let user_agent = request.headers.value("User-Agent");
+
+            // Principal is added by the authentication middleware registered before this.
+            let user = request
+                .extensions
+                .get::<Principal>()
+                .map(|principal| principal.id.clone());
+            let transfer = TransferSize::of(&request).cloned();
+
+            let mut response = next.run(request).await;
+            let (status_code, _) = response.status();
+
+            let bytes = if response.serve_default() {
+                response.get_body().len()
+            } else {
+                response
+                    .get_headers()
+                    .value("Content-Length")
+                    .and_then(|value| value.parse().ok())
+                    .or_else(|| {
+                        let transfer = transfer.as_ref()?;
+                        Some(transfer.bytes_written() as usize)
+                    })
+                    .unwrap_or(0)
+            };
+
+            let entry = AccessLogEntry {
+                time,
+                client_ip,
+                user,
+                method,
+                path,
+                http_version,
+                status_code,
+                bytes,
+                bytes_received: transfer.map_or(0, |transfer| transfer.bytes_read()),
+                latency: started_at.elapsed(),
+                request_id: request_id.or_else(|| response.get_headers().value("X-Request-Id")),
+                user_agent,
+            };
+            access_log.write(&entry);
+            response
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use chrono::{Local, TimeZone};
+
+    use super::AccessLogEntry;
+
+    #[test]
+    fn test_access_log_entry() {
+        let entry = AccessLogEntry {
+            time: Local.with_ymd_and_hms(2024, 10, 10, 13, 55, 36).unwrap(),
+            client_ip: Some("127.0.0.1".to_string()),
+            user: None,
+            method: "GET".to_string(),
+            path: "/users?page=2".to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            status_code: 200,
+            bytes: 2326,
+            bytes_received: 120,
+            latency: Duration::from_millis(12),
+            request_id: Some("abc".to_string()),
+            user_agent: None,
+        };
+
+        let common = entry.common();
+        assert!(common.starts_with("127.0.0.1 - - [10/Oct/2024:13:55:36 "));
+        assert!(common.ends_with("] \"GET /users?page=2 HTTP/1.1\" 200 2326"));
+
+        let json: serde_json::Value = serde_json::from_str(&entry.json()).unwrap();
+        assert_eq!(200, json["status"]);
+        assert_eq!("abc", json["request_id"]);
+        assert_eq!(12.0, json["latency_ms"]);
+        assert_eq!(120, json["bytes_received"]);
+    }
+}
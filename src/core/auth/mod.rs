@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+use crate::racoon_debug;
+
+pub type AuthResult<'a, T> = Box<dyn Future<Output = T> + Send + Unpin + 'a>;
+
+///
+/// Authenticated user or client attached to the request extensions by [`Authentication`]
+/// middleware.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal {
+    pub id: String,
+    pub roles: Vec<String>,
+    /// Name of the authenticator which resolved the principal. For example `session`.
+    pub scheme: String,
+}
+
+impl Principal {
+    pub fn new<S: AsRef<str>>(id: S) -> Self {
+        Self {
+            id: id.as_ref().to_string(),
+            roles: vec![],
+            scheme: String::new(),
+        }
+    }
+
+    pub fn roles<S: AsRef<str>>(mut self, roles: &[S]) -> Self {
+        for role in roles {
+            self.roles.push(role.as_ref().to_string());
+        }
+        self
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|value| value == role)
+    }
+}
+
+pub trait AbstractAuthenticator: Sync + Send {
+    ///
+    /// Returns principal if the request carries valid credentials for this authenticator.
+    ///
+    fn authenticate<'a>(&'a self, request: &'a Request) -> AuthResult<'a, Option<Principal>>;
+}
+
+///
+/// Middleware resolving the principal with the authenticators in the order they are added. The
+/// first principal found is inserted into `request.extensions`. Requests without credentials are
+/// still passed to the view, so routes are protected with [`require_auth`] or [`require_role`]
+/// guards.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::auth::{
+///     require_auth, require_role, ApiKeyAuthenticator, Authentication, JwtAuthenticator,
+///     Principal, SessionAuthenticator,
+/// };
+/// use racoon::core::path::Path;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::server::Server;
+/// use racoon::view;
+///
+/// async fn profile(request: Request) -> Response {
+///     let principal = request.extensions.get::<Principal>().unwrap();
+///     HttpResponse::ok().body(format!("Hello {}", principal.id))
+/// }
+///
+/// let authentication = Authentication::new()
+///     .authenticator(SessionAuthenticator::new())
+///     .authenticator(ApiKeyAuthenticator::new("X-Api-Key").key("secret", Principal::new("reports")))
+///     .authenticator(JwtAuthenticator::hs256("jwt-secret"));
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(authentication);
+/// server.urls(vec![
+///     Path::get("/profile", view!(profile)).guard(require_auth()),
+///     Path::get("/admin", view!(profile)).guard(require_role("admin")),
+/// ]);
+/// ```
+///
+#[derive(Clone, Default)]
+pub struct Authentication {
+    authenticators: Vec<Arc<dyn AbstractAuthenticator>>,
+}
+
+impl Authentication {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn authenticator<T: AbstractAuthenticator + 'static>(mut self, authenticator: T) -> Self {
+        self.authenticators.push(Arc::new(authenticator));
+        self
+    }
+}
+
+impl AbstractMiddleware for Authentication {
+    fn handle(&self, mut request: Request, next: Next) -> MiddlewareResult {
+        let authenticators = self.authenticators.clone();
+
+        Box::new(Box::pin(async move {
+            for authenticator in authenticators {
+                if let Some(principal) = authenticator.authenticate(&request).await {
+                    racoon_debug!("Authenticated principal: {}", principal.id);
+                    request.extensions.insert(principal);
+                    break;
+                }
+            }
+            next.run(request).await
+        }))
+    }
+}
+
+///
+/// Guard rejecting requests without principal with `401 Unauthorized`.
+///
+pub fn require_auth() -> impl Fn(&Request) -> Result<(), Response> + Send + Sync + 'static {
+    |request: &Request| {
+        if request.extensions.contains::<Principal>() {
+            return Ok(());
+        }
+        Err(HttpResponse::unauthorized().body("401 Unauthorized"))
+    }
+}
+
+///
+/// Guard rejecting requests without principal with `401 Unauthorized` and the principals without
+/// the role with `403 Forbidden`.
+///
+pub fn require_role<S: AsRef<str>>(
+    role: S,
+) -> impl Fn(&Request) -> Result<(), Response> + Send + Sync + 'static {
+    let role = role.as_ref().to_string();
+
+    move |request: &Request| match request.extensions.get::<Principal>() {
+        Some(principal) if principal.has_role(&role) => Ok(()),
+        Some(_) => Err(HttpResponse::forbidden().body("403 Forbidden")),
+        None => Err(HttpResponse::unauthorized().body("401 Unauthorized")),
+    }
+}
+
+///
+/// Authenticates with the user id stored in the session. Roles are read as comma separated
+/// value from another session key.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+///
+/// async fn login(request: Request) {
+///     let _ = request.session.set("user_id", "42").await;
+///     let _ = request.session.set("roles", "admin,editor").await;
+/// }
+/// ```
+///
+#[derive(Clone)]
+pub struct SessionAuthenticator {
+    user_key: String,
+    roles_key: String,
+}
+
+impl Default for SessionAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionAuthenticator {
+    pub fn new() -> Self {
+        Self {
+            user_key: "user_id".to_string(),
+            roles_key: "roles".to_string(),
+        }
+    }
+
+    pub fn user_key<S: AsRef<str>>(mut self, key: S) -> Self {
+        self.user_key = key.as_ref().to_string();
+        self
+    }
+
+    pub fn roles_key<S: AsRef<str>>(mut self, key: S) -> Self {
+        self.roles_key = key.as_ref().to_string();
+        self
+    }
+}
+
+impl AbstractAuthenticator for SessionAuthenticator {
+    fn authenticate<'a>(&'a self, request: &'a Request) -> AuthResult<'a, Option<Principal>> {
+        Box::new(Box::pin(async move {
+            let user_id = request.session.get(&self.user_key).await?;
+            let roles = request
+                .session
+                .get(&self.roles_key)
+                .await
+                .unwrap_or_default();
+
+            let mut principal = Principal::new(user_id);
+            principal.roles = split_roles(&roles);
+            principal.scheme = "session".to_string();
+            Some(principal)
+        }))
+    }
+}
+
+///
+/// Authenticates with the static API keys sent in the request header.
+///
+#[derive(Clone)]
+pub struct ApiKeyAuthenticator {
+    header: String,
+    keys: HashMap<String, Principal>,
+}
+
+impl ApiKeyAuthenticator {
+    pub fn new<S: AsRef<str>>(header: S) -> Self {
+        Self {
+            header: header.as_ref().to_string(),
+            keys: HashMap::new(),
+        }
+    }
+
+    pub fn key<S: AsRef<str>>(mut self, key: S, principal: Principal) -> Self {
+        self.keys.insert(key.as_ref().to_string(), principal);
+        self
+    }
+}
+
+impl AbstractAuthenticator for ApiKeyAuthenticator {
+    fn authenticate<'a>(&'a self, request: &'a Request) -> AuthResult<'a, Option<Principal>> {
+        Box::new(Box::pin(async move {
+            let api_key = request.headers.value(&self.header)?;
+
+            // Compares every key, so the time taken does not reveal the matching prefix.
+            let mut found = None;
+            for (key, principal) in &self.keys {
+                if constant_time_eq(key.as_bytes(), api_key.as_bytes()) {
+                    found = Some(principal);
+                }
+            }
+
+            let mut principal = found?.clone();
+            principal.scheme = "api_key".to_string();
+            Some(principal)
+        }))
+    }
+}
+
+///
+/// Authenticates with the JSON Web Token signed with HMAC-SHA256 and sent as
+/// `Authorization: Bearer <token>`. The `sub` claim becomes the principal id and the `roles`
+/// claim its roles. Expired tokens and tokens not valid yet are rejected.
+///
+#[derive(Clone)]
+pub struct JwtAuthenticator {
+    secret: Vec<u8>,
+}
+
+impl JwtAuthenticator {
+    pub fn hs256<B: AsRef<[u8]>>(secret: B) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+        }
+    }
+
+    ///
+    /// Signs the claims and returns the token.
+    ///
+    pub fn encode(&self, claims: &serde_json::Value) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+        let signing_input = format!("{}.{}", header, payload);
+
+        let signature = URL_SAFE_NO_PAD.encode(self.sign(signing_input.as_bytes()));
+        format!("{}.{}", signing_input, signature)
+    }
+
+    ///
+    /// Returns the claims if the token signature is valid and the token is not expired.
+    ///
+    pub fn decode(&self, token: &str) -> Option<serde_json::Value> {
+        let mut parts = token.split('.');
+        let (header, payload, signature) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let header: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+        if header.get("alg")?.as_str()? != "HS256" {
+            return None;
+        }
+
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        let (signing_input, _) = token.rsplit_once('.')?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).ok()?;
+        mac.update(signing_input.as_bytes());
+        mac.verify_slice(&signature).ok()?;
+
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if let Some(expires_at) = claims.get("exp").and_then(|value| value.as_u64()) {
+            if now >= expires_at {
+                return None;
+            }
+        }
+        if let Some(not_before) = claims.get("nbf").and_then(|value| value.as_u64()) {
+            if now < not_before {
+                return None;
+            }
+        }
+        Some(claims)
+    }
+
+    fn sign(&self, bytes: &[u8]) -> Vec<u8> {
+        // HMAC accepts key of any length.
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).unwrap();
+        mac.update(bytes);
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+impl AbstractAuthenticator for JwtAuthenticator {
+    fn authenticate<'a>(&'a self, request: &'a Request) -> AuthResult<'a, Option<Principal>> {
+        Box::new(Box::pin(async move {
+            let authorization = request.headers.value("Authorization")?;
+            let token = authorization
+                .strip_prefix("Bearer ")
+                .or_else(|| authorization.strip_prefix("bearer "))?;
+
+            let claims = self.decode(token.trim())?;
+            let mut principal = Principal::new(claims.get("sub")?.as_str()?);
+
+            if let Some(roles) = claims.get("roles").and_then(|value| value.as_array()) {
+                principal.roles = roles
+                    .iter()
+                    .filter_map(|role| role.as_str().map(|role| role.to_string()))
+                    .collect();
+            }
+            principal.scheme = "jwt".to_string();
+            Some(principal)
+        }))
+    }
+}
+
+fn split_roles(roles: &str) -> Vec<String> {
+    roles
+        .split(',')
+        .map(|role| role.trim())
+        .filter(|role| !role.is_empty())
+        .map(|role| role.to_string())
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |result, (x, y)| result | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+
+    use super::{
+        require_role, AbstractAuthenticator, ApiKeyAuthenticator, JwtAuthenticator, Principal,
+    };
+
+    #[test]
+    fn test_jwt() {
+        let authenticator = JwtAuthenticator::hs256("secret");
+        let token = authenticator.encode(&serde_json::json!({"sub": "42", "roles": ["admin"]}));
+
+        let claims = authenticator.decode(&token).unwrap();
+        assert_eq!(Some("42"), claims["sub"].as_str());
+
+        assert!(JwtAuthenticator::hs256("other").decode(&token).is_none());
+
+        let expired = authenticator.encode(&serde_json::json!({"sub": "42", "exp": 1}));
+        assert!(authenticator.decode(&expired).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_authenticators() {
+        let jwt = JwtAuthenticator::hs256("secret");
+        let token = jwt.encode(&serde_json::json!({"sub": "42", "roles": ["admin"]}));
+
+        let mut headers = Headers::new();
+        headers.set("Authorization", format!("Bearer {}", token));
+        headers.set("X-Api-Key", "key");
+        let mut request = request("/", headers, b"").await;
+
+        let principal = jwt.authenticate(&request).await.unwrap();
+        assert_eq!("42", principal.id);
+        assert!(principal.has_role("admin"));
+
+        let api_key = ApiKeyAuthenticator::new("X-Api-Key").key("key", Principal::new("service"));
+        assert_eq!("service", api_key.authenticate(&request).await.unwrap().id);
+
+        let guard = require_role("admin");
+        assert_eq!(401, guard(&request).unwrap_err().status().0);
+
+        request
+            .extensions
+            .insert(Principal::new("1").roles(&["editor"]));
+        assert_eq!(403, guard(&request).unwrap_err().status().0);
+
+        request.extensions.insert(principal);
+        assert!(guard(&request).is_ok());
+    }
+}
@@ -0,0 +1,366 @@
+pub mod stores;
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+use crate::racoon_debug;
+
+use self::stores::MemoryCacheStore;
+
+pub type CacheResult<T> = Box<dyn Future<Output = T> + Send + Unpin>;
+
+///
+/// Snapshot of the response stored in the cache.
+///
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status_code: u32,
+    pub status_text: String,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn from_response(response: &mut Response) -> Self {
+        let (status_code, status_text) = response.status();
+
+        Self {
+            status_code,
+            status_text,
+            headers: response.get_headers().clone(),
+            body: response.get_body().clone(),
+        }
+    }
+
+    pub fn into_response(self) -> Response {
+        let mut response: Response =
+            HttpResponse::with_status(self.status_code, &self.status_text).empty();
+        let headers = response.get_headers();
+
+        for (name, values) in self.headers {
+            for (index, value) in values.iter().enumerate() {
+                if index == 0 {
+                    headers.set(&name, value);
+                } else {
+                    headers.set_multiple(&name, value);
+                }
+            }
+        }
+
+        *response.get_body() = self.body;
+        response
+    }
+}
+
+pub trait AbstractCacheStore: Sync + Send {
+    /// Returns cached response if present and not expired.
+    fn get(&self, key: &str) -> CacheResult<Option<CachedResponse>>;
+
+    /// Stores response for the given duration.
+    fn set(&self, key: &str, response: CachedResponse, ttl: Duration) -> CacheResult<()>;
+
+    /// Removes single cached response.
+    fn remove(&self, key: &str) -> CacheResult<()>;
+
+    /// Removes all cached responses whose key starts with the given prefix.
+    fn remove_prefix(&self, prefix: &str) -> CacheResult<()>;
+
+    /// Removes all cached responses.
+    fn clear(&self) -> CacheResult<()>;
+}
+
+pub type CacheStore = Box<dyn AbstractCacheStore>;
+
+///
+/// Opt-in response cache for read heavy views. Responses are keyed by request method, path with
+/// query, `Host` header and the values of configured vary headers, so the sites served by the
+/// same server don't share the responses.
+///
+/// Only successful `GET` and `HEAD` responses without `Set-Cookie` header are cached.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::cache::ResponseCache;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+///
+/// async fn articles(request: Request) -> Response {
+///     let cache = request.context::<ResponseCache>().unwrap();
+///
+///     cache.cached(&request, Duration::from_secs(60), || async {
+///         let response: Response = HttpResponse::ok().body("Articles");
+///         response
+///     }).await
+/// }
+/// ```
+///
+pub struct ResponseCache {
+    store: Arc<CacheStore>,
+    vary_headers: Vec<String>,
+}
+
+impl Clone for ResponseCache {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            vary_headers: self.vary_headers.clone(),
+        }
+    }
+}
+
+impl ResponseCache {
+    ///
+    /// Creates response cache backed by in-memory LRU store holding at most `capacity` responses.
+    ///
+    pub fn new(capacity: usize) -> Self {
+        Self::with_store(MemoryCacheStore::new(capacity))
+    }
+
+    pub fn with_store<T: AbstractCacheStore + 'static>(store: T) -> Self {
+        Self {
+            store: Arc::new(Box::new(store)),
+            vary_headers: vec![],
+        }
+    }
+
+    ///
+    /// Request headers whose values become part of the cache key. For example `Accept-Language`.
+    ///
+    pub fn vary<S: AsRef<str>>(mut self, headers: &[S]) -> Self {
+        for header in headers {
+            self.vary_headers.push(header.as_ref().to_lowercase());
+        }
+        self
+    }
+
+    pub fn key(&self, request: &Request) -> String {
+        let host = request.headers.value("host").unwrap_or_default();
+        let mut key = format!("{} {}|host={}", request.method, request.path, host);
+
+        for name in &self.vary_headers {
+            let value = request.headers.value(name).unwrap_or_default();
+            key.push_str(&format!("|{}={}", name, value));
+        }
+
+        key
+    }
+
+    ///
+    /// Returns cached response if available, else calls the view and caches its response for the
+    /// given time to live.
+    ///
+    pub async fn cached<F, Fut>(&self, request: &Request, ttl: Duration, view: F) -> Response
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let is_cacheable_method = request.method == "GET" || request.method == "HEAD";
+        if !is_cacheable_method {
+            return view().await;
+        }
+
+        let key = self.key(request);
+        if let Some(cached_response) = self.store.get(&key).await {
+            racoon_debug!("Serving cached response for: {}", key);
+            return cached_response.into_response();
+        }
+
+        let mut response = view().await;
+        let (status_code, _) = response.status();
+
+        if status_code == 200
+            && response.serve_default()
+            && response.get_headers().value("Set-Cookie").is_none()
+        {
+            let cached_response = CachedResponse::from_response(&mut response);
+            self.store.set(&key, cached_response, ttl).await;
+        }
+
+        response
+    }
+
+    ///
+    /// Removes cached responses of the given path for every method, query, host and vary header
+    /// values.
+    ///
+    pub async fn invalidate<S: AsRef<str>>(&self, path: S) {
+        let path = path.as_ref();
+
+        for method in ["GET", "HEAD"] {
+            let key = format!("{} {}", method, path);
+
+            // Host and vary header values follow the path with or without the query parameters.
+            self.store.remove_prefix(&format!("{}?", key)).await;
+            self.store.remove_prefix(&format!("{}|", key)).await;
+        }
+    }
+
+    ///
+    /// Removes single cached response by exact key returned from `ResponseCache::key()`.
+    ///
+    pub async fn invalidate_key<S: AsRef<str>>(&self, key: S) {
+        self.store.remove(key.as_ref()).await;
+    }
+
+    pub async fn clear(&self) {
+        self.store.clear().await;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use crate::core::cookie::set_cookie;
+    use crate::core::extract::tests::request as test_request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+
+    use super::ResponseCache;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    type MakeResponse = fn() -> Response;
+
+    async fn cache_request(method: &str, raw_path: &str, language: &str) -> Request {
+        let mut headers = Headers::new();
+        headers.set("Host", "example.com");
+        headers.set("Accept-Language", language);
+
+        let mut request = test_request(raw_path, headers, b"").await;
+        request.method = method.to_string();
+        request
+    }
+
+    async fn get(cache: &ResponseCache, calls: &AtomicUsize, raw_path: &str, language: &str) {
+        let request = cache_request("GET", raw_path, language).await;
+        cache
+            .cached(&request, TTL, || async {
+                calls.fetch_add(1, Ordering::Relaxed);
+                let response: Response = HttpResponse::ok().body("Articles");
+                response
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_cache_key() {
+        let cache = ResponseCache::new(10).vary(&["Accept-Language"]);
+
+        let request = cache_request("GET", "/articles?page=2", "en").await;
+        assert_eq!(
+            "GET /articles?page=2|host=example.com|accept-language=en",
+            cache.key(&request)
+        );
+
+        let request = test_request("/articles", Headers::new(), b"").await;
+        assert_eq!("POST /articles|host=|accept-language=", cache.key(&request));
+    }
+
+    #[tokio::test]
+    async fn test_cached() {
+        let cache = ResponseCache::new(10).vary(&["Accept-Language"]);
+        let calls = AtomicUsize::new(0);
+
+        get(&cache, &calls, "/articles", "en").await;
+        get(&cache, &calls, "/articles", "en").await;
+        assert_eq!(1, calls.load(Ordering::Relaxed));
+
+        // Each value of the vary header is cached separately.
+        get(&cache, &calls, "/articles", "de").await;
+        assert_eq!(2, calls.load(Ordering::Relaxed));
+
+        let uncacheable: [(&str, MakeResponse); 3] = [
+            ("POST", || HttpResponse::ok().body("Created")),
+            ("GET", || HttpResponse::not_found().body("Not Found")),
+            ("GET", || {
+                let mut response: Response = HttpResponse::ok().body("Logged in");
+                set_cookie(response.get_headers(), "name", "John", TTL);
+                response
+            }),
+        ];
+
+        for (method, response) in uncacheable {
+            let calls = AtomicUsize::new(0);
+            for _ in 0..2 {
+                let request = cache_request(method, "/login", "en").await;
+                let mut response = cache
+                    .cached(&request, TTL, || async {
+                        calls.fetch_add(1, Ordering::Relaxed);
+                        response()
+                    })
+                    .await;
+                assert!(!response.get_body().is_empty());
+            }
+            assert_eq!(2, calls.load(Ordering::Relaxed));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        let cache = ResponseCache::new(10).vary(&["Accept-Language"]);
+        let calls = AtomicUsize::new(0);
+
+        let paths = [
+            ("/articles", "en"),
+            ("/articles?page=2", "en"),
+            ("/articles", "de"),
+            ("/articles-archive", "en"),
+        ];
+        for (raw_path, language) in paths {
+            get(&cache, &calls, raw_path, language).await;
+        }
+        assert_eq!(4, calls.load(Ordering::Relaxed));
+
+        cache.invalidate("/articles").await;
+
+        // Variants of the path with the query and the vary header values are removed.
+        for (raw_path, language) in paths {
+            get(&cache, &calls, raw_path, language).await;
+        }
+        assert_eq!(7, calls.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn test_cache_host() {
+        let cache = ResponseCache::new(10);
+        let calls = AtomicUsize::new(0);
+
+        let hosts = ["a.example.com", "b.example.com"];
+        for host in hosts.iter().chain(&hosts) {
+            let mut headers = Headers::new();
+            headers.set("Host", host);
+            let mut request = test_request("/articles", headers, b"").await;
+            request.method = "GET".to_string();
+
+            let mut response = cache
+                .cached(&request, TTL, || async {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    let response: Response = HttpResponse::ok().body(host);
+                    response
+                })
+                .await;
+            assert_eq!(host.as_bytes().to_vec(), *response.get_body());
+        }
+
+        // Each host is cached separately and the path is invalidated for all of them.
+        assert_eq!(2, calls.load(Ordering::Relaxed));
+        cache.invalidate("/articles").await;
+        for host in hosts {
+            let key = format!("GET /articles|host={}", host);
+            assert!(cache.store.get(&key).await.is_none());
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::core::cache::{AbstractCacheStore, CacheResult, CachedResponse};
+use crate::core::clock::{self, Clock};
+
+struct CacheEntry {
+    response: CachedResponse,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+struct LruState {
+    entries: HashMap<String, CacheEntry>,
+    counter: u64,
+}
+
+///
+/// In-memory cache store which evicts least recently used response when the capacity is full.
+///
+pub struct MemoryCacheStore {
+    capacity: usize,
+    state: Arc<Mutex<LruState>>,
+    clock: Clock,
+}
+
+impl MemoryCacheStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Arc::new(Mutex::new(LruState {
+                entries: HashMap::new(),
+                counter: 0,
+            })),
+            clock: clock::system(),
+        }
+    }
+
+    ///
+    /// Clock used for expiring the cached responses. Defaults to the system clock.
+    ///
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl AbstractCacheStore for MemoryCacheStore {
+    fn get(&self, key: &str) -> CacheResult<Option<CachedResponse>> {
+        let state_ref = self.state.clone();
+        let now = self.clock.now();
+        let key = key.to_string();
+
+        Box::new(Box::pin(async move {
+            let mut state = state_ref.lock().await;
+            state.counter += 1;
+            let counter = state.counter;
+
+            let is_expired;
+            if let Some(entry) = state.entries.get_mut(&key) {
+                if entry.expires_at > now {
+                    entry.last_used = counter;
+                    return Some(entry.response.clone());
+                }
+
+                is_expired = true;
+            } else {
+                is_expired = false;
+            }
+
+            if is_expired {
+                state.entries.remove(&key);
+            }
+
+            None
+        }))
+    }
+
+    fn set(&self, key: &str, response: CachedResponse, ttl: Duration) -> CacheResult<()> {
+        let state_ref = self.state.clone();
+        let capacity = self.capacity;
+        let now = self.clock.now();
+        let key = key.to_string();
+
+        Box::new(Box::pin(async move {
+            if capacity == 0 {
+                return;
+            }
+
+            let mut state = state_ref.lock().await;
+            state.counter += 1;
+            let counter = state.counter;
+
+            if !state.entries.contains_key(&key) && state.entries.len() >= capacity {
+                // Removes expired entries first, else the least recently used one.
+                state.entries.retain(|_, entry| entry.expires_at > now);
+
+                if state.entries.len() >= capacity {
+                    let lru_key = state
+                        .entries
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.last_used)
+                        .map(|(key, _)| key.to_owned());
+
+                    if let Some(lru_key) = lru_key {
+                        state.entries.remove(&lru_key);
+                    }
+                }
+            }
+
+            state.entries.insert(
+                key,
+                CacheEntry {
+                    response,
+                    expires_at: now + ttl,
+                    last_used: counter,
+                },
+            );
+        }))
+    }
+
+    fn remove(&self, key: &str) -> CacheResult<()> {
+        let state_ref = self.state.clone();
+        let key = key.to_string();
+
+        Box::new(Box::pin(async move {
+            let mut state = state_ref.lock().await;
+            state.entries.remove(&key);
+        }))
+    }
+
+    fn remove_prefix(&self, prefix: &str) -> CacheResult<()> {
+        let state_ref = self.state.clone();
+        let prefix = prefix.to_string();
+
+        Box::new(Box::pin(async move {
+            let mut state = state_ref.lock().await;
+            state.entries.retain(|key, _| !key.starts_with(&prefix));
+        }))
+    }
+
+    fn clear(&self) -> CacheResult<()> {
+        let state_ref = self.state.clone();
+
+        Box::new(Box::pin(async move {
+            let mut state = state_ref.lock().await;
+            state.entries.clear();
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use crate::core::cache::{AbstractCacheStore, CachedResponse};
+    use crate::core::headers::Headers;
+
+    use super::MemoryCacheStore;
+
+    fn cached_response(body: &str) -> CachedResponse {
+        CachedResponse {
+            status_code: 200,
+            status_text: "OK".to_string(),
+            headers: Headers::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_lru() {
+        let store = MemoryCacheStore::new(2);
+        let ttl = Duration::from_secs(60);
+
+        store.set("GET /a", cached_response("a"), ttl).await;
+        store.set("GET /b", cached_response("b"), ttl).await;
+
+        // Marks /a as recently used, so /b gets evicted.
+        assert!(store.get("GET /a").await.is_some());
+        store.set("GET /c", cached_response("c"), ttl).await;
+
+        assert!(store.get("GET /a").await.is_some());
+        assert!(store.get("GET /b").await.is_none());
+        assert_eq!(b"c".to_vec(), store.get("GET /c").await.unwrap().body);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_expiry_and_invalidation() {
+        let store = MemoryCacheStore::new(10);
+
+        store.set("GET /a", cached_response("a"), Duration::ZERO).await;
+        assert!(store.get("GET /a").await.is_none());
+
+        let ttl = Duration::from_secs(60);
+        store.set("GET /users?page=1", cached_response("1"), ttl).await;
+        store.set("GET /users?page=2", cached_response("2"), ttl).await;
+        store.set("GET /articles", cached_response("3"), ttl).await;
+
+        store.remove_prefix("GET /users?").await;
+        assert!(store.get("GET /users?page=1").await.is_none());
+        assert!(store.get("GET /users?page=2").await.is_none());
+        assert!(store.get("GET /articles").await.is_some());
+    }
+}
@@ -0,0 +1,774 @@
+//!
+//! HTTP/1.1 client for calling the other services like the webhook receivers. Every request uses
+//! a new connection.
+//!
+//! Requests are signed with HMAC-SHA256 if the signing secret is set, retried with the
+//! exponential backoff on the connection errors and the `429` or `5xx` responses, and time out
+//! after 30 seconds by default.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use racoon::core::client::{HttpClient, SIGNATURE_HEADER};
+//!
+//! async fn send_webhook() -> std::io::Result<()> {
+//!     let client = HttpClient::new()?
+//!         .timeout(Duration::from_secs(10))
+//!         .retries(5, Duration::from_millis(500))
+//!         .sign(SIGNATURE_HEADER, "webhook-secret");
+//!
+//!     let payload = serde_json::json!({"event": "order.paid", "order_id": 42});
+//!     let response = client
+//!         .post_json("https://example.com/webhooks", &payload)
+//!         .await?;
+//!
+//!     if !response.is_success() {
+//!         return Err(std::io::Error::other("Webhook was rejected."));
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use rustls::pki_types::ServerName;
+use rustls::RootCertStore;
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::parser::chunked::decode_chunked;
+use crate::racoon_debug;
+
+const USER_AGENT: &str = concat!("racoon/", env!("CARGO_PKG_VERSION"));
+
+///
+/// Default header of the request signature. Value is in the format `t=<timestamp>,v1=<hex>`
+/// where the HMAC-SHA256 is computed over `<timestamp>.<body>`.
+///
+pub const SIGNATURE_HEADER: &str = "X-Racoon-Signature";
+
+///
+/// Response received from the server.
+///
+pub struct ClientResponse {
+    pub status: u16,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl ClientResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn json(&self) -> std::io::Result<Value> {
+        serde_json::from_slice(&self.body).map_err(std::io::Error::other)
+    }
+
+    pub fn location(&self) -> std::io::Result<String> {
+        match self.headers.value("Location") {
+            Some(location) => Ok(location),
+            None => Err(std::io::Error::other("Location header is missing.")),
+        }
+    }
+}
+
+///
+/// Request sent with `HttpClient::send`.
+///
+#[derive(Debug, Clone)]
+pub struct ClientRequest {
+    method: String,
+    url: String,
+    headers: Headers,
+    body: Vec<u8>,
+}
+
+impl ClientRequest {
+    pub fn new<M: AsRef<str>, S: AsRef<str>>(method: M, url: S) -> Self {
+        Self {
+            method: method.as_ref().to_uppercase(),
+            url: url.as_ref().to_string(),
+            headers: Headers::new(),
+            body: vec![],
+        }
+    }
+
+    pub fn get<S: AsRef<str>>(url: S) -> Self {
+        Self::new("GET", url)
+    }
+
+    pub fn post<S: AsRef<str>>(url: S) -> Self {
+        Self::new("POST", url)
+    }
+
+    pub fn header<S: AsRef<str>>(mut self, name: &str, value: S) -> Self {
+        self.headers.set_multiple(name, value.as_ref());
+        self
+    }
+
+    pub fn body<B: AsRef<[u8]>>(mut self, body: B) -> Self {
+        self.body = body.as_ref().to_vec();
+        self
+    }
+
+    pub fn json(self, json: &Value) -> Self {
+        self.header("Content-Type", "application/json")
+            .body(json.to_string())
+    }
+}
+
+///
+/// HTTP client with the timeout, retries and request signing. Cloned clients share the TLS
+/// config.
+///
+#[derive(Clone)]
+pub struct HttpClient {
+    tls_connector: TlsConnector,
+    timeout: Duration,
+    max_retries: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    max_response_size: usize,
+    signing: Option<(String, Arc<Vec<u8>>)>,
+}
+
+impl HttpClient {
+    ///
+    /// Creates client trusting the CA certificates of the platform. Locations can be overridden
+    /// with `SSL_CERT_FILE` and `SSL_CERT_DIR` environment variables.
+    ///
+    pub fn new() -> std::io::Result<Self> {
+        let native_certs = rustls_native_certs::load_native_certs();
+
+        let mut root_store = RootCertStore::empty();
+        // Unsupported certificates in the platform store are skipped.
+        let (added, _) = root_store.add_parsable_certificates(native_certs.certs);
+        if added == 0 {
+            let errors: Vec<String> = native_certs
+                .errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect();
+            return Err(std::io::Error::other(format!(
+                "No CA certificates found in the platform store. Errors: {}",
+                errors.join(", ")
+            )));
+        }
+        Ok(Self::with_root_store(root_store))
+    }
+
+    ///
+    /// Creates client trusting the PEM encoded CA certificates in the bundle.
+    ///
+    pub fn with_ca_bundle(ca_bundle: &str) -> std::io::Result<Self> {
+        let ca_file = std::fs::File::open(ca_bundle).map_err(|error| {
+            std::io::Error::other(format!(
+                "Failed to open CA bundle {}. Error: {}",
+                ca_bundle, error
+            ))
+        })?;
+
+        let mut root_store = RootCertStore::empty();
+        for certificate in rustls_pemfile::certs(&mut std::io::BufReader::new(ca_file)) {
+            // Unsupported certificates in the bundle are skipped.
+            let _ = root_store.add(certificate?);
+        }
+        Ok(Self::with_root_store(root_store))
+    }
+
+    pub fn with_root_store(root_store: RootCertStore) -> Self {
+        let client_config = rustls::ClientConfig::builder()
+            .with_root_certificates(root_store)
+            .with_no_client_auth();
+
+        Self {
+            tls_connector: TlsConnector::from(Arc::new(client_config)),
+            timeout: Duration::from_secs(30),
+            max_retries: 0,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_response_size: 10 * 1024 * 1024,
+            signing: None,
+        }
+    }
+
+    ///
+    /// Time allowed for each attempt including the connection and reading the response.
+    ///
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    ///
+    /// Retries the failed request up to `max_retries` times. Delay starts from `backoff` and is
+    /// doubled after each attempt with the random jitter, unless the server sends `Retry-After`
+    /// in seconds.
+    ///
+    pub fn retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    ///
+    /// Max size of the response in bytes including the head. Request fails if the server sends
+    /// more. Default is 10 MiB.
+    ///
+    pub fn max_response_size(mut self, max_response_size: usize) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    ///
+    /// Signs the body of each request with the secret and sends the signature in the header.
+    /// Receivers check it with [`verify_signature`].
+    ///
+    pub fn sign<H: AsRef<str>, S: AsRef<[u8]>>(mut self, header: H, secret: S) -> Self {
+        self.signing = Some((
+            header.as_ref().to_string(),
+            Arc::new(secret.as_ref().to_vec()),
+        ));
+        self
+    }
+
+    pub async fn get<S: AsRef<str>>(&self, url: S) -> std::io::Result<ClientResponse> {
+        self.send(ClientRequest::get(url)).await
+    }
+
+    pub async fn post_json<S: AsRef<str>>(
+        &self,
+        url: S,
+        json: &Value,
+    ) -> std::io::Result<ClientResponse> {
+        self.send(ClientRequest::post(url).json(json)).await
+    }
+
+    ///
+    /// Sends the request with the retries. Returns the last response or error if all the
+    /// attempts fail.
+    ///
+    pub async fn send(&self, mut request: ClientRequest) -> std::io::Result<ClientResponse> {
+        let mut attempt = 0;
+
+        loop {
+            if let Some((header, secret)) = &self.signing {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                request
+                    .headers
+                    .set(header, signature(secret, timestamp, &request.body));
+            }
+
+            let result = self.send_once(&request).await;
+            let retry_after = match &result {
+                Ok(response) if response.status == 429 || response.status >= 500 => {
+                    Some(response.headers.value("Retry-After"))
+                }
+                Ok(_) => None,
+                Err(_) => Some(None),
+            };
+
+            let retry_after = match retry_after {
+                Some(retry_after) if attempt < self.max_retries => retry_after,
+                _ => return result,
+            };
+
+            let delay = retry_after
+                .and_then(|seconds| seconds.trim().parse::<u64>().ok())
+                .map(|seconds| Duration::from_secs(seconds).min(self.max_backoff))
+                .unwrap_or_else(|| self.backoff_delay(attempt));
+
+            match &result {
+                Ok(response) => racoon_debug!(
+                    "Request to {} failed with status {}. Retrying in {:?}.",
+                    request.url,
+                    response.status,
+                    delay
+                ),
+                Err(error) => racoon_debug!(
+                    "Request to {} failed. Error: {}. Retrying in {:?}.",
+                    request.url,
+                    error,
+                    delay
+                ),
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    ///
+    /// Exponential backoff with the jitter between the half and the full delay, so the clients
+    /// failing at the same time do not retry together.
+    ///
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        if delay.is_zero() {
+            return delay;
+        }
+        rand::thread_rng().gen_range(delay / 2..=delay)
+    }
+
+    async fn send_once(&self, request: &ClientRequest) -> std::io::Result<ClientResponse> {
+        let (is_tls, host, port, path) = parse_url(&request.url)?;
+        let authority = host_header(is_tls, &host, port);
+        let max_response_size = self.max_response_size;
+
+        let request_future = async {
+            let tcp_stream = TcpStream::connect((host.as_str(), port)).await?;
+            if is_tls {
+                let server_name = ServerName::try_from(host.clone()).map_err(|error| {
+                    std::io::Error::other(format!("Invalid host {}. Error: {}", host, error))
+                })?;
+                let tls_stream = self.tls_connector.connect(server_name, tcp_stream).await?;
+                send(tls_stream, request, &authority, &path, max_response_size).await
+            } else {
+                send(tcp_stream, request, &authority, &path, max_response_size).await
+            }
+        };
+
+        match tokio::time::timeout(self.timeout, request_future).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("Request to {} timed out.", request.url),
+            )),
+        }
+    }
+}
+
+///
+/// Returns the signature header value for the body.
+///
+pub fn signature(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("t={},v1={}", timestamp, digest)
+}
+
+///
+/// Verifies the signature header value sent by [`HttpClient::sign`]. Signatures older than the
+/// tolerance are rejected to prevent the replay of the captured requests.
+///
+pub fn verify_signature(
+    secret: &[u8],
+    header_value: &str,
+    body: &[u8],
+    tolerance: Duration,
+) -> bool {
+    let mut timestamp = None;
+    let mut digest = None;
+    for part in header_value.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<u64>().ok(),
+            Some(("v1", value)) => digest = decode_hex(value),
+            _ => {}
+        }
+    }
+
+    let (timestamp, digest) = match (timestamp, digest) {
+        (Some(timestamp), Some(digest)) => (timestamp, digest),
+        _ => return false,
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if now.abs_diff(timestamp) > tolerance.as_secs() {
+        return false;
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&digest).is_ok()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => u8::from_str_radix(std::str::from_utf8(&[*high, *low]).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+///
+/// Splits the URL into scheme, host, port and path. IPv6 host is returned without the brackets.
+///
+pub(crate) fn parse_url(url: &str) -> std::io::Result<(bool, String, u16, String)> {
+    let (is_tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (false, rest)
+    } else {
+        return Err(std::io::Error::other(format!("Unsupported URL {}", url)));
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(position) => (&rest[..position], &rest[position..]),
+        None => (rest, "/"),
+    };
+
+    let default_port = if is_tls { 443 } else { 80 };
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        // IPv6 literal like [::1]:8080
+        match rest.split_once(']') {
+            Some((host, "")) => (host, None),
+            Some((host, port)) if port.starts_with(':') => (host, Some(&port[1..])),
+            _ => return Err(std::io::Error::other(format!("Invalid host in {}", url))),
+        }
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+
+    let port = match port {
+        Some(port) => match port.parse() {
+            Ok(port) => port,
+            Err(_) => return Err(std::io::Error::other(format!("Invalid port in {}", url))),
+        },
+        None => default_port,
+    };
+
+    Ok((is_tls, host.to_string(), port, path.to_string()))
+}
+
+///
+/// Value of the `Host` header. Port is included if it is not the default port of the scheme.
+///
+fn host_header(is_tls: bool, host: &str, port: u16) -> String {
+    let host = if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    };
+
+    let default_port = if is_tls { 443 } else { 80 };
+    if port == default_port {
+        host
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+async fn send<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    request: &ClientRequest,
+    host: &str,
+    path: &str,
+    max_response_size: usize,
+) -> std::io::Result<ClientResponse> {
+    let mut request_head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\nConnection: close\r\n",
+        request.method, path, host, USER_AGENT
+    );
+
+    for (name, values) in &request.headers {
+        for value in values {
+            request_head.push_str(&format!("{}: {}\r\n", name, String::from_utf8_lossy(value)));
+        }
+    }
+
+    if request.method != "GET" && request.method != "HEAD" {
+        request_head.push_str(&format!("Content-Length: {}\r\n", request.body.len()));
+    }
+    request_head.push_str("\r\n");
+
+    stream.write_all(request_head.as_bytes()).await?;
+    stream.write_all(&request.body).await?;
+    stream.flush().await?;
+
+    let mut buffer = vec![];
+    let mut chunk = [0; 8192];
+    loop {
+        match stream.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(read_size) => {
+                if buffer.len() + read_size > max_response_size {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "Response from {} exceeds the max size of {} bytes.",
+                            request.url, max_response_size
+                        ),
+                    ));
+                }
+                buffer.extend_from_slice(&chunk[..read_size]);
+            }
+            // Some servers close the connection without TLS close notify.
+            Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error),
+        }
+    }
+
+    parse_response(&buffer, request.method == "HEAD")
+}
+
+pub(crate) fn parse_response(buffer: &[u8], is_head: bool) -> std::io::Result<ClientResponse> {
+    let mut header_buffer = [httparse::EMPTY_HEADER; 64];
+    let mut response = httparse::Response::new(&mut header_buffer);
+
+    let header_size = match response.parse(buffer) {
+        Ok(httparse::Status::Complete(header_size)) => header_size,
+        Ok(httparse::Status::Partial) => {
+            return Err(std::io::Error::other("Incomplete response from server."));
+        }
+        Err(error) => return Err(std::io::Error::other(error)),
+    };
+
+    let mut headers = Headers::new();
+    for header in response.headers.iter() {
+        headers.set_multiple(header.name, header.value);
+    }
+
+    let status = response.code.unwrap_or_default();
+    let mut body = buffer[header_size..].to_vec();
+
+    let is_chunked = headers
+        .value("Transfer-Encoding")
+        .map(|value| value.to_lowercase().contains("chunked"))
+        .unwrap_or(false);
+
+    if is_head {
+        body.clear();
+    } else if is_chunked {
+        body = decode_chunked(&body)?;
+    } else if let Some(content_length) = headers.value("Content-Length") {
+        if let Ok(content_length) = content_length.trim().parse::<usize>() {
+            body.truncate(content_length);
+        }
+    }
+
+    Ok(ClientResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use rustls::RootCertStore;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::{
+        host_header, parse_url, signature, verify_signature, ClientRequest, HttpClient,
+        SIGNATURE_HEADER,
+    };
+
+    #[test]
+    fn test_signature() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let header_value = signature(b"secret", now, b"{}");
+        assert!(header_value.starts_with(&format!("t={},v1=", now)));
+
+        let tolerance = Duration::from_secs(300);
+        assert!(verify_signature(b"secret", &header_value, b"{}", tolerance));
+        assert!(!verify_signature(b"other", &header_value, b"{}", tolerance));
+        assert!(!verify_signature(
+            b"secret",
+            &header_value,
+            b"[]",
+            tolerance
+        ));
+
+        let expired = signature(b"secret", now - 600, b"{}");
+        assert!(!verify_signature(b"secret", &expired, b"{}", tolerance));
+        assert!(!verify_signature(b"secret", "v1=zz", b"{}", tolerance));
+    }
+
+    #[test]
+    fn test_host_header() {
+        assert_eq!("example.com", host_header(true, "example.com", 443));
+        assert_eq!("example.com", host_header(false, "example.com", 80));
+        assert_eq!("example.com:80", host_header(true, "example.com", 80));
+        assert_eq!("127.0.0.1:8080", host_header(false, "127.0.0.1", 8080));
+        assert_eq!("[::1]:8080", host_header(false, "::1", 8080));
+        assert_eq!("[::1]", host_header(true, "::1", 443));
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            (false, "::1".to_string(), 8080, "/hook".to_string()),
+            parse_url("http://[::1]:8080/hook").unwrap()
+        );
+        assert_eq!(
+            (true, "2001:db8::1".to_string(), 443, "/".to_string()),
+            parse_url("https://[2001:db8::1]").unwrap()
+        );
+        assert_eq!(
+            (false, "example.com".to_string(), 80, "/".to_string()),
+            parse_url("http://example.com").unwrap()
+        );
+        assert!(parse_url("http://[::1/hook").is_err());
+        assert!(parse_url("http://[::1]8080/hook").is_err());
+        assert!(parse_url("http://example.com:port/hook").is_err());
+    }
+
+    #[test]
+    fn test_native_certs() {
+        let certificate =
+            rcgen::generate_simple_self_signed(vec!["example.com".to_string()]).unwrap();
+        let ca_file = std::env::temp_dir().join(format!("racoon-ca-{}.pem", uuid::Uuid::new_v4()));
+        std::fs::write(&ca_file, certificate.cert.pem()).unwrap();
+
+        let cert_file = std::env::var_os("SSL_CERT_FILE");
+        let cert_dir = std::env::var_os("SSL_CERT_DIR");
+        std::env::set_var("SSL_CERT_FILE", &ca_file);
+        std::env::remove_var("SSL_CERT_DIR");
+        assert!(HttpClient::new().is_ok());
+
+        std::fs::write(&ca_file, "").unwrap();
+        assert!(HttpClient::new().is_err());
+
+        for (name, value) in [("SSL_CERT_FILE", cert_file), ("SSL_CERT_DIR", cert_dir)] {
+            match value {
+                Some(value) => std::env::set_var(name, value),
+                None => std::env::remove_var(name),
+            }
+        }
+        let _ = std::fs::remove_file(ca_file);
+    }
+
+    #[tokio::test]
+    async fn test_retries() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let server_attempts = attempts.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let read_size = stream.read(&mut buffer).await.unwrap();
+                let request = String::from_utf8_lossy(&buffer[..read_size]).to_string();
+
+                let response = if server_attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n".to_string()
+                } else {
+                    // Echoes the signature header back.
+                    let signature = request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("X-Racoon-Signature: "))
+                        .unwrap_or_default()
+                        .to_string();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        signature.len(),
+                        signature
+                    )
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let client = HttpClient::with_root_store(RootCertStore::empty())
+            .retries(3, Duration::from_millis(10))
+            .sign(SIGNATURE_HEADER, "secret");
+        let response = client
+            .send(
+                ClientRequest::post(&url)
+                    .header("X-Event", "ping")
+                    .body("{}"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(200, response.status);
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+
+        let header_value = String::from_utf8(response.body).unwrap();
+        assert!(verify_signature(
+            b"secret",
+            &header_value,
+            b"{}",
+            Duration::from_secs(60)
+        ));
+
+        // Last response is returned once the retries are exhausted.
+        attempts.store(0, Ordering::SeqCst);
+        let client = HttpClient::with_root_store(RootCertStore::empty())
+            .retries(1, Duration::from_millis(10));
+        let response = client.get(&url).await.unwrap();
+        assert_eq!(503, response.status);
+        assert_eq!(2, attempts.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/export", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let _ = stream.read(&mut buffer).await.unwrap();
+
+                let body = "a".repeat(4096);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let client = HttpClient::with_root_store(RootCertStore::empty()).max_response_size(1024);
+        let error = client.get(&url).await.err().unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+
+        let client = HttpClient::with_root_store(RootCertStore::empty()).max_response_size(8192);
+        let response = client.get(&url).await.unwrap();
+        assert_eq!(4096, response.body.len());
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+///
+/// Source of the current time for the time-based features such as rate limiting, session expiry
+/// and response caching.
+///
+pub trait AbstractClock: Sync + Send {
+    /// Monotonic time used for measuring the durations and expiry.
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time used where the time needs to agree between server instances.
+    fn system_time(&self) -> SystemTime;
+}
+
+pub type Clock = Arc<dyn AbstractClock>;
+
+///
+/// Clock reading the time from the operating system. Used unless another clock is set.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl AbstractClock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+///
+/// Returns the clock reading the time from the operating system.
+///
+pub fn system() -> Clock {
+    Arc::new(SystemClock)
+}
+
+///
+/// Clock which only moves when it is advanced, so the tests of the time-based features do not
+/// need to sleep. Clones share the same time.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// use racoon::core::cache::stores::MemoryCacheStore;
+/// use racoon::core::cache::{AbstractCacheStore, CachedResponse};
+/// use racoon::core::clock::ManualClock;
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::response::{HttpResponse, Response};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let clock = ManualClock::new();
+/// let store = MemoryCacheStore::new(100).clock(Arc::new(clock.clone()));
+///
+/// let mut response: Response = HttpResponse::ok().body("Hello");
+/// let cached = CachedResponse::from_response(&mut response);
+/// store.set("home", cached, Duration::from_secs(60)).await;
+/// assert!(store.get("home").await.is_some());
+///
+/// clock.advance(Duration::from_secs(61));
+/// assert!(store.get("home").await.is_none());
+/// # }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    start: Instant,
+    start_system_time: SystemTime,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    ///
+    /// Creates the clock stopped at the current time.
+    ///
+    pub fn new() -> Self {
+        Self::at(SystemTime::now())
+    }
+
+    ///
+    /// Creates the clock stopped at the given wall-clock time.
+    ///
+    pub fn at(system_time: SystemTime) -> Self {
+        Self {
+            start: Instant::now(),
+            start_system_time: system_time,
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        match self.elapsed.lock() {
+            Ok(elapsed) => *elapsed,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+
+    ///
+    /// Moves the time forward by the duration.
+    ///
+    pub fn advance(&self, duration: Duration) {
+        match self.elapsed.lock() {
+            Ok(mut elapsed) => *elapsed += duration,
+            Err(poisoned) => *poisoned.into_inner() += duration,
+        }
+    }
+}
+
+impl AbstractClock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.start_system_time + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{AbstractClock, ManualClock};
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::at(UNIX_EPOCH + Duration::from_secs(100));
+        let start = clock.now();
+
+        let shared = clock.clone();
+        shared.advance(Duration::from_secs(30));
+
+        assert_eq!(Duration::from_secs(30), clock.now() - start);
+        assert_eq!(UNIX_EPOCH + Duration::from_secs(130), clock.system_time());
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+use crate::racoon_debug;
+
+///
+/// Limits the number of requests handled at the same time. Requests over the limit are not
+/// queued but rejected immediately with `503 Service Unavailable`, keeping the latency of the
+/// accepted requests low under overload.
+///
+/// Limit is shared by the routes using the same instance. Registered with `Server::middleware`,
+/// it limits all the requests and registered with `Path::middleware` or `Scope::middleware`, it
+/// limits only those routes.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::concurrency::ConcurrencyLimit;
+/// use racoon::core::path::Path;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::server::Server;
+/// use racoon::view;
+///
+/// async fn report(request: Request) -> Response {
+///     HttpResponse::ok().body("Report")
+/// }
+///
+/// let paths = vec![
+///     Path::get("/report", view!(report)).middleware(ConcurrencyLimit::new(4)),
+/// ];
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(ConcurrencyLimit::new(1000).retry_after(Duration::from_secs(1)));
+/// server.urls(paths);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+    max_in_flight: usize,
+    semaphore: Arc<Semaphore>,
+    retry_after: Option<Duration>,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight,
+            semaphore: Arc::new(Semaphore::new(max_in_flight)),
+            retry_after: None,
+        }
+    }
+
+    ///
+    /// Sends `Retry-After` header with the rejected response.
+    ///
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    ///
+    /// Number of requests being handled currently.
+    ///
+    pub fn in_flight(&self) -> usize {
+        self.max_in_flight - self.semaphore.available_permits()
+    }
+}
+
+impl AbstractMiddleware for ConcurrencyLimit {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let limit = self.clone();
+
+        Box::new(Box::pin(async move {
+            // Permit is released when the response is returned or the handler is cancelled.
+            let _permit = match limit.semaphore.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    racoon_debug!(
+                        "Request rejected. {} requests are already in flight.",
+                        limit.max_in_flight
+                    );
+
+                    let mut response: Response =
+                        HttpResponse::service_unavailable().body("503 Service Unavailable");
+                    if let Some(retry_after) = limit.retry_after {
+                        response
+                            .get_headers()
+                            .set("Retry-After", retry_after.as_secs().max(1).to_string());
+                    }
+                    return response;
+                }
+            };
+
+            next.run(request).await
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::Headers;
+    use crate::core::middleware::Next;
+    use crate::core::path::View;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse};
+
+    use super::ConcurrencyLimit;
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let view: View = |_| {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Done");
+                response
+            })
+        };
+
+        let limit = ConcurrencyLimit::new(1);
+        let next = Next::new(Arc::new(vec![Arc::new(limit.clone())]), None);
+
+        let first = tokio::spawn(
+            next.clone()
+                .with_view(Some(view))
+                .run(request("/", Headers::new(), b"").await),
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(1, limit.in_flight());
+
+        let response = next
+            .clone()
+            .with_view(Some(view))
+            .run(request("/", Headers::new(), b"").await)
+            .await;
+        assert_eq!(503, response.status().0);
+
+        assert_eq!(200, first.await.unwrap().status().0);
+        assert_eq!(0, limit.in_flight());
+    }
+}
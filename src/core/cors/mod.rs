@@ -0,0 +1,493 @@
+use std::time::Duration;
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse};
+
+///
+/// Cross-Origin Resource Sharing configuration. When configured on the server, `OPTIONS`
+/// preflight requests for the registered routes are answered automatically.
+///
+/// It can also be used as middleware, globally with `Server::middleware` or for the scope with
+/// `Scope::middleware`. The middleware answers preflight requests and adds the CORS headers to
+/// the responses of the wrapped routes.
+///
+/// Origins can be exact like `https://example.com`, wildcard subdomains like
+/// `https://*.example.com` or `*` for any origin.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::cors::Cors;
+/// use racoon::core::server::Server;
+///
+/// let cors = Cors::new()
+///     .allow_origin("https://example.com")
+///     .allow_origin("https://*.example.com")
+///     .allow_methods(&["GET", "POST"])
+///     .allow_headers(&["Content-Type", "Authorization"])
+///     .allow_credentials(true)
+///     .max_age(Duration::from_secs(3600));
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.cors(cors);
+/// ```
+///
+/// More information: <https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS>
+///
+#[derive(Debug, Clone, Default)]
+pub struct Cors {
+    origins: Vec<String>,
+    origin_predicate: Option<fn(&str) -> bool>,
+    methods: Vec<String>,
+    headers: Vec<String>,
+    expose_headers: Vec<String>,
+    allow_credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Allows requests from the origin. Can be called multiple times.
+    ///
+    pub fn allow_origin<S: AsRef<str>>(mut self, origin: S) -> Self {
+        let origin = origin.as_ref().trim().trim_end_matches('/').to_lowercase();
+        self.origins.push(origin);
+        self
+    }
+
+    pub fn allow_any_origin(self) -> Self {
+        self.allow_origin("*")
+    }
+
+    ///
+    /// Allows origins for which the predicate returns true in addition to the listed origins.
+    ///
+    pub fn allow_origin_fn(mut self, predicate: fn(&str) -> bool) -> Self {
+        self.origin_predicate = Some(predicate);
+        self
+    }
+
+    ///
+    /// Methods allowed in the preflight response. If not specified, methods of the matched route
+    /// are used.
+    ///
+    pub fn allow_methods<S: AsRef<str>>(mut self, methods: &[S]) -> Self {
+        for method in methods {
+            self.methods.push(method.as_ref().to_uppercase());
+        }
+        self
+    }
+
+    ///
+    /// Request headers allowed in the preflight response. If not specified, headers requested by
+    /// the client are allowed.
+    ///
+    pub fn allow_headers<S: AsRef<str>>(mut self, headers: &[S]) -> Self {
+        for header in headers {
+            self.headers.push(header.as_ref().to_string());
+        }
+        self
+    }
+
+    ///
+    /// Response headers which can be read by the client script.
+    ///
+    pub fn expose_headers<S: AsRef<str>>(mut self, headers: &[S]) -> Self {
+        for header in headers {
+            self.expose_headers.push(header.as_ref().to_string());
+        }
+        self
+    }
+
+    ///
+    /// Allows cookies and authorization headers. The request origin is sent back instead of `*`
+    /// since browsers reject wildcard with credentials.
+    ///
+    pub fn allow_credentials(mut self, allow: bool) -> Self {
+        self.allow_credentials = allow;
+        self
+    }
+
+    ///
+    /// Duration for which the preflight response can be cached by the client.
+    ///
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn exposed_headers(&self) -> &[String] {
+        &self.expose_headers
+    }
+
+    pub fn is_origin_allowed(&self, origin: &str) -> bool {
+        let origin = origin.trim().trim_end_matches('/').to_lowercase();
+
+        let is_listed = self
+            .origins
+            .iter()
+            .any(|allowed| origin_matches(allowed, &origin));
+        if is_listed {
+            return true;
+        }
+
+        match self.origin_predicate {
+            Some(predicate) => predicate(&origin),
+            None => false,
+        }
+    }
+
+    ///
+    /// Returns true if the request is preflight request sent by the browser before the actual
+    /// cross-origin request.
+    ///
+    pub fn is_preflight(method: &str, request_headers: &Headers) -> bool {
+        method == "OPTIONS"
+            && request_headers.value("Origin").is_some()
+            && request_headers
+                .value("Access-Control-Request-Method")
+                .is_some()
+    }
+
+    ///
+    /// Headers for allowing the origin. Returns empty headers if the origin is not allowed.
+    ///
+    pub fn origin_headers(&self, request_headers: &Headers) -> Headers {
+        let mut headers = Headers::new();
+        headers.set("Vary", "Origin");
+
+        let origin = match request_headers.value("Origin") {
+            Some(origin) => origin,
+            None => return headers,
+        };
+
+        if !self.is_origin_allowed(&origin) {
+            return headers;
+        }
+
+        let allows_any = self.origins.iter().any(|allowed| allowed == "*");
+        if allows_any && !self.allow_credentials {
+            headers.set("Access-Control-Allow-Origin", "*");
+        } else {
+            headers.set("Access-Control-Allow-Origin", origin);
+        }
+
+        if self.allow_credentials {
+            headers.set("Access-Control-Allow-Credentials", "true");
+        }
+        headers
+    }
+
+    ///
+    /// Headers for answering the preflight request. Methods of the matched route are allowed if
+    /// the methods are not configured.
+    ///
+    pub fn preflight_headers(
+        &self,
+        request_headers: &Headers,
+        route_methods: &[String],
+    ) -> Headers {
+        let mut headers = self.origin_headers(request_headers);
+        if headers.value("Access-Control-Allow-Origin").is_none() {
+            return headers;
+        }
+
+        let methods = if !self.methods.is_empty() {
+            self.methods.join(", ")
+        } else if !route_methods.is_empty() {
+            route_methods.join(", ")
+        } else {
+            request_headers
+                .value("Access-Control-Request-Method")
+                .unwrap_or_default()
+                .to_uppercase()
+        };
+        headers.set("Access-Control-Allow-Methods", methods);
+
+        if !self.headers.is_empty() {
+            headers.set("Access-Control-Allow-Headers", self.headers.join(", "));
+        } else if let Some(requested) = request_headers.value("Access-Control-Request-Headers") {
+            headers.set("Access-Control-Allow-Headers", requested);
+            headers.set("Vary", "Origin, Access-Control-Request-Headers");
+        }
+
+        if let Some(max_age) = self.max_age {
+            headers.set("Access-Control-Max-Age", max_age.as_secs().to_string());
+        }
+        headers
+    }
+}
+
+impl AbstractMiddleware for Cors {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let cors = self.clone();
+
+        Box::new(Box::pin(async move {
+            if request.headers.value("Origin").is_none() {
+                return next.run(request).await;
+            }
+
+            if Cors::is_preflight(&request.method, &request.headers) {
+                let mut response = HttpResponse::no_content().empty();
+                let headers = response.get_headers();
+                for (name, values) in cors.preflight_headers(&request.headers, &[]) {
+                    for value in values {
+                        headers.set(&name, value);
+                    }
+                }
+                return response;
+            }
+
+            let mut origin_headers = cors.origin_headers(&request.headers);
+            if origin_headers
+                .value("Access-Control-Allow-Origin")
+                .is_some()
+                && !cors.expose_headers.is_empty()
+            {
+                origin_headers.set(
+                    "Access-Control-Expose-Headers",
+                    cors.expose_headers.join(", "),
+                );
+            }
+
+            // Streamed responses write the head before returning, so the headers are also added
+            // to the request to be sent with the head.
+            {
+                let mut response_headers = request.response_headers.lock().await;
+                add_origin_headers(&mut response_headers, &origin_headers);
+            }
+
+            let mut response = next.run(request).await;
+            add_origin_headers(response.get_headers(), &origin_headers);
+            response
+        }))
+    }
+}
+
+///
+/// Adds the origin headers to the response headers. `Vary` is appended to the existing value
+/// instead of replacing the one set by the view or the other middlewares.
+///
+fn add_origin_headers(headers: &mut Headers, origin_headers: &Headers) {
+    for (name, values) in origin_headers {
+        for value in values {
+            if !name.eq_ignore_ascii_case("Vary") {
+                headers.set(name, value);
+                continue;
+            }
+
+            // Vary may be set by different cases or multiple times when the request headers are
+            // merged to the response.
+            let mut vary: Vec<String> = vec![];
+            headers.retain(|key, existing_values| {
+                if !key.eq_ignore_ascii_case("Vary") {
+                    return true;
+                }
+
+                for existing_value in existing_values.iter() {
+                    vary.extend(
+                        String::from_utf8_lossy(existing_value)
+                            .split(',')
+                            .map(|field| field.trim().to_string())
+                            .filter(|field| !field.is_empty()),
+                    );
+                }
+                false
+            });
+
+            for field in String::from_utf8_lossy(value).split(',') {
+                let field = field.trim();
+                if !vary
+                    .iter()
+                    .any(|existing| existing.eq_ignore_ascii_case(field))
+                {
+                    vary.push(field.to_string());
+                }
+            }
+            headers.set("Vary", vary.join(", "));
+        }
+    }
+}
+
+fn origin_matches(allowed: &str, origin: &str) -> bool {
+    if allowed == "*" || allowed == origin {
+        return true;
+    }
+
+    // Wildcard subdomain like https://*.example.com
+    match allowed.split_once('*') {
+        Some((prefix, suffix)) => {
+            origin.len() > prefix.len() + suffix.len()
+                && origin.starts_with(prefix)
+                && origin.ends_with(suffix)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::middleware::Next;
+    use crate::core::path::{Path, View};
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::writer::ResponseWriter;
+    use crate::core::response::{AbstractResponse, HttpResponse, Response};
+    use crate::core::server::tests::{send, serve};
+
+    use super::Cors;
+
+    #[test]
+    fn test_preflight_headers() {
+        let cors = Cors::new()
+            .allow_origin("https://example.com/")
+            .allow_origin("https://*.example.org")
+            .allow_credentials(true)
+            .max_age(Duration::from_secs(600));
+
+        assert!(cors.is_origin_allowed("https://EXAMPLE.com"));
+        assert!(cors.is_origin_allowed("https://app.example.org"));
+        assert!(!cors.is_origin_allowed("https://example.org"));
+        assert!(!cors.is_origin_allowed("https://evil.com"));
+
+        let mut request_headers = Headers::new();
+        request_headers.set("Origin", "https://app.example.org");
+        request_headers.set("Access-Control-Request-Method", "PUT");
+        request_headers.set("Access-Control-Request-Headers", "Content-Type");
+        assert!(Cors::is_preflight("OPTIONS", &request_headers));
+
+        let headers =
+            cors.preflight_headers(&request_headers, &["GET".to_string(), "PUT".to_string()]);
+        assert_eq!(
+            Some("https://app.example.org".to_string()),
+            headers.value("Access-Control-Allow-Origin")
+        );
+        assert_eq!(
+            Some("GET, PUT".to_string()),
+            headers.value("Access-Control-Allow-Methods")
+        );
+        assert_eq!(
+            Some("Content-Type".to_string()),
+            headers.value("Access-Control-Allow-Headers")
+        );
+        assert_eq!(
+            Some("600".to_string()),
+            headers.value("Access-Control-Max-Age")
+        );
+
+        request_headers.set("Origin", "https://evil.com");
+        let headers = cors.preflight_headers(&request_headers, &[]);
+        assert_eq!(None, headers.value("Access-Control-Allow-Origin"));
+    }
+
+    #[tokio::test]
+    async fn test_cors_middleware() {
+        let cors = Cors::new()
+            .allow_origin("https://example.com")
+            .expose_headers(&["X-Total-Count"]);
+        let next = Next::new(Arc::new(vec![Arc::new(cors)]), None);
+
+        let view: View = |_| {
+            Box::pin(async move {
+                let response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Users");
+                response
+            })
+        };
+
+        let mut headers = Headers::new();
+        headers.set("Origin", "https://example.com");
+        let mut response = next
+            .clone()
+            .with_view(Some(view))
+            .run(request("/users", headers.clone(), b"").await)
+            .await;
+        assert_eq!(200, response.status().0);
+        assert_eq!(
+            Some("https://example.com".to_string()),
+            response.get_headers().value("Access-Control-Allow-Origin")
+        );
+        assert_eq!(
+            Some("X-Total-Count".to_string()),
+            response
+                .get_headers()
+                .value("Access-Control-Expose-Headers")
+        );
+
+        headers.set("Access-Control-Request-Method", "DELETE");
+        let mut preflight_request = request("/users", headers, b"").await;
+        preflight_request.method = "OPTIONS".to_string();
+        let mut response = next.with_view(Some(view)).run(preflight_request).await;
+        assert_eq!(204, response.status().0);
+        assert_eq!(
+            Some("DELETE".to_string()),
+            response.get_headers().value("Access-Control-Allow-Methods")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cors_streamed_response() {
+        let view: View = |request| {
+            Box::pin(async move {
+                let mut writer = ResponseWriter::from(&request);
+                let _ = writer.write_chunk("ping").await;
+                let response: Response = writer.finish().await;
+                response
+            })
+        };
+
+        let address = serve(move |server| {
+            server
+                .middleware(Cors::new().allow_origin("https://example.com"))
+                .urls(vec![Path::new("/events", view)]);
+        });
+
+        let request = "GET /events HTTP/1.1\r\nOrigin: https://example.com\r\n\
+            Connection: close\r\n\r\n";
+        let response = send(address, request).await;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head
+            .to_lowercase()
+            .contains("access-control-allow-origin: https://example.com"));
+        assert_eq!("4\r\nping\r\n0\r\n\r\n", body);
+    }
+
+    #[tokio::test]
+    async fn test_cors_vary() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let mut response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Users");
+                response.get_headers().set("Vary", "Accept-Encoding");
+                response
+            })
+        };
+
+        let address = serve(move |server| {
+            server
+                .middleware(Cors::new().allow_origin("https://example.com"))
+                .urls(vec![Path::new("/users", view)]);
+        });
+
+        let request = "GET /users HTTP/1.1\r\nOrigin: https://example.com\r\n\
+            Connection: close\r\n\r\n";
+        let response = send(address, request).await;
+        let (head, _) = response.split_once("\r\n\r\n").unwrap();
+        let vary: Vec<&str> = head
+            .split("\r\n")
+            .filter(|line| line.to_lowercase().starts_with("vary:"))
+            .collect();
+        assert_eq!(vec!["Vary: Accept-Encoding, Origin"], vary);
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::Read;
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+use crate::core::forms::FormFieldError;
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::HttpResponse;
+use crate::racoon_debug;
+
+///
+/// Decompresses request body sent with `Content-Encoding` of `gzip`, `deflate` or `br` before it
+/// reaches the view, so forms and JSON bodies are parsed as usual. Decompressed body larger than
+/// the max size is rejected with `413 Payload Too Large` to protect from decompression bombs.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::decompression::Decompression;
+/// use racoon::core::server::Server;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(Decompression::new().max_size(2 * 1024 * 1024));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Decompression {
+    max_size: usize,
+}
+
+impl Default for Decompression {
+    fn default() -> Self {
+        Self {
+            max_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl Decompression {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Max size of the decompressed body in bytes. Default is 10 MiB.
+    ///
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DecompressionError {
+    UnsupportedEncoding(String),
+    MaxSizeExceed,
+    InvalidBody(String),
+}
+
+///
+/// Decodes the body with the encodings listed in `Content-Encoding` header. Encodings are removed
+/// in the reverse order they were applied.
+///
+pub fn decompress(
+    content_encoding: &str,
+    body: Vec<u8>,
+    max_size: usize,
+) -> Result<Vec<u8>, DecompressionError> {
+    let mut body = body;
+
+    for encoding in content_encoding.rsplit(',') {
+        let encoding = encoding.trim().to_lowercase();
+
+        let decoder: Box<dyn Read> = match encoding.as_str() {
+            "identity" | "" => continue,
+            "gzip" | "x-gzip" => Box::new(GzDecoder::new(body.as_slice())),
+            "deflate" => Box::new(ZlibDecoder::new(body.as_slice())),
+            "br" => Box::new(brotli_decompressor::Decompressor::new(
+                body.as_slice(),
+                4096,
+            )),
+            _ => return Err(DecompressionError::UnsupportedEncoding(encoding)),
+        };
+
+        // Reads one more byte than allowed to detect oversized body without decoding all of it.
+        let mut decoded = vec![];
+        if let Err(error) = decoder.take(max_size as u64 + 1).read_to_end(&mut decoded) {
+            return Err(DecompressionError::InvalidBody(error.to_string()));
+        }
+
+        if decoded.len() > max_size {
+            return Err(DecompressionError::MaxSizeExceed);
+        }
+        body = decoded;
+    }
+
+    Ok(body)
+}
+
+impl AbstractMiddleware for Decompression {
+    fn handle(&self, mut request: Request, next: Next) -> MiddlewareResult {
+        let max_size = self.max_size;
+
+        Box::new(Box::pin(async move {
+            let content_encoding = match request.headers.value("Content-Encoding") {
+                Some(content_encoding) => content_encoding,
+                None => return next.run(request).await,
+            };
+
+            let body = match request.body().await {
+                Ok(body) => body,
+                Err(FormFieldError::MaxBodySizeExceed) => {
+                    return HttpResponse::payload_too_large().body("413 Payload Too Large");
+                }
+                Err(error) => {
+                    racoon_debug!("Failed to read compressed body. Error: {:?}", error);
+                    return HttpResponse::bad_request().body("400 Bad Request");
+                }
+            };
+
+            let decompressed = match decompress(&content_encoding, body, max_size) {
+                Ok(decompressed) => decompressed,
+                Err(DecompressionError::UnsupportedEncoding(encoding)) => {
+                    racoon_debug!("Unsupported content encoding: {}", encoding);
+                    return HttpResponse::unsupported_media_type()
+                        .body("415 Unsupported Media Type");
+                }
+                Err(DecompressionError::MaxSizeExceed) => {
+                    return HttpResponse::payload_too_large().body("413 Payload Too Large");
+                }
+                Err(DecompressionError::InvalidBody(error)) => {
+                    racoon_debug!("Failed to decompress body. Error: {}", error);
+                    return HttpResponse::bad_request().body("400 Bad Request");
+                }
+            };
+
+            // Bytes of the next request restored while reading the body are kept after the
+            // decompressed body.
+            let mut payload = decompressed.clone();
+            if request.stream.restored_len().await > 0 {
+                match request.stream.read_chunk().await {
+                    Ok(extra_bytes) => payload.extend(extra_bytes),
+                    Err(error) => {
+                        racoon_debug!("Failed to read restored bytes. Error: {}", error);
+                        return HttpResponse::bad_request().body("400 Bad Request");
+                    }
+                }
+            }
+
+            if let Err(error) = request.stream.restore_payload(&payload).await {
+                racoon_debug!("Failed to restore decompressed body. Error: {}", error);
+                return HttpResponse::bad_request().body("400 Bad Request");
+            }
+
+            request.headers.retain(|name, _| {
+                !name.eq_ignore_ascii_case("Content-Encoding")
+                    && !name.eq_ignore_ascii_case("Content-Length")
+            });
+            request
+                .headers
+                .set("Content-Length", decompressed.len().to_string());
+            request.body_read.store(
+                decompressed.is_empty(),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+
+            next.run(request).await
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::middleware::Next;
+    use crate::core::path::View;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse};
+
+    use super::{decompress, Decompression, DecompressionError};
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decompress() {
+        let compressed = gzip(b"name=John");
+        assert_eq!(
+            Ok(b"name=John".to_vec()),
+            decompress("gzip", compressed.clone(), 1024)
+        );
+        assert_eq!(
+            Err(DecompressionError::MaxSizeExceed),
+            decompress("gzip", compressed, 4)
+        );
+
+        let bomb = gzip(&vec![0u8; 100_000]);
+        assert_eq!(
+            Err(DecompressionError::MaxSizeExceed),
+            decompress("gzip", bomb, 1024)
+        );
+        assert!(matches!(
+            decompress("compress", vec![], 1024),
+            Err(DecompressionError::UnsupportedEncoding(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_decompression_middleware() {
+        let middleware = Decompression::new().max_size(1024);
+        let next = Next::new(Arc::new(vec![Arc::new(middleware)]), None);
+
+        let view: View = |request| {
+            Box::pin(async move {
+                let body = request.body().await.unwrap();
+                let response: Box<dyn AbstractResponse> = HttpResponse::ok().bytes(body);
+                response
+            })
+        };
+
+        let compressed = gzip(b"{\"name\": \"John\"}");
+        let mut headers = Headers::new();
+        headers.set("Content-Encoding", "gzip");
+        headers.set("Content-Length", compressed.len().to_string());
+        let mut response = next
+            .with_view(Some(view))
+            .run(request("/users", headers, &compressed).await)
+            .await;
+        assert_eq!(200, response.status().0);
+        assert_eq!(
+            Some("16".to_string()),
+            response.get_headers().value("Content-Length")
+        );
+    }
+}
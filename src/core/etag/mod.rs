@@ -0,0 +1,220 @@
+use sha1::{Digest, Sha1};
+
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+
+///
+/// Headers of the original response which are also sent with `304 Not Modified`.
+///
+pub(crate) const NOT_MODIFIED_HEADERS: [&str; 5] = [
+    "Cache-Control",
+    "Content-Location",
+    "Date",
+    "Expires",
+    "Vary",
+];
+
+///
+/// Adds `ETag` header to the buffered `GET` and `HEAD` responses by hashing the body and answers
+/// `304 Not Modified` without body when the `If-None-Match` header of the request matches. ETag
+/// set by the view is used as it is. Streamed responses and responses larger than the max size are
+/// not hashed.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::etag::ETag;
+/// use racoon::core::path::{Path, Scope};
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::view;
+///
+/// async fn products(request: Request) -> Response {
+///     HttpResponse::ok().body("[]")
+/// }
+///
+/// let api = Scope::new()
+///     .middleware(ETag::new().max_size(512 * 1024))
+///     .urls(vec![Path::get("/products", view!(products))]);
+/// let paths = Scope::new().mount("/api", api).into_paths();
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct ETag {
+    max_size: usize,
+    weak: bool,
+}
+
+impl Default for ETag {
+    fn default() -> Self {
+        Self {
+            max_size: 1024 * 1024,
+            weak: false,
+        }
+    }
+}
+
+impl ETag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Max size of the response body in bytes to be hashed. Default is 1 MiB.
+    ///
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    ///
+    /// Generates weak ETag like `W/"..."`, for responses which are semantically same but may
+    /// differ byte by byte.
+    ///
+    pub fn weak(mut self, weak: bool) -> Self {
+        self.weak = weak;
+        self
+    }
+
+    ///
+    /// Returns ETag for the body.
+    ///
+    pub fn generate(&self, body: &[u8]) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(body);
+        let hash = format!("{:x}", hasher.finalize());
+
+        if self.weak {
+            format!("W/\"{}\"", hash)
+        } else {
+            format!("\"{}\"", hash)
+        }
+    }
+}
+
+///
+/// Returns true if the `If-None-Match` header value matches the ETag. Weak comparison is used as
+/// required for `If-None-Match`.
+///
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim().trim_start_matches("W/");
+
+    if_none_match.split(',').any(|value| {
+        let value = value.trim();
+        value == "*" || value.trim_start_matches("W/") == etag
+    })
+}
+
+impl AbstractMiddleware for ETag {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let config = self.clone();
+
+        Box::new(Box::pin(async move {
+            if request.method != "GET" && request.method != "HEAD" {
+                return next.run(request).await;
+            }
+
+            let if_none_match = request.headers.value("If-None-Match");
+            let mut response = next.run(request).await;
+
+            let (status_code, _) = response.status();
+            if status_code != 200 || !response.serve_default() {
+                return response;
+            }
+
+            let etag = match response.get_headers().value("ETag") {
+                Some(etag) => etag,
+                None => {
+                    if response.get_body().len() > config.max_size {
+                        return response;
+                    }
+
+                    let etag = config.generate(response.get_body());
+                    response.get_headers().set("ETag", &etag);
+                    etag
+                }
+            };
+
+            match if_none_match {
+                Some(if_none_match) if etag_matches(&if_none_match, &etag) => {
+                    not_modified(&mut response, &etag)
+                }
+                _ => response,
+            }
+        }))
+    }
+}
+
+fn not_modified(response: &mut Response, etag: &str) -> Response {
+    let mut not_modified: Response = HttpResponse::not_modified().empty();
+    let headers = response.get_headers();
+
+    for name in NOT_MODIFIED_HEADERS {
+        for value in headers.multiple_values(name) {
+            not_modified.get_headers().set_multiple(name, value);
+        }
+    }
+
+    let not_modified_headers = not_modified.get_headers();
+    not_modified_headers.set("ETag", etag);
+    // Body is never sent with 304, so the length of the empty body is not advertised.
+    not_modified_headers.remove("Content-Length");
+    not_modified
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::middleware::Next;
+    use crate::core::path::View;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse};
+
+    use super::{etag_matches, ETag};
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"xyz\", \"abc\"", "W/\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"xyz\"", "\"abc\""));
+    }
+
+    #[tokio::test]
+    async fn test_etag_middleware() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let mut response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Products");
+                response.get_headers().set("Cache-Control", "max-age=60");
+                response
+            })
+        };
+        let next = Next::new(Arc::new(vec![Arc::new(ETag::new())]), None);
+
+        let mut get_request = request("/products", Headers::new(), b"").await;
+        get_request.method = "GET".to_string();
+        let mut response = next.clone().with_view(Some(view)).run(get_request).await;
+        assert_eq!(200, response.status().0);
+        let etag = response.get_headers().value("ETag").unwrap();
+        assert_eq!(ETag::new().generate(b"Products"), etag);
+
+        let mut headers = Headers::new();
+        headers.set("If-None-Match", &etag);
+        let mut conditional_request = request("/products", headers, b"").await;
+        conditional_request.method = "GET".to_string();
+        let mut response = next.with_view(Some(view)).run(conditional_request).await;
+        assert_eq!(304, response.status().0);
+        assert!(response.get_body().is_empty());
+        assert_eq!(
+            Some("max-age=60".to_string()),
+            response.get_headers().value("Cache-Control")
+        );
+    }
+}
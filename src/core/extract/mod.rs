@@ -0,0 +1,526 @@
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::core::forms::FormFieldError;
+use crate::core::headers::HeaderValue;
+use crate::core::request::Request;
+use crate::core::response::builder::ResponseBuilder;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse, Response};
+use crate::{racoon_debug, racoon_error};
+
+pub type ExtractResult<T> = Box<dyn Future<Output = Result<T, Response>> + Send + Unpin>;
+
+///
+/// Types which can be created from the request and used as the typed handler arguments. If the
+/// extraction fails, the returned error response is sent to the client and the handler is not
+/// called.
+///
+pub trait FromRequest: Sized {
+    fn from_request(request: Request) -> ExtractResult<Self>;
+}
+
+///
+/// Types which can be returned from the typed handlers.
+///
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl<R: AbstractResponse + 'static> IntoResponse for Box<R> {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Response {
+        HttpResponse::ok().body(self)
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> Response {
+        HttpResponse::ok().body(self)
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(error) => error.into_response(),
+        }
+    }
+}
+
+impl FromRequest for Request {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move { Ok(request) }))
+    }
+}
+
+///
+/// Extracts path params. If the route has single param, it is parsed as `T` like `Path<u64>`.
+/// Otherwise, params are deserialized to the struct with the param names as fields. Responds
+/// with `404 Not Found` if the params cannot be parsed.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Path<T> {
+    fn from_request(mut request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            let params: Vec<(String, String)> =
+                request.path_params.map().clone().into_iter().collect();
+            let encoded = serde_urlencoded::to_string(&params).unwrap_or_default();
+
+            if params.len() == 1 {
+                if let Ok(mut values) = serde_urlencoded::from_str::<Vec<(String, T)>>(&encoded) {
+                    if let Some((_, value)) = values.pop() {
+                        return Ok(Path(value));
+                    }
+                }
+            }
+
+            match serde_urlencoded::from_str::<T>(&encoded) {
+                Ok(value) => Ok(Path(value)),
+                Err(error) => {
+                    racoon_debug!("Failed to extract path params. Error: {}", error);
+                    Err(error_response(HttpResponse::not_found(), "Not Found"))
+                }
+            }
+        }))
+    }
+}
+
+///
+/// Deserializes query params to `T`. Responds with `400 Bad Request` if the query params are
+/// invalid.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Query<T> {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            let mut params = vec![];
+            for (name, values) in request.query_params.iter() {
+                for value in values {
+                    params.push((name, value));
+                }
+            }
+
+            let encoded = serde_urlencoded::to_string(&params).unwrap_or_default();
+            match serde_urlencoded::from_str::<T>(&encoded) {
+                Ok(value) => Ok(Query(value)),
+                Err(error) => Err(bad_request(format!("Invalid query params. {}", error))),
+            }
+        }))
+    }
+}
+
+///
+/// Deserializes JSON request body to `T`. Can also be returned from the handlers to respond with
+/// JSON body.
+///
+/// Responds with `415 Unsupported Media Type` if the content type is not JSON, `413 Payload Too
+/// Large` if the body exceeds the max body size and `400 Bad Request` if the body is invalid.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Json<T> {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            let content_type = request.headers.value("Content-Type").unwrap_or_default();
+            let mime_type = content_type.split(';').next().unwrap_or_default().trim();
+
+            let is_json = mime_type.eq_ignore_ascii_case("application/json")
+                || mime_type.to_lowercase().ends_with("+json");
+            if !is_json {
+                return Err(error_response(
+                    HttpResponse::unsupported_media_type(),
+                    "Expected request with Content-Type: application/json",
+                ));
+            }
+
+            let body = read_body(&request).await?;
+            match serde_json::from_slice::<T>(&body) {
+                Ok(value) => Ok(Json(value)),
+                Err(error) => Err(bad_request(format!("Invalid JSON body. {}", error))),
+            }
+        }))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        ResponseBuilder::ok().json(&self.0)
+    }
+}
+
+///
+/// Deserializes `application/x-www-form-urlencoded` request body to `T`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Form<T>(pub T);
+
+impl<T: DeserializeOwned + Send + 'static> FromRequest for Form<T> {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            let content_type = request.headers.value("Content-Type").unwrap_or_default();
+            if !content_type
+                .to_lowercase()
+                .starts_with("application/x-www-form-urlencoded")
+            {
+                return Err(error_response(
+                    HttpResponse::unsupported_media_type(),
+                    "Expected request with Content-Type: application/x-www-form-urlencoded",
+                ));
+            }
+
+            let body = read_body(&request).await?;
+            match serde_urlencoded::from_bytes::<T>(&body) {
+                Ok(value) => Ok(Form(value)),
+                Err(error) => Err(bad_request(format!("Invalid form body. {}", error))),
+            }
+        }))
+    }
+}
+
+///
+/// Clones the application state registered with `Server::with_state`. Wrap the state in `Arc`
+/// if it is expensive to clone. Responds with `500 Internal Server Error` if the state of the
+/// type is not registered.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::extract::State;
+///
+/// #[derive(Clone)]
+/// struct Config {
+///     site_name: String,
+/// }
+///
+/// async fn home(State(config): State<Config>) -> String {
+///     format!("Welcome to {}", config.site_name)
+/// }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct State<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequest for State<T> {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            match request.state::<T>() {
+                Some(state) => Ok(State(state.clone())),
+                None => {
+                    racoon_error!(
+                        "State of type {} is not registered.",
+                        std::any::type_name::<T>()
+                    );
+                    Err(error_response(
+                        HttpResponse::internal_server_error(),
+                        "Internal Server Error",
+                    ))
+                }
+            }
+        }))
+    }
+}
+
+macro_rules! impl_deref {
+    ($($extractor: ident),*) => {
+        $(
+            impl<T> Deref for $extractor<T> {
+                type Target = T;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.0
+                }
+            }
+
+            impl<T> DerefMut for $extractor<T> {
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    &mut self.0
+                }
+            }
+        )*
+    };
+}
+
+impl_deref!(Path, Query, Json, Form, State);
+
+pub(crate) async fn read_body(request: &Request) -> Result<Vec<u8>, Response> {
+    match request.body().await {
+        Ok(body) => Ok(body),
+        Err(FormFieldError::MaxBodySizeExceed) => Err(error_response(
+            HttpResponse::payload_too_large(),
+            "Payload Too Large",
+        )),
+        Err(error) => {
+            racoon_debug!("Failed to read request body. Error: {:?}", error);
+            Err(bad_request("Failed to read request body."))
+        }
+    }
+}
+
+pub(crate) fn bad_request<S: AsRef<str>>(message: S) -> Response {
+    error_response(HttpResponse::bad_request(), message)
+}
+
+fn error_response<S: AsRef<str>>(response: HttpResponse, message: S) -> Response {
+    response.body(message)
+}
+
+///
+/// Async functions taking extractors as arguments. Use `handler!` macro to convert the function
+/// to the view.
+///
+pub trait Handler<Args>: Copy + Send + Sync + 'static {
+    fn call(self, request: Request) -> Pin<Box<dyn Future<Output = Response> + Send>>;
+}
+
+macro_rules! impl_handler {
+    ($($arg: ident),*) => {
+        impl<F, Fut, R, $($arg,)*> Handler<($($arg,)*)> for F
+        where
+            F: Fn($($arg,)*) -> Fut + Copy + Send + Sync + 'static,
+            Fut: Future<Output = R> + Send,
+            R: IntoResponse,
+            $($arg: FromRequest + Send,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn call(self, request: Request) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+                Box::pin(async move {
+                    $(
+                        let $arg = match $arg::from_request(request.clone()).await {
+                            Ok(value) => value,
+                            Err(response) => return response,
+                        };
+                    )*
+
+                    self($($arg,)*).await.into_response()
+                })
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(A1);
+impl_handler!(A1, A2);
+impl_handler!(A1, A2, A3);
+impl_handler!(A1, A2, A3, A4);
+impl_handler!(A1, A2, A3, A4, A5);
+impl_handler!(A1, A2, A3, A4, A5, A6);
+
+///
+/// Converts async function with extractor arguments to the view.
+///
+/// # Examples
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// use racoon::core::extract::{Json, Path, Query};
+/// use racoon::core::path::Path as Route;
+/// use racoon::handler;
+///
+/// #[derive(Deserialize)]
+/// struct Filters {
+///     page: Option<u32>,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Comment {
+///     text: String,
+/// }
+///
+/// async fn add_comment(Path(post_id): Path<u64>, Query(filters): Query<Filters>, Json(comment): Json<Comment>) -> String {
+///     format!("Post {} page {:?}: {}", post_id, filters.page, comment.text)
+/// }
+///
+/// let paths = vec![
+///     Route::post("/posts/{id:uint}/comments", handler!(add_comment)),
+/// ];
+/// ```
+///
+#[macro_export]
+macro_rules! handler {
+    ($handler_name: ident) => {
+        |request: racoon::core::request::Request| {
+            racoon::core::extract::Handler::call($handler_name, request)
+        }
+    };
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use serde::Deserialize;
+    use tokio::sync::Mutex;
+
+    use crate::core::forms::FormConstraints;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::path::PathParams;
+    use crate::core::request::Request;
+    use crate::core::response::Response;
+    use crate::core::server::{Context, Server};
+    use crate::core::session::{AbstractSessionManager, SessionManager, SessionResult};
+    use crate::core::stream::{Stream, TestStreamWrapper};
+    use crate::core::testing::TestServer;
+
+    use super::{Handler, Json, Path, Query, State};
+
+    struct NoSession;
+
+    impl AbstractSessionManager for NoSession {
+        fn set(&self, _: &String, _: &str, _: &str) -> SessionResult<std::io::Result<()>> {
+            Box::new(Box::pin(async move { Ok(()) }))
+        }
+
+        fn get(&self, _: &String, _: &str) -> SessionResult<Option<String>> {
+            Box::new(Box::pin(async move { None }))
+        }
+
+        fn remove(&self, _: &String, _: &str) -> SessionResult<std::io::Result<()>> {
+            Box::new(Box::pin(async move { Ok(()) }))
+        }
+
+        fn destroy(&self, _: &String) -> SessionResult<std::io::Result<()>> {
+            Box::new(Box::pin(async move { Ok(()) }))
+        }
+    }
+
+    pub async fn request(raw_path: &str, headers: Headers, body: &[u8]) -> Request {
+        let stream: Stream = Box::new(TestStreamWrapper::new(body.to_vec(), 1024));
+        let context: Context = Box::pin(());
+        let session_manager: SessionManager = Box::new(NoSession);
+
+        let mut path_params = PathParams::new();
+        path_params.insert("id", "42");
+
+        let (_, raw_query) = raw_path.split_once('?').unwrap_or_default();
+        let query_params = crate::core::parser::params::parse_url_encoded(raw_query);
+
+        Request::from(
+            Arc::new(stream),
+            Arc::new(context),
+            "http".to_string(),
+            "POST".to_string(),
+            raw_path.to_string(),
+            1,
+            headers,
+            path_params,
+            query_params,
+            Arc::new(session_manager),
+            Arc::new(AtomicBool::new(false)),
+            Arc::new(FormConstraints::new(1024, 1024, 1024, 1024, HashMap::new())),
+            Arc::new(Mutex::new(Headers::new())),
+        )
+        .await
+    }
+
+    #[derive(Deserialize)]
+    struct Filters {
+        page: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct Comment {
+        text: String,
+    }
+
+    async fn add_comment(
+        Path(id): Path<u64>,
+        Query(filters): Query<Filters>,
+        Json(comment): Json<Comment>,
+    ) -> String {
+        format!("{} {} {}", id, filters.page, comment.text)
+    }
+
+    fn status_of(response: Response) -> u32 {
+        let (status_code, _) = response.status();
+        status_code
+    }
+
+    #[tokio::test]
+    async fn test_handler_extractors() {
+        let body = br#"{"text": "Hello"}"#;
+        let mut headers = Headers::new();
+        headers.set("Content-Type", "application/json");
+        headers.set("Content-Length", body.len().to_string());
+
+        let mut response = add_comment
+            .call(request("/posts/42?page=2", headers.clone(), body).await)
+            .await;
+        assert_eq!(b"42 2 Hello".to_vec(), *response.get_body());
+
+        let response = add_comment
+            .call(request("/posts/42?page=abc", headers.clone(), body).await)
+            .await;
+        assert_eq!(400, status_of(response));
+
+        let mut headers = Headers::new();
+        headers.set("Content-Type", "text/plain");
+        let response = add_comment
+            .call(request("/posts/42?page=1", headers, body).await)
+            .await;
+        assert_eq!(415, status_of(response));
+    }
+
+    #[derive(Clone)]
+    struct SiteName(&'static str);
+
+    async fn site_name(State(site_name): State<SiteName>) -> String {
+        site_name.0.to_string()
+    }
+
+    async fn visits(request: Request) -> String {
+        let visits = request.state::<Arc<AtomicUsize>>().unwrap();
+        (visits.fetch_add(1, Ordering::Relaxed) + 1).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_state() {
+        let mut server = Server::bind("127.0.0.1:0");
+        server
+            .with_state(SiteName("Racoon"))
+            .with_state(Arc::new(AtomicUsize::new(0)))
+            .urls(vec![
+                crate::core::path::Path::get("/", |request| site_name.call(request)),
+                crate::core::path::Path::get("/visits", |request| visits.call(request)),
+            ]);
+
+        let client = TestServer::new(server).client();
+        let mut response = client.get("/").await;
+        assert_eq!(b"Racoon".to_vec(), *response.get_body());
+
+        client.get("/visits").await;
+        let mut response = client.get("/visits").await;
+        assert_eq!(b"2".to_vec(), *response.get_body());
+
+        // State is not registered.
+        let response = site_name
+            .call(request("/", Headers::new(), b"").await)
+            .await;
+        assert_eq!(500, status_of(response));
+    }
+}
@@ -0,0 +1,147 @@
+use serde_json::Value;
+
+use crate::core::session::Session;
+
+///
+/// Session key under which the pending flash messages are stored.
+///
+pub const FLASH_SESSION_KEY: &str = "_flash";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Debug,
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Success => "success",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "debug" => Some(Level::Debug),
+            "info" => Some(Level::Info),
+            "success" => Some(Level::Success),
+            "warning" => Some(Level::Warning),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlashMessage {
+    pub level: Level,
+    pub message: String,
+}
+
+fn decode(value: Option<String>) -> Vec<FlashMessage> {
+    let value = match value {
+        Some(value) => value,
+        None => return vec![],
+    };
+
+    let items = match serde_json::from_str::<Vec<(String, String)>>(&value) {
+        Ok(items) => items,
+        Err(_) => return vec![],
+    };
+
+    items
+        .into_iter()
+        .filter_map(|(level, message)| {
+            Some(FlashMessage {
+                level: Level::parse(&level)?,
+                message,
+            })
+        })
+        .collect()
+}
+
+fn encode(messages: &[FlashMessage]) -> String {
+    let items: Vec<Value> = messages
+        .iter()
+        .map(|message| serde_json::json!([message.level.as_str(), message.message]))
+        .collect();
+    Value::Array(items).to_string()
+}
+
+///
+/// Stores the message in the session to be shown in the next request.
+///
+pub async fn add<S: AsRef<str>>(
+    session: &Session,
+    message: S,
+    level: Level,
+) -> std::io::Result<()> {
+    let mut messages = decode(session.get(FLASH_SESSION_KEY).await);
+    messages.push(FlashMessage {
+        level,
+        message: message.as_ref().to_string(),
+    });
+
+    session.set(FLASH_SESSION_KEY, &encode(&messages)).await
+}
+
+///
+/// Returns the pending messages and removes them from the session, so they are shown only once.
+///
+pub async fn take(session: &Session) -> Vec<FlashMessage> {
+    let messages = decode(session.get(FLASH_SESSION_KEY).await);
+    if !messages.is_empty() {
+        let _ = session.remove(FLASH_SESSION_KEY).await;
+    }
+    messages
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::Mutex;
+
+    use crate::core::headers::Headers;
+    use crate::core::session::managers::MemorySessionManager;
+    use crate::core::session::{Session, SessionManager};
+
+    use super::{add, take, FlashMessage, Level};
+
+    #[tokio::test]
+    async fn test_flash_messages() {
+        let session_manager: SessionManager = Box::<MemorySessionManager>::default();
+        let session = Session::from(
+            Arc::new(session_manager),
+            None,
+            Arc::new(Mutex::new(Headers::new())),
+        );
+
+        assert!(add(&session, "Saved!", Level::Success).await.is_ok());
+        assert!(add(&session, "Quota almost full", Level::Warning)
+            .await
+            .is_ok());
+
+        assert_eq!(
+            vec![
+                FlashMessage {
+                    level: Level::Success,
+                    message: "Saved!".to_string()
+                },
+                FlashMessage {
+                    level: Level::Warning,
+                    message: "Quota almost full".to_string()
+                }
+            ],
+            take(&session).await
+        );
+        assert!(take(&session).await.is_empty());
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use serde::de::value::{Error, SeqDeserializer};
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Deserializer, Error as _, IntoDeserializer, MapAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+///
+/// Deserializes the form data or query params to the struct with the field names as the struct
+/// fields. Lighter alternative to the form validator when the errors of each field are not
+/// needed.
+///
+/// Fields of `Vec` type receive all the values of the field and the other fields receive the
+/// first value. `Option` fields are `None` if the field is missing or empty. Booleans accept
+/// `true`/`false`, `on`/`off` and `1`/`0`, so the checkbox values can be deserialized.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::forms::deserialize::DeserializeValues;
+/// use racoon::core::forms::FormData;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Signup {
+///     username: String,
+///     age: u8,
+///     newsletter: bool,
+///     tags: Vec<String>,
+///     referrer: Option<String>,
+/// }
+///
+/// let mut form_data = FormData::new();
+/// form_data.insert("username".to_string(), vec!["john".to_string()]);
+/// form_data.insert("age".to_string(), vec!["25".to_string()]);
+/// form_data.insert("newsletter".to_string(), vec!["on".to_string()]);
+/// form_data.insert("tags".to_string(), vec!["rust".to_string(), "web".to_string()]);
+///
+/// let signup: Signup = form_data.deserialize().unwrap();
+/// assert_eq!("john", signup.username);
+/// assert_eq!(25, signup.age);
+/// assert!(signup.newsletter);
+/// assert_eq!(vec!["rust", "web"], signup.tags);
+/// assert_eq!(None, signup.referrer);
+/// ```
+///
+pub trait DeserializeValues {
+    fn deserialize<T: DeserializeOwned>(&self) -> Result<T, Error>;
+}
+
+impl DeserializeValues for HashMap<String, Vec<String>> {
+    fn deserialize<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        T::deserialize(FieldsDeserializer { fields: self })
+    }
+}
+
+struct FieldsDeserializer<'a> {
+    fields: &'a HashMap<String, Vec<String>>,
+}
+
+impl<'de> Deserializer<'de> for FieldsDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(FieldsAccess {
+            fields: self.fields.iter(),
+            current: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+struct FieldsAccess<'a, I> {
+    fields: I,
+    current: Option<(&'a str, &'a [String])>,
+}
+
+impl<'de, 'a, I> MapAccess<'de> for FieldsAccess<'a, I>
+where
+    I: Iterator<Item = (&'a String, &'a Vec<String>)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.fields.next() {
+            Some((name, values)) => {
+                self.current = Some((name, values));
+                seed.deserialize(name.as_str().into_deserializer())
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (name, values) = match self.current.take() {
+            Some(current) => current,
+            None => return Err(Error::custom("Value is requested before the field name.")),
+        };
+
+        // Error of the value is prefixed with the field name.
+        seed.deserialize(ValuesDeserializer { values })
+            .map_err(|error| Error::custom(format!("{}: {}", name, error)))
+    }
+}
+
+///
+/// All the values of a field. Sequences receive every value and the other types the first one.
+///
+struct ValuesDeserializer<'a> {
+    values: &'a [String],
+}
+
+impl<'a> ValuesDeserializer<'a> {
+    fn first(&self) -> Result<ValueDeserializer<'a>, Error> {
+        match self.values.first() {
+            Some(value) => Ok(ValueDeserializer { value }),
+            None => Err(Error::custom("Value is missing.")),
+        }
+    }
+
+    fn seq(&self) -> SeqDeserializer<impl Iterator<Item = ValueDeserializer<'a>>, Error> {
+        SeqDeserializer::new(self.values.iter().map(|value| ValueDeserializer { value }))
+    }
+}
+
+macro_rules! deserialize_first {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.first()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValuesDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.values.len() == 1 {
+            return self.first()?.deserialize_any(visitor);
+        }
+        visitor.visit_seq(self.seq())
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(self.seq())
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(self.seq())
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(self.seq())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Empty inputs of the submitted form are treated as missing.
+        if self.values.iter().all(|value| value.is_empty()) {
+            return visitor.visit_none();
+        }
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.first()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    deserialize_first! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+
+    forward_to_deserialize_any! {
+        unit unit_struct map struct
+    }
+}
+
+///
+/// Single text value parsed to the type requested by the struct field.
+///
+struct ValueDeserializer<'a> {
+    value: &'a str,
+}
+
+impl ValueDeserializer<'_> {
+    fn invalid(&self, expected: &str) -> Error {
+        Error::custom(format!("\"{}\" is not a valid {}.", self.value, expected))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $expected:literal)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.value.trim().parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(self.invalid($expected)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str(self.value)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.trim().to_lowercase().as_str() {
+            "true" | "on" | "1" => visitor.visit_bool(true),
+            "false" | "off" | "0" => visitor.visit_bool(false),
+            _ => Err(self.invalid("boolean")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.value.is_empty() {
+            return visitor.visit_none();
+        }
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8: "integer"
+        deserialize_i16 => visit_i16: "integer"
+        deserialize_i32 => visit_i32: "integer"
+        deserialize_i64 => visit_i64: "integer"
+        deserialize_i128 => visit_i128: "integer"
+        deserialize_u8 => visit_u8: "positive integer"
+        deserialize_u16 => visit_u16: "positive integer"
+        deserialize_u32 => visit_u32: "positive integer"
+        deserialize_u64 => visit_u64: "positive integer"
+        deserialize_u128 => visit_u128: "positive integer"
+        deserialize_f32 => visit_f32: "number"
+        deserialize_f64 => visit_f64: "number"
+        deserialize_char => visit_char: "character"
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::DeserializeValues;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Sort {
+        Newest,
+        Oldest,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Search {
+        q: String,
+        page: Option<u32>,
+        sort: Sort,
+        tags: Vec<String>,
+        #[serde(default)]
+        ids: Vec<u64>,
+        price: (f32, f32),
+    }
+
+    fn values(pairs: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        pairs
+            .iter()
+            .map(|(name, values)| {
+                let values = values.iter().map(|value| value.to_string()).collect();
+                (name.to_string(), values)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_deserialize_values() {
+        let query_params = values(&[
+            ("q", &["racoon"]),
+            ("page", &[""]),
+            ("sort", &["oldest"]),
+            ("tags", &["rust"]),
+            ("price", &["10", "99.5"]),
+            ("unknown", &["ignored"]),
+        ]);
+
+        let search: Search = query_params.deserialize().unwrap();
+        assert_eq!("racoon", search.q);
+        assert_eq!(None, search.page);
+        assert_eq!(Sort::Oldest, search.sort);
+        assert_eq!(vec!["rust".to_string()], search.tags);
+        assert!(search.ids.is_empty());
+        assert_eq!((10.0, 99.5), search.price);
+
+        let query_params = values(&[
+            ("q", &["racoon"]),
+            ("sort", &["newest"]),
+            ("tags", &[]),
+            ("ids", &["1", "x"]),
+            ("price", &["1", "2"]),
+        ]);
+        let error = query_params.deserialize::<Search>().unwrap_err();
+        assert_eq!(
+            "ids: \"x\" is not a valid positive integer.",
+            error.to_string()
+        );
+
+        let query_params = values(&[("sort", &["newest"])]);
+        let error = query_params.deserialize::<Search>().unwrap_err();
+        assert!(error.to_string().starts_with("missing field"));
+    }
+}
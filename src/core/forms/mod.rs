@@ -1,3 +1,7 @@
+pub mod deserialize;
+pub mod progress;
+pub mod uploads;
+
 use std::{collections::HashMap, path::PathBuf};
 
 use async_tempfile::TempFile;
@@ -7,6 +11,9 @@ pub struct FileField {
     pub name: String,
     temp_file: TempFile,
     pub temp_path: PathBuf,
+    /// Content type of the file sent by the client.
+    pub content_type: Option<String>,
+    upload: Option<uploads::TempUpload>,
 }
 
 impl FileField {
@@ -17,12 +24,38 @@ impl FileField {
             name: name.as_ref().to_string(),
             temp_file,
             temp_path,
+            content_type: None,
+            upload: None,
         }
     }
 
+    ///
+    /// Deletes the temp file when this field is dropped.
+    ///
+    pub(crate) fn with_upload(mut self, upload: Option<uploads::TempUpload>) -> Self {
+        self.upload = upload;
+        self
+    }
+
+    pub fn with_content_type<S: AsRef<str>>(mut self, content_type: S) -> Self {
+        self.content_type = Some(content_type.as_ref().to_string());
+        self
+    }
+
     pub fn temp_file(&self) -> &TempFile {
         &self.temp_file
     }
+
+    ///
+    /// Returns the temp file which is no longer deleted when the request completes. The file is
+    /// deleted when the returned temp file is dropped.
+    ///
+    pub fn into_temp_file(mut self) -> TempFile {
+        if let Some(upload) = self.upload.take() {
+            upload.release();
+        }
+        self.temp_file
+    }
 }
 
 pub type Files = HashMap<String, Vec<FileField>>;
@@ -64,6 +97,7 @@ impl FileFieldShortcut for Files {
 ///  .run().await;
 /// ```
 ///
+#[derive(Clone)]
 pub struct FormConstraints {
     /// Maximum allowed body size.
     max_body_size: usize,
@@ -77,6 +111,18 @@ pub struct FormConstraints {
     custom_max_sizes: HashMap<String, usize>,
 }
 
+impl Default for FormConstraints {
+    fn default() -> Self {
+        Self::new(
+            512 * 1024 * 1024, // 512 MiB
+            2 * 1024,          // 2 KiB
+            512 * 1024 * 1024, // 512 MiB
+            2 * 1024 * 1024,   // 2 MiB
+            HashMap::new(),
+        )
+    }
+}
+
 impl FormConstraints {
     pub fn new(
         max_body_size: usize,
@@ -94,6 +140,15 @@ impl FormConstraints {
         }
     }
 
+    ///
+    /// Returns copy of the constraints with different maximum body size.
+    ///
+    pub fn with_max_body_size(&self, max_body_size: usize) -> Self {
+        let mut form_constraints = self.clone();
+        form_constraints.max_body_size = max_body_size;
+        form_constraints
+    }
+
     pub fn max_body_size(&self, buffer_size: usize) -> usize {
         if buffer_size > self.max_body_size {
             return buffer_size;
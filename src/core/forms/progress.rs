@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::core::request::Request;
+use crate::core::response::sse::{Event, SseResponse};
+use crate::core::stream::{AbstractStream, Stream, StreamResult};
+
+///
+/// Called with the number of request body bytes received so far.
+///
+pub type ProgressHook = Arc<dyn Fn(u64) + Send + Sync>;
+
+///
+/// Reports the bytes read from the wrapped stream to the progress hook. Bytes restored back to
+/// the stream are subtracted, so they are not counted twice when read again.
+///
+pub(crate) struct ProgressStream {
+    inner: Arc<Stream>,
+    received: AtomicU64,
+    hook: ProgressHook,
+}
+
+impl ProgressStream {
+    pub(crate) fn new(inner: Arc<Stream>, hook: ProgressHook) -> Self {
+        Self {
+            inner,
+            received: AtomicU64::new(0),
+            hook,
+        }
+    }
+}
+
+impl AbstractStream for ProgressStream {
+    fn buffer_size(&self) -> StreamResult<'_, usize> {
+        self.inner.buffer_size()
+    }
+
+    fn peer_addr(&self) -> StreamResult<'_, Option<String>> {
+        self.inner.peer_addr()
+    }
+
+    fn restore_payload(&self, bytes: &[u8]) -> StreamResult<'_, std::io::Result<()>> {
+        let bytes = bytes.to_vec();
+
+        Box::new(Box::pin(async move {
+            self.inner.restore_payload(&bytes).await?;
+
+            let length = bytes.len() as u64;
+            let _ = self
+                .received
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |received| {
+                    Some(received.saturating_sub(length))
+                });
+            Ok(())
+        }))
+    }
+
+    fn restored_len(&self) -> StreamResult<'_, usize> {
+        self.inner.restored_len()
+    }
+
+    fn read_chunk(&self) -> StreamResult<'_, std::io::Result<Vec<u8>>> {
+        Box::new(Box::pin(async move {
+            let chunk = self.inner.read_chunk().await?;
+            let received = self
+                .received
+                .fetch_add(chunk.len() as u64, Ordering::Relaxed)
+                + chunk.len() as u64;
+            (self.hook)(received);
+            Ok(chunk)
+        }))
+    }
+
+    fn write_chunk<'a>(&'a self, bytes: &'a [u8]) -> StreamResult<'a, std::io::Result<()>> {
+        self.inner.write_chunk(bytes)
+    }
+
+    fn shutdown(&self) -> StreamResult<'_, std::io::Result<()>> {
+        self.inner.shutdown()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadState {
+    Waiting,
+    Uploading,
+    Completed,
+    Failed,
+}
+
+impl UploadState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadState::Waiting => "waiting",
+            UploadState::Uploading => "uploading",
+            UploadState::Completed => "completed",
+            UploadState::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadProgress {
+    pub received: u64,
+    /// Size of the request body from the `Content-Length` header.
+    pub total: Option<u64>,
+    pub state: UploadState,
+}
+
+impl UploadProgress {
+    ///
+    /// Percent of the request body received. Returns `None` if the total size is unknown.
+    ///
+    pub fn percent(&self) -> Option<u8> {
+        if self.state == UploadState::Completed {
+            return Some(100);
+        }
+
+        match self.total {
+            Some(0) => Some(100),
+            Some(total) => Some((self.received.min(total) * 100 / total) as u8),
+            None => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, UploadState::Completed | UploadState::Failed)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "received": self.received,
+            "total": self.total,
+            "percent": self.percent(),
+            "state": self.state.as_str(),
+        })
+        .to_string()
+    }
+}
+
+struct Upload {
+    sender: watch::Sender<UploadProgress>,
+    active: bool,
+}
+
+type Uploads = HashMap<String, Upload>;
+
+///
+/// Publishes the progress of the request bodies being parsed, keyed by the upload ID chosen by
+/// the client. A companion endpoint subscribes to the same upload ID and streams the progress
+/// to the browser while the upload request is still running.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::OnceLock;
+///
+/// use racoon::core::forms::progress::ProgressChannel;
+/// use racoon::core::path::Path;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::shortcuts::SingleText;
+/// use racoon::view;
+///
+/// fn uploads() -> &'static ProgressChannel {
+///     static UPLOADS: OnceLock<ProgressChannel> = OnceLock::new();
+///     UPLOADS.get_or_init(ProgressChannel::new)
+/// }
+///
+/// // Upload ID is generated by the browser, e.g. `POST /upload?upload_id=abc`.
+/// async fn upload(request: Request) -> Response {
+///     let upload_id = match request.query_params.value("upload_id") {
+///         Some(upload_id) => upload_id.to_owned(),
+///         None => return HttpResponse::bad_request().body("Upload ID is missing."),
+///     };
+///
+///     let (form_data, files) = request.parse_with_progress(uploads(), &upload_id).await;
+///     HttpResponse::ok().body("Uploaded")
+/// }
+///
+/// // Opened with `new EventSource("/upload/progress?upload_id=abc")` before starting the upload.
+/// async fn progress(request: Request) -> Response {
+///     let upload_id = match request.query_params.value("upload_id") {
+///         Some(upload_id) => upload_id.to_owned(),
+///         None => return HttpResponse::bad_request().body("Upload ID is missing."),
+///     };
+///
+///     uploads().sse(&request, &upload_id).await
+/// }
+///
+/// let paths = vec![
+///     Path::post("/upload", view!(upload)),
+///     Path::get("/upload/progress", view!(progress)),
+/// ];
+/// ```
+///
+#[derive(Clone, Default)]
+pub struct ProgressChannel {
+    uploads: Arc<Mutex<Uploads>>,
+}
+
+impl ProgressChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn uploads(&self) -> std::sync::MutexGuard<'_, Uploads> {
+        let mut uploads = match self.uploads.lock() {
+            Ok(uploads) => uploads,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        // Removes the uploads nobody is waiting for anymore.
+        uploads.retain(|_, upload| upload.active || upload.sender.receiver_count() > 0);
+        uploads
+    }
+
+    fn sender<S: AsRef<str>>(uploads: &mut Uploads, upload_id: S) -> &mut Upload {
+        uploads
+            .entry(upload_id.as_ref().to_string())
+            .or_insert_with(|| Upload {
+                sender: watch::channel(UploadProgress {
+                    received: 0,
+                    total: None,
+                    state: UploadState::Waiting,
+                })
+                .0,
+                active: false,
+            })
+    }
+
+    ///
+    /// Returns the receiver of the upload progress. The upload may be subscribed before it
+    /// starts. The channel is closed after the upload is completed or failed.
+    ///
+    pub fn subscribe<S: AsRef<str>>(&self, upload_id: S) -> watch::Receiver<UploadProgress> {
+        let mut uploads = self.uploads();
+        Self::sender(&mut uploads, upload_id).sender.subscribe()
+    }
+
+    ///
+    /// Returns the current progress of the running upload.
+    ///
+    pub fn progress<S: AsRef<str>>(&self, upload_id: S) -> Option<UploadProgress> {
+        let uploads = self.uploads();
+        uploads
+            .get(upload_id.as_ref())
+            .filter(|upload| upload.active)
+            .map(|upload| upload.sender.borrow().clone())
+    }
+
+    ///
+    /// Starts the upload and returns the hook updating its progress. Used by
+    /// `Request::parse_with_progress`.
+    ///
+    pub fn start<S: AsRef<str>>(&self, upload_id: S, total: Option<u64>) -> ProgressHook {
+        let mut uploads = self.uploads();
+        let upload = Self::sender(&mut uploads, upload_id);
+        upload.active = true;
+        upload.sender.send_replace(UploadProgress {
+            received: 0,
+            total,
+            state: UploadState::Uploading,
+        });
+
+        let sender = upload.sender.clone();
+        Arc::new(move |received| {
+            sender.send_modify(|progress| progress.received = received);
+        })
+    }
+
+    ///
+    /// Sends the final state of the upload to the subscribers and removes it.
+    ///
+    pub fn finish<S: AsRef<str>>(&self, upload_id: S, completed: bool) {
+        let mut uploads = self.uploads();
+
+        if let Some(upload) = uploads.remove(upload_id.as_ref()) {
+            upload.sender.send_modify(|progress| {
+                progress.state = if completed {
+                    UploadState::Completed
+                } else {
+                    UploadState::Failed
+                };
+            });
+        }
+    }
+
+    ///
+    /// Streams the upload progress as server-sent events until the upload is finished. Event type
+    /// is the upload state and the data is the progress in JSON format.
+    ///
+    /// ```json
+    /// {"received": 5242880, "total": 10485760, "percent": 50, "state": "uploading"}
+    /// ```
+    ///
+    pub async fn sse<S: AsRef<str>>(&self, request: &Request, upload_id: S) -> Box<SseResponse> {
+        let mut receiver = self.subscribe(upload_id);
+        let mut sse = SseResponse::from(request);
+
+        loop {
+            let progress = receiver.borrow_and_update().clone();
+            let event = Event::data(progress.to_json()).event(progress.state.as_str());
+
+            if sse.send(event).await.is_err() || progress.is_finished() {
+                break;
+            }
+
+            // Comment is sent periodically to detect the disconnected client.
+            let changed = loop {
+                let changed = tokio::select! {
+                    changed = tokio::time::timeout(Duration::from_secs(15), receiver.changed()) => {
+                        changed
+                    }
+                    _ = sse.wait_shutdown() => return sse.finish().await,
+                };
+
+                match changed {
+                    Ok(changed) => break changed,
+                    Err(_) => {
+                        if sse.comment("waiting").await.is_err() {
+                            return sse.finish().await;
+                        }
+                    }
+                }
+            };
+
+            if changed.is_err() {
+                break;
+            }
+        }
+
+        sse.finish().await
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{ProgressChannel, UploadState};
+
+    #[test]
+    fn test_progress_channel() {
+        let channel = ProgressChannel::new();
+        let mut receiver = channel.subscribe("abc");
+        assert_eq!(UploadState::Waiting, receiver.borrow().state);
+
+        let hook = channel.start("abc", Some(200));
+        hook(50);
+
+        let progress = receiver.borrow_and_update().clone();
+        assert_eq!(UploadState::Uploading, progress.state);
+        assert_eq!(Some(25), progress.percent());
+        assert_eq!(Some(progress), channel.progress("abc"));
+
+        channel.finish("abc", true);
+        let progress = receiver.borrow_and_update().clone();
+        assert_eq!(UploadState::Completed, progress.state);
+        assert_eq!(Some(100), progress.percent());
+        assert!(channel.progress("abc").is_none());
+
+        // Uploads subscribed but never started are removed after the receiver is dropped.
+        let receiver = channel.subscribe("xyz");
+        drop(receiver);
+        assert!(channel.uploads().is_empty());
+    }
+}
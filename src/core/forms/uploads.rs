@@ -0,0 +1,283 @@
+//!
+//! Tracking and cleanup of the temp files created for the uploaded files.
+//!
+//! Each file received by the multipart parser is deleted when the request completes, even if the
+//! handler keeps it open or the parsing fails midway. Files left by the crashed processes are
+//! deleted by the reaper job.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use racoon::core::forms::uploads;
+//! use racoon::core::server::Server;
+//!
+//! let mut server = Server::bind("127.0.0.1:8080");
+//! // Deletes the uploads older than a day left by the previous crashed runs.
+//! server.schedule(uploads::reaper(
+//!     Duration::from_secs(3600),
+//!     Duration::from_secs(24 * 3600),
+//! ));
+//! ```
+//!
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use async_tempfile::TempFile;
+use uuid::Uuid;
+
+use crate::core::metrics::MetricsRegistry;
+use crate::core::scheduler::Job;
+use crate::racoon_debug;
+
+///
+/// Prefix of the temp file names, followed by the process ID and the random ID.
+///
+pub const TEMP_FILE_PREFIX: &str = "racoon-upload-";
+
+///
+/// Paths of the temp files owned by the uploads of this process.
+///
+fn active_uploads() -> MutexGuard<'static, HashSet<PathBuf>> {
+    static ACTIVE: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+
+    let active = ACTIVE.get_or_init(|| Mutex::new(HashSet::new()));
+    match active.lock() {
+        Ok(active) => active,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+///
+/// Deletes the temp file of the upload when dropped. Files moved to another path by the handler
+/// are kept at the new path.
+///
+#[derive(Debug)]
+pub struct TempUpload {
+    path: PathBuf,
+    released: bool,
+}
+
+impl TempUpload {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    ///
+    /// Stops tracking the file without deleting it. The owner of the file is responsible for
+    /// deleting it.
+    ///
+    pub(crate) fn release(mut self) {
+        self.released = true;
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        if !self.released {
+            if let Err(error) = std::fs::remove_file(&self.path) {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    racoon_debug!(
+                        "Failed to delete temp upload {:?}. Error: {}",
+                        self.path,
+                        error
+                    );
+                }
+            }
+        }
+
+        if active_uploads().remove(&self.path) {
+            MetricsRegistry::global().temp_upload_closed();
+        }
+    }
+}
+
+///
+/// Creates the temp file for the upload. The file is tracked before it is created, so the reaper
+/// never deletes the file being written.
+///
+pub(crate) async fn create_temp_file() -> std::io::Result<(TempFile, TempUpload)> {
+    let name = format!(
+        "{}{}-{}",
+        TEMP_FILE_PREFIX,
+        std::process::id(),
+        Uuid::new_v4().simple()
+    );
+
+    let upload = TempUpload {
+        path: std::env::temp_dir().join(&name),
+        released: false,
+    };
+    active_uploads().insert(upload.path.clone());
+    MetricsRegistry::global().temp_upload_opened();
+
+    let temp_file = TempFile::new_with_name(name)
+        .await
+        .map_err(std::io::Error::other)?;
+    Ok((temp_file, upload))
+}
+
+///
+/// Returns the process ID of the temp upload file name.
+///
+fn owner_pid(file_name: &str) -> Option<u32> {
+    let rest = file_name.strip_prefix(TEMP_FILE_PREFIX)?;
+    let (pid, _) = rest.split_once('-')?;
+    pid.parse().ok()
+}
+
+///
+/// Returns true if the other process which created the file is still running. Files of this
+/// process which are not tracked are left by the previous process with the same ID.
+///
+fn is_owner_running(pid: u32) -> bool {
+    pid != std::process::id() && Path::new("/proc").join(pid.to_string()).exists()
+}
+
+///
+/// Number and total size of the temp upload files in the temp directory.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TempDirUsage {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+///
+/// Scans the temp directory for the upload files of all the processes.
+///
+pub async fn temp_dir_usage() -> std::io::Result<TempDirUsage> {
+    let mut usage = TempDirUsage::default();
+    let mut entries = tokio::fs::read_dir(std::env::temp_dir()).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(TEMP_FILE_PREFIX)
+        {
+            continue;
+        }
+
+        if let Ok(metadata) = entry.metadata().await {
+            usage.files += 1;
+            usage.bytes += metadata.len();
+        }
+    }
+    Ok(usage)
+}
+
+///
+/// Deletes the temp upload files which are not used by any running request. Files of the running
+/// processes are deleted only if they are not modified for `max_age`, in case the process is
+/// stuck. Returns the number of deleted files.
+///
+pub async fn reap_orphans(max_age: Duration) -> std::io::Result<usize> {
+    let mut reaped = 0;
+    let mut entries = tokio::fs::read_dir(std::env::temp_dir()).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let pid = match owner_pid(&file_name) {
+            Some(pid) => pid,
+            None => continue,
+        };
+
+        let path = entry.path();
+        if active_uploads().contains(&path) {
+            continue;
+        }
+
+        if is_owner_running(pid) {
+            let modified = match entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+            {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+
+            let age = SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default();
+            if age < max_age {
+                continue;
+            }
+        }
+
+        if tokio::fs::remove_file(&path).await.is_ok() {
+            racoon_debug!("Deleted orphan temp upload {:?}.", path);
+            reaped += 1;
+        }
+    }
+
+    MetricsRegistry::global().temp_uploads_reaped(reaped as u64);
+    Ok(reaped)
+}
+
+///
+/// Job deleting the orphan temp uploads with the interval and updating the temp directory usage
+/// metrics. Runs as soon as the server is ready to clean up after the previous crashed process.
+///
+pub fn reaper(interval: Duration, max_age: Duration) -> Job {
+    Job::every("temp-upload-reaper", interval, move || async move {
+        reap_orphans(max_age).await?;
+
+        let usage = temp_dir_usage().await?;
+        MetricsRegistry::global().set_temp_dir_usage(usage.files, usage.bytes);
+        Ok(())
+    })
+    .run_on_start()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::core::forms::FormConstraints;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::parser::multipart::MultipartParser;
+
+    use super::{active_uploads, owner_pid, reap_orphans, TEMP_FILE_PREFIX};
+
+    #[tokio::test]
+    async fn test_temp_upload_cleanup() {
+        let body = b"--boundary\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\nHello\r\n--boundary--\r\n";
+        let mut headers = Headers::new();
+        headers.set("Content-Type", "multipart/form-data; boundary=boundary");
+        headers.set("Content-Length", body.len().to_string());
+
+        let (_, mut files) =
+            MultipartParser::parse_bytes(body, 7, Arc::new(FormConstraints::default()), &headers)
+                .await
+                .unwrap();
+        let file = files.remove("file").unwrap().remove(0);
+        let temp_path = file.temp_path.clone();
+        assert!(temp_path.exists());
+        assert!(active_uploads().contains(&temp_path));
+
+        // Deleted when the request completes even if another handle is still open.
+        let temp_file = file.temp_file().open_ro().await.unwrap();
+        drop(file);
+        assert!(!temp_path.exists());
+        assert!(!active_uploads().contains(&temp_path));
+        drop(temp_file);
+
+        // Files of the crashed processes are reaped. Process ID 0 is never a user process.
+        let orphan = std::env::temp_dir().join(format!("{}0-orphan", TEMP_FILE_PREFIX));
+        tokio::fs::write(&orphan, b"Orphan").await.unwrap();
+        assert_eq!(
+            Some(0),
+            owner_pid(&orphan.file_name().unwrap().to_string_lossy())
+        );
+
+        assert!(reap_orphans(Duration::from_secs(3600)).await.unwrap() >= 1);
+        assert!(!orphan.exists());
+    }
+}
@@ -0,0 +1,415 @@
+//!
+//! GraphQL endpoint for the `async-graphql` schemas, available with the `graphql` feature.
+//!
+//! Queries and mutations are sent as JSON over `POST`. Queries can also be sent over `GET` with
+//! the `query`, `variables` and `extensions` params, which is used by the persisted queries when
+//! the `ApolloPersistedQueries` extension is added to the schema. Subscriptions are served over
+//! WebSocket with the `graphql-transport-ws` and the legacy `graphql-ws` protocols.
+//!
+//! More information: <https://graphql.org/learn/serving-over-http/>
+//!
+
+use std::sync::Arc;
+
+use async_graphql::http::{parse_query_string, WebSocket as GraphQLWebSocket, WsMessage};
+use async_graphql::http::{WebSocketProtocols, ALL_WEBSOCKET_PROTOCOLS};
+use async_graphql::parser::types::{DocumentOperations, OperationType};
+use async_graphql::{BatchRequest, BatchResponse, Data, Executor};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+use crate::core::extract::read_body;
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HtmlResponse, HttpResponse, Response};
+use crate::core::websocket::{has_token, Message, WebSocket, WebSocketConfig};
+use crate::racoon_debug;
+
+///
+/// Callback adding the request data like the authenticated user to the GraphQL context. Called
+/// for each operation of the HTTP request and once for the WebSocket connection.
+///
+pub type ContextHook = Arc<dyn Fn(&Request, &mut Data) + Send + Sync>;
+
+///
+/// Middleware serving the schema at the path. Other requests are passed to the next middleware.
+///
+/// # Examples
+///
+/// ```
+/// use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema};
+///
+/// use racoon::core::auth::Principal;
+/// use racoon::core::graphql::GraphQL;
+/// use racoon::core::server::Server;
+///
+/// struct Query;
+///
+/// #[Object]
+/// impl Query {
+///     async fn hello(&self, context: &Context<'_>) -> String {
+///         match context.data_opt::<Principal>() {
+///             Some(principal) => format!("Hello {}", principal.id),
+///             None => "Hello".to_string(),
+///         }
+///     }
+/// }
+///
+/// let schema = Schema::new(Query, EmptyMutation, EmptySubscription);
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(
+///     GraphQL::new("/graphql", schema)
+///         .graphiql("/graphiql")
+///         .subscriptions()
+///         .context(|request, data| {
+///             if let Some(principal) = request.extensions.get::<Principal>() {
+///                 data.insert(principal.clone());
+///             }
+///         }),
+/// );
+/// ```
+///
+#[derive(Clone)]
+pub struct GraphQL<E: Executor> {
+    path: String,
+    executor: E,
+    graphiql: Option<String>,
+    subscriptions: bool,
+    context: Option<ContextHook>,
+}
+
+impl<E: Executor> GraphQL<E> {
+    pub fn new<S: AsRef<str>>(path: S, executor: E) -> Self {
+        Self {
+            path: path.as_ref().to_string(),
+            executor,
+            graphiql: None,
+            subscriptions: false,
+            context: None,
+        }
+    }
+
+    ///
+    /// Serves the GraphiQL IDE for the endpoint at the path.
+    ///
+    pub fn graphiql<S: AsRef<str>>(mut self, path: S) -> Self {
+        self.graphiql = Some(path.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Accepts the WebSocket upgrade requests at the path for the subscriptions.
+    ///
+    pub fn subscriptions(mut self) -> Self {
+        self.subscriptions = true;
+        self
+    }
+
+    pub fn context<F>(mut self, context: F) -> Self
+    where
+        F: Fn(&Request, &mut Data) + Send + Sync + 'static,
+    {
+        self.context = Some(Arc::new(context));
+        self
+    }
+
+    fn data(&self, request: &Request) -> Data {
+        let mut data = Data::default();
+        if let Some(context) = &self.context {
+            context(request, &mut data);
+        }
+        data
+    }
+
+    async fn serve(&self, request: Request) -> Response {
+        let is_upgrade = request
+            .headers
+            .value("Upgrade")
+            .is_some_and(|value| has_token(&value, "websocket"));
+
+        let mut batch_request = match request.method.as_str() {
+            "GET" if is_upgrade && self.subscriptions => {
+                return self.serve_websocket(request).await;
+            }
+            "GET" => {
+                let query = request.path.split_once('?').unwrap_or_default().1;
+                match parse_query_string(query) {
+                    Ok(mut graphql_request) => {
+                        // Mutations over GET could be triggered by the links and the prefetches.
+                        if is_mutation(&mut graphql_request) {
+                            let mut response = HttpResponse::method_not_allowed();
+                            response.get_headers().set("Allow", "POST");
+                            return response.body("Mutations are only allowed over POST.");
+                        }
+                        BatchRequest::Single(graphql_request)
+                    }
+                    Err(error) => return bad_request(error.to_string()),
+                }
+            }
+            "POST" => {
+                let body = match read_body(&request).await {
+                    Ok(body) => body,
+                    Err(response) => return response,
+                };
+
+                match serde_json::from_slice::<BatchRequest>(&body) {
+                    Ok(batch_request) => batch_request,
+                    Err(error) => {
+                        return bad_request(format!("Invalid GraphQL request. {}", error))
+                    }
+                }
+            }
+            _ => {
+                let mut response = HttpResponse::method_not_allowed();
+                response.get_headers().set("Allow", "GET, POST");
+                return response.body("Method Not Allowed");
+            }
+        };
+
+        if let Some(context) = &self.context {
+            for graphql_request in batch_request.iter_mut() {
+                context(&request, &mut graphql_request.data);
+            }
+        }
+
+        let batch_response = self.executor.execute_batch(batch_request).await;
+        json_response(&batch_response)
+    }
+
+    async fn serve_websocket(&self, request: Request) -> Response {
+        let config = request
+            .extensions
+            .get::<WebSocketConfig>()
+            .cloned()
+            .unwrap_or_default()
+            .protocols(&ALL_WEBSOCKET_PROTOCOLS);
+
+        let (websocket, connected) = WebSocket::from_config(&request, config).await;
+        if !connected {
+            return websocket.bad_request().await;
+        }
+
+        // Clients without the protocol are assumed to use the legacy protocol.
+        let protocol = websocket
+            .protocol()
+            .and_then(|protocol| protocol.parse().ok())
+            .unwrap_or(WebSocketProtocols::SubscriptionsTransportWS);
+
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>(16);
+        let reader = tokio::spawn({
+            let websocket = websocket.clone();
+            async move {
+                while let Some(message) = websocket.recv().await {
+                    let payload = match message {
+                        Message::Text(text) => text.into_bytes(),
+                        Message::Binary(bytes) => bytes,
+                        Message::Close(_, _) => break,
+                        Message::Ping(_) | Message::Pong(_) => continue,
+                    };
+
+                    if sender.send(payload).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut messages = Box::pin(
+            GraphQLWebSocket::new(
+                self.executor.clone(),
+                ReceiverStream::new(receiver),
+                protocol,
+            )
+            .connection_data(self.data(&request)),
+        );
+
+        while let Some(message) = messages.next().await {
+            match message {
+                WsMessage::Text(text) => {
+                    if let Err(error) = websocket.send_text(text).await {
+                        racoon_debug!("Failed to send GraphQL message. Error: {}", error);
+                        break;
+                    }
+                }
+                WsMessage::Close(code, reason) => {
+                    let _ = websocket.close(code, reason).await;
+                    break;
+                }
+            }
+        }
+
+        reader.abort();
+        websocket.exit()
+    }
+
+    fn graphiql_page(&self) -> String {
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>GraphiQL</title>
+<style>body {{ margin: 0; }} #graphiql {{ height: 100vh; }}</style>
+<link rel="stylesheet" href="https://unpkg.com/graphiql@3/graphiql.min.css">
+</head>
+<body>
+<div id="graphiql"></div>
+<script src="https://unpkg.com/react@18/umd/react.production.min.js"></script>
+<script src="https://unpkg.com/react-dom@18/umd/react-dom.production.min.js"></script>
+<script src="https://unpkg.com/graphiql@3/graphiql.min.js"></script>
+<script>
+const url = {url};
+const scheme = location.protocol === "https:" ? "wss://" : "ws://";
+const fetcher = GraphiQL.createFetcher({{
+  url: url,
+  subscriptionUrl: {subscriptions} ? scheme + location.host + url : undefined,
+}});
+ReactDOM.createRoot(document.getElementById("graphiql")).render(
+  React.createElement(GraphiQL, {{ fetcher: fetcher }})
+);
+</script>
+</body>
+</html>"#,
+            url = Value::from(self.path.as_str()),
+            subscriptions = self.subscriptions,
+        )
+    }
+}
+
+impl<E: Executor> AbstractMiddleware for GraphQL<E> {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let graphql = self.clone();
+
+        Box::new(Box::pin(async move {
+            let path = request.path.split('?').next().unwrap_or_default();
+
+            if path == graphql.path {
+                return graphql.serve(request).await;
+            }
+
+            if request.method == "GET" && graphql.graphiql.as_deref() == Some(path) {
+                let response: Response = HtmlResponse::ok().body(graphql.graphiql_page());
+                return response;
+            }
+
+            next.run(request).await
+        }))
+    }
+}
+
+///
+/// Returns true if the operation selected by the request is a mutation.
+///
+fn is_mutation(request: &mut async_graphql::Request) -> bool {
+    let operation_name = request.operation_name.clone();
+    let document = match request.parsed_query() {
+        Ok(document) => document,
+        // Invalid queries and the persisted query hashes are handled by the executor.
+        Err(_) => return false,
+    };
+
+    let operation = match &document.operations {
+        DocumentOperations::Single(operation) => Some(operation),
+        DocumentOperations::Multiple(operations) => {
+            operation_name.and_then(|operation_name| operations.get(operation_name.as_str()))
+        }
+    };
+    operation.is_some_and(|operation| operation.node.ty == OperationType::Mutation)
+}
+
+fn json_response(batch_response: &BatchResponse) -> Response {
+    let mut response = HttpResponse::ok();
+    if let Some(cache_control) = batch_response.cache_control().value() {
+        response.get_headers().set("Cache-Control", cache_control);
+    }
+
+    let body = serde_json::to_string(batch_response).unwrap_or_default();
+    response.content_type("application/json").body(body)
+}
+
+fn bad_request<S: AsRef<str>>(message: S) -> Response {
+    HttpResponse::bad_request().body(message)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use async_graphql::{EmptySubscription, Object, Schema};
+
+    use crate::core::server::Server;
+    use crate::core::testing::request::TestRequest;
+    use crate::core::testing::TestServer;
+
+    use super::GraphQL;
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn add(&self, a: i32, b: i32) -> i32 {
+            a + b
+        }
+    }
+
+    struct Mutation;
+
+    #[Object]
+    impl Mutation {
+        async fn ping(&self) -> String {
+            "pong".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_graphql() {
+        let schema = Schema::new(Query, Mutation, EmptySubscription);
+        let mut server = Server::bind("127.0.0.1:0");
+        server.middleware(GraphQL::new("/graphql", schema).graphiql("/graphiql"));
+
+        let client = TestServer::new(server).client();
+        let mut response = client
+            .send(TestRequest::post("/graphql").json(serde_json::json!({
+                "query": "query Add($a: Int!) { add(a: $a, b: 2) }",
+                "variables": {"a": 1},
+            })))
+            .await;
+        assert_eq!(
+            r#"{"data":{"add":3}}"#.as_bytes().to_vec(),
+            *response.get_body()
+        );
+
+        // Batched operations are answered in the same order.
+        let mut response = client
+            .send(TestRequest::post("/graphql").json(serde_json::json!([
+                {"query": "{ add(a: 1, b: 1) }"},
+                {"query": "mutation { ping }"},
+            ])))
+            .await;
+        assert_eq!(
+            r#"[{"data":{"add":2}},{"data":{"ping":"pong"}}]"#.as_bytes().to_vec(),
+            *response.get_body()
+        );
+
+        let mut response = client
+            .send(TestRequest::get("/graphql").query("query", "{ add(a: 2, b: 2) }"))
+            .await;
+        assert_eq!(
+            r#"{"data":{"add":4}}"#.as_bytes().to_vec(),
+            *response.get_body()
+        );
+
+        let response = client
+            .send(TestRequest::get("/graphql").query("query", "mutation { ping }"))
+            .await;
+        assert_eq!(405, response.status().0);
+
+        let response = client.get("/graphiql").await;
+        assert_eq!(200, response.status().0);
+        let response = client.post("/graphql", "application/json", b"{").await;
+        assert_eq!(400, response.status().0);
+    }
+}
@@ -21,7 +21,7 @@ impl HeaderValue for Headers {
         let name = name.as_ref();
 
         for (key, values) in self.iter() {
-            if key.to_lowercase() != name.to_lowercase() {
+            if !key.eq_ignore_ascii_case(name) {
                 continue;
             }
 
@@ -40,7 +40,7 @@ impl HeaderValue for Headers {
         let mut multiple_headers = vec![];
 
         for (key, values) in self.iter() {
-            if key.to_lowercase() != name.to_lowercase() {
+            if !key.eq_ignore_ascii_case(name) {
                 continue;
             }
 
@@ -56,7 +56,7 @@ impl HeaderValue for Headers {
     fn set<B: AsRef<[u8]>>(&mut self, name: &str, value: B) {
         let value = value.as_ref();
 
-        if let Some(values) = self.get_mut(&name.to_string()) {
+        if let Some(values) = self.get_mut(name) {
             if values.len() > 0 {
                 values.clear();
             }
@@ -70,7 +70,7 @@ impl HeaderValue for Headers {
     fn set_multiple<B: AsRef<[u8]>>(&mut self, name: &str, value: B) {
         let value = value.as_ref();
 
-        if let Some(values) = self.get_mut(&name.to_string()) {
+        if let Some(values) = self.get_mut(name) {
             values.push(value.to_vec());
         } else {
             self.insert(name.to_string(), vec![value.to_vec()]);
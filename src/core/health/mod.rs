@@ -0,0 +1,250 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{JsonResponse, Response};
+use crate::racoon_warn;
+
+pub type HealthCheckResult = Pin<Box<dyn Future<Output = std::io::Result<()>> + Send>>;
+
+///
+/// Async check run when the health endpoint is requested.
+///
+pub type HealthCheck = Arc<dyn Fn() -> HealthCheckResult + Send + Sync>;
+
+///
+/// Liveness and readiness endpoints for Kubernetes probes. Liveness endpoint responds
+/// `200 OK` while the liveness checks pass. Readiness endpoint additionally responds
+/// `503 Service Unavailable` until the server starts accepting connections and while it is
+/// draining connections on shutdown.
+///
+/// Endpoints are answered before the other middlewares, so the probes are not affected by
+/// authentication or rate limiting.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::health::Health;
+/// use racoon::core::server::Server;
+///
+/// let health = Health::new()
+///     .readiness_check("database", || async {
+///         // Ping the database here.
+///         Ok(())
+///     })
+///     .check_timeout(Duration::from_secs(2));
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.health(health);
+/// ```
+///
+#[derive(Clone)]
+pub struct Health {
+    liveness_path: String,
+    readiness_path: String,
+    liveness_checks: Vec<(String, HealthCheck)>,
+    readiness_checks: Vec<(String, HealthCheck)>,
+    check_timeout: Duration,
+    server_ready: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self {
+            liveness_path: "/healthz".to_string(),
+            readiness_path: "/readyz".to_string(),
+            liveness_checks: vec![],
+            readiness_checks: vec![],
+            check_timeout: Duration::from_secs(5),
+            server_ready: None,
+        }
+    }
+
+    ///
+    /// Path of the liveness endpoint. Defaults to `/healthz`.
+    ///
+    pub fn liveness_path<S: AsRef<str>>(mut self, path: S) -> Self {
+        self.liveness_path = path.as_ref().to_string();
+        self
+    }
+
+    ///
+    /// Path of the readiness endpoint. Defaults to `/readyz`.
+    ///
+    pub fn readiness_path<S: AsRef<str>>(mut self, path: S) -> Self {
+        self.readiness_path = path.as_ref().to_string();
+        self
+    }
+
+    ///
+    /// Adds check for the liveness endpoint. Failing liveness checks usually get the process
+    /// restarted, so only the unrecoverable states need to be checked.
+    ///
+    pub fn liveness_check<S, F, Fut>(mut self, name: S, check: F) -> Self
+    where
+        S: AsRef<str>,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<()>> + Send + 'static,
+    {
+        self.liveness_checks.push((
+            name.as_ref().to_string(),
+            Arc::new(move || Box::pin(check())),
+        ));
+        self
+    }
+
+    ///
+    /// Adds check for the readiness endpoint such as database ping or queue depth.
+    ///
+    pub fn readiness_check<S, F, Fut>(mut self, name: S, check: F) -> Self
+    where
+        S: AsRef<str>,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<()>> + Send + 'static,
+    {
+        self.readiness_checks.push((
+            name.as_ref().to_string(),
+            Arc::new(move || Box::pin(check())),
+        ));
+        self
+    }
+
+    ///
+    /// Check not completed within the duration is reported as failed. Defaults to 5 seconds.
+    ///
+    pub fn check_timeout(mut self, timeout: Duration) -> Self {
+        self.check_timeout = timeout;
+        self
+    }
+
+    ///
+    /// Sets the function returning whether the server is accepting connections and not
+    /// draining.
+    ///
+    pub(crate) fn server_ready<F: Fn() -> bool + Send + Sync + 'static>(
+        mut self,
+        server_ready: F,
+    ) -> Self {
+        self.server_ready = Some(Arc::new(server_ready));
+        self
+    }
+
+    async fn respond(&self, checks: &[(String, HealthCheck)], check_server: bool) -> Response {
+        if check_server {
+            if let Some(server_ready) = &self.server_ready {
+                if !server_ready() {
+                    return JsonResponse::service_unavailable()
+                        .body(json!({"status": "unavailable", "checks": {}}));
+                }
+            }
+        }
+
+        let mut healthy = true;
+        let mut results = Map::new();
+
+        for (name, check) in checks {
+            let result = match tokio::time::timeout(self.check_timeout, check()).await {
+                Ok(Ok(())) => Value::from("ok"),
+                Ok(Err(error)) => Value::from(error.to_string()),
+                Err(_) => Value::from("timed out"),
+            };
+
+            healthy = healthy && result == "ok";
+            results.insert(name.clone(), result);
+        }
+
+        if healthy {
+            JsonResponse::ok().body(json!({"status": "ok", "checks": results}))
+        } else {
+            let checks = Value::Object(results.clone());
+            racoon_warn!("Health check failed: {}", checks);
+            JsonResponse::service_unavailable()
+                .body(json!({"status": "unavailable", "checks": results}))
+        }
+    }
+}
+
+impl AbstractMiddleware for Health {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let health = self.clone();
+
+        Box::new(Box::pin(async move {
+            if request.path == health.liveness_path {
+                health.respond(&health.liveness_checks, false).await
+            } else if request.path == health.readiness_path {
+                let mut checks = health.liveness_checks.clone();
+                checks.extend(health.readiness_checks.iter().cloned());
+                health.respond(&checks, true).await
+            } else {
+                next.run(request).await
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::Headers;
+    use crate::core::middleware::Next;
+
+    use super::Health;
+
+    #[tokio::test]
+    async fn test_health_endpoints() {
+        let queue_full = Arc::new(AtomicBool::new(false));
+        let server_ready = Arc::new(AtomicBool::new(true));
+
+        let check_queue = queue_full.clone();
+        let check_server = server_ready.clone();
+        let health = Health::new()
+            .readiness_check("queue", move || {
+                let queue_full = check_queue.load(Ordering::Relaxed);
+                async move {
+                    if queue_full {
+                        return Err(std::io::Error::other("Queue is full."));
+                    }
+                    Ok(())
+                }
+            })
+            .server_ready(move || check_server.load(Ordering::Relaxed));
+        let next = Next::new(Arc::new(vec![Arc::new(health)]), None);
+
+        let status = |path: &'static str| {
+            let next = next.clone();
+            async move {
+                let request = request(path, Headers::new(), b"").await;
+                next.run(request).await.status().0
+            }
+        };
+
+        assert_eq!(200, status("/healthz").await);
+        assert_eq!(200, status("/readyz").await);
+
+        queue_full.store(true, Ordering::Relaxed);
+        assert_eq!(200, status("/healthz").await);
+        assert_eq!(503, status("/readyz").await);
+
+        queue_full.store(false, Ordering::Relaxed);
+        server_ready.store(false, Ordering::Relaxed);
+        assert_eq!(200, status("/healthz").await);
+        assert_eq!(503, status("/readyz").await);
+    }
+}
@@ -0,0 +1,69 @@
+///
+/// Escapes html special characters so that the text can be safely placed inside html elements
+/// and quoted attribute values.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::html::escape_html;
+///
+/// let escaped = escape_html("<script>alert('Hi')</script>");
+/// assert_eq!(escaped, "&lt;script&gt;alert(&#x27;Hi&#x27;)&lt;&#x2F;script&gt;");
+/// ```
+///
+pub fn escape_html<S: AsRef<str>>(text: S) -> String {
+    let text = text.as_ref();
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            '/' => escaped.push_str("&#x2F;"),
+            _ => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+///
+/// Formats html like `format!` but escapes every argument with `escape_html`. The format string
+/// itself is trusted.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::format_html;
+///
+/// let name = "<b>John</b>";
+/// let html = format_html!("<p>Hello {}</p>", name);
+/// assert_eq!(html, "<p>Hello &lt;b&gt;John&lt;&#x2F;b&gt;</p>");
+/// ```
+///
+#[macro_export]
+macro_rules! format_html {
+    ($format: literal) => {
+        format!($format)
+    };
+    ($format: literal, $($argument: expr),+ $(,)?) => {
+        format!($format, $($crate::core::html::escape_html($argument.to_string())),+)
+    };
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::escape_html;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!("Hello World", escape_html("Hello World"));
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&lt;&#x2F;a&gt;",
+            escape_html("<a href=\"x\">Tom & Jerry</a>")
+        );
+    }
+}
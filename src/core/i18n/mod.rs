@@ -0,0 +1,561 @@
+//!
+//! Translation of the responses, templates and form errors with the Fluent catalogs, available
+//! with the `i18n` feature.
+//!
+//! The [`I18n`] middleware picks the locale of the request from the language cookie or the
+//! `Accept-Language` header and inserts the [`Translator`] into the request extensions. The
+//! translator is also available with [`current`] and the [`t!`](crate::t) macro while the request
+//! is handled, so the templates and the form validators do not need the request.
+//!
+//! Default form error messages are translated with these message IDs:
+//!
+//! | ID                      | Arguments |
+//! |-------------------------|-----------|
+//! | `form-max-body-size`    |           |
+//! | `form-max-header-size`  |           |
+//! | `form-max-file-size`    |           |
+//! | `form-max-value-length` |           |
+//! | `form-field-missing`    |           |
+//! | `form-field-required`   |           |
+//! | `form-max-length`       | `$max`    |
+//! | `form-min-length`       | `$min`    |
+//!
+//! More information: <https://projectfluent.org/>
+//!
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::FluentResource;
+
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::Response;
+use crate::core::shortcuts::SingleText;
+use crate::racoon_debug;
+
+pub use fluent_bundle::{FluentArgs, FluentValue};
+pub use unic_langid::LanguageIdentifier;
+
+type Bundle = FluentBundle<FluentResource>;
+
+fn parse_locale(locale: &str) -> std::io::Result<LanguageIdentifier> {
+    locale.parse().map_err(|error| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid locale {}: {}", locale, error),
+        )
+    })
+}
+
+///
+/// Translated messages of all the supported locales.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::i18n::{Catalog, FluentArgs};
+///
+/// let catalog = Catalog::new("en")
+///     .unwrap()
+///     .resource("en", "hello = Hello { $name }!")
+///     .unwrap()
+///     .resource("fr", "hello = Bonjour { $name } !")
+///     .unwrap();
+///
+/// let locale = catalog.negotiate("fr-CA,fr;q=0.9,en;q=0.8");
+/// let mut args = FluentArgs::new();
+/// args.set("name", "Marie");
+/// assert_eq!(
+///     Some("Bonjour Marie !".to_string()),
+///     catalog.translate(&locale, "hello", Some(&args))
+/// );
+/// ```
+///
+pub struct Catalog {
+    default_locale: LanguageIdentifier,
+    bundles: HashMap<LanguageIdentifier, Bundle>,
+}
+
+impl Catalog {
+    ///
+    /// Creates empty catalog. Messages missing in the requested locale are taken from the default
+    /// locale.
+    ///
+    pub fn new(default_locale: &str) -> std::io::Result<Self> {
+        Ok(Self {
+            default_locale: parse_locale(default_locale)?,
+            bundles: HashMap::new(),
+        })
+    }
+
+    ///
+    /// Adds the messages in Fluent syntax to the locale. Returns error if the source has syntax
+    /// errors or the message is already defined.
+    ///
+    pub fn resource(mut self, locale: &str, source: &str) -> std::io::Result<Self> {
+        let locale = parse_locale(locale)?;
+        let resource = FluentResource::try_new(source.to_string()).map_err(|(_, errors)| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid Fluent resource for {}: {:?}", locale, errors),
+            )
+        })?;
+
+        let bundle = self.bundles.entry(locale.clone()).or_insert_with(|| {
+            let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+            // Unicode isolation marks around the arguments break the plain text responses.
+            bundle.set_use_isolating(false);
+            bundle
+        });
+
+        bundle.add_resource(resource).map_err(|errors| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to add Fluent resource for {}: {:?}", locale, errors),
+            )
+        })?;
+        Ok(self)
+    }
+
+    ///
+    /// Loads the `.ftl` files from the directory with the subdirectory for each locale. For
+    /// example `locales/en/main.ftl` and `locales/fr/main.ftl`.
+    ///
+    pub fn load_dir<P: AsRef<Path>>(mut self, path: P) -> std::io::Result<Self> {
+        for locale_entry in std::fs::read_dir(path)? {
+            let locale_entry = locale_entry?;
+            if !locale_entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let locale = locale_entry.file_name().to_string_lossy().to_string();
+            for file_entry in std::fs::read_dir(locale_entry.path())? {
+                let file_path = file_entry?.path();
+                if file_path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    != Some("ftl")
+                {
+                    continue;
+                }
+
+                let source = std::fs::read_to_string(&file_path)?;
+                self = self.resource(&locale, &source)?;
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn default_locale(&self) -> &LanguageIdentifier {
+        &self.default_locale
+    }
+
+    ///
+    /// Returns the supported locale matching the language tag. Tag with the region like `fr-CA`
+    /// also matches the locale of the same language like `fr`.
+    ///
+    pub fn supported_locale(&self, tag: &str) -> Option<LanguageIdentifier> {
+        let requested = tag.trim().parse::<LanguageIdentifier>().ok()?;
+        if self.bundles.contains_key(&requested) {
+            return Some(requested);
+        }
+
+        let mut locales: Vec<&LanguageIdentifier> = self
+            .bundles
+            .keys()
+            .filter(|locale| locale.language == requested.language)
+            .collect();
+        // Prefers the locale without the region, then the stable order.
+        locales.sort_by_key(|locale| (locale.region.is_some(), locale.to_string()));
+        locales.first().map(|locale| (*locale).clone())
+    }
+
+    ///
+    /// Returns the best supported locale for the `Accept-Language` header value, or the default
+    /// locale.
+    ///
+    pub fn negotiate(&self, accept_language: &str) -> LanguageIdentifier {
+        accept_language_tags(accept_language)
+            .iter()
+            .find_map(|tag| self.supported_locale(tag))
+            .unwrap_or_else(|| self.default_locale.clone())
+    }
+
+    ///
+    /// Formats the message in the locale, falling back to the default locale. Returns `None` if
+    /// the message is not defined in both.
+    ///
+    pub fn translate(
+        &self,
+        locale: &LanguageIdentifier,
+        id: &str,
+        args: Option<&FluentArgs>,
+    ) -> Option<String> {
+        [locale, &self.default_locale]
+            .into_iter()
+            .filter_map(|locale| self.bundles.get(locale))
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = vec![];
+                let text = bundle.format_pattern(pattern, args, &mut errors);
+
+                if !errors.is_empty() {
+                    racoon_debug!("Failed to format message {}. Errors: {:?}", id, errors);
+                }
+                Some(text.to_string())
+            })
+    }
+}
+
+///
+/// Language tags of the `Accept-Language` header value ordered by the quality. Tags with zero
+/// quality and the wildcard are skipped.
+///
+pub fn accept_language_tags(accept_language: &str) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = accept_language
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+
+            let quality = parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .map(|quality| quality.trim().parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+
+            if tag.is_empty() || tag == "*" || quality <= 0.0 {
+                return None;
+            }
+            Some((tag.to_string(), quality))
+        })
+        .collect();
+
+    // Stable sort keeps the order of the tags with the same quality.
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+///
+/// Translates the messages to the locale of the request.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::i18n::Translator;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+///
+/// async fn home(request: Request) -> Response {
+///     let title = match request.extensions.get::<Translator>() {
+///         Some(translator) => translator.t("home-title"),
+///         None => "Home".to_string(),
+///     };
+///     HttpResponse::ok().body(title)
+/// }
+/// ```
+///
+#[derive(Clone)]
+pub struct Translator {
+    catalog: Arc<Catalog>,
+    locale: LanguageIdentifier,
+}
+
+impl Translator {
+    pub fn new(catalog: Arc<Catalog>, locale: LanguageIdentifier) -> Self {
+        Self { catalog, locale }
+    }
+
+    pub fn locale(&self) -> &LanguageIdentifier {
+        &self.locale
+    }
+
+    pub fn translate(&self, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+        self.catalog.translate(&self.locale, id, args)
+    }
+
+    ///
+    /// Returns the translated message, or the message ID if the message is not defined.
+    ///
+    pub fn t(&self, id: &str) -> String {
+        self.t_args(id, &FluentArgs::new())
+    }
+
+    pub fn t_args(&self, id: &str, args: &FluentArgs) -> String {
+        self.translate(id, Some(args))
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: Translator;
+}
+
+///
+/// Returns the translator of the request being handled by the current task.
+///
+pub fn current() -> Option<Translator> {
+    CURRENT.try_with(|translator| translator.clone()).ok()
+}
+
+///
+/// Runs the future with the translator returned by [`current`].
+///
+pub async fn scope<F: Future>(translator: Translator, future: F) -> F::Output {
+    CURRENT.scope(translator, future).await
+}
+
+///
+/// Translates the message with the translator of the current request. Returns the message ID
+/// outside the request or if the message is not defined, so the templates still render.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::{format_html, t};
+///
+/// let html = format_html!(
+///     "<h1>{}</h1><p>{}</p>",
+///     t!("welcome-title"),
+///     t!("unread-messages", count = 3)
+/// );
+/// assert_eq!("<h1>welcome-title</h1><p>unread-messages</p>", html);
+/// ```
+///
+#[macro_export]
+macro_rules! t {
+    ($id: expr) => {{
+        let id: &str = $id.as_ref();
+        $crate::core::i18n::current()
+            .map(|translator| translator.t(id))
+            .unwrap_or_else(|| id.to_string())
+    }};
+    ($id: expr, $($name: ident = $value: expr),+ $(,)?) => {{
+        let id: &str = $id.as_ref();
+        let mut args = $crate::core::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+
+        $crate::core::i18n::current()
+            .map(|translator| translator.t_args(id, &args))
+            .unwrap_or_else(|| id.to_string())
+    }};
+}
+
+///
+/// Middleware selecting the locale of the request and making the [`Translator`] available to the
+/// handler. Locale is taken from the language cookie if set, then from the `Accept-Language`
+/// header. Responses get the `Content-Language` header.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::i18n::{Catalog, I18n};
+/// use racoon::core::server::Server;
+///
+/// let catalog = Catalog::new("en")
+///     .unwrap()
+///     .resource("en", "greeting = Hello")
+///     .unwrap()
+///     .resource("ne", "greeting = नमस्ते")
+///     .unwrap();
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(I18n::new(catalog).cookie("lang"));
+/// ```
+///
+#[derive(Clone)]
+pub struct I18n {
+    catalog: Arc<Catalog>,
+    cookie: Option<String>,
+}
+
+impl I18n {
+    pub fn new(catalog: Catalog) -> Self {
+        Self {
+            catalog: Arc::new(catalog),
+            cookie: None,
+        }
+    }
+
+    ///
+    /// Name of the cookie storing the locale chosen by the user. Takes precedence over the
+    /// `Accept-Language` header if the locale is supported.
+    ///
+    pub fn cookie<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.cookie = Some(name.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Returns the supported locale for the request.
+    ///
+    pub fn locale(&self, request: &Request) -> LanguageIdentifier {
+        let cookie_locale = self
+            .cookie
+            .as_ref()
+            .and_then(|name| request.cookies.value(name))
+            .and_then(|value| self.catalog.supported_locale(value));
+
+        match cookie_locale {
+            Some(locale) => locale,
+            None => {
+                let accept_language = request.headers.value("Accept-Language").unwrap_or_default();
+                self.catalog.negotiate(&accept_language)
+            }
+        }
+    }
+}
+
+impl AbstractMiddleware for I18n {
+    fn handle(&self, mut request: Request, next: Next) -> MiddlewareResult {
+        let locale = self.locale(&request);
+        let translator = Translator::new(self.catalog.clone(), locale.clone());
+        request.extensions.insert(translator.clone());
+
+        Box::new(Box::pin(async move {
+            let mut response: Response = scope(translator, next.run(request)).await;
+
+            let headers = response.get_headers();
+            if headers.value("Content-Language").is_none() {
+                headers.set("Content-Language", locale.to_string());
+            }
+
+            // Caches must not serve the response of one language to the other.
+            let vary = match headers.value("Vary") {
+                Some(vary) => format!("{}, Accept-Language", vary),
+                None => "Accept-Language".to_string(),
+            };
+            headers.set("Vary", vary);
+            response
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::headers::HeaderValue;
+    use crate::core::path::{Path, View};
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::HttpResponse;
+    use crate::core::server::Server;
+    use crate::core::testing::request::TestRequest;
+    use crate::core::testing::TestServer;
+    use crate::forms::fields::input_field::InputField;
+    use crate::forms::FormValidator;
+
+    use super::{accept_language_tags, Catalog, I18n};
+
+    fn catalog() -> Catalog {
+        Catalog::new("en")
+            .unwrap()
+            .resource("en", "greeting = Hello { $name }\nonly-english = English")
+            .unwrap()
+            .resource(
+                "fr",
+                "greeting = Bonjour { $name }\nform-field-missing = Ce champ est manquant.",
+            )
+            .unwrap()
+    }
+
+    struct NameForm {
+        name: InputField<String>,
+    }
+
+    impl FormValidator for NameForm {
+        fn new() -> Self {
+            Self {
+                name: InputField::new("name"),
+            }
+        }
+
+        fn form_fields(&mut self) -> crate::forms::FormFields {
+            vec![Box::new(self.name.clone())]
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            vec!["fr-CA", "en"],
+            accept_language_tags("en;q=0.5, *, de;q=0, fr-CA")
+        );
+
+        let catalog = catalog();
+        assert_eq!("fr", catalog.negotiate("fr-CA,en;q=0.8").to_string());
+        assert_eq!("en", catalog.negotiate("de").to_string());
+
+        let locale = catalog.negotiate("fr");
+        assert_eq!(
+            Some("English".to_string()),
+            catalog.translate(&locale, "only-english", None)
+        );
+        assert_eq!(None, catalog.translate(&locale, "unknown", None));
+    }
+
+    #[tokio::test]
+    async fn test_i18n_middleware() {
+        let greeting: View = |_| {
+            Box::pin(async move {
+                let body = crate::t!("greeting", name = "Marie");
+                let response: crate::core::response::Response = HttpResponse::ok().body(body);
+                response
+            })
+        };
+        let form: View = |request| {
+            Box::pin(async move {
+                let body = match NameForm::new().validate(&request).await {
+                    Ok(_) => "Valid".to_string(),
+                    Err(error) => error.field_errors["name"].join(" "),
+                };
+                let response: crate::core::response::Response = HttpResponse::ok().body(body);
+                response
+            })
+        };
+
+        let mut server = Server::bind("127.0.0.1:0");
+        server
+            .middleware(I18n::new(catalog()).cookie("lang"))
+            .urls(vec![Path::new("/", greeting), Path::new("/form", form)]);
+
+        let client = TestServer::new(server).client();
+        let mut response = client
+            .send(TestRequest::get("/").header("Accept-Language", "fr-FR, en;q=0.5"))
+            .await;
+        assert_eq!(b"Bonjour Marie".to_vec(), *response.get_body());
+        assert_eq!(
+            Some("fr".to_string()),
+            response.get_headers().value("Content-Language")
+        );
+        assert_eq!(
+            Some("Accept-Language".to_string()),
+            response.get_headers().value("Vary")
+        );
+
+        // Cookie takes precedence over the header.
+        let mut response = client
+            .send(
+                TestRequest::get("/")
+                    .header("Accept-Language", "fr")
+                    .cookie("lang", "en"),
+            )
+            .await;
+        assert_eq!(b"Hello Marie".to_vec(), *response.get_body());
+
+        let mut response = client
+            .send(
+                TestRequest::post("/form")
+                    .header("Accept-Language", "fr")
+                    .field("other", "value"),
+            )
+            .await;
+        assert_eq!(b"Ce champ est manquant.".to_vec(), *response.get_body());
+
+        // Outside the request, the message ID is returned.
+        assert_eq!("greeting", crate::t!("greeting"));
+    }
+}
@@ -0,0 +1,287 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::core::forms::FormConstraints;
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::parser::params;
+use crate::core::path::PathParams;
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+use crate::core::server::Context;
+use crate::core::session::managers::MemorySessionManager;
+use crate::core::session::SessionManager;
+use crate::core::stream::{Stream, TestStreamWrapper};
+
+///
+/// Headers managing the HTTP/1 connection. These are left to the HTTP implementation receiving
+/// the converted message.
+///
+fn is_connection_header(name: &str) -> bool {
+    name.eq_ignore_ascii_case("connection") || name.eq_ignore_ascii_case("keep-alive")
+}
+
+impl Request {
+    ///
+    /// Creates the request from the `http::Request`, so the handlers can be called with the
+    /// requests received by the libraries using the `http` crate. The body is read from memory
+    /// and the sessions are stored in memory.
+    ///
+    /// Requires the `http` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::headers::HeaderValue;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::shortcuts::SingleText;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let request = http::Request::post("https://example.com/users?page=2")
+    ///     .header("Content-Type", "application/json")
+    ///     .body(r#"{"name":"John"}"#)
+    ///     .unwrap();
+    ///
+    /// let request = Request::from_http(request).await;
+    /// assert_eq!("POST", request.method);
+    /// assert_eq!("/users?page=2", request.path);
+    /// assert_eq!(Some(&"2".to_string()), request.query_params.value("page"));
+    /// assert_eq!(Some("example.com".to_string()), request.headers.value("Host"));
+    /// assert_eq!(br#"{"name":"John"}"#.to_vec(), request.body().await.unwrap());
+    /// # }
+    /// ```
+    ///
+    pub async fn from_http<B: AsRef<[u8]>>(request: http::Request<B>) -> Self {
+        let (parts, body) = request.into_parts();
+        let body = body.as_ref().to_vec();
+
+        let mut headers = Headers::new();
+        for (name, value) in parts.headers.iter() {
+            headers.set_multiple(name.as_str(), value.as_bytes());
+        }
+
+        if let Some(authority) = parts.uri.authority() {
+            if headers.value("Host").is_none() {
+                headers.set("Host", authority.as_str());
+            }
+        }
+
+        if !body.is_empty() && headers.value("Content-Length").is_none() {
+            headers.set("Content-Length", body.len().to_string());
+        }
+
+        let raw_path = parts
+            .uri
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/")
+            .to_string();
+        let query_params = params::query_params_from_raw(&raw_path);
+        let scheme = parts.uri.scheme_str().unwrap_or("http").to_string();
+        let http_version = if parts.version == http::Version::HTTP_10 {
+            0
+        } else {
+            1
+        };
+
+        let body_read = Arc::new(AtomicBool::new(body.is_empty()));
+        let stream: Stream = Box::new(TestStreamWrapper::new(body, 8096));
+        let context: Context = Box::pin(None::<String>);
+        let session_manager: SessionManager = Box::<MemorySessionManager>::default();
+
+        Request::from(
+            Arc::new(stream),
+            Arc::new(context),
+            scheme,
+            parts.method.to_string(),
+            raw_path,
+            http_version,
+            headers,
+            PathParams::new(),
+            query_params,
+            Arc::new(session_manager),
+            body_read,
+            Arc::new(FormConstraints::default()),
+            Arc::new(Mutex::new(Headers::new())),
+        )
+        .await
+    }
+
+    ///
+    /// Reads the body and converts the request to the `http::Request`, so it can be forwarded
+    /// with the HTTP clients using the `http` crate. The URI is absolute if the `Host` header is
+    /// present.
+    ///
+    /// Requires the `http` feature.
+    ///
+    pub async fn into_http(self) -> std::io::Result<http::Request<Vec<u8>>> {
+        let body = self
+            .body()
+            .await
+            .map_err(|error| std::io::Error::other(format!("{:?}", error)))?;
+
+        let uri = match self.headers.value("Host") {
+            Some(host) => format!("{}://{}{}", self.scheme, host, self.path),
+            None => self.path.clone(),
+        };
+
+        let version = if self.http_version == 0 {
+            http::Version::HTTP_10
+        } else {
+            http::Version::HTTP_11
+        };
+
+        let mut builder = http::Request::builder()
+            .method(self.method.as_str())
+            .uri(uri)
+            .version(version);
+
+        for (name, values) in self.headers.iter() {
+            if is_connection_header(name) {
+                continue;
+            }
+
+            for value in values {
+                builder = builder.header(name.as_str(), value.as_slice());
+            }
+        }
+
+        builder.body(body).map_err(std::io::Error::other)
+    }
+}
+
+///
+/// Converts the `http::Response` returned by the libraries using the `http` crate to the
+/// response which can be returned from the handlers. Requires the `http` feature.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::headers::HeaderValue;
+/// use racoon::core::response::Response;
+///
+/// let response = http::Response::builder()
+///     .status(201)
+///     .header("Location", "/users/7")
+///     .body("Created")
+///     .unwrap();
+///
+/// let mut response = Response::from(response);
+/// assert_eq!(201, response.status().0);
+/// assert_eq!(Some("/users/7".to_string()), response.get_headers().value("Location"));
+/// assert_eq!(b"Created".to_vec(), *response.get_body());
+/// ```
+///
+impl<B: AsRef<[u8]>> From<http::Response<B>> for Response {
+    fn from(response: http::Response<B>) -> Self {
+        let (parts, body) = response.into_parts();
+        let status_text = parts.status.canonical_reason().unwrap_or_default();
+        let keep_alive = !parts
+            .headers
+            .get_all(http::header::CONNECTION)
+            .iter()
+            .any(|value| value.as_bytes().eq_ignore_ascii_case(b"close"));
+
+        let mut response: Response =
+            HttpResponse::with_status(parts.status.as_u16() as u32, status_text)
+                .keep_alive(keep_alive)
+                .bytes(body);
+        let headers = response.get_headers();
+
+        for (name, value) in parts.headers.iter() {
+            // Body is complete, so the length and connection headers set by the response are kept.
+            if name == http::header::CONTENT_LENGTH
+                || name == http::header::TRANSFER_ENCODING
+                || is_connection_header(name.as_str())
+            {
+                continue;
+            }
+            headers.set_multiple(name.as_str(), value.as_bytes());
+        }
+        response
+    }
+}
+
+///
+/// Converts the response to the `http::Response`. Fails for the responses which are written to
+/// the stream by the handler such as server-sent events. Requires the `http` feature.
+///
+impl TryFrom<Response> for http::Response<Vec<u8>> {
+    type Error = std::io::Error;
+
+    fn try_from(mut response: Response) -> Result<Self, Self::Error> {
+        if !response.serve_default() {
+            return Err(std::io::Error::other(
+                "Response is written to the stream by the handler.",
+            ));
+        }
+
+        let (status_code, _) = response.status();
+        let mut builder = http::Response::builder().status(status_code as u16);
+
+        for (name, values) in response.get_headers().iter() {
+            if is_connection_header(name) {
+                continue;
+            }
+
+            for value in values {
+                builder = builder.header(name.as_str(), value.as_slice());
+            }
+        }
+
+        let body = std::mem::take(response.get_body());
+        builder.body(body).map_err(std::io::Error::other)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::headers::HeaderValue;
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+
+    #[tokio::test]
+    async fn test_http_conversions() {
+        let request = http::Request::put("/users/7")
+            .header("Host", "example.com")
+            .header("Accept", "text/html")
+            .header("Accept", "application/json")
+            .body(b"name=John".to_vec())
+            .unwrap();
+
+        let request = Request::from_http(request).await;
+        assert_eq!(2, request.headers["accept"].len());
+
+        let request = request.into_http().await.unwrap();
+        assert_eq!(http::Method::PUT, request.method());
+        assert_eq!("http://example.com/users/7", request.uri().to_string());
+        assert_eq!(2, request.headers().get_all("accept").iter().count());
+        assert_eq!(b"name=John".to_vec(), *request.body());
+
+        let response: Response = HttpResponse::not_found()
+            .keep_alive(false)
+            .body("Not found");
+        let response = http::Response::try_from(response).unwrap();
+        assert_eq!(http::StatusCode::NOT_FOUND, response.status());
+        assert!(response.headers().get("connection").is_none());
+        assert_eq!("9", response.headers()["content-length"]);
+
+        let response = http::Response::builder()
+            .header("Connection", "close")
+            .body(vec![])
+            .unwrap();
+        let mut response = Response::from(response);
+        assert!(response.should_close());
+        assert_eq!(
+            Some("close".to_string()),
+            response.get_headers().value("Connection")
+        );
+
+        let response: Response = HttpResponse::ok().disable_serve_default().body("");
+        assert!(http::Response::try_from(response).is_err());
+    }
+}
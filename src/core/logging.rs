@@ -1,59 +1,161 @@
+//!
+//! Logs of the framework such as connection errors, rejected requests and handler panics.
+//!
+//! Logs are emitted through the `log` facade, or through `tracing` with the `tracing` feature so
+//! they are recorded within the request spans. Installed logger or subscriber decides where they
+//! are written and can filter them by the module like `racoon::core::server`.
+//!
+//! Levels emitted by racoon are set with the `RACOON_LOGGING` environment variable or
+//! `set_max_level`. It accepts `off`, `error`, `warn`, `info`, `debug` or `trace`. `true` enables
+//! all the levels and `false` disables the logs. Warnings and errors are emitted by default.
+//!
+//! # Examples
+//!
+//! ```
+//! use log::LevelFilter;
+//!
+//! use racoon::core::logging;
+//!
+//! logging::set_max_level(LevelFilter::Info);
+//! assert!(logging::enabled(log::Level::Info));
+//! assert!(!logging::enabled(log::Level::Debug));
+//! ```
+//!
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub use log::{Level, LevelFilter};
+
+///
+/// Macros of the logging backend used by the racoon macros.
+///
+#[doc(hidden)]
+pub mod backend {
+    #[cfg(not(feature = "tracing"))]
+    pub use log::{debug, error, info, trace, warn};
+
+    #[cfg(feature = "tracing")]
+    pub use tracing::{debug, error, info, trace, warn};
+}
+
+/// Marks the level as not read from the environment yet.
+const UNINITIALIZED: usize = usize::MAX;
+
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(UNINITIALIZED);
+
+fn level_from_usize(value: usize) -> LevelFilter {
+    LevelFilter::iter()
+        .find(|level| *level as usize == value)
+        .unwrap_or(LevelFilter::Warn)
+}
+
+///
+/// Parses the value of `RACOON_LOGGING`.
+///
+fn parse_level(value: &str) -> Option<LevelFilter> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "on" => Some(LevelFilter::Trace),
+        "false" | "0" => Some(LevelFilter::Off),
+        value => value.parse().ok(),
+    }
+}
+
+///
+/// Highest level emitted by racoon. Read from `RACOON_LOGGING` on first use.
+///
+pub fn max_level() -> LevelFilter {
+    let value = MAX_LEVEL.load(Ordering::Relaxed);
+    if value != UNINITIALIZED {
+        return level_from_usize(value);
+    }
+
+    let level = std::env::var("RACOON_LOGGING")
+        .ok()
+        .and_then(|value| parse_level(&value))
+        .unwrap_or(LevelFilter::Warn);
+    MAX_LEVEL.store(level as usize, Ordering::Relaxed);
+    level
+}
+
+///
+/// Sets the highest level emitted by racoon, overriding `RACOON_LOGGING`.
+///
+pub fn set_max_level(level: LevelFilter) {
+    MAX_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level <= max_level()
+}
+
 pub mod condition {
-    use std::env;
+    use log::Level;
 
+    ///
+    /// Returns true if the debug logs are emitted.
+    ///
     pub fn is_logging_enabled() -> bool {
-        return match env::var("RACOON_LOGGING") {
-            Ok(value) => {
-                value.to_lowercase() == "true"
-            }
-            Err(_) => {
-                false
-            }
-        };
+        super::enabled(Level::Debug)
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __racoon_log {
+    ($macro_name: ident, $level: ident, $($arg:tt)*) => {
+        if $crate::core::logging::enabled($crate::core::logging::Level::$level) {
+            $crate::core::logging::backend::$macro_name!($($arg)*);
+        }
     }
 }
 
 #[macro_export]
 macro_rules! racoon_debug {
     ($($arg:tt)*) => {
-        if crate::core::logging::condition::is_logging_enabled() {
-            log::debug!($($arg)*);
-        }
+        $crate::__racoon_log!(debug, Debug, $($arg)*)
     }
 }
 
 #[macro_export]
 macro_rules! racoon_info {
     ($($arg:tt)*) => {
-        if crate::core::logging::condition::is_logging_enabled() {
-            log::info!($($arg)*);
-        }
+        $crate::__racoon_log!(info, Info, $($arg)*)
     }
 }
 
 #[macro_export]
 macro_rules! racoon_warn {
     ($($arg:tt)*) => {
-        if use crate::core::logging::condition::is_logging_enabled() {
-            log::warn!($($arg)*);
-        }
+        $crate::__racoon_log!(warn, Warn, $($arg)*)
     }
 }
 
 #[macro_export]
 macro_rules! racoon_trace {
     ($($arg:tt)*) => {
-        if use crate::core::logging::condition::is_logging_enabled() {
-            log::trace!($($arg)*);
-        }
+        $crate::__racoon_log!(trace, Trace, $($arg)*)
     }
 }
 
 #[macro_export]
 macro_rules! racoon_error {
     ($($arg:tt)*) => {
-        if crate::core::logging::condition::is_logging_enabled() {
-            log::error!($($arg)*);
-        }
+        $crate::__racoon_log!(error, Error, $($arg)*)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use log::LevelFilter;
+
+    use super::parse_level;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(Some(LevelFilter::Trace), parse_level("true"));
+        assert_eq!(Some(LevelFilter::Off), parse_level("false"));
+        assert_eq!(Some(LevelFilter::Off), parse_level("off"));
+        assert_eq!(Some(LevelFilter::Info), parse_level("INFO"));
+        assert_eq!(None, parse_level("verbose"));
     }
 }
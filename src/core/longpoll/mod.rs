@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use crate::core::extract::IntoResponse;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse, Response};
+
+type Topics<T> = HashMap<String, broadcast::Sender<T>>;
+
+///
+/// Wakes the tasks waiting for the topic, such as the parked long-polling requests. Values
+/// notified while nobody is waiting are not kept, so clients need to fetch the current state
+/// before polling for the changes.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::extract::State;
+/// use racoon::core::longpoll::Notifier;
+/// use racoon::core::path::Path;
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::server::Server;
+/// use racoon::handler;
+///
+/// async fn poll(State(notifier): State<Notifier<String>>) -> Response {
+///     // Responds `204 No Content` if nothing is notified within 30 seconds.
+///     notifier
+///         .poll("orders", Duration::from_secs(30), |order| order)
+///         .await
+/// }
+///
+/// async fn create_order(State(notifier): State<Notifier<String>>) -> Response {
+///     notifier.notify("orders", "Order created.".to_string());
+///     HttpResponse::ok().body("Created")
+/// }
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.with_state(Notifier::<String>::new()).urls(vec![
+///     Path::get("/orders/poll", handler!(poll)),
+///     Path::post("/orders", handler!(create_order)),
+/// ]);
+/// ```
+///
+pub struct Notifier<T> {
+    topics: Arc<Mutex<Topics<T>>>,
+}
+
+impl<T> Clone for Notifier<T> {
+    fn clone(&self) -> Self {
+        Self {
+            topics: self.topics.clone(),
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Default for Notifier<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Send + 'static> Notifier<T> {
+    pub fn new() -> Self {
+        Self {
+            topics: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn topics(&self) -> std::sync::MutexGuard<'_, Topics<T>> {
+        match self.topics.lock() {
+            Ok(topics) => topics,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    ///
+    /// Sends the value to the tasks waiting for the topic. Returns the number of woken tasks.
+    ///
+    pub fn notify<S: AsRef<str>>(&self, topic: S, value: T) -> usize {
+        let mut topics = self.topics();
+        let topic = topic.as_ref();
+
+        match topics.get(topic) {
+            Some(sender) => match sender.send(value) {
+                Ok(count) => count,
+                Err(_) => {
+                    topics.remove(topic);
+                    0
+                }
+            },
+            None => 0,
+        }
+    }
+
+    ///
+    /// Waits for the value notified to the topic. Returns `None` if nothing is notified within
+    /// the timeout.
+    ///
+    pub async fn wait<S: AsRef<str>>(&self, topic: S, timeout: Duration) -> Option<T> {
+        let topic = topic.as_ref();
+        let mut receiver = self
+            .topics()
+            .entry(topic.to_string())
+            .or_insert_with(|| broadcast::channel(16).0)
+            .subscribe();
+
+        let value = tokio::time::timeout(timeout, async {
+            loop {
+                match receiver.recv().await {
+                    Ok(value) => return Some(value),
+                    // Values dropped from the full channel are skipped.
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .await
+        .unwrap_or(None);
+
+        // Topic is removed by the last waiter.
+        drop(receiver);
+        let mut topics = self.topics();
+        if let Some(sender) = topics.get(topic) {
+            if sender.receiver_count() == 0 {
+                topics.remove(topic);
+            }
+        }
+
+        value
+    }
+
+    ///
+    /// Parks the long-polling request until the value is notified to the topic and responds with
+    /// the response created from the value. Responds `204 No Content` if nothing is notified
+    /// within the timeout.
+    ///
+    pub async fn poll<S, F, R>(&self, topic: S, timeout: Duration, respond: F) -> Response
+    where
+        S: AsRef<str>,
+        F: FnOnce(T) -> R,
+        R: IntoResponse,
+    {
+        match self.wait(topic, timeout).await {
+            Some(value) => respond(value).into_response(),
+            None => {
+                let response: Box<dyn AbstractResponse> = HttpResponse::no_content().empty();
+                response
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use super::Notifier;
+
+    #[tokio::test]
+    async fn test_notifier() {
+        let notifier = Notifier::new();
+
+        let waiter = notifier.clone();
+        let waiting = tokio::spawn(async move {
+            waiter
+                .poll("orders", Duration::from_secs(5), |value: String| value)
+                .await
+        });
+
+        // Waits until the request is parked.
+        while notifier.notify("orders", "created".to_string()) == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut response = waiting.await.unwrap();
+        assert_eq!(200, response.status().0);
+        assert_eq!(b"created".to_vec(), *response.get_body());
+        assert!(notifier.topics().is_empty());
+
+        let response = notifier
+            .poll("orders", Duration::from_millis(10), |value| value)
+            .await;
+        assert_eq!(204, response.status().0);
+    }
+}
@@ -0,0 +1,346 @@
+//!
+//! Request and connection metrics exposed in Prometheus text format.
+//!
+//! # Examples
+//!
+//! ```
+//! use racoon::core::server::Server;
+//!
+//! let mut server = Server::bind("127.0.0.1:8080");
+//! // Records requests handled by the server and serves the metrics at /metrics.
+//! server.metrics("/metrics");
+//! ```
+//!
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse, Response};
+use crate::core::router::MatchedPath;
+
+/// Upper bounds of the request duration histogram buckets in seconds.
+pub const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (index, bound) in DURATION_BUCKETS.iter().enumerate() {
+            if seconds <= *bound {
+                self.buckets[index] += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct RequestStats {
+    /// Request count by method, route and status code.
+    counts: BTreeMap<(String, String, u32), u64>,
+    /// Request duration by method and route.
+    durations: BTreeMap<(String, String), Histogram>,
+}
+
+///
+/// Metrics collected by the server. Routes are labelled by the registered pattern like
+/// `/users/{id}`, so the number of series does not grow with the requested paths.
+///
+#[derive(Default)]
+pub struct MetricsRegistry {
+    requests: Mutex<RequestStats>,
+    requests_in_flight: AtomicI64,
+    connections_total: AtomicU64,
+    connections_active: AtomicI64,
+    temp_uploads_active: AtomicI64,
+    temp_uploads_reaped: AtomicU64,
+    temp_dir_files: AtomicU64,
+    temp_dir_bytes: AtomicU64,
+}
+
+impl MetricsRegistry {
+    ///
+    /// Registry shared by the server and the metrics view.
+    ///
+    pub fn global() -> &'static MetricsRegistry {
+        static REGISTRY: OnceLock<MetricsRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(MetricsRegistry::default)
+    }
+
+    pub fn record_request(&self, method: &str, path: &str, status_code: u32, duration: Duration) {
+        let mut requests = match self.requests.lock() {
+            Ok(requests) => requests,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        *requests
+            .counts
+            .entry((method.to_string(), path.to_string(), status_code))
+            .or_default() += 1;
+        requests
+            .durations
+            .entry((method.to_string(), path.to_string()))
+            .or_default()
+            .observe(duration.as_secs_f64());
+    }
+
+    pub(crate) fn connection_opened(&self) {
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+        self.connections_active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn connection_closed(&self) {
+        self.connections_active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn temp_upload_opened(&self) {
+        self.temp_uploads_active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn temp_upload_closed(&self) {
+        self.temp_uploads_active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn temp_uploads_reaped(&self, count: u64) {
+        self.temp_uploads_reaped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    ///
+    /// Records the temp upload files found in the temp directory by the reaper.
+    ///
+    pub(crate) fn set_temp_dir_usage(&self, files: u64, bytes: u64) {
+        self.temp_dir_files.store(files, Ordering::Relaxed);
+        self.temp_dir_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    ///
+    /// Renders the metrics in Prometheus text exposition format.
+    ///
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        let requests = match self.requests.lock() {
+            Ok(requests) => requests,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        output
+            .push_str("# HELP racoon_http_requests_total Total number of handled HTTP requests.\n");
+        output.push_str("# TYPE racoon_http_requests_total counter\n");
+        for ((method, path, status_code), count) in &requests.counts {
+            let _ = writeln!(
+                output,
+                "racoon_http_requests_total{{method=\"{}\",path=\"{}\",status=\"{}\"}} {}",
+                escape_label(method),
+                escape_label(path),
+                status_code,
+                count
+            );
+        }
+
+        output.push_str(
+            "# HELP racoon_http_request_duration_seconds Time taken to handle HTTP requests.\n",
+        );
+        output.push_str("# TYPE racoon_http_request_duration_seconds histogram\n");
+        for ((method, path), histogram) in &requests.durations {
+            let labels = format!(
+                "method=\"{}\",path=\"{}\"",
+                escape_label(method),
+                escape_label(path)
+            );
+
+            for (index, bound) in DURATION_BUCKETS.iter().enumerate() {
+                let _ = writeln!(
+                    output,
+                    "racoon_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, histogram.buckets[index]
+                );
+            }
+            let _ = writeln!(
+                output,
+                "racoon_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, histogram.count
+            );
+            let _ = writeln!(
+                output,
+                "racoon_http_request_duration_seconds_sum{{{}}} {}",
+                labels, histogram.sum
+            );
+            let _ = writeln!(
+                output,
+                "racoon_http_request_duration_seconds_count{{{}}} {}",
+                labels, histogram.count
+            );
+        }
+        drop(requests);
+
+        let gauges = [
+            (
+                "racoon_http_requests_in_flight",
+                "gauge",
+                "Number of HTTP requests being handled.",
+                self.requests_in_flight.load(Ordering::Relaxed),
+            ),
+            (
+                "racoon_connections_total",
+                "counter",
+                "Total number of accepted connections.",
+                self.connections_total.load(Ordering::Relaxed) as i64,
+            ),
+            (
+                "racoon_connections_active",
+                "gauge",
+                "Number of open connections.",
+                self.connections_active.load(Ordering::Relaxed),
+            ),
+            (
+                "racoon_temp_uploads_active",
+                "gauge",
+                "Number of temp upload files used by the requests.",
+                self.temp_uploads_active.load(Ordering::Relaxed),
+            ),
+            (
+                "racoon_temp_uploads_reaped_total",
+                "counter",
+                "Total number of orphan temp upload files deleted by the reaper.",
+                self.temp_uploads_reaped.load(Ordering::Relaxed) as i64,
+            ),
+            (
+                "racoon_temp_dir_upload_files",
+                "gauge",
+                "Number of temp upload files in the temp directory at the last reaper run.",
+                self.temp_dir_files.load(Ordering::Relaxed) as i64,
+            ),
+            (
+                "racoon_temp_dir_upload_bytes",
+                "gauge",
+                "Size of the temp upload files in the temp directory at the last reaper run.",
+                self.temp_dir_bytes.load(Ordering::Relaxed) as i64,
+            ),
+        ];
+
+        for (name, metric_type, help, value) in gauges {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} {}", name, metric_type);
+            let _ = writeln!(output, "{} {}", name, value);
+        }
+        output
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+///
+/// Counts the connection as active until dropped.
+///
+pub(crate) struct ConnectionGuard;
+
+impl ConnectionGuard {
+    pub(crate) fn new() -> Self {
+        MetricsRegistry::global().connection_opened();
+        Self
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        MetricsRegistry::global().connection_closed();
+    }
+}
+
+///
+/// Middleware recording count, duration and in-flight requests to the global registry. Register
+/// it before other middlewares to include their time.
+///
+#[derive(Clone, Default)]
+pub struct Metrics;
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl AbstractMiddleware for Metrics {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        Box::new(Box::pin(async move {
+            let registry = MetricsRegistry::global();
+            let method = request.method.clone();
+
+            // Unmatched paths share the label, so scanning random paths does not create series.
+            let path = match request.extensions.get::<MatchedPath>() {
+                Some(matched_path) => matched_path.0.clone(),
+                None => "unmatched".to_string(),
+            };
+
+            registry.requests_in_flight.fetch_add(1, Ordering::Relaxed);
+            let started_at = Instant::now();
+            let response = next.run(request).await;
+            registry.requests_in_flight.fetch_sub(1, Ordering::Relaxed);
+
+            let (status_code, _) = response.status();
+            registry.record_request(&method, &path, status_code, started_at.elapsed());
+            response
+        }))
+    }
+}
+
+///
+/// View serving the metrics of the global registry.
+///
+pub async fn metrics_view(_: Request) -> Response {
+    let mut response = HttpResponse::ok().body(MetricsRegistry::global().render());
+    response
+        .get_headers()
+        .set("Content-Type", "text/plain; version=0.0.4; charset=utf-8");
+    response
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use super::MetricsRegistry;
+
+    #[test]
+    fn test_render() {
+        let registry = MetricsRegistry::default();
+        registry.record_request("GET", "/users/{id}", 200, Duration::from_millis(20));
+        registry.record_request("GET", "/users/{id}", 404, Duration::from_secs(3));
+        registry.connection_opened();
+
+        let output = registry.render();
+        assert!(output.contains(
+            "racoon_http_requests_total{method=\"GET\",path=\"/users/{id}\",status=\"200\"} 1\n"
+        ));
+        assert!(output.contains(
+            "racoon_http_request_duration_seconds_bucket{method=\"GET\",path=\"/users/{id}\",le=\"0.025\"} 1\n"
+        ));
+        assert!(output.contains(
+            "racoon_http_request_duration_seconds_bucket{method=\"GET\",path=\"/users/{id}\",le=\"5\"} 2\n"
+        ));
+        assert!(output.contains(
+            "racoon_http_request_duration_seconds_count{method=\"GET\",path=\"/users/{id}\"} 2\n"
+        ));
+        assert!(output.contains("racoon_connections_active 1\n"));
+    }
+}
@@ -1,15 +1,419 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 
-use crate::core::path::View;
+use crate::core::headers::HeaderValue;
+use crate::core::path::{self, Path, View};
 use crate::core::request::Request;
-use crate::core::response::AbstractResponse;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse, Response};
+use crate::core::router::RouteMetadata;
+use crate::racoon_debug;
 
 pub type Middleware = fn(Request, Option<View>) -> Pin<Box<dyn Future<Output=Box<dyn AbstractResponse>> + Send>>;
 
+pub type MiddlewareResult = Box<dyn Future<Output = Response> + Send + Unpin>;
+
+pub type Middlewares = Vec<Arc<dyn AbstractMiddleware>>;
+
+///
+/// Middleware registered on the server. Receives the request and the rest of the chain as
+/// `next`. Middleware can modify the request before calling `next.run(request)`, post-process
+/// the returned response or return its own response without calling `next` at all.
+///
+/// Async functions and closures with signature `(Request, Next) -> Response` implement this
+/// trait, so a struct is only needed when the middleware has configuration.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::headers::HeaderValue;
+/// use racoon::core::middleware::Next;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{AbstractResponse, HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::server::Server;
+///
+/// async fn maintenance(request: Request, next: Next) -> Response {
+///     if request.path.starts_with("/admin") {
+///         return HttpResponse::service_unavailable().body("Under maintenance");
+///     }
+///     next.run(request).await
+/// }
+///
+/// async fn server_header(request: Request, next: Next) -> Response {
+///     let mut response = next.run(request).await;
+///     response.get_headers().set("Server", "Racoon");
+///     response
+/// }
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(maintenance).middleware(server_header);
+/// ```
+///
+pub trait AbstractMiddleware: Send + Sync {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult;
+}
+
+impl<F, Fut> AbstractMiddleware for F
+where
+    F: Fn(Request, Next) -> Fut + Send + Sync,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        Box::new(Box::pin(self(request, next)))
+    }
+}
+
+///
+/// Adapts middleware view attached with `wrap` to the middleware chain of the route.
+///
+struct ViewMiddleware(Middleware);
+
+impl AbstractMiddleware for ViewMiddleware {
+    fn handle(&self, request: Request, _: Next) -> MiddlewareResult {
+        Box::new((self.0)(request, Some(next_view)))
+    }
+}
+
+pub(crate) fn from_view(middleware: Middleware) -> Arc<dyn AbstractMiddleware> {
+    Arc::new(ViewMiddleware(middleware))
+}
+
+///
+/// Remaining middlewares of the server and the view which they wrap. Middlewares are called in
+/// the order they are registered, so the first registered middleware sees the request first and
+/// the response last.
+///
+#[derive(Clone, Default)]
+pub struct Next {
+    middlewares: Arc<Middlewares>,
+    position: usize,
+    wrap: Option<Middleware>,
+    view: Option<View>,
+}
+
+impl Next {
+    pub(crate) fn new(middlewares: Arc<Middlewares>, wrap: Option<Middleware>) -> Self {
+        Self {
+            middlewares,
+            position: 0,
+            wrap,
+            view: None,
+        }
+    }
+
+    pub(crate) fn with_view(mut self, view: Option<View>) -> Self {
+        self.view = view;
+        self
+    }
+
+    ///
+    /// Calls the next middleware or the view if all the middlewares are called.
+    ///
+    pub async fn run(mut self, request: Request) -> Response {
+        if let Some(middleware) = self.middlewares.get(self.position).cloned() {
+            self.position += 1;
+            return middleware.handle(request, self).await;
+        }
+
+        match self.wrap {
+            Some(wrap) => {
+                racoon_debug!("Middleware found. Passing request to middleware.");
+                wrap(request, self.view).await
+            }
+            None => Path::resolve(request, self.view).await,
+        }
+    }
+}
+
+///
+/// Predicate evaluated before the route view. Returns error response to reject the request
+/// without calling the view.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::headers::HeaderValue;
+/// use racoon::core::path::Path;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::view;
+///
+/// fn api_key_present(request: &Request) -> Result<(), Response> {
+///     if request.headers.value("X-Api-Key").is_none() {
+///         return Err(HttpResponse::unauthorized().body("API key is missing."));
+///     }
+///     Ok(())
+/// }
+///
+/// async fn report(request: Request) -> Response {
+///     HttpResponse::ok().body("Report")
+/// }
+///
+/// let paths = vec![
+///     Path::get("/report", view!(report)).guard(api_key_present),
+/// ];
+/// ```
+///
+pub type Guard = Arc<dyn Fn(&Request) -> Result<(), Response> + Send + Sync>;
+
+///
+/// Guard rejecting requests without JSON content type with `415 Unsupported Media Type`.
+///
+pub fn json_content_type(request: &Request) -> Result<(), Response> {
+    let content_type = request.headers.value("Content-Type").unwrap_or_default();
+    let mime_type = content_type.split(';').next().unwrap_or_default().trim();
+
+    if mime_type.eq_ignore_ascii_case("application/json") {
+        return Ok(());
+    }
+    Err(HttpResponse::unsupported_media_type()
+        .body("Expected request with Content-Type: application/json"))
+}
+
+///
+/// Runs the middleware only for the requests matching the predicate. Other requests are passed
+/// to the next middleware.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::middleware::{Next, When};
+/// use racoon::core::path::Path;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::server::Server;
+/// use racoon::view;
+///
+/// async fn login_required(request: Request, next: Next) -> Response {
+///     if request.session.get("user_id").await.is_none() {
+///         return HttpResponse::unauthorized().body("401 Unauthorized");
+///     }
+///     next.run(request).await
+/// }
+///
+/// async fn home(request: Request) -> Response {
+///     HttpResponse::ok().body("Home")
+/// }
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server
+///     .middleware(When::not_tagged("public", login_required))
+///     .urls(vec![
+///         Path::get("/", view!(home)).tag("public"),
+///         Path::get("/dashboard", view!(home)),
+///     ]);
+/// ```
+///
+pub struct When<M> {
+    predicate: Arc<dyn Fn(&Request) -> bool + Send + Sync>,
+    middleware: M,
+}
+
+impl<M: AbstractMiddleware> When<M> {
+    pub fn new<P>(predicate: P, middleware: M) -> Self
+    where
+        P: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            predicate: Arc::new(predicate),
+            middleware,
+        }
+    }
+
+    ///
+    /// Runs the middleware only for the routes with the tag.
+    ///
+    pub fn tagged<S: AsRef<str>>(tag: S, middleware: M) -> Self {
+        let tag = tag.as_ref().to_string();
+        Self::new(
+            move |request| {
+                RouteMetadata::of(request).is_some_and(|metadata| metadata.has_tag(&tag))
+            },
+            middleware,
+        )
+    }
+
+    ///
+    /// Skips the middleware for the routes with the tag. Requests not matching any route still
+    /// run the middleware.
+    ///
+    pub fn not_tagged<S: AsRef<str>>(tag: S, middleware: M) -> Self {
+        let tag = tag.as_ref().to_string();
+        Self::new(
+            move |request| {
+                !RouteMetadata::of(request).is_some_and(|metadata| metadata.has_tag(&tag))
+            },
+            middleware,
+        )
+    }
+}
+
+impl<M: AbstractMiddleware> AbstractMiddleware for When<M> {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        if (self.predicate)(&request) {
+            return self.middleware.handle(request, next);
+        }
+        Box::new(Box::pin(next.run(request)))
+    }
+}
+
+///
+/// Middlewares attached to the matched route and the view which they wrap. The position is kept
+/// in each request, so a middleware can safely call the next view more than once.
+///
+#[derive(Clone, Default)]
+pub struct MiddlewareChain {
+    middlewares: Arc<Middlewares>,
+    guards: Arc<Vec<Guard>>,
+    view: Option<View>,
+    position: usize,
+}
+
+impl MiddlewareChain {
+    pub fn new(
+        middlewares: Arc<Middlewares>,
+        guards: Arc<Vec<Guard>>,
+        view: Option<View>,
+    ) -> Self {
+        Self {
+            middlewares,
+            guards,
+            view,
+            position: 0,
+        }
+    }
+}
+
+///
+/// View passed to route middlewares as the next view. Calls the next middleware of the chain or
+/// the route view if all the middlewares are called. Guards are evaluated before the route view.
+///
+pub fn next_view(mut request: Request) -> Pin<Box<dyn Future<Output=Box<dyn AbstractResponse>> + Send>> {
+    Box::pin(async move {
+        let chain = request.middleware_chain.clone();
+
+        if let Some(middleware) = chain.middlewares.get(chain.position) {
+            request.middleware_chain.position += 1;
+
+            // Position is kept in the request, so next continues the chain of the route.
+            let next = Next::new(Arc::default(), Some(|request, _| next_view(request)));
+            return middleware.handle(request, next).await;
+        }
+
+        for guard in chain.guards.iter() {
+            if let Err(mut response) = guard(&request) {
+                path::add_response_headers(&request, &mut response).await;
+                return response;
+            }
+        }
+
+        Path::resolve(request, chain.view).await
+    })
+}
+
 #[macro_export]
 macro_rules! wrap_view {
     ($middleware_fn: ident) => {
             |request: Request, view: Option<View>| Box::pin($middleware_fn(request, view))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::path::{Path, Scope, View};
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse, Response};
+    use crate::core::server::Server;
+    use crate::core::testing::TestServer;
+
+    use super::{Middlewares, Next, When};
+
+    async fn trace(mut request: Request, next: Next) -> Response {
+        request.headers.set("X-Trace", "outer");
+        let mut response = next.run(request).await;
+        response.get_headers().set("X-Processed", "true");
+        response
+    }
+
+    async fn block_admin(request: Request, next: Next) -> Response {
+        if request.path.starts_with("/admin") {
+            return HttpResponse::forbidden().body("Forbidden");
+        }
+        next.run(request).await
+    }
+
+    #[tokio::test]
+    async fn test_middleware_chain() {
+        let middlewares: Middlewares = vec![Arc::new(trace), Arc::new(block_admin)];
+        let next = Next::new(Arc::new(middlewares), None);
+
+        let view: View = |request| {
+            Box::pin(async move {
+                let trace = request.headers.value("X-Trace").unwrap_or_default();
+                let response: Box<dyn AbstractResponse> = HttpResponse::ok().body(trace);
+                response
+            })
+        };
+
+        let users_request = request("/users", Headers::new(), b"").await;
+        let mut response = next.clone().with_view(Some(view)).run(users_request).await;
+        assert_eq!(200, response.status().0);
+        assert_eq!(b"outer".to_vec(), *response.get_body());
+        assert_eq!(
+            Some("true".to_string()),
+            response.get_headers().value("X-Processed")
+        );
+
+        let admin_request = request("/admin", Headers::new(), b"").await;
+        let mut response = next.with_view(Some(view)).run(admin_request).await;
+        assert_eq!(403, response.status().0);
+        assert_eq!(
+            Some("true".to_string()),
+            response.get_headers().value("X-Processed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_when_tagged() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let response: Response = HttpResponse::ok().body("OK");
+                response
+            })
+        };
+
+        let paths = Scope::new()
+            .tag("public")
+            .metadata("section", "docs")
+            .urls(vec![Path::new("/about", view).metadata("section", "about")])
+            .into_paths();
+        let metadata = paths[0].route_metadata();
+        assert!(metadata.has_tag("public"));
+        assert_eq!(Some("about"), metadata.get("section"));
+
+        let mut server = Server::bind("127.0.0.1:0");
+        server
+            .middleware(When::not_tagged("public", block_admin))
+            .mount("/docs", Scope::new().tag("public").path("/admin", view))
+            .urls(vec![
+                Path::new("/admin", view),
+                Path::new("/admin/public", view).tag("public"),
+            ]);
+
+        let client = TestServer::new(server).client();
+        assert_eq!(403, client.get("/admin").await.status().0);
+        assert_eq!(200, client.get("/admin/public").await.status().0);
+        assert_eq!(200, client.get("/docs/admin").await.status().0);
+        // Requests without route still run the middleware.
+        assert_eq!(403, client.get("/admin/missing").await.status().0);
+    }
+}
@@ -1,15 +1,50 @@
 pub mod request;
+pub mod access_log;
+pub mod auth;
+pub mod cache;
+pub mod client;
+pub mod clock;
 pub mod cookie;
+pub mod concurrency;
+pub mod cors;
+pub mod decompression;
+pub mod etag;
+pub mod flash;
+pub mod health;
+pub mod ratelimit;
+pub mod recovery;
 pub mod session;
 pub mod path;
+pub mod profiling;
+pub mod router;
+pub mod scheduler;
+pub mod extract;
 pub mod server;
 pub mod response;
 pub mod parser;
 pub mod stream;
+pub mod telemetry;
+pub mod tenancy;
+pub mod transfer;
+pub mod timeout;
+#[cfg(feature = "tower")]
+pub mod tower;
 pub mod logging;
+pub mod metrics;
+pub mod openapi;
+pub mod pagination;
 pub mod middleware;
 pub mod headers;
+pub mod html;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "i18n")]
+pub mod i18n;
+#[cfg(feature = "http")]
+pub mod interop;
+pub mod longpoll;
 pub mod forms;
 
+pub mod testing;
 pub mod websocket;
 pub mod shortcuts;
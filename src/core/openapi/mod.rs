@@ -0,0 +1,490 @@
+use std::sync::Arc;
+
+use serde_json::{json, Map, Value};
+
+use crate::core::html::escape_html;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::path::{self, Path};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HtmlResponse, HttpResponse, Response};
+use crate::forms::fields::FieldSchema;
+use crate::forms::FormValidator;
+
+///
+/// Documentation of a route in the OpenAPI document. Attached to the route with
+/// `Path::openapi`.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    form_fields: Vec<FieldSchema>,
+    query_fields: Vec<FieldSchema>,
+    responses: Vec<(u16, String)>,
+    deprecated: bool,
+    hidden: bool,
+}
+
+impl Operation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn summary<S: AsRef<str>>(mut self, summary: S) -> Self {
+        self.summary = Some(summary.as_ref().to_string());
+        self
+    }
+
+    pub fn description<S: AsRef<str>>(mut self, description: S) -> Self {
+        self.description = Some(description.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Groups the operation under the tag. Can be called multiple times.
+    ///
+    pub fn tag<S: AsRef<str>>(mut self, tag: S) -> Self {
+        self.tags.push(tag.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Documents the request body with the fields of the form. Forms with file fields are
+    /// documented as `multipart/form-data`, otherwise as `application/x-www-form-urlencoded`.
+    ///
+    pub fn form<F: FormValidator>(mut self) -> Self {
+        self.form_fields = form_schema::<F>();
+        self
+    }
+
+    ///
+    /// Documents the query params with the fields of the form.
+    ///
+    pub fn query<F: FormValidator>(mut self) -> Self {
+        self.query_fields = form_schema::<F>();
+        self
+    }
+
+    ///
+    /// Documents the response with the status code. `200 OK` is documented if no response is
+    /// added.
+    ///
+    pub fn response<S: AsRef<str>>(mut self, status_code: u16, description: S) -> Self {
+        self.responses
+            .push((status_code, description.as_ref().to_string()));
+        self
+    }
+
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self
+    }
+
+    ///
+    /// Excludes the route from the document.
+    ///
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+}
+
+fn form_schema<F: FormValidator>() -> Vec<FieldSchema> {
+    F::new()
+        .form_fields()
+        .iter()
+        .filter_map(|field| field.schema())
+        .collect()
+}
+
+///
+/// Generates the OpenAPI 3 document from the registered routes and serves it as JSON. Path
+/// params are documented from the route with their constraints and the request bodies, query
+/// params and responses from the [`Operation`] attached to the route.
+///
+/// Document is generated once when the server starts, so it always matches the routes. The
+/// endpoints are served after the other middlewares, so they can be protected with the
+/// authentication middleware.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::openapi::OpenApi;
+/// use racoon::core::server::Server;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.openapi(
+///     OpenApi::new("Blog API", "1.0.0")
+///         .description("Posts and comments.")
+///         .swagger_ui("/docs"),
+/// );
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+    servers: Vec<String>,
+    path: String,
+    swagger_ui: Option<String>,
+}
+
+impl OpenApi {
+    pub fn new<S: AsRef<str>, V: AsRef<str>>(title: S, version: V) -> Self {
+        Self {
+            title: title.as_ref().to_string(),
+            version: version.as_ref().to_string(),
+            description: None,
+            servers: vec![],
+            path: "/openapi.json".to_string(),
+            swagger_ui: None,
+        }
+    }
+
+    pub fn description<S: AsRef<str>>(mut self, description: S) -> Self {
+        self.description = Some(description.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Adds the base URL of the API like `https://api.example.com`.
+    ///
+    pub fn server<S: AsRef<str>>(mut self, url: S) -> Self {
+        self.servers.push(url.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Path of the JSON document. Defaults to `/openapi.json`.
+    ///
+    pub fn path<S: AsRef<str>>(mut self, path: S) -> Self {
+        self.path = path.as_ref().to_string();
+        self
+    }
+
+    ///
+    /// Serves Swagger UI for the document at the path. The UI is loaded from the CDN.
+    ///
+    pub fn swagger_ui<S: AsRef<str>>(mut self, path: S) -> Self {
+        self.swagger_ui = Some(path.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Returns the OpenAPI document of the paths.
+    ///
+    pub fn document(&self, paths: &[Path]) -> Value {
+        let mut info = json!({"title": self.title, "version": self.version});
+        if let Some(description) = &self.description {
+            info["description"] = description.clone().into();
+        }
+
+        let mut document_paths = Map::new();
+        for path in paths {
+            let operation = path.operation().cloned().unwrap_or_default();
+            if path.is_redirect() || operation.hidden {
+                continue;
+            }
+
+            let (template, parameters) = path_parameters(path);
+            let operation = operation_object(&operation, parameters);
+
+            let methods = if path.allowed_methods().is_empty() {
+                vec!["get".to_string()]
+            } else {
+                path.allowed_methods()
+                    .iter()
+                    .map(|method| method.to_lowercase())
+                    .filter(|method| method != "head" && method != "options")
+                    .collect()
+            };
+
+            let item = document_paths
+                .entry(template)
+                .or_insert_with(|| Value::Object(Map::new()));
+            for method in methods {
+                item[method] = operation.clone();
+            }
+        }
+
+        let mut document = json!({
+            "openapi": "3.0.3",
+            "info": info,
+            "paths": document_paths,
+        });
+
+        if !self.servers.is_empty() {
+            let servers: Vec<Value> = self.servers.iter().map(|url| json!({"url": url})).collect();
+            document["servers"] = servers.into();
+        }
+        document
+    }
+
+    pub(crate) fn docs(&self, paths: &[Path]) -> OpenApiDocs {
+        OpenApiDocs {
+            document: Arc::new(self.document(paths).to_string()),
+            openapi: self.clone(),
+        }
+    }
+
+    fn swagger_ui_page(&self) -> String {
+        format!(
+            r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>
+window.onload = () => SwaggerUIBundle({{url: {url}, dom_id: "#swagger-ui"}});
+</script>
+</body>
+</html>"##,
+            title = escape_html(&self.title),
+            url = Value::from(self.path.as_str()),
+        )
+    }
+}
+
+///
+/// Converts the route pattern to the OpenAPI path template and documents its params. Catch-all
+/// params are documented as a single param since OpenAPI does not support them.
+///
+fn path_parameters(path: &Path) -> (String, Vec<Value>) {
+    let mut template = String::new();
+    let mut parameters = vec![];
+    let mut rest = path.pattern();
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let name = rest[start + 1..end].trim_start_matches('*');
+        template.push_str(&rest[..start]);
+        template.push_str(&format!("{{{}}}", name));
+
+        let schema = path
+            .constraints()
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, regex)| constraint_schema(regex.as_str()))
+            .unwrap_or_else(|| json!({"type": "string"}));
+
+        parameters.push(json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": schema,
+        }));
+        rest = &rest[end + 1..];
+    }
+
+    template.push_str(rest);
+    (template, parameters)
+}
+
+fn constraint_schema(regex: &str) -> Value {
+    let is_constraint =
+        |constraint: &str| regex == format!("^(?:{})$", path::constraint_regex(constraint));
+
+    if is_constraint("int") {
+        json!({"type": "integer"})
+    } else if is_constraint("uint") {
+        json!({"type": "integer", "minimum": 0})
+    } else if is_constraint("uuid") {
+        json!({"type": "string", "format": "uuid"})
+    } else {
+        json!({"type": "string", "pattern": regex})
+    }
+}
+
+fn object_schema(fields: &[FieldSchema]) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+
+    for field in fields {
+        properties.insert(field.name.clone(), field.schema.clone());
+        if field.required {
+            required.push(Value::from(field.name.as_str()));
+        }
+    }
+
+    let mut schema = json!({"type": "object", "properties": properties});
+    if !required.is_empty() {
+        schema["required"] = required.into();
+    }
+    schema
+}
+
+fn operation_object(operation: &Operation, mut parameters: Vec<Value>) -> Value {
+    let mut object = Map::new();
+
+    if let Some(summary) = &operation.summary {
+        object.insert("summary".to_string(), summary.as_str().into());
+    }
+    if let Some(description) = &operation.description {
+        object.insert("description".to_string(), description.as_str().into());
+    }
+    if !operation.tags.is_empty() {
+        object.insert("tags".to_string(), operation.tags.clone().into());
+    }
+    if operation.deprecated {
+        object.insert("deprecated".to_string(), true.into());
+    }
+
+    for field in &operation.query_fields {
+        parameters.push(json!({
+            "name": field.name,
+            "in": "query",
+            "required": field.required,
+            "schema": field.schema,
+        }));
+    }
+    if !parameters.is_empty() {
+        object.insert("parameters".to_string(), parameters.into());
+    }
+
+    if !operation.form_fields.is_empty() {
+        let content_type = if operation.form_fields.iter().any(|field| field.is_file) {
+            "multipart/form-data"
+        } else {
+            "application/x-www-form-urlencoded"
+        };
+
+        let schema = object_schema(&operation.form_fields);
+        object.insert(
+            "requestBody".to_string(),
+            json!({"content": {content_type: {"schema": schema}}}),
+        );
+    }
+
+    let mut responses = Map::new();
+    for (status_code, description) in &operation.responses {
+        responses.insert(status_code.to_string(), json!({"description": description}));
+    }
+    if responses.is_empty() {
+        responses.insert("200".to_string(), json!({"description": "OK"}));
+    }
+    object.insert("responses".to_string(), responses.into());
+
+    Value::Object(object)
+}
+
+///
+/// Middleware serving the generated document and the Swagger UI.
+///
+#[derive(Clone)]
+pub(crate) struct OpenApiDocs {
+    document: Arc<String>,
+    openapi: OpenApi,
+}
+
+impl AbstractMiddleware for OpenApiDocs {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let docs = self.clone();
+
+        Box::new(Box::pin(async move {
+            let is_get = request.method == "GET" || request.method == "HEAD";
+            let path = request.path.split('?').next().unwrap_or_default();
+
+            if is_get && path == docs.openapi.path {
+                let response: Response = HttpResponse::ok()
+                    .content_type("application/json")
+                    .body(docs.document.as_str());
+                return response;
+            }
+
+            if is_get && docs.openapi.swagger_ui.as_deref() == Some(path) {
+                let response: Response = HtmlResponse::ok().body(docs.openapi.swagger_ui_page());
+                return response;
+            }
+
+            next.run(request).await
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::path::{Path, View};
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+    use crate::forms::fields::file_field::{FileField, UploadedFile};
+    use crate::forms::fields::input_field::InputField;
+    use crate::forms::{FormFields, FormValidator};
+
+    use super::{OpenApi, Operation};
+
+    struct UploadForm {
+        title: InputField<String>,
+        tags: InputField<Option<Vec<String>>>,
+        file: FileField<UploadedFile>,
+    }
+
+    impl FormValidator for UploadForm {
+        fn new() -> Self {
+            Self {
+                title: InputField::new("title").max_length(100),
+                tags: InputField::new("tags"),
+                file: FileField::new("file"),
+            }
+        }
+
+        fn form_fields(&mut self) -> FormFields {
+            vec![
+                Box::new(self.title.clone()),
+                Box::new(self.tags.clone()),
+                Box::new(self.file.clone()),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_openapi_document() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let response: Response = HttpResponse::ok().empty();
+                response
+            })
+        };
+
+        let paths = vec![
+            Path::get("/posts/{id:int}/files/{path:*}", view),
+            Path::post("/upload", view).openapi(
+                Operation::new()
+                    .summary("Upload file")
+                    .form::<UploadForm>()
+                    .response(201, "Uploaded"),
+            ),
+            Path::get("/internal", view).openapi(Operation::new().hidden()),
+            Path::redirect("/old", "/posts", 301),
+        ];
+
+        let document = OpenApi::new("API", "1.0").document(&paths);
+        let paths = document["paths"].as_object().unwrap();
+        assert_eq!(2, paths.len());
+
+        let parameters = &paths["/posts/{id}/files/{path}"]["get"]["parameters"];
+        assert_eq!("integer", parameters[0]["schema"]["type"]);
+        assert_eq!("path", parameters[1]["name"]);
+
+        let upload = &paths["/upload"]["post"];
+        assert_eq!("Upload file", upload["summary"]);
+        assert_eq!("Uploaded", upload["responses"]["201"]["description"]);
+
+        let schema = &upload["requestBody"]["content"]["multipart/form-data"]["schema"];
+        assert_eq!(100, schema["properties"]["title"]["maxLength"]);
+        assert_eq!("array", schema["properties"]["tags"]["type"]);
+        assert_eq!("binary", schema["properties"]["file"]["format"]);
+        assert_eq!(serde_json::json!(["title", "file"]), schema["required"]);
+    }
+}
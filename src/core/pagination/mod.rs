@@ -0,0 +1,353 @@
+//!
+//! Extractors parsing the pagination query params with the limits and building the `Link`
+//! header of the paginated responses.
+//!
+//! [`Pagination`] uses the page number with the `page` and `per_page` params. [`CursorPagination`]
+//! uses the opaque cursor of the next page with the `cursor` and `per_page` params. Limits are
+//! read from [`PaginationConfig`] registered with `Server::with_state`, otherwise the defaults
+//! are used. Invalid params are rejected with `400 Bad Request`.
+//!
+//! # Examples
+//!
+//! ```
+//! use racoon::core::headers::HeaderValue;
+//! use racoon::core::pagination::{Pagination, PaginationConfig};
+//! use racoon::core::request::Request;
+//! use racoon::core::response::status::ResponseStatus;
+//! use racoon::core::response::{AbstractResponse, HttpResponse, Response};
+//! use racoon::core::server::Server;
+//!
+//! async fn users(request: Request, pagination: Pagination) -> Response {
+//!     let total = 95;
+//!     // SELECT * FROM users LIMIT pagination.limit() OFFSET pagination.offset()
+//!
+//!     let mut response = HttpResponse::ok().body("[]");
+//!     response
+//!         .get_headers()
+//!         .set("Link", pagination.link_header(&request, Some(total)));
+//!     response
+//! }
+//!
+//! let mut server = Server::bind("127.0.0.1:8080");
+//! server.with_state(PaginationConfig::new().default_per_page(25).max_per_page(50));
+//! ```
+//!
+
+use crate::core::extract::{bad_request, ExtractResult, FromRequest};
+use crate::core::parser::path::path_and_raw_query;
+use crate::core::request::{QueryParams, Request};
+
+///
+/// Limits of the page size. Register with `Server::with_state` to override the defaults of 20
+/// and 100 items.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationConfig {
+    default_per_page: u64,
+    max_per_page: u64,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaginationConfig {
+    pub fn new() -> Self {
+        Self {
+            default_per_page: 20,
+            max_per_page: 100,
+        }
+    }
+
+    ///
+    /// Page size used if the `per_page` param is missing.
+    ///
+    pub fn default_per_page(mut self, default_per_page: u64) -> Self {
+        self.default_per_page = default_per_page.max(1);
+        self
+    }
+
+    pub fn max_per_page(mut self, max_per_page: u64) -> Self {
+        self.max_per_page = max_per_page.max(1);
+        self
+    }
+
+    fn of(request: &Request) -> Self {
+        request
+            .state::<PaginationConfig>()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Parses the page size. The default is capped by the max so the config is always valid.
+    ///
+    fn per_page(&self, query_params: &QueryParams) -> Result<u64, String> {
+        match first_param(query_params, "per_page") {
+            Some(value) => match value.parse::<u64>() {
+                Ok(per_page) if (1..=self.max_per_page).contains(&per_page) => Ok(per_page),
+                _ => Err(format!(
+                    "per_page must be a number between 1 and {}.",
+                    self.max_per_page
+                )),
+            },
+            None => Ok(self.default_per_page.min(self.max_per_page)),
+        }
+    }
+}
+
+fn first_param<'a>(query_params: &'a QueryParams, name: &str) -> Option<&'a str> {
+    query_params
+        .get(name)
+        .and_then(|values| values.first())
+        .map(String::as_str)
+}
+
+///
+/// Builds the URL of the request path with the params replaced. Other params of the request are
+/// kept in the sorted order.
+///
+fn page_url(request: &Request, replaced: &[(&str, String)]) -> String {
+    let (path, _) = path_and_raw_query(&request.path);
+
+    let mut names: Vec<&String> = request.query_params.keys().collect();
+    names.sort();
+
+    let mut params: Vec<(&str, &str)> = vec![];
+    for name in names {
+        if replaced
+            .iter()
+            .any(|(replaced_name, _)| name == replaced_name)
+        {
+            continue;
+        }
+        for value in &request.query_params[name] {
+            params.push((name, value));
+        }
+    }
+    for (name, value) in replaced {
+        params.push((name, value));
+    }
+
+    let query = serde_urlencoded::to_string(&params).unwrap_or_default();
+    format!("{}?{}", path, query)
+}
+
+fn link(url: String, rel: &str) -> String {
+    format!("<{}>; rel=\"{}\"", url, rel)
+}
+
+///
+/// Page number pagination parsed from the `page` and `per_page` params. Pages start from 1.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: u64,
+    pub per_page: u64,
+}
+
+impl Pagination {
+    pub fn from_query(
+        query_params: &QueryParams,
+        config: &PaginationConfig,
+    ) -> Result<Self, String> {
+        let page = match first_param(query_params, "page") {
+            Some(value) => match value.parse::<u64>() {
+                Ok(page) if page >= 1 => page,
+                _ => return Err("page must be a number greater than 0.".to_string()),
+            },
+            None => 1,
+        };
+
+        Ok(Self {
+            page,
+            per_page: config.per_page(query_params)?,
+        })
+    }
+
+    ///
+    /// Number of the items to skip.
+    ///
+    pub fn offset(&self) -> u64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.per_page
+    }
+
+    ///
+    /// Number of the pages for the total number of items. At least one page is returned, so the
+    /// empty list has a page.
+    ///
+    pub fn total_pages(&self, total: u64) -> u64 {
+        total.div_ceil(self.per_page).max(1)
+    }
+
+    ///
+    /// Value of the `Link` header with the `first`, `prev`, `next` and `last` pages. Without the
+    /// total number of items, `next` page is always included and `last` page is omitted.
+    ///
+    /// More information: <https://datatracker.ietf.org/doc/html/rfc8288>
+    ///
+    pub fn link_header(&self, request: &Request, total: Option<u64>) -> String {
+        let per_page = self.per_page.to_string();
+        let url = |page: u64| {
+            page_url(
+                request,
+                &[("page", page.to_string()), ("per_page", per_page.clone())],
+            )
+        };
+
+        let mut links = vec![link(url(1), "first")];
+        if self.page > 1 {
+            links.push(link(url(self.page - 1), "prev"));
+        }
+
+        match total {
+            Some(total) => {
+                let last_page = self.total_pages(total);
+                if self.page < last_page {
+                    links.push(link(url(self.page + 1), "next"));
+                }
+                links.push(link(url(last_page), "last"));
+            }
+            None => {
+                // Max page has no next page.
+                if let Some(next_page) = self.page.checked_add(1) {
+                    links.push(link(url(next_page), "next"));
+                }
+            }
+        }
+        links.join(", ")
+    }
+}
+
+impl FromRequest for Pagination {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            let config = PaginationConfig::of(&request);
+            Pagination::from_query(&request.query_params, &config).map_err(bad_request)
+        }))
+    }
+}
+
+///
+/// Cursor pagination parsed from the `cursor` and `per_page` params. Cursor is the opaque value
+/// such as the last seen ID returned by the previous page, and is missing for the first page.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorPagination {
+    pub cursor: Option<String>,
+    pub per_page: u64,
+}
+
+impl CursorPagination {
+    pub fn from_query(
+        query_params: &QueryParams,
+        config: &PaginationConfig,
+    ) -> Result<Self, String> {
+        let cursor = first_param(query_params, "cursor")
+            .filter(|cursor| !cursor.is_empty())
+            .map(str::to_string);
+
+        Ok(Self {
+            cursor,
+            per_page: config.per_page(query_params)?,
+        })
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.per_page
+    }
+
+    ///
+    /// Value of the `Link` header with the `next` page, or `None` for the last page.
+    ///
+    pub fn link_header(&self, request: &Request, next_cursor: Option<&str>) -> Option<String> {
+        let next_cursor = next_cursor?;
+        let url = page_url(
+            request,
+            &[
+                ("cursor", next_cursor.to_string()),
+                ("per_page", self.per_page.to_string()),
+            ],
+        );
+        Some(link(url, "next"))
+    }
+}
+
+impl FromRequest for CursorPagination {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            let config = PaginationConfig::of(&request);
+            CursorPagination::from_query(&request.query_params, &config).map_err(bad_request)
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::extract::tests::request as test_request;
+    use crate::core::extract::FromRequest;
+    use crate::core::headers::Headers;
+
+    use super::{CursorPagination, Pagination, PaginationConfig};
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let config = PaginationConfig::new().max_per_page(50);
+
+        let request =
+            test_request("/users?sort=name&page=3&per_page=10", Headers::new(), b"").await;
+        let pagination = Pagination::from_query(&request.query_params, &config).unwrap();
+        assert_eq!(20, pagination.offset());
+        assert_eq!(10, pagination.limit());
+        assert_eq!(
+            "</users?sort=name&page=1&per_page=10>; rel=\"first\", \
+            </users?sort=name&page=2&per_page=10>; rel=\"prev\", \
+            </users?sort=name&page=4&per_page=10>; rel=\"next\", \
+            </users?sort=name&page=5&per_page=10>; rel=\"last\"",
+            pagination.link_header(&request, Some(45))
+        );
+
+        let request = test_request("/users?page=18446744073709551615", Headers::new(), b"").await;
+        let pagination = Pagination::from_query(&request.query_params, &config).unwrap();
+        assert_eq!(
+            "</users?page=1&per_page=20>; rel=\"first\", \
+            </users?page=18446744073709551614&per_page=20>; rel=\"prev\"",
+            pagination.link_header(&request, None)
+        );
+
+        for raw_path in ["/users?page=0", "/users?page=a", "/users?per_page=51"] {
+            let request = test_request(raw_path, Headers::new(), b"").await;
+            assert!(Pagination::from_query(&request.query_params, &config).is_err());
+        }
+
+        // Defaults are used without the params and the config.
+        let request = test_request("/users", Headers::new(), b"").await;
+        let pagination = Pagination::from_request(request).await.ok();
+        assert_eq!(
+            Some(Pagination {
+                page: 1,
+                per_page: 20
+            }),
+            pagination
+        );
+
+        let request = test_request("/users?per_page=500", Headers::new(), b"").await;
+        let response = Pagination::from_request(request).await.unwrap_err();
+        assert_eq!(400, response.status().0);
+
+        let request = test_request("/events?cursor=abc&per_page=5", Headers::new(), b"").await;
+        let pagination = CursorPagination::from_query(&request.query_params, &config).unwrap();
+        assert_eq!(Some("abc".to_string()), pagination.cursor);
+        assert_eq!(
+            Some("</events?cursor=a+b%26c&per_page=5>; rel=\"next\"".to_string()),
+            pagination.link_header(&request, Some("a b&c"))
+        );
+        assert_eq!(None, pagination.link_header(&request, None));
+    }
+}
@@ -0,0 +1,205 @@
+///
+/// Size of the chunk size line or trailer line after which the body is rejected.
+///
+pub const MAX_LINE_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+///
+/// Parses the hexadecimal chunk size before the optional chunk extensions.
+///
+fn parse_chunk_size(line: &[u8]) -> std::io::Result<usize> {
+    let size = line.split(|byte| *byte == b';').next().unwrap_or_default();
+    let size = size.trim_ascii();
+
+    if size.is_empty() || !size.iter().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(invalid("Invalid chunk size."));
+    }
+
+    // Hex digits are valid UTF-8. Sizes overflowing usize are rejected.
+    let size = String::from_utf8_lossy(size);
+    usize::from_str_radix(&size, 16).map_err(|_| invalid("Chunk size is too large."))
+}
+
+///
+/// Incremental decoder of the `Transfer-Encoding: chunked` body. Bytes can be fed as they are
+/// received and the decoded data is returned as soon as it is available, so chunks split across
+/// reads are handled without buffering the whole body.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::parser::chunked::ChunkedDecoder;
+///
+/// let mut decoder = ChunkedDecoder::new();
+/// let mut body = decoder.feed(b"5\r\nHel").unwrap();
+/// body.extend(decoder.feed(b"lo\r\n0\r\n\r\nNEXT").unwrap());
+///
+/// assert_eq!(b"Hello".to_vec(), body);
+/// assert!(decoder.is_finished());
+/// assert_eq!(b"NEXT", decoder.remaining());
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct ChunkedDecoder {
+    state: State,
+    buffer: Vec<u8>,
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkedDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: State::Size,
+            buffer: vec![],
+        }
+    }
+
+    ///
+    /// Decodes the bytes and returns the chunk data decoded from them. Incomplete size lines are
+    /// kept until the next bytes are fed. Bytes after the last chunk and trailers are kept in
+    /// `remaining`.
+    ///
+    pub fn feed(&mut self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut decoded = vec![];
+        let mut position = 0;
+
+        loop {
+            let pending = &self.buffer[position..];
+
+            match self.state {
+                State::Size | State::Trailers => {
+                    let line_end = match pending.windows(2).position(|window| window == b"\r\n") {
+                        Some(line_end) => line_end,
+                        None if pending.len() > MAX_LINE_SIZE => {
+                            return Err(invalid("Chunk line is too long."));
+                        }
+                        None => break,
+                    };
+
+                    if line_end > MAX_LINE_SIZE {
+                        return Err(invalid("Chunk line is too long."));
+                    }
+
+                    self.state = match self.state {
+                        State::Size => match parse_chunk_size(&pending[..line_end])? {
+                            0 => State::Trailers,
+                            size => State::Data(size),
+                        },
+                        // Trailer fields are ignored until the empty line.
+                        _ if line_end == 0 => State::Done,
+                        state => state,
+                    };
+                    position += line_end + 2;
+                }
+                State::Data(remaining) => {
+                    if pending.is_empty() {
+                        break;
+                    }
+
+                    let length = remaining.min(pending.len());
+                    decoded.extend_from_slice(&pending[..length]);
+                    position += length;
+
+                    self.state = if length == remaining {
+                        State::DataEnd
+                    } else {
+                        State::Data(remaining - length)
+                    };
+                }
+                State::DataEnd => {
+                    if pending.len() < 2 {
+                        break;
+                    }
+
+                    if &pending[..2] != b"\r\n" {
+                        return Err(invalid("Chunk data is not followed by CRLF."));
+                    }
+                    position += 2;
+                    self.state = State::Size;
+                }
+                State::Done => break,
+            }
+        }
+
+        self.buffer.drain(..position);
+        Ok(decoded)
+    }
+
+    ///
+    /// Returns true after the last chunk and the trailers are decoded.
+    ///
+    pub fn is_finished(&self) -> bool {
+        self.state == State::Done
+    }
+
+    ///
+    /// Bytes received after the end of the chunked body, such as the next pipelined message.
+    ///
+    pub fn remaining(&self) -> &[u8] {
+        if self.is_finished() {
+            &self.buffer
+        } else {
+            &[]
+        }
+    }
+}
+
+///
+/// Decodes the complete chunked body. Returns error if the body is invalid or incomplete.
+///
+pub fn decode_chunked(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decoder = ChunkedDecoder::new();
+    let body = decoder.feed(bytes)?;
+
+    if !decoder.is_finished() {
+        return Err(invalid("Incomplete chunked body."));
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{decode_chunked, ChunkedDecoder};
+
+    #[test]
+    fn test_chunked_decoder() {
+        let body = b"4;name=value\r\nWiki\r\n5\r\npedia\r\nE\r\n in\r\n\r\nchunks.\r\n0\r\nExpires: never\r\n\r\n";
+        assert_eq!(
+            b"Wikipedia in\r\n\r\nchunks.".to_vec(),
+            decode_chunked(body).unwrap()
+        );
+
+        // Same result when fed byte by byte.
+        let mut decoder = ChunkedDecoder::new();
+        let mut decoded = vec![];
+        for byte in body {
+            decoded.extend(decoder.feed(&[*byte]).unwrap());
+        }
+        assert!(decoder.is_finished());
+        assert_eq!(decode_chunked(body).unwrap(), decoded);
+
+        assert!(decode_chunked(b"5\r\nHello").is_err());
+        assert!(decode_chunked(b"5\r\nHelloXX0\r\n\r\n").is_err());
+        assert!(decode_chunked(b"zz\r\n").is_err());
+        assert!(decode_chunked(b"FFFFFFFFFFFFFFFFFF\r\n").is_err());
+    }
+}
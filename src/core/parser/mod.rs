@@ -1,3 +1,4 @@
+pub mod chunked;
 pub mod multipart;
 pub mod urlencoded;
 
@@ -5,11 +6,16 @@ pub mod headers {
     use std::collections::HashMap;
     use std::sync::Arc;
 
+    use tokio::time::Instant;
+
     use crate::core::headers::{Headers, HeaderValue};
     use crate::core::request::RequestError;
     use crate::core::server::RequestConstraints;
     use crate::core::stream::Stream;
 
+    /// Connection preface sent by HTTP/2 clients.
+    pub const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
     #[derive(Debug)]
     pub struct RequestHeaderResult {
         pub method: Option<String>,
@@ -29,6 +35,52 @@ pub mod headers {
         }
     }
 
+    ///
+    /// Parses the request line and headers from the bytes received so far. Returns `None` if the
+    /// head is not complete yet, else the parsed head and its size in bytes. Bytes after the head
+    /// belong to the request body.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::headers::HeaderValue;
+    /// use racoon::core::parser::headers::parse_request_head;
+    ///
+    /// let bytes = b"GET /about HTTP/1.1\r\nHost: example.com\r\n\r\nbody";
+    /// assert!(parse_request_head(&bytes[..20], 100).unwrap().is_none());
+    ///
+    /// let (request_header, head_size) = parse_request_head(bytes, 100).unwrap().unwrap();
+    /// assert_eq!(Some("/about".to_string()), request_header.raw_path);
+    /// assert_eq!(Some("example.com".to_string()), request_header.headers.value("host"));
+    /// assert_eq!(b"body", &bytes[head_size..]);
+    /// ```
+    ///
+    pub fn parse_request_head(buffer: &[u8], max_header_count: usize)
+                              -> Result<Option<(RequestHeaderResult, usize)>, RequestError> {
+        let mut headers = vec![httparse::EMPTY_HEADER; max_header_count];
+        let mut request = httparse::Request::new(&mut headers);
+
+        let head_size = match request.parse(buffer) {
+            Ok(httparse::Status::Complete(head_size)) => head_size,
+            Ok(httparse::Status::Partial) => return Ok(None),
+            Err(httparse::Error::TooManyHeaders) => return Err(RequestError::HeaderSizeExceed),
+            Err(error) => return Err(RequestError::Others(error.to_string())),
+        };
+
+        let mut headers = HashMap::with_capacity(request.headers.len());
+        request.headers.iter().for_each(|header| {
+            headers.set_multiple(header.name, header.value);
+        });
+
+        let request_header = RequestHeaderResult {
+            method: request.method.map(|method| method.to_string()),
+            http_version: request.version,
+            raw_path: request.path.map(|path| path.to_owned()),
+            headers,
+        };
+        Ok(Some((request_header, head_size)))
+    }
+
     pub async fn read_request_headers(stream: Arc<Stream>,
                                       request_constraints: Arc<RequestConstraints>)
                                       -> Result<RequestHeaderResult, RequestError> {
@@ -38,70 +90,65 @@ pub mod headers {
 
         let mut bytes_read = 0;
 
+        // Timeout is counted from the first received byte, so the idle keep-alive connections are
+        // not affected.
+        let mut deadline: Option<Instant> = None;
+
         loop {
-            let chunk = match stream.read_chunk().await {
+            let read_result = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, stream.read_chunk()).await {
+                        Ok(read_result) => read_result,
+                        Err(_) => return Err(RequestError::HeaderReadTimeout),
+                    }
+                }
+                None => stream.read_chunk().await,
+            };
+
+            let chunk = match read_result {
                 Ok(bytes) => bytes,
                 Err(error) => {
                     return Err(RequestError::Others(error.to_string()));
                 }
             };
+
+            if deadline.is_none() {
+                if let Some(timeout) = request_constraints.header_read_timeout {
+                    deadline = Some(Instant::now() + timeout);
+                }
+            }
+
             bytes_read += chunk.len();
-            buffer.extend(chunk);
+
+            // Headers usually arrive in the first chunk, which is then parsed without copying.
+            if buffer.is_empty() {
+                buffer = chunk;
+            } else {
+                buffer.extend_from_slice(&chunk);
+            }
 
             if bytes_read > max_request_header_size {
                 return Err(RequestError::HeaderSizeExceed);
             }
 
-            let mut headers = vec![httparse::EMPTY_HEADER; request_constraints.max_header_count];
-            let mut request = httparse::Request::new(&mut headers);
-            let result = request.parse(&buffer);
-
-            match result {
-                Ok(status) => {
-                    if status.is_partial() {
-                        continue;
-                    }
-
-                    let matched_position = status.unwrap();
-                    let partial_body = &buffer[matched_position..];
+            match parse_request_head(&buffer, request_constraints.max_header_count) {
+                Ok(Some((request_header, head_size))) => {
+                    let partial_body = &buffer[head_size..];
                     let _ = stream.restore_payload(partial_body).await;
-
-                    let request_method;
-                    if let Some(method) = request.method {
-                        request_method = Some(method.to_string());
-                    } else {
-                        request_method = None;
-                    }
-
-                    let http_version;
-                    if let Some(version) = request.version {
-                        http_version = Some(version);
-                    } else {
-                        http_version = None;
-                    }
-
-                    let path;
-                    if let Some(request_path) = request.path {
-                        path = Some(request_path.to_owned());
-                    } else {
-                        path = None;
-                    }
-
-                    let mut headers = HashMap::new();
-                    request.headers.iter().for_each(|header| {
-                        headers.set_multiple(header.name, header.value);
-                    });
-
-                    if status.is_complete() {
-                        return Ok(RequestHeaderResult {
-                            method: request_method,
-                            http_version,
-                            raw_path: path,
-                            headers,
-                        });
-                    }
+                    return Ok(request_header);
+                }
+                Ok(None) => continue,
+                Err(RequestError::HeaderSizeExceed) => {
+                    return Err(RequestError::HeaderSizeExceed);
                 }
                 Err(_) => {
+                    // HTTP/2 connection preface is not a valid HTTP/1 request. Bytes are restored
+                    // for the HTTP/2 connection.
+                    if buffer.starts_with(HTTP2_PREFACE) {
+                        let _ = stream.restore_payload(&buffer).await;
+                        return Err(RequestError::Http2Preface);
+                    }
+
                     // Not actual error
                     // Wait until header is not completely found
                 }
@@ -200,3 +247,45 @@ pub mod params {
         return params;
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::request::RequestError;
+    use crate::core::server::RequestConstraints;
+    use crate::core::stream::{Stream, TcpStreamWrapper, TestStreamWrapper};
+
+    use super::headers::read_request_headers;
+
+    fn request_constraints(header_read_timeout: Option<Duration>) -> Arc<RequestConstraints> {
+        Arc::new(RequestConstraints {
+            max_request_header_size: 1024,
+            max_header_count: 2,
+            header_read_timeout,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_header_limits() {
+        let test_data = b"GET / HTTP/1.1\r\nHost: a\r\nAccept: */*\r\nUser-Agent: test\r\n\r\n";
+        let stream: Stream = Box::new(TestStreamWrapper::new(test_data.to_vec(), 1024));
+        let result = read_request_headers(Arc::new(stream), request_constraints(None)).await;
+        assert!(matches!(result, Err(RequestError::HeaderSizeExceed)));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        // Client sends part of the headers and stops.
+        client.write_all(b"GET / HTTP/1.1\r\nHost: a\r\n").await.unwrap();
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server, 1024).unwrap());
+        let constraints = request_constraints(Some(Duration::from_millis(50)));
+        let result = read_request_headers(Arc::new(stream), constraints).await;
+        assert!(matches!(result, Err(RequestError::HeaderReadTimeout)));
+    }
+}
@@ -7,8 +7,9 @@ use tokio::io::AsyncWriteExt;
 use crate::core::headers;
 use crate::core::headers::{HeaderValue, Headers};
 
-use crate::core::stream::Stream;
+use crate::core::stream::{Stream, TestStreamWrapper};
 
+use crate::core::forms::uploads::{self, TempUpload};
 use crate::core::forms::{FileField, Files, FormConstraints, FormData, FormFieldError};
 
 #[derive(Debug)]
@@ -18,6 +19,8 @@ pub struct FormPart {
     pub filename: Option<String>,
     pub content_type: Option<String>,
     pub file: Option<TempFile>,
+    /// Deletes the temp file if the part is dropped before the file is used.
+    pub(crate) upload: Option<TempUpload>,
 }
 
 pub struct MultipartParser {
@@ -94,7 +97,9 @@ impl MultipartParser {
                     ));
                 }
 
-                let temp_file = FileField::from(filename, named_temp_file);
+                let mut temp_file =
+                    FileField::from(filename, named_temp_file).with_upload(form_part.upload);
+                temp_file.content_type = form_part.content_type;
                 if let Some(files) = files.get_mut(&field_name) {
                     files.push(temp_file);
                 } else {
@@ -219,6 +224,21 @@ impl MultipartParser {
         }
     }
 
+    ///
+    /// Parses the multipart body from the bytes instead of the connection stream. The bytes are
+    /// read in chunks of `chunk_size`, so the parts split across reads are parsed the same way as
+    /// the bytes received from the network.
+    ///
+    pub async fn parse_bytes(
+        bytes: &[u8],
+        chunk_size: usize,
+        form_constraints: Arc<FormConstraints>,
+        headers: &Headers,
+    ) -> Result<(FormData, Files), FormFieldError> {
+        let stream: Stream = Box::new(TestStreamWrapper::new(bytes.to_vec(), chunk_size.max(1)));
+        Self::parse(Arc::new(stream), form_constraints, headers).await
+    }
+
     pub async fn parse_file(&mut self, form_part: &mut FormPart) -> Result<bool, FormFieldError> {
         let form_constraints = self.form_constraints.clone();
         let field_name;
@@ -240,13 +260,17 @@ impl MultipartParser {
         let value_terminator = format!("\r\n--{}", self.boundary);
         let value_terminator_bytes = value_terminator.as_bytes();
 
-        let mut temp_file = match TempFile::new().await {
-            Ok(file) => match file.open_rw().await {
-                Ok(result) => result,
-                Err(error) => {
-                    return Err(FormFieldError::Others(None, error.to_string(), true));
+        // Temp file is deleted with the form part if the parsing fails.
+        let mut temp_file = match uploads::create_temp_file().await {
+            Ok((file, upload)) => {
+                form_part.upload = Some(upload);
+                match file.open_rw().await {
+                    Ok(result) => result,
+                    Err(error) => {
+                        return Err(FormFieldError::Others(None, error.to_string(), true));
+                    }
                 }
-            },
+            }
             Err(error) => {
                 return Err(FormFieldError::Others(None, error.to_string(), true));
             }
@@ -433,6 +457,7 @@ pub fn parse_form_part_header(header_bytes: &[u8]) -> Result<FormPart, FormField
         content_type: None,
         file: None,
         value: None,
+        upload: None,
     };
 
     loop {
@@ -570,8 +595,34 @@ pub mod tests {
         let file = file_field.unwrap();
         let file_path = &file.temp_path;
         assert_eq!("example.txt".to_string(), file.name);
+        assert_eq!(Some("text/plain".to_string()), file.content_type);
 
         let file_content = tokio::fs::read_to_string(&file_path).await.unwrap();
         assert_eq!("Hello World".to_string(), file_content);
     }
+
+    #[tokio::test]
+    async fn test_multipart_parse_bytes() {
+        let test_data = b"--boundary123\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nJohn\r\n--boundary123\r\nContent-Disposition: form-data; name=\"location\"\r\n\r\nktm\r\n--boundary123--\r\n";
+
+        let mut headers = Headers::new();
+        headers.set("Content-Type", "multipart/form-data; boundary=boundary123");
+        headers.set("Content-Length", test_data.len().to_string());
+
+        // Small chunks split the boundaries and part headers across reads.
+        for chunk_size in [1, 3, 7, 1024] {
+            let form_constraints = Arc::new(FormConstraints::default());
+            let (form_data, _) =
+                MultipartParser::parse_bytes(test_data, chunk_size, form_constraints, &headers)
+                    .await
+                    .unwrap();
+            assert_eq!(Some(&"John".to_string()), form_data.value("name"));
+            assert_eq!(Some(&"ktm".to_string()), form_data.value("location"));
+        }
+
+        let form_constraints = Arc::new(FormConstraints::default());
+        let result =
+            MultipartParser::parse_bytes(b"--other\r\n", 1024, form_constraints, &headers).await;
+        assert!(result.is_err());
+    }
 }
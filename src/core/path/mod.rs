@@ -1,27 +1,598 @@
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use regex::Regex;
+use tokio::sync::Mutex;
+
+use crate::core::middleware::{self, AbstractMiddleware, Guard, Middleware, Middlewares};
+use crate::core::openapi::Operation;
+use crate::core::router::{RouteMetadata, TrailingSlash};
 
 use crate::core::request::Request;
 use crate::core::response::status::ResponseStatus;
 use crate::core::response::{AbstractResponse, HttpResponse, Response};
 use crate::core::shortcuts::SingleText;
 
-use super::headers::HeaderValue;
+use super::headers::{HeaderValue, Headers};
 
 pub type View = fn(Request) -> Pin<Box<dyn Future<Output = Box<dyn AbstractResponse>> + Send>>;
 
 pub struct Path {
     pub name: String,
     pub view: View,
+    pattern: String,
+    constraints: Vec<(String, Regex)>,
+    middlewares: Arc<Middlewares>,
+    guards: Arc<Vec<Guard>>,
+    methods: Vec<String>,
+    trailing_slash: Option<TrailingSlash>,
+    host: Option<HostPattern>,
+    timeout: Option<Duration>,
+    redirect: Option<(String, u16)>,
+    max_body_size: Option<usize>,
+    operation: Option<Operation>,
+    metadata: RouteMetadata,
 }
 
 impl Path {
+    ///
+    /// Creates route for the view.
+    ///
+    /// Path parameters are written as `{name}`. Parameters can be restricted with regex like
+    /// `{id:(\d+)}` or with predefined types `int`, `uint`, `uuid`, `slug` and `alpha` like
+    /// `{id:int}`. Remaining path can be captured with `{path:*}`. If the constraint does not
+    /// match, the request is handled as `404 Not Found`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::Path;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::view;
+    ///
+    /// async fn post_detail(request: Request) -> Response {
+    ///     HttpResponse::ok().body("Post")
+    /// }
+    ///
+    /// let paths = vec![
+    ///     Path::new("/posts/{id:(\\d+)}", view!(post_detail)),
+    ///     Path::new("/files/{path:*}", view!(post_detail)),
+    /// ];
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constraint is not a valid regex.
+    ///
     pub fn new<S: AsRef<str>>(name: S, view: View) -> Self {
+        let name = name.as_ref();
+        let (pattern, constraints) = match parse_pattern(name) {
+            Ok(parsed) => parsed,
+            Err(error) => panic!("Invalid path \"{}\" pattern. Error: {}", name, error),
+        };
+
         Self {
-            name: name.as_ref().to_string(),
+            name: name.to_string(),
             view,
+            pattern,
+            constraints,
+            middlewares: Arc::new(vec![]),
+            guards: Arc::new(vec![]),
+            methods: vec![],
+            trailing_slash: None,
+            host: None,
+            timeout: None,
+            redirect: None,
+            max_body_size: None,
+            operation: None,
+            metadata: RouteMetadata::default(),
+        }
+    }
+
+    ///
+    /// Creates route which redirects to the target without calling any view. Path params can be
+    /// used in the target like `{slug}` and the query string is kept. Supported status codes are
+    /// 301, 302, 303, 307 and 308.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::Path;
+    ///
+    /// let paths = vec![
+    ///     Path::redirect("/old-about", "/about", 301),
+    ///     Path::redirect("/blog/{slug}", "/posts/{slug}", 308),
+    ///     Path::redirect("/docs/{rest:*}", "https://docs.example.com/{rest}", 302),
+    /// ];
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the status code is not a redirect status code.
+    ///
+    pub fn redirect<S: AsRef<str>, T: AsRef<str>>(name: S, target: T, status_code: u16) -> Self {
+        if ![301, 302, 303, 307, 308].contains(&status_code) {
+            panic!(
+                "Invalid redirect status code {} for path \"{}\".",
+                status_code,
+                name.as_ref()
+            );
         }
+
+        let mut path = Self::new(name, |_| {
+            Box::pin(async move {
+                let response: Response = HttpResponse::internal_server_error().empty();
+                response
+            })
+        });
+        path.redirect = Some((target.as_ref().to_string(), status_code));
+        path
+    }
+
+    ///
+    /// Creates route which accepts only `GET` requests. `HEAD` requests are also answered with
+    /// the same headers and without the body.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::Path;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::view;
+    ///
+    /// async fn list_users(request: Request) -> Response {
+    ///     HttpResponse::ok().body("Users")
+    /// }
+    ///
+    /// async fn create_user(request: Request) -> Response {
+    ///     HttpResponse::created().body("Created")
+    /// }
+    ///
+    /// let paths = vec![
+    ///     Path::get("/users", view!(list_users)),
+    ///     Path::post("/users", view!(create_user)),
+    /// ];
+    /// ```
+    ///
+    pub fn get<S: AsRef<str>>(name: S, view: View) -> Self {
+        Self::new(name, view).methods(&["GET"])
+    }
+
+    pub fn post<S: AsRef<str>>(name: S, view: View) -> Self {
+        Self::new(name, view).methods(&["POST"])
+    }
+
+    pub fn put<S: AsRef<str>>(name: S, view: View) -> Self {
+        Self::new(name, view).methods(&["PUT"])
+    }
+
+    pub fn patch<S: AsRef<str>>(name: S, view: View) -> Self {
+        Self::new(name, view).methods(&["PATCH"])
+    }
+
+    pub fn delete<S: AsRef<str>>(name: S, view: View) -> Self {
+        Self::new(name, view).methods(&["DELETE"])
+    }
+
+    ///
+    /// Restricts the route to the given request methods. Requests with other methods receive
+    /// `405 Method Not Allowed`. By default, all the methods are allowed.
+    ///
+    pub fn methods<S: AsRef<str>>(mut self, methods: &[S]) -> Self {
+        for method in methods {
+            let method = method.as_ref().to_uppercase();
+            if !self.methods.contains(&method) {
+                self.methods.push(method);
+            }
+        }
+        self
+    }
+
+    ///
+    /// Allowed request methods. Empty if all the methods are allowed.
+    ///
+    pub fn allowed_methods(&self) -> &[String] {
+        &self.methods
+    }
+
+    ///
+    /// Overrides the server trailing slash policy for this route.
+    ///
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = Some(policy);
+        self
+    }
+
+    pub fn trailing_slash_policy(&self) -> Option<TrailingSlash> {
+        self.trailing_slash
+    }
+
+    ///
+    /// Restricts the route to requests whose `Host` header matches the pattern. Subdomains can be
+    /// captured as path params with `{name}` for a single label or `{name:*}` for one or more
+    /// labels. Port in the `Host` header is ignored and matching is case-insensitive.
+    ///
+    /// Routes with host are tried before the routes without host registered with the same path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::Path;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::core::shortcuts::SingleText;
+    /// use racoon::view;
+    ///
+    /// async fn dashboard(request: Request) -> Response {
+    ///     let tenant = request.path_params.value("tenant").cloned().unwrap_or_default();
+    ///     HttpResponse::ok().body(format!("Dashboard of {}", tenant))
+    /// }
+    ///
+    /// let paths = vec![
+    ///     Path::new("/", view!(dashboard)).host("admin.example.com"),
+    ///     Path::new("/", view!(dashboard)).host("{tenant}.example.com"),
+    /// ];
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the host pattern is invalid.
+    ///
+    pub fn host<S: AsRef<str>>(mut self, host: S) -> Self {
+        let host = host.as_ref();
+        match HostPattern::parse(host) {
+            Ok(pattern) => self.host = Some(pattern),
+            Err(error) => panic!("Invalid host \"{}\" pattern. Error: {}", host, error),
+        }
+        self
+    }
+
+    pub fn host_pattern(&self) -> Option<&str> {
+        self.host.as_ref().map(|host| host.pattern.as_str())
+    }
+
+    ///
+    /// Matches the `Host` header value against the host pattern of this route. Returns captured
+    /// subdomain params if matched. Routes without host match any host.
+    ///
+    pub fn match_host(&self, host: Option<&str>) -> Option<Vec<(String, String)>> {
+        let host_pattern = match &self.host {
+            Some(host_pattern) => host_pattern,
+            None => return Some(vec![]),
+        };
+
+        host_pattern.captures(host?)
+    }
+
+    ///
+    /// Maximum duration for handling the request including the middlewares. If exceeded, the
+    /// handler is cancelled and the client receives `504 Gateway Timeout`, which can be
+    /// customized with the error handler of the server. The connection is closed after the
+    /// timeout since the request body may be partially read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use racoon::core::path::Path;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::view;
+    ///
+    /// async fn upload(request: Request) -> Response {
+    ///     HttpResponse::ok().body("Uploaded")
+    /// }
+    ///
+    /// let paths = vec![
+    ///     Path::post("/upload", view!(upload)).timeout(Duration::from_secs(600)),
+    /// ];
+    /// ```
+    ///
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    ///
+    /// Overrides the maximum body size of the server form constraints for this route. Requests
+    /// with larger `Content-Length` receive `413 Payload Too Large` before the body is read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::Path;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::view;
+    ///
+    /// async fn login(request: Request) -> Response {
+    ///     HttpResponse::ok().body("Login")
+    /// }
+    ///
+    /// let paths = vec![
+    ///     Path::post("/login", view!(login)).max_body_size(4 * 1024),
+    /// ];
+    /// ```
+    ///
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    pub fn body_size_limit(&self) -> Option<usize> {
+        self.max_body_size
+    }
+
+    ///
+    /// Attaches the value to the route metadata readable by the middlewares. See
+    /// [`RouteMetadata`].
+    ///
+    pub fn metadata<K: AsRef<str>, V: AsRef<str>>(mut self, key: K, value: V) -> Self {
+        self.metadata
+            .insert(key.as_ref().to_string(), value.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Tags the route like `public`, so the middlewares can be skipped for the tagged routes with
+    /// [`When::not_tagged`](crate::core::middleware::When::not_tagged).
+    ///
+    pub fn tag<S: AsRef<str>>(mut self, tag: S) -> Self {
+        self.metadata.add_tag(tag.as_ref().to_string());
+        self
+    }
+
+    pub fn route_metadata(&self) -> &RouteMetadata {
+        &self.metadata
+    }
+
+    ///
+    /// Describes this route in the OpenAPI document served with `Server::openapi`. Routes
+    /// without the operation are documented only with their path params.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::openapi::Operation;
+    /// use racoon::core::path::Path;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::view;
+    ///
+    /// async fn post_detail(request: Request) -> Response {
+    ///     HttpResponse::ok().body("Post")
+    /// }
+    ///
+    /// let paths = vec![
+    ///     Path::get("/posts/{id:int}", view!(post_detail)).openapi(
+    ///         Operation::new()
+    ///             .summary("Post detail")
+    ///             .tag("posts")
+    ///             .response(404, "Post does not exist."),
+    ///     ),
+    /// ];
+    /// ```
+    ///
+    pub fn openapi(mut self, operation: Operation) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
+    pub fn operation(&self) -> Option<&Operation> {
+        self.operation.as_ref()
+    }
+
+    pub(crate) fn is_redirect(&self) -> bool {
+        self.redirect.is_some()
+    }
+
+    ///
+    /// Regex constraints of the path params.
+    ///
+    pub(crate) fn constraints(&self) -> &[(String, Regex)] {
+        &self.constraints
+    }
+
+    ///
+    /// Returns redirect location with the path params substituted and the status code if this is
+    /// a redirect route.
+    ///
+    pub fn redirect_location(&self, params: &PathParams) -> Option<(String, u16)> {
+        let (target, status_code) = self.redirect.as_ref()?;
+
+        let mut location = String::new();
+        let mut rest = target.as_str();
+
+        while let Some(start) = rest.find('{') {
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+
+            location.push_str(&rest[..start]);
+            let name = rest[start + 1..end].trim_start_matches('*');
+            let name = name.split(':').next().unwrap_or_default().trim();
+            location.push_str(params.value(name).map(|value| value.as_str()).unwrap_or(""));
+            rest = &rest[end + 1..];
+        }
+
+        location.push_str(rest);
+
+        // Params like `/evil.com` would turn the path into the protocol relative URL
+        // `//evil.com`, so leading slashes are collapsed for the path targets.
+        if target.starts_with('/') && !target.starts_with("//") {
+            location = format!("/{}", location.trim_start_matches(['/', '\\']));
+        }
+        Some((location, *status_code))
+    }
+
+    pub fn allows_method(&self, method: &str) -> bool {
+        self.methods.is_empty() || self.methods.iter().any(|allowed| allowed == method)
+    }
+
+    ///
+    /// Attaches middleware only to this route. Middlewares are called in the order they are
+    /// attached and run after the global middleware.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::{Path, View};
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::{view, wrap_view};
+    ///
+    /// async fn rate_limit(request: Request, view: Option<View>) -> Response {
+    ///     Path::resolve(request, view).await
+    /// }
+    ///
+    /// async fn login(request: Request) -> Response {
+    ///     HttpResponse::ok().body("Login")
+    /// }
+    ///
+    /// let paths = vec![
+    ///     Path::new("/login", view!(login)).wrap(wrap_view!(rate_limit)),
+    /// ];
+    /// ```
+    ///
+    pub fn wrap(mut self, middleware: Middleware) -> Self {
+        Arc::make_mut(&mut self.middlewares).push(middleware::from_view(middleware));
+        self
+    }
+
+    ///
+    /// Attaches middleware implementing [`AbstractMiddleware`] only to this route. Called in the
+    /// same order as the middlewares attached with `wrap`.
+    ///
+    pub fn middleware<M: AbstractMiddleware + 'static>(mut self, middleware: M) -> Self {
+        Arc::make_mut(&mut self.middlewares).push(Arc::new(middleware));
+        self
+    }
+
+    pub fn middlewares(&self) -> &Arc<Middlewares> {
+        &self.middlewares
+    }
+
+    ///
+    /// Attaches guard evaluated after the middlewares and before the view. Guards are evaluated
+    /// in the order they are attached and the first rejection is sent as response.
+    ///
+    pub fn guard<G>(mut self, guard: G) -> Self
+    where
+        G: Fn(&Request) -> Result<(), Response> + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.guards).push(Arc::new(guard));
+        self
+    }
+
+    pub fn guards(&self) -> &Arc<Vec<Guard>> {
+        &self.guards
+    }
+
+    ///
+    /// Returns copy of this path with the prefix prepended to the route.
+    ///
+    pub fn with_prefix<S: AsRef<str>>(&self, prefix: S) -> Self {
+        let mut path = self.clone();
+        let name = join_prefix(prefix.as_ref(), &self.name);
+
+        let (pattern, constraints) = match parse_pattern(&name) {
+            Ok(parsed) => parsed,
+            Err(error) => panic!("Invalid path \"{}\" pattern. Error: {}", name, error),
+        };
+
+        path.name = name;
+        path.pattern = pattern;
+        path.constraints = constraints;
+        path
+    }
+
+    ///
+    /// Route pattern in the router syntax with the constraints removed.
+    ///
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    ///
+    /// Name of the catch-all parameter if the route ends with `{name:*}`. Catch-all parameter
+    /// captures the remaining path without the leading slash and is empty if nothing remains.
+    ///
+    pub fn catch_all(&self) -> Option<&str> {
+        let start = self.pattern.rfind("{*")?;
+        let end = self.pattern.len().checked_sub(1)?;
+
+        if !self.pattern.ends_with('}') || start + 2 > end {
+            return None;
+        }
+        Some(&self.pattern[start + 2..end])
+    }
+
+    ///
+    /// Router pattern for the catch-all route when nothing remains after the prefix.
+    ///
+    pub(crate) fn catch_all_prefix(&self) -> Option<&str> {
+        self.catch_all()?;
+        let start = self.pattern.rfind("{*")?;
+        Some(&self.pattern[..start])
+    }
+
+    ///
+    /// Returns true if all the matched path params satisfy the constraints of this path.
+    ///
+    pub fn is_match<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(&self, params: I) -> bool {
+        if self.constraints.is_empty() {
+            return true;
+        }
+
+        for (key, value) in params {
+            let constraint = self.constraints.iter().find(|(name, _)| name == key);
+            if let Some((_, regex)) = constraint {
+                if !regex.is_match(value) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    ///
+    /// Returns true if this path is never selected for the request methods it shares with the
+    /// other path registered earlier with the same pattern. The other path shadows this path when
+    /// it matches the same host and its constraints accept every param accepted by this path.
+    ///
+    pub(crate) fn is_shadowed_by(&self, other: &Path) -> bool {
+        let overlapping_methods = other.methods.is_empty()
+            || self.methods.is_empty()
+            || self
+                .methods
+                .iter()
+                .any(|method| other.allows_method(method));
+
+        let accepts_params = other.constraints.iter().all(|(name, regex)| {
+            self.constraints
+                .iter()
+                .any(|(key, constraint)| key == name && constraint.as_str() == regex.as_str())
+        });
+
+        overlapping_methods && self.host_pattern() == other.host_pattern() && accepts_params
     }
 
     pub async fn resolve(request: Request, view: Option<View>) -> Response {
@@ -34,17 +605,37 @@ impl Path {
             response = HttpResponse::not_found().body("404 Page not found");
         }
 
-        // Adds additional headers received from request struct.
+        add_headers(&response_headers_from_request_ref, &mut response).await;
+        response
+    }
+}
 
-        let response_headers_from_request = response_headers_from_request_ref.lock().await;
-        let response_headers = response.get_headers();
+///
+/// Adds headers set on the request such as session cookies to the response.
+///
+pub(crate) async fn add_response_headers(request: &Request, response: &mut Response) {
+    add_headers(&request.response_headers, response).await;
+}
 
-        for (name, values) in response_headers_from_request.iter() {
-            for value in values {
-                response_headers.set_multiple(name, value);
-            }
+async fn add_headers(request_headers: &Mutex<Headers>, response: &mut Response) {
+    merge_headers(request_headers, response.get_headers()).await;
+}
+
+///
+/// Moves headers set on the request into the response headers. Used by the streamed responses
+/// whose head is written before the view returns.
+///
+pub(crate) async fn merge_headers(
+    request_headers: &Mutex<Headers>,
+    response_headers: &mut Headers,
+) {
+    // Headers are taken, so resolving again from the middlewares does not add them twice.
+    let response_headers_from_request = std::mem::take(&mut *request_headers.lock().await);
+
+    for (name, values) in response_headers_from_request.iter() {
+        for value in values {
+            response_headers.set_multiple(name, value);
         }
-        response
     }
 }
 
@@ -53,12 +644,513 @@ impl Clone for Path {
         Self {
             name: self.name.clone(),
             view: self.view.clone(),
+            pattern: self.pattern.clone(),
+            constraints: self.constraints.clone(),
+            middlewares: self.middlewares.clone(),
+            guards: self.guards.clone(),
+            methods: self.methods.clone(),
+            trailing_slash: self.trailing_slash,
+            host: self.host.clone(),
+            timeout: self.timeout,
+            redirect: self.redirect.clone(),
+            max_body_size: self.max_body_size,
+            operation: self.operation.clone(),
+            metadata: self.metadata.clone(),
         }
     }
 }
 
 pub type Paths = Vec<Path>;
 
+///
+/// Result of selecting the route among the paths registered with the same pattern.
+///
+pub enum RouteMatch<'a> {
+    Found(&'a Path),
+    /// Path exists but does not accept the request method. Contains the allowed methods.
+    MethodNotAllowed(Vec<String>),
+    NotFound,
+}
+
+///
+/// Selects the first path satisfying the host, parameter constraints and the request method.
+/// `HEAD` requests are handled by `GET` paths unless a path accepts `HEAD` explicitly.
+///
+pub fn select_route<'a>(
+    paths: &'a [Path],
+    method: &str,
+    host: Option<&str>,
+    params: &[(&str, &str)],
+) -> RouteMatch<'a> {
+    let mut allowed_methods: Vec<String> = vec![];
+    let mut head_fallback = None;
+
+    for path in paths {
+        if !path.is_match(params.iter().copied()) || path.match_host(host).is_none() {
+            continue;
+        }
+
+        if path.allows_method(method) {
+            return RouteMatch::Found(path);
+        }
+
+        if method == "HEAD" && head_fallback.is_none() && path.allows_method("GET") {
+            head_fallback = Some(path);
+        }
+
+        for allowed in path.allowed_methods() {
+            if !allowed_methods.contains(allowed) {
+                allowed_methods.push(allowed.to_owned());
+            }
+        }
+    }
+
+    if let Some(path) = head_fallback {
+        return RouteMatch::Found(path);
+    }
+
+    if allowed_methods.is_empty() {
+        return RouteMatch::NotFound;
+    }
+
+    let head = "HEAD".to_string();
+    if allowed_methods.iter().any(|allowed| allowed == "GET") && !allowed_methods.contains(&head) {
+        allowed_methods.push(head);
+    }
+    RouteMatch::MethodNotAllowed(allowed_methods)
+}
+
+///
+/// Group of paths which can be mounted under a prefix. Large applications can define scope per
+/// module and reusable crates can expose their routes as a scope.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::path::{Path, Scope};
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::server::Server;
+/// use racoon::view;
+///
+/// async fn users(request: Request) -> Response {
+///     HttpResponse::ok().body("Users")
+/// }
+///
+/// let users_scope = Scope::new()
+///     .path("/", view!(users))
+///     .path("/{id:int}", view!(users));
+///
+/// let api = Scope::new().mount("/users", users_scope);
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.mount("/api/v1", api);
+/// ```
+///
+#[derive(Clone, Default)]
+pub struct Scope {
+    paths: Paths,
+    middlewares: Middlewares,
+    guards: Vec<Guard>,
+    trailing_slash: Option<TrailingSlash>,
+    host: Option<String>,
+    timeout: Option<Duration>,
+    max_body_size: Option<usize>,
+    metadata: RouteMetadata,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn path<S: AsRef<str>>(mut self, name: S, view: View) -> Self {
+        self.paths.push(Path::new(name, view));
+        self
+    }
+
+    ///
+    /// Adds path accepting only the given request methods.
+    ///
+    pub fn route<S: AsRef<str>, M: AsRef<str>>(
+        mut self,
+        name: S,
+        methods: &[M],
+        view: View,
+    ) -> Self {
+        self.paths.push(Path::new(name, view).methods(methods));
+        self
+    }
+
+    pub fn get<S: AsRef<str>>(self, name: S, view: View) -> Self {
+        self.route(name, &["GET"], view)
+    }
+
+    pub fn post<S: AsRef<str>>(self, name: S, view: View) -> Self {
+        self.route(name, &["POST"], view)
+    }
+
+    pub fn put<S: AsRef<str>>(self, name: S, view: View) -> Self {
+        self.route(name, &["PUT"], view)
+    }
+
+    pub fn patch<S: AsRef<str>>(self, name: S, view: View) -> Self {
+        self.route(name, &["PATCH"], view)
+    }
+
+    pub fn delete<S: AsRef<str>>(self, name: S, view: View) -> Self {
+        self.route(name, &["DELETE"], view)
+    }
+
+    ///
+    /// Adds redirect route. See [`Path::redirect`].
+    ///
+    pub fn redirect<S: AsRef<str>, T: AsRef<str>>(
+        mut self,
+        name: S,
+        target: T,
+        status_code: u16,
+    ) -> Self {
+        self.paths.push(Path::redirect(name, target, status_code));
+        self
+    }
+
+    pub fn urls(mut self, paths: Paths) -> Self {
+        self.paths.extend(paths);
+        self
+    }
+
+    ///
+    /// Attaches middleware to all the paths of this scope including mounted scopes. Scope
+    /// middlewares run before the middlewares attached to the individual paths.
+    ///
+    pub fn wrap(mut self, middleware: Middleware) -> Self {
+        self.middlewares.push(middleware::from_view(middleware));
+        self
+    }
+
+    ///
+    /// Attaches middleware implementing [`AbstractMiddleware`] to all the paths of this scope.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::cors::Cors;
+    /// use racoon::core::path::Scope;
+    ///
+    /// let api = Scope::new().middleware(Cors::new().allow_origin("https://example.com"));
+    /// ```
+    ///
+    pub fn middleware<M: AbstractMiddleware + 'static>(mut self, middleware: M) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    ///
+    /// Attaches guard to all the paths of this scope including mounted scopes. Scope guards are
+    /// evaluated before the guards attached to the individual paths.
+    ///
+    pub fn guard<G>(mut self, guard: G) -> Self
+    where
+        G: Fn(&Request) -> Result<(), Response> + Send + Sync + 'static,
+    {
+        self.guards.push(Arc::new(guard));
+        self
+    }
+
+    ///
+    /// Trailing slash policy for the paths of this scope which do not specify their own policy.
+    ///
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = Some(policy);
+        self
+    }
+
+    ///
+    /// Restricts the paths of this scope which do not specify their own host to the host
+    /// pattern. See [`Path::host`] for the syntax.
+    ///
+    pub fn host<S: AsRef<str>>(mut self, host: S) -> Self {
+        self.host = Some(host.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Timeout for the paths of this scope which do not specify their own timeout.
+    ///
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    ///
+    /// Maximum body size for the paths of this scope which do not specify their own limit.
+    ///
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    ///
+    /// Metadata value for the paths of this scope which do not specify their own value.
+    ///
+    pub fn metadata<K: AsRef<str>, V: AsRef<str>>(mut self, key: K, value: V) -> Self {
+        self.metadata
+            .insert(key.as_ref().to_string(), value.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Tags all the paths of this scope.
+    ///
+    pub fn tag<S: AsRef<str>>(mut self, tag: S) -> Self {
+        self.metadata.add_tag(tag.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Adds all the paths of other scope under the prefix.
+    ///
+    pub fn mount<S: AsRef<str>>(mut self, prefix: S, scope: Scope) -> Self {
+        let prefix = prefix.as_ref();
+
+        for path in scope.into_paths() {
+            self.paths.push(path.with_prefix(prefix));
+        }
+        self
+    }
+
+    ///
+    /// Returns paths with the scope middlewares, guards, trailing slash policy, host, timeout and
+    /// body size limit attached.
+    ///
+    pub fn into_paths(self) -> Paths {
+        let mut paths = self.paths;
+
+        if let Some(timeout) = self.timeout {
+            for path in paths.iter_mut() {
+                path.timeout.get_or_insert(timeout);
+            }
+        }
+
+        if let Some(max_body_size) = self.max_body_size {
+            for path in paths.iter_mut() {
+                path.max_body_size.get_or_insert(max_body_size);
+            }
+        }
+
+        if !self.metadata.is_empty() {
+            for path in paths.iter_mut() {
+                path.metadata.inherit(&self.metadata);
+            }
+        }
+
+        if let Some(host) = self.host {
+            paths = paths
+                .into_iter()
+                .map(|path| {
+                    if path.host.is_some() {
+                        path
+                    } else {
+                        path.host(&host)
+                    }
+                })
+                .collect();
+        }
+
+        if let Some(policy) = self.trailing_slash {
+            for path in paths.iter_mut() {
+                path.trailing_slash.get_or_insert(policy);
+            }
+        }
+
+        if !self.middlewares.is_empty() {
+            for path in paths.iter_mut() {
+                let mut middlewares = self.middlewares.clone();
+                middlewares.extend(path.middlewares.iter().cloned());
+                path.middlewares = Arc::new(middlewares);
+            }
+        }
+
+        if !self.guards.is_empty() {
+            for path in paths.iter_mut() {
+                let mut guards = self.guards.clone();
+                guards.extend(path.guards.iter().cloned());
+                path.guards = Arc::new(guards);
+            }
+        }
+        paths
+    }
+}
+
+///
+/// Joins prefix and route avoiding duplicate or missing slash between them.
+///
+fn join_prefix(prefix: &str, name: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    let prefix = if prefix.is_empty() || prefix.starts_with('/') {
+        prefix.to_string()
+    } else {
+        format!("/{}", prefix)
+    };
+
+    if name.starts_with('/') {
+        format!("{}{}", prefix, name)
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+pub(crate) fn constraint_regex(constraint: &str) -> &str {
+    match constraint {
+        "int" => r"-?\d+",
+        "uint" => r"\d+",
+        "uuid" => r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+        "slug" => r"[a-zA-Z0-9_-]+",
+        "alpha" => r"[a-zA-Z]+",
+        _ => constraint,
+    }
+}
+
+///
+/// Converts route with constraints like `/posts/{id:(\d+)}` to the router pattern `/posts/{id}`
+/// and returns the constraints of each parameter.
+///
+fn parse_pattern(name: &str) -> Result<(String, Vec<(String, Regex)>), String> {
+    let mut pattern = String::new();
+    let mut constraints = vec![];
+    let mut chars = name.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        // Escaped braces are kept as it is.
+        if (char == '{' || char == '}') && chars.peek() == Some(&char) {
+            pattern.push(char);
+            pattern.push(chars.next().unwrap_or(char));
+            continue;
+        }
+
+        if char != '{' {
+            pattern.push(char);
+            continue;
+        }
+
+        // Reads parameter until the matching closing brace since regex may contain braces.
+        let mut depth = 1;
+        let mut parameter = String::new();
+        for char in chars.by_ref() {
+            match char {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+
+            if depth == 0 {
+                break;
+            }
+            parameter.push(char);
+        }
+
+        if depth != 0 {
+            return Err("Unclosed path parameter.".to_string());
+        }
+
+        let (param_name, constraint) = match parameter.split_once(':') {
+            Some((param_name, constraint)) => (param_name.trim(), Some(constraint.trim())),
+            None => (parameter.trim(), None),
+        };
+
+        match constraint {
+            Some("*") => {
+                pattern.push_str(&format!("{{*{}}}", param_name));
+            }
+            Some(constraint) => {
+                let regex = format!("^(?:{})$", constraint_regex(constraint));
+                let regex = Regex::new(&regex).map_err(|error| error.to_string())?;
+                constraints.push((param_name.to_string(), regex));
+                pattern.push_str(&format!("{{{}}}", param_name));
+            }
+            None => {
+                pattern.push_str(&format!("{{{}}}", param_name));
+            }
+        }
+    }
+
+    Ok((pattern, constraints))
+}
+
+///
+/// Host pattern like `{tenant}.example.com` compiled to regex.
+///
+#[derive(Clone)]
+struct HostPattern {
+    pattern: String,
+    regex: Regex,
+    names: Vec<String>,
+}
+
+impl HostPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let mut regex = String::from("(?i)^");
+        let mut names = vec![];
+        let mut rest = pattern.trim();
+
+        while let Some(start) = rest.find('{') {
+            regex.push_str(&regex::escape(&rest[..start]));
+
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err("Unclosed host parameter.".to_string()),
+            };
+
+            let parameter = &rest[start + 1..end];
+            let (name, label_regex) = match parameter.split_once(':') {
+                Some((name, "*")) => (name.trim(), r"[^:]+"),
+                Some(_) => return Err("Only * constraint is supported in host.".to_string()),
+                None => (parameter.trim(), r"[^.:]+"),
+            };
+
+            let is_valid_name = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || char == '_');
+            if !is_valid_name {
+                return Err(format!("Invalid host parameter name \"{}\".", name));
+            }
+
+            regex.push_str(&format!("({})", label_regex));
+            names.push(name.to_string());
+            rest = &rest[end + 1..];
+        }
+
+        regex.push_str(&regex::escape(rest));
+        regex.push('$');
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&regex).map_err(|error| error.to_string())?,
+            names,
+        })
+    }
+
+    fn captures(&self, host: &str) -> Option<Vec<(String, String)>> {
+        // Removes port from the host. IPv6 addresses are enclosed in brackets.
+        let host = match host.rfind(':') {
+            Some(index) if !host[index..].contains(']') => &host[..index],
+            _ => host,
+        };
+        let host = host.trim_end_matches('.');
+
+        let captures = self.regex.captures(host)?;
+        let mut params = vec![];
+        for (index, name) in self.names.iter().enumerate() {
+            let value = captures.get(index + 1)?.as_str().to_lowercase();
+            params.push((name.to_owned(), value));
+        }
+        Some(params)
+    }
+}
+
 #[derive(Debug)]
 pub struct PathParams {
     params: HashMap<String, String>,
@@ -101,3 +1193,197 @@ macro_rules! view {
         |request: racoon::core::request::Request| Box::pin($view_name(request))
     };
 }
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::middleware::json_content_type;
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+
+    use super::{select_route, Path, PathParams, RouteMatch, Scope, View};
+
+    async fn view(_: Request) -> Response {
+        HttpResponse::ok().empty()
+    }
+
+    async fn middleware(request: Request, view: Option<View>) -> Response {
+        Path::resolve(request, view).await
+    }
+
+    #[test]
+    fn test_path_constraints() {
+        let path = Path::new("/posts/{id:(\\d+)}/{slug:slug}", |request| {
+            Box::pin(view(request))
+        });
+        assert_eq!("/posts/{id}/{slug}", path.pattern());
+        assert!(path.is_match([("id", "12"), ("slug", "hello-world")]));
+        assert!(!path.is_match([("id", "abc"), ("slug", "hello")]));
+        assert!(!path.is_match([("id", "12"), ("slug", "hello world")]));
+
+        let path = Path::new("/archive/{year:\\d{4}}/{path:*}", |request| {
+            Box::pin(view(request))
+        });
+        assert_eq!("/archive/{year}/{*path}", path.pattern());
+        assert!(path.is_match([("year", "2024"), ("path", "a/b")]));
+        assert!(!path.is_match([("year", "24"), ("path", "a/b")]));
+    }
+
+    #[test]
+    fn test_select_route() {
+        let paths = vec![
+            Path::get("/users/{id:int}", |request| Box::pin(view(request))),
+            Path::new("/users/{id:int}", |request| Box::pin(view(request)))
+                .methods(&["put", "PATCH"]),
+        ];
+
+        let params = [("id", "10")];
+        assert!(
+            matches!(select_route(&paths, "PATCH", None, &params), RouteMatch::Found(path) if path.allowed_methods().len() == 2)
+        );
+
+        match select_route(&paths, "DELETE", None, &params) {
+            RouteMatch::MethodNotAllowed(methods) => {
+                assert_eq!(vec!["GET", "PUT", "PATCH", "HEAD"], methods)
+            }
+            _ => panic!("Expected method not allowed."),
+        }
+
+        assert!(matches!(
+            select_route(&paths, "HEAD", None, &params),
+            RouteMatch::Found(path) if path.allowed_methods() == ["GET"]
+        ));
+
+        assert!(matches!(
+            select_route(&paths, "GET", None, &[("id", "abc")]),
+            RouteMatch::NotFound
+        ));
+
+        // Path accepting HEAD explicitly is preferred over the GET fallback.
+        let paths = [
+            Path::get("/files", |request| Box::pin(view(request))),
+            Path::new("/files", |request| Box::pin(view(request))).methods(&["HEAD"]),
+            Path::post("/upload", |request| Box::pin(view(request))),
+        ];
+        assert!(matches!(
+            select_route(&paths[..2], "HEAD", None, &[]),
+            RouteMatch::Found(path) if path.allowed_methods() == ["HEAD"]
+        ));
+        assert!(matches!(
+            select_route(&paths[2..], "HEAD", None, &[]),
+            RouteMatch::MethodNotAllowed(methods) if methods == ["POST"]
+        ));
+    }
+
+    #[test]
+    fn test_host() {
+        let path = Path::new("/", |request| Box::pin(view(request))).host("{tenant}.example.com");
+        assert_eq!(
+            Some(vec![("tenant".to_string(), "acme".to_string())]),
+            path.match_host(Some("ACME.example.com:8080"))
+        );
+        assert_eq!(None, path.match_host(Some("a.b.example.com")));
+        assert_eq!(None, path.match_host(Some("example.com")));
+        assert_eq!(None, path.match_host(None));
+
+        let path = Path::new("/", |request| Box::pin(view(request))).host("{sub:*}.example.com");
+        assert_eq!(
+            Some(vec![("sub".to_string(), "a.b".to_string())]),
+            path.match_host(Some("a.b.example.com"))
+        );
+
+        let path = Path::new("/", |request| Box::pin(view(request)));
+        assert_eq!(Some(vec![]), path.match_host(None));
+    }
+
+    #[test]
+    fn test_redirect_location() {
+        let path = Path::redirect("/docs/{section}/{rest:*}", "/v2/{section}/{*rest}", 301);
+
+        let mut params = PathParams::new();
+        params.insert("section", "guide");
+        params.insert("rest", "install/linux");
+        assert_eq!(
+            Some(("/v2/guide/install/linux".to_string(), 301)),
+            path.redirect_location(&params)
+        );
+
+        let path = Path::new("/docs", |request| Box::pin(view(request)));
+        assert_eq!(None, path.redirect_location(&params));
+
+        let path = Path::redirect("/go/{rest:*}", "/{rest}", 302);
+        for rest in ["/evil.com", "\\evil.com"] {
+            let mut params = PathParams::new();
+            params.insert("rest", rest);
+            assert_eq!(
+                Some(("/evil.com".to_string(), 302)),
+                path.redirect_location(&params)
+            );
+        }
+    }
+
+    #[test]
+    fn test_catch_all() {
+        let path = Path::new("/static/{rest:*}", |request| Box::pin(view(request)));
+        assert_eq!(Some("rest"), path.catch_all());
+        assert_eq!(Some("/static/"), path.catch_all_prefix());
+
+        let path = Path::new("/static/{rest}", |request| Box::pin(view(request)));
+        assert_eq!(None, path.catch_all());
+    }
+
+    #[test]
+    fn test_scope_mount() {
+        let users = Scope::new()
+            .path("/", |request| Box::pin(view(request)))
+            .path("{id:int}", |request| Box::pin(view(request)));
+
+        let api = Scope::new().mount("/users/", users);
+        let paths = Scope::new().mount("api/v1", api).into_paths();
+
+        let names: Vec<&str> = paths.iter().map(|path| path.name.as_str()).collect();
+        assert_eq!(vec!["/api/v1/users/", "/api/v1/users/{id:int}"], names);
+        assert_eq!("/api/v1/users/{id}", paths[1].pattern());
+        assert!(!paths[1].is_match([("id", "abc")]));
+    }
+
+    #[test]
+    fn test_scope_middlewares() {
+        let admin = Scope::new()
+            .path("/", |request| Box::pin(view(request)))
+            .urls(vec![Path::new("/users", |request| Box::pin(view(request)))
+                .wrap(|request, view| Box::pin(middleware(request, view)))])
+            .wrap(|request, view| Box::pin(middleware(request, view)));
+
+        let paths = Scope::new()
+            .path("/login", |request| Box::pin(view(request)))
+            .mount("/admin", admin)
+            .into_paths();
+
+        let middleware_counts: Vec<usize> =
+            paths.iter().map(|path| path.middlewares().len()).collect();
+        assert_eq!(vec![0, 1, 2], middleware_counts);
+
+        let paths = Scope::new()
+            .urls(vec![
+                Path::new("/users", |request| Box::pin(view(request))).guard(json_content_type)
+            ])
+            .guard(json_content_type)
+            .into_paths();
+        assert_eq!(2, paths[0].guards().len());
+    }
+
+    #[test]
+    fn test_scope_max_body_size() {
+        let paths = Scope::new()
+            .urls(vec![
+                Path::post("/login", |request| Box::pin(view(request))).max_body_size(1024),
+                Path::post("/upload", |request| Box::pin(view(request))),
+            ])
+            .max_body_size(100 * 1024 * 1024)
+            .into_paths();
+
+        assert_eq!(Some(1024), paths[0].body_size_limit());
+        assert_eq!(Some(100 * 1024 * 1024), paths[1].body_size_limit());
+    }
+}
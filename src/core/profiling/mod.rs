@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::router::MatchedPath;
+use crate::racoon_warn;
+
+///
+/// Timing of the handled request passed to the hooks and profilers.
+///
+#[derive(Debug, Clone)]
+pub struct RequestTiming {
+    pub method: String,
+    pub path: String,
+    /// Pattern of the matched route like `/users/{id}`.
+    pub route: Option<String>,
+    pub params: HashMap<String, String>,
+    pub status_code: u32,
+    /// Time taken by the downstream middlewares and view.
+    pub latency: Duration,
+    /// True if the latency exceeds the threshold.
+    pub slow: bool,
+}
+
+///
+/// Called with the timing once the request is handled.
+///
+pub type ProfilerGuard = Box<dyn FnOnce(&RequestTiming) + Send>;
+
+///
+/// Profiler started before the request is handled, for attaching tools such as CPU profilers or
+/// flamegraph generators in development. The returned guard is called after the response, so
+/// the report can be saved only for the slow requests.
+///
+pub trait AbstractProfiler: Send + Sync {
+    fn start(&self, request: &Request) -> ProfilerGuard;
+}
+
+impl<F> AbstractProfiler for F
+where
+    F: Fn(&Request) -> ProfilerGuard + Send + Sync,
+{
+    fn start(&self, request: &Request) -> ProfilerGuard {
+        self(request)
+    }
+}
+
+///
+/// Measures the time taken to handle each request and logs the requests slower than the
+/// threshold with their route and path params at `warn` level with `racoon::slow_request` target.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::profiling::{ProfilerGuard, SlowRequests};
+/// use racoon::core::request::Request;
+/// use racoon::core::server::Server;
+///
+/// let slow_requests = SlowRequests::new(Duration::from_millis(500))
+///     .hook(|timing| println!("{} took {:?}", timing.path, timing.latency))
+///     .profiler(|_: &Request| -> ProfilerGuard {
+///         // Start the profiler here and write the report if the request is slow.
+///         Box::new(|timing| {
+///             if timing.slow {
+///                 println!("Saving profile of {}", timing.path);
+///             }
+///         })
+///     });
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(slow_requests);
+/// ```
+///
+#[derive(Clone)]
+pub struct SlowRequests {
+    threshold: Duration,
+    hook: Option<fn(&RequestTiming)>,
+    profiler: Option<Arc<dyn AbstractProfiler>>,
+}
+
+impl SlowRequests {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            hook: None,
+            profiler: None,
+        }
+    }
+
+    ///
+    /// Function called with the timing of every request, such as for exporting latency to the
+    /// monitoring tools.
+    ///
+    pub fn hook(mut self, hook: fn(&RequestTiming)) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    pub fn profiler<P: AbstractProfiler + 'static>(mut self, profiler: P) -> Self {
+        self.profiler = Some(Arc::new(profiler));
+        self
+    }
+}
+
+impl AbstractMiddleware for SlowRequests {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let slow_requests = self.clone();
+
+        Box::new(Box::pin(async move {
+            let method = request.method.clone();
+            let path = request.path.clone();
+            let route = request
+                .extensions
+                .get::<MatchedPath>()
+                .map(|matched_path| matched_path.0.clone());
+            let mut path_params = request.path_params.clone();
+            let params = std::mem::take(path_params.map());
+
+            let profiler_guard = slow_requests
+                .profiler
+                .as_ref()
+                .map(|profiler| profiler.start(&request));
+
+            let started_at = Instant::now();
+            let response = next.run(request).await;
+            let latency = started_at.elapsed();
+
+            let timing = RequestTiming {
+                method,
+                path,
+                route,
+                params,
+                status_code: response.status().0,
+                latency,
+                slow: latency > slow_requests.threshold,
+            };
+
+            if timing.slow {
+                racoon_warn!(
+                    target: "racoon::slow_request",
+                    "Slow request: {} {} (route: {}, params: {:?}) took {:?} with status {}",
+                    timing.method,
+                    timing.path,
+                    timing.route.as_deref().unwrap_or("-"),
+                    timing.params,
+                    timing.latency,
+                    timing.status_code
+                );
+            }
+
+            if let Some(hook) = slow_requests.hook {
+                hook(&timing);
+            }
+
+            if let Some(profiler_guard) = profiler_guard {
+                profiler_guard(&timing);
+            }
+            response
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::Headers;
+    use crate::core::middleware::Next;
+    use crate::core::path::View;
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse};
+
+    use super::{ProfilerGuard, SlowRequests};
+
+    static PROFILED_SLOW: AtomicBool = AtomicBool::new(false);
+
+    #[tokio::test]
+    async fn test_slow_requests() {
+        let view: View = |_| {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                let response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Done");
+                response
+            })
+        };
+
+        let slow_requests =
+            SlowRequests::new(Duration::from_millis(10)).profiler(|_: &Request| -> ProfilerGuard {
+                Box::new(|timing| {
+                    assert_eq!(Some(&"42".to_string()), timing.params.get("id"));
+                    PROFILED_SLOW.store(timing.slow, Ordering::Relaxed);
+                })
+            });
+        let next = Next::new(Arc::new(vec![Arc::new(slow_requests)]), None);
+
+        let response = next
+            .with_view(Some(view))
+            .run(request("/reports/42", Headers::new(), b"").await)
+            .await;
+        assert_eq!(200, response.status().0);
+        assert!(PROFILED_SLOW.load(Ordering::Relaxed));
+    }
+}
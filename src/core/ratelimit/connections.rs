@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::clock::{self, Clock};
+
+struct ClientConnections {
+    window_start: Instant,
+    opened: u32,
+    active: usize,
+}
+
+struct ConnectionState {
+    clients: HashMap<IpAddr, ClientConnections>,
+    last_cleanup: Instant,
+}
+
+///
+/// Limits the connections per client IP address. Connections over the limit are closed right
+/// after they are accepted, before reading the request.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::ratelimit::connections::ConnectionRateLimit;
+/// use racoon::core::server::Server;
+///
+/// let connection_rate_limit = ConnectionRateLimit::new()
+///     .max_rate(20, Duration::from_secs(1))
+///     .max_concurrent(50);
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.connection_rate_limit(connection_rate_limit);
+/// ```
+///
+#[derive(Clone)]
+pub struct ConnectionRateLimit {
+    max_rate: Option<(u32, Duration)>,
+    max_concurrent: Option<usize>,
+    state: Arc<Mutex<ConnectionState>>,
+    clock: Clock,
+}
+
+impl std::fmt::Debug for ConnectionRateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionRateLimit")
+            .field("max_rate", &self.max_rate)
+            .field("max_concurrent", &self.max_concurrent)
+            .finish()
+    }
+}
+
+impl Default for ConnectionRateLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectionRateLimit {
+    pub fn new() -> Self {
+        Self {
+            max_rate: None,
+            max_concurrent: None,
+            state: Arc::new(Mutex::new(ConnectionState {
+                clients: HashMap::new(),
+                last_cleanup: Instant::now(),
+            })),
+            clock: clock::system(),
+        }
+    }
+
+    ///
+    /// Clock used for counting the connections in the window. Defaults to the system clock.
+    ///
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    ///
+    /// Allows `limit` new connections per client in the `window`.
+    ///
+    pub fn max_rate(mut self, limit: u32, window: Duration) -> Self {
+        self.max_rate = Some((limit, window));
+        self
+    }
+
+    ///
+    /// Number of connections a client can keep open at the same time.
+    ///
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = Some(max_concurrent);
+        self
+    }
+
+    ///
+    /// Counts the new connection from the client. Returns `None` if the connection exceeds the
+    /// limit, else the guard which needs to be kept until the connection is closed.
+    ///
+    pub fn acquire(&self, ip: IpAddr) -> Option<ConnectionRateGuard> {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let now = self.clock.now();
+        let window = match self.max_rate {
+            Some((_, window)) => window,
+            None => Duration::ZERO,
+        };
+
+        // Clients without open connections and with expired window are no longer tracked.
+        if now.saturating_duration_since(state.last_cleanup) > Duration::from_secs(60) {
+            state.clients.retain(|_, client| {
+                client.active > 0 || now.saturating_duration_since(client.window_start) < window
+            });
+            state.last_cleanup = now;
+        }
+
+        let client = state.clients.entry(ip).or_insert(ClientConnections {
+            window_start: now,
+            opened: 0,
+            active: 0,
+        });
+
+        if let Some(max_concurrent) = self.max_concurrent {
+            if client.active >= max_concurrent {
+                return None;
+            }
+        }
+
+        if let Some((limit, window)) = self.max_rate {
+            if now.saturating_duration_since(client.window_start) >= window {
+                client.window_start = now;
+                client.opened = 0;
+            }
+
+            if client.opened >= limit {
+                return None;
+            }
+            client.opened += 1;
+        }
+
+        client.active += 1;
+        Some(ConnectionRateGuard {
+            ip,
+            state: self.state.clone(),
+        })
+    }
+}
+
+///
+/// Counts the connection as open until dropped.
+///
+pub struct ConnectionRateGuard {
+    ip: IpAddr,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl Drop for ConnectionRateGuard {
+    fn drop(&mut self) {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if let Some(client) = state.clients.get_mut(&self.ip) {
+            client.active = client.active.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    use super::ConnectionRateLimit;
+
+    #[test]
+    fn test_connection_rate_limit() {
+        let client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let other_client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        let concurrent_limit = ConnectionRateLimit::new().max_concurrent(2);
+        let first = concurrent_limit.acquire(client).unwrap();
+        let _second = concurrent_limit.acquire(client).unwrap();
+        assert!(concurrent_limit.acquire(client).is_none());
+        assert!(concurrent_limit.acquire(other_client).is_some());
+
+        drop(first);
+        assert!(concurrent_limit.acquire(client).is_some());
+
+        let rate_limit = ConnectionRateLimit::new().max_rate(2, Duration::from_secs(3600));
+        assert!(rate_limit.acquire(client).is_some());
+        assert!(rate_limit.acquire(client).is_some());
+        assert!(rate_limit.acquire(client).is_none());
+        assert!(rate_limit.acquire(other_client).is_some());
+    }
+}
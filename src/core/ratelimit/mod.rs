@@ -0,0 +1,304 @@
+pub mod connections;
+pub mod stores;
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse, Response};
+use crate::racoon_error;
+
+use self::stores::MemoryRateLimitStore;
+
+pub type RateLimitResult<T> = Box<dyn Future<Output = T> + Send + Unpin>;
+
+///
+/// Result of counting the request against the limit.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until the current window ends.
+    pub reset_after: Duration,
+}
+
+pub trait AbstractRateLimitStore: Sync + Send {
+    ///
+    /// Counts the request for the key if the limit is not exceeded. Requests are counted with
+    /// sliding window, so the count of the previous window is weighted by its overlap with the
+    /// last `window` duration.
+    ///
+    fn hit(
+        &self,
+        key: &str,
+        limit: u32,
+        window: Duration,
+    ) -> RateLimitResult<std::io::Result<RateLimitStatus>>;
+}
+
+pub type RateLimitStore = Box<dyn AbstractRateLimitStore>;
+
+///
+/// Identifies the client whose requests are counted. Requests without key are not limited.
+///
+#[derive(Clone)]
+pub enum RateLimitKey {
+    /// IP address of the connected client.
+    Ip,
+    /// Value of the request header such as `X-Api-Key`.
+    Header(String),
+    /// Key returned by the function.
+    Custom(fn(&Request) -> Option<String>),
+}
+
+impl RateLimitKey {
+    pub async fn extract(&self, request: &Request) -> Option<String> {
+        match self {
+            RateLimitKey::Ip => {
+                let remote_addr = request.remote_addr().await?;
+                match SocketAddr::from_str(&remote_addr) {
+                    Ok(socket_addr) => Some(socket_addr.ip().to_string()),
+                    Err(_) => Some(remote_addr),
+                }
+            }
+            RateLimitKey::Header(name) => request.headers.value(name),
+            RateLimitKey::Custom(extractor) => extractor(request),
+        }
+    }
+}
+
+///
+/// Middleware limiting the number of requests per client in the time window. Rejected requests
+/// receive `429 Too Many Requests` with `Retry-After` header. Every response carries
+/// `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers.
+///
+/// Counts are kept in memory by default. Use `store` to share them between server instances,
+/// for example with `RedisRateLimitStore` available with the `redis` feature.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::path::Path;
+/// use racoon::core::ratelimit::{RateLimit, RateLimitKey};
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::server::Server;
+/// use racoon::view;
+///
+/// async fn login(request: Request) -> Response {
+///     HttpResponse::ok().body("Login")
+/// }
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(RateLimit::new(1000, Duration::from_secs(60)));
+///
+/// let login_limit = RateLimit::new(5, Duration::from_secs(60)).prefix("login");
+/// server.urls(vec![Path::post("/login", view!(login)).middleware(login_limit)]);
+///
+/// let api_limit =
+///     RateLimit::new(100, Duration::from_secs(1)).key(RateLimitKey::Header("X-Api-Key".into()));
+/// ```
+///
+#[derive(Clone)]
+pub struct RateLimit {
+    limit: u32,
+    window: Duration,
+    key: RateLimitKey,
+    prefix: String,
+    store: Arc<RateLimitStore>,
+}
+
+impl RateLimit {
+    ///
+    /// Allows `limit` requests per client IP address in the `window`.
+    ///
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            key: RateLimitKey::Ip,
+            prefix: "ratelimit".to_string(),
+            store: Arc::new(Box::new(MemoryRateLimitStore::new())),
+        }
+    }
+
+    pub fn key(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    ///
+    /// Prefix of the store keys. Limits sharing the same store need different prefixes to be
+    /// counted separately.
+    ///
+    pub fn prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
+        self.prefix = prefix.as_ref().to_string();
+        self
+    }
+
+    pub fn store<T: AbstractRateLimitStore + 'static>(mut self, store: T) -> Self {
+        self.store = Arc::new(Box::new(store));
+        self
+    }
+}
+
+impl AbstractMiddleware for RateLimit {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let rate_limit = self.clone();
+
+        Box::new(Box::pin(async move {
+            let client_key = match rate_limit.key.extract(&request).await {
+                Some(client_key) => client_key,
+                None => return next.run(request).await,
+            };
+
+            let key = format!("{}:{}", rate_limit.prefix, client_key);
+            let status = match rate_limit
+                .store
+                .hit(&key, rate_limit.limit, rate_limit.window)
+                .await
+            {
+                Ok(status) => status,
+                Err(error) => {
+                    // Requests are not blocked when the store is unavailable.
+                    racoon_error!("Failed to count request for rate limit. Error: {}", error);
+                    return next.run(request).await;
+                }
+            };
+
+            let mut response: Response = if status.allowed {
+                next.run(request).await
+            } else {
+                let mut response = HttpResponse::too_many_requests().body("429 Too Many Requests");
+                let retry_after = status.reset_after.as_secs_f64().ceil() as u64;
+                response
+                    .get_headers()
+                    .set("Retry-After", retry_after.max(1).to_string());
+                response
+            };
+
+            add_rate_limit_headers(response.get_headers(), &status);
+            response
+        }))
+    }
+}
+
+fn add_rate_limit_headers(headers: &mut Headers, status: &RateLimitStatus) {
+    headers.set("X-RateLimit-Limit", status.limit.to_string());
+    headers.set("X-RateLimit-Remaining", status.remaining.to_string());
+
+    let reset = status.reset_after.as_secs_f64().ceil() as u64;
+    headers.set("X-RateLimit-Reset", reset.to_string());
+}
+
+///
+/// Returns index of the current window and the elapsed time in it. Windows are aligned to the
+/// unix epoch, so all the server instances agree on the window boundaries.
+///
+pub fn current_window(window: Duration) -> (u64, Duration) {
+    window_at(SystemTime::now(), window)
+}
+
+///
+/// Returns index of the window containing the time and the elapsed time in it.
+///
+pub fn window_at(time: SystemTime, window: Duration) -> (u64, Duration) {
+    let now = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let window_nanos = window.as_nanos().max(1);
+
+    let index = (now.as_nanos() / window_nanos) as u64;
+    let elapsed = Duration::from_nanos((now.as_nanos() % window_nanos) as u64);
+    (index, elapsed)
+}
+
+///
+/// Sliding window estimate of the requests counted in the last window duration.
+///
+pub fn sliding_count(previous: u32, current: u32, elapsed: Duration, window: Duration) -> f64 {
+    let previous_weight = 1.0 - elapsed.as_secs_f64() / window.as_secs_f64();
+    previous as f64 * previous_weight.max(0.0) + current as f64
+}
+
+///
+/// Builds status for the request counted with the sliding window. `current` includes the request
+/// if it is allowed.
+///
+pub fn window_status(
+    previous: u32,
+    current: u32,
+    limit: u32,
+    elapsed: Duration,
+    window: Duration,
+) -> RateLimitStatus {
+    let count = sliding_count(previous, current, elapsed, window);
+    let allowed = count <= limit as f64;
+
+    RateLimitStatus {
+        allowed,
+        limit,
+        remaining: (limit as f64 - count).floor().max(0.0) as u32,
+        reset_after: window.saturating_sub(elapsed),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::core::clock::ManualClock;
+
+    use super::stores::MemoryRateLimitStore;
+    use super::{window_status, AbstractRateLimitStore};
+
+    #[test]
+    fn test_window_status() {
+        let window = Duration::from_secs(60);
+
+        // Half of the previous window overlaps with the last 60 seconds.
+        let status = window_status(10, 5, 10, Duration::from_secs(30), window);
+        assert!(status.allowed);
+        assert_eq!(0, status.remaining);
+        assert_eq!(Duration::from_secs(30), status.reset_after);
+
+        let status = window_status(10, 6, 10, Duration::from_secs(30), window);
+        assert!(!status.allowed);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let window = Duration::from_secs(3600);
+        let clock = ManualClock::at(UNIX_EPOCH + window * 10);
+        let store = MemoryRateLimitStore::new().clock(Arc::new(clock.clone()));
+
+        for remaining in (0..3).rev() {
+            let status = store.hit("client", 3, window).await.unwrap();
+            assert!(status.allowed);
+            assert_eq!(remaining, status.remaining);
+        }
+
+        let status = store.hit("client", 3, window).await.unwrap();
+        assert!(!status.allowed);
+        assert_eq!(window, status.reset_after);
+
+        let status = store.hit("other", 3, window).await.unwrap();
+        assert!(status.allowed);
+
+        // Counts of the windows before the previous window are not weighted.
+        clock.advance(window * 2);
+        let status = store.hit("client", 3, window).await.unwrap();
+        assert!(status.allowed);
+        assert_eq!(2, status.remaining);
+    }
+}
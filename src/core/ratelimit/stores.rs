@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::core::clock::{self, Clock};
+use crate::core::ratelimit::{self, AbstractRateLimitStore, RateLimitResult, RateLimitStatus};
+
+struct WindowCounter {
+    index: u64,
+    current: u32,
+    previous: u32,
+    window: Duration,
+}
+
+struct CounterState {
+    counters: HashMap<String, WindowCounter>,
+    last_cleanup: Instant,
+}
+
+///
+/// In-memory rate limit store. Counts are not shared between the server instances.
+///
+pub struct MemoryRateLimitStore {
+    state: Arc<Mutex<CounterState>>,
+    clock: Clock,
+}
+
+impl Default for MemoryRateLimitStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CounterState {
+                counters: HashMap::new(),
+                last_cleanup: Instant::now(),
+            })),
+            clock: clock::system(),
+        }
+    }
+
+    ///
+    /// Clock used for counting the requests in the windows. Defaults to the system clock.
+    ///
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl AbstractRateLimitStore for MemoryRateLimitStore {
+    fn hit(
+        &self,
+        key: &str,
+        limit: u32,
+        window: Duration,
+    ) -> RateLimitResult<std::io::Result<RateLimitStatus>> {
+        let state_ref = self.state.clone();
+        let now = self.clock.now();
+        let system_time = self.clock.system_time();
+        let key = key.to_string();
+
+        Box::new(Box::pin(async move {
+            let mut state = state_ref.lock().await;
+            let (index, elapsed) = ratelimit::window_at(system_time, window);
+
+            // Counters older than the previous window no longer affect the count.
+            if now.saturating_duration_since(state.last_cleanup) > Duration::from_secs(60) {
+                state.counters.retain(|_, counter| {
+                    let (current_index, _) = ratelimit::window_at(system_time, counter.window);
+                    counter.index + 1 >= current_index
+                });
+                state.last_cleanup = now;
+            }
+
+            let counter = state.counters.entry(key).or_insert(WindowCounter {
+                index,
+                current: 0,
+                previous: 0,
+                window,
+            });
+
+            if counter.index != index {
+                counter.previous = if counter.index + 1 == index {
+                    counter.current
+                } else {
+                    0
+                };
+                counter.current = 0;
+                counter.index = index;
+            }
+
+            let status = ratelimit::window_status(
+                counter.previous,
+                counter.current + 1,
+                limit,
+                elapsed,
+                window,
+            );
+            if status.allowed {
+                counter.current += 1;
+            }
+            Ok(status)
+        }))
+    }
+}
+
+///
+/// Rate limit store keeping counts in Redis, so the limit is shared between server instances.
+/// Available with the `redis` feature.
+///
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisRateLimitStore {
+    connection: redis::aio::ConnectionManager,
+}
+
+#[cfg(feature = "redis")]
+impl RedisRateLimitStore {
+    pub async fn new<S: AsRef<str>>(url: S) -> std::io::Result<Self> {
+        let client = redis::Client::open(url.as_ref()).map_err(std::io::Error::other)?;
+        let connection = redis::aio::ConnectionManager::new(client)
+            .await
+            .map_err(std::io::Error::other)?;
+        Ok(Self { connection })
+    }
+}
+
+#[cfg(feature = "redis")]
+impl AbstractRateLimitStore for RedisRateLimitStore {
+    fn hit(
+        &self,
+        key: &str,
+        limit: u32,
+        window: Duration,
+    ) -> RateLimitResult<std::io::Result<RateLimitStatus>> {
+        let mut connection = self.connection.clone();
+        let key = key.to_string();
+
+        Box::new(Box::pin(async move {
+            let (index, elapsed) = ratelimit::current_window(window);
+            let current_key = format!("{}:{}", key, index);
+            let previous_key = format!("{}:{}", key, index.saturating_sub(1));
+            let ttl = (window.as_secs() * 2).max(1) as i64;
+
+            let (current, previous): (u32, Option<u32>) = redis::pipe()
+                .atomic()
+                .incr(&current_key, 1)
+                .expire(&current_key, ttl)
+                .ignore()
+                .get(&previous_key)
+                .query_async(&mut connection)
+                .await
+                .map_err(std::io::Error::other)?;
+
+            let status =
+                ratelimit::window_status(previous.unwrap_or(0), current, limit, elapsed, window);
+
+            // Rejected requests are not counted.
+            if !status.allowed {
+                redis::cmd("DECR")
+                    .arg(&current_key)
+                    .query_async::<_, ()>(&mut connection)
+                    .await
+                    .map_err(std::io::Error::other)?;
+            }
+            Ok(status)
+        }))
+    }
+}
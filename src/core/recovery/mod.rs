@@ -0,0 +1,128 @@
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Once;
+use std::task::{Context, Poll};
+
+///
+/// Details of the panic caught while running the request handler.
+///
+#[derive(Debug, Clone)]
+pub struct Panic {
+    pub message: String,
+    /// File and line where the panic occurred.
+    pub location: Option<String>,
+    pub backtrace: Option<String>,
+}
+
+thread_local! {
+    // Number of catch-unwind futures being polled on the current thread.
+    static RECOVERING: Cell<usize> = const { Cell::new(0) };
+    static LAST_PANIC: RefCell<Option<(Option<String>, String)>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+///
+/// Installs panic hook which records location and backtrace of the panics caught by
+/// `catch_unwind`. Other panics are reported by the previous hook as usual.
+///
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if RECOVERING.with(|recovering| recovering.get()) == 0 {
+                previous_hook(info);
+                return;
+            }
+
+            let location = info
+                .location()
+                .map(|location| format!("{}:{}", location.file(), location.line()));
+            let backtrace = Backtrace::force_capture().to_string();
+            LAST_PANIC.with(|last_panic| {
+                *last_panic.borrow_mut() = Some((location, backtrace));
+            });
+        }));
+    });
+}
+
+///
+/// Future returned by `catch_unwind`.
+///
+pub struct CatchUnwind<F: Future> {
+    future: Pin<Box<F>>,
+}
+
+///
+/// Runs the future and returns the panic as error instead of unwinding the task polling it.
+///
+pub fn catch_unwind<F: Future>(future: F) -> CatchUnwind<F> {
+    install_hook();
+    CatchUnwind {
+        future: Box::pin(future),
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Panic>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.future.as_mut();
+
+        RECOVERING.with(|recovering| recovering.set(recovering.get() + 1));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx)));
+        RECOVERING.with(|recovering| recovering.set(recovering.get() - 1));
+
+        match result {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => {
+                let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                    message.to_string()
+                } else if let Some(message) = payload.downcast_ref::<String>() {
+                    message.clone()
+                } else {
+                    "Unknown panic".to_string()
+                };
+
+                let (location, backtrace) =
+                    match LAST_PANIC.with(|last_panic| last_panic.borrow_mut().take()) {
+                        Some((location, backtrace)) => (location, Some(backtrace)),
+                        None => (None, None),
+                    };
+
+                Poll::Ready(Err(Panic {
+                    message,
+                    location,
+                    backtrace,
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::catch_unwind;
+
+    #[tokio::test]
+    async fn test_catch_unwind() {
+        let result = catch_unwind(async { 1 + 1 }).await;
+        assert_eq!(2, result.unwrap());
+
+        let result = catch_unwind(async {
+            tokio::task::yield_now().await;
+            panic!("Database is down");
+        })
+        .await;
+
+        let caught = result.unwrap_err();
+        assert_eq!("Database is down", caught.message);
+        assert!(caught.location.unwrap().contains("recovery"));
+        assert!(caught.backtrace.is_some());
+    }
+}
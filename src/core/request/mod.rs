@@ -1,12 +1,16 @@
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use tokio::sync::Mutex;
 
+use crate::core::flash::{self, FlashMessage, Level};
+use crate::core::forms::progress::{ProgressChannel, ProgressHook, ProgressStream};
 use crate::core::forms::{Files, FormConstraints, FormData};
 
 use crate::core::headers::{HeaderValue, Headers};
+use crate::core::middleware::MiddlewareChain;
 use crate::core::parser::multipart::MultipartParser;
 use crate::core::parser::urlencoded::UrlEncodedParser;
 use crate::core::server::Context;
@@ -23,6 +27,67 @@ use super::forms::FormFieldError;
 
 pub type QueryParams = HashMap<String, Vec<String>>;
 
+///
+/// Values attached to the request by the middlewares, such as the authenticated user, keyed by
+/// their type.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::middleware::Next;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+///
+/// struct TenantId(u64);
+///
+/// async fn tenant(mut request: Request, next: Next) -> Response {
+///     request.extensions.insert(TenantId(1));
+///     next.run(request).await
+/// }
+///
+/// async fn home(request: Request) {
+///     let tenant_id = request.extensions.get::<TenantId>().map(|tenant| tenant.0);
+/// }
+/// ```
+///
+#[derive(Clone, Default)]
+pub struct Extensions {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Inserts the value replacing the existing value of the same type.
+    ///
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>())?.downcast_ref::<T>()
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) {
+        self.values.remove(&TypeId::of::<T>());
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+}
+
 pub struct Request {
     pub stream: Arc<Stream>,
     context: Arc<Context>,
@@ -38,6 +103,9 @@ pub struct Request {
     pub body_read: Arc<AtomicBool>,
     pub form_constraints: Arc<FormConstraints>,
     pub response_headers: Arc<Mutex<Headers>>,
+    pub extensions: Extensions,
+    pub(crate) state: Arc<Extensions>,
+    pub(crate) middleware_chain: MiddlewareChain,
 }
 
 impl Request {
@@ -76,6 +144,9 @@ impl Request {
             body_read,
             form_constraints,
             response_headers,
+            extensions: Extensions::new(),
+            state: Arc::default(),
+            middleware_chain: MiddlewareChain::default(),
         }
     }
 
@@ -87,6 +158,85 @@ impl Request {
         self.context.downcast_ref::<T>()
     }
 
+    ///
+    /// Returns the application state of the type registered with `Server::with_state`. Unlike
+    /// the extensions, the state is shared by all the requests and cannot be changed by the
+    /// middlewares.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::core::response::{HttpResponse, Response};
+    ///
+    /// struct Config {
+    ///     site_name: String,
+    /// }
+    ///
+    /// async fn home(request: Request) -> Response {
+    ///     let config = request.state::<Config>().expect("Config is not registered.");
+    ///     HttpResponse::ok().body(config.site_name.clone())
+    /// }
+    /// ```
+    ///
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state.get::<T>()
+    }
+
+    ///
+    /// Session of the client. Values are stored by the session manager as soon as they are set
+    /// and the `sessionid` cookie is sent with the response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::request::Request;
+    ///
+    /// async fn login(request: Request) {
+    ///     let _ = request.session().set("user_id", "1").await;
+    ///     let user_id = request.session().get("user_id").await;
+    /// }
+    /// ```
+    ///
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    ///
+    /// Stores the message in the session to be shown once in the next request, such as after
+    /// redirecting from the submitted form.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::flash::Level;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    ///
+    /// async fn save_profile(request: Request) -> Response {
+    ///     let _ = request.flash("Saved!", Level::Success).await;
+    ///     HttpResponse::see_other().location("/profile")
+    /// }
+    ///
+    /// async fn profile(request: Request) -> Response {
+    ///     let messages = request.flash_messages().await;
+    ///     HttpResponse::ok().body(format!("{} messages", messages.len()))
+    /// }
+    /// ```
+    ///
+    pub async fn flash<S: AsRef<str>>(&self, message: S, level: Level) -> std::io::Result<()> {
+        flash::add(&self.session, message, level).await
+    }
+
+    ///
+    /// Returns the flash messages stored by the previous requests and removes them.
+    ///
+    pub async fn flash_messages(&self) -> Vec<FlashMessage> {
+        flash::take(&self.session).await
+    }
+
     pub async fn parse(&self) -> (FormData, Files) {
         return match self.parse_body(self.form_constraints.clone()).await {
             Ok((form_data, files)) => (form_data, files),
@@ -94,9 +244,58 @@ impl Request {
         };
     }
 
+    ///
+    /// Parses the request body while publishing its progress to the channel under the upload ID.
+    /// The upload is marked as failed if the body cannot be parsed.
+    ///
+    pub async fn parse_with_progress<S: AsRef<str>>(
+        &self,
+        channel: &ProgressChannel,
+        upload_id: S,
+    ) -> (FormData, Files) {
+        let upload_id = upload_id.as_ref();
+        let total = self
+            .headers
+            .value("Content-Length")
+            .and_then(|value| value.parse::<u64>().ok());
+
+        let hook = channel.start(upload_id, total);
+        let result = self
+            .parse_body_with_progress(self.form_constraints.clone(), hook)
+            .await;
+        channel.finish(upload_id, result.is_ok());
+
+        match result {
+            Ok((form_data, files)) => (form_data, files),
+            Err(_) => (FormData::new(), Files::new()),
+        }
+    }
+
     pub async fn parse_body(
         &self,
         form_constraints: Arc<FormConstraints>,
+    ) -> Result<(FormData, Files), FormFieldError> {
+        self.parse_stream(self.stream.clone(), form_constraints)
+            .await
+    }
+
+    ///
+    /// Parses the request body and calls the hook with the number of bytes received as the body
+    /// is read.
+    ///
+    pub async fn parse_body_with_progress(
+        &self,
+        form_constraints: Arc<FormConstraints>,
+        hook: ProgressHook,
+    ) -> Result<(FormData, Files), FormFieldError> {
+        let stream: Stream = Box::new(ProgressStream::new(self.stream.clone(), hook));
+        self.parse_stream(Arc::new(stream), form_constraints).await
+    }
+
+    async fn parse_stream(
+        &self,
+        stream: Arc<Stream>,
+        form_constraints: Arc<FormConstraints>,
     ) -> Result<(FormData, Files), FormFieldError> {
         let form_data = FormData::new();
         let files = Files::new();
@@ -118,13 +317,7 @@ impl Request {
         {
             racoon_debug!("Parsing with MultipartParser");
 
-            return match MultipartParser::parse(
-                self.stream.clone(),
-                form_constraints,
-                &self.headers,
-            )
-            .await
-            {
+            return match MultipartParser::parse(stream, form_constraints, &self.headers).await {
                 Ok((form_data, files)) => {
                     self.body_read.store(true, Ordering::Relaxed);
                     Ok((form_data, files))
@@ -140,13 +333,7 @@ impl Request {
         {
             racoon_debug!("Parsing with UrlEncoded parser.");
 
-            return match UrlEncodedParser::parse(
-                self.stream.clone(),
-                &self.headers,
-                form_constraints,
-            )
-            .await
-            {
+            return match UrlEncodedParser::parse(stream, &self.headers, form_constraints).await {
                 Ok(form_data) => {
                     self.body_read.store(true, Ordering::Relaxed);
                     Ok((form_data, files))
@@ -161,6 +348,58 @@ impl Request {
         racoon_debug!("Unhandled enctype: {}", content_type);
         Ok((form_data, files))
     }
+
+    ///
+    /// Reads raw request body of size specified in the `Content-Length` header. Returns empty
+    /// body if the header is missing. Body larger than the max body size of the form constraints
+    /// is rejected without reading.
+    ///
+    pub async fn body(&self) -> Result<Vec<u8>, FormFieldError> {
+        let content_length = match self.headers.value("Content-Length") {
+            Some(value) => match value.parse::<usize>() {
+                Ok(content_length) => content_length,
+                Err(_) => {
+                    return Err(FormFieldError::Others(
+                        None,
+                        "Invalid content length header.".to_owned(),
+                        false,
+                    ));
+                }
+            },
+            None => return Ok(vec![]),
+        };
+
+        let max_body_size = self
+            .form_constraints
+            .max_body_size(self.stream.buffer_size().await);
+        if content_length > max_body_size {
+            return Err(FormFieldError::MaxBodySizeExceed);
+        }
+
+        self.body_read.store(false, Ordering::Relaxed);
+        let mut body = vec![];
+
+        while body.len() < content_length {
+            let chunk = match self.stream.read_chunk().await {
+                Ok(bytes) => bytes,
+                Err(error) => {
+                    return Err(FormFieldError::Others(None, error.to_string(), true));
+                }
+            };
+            body.extend(chunk);
+        }
+
+        // Bytes of the next request are restored for reading later.
+        if body.len() > content_length {
+            let extra_bytes = body.split_off(content_length);
+            if let Err(error) = self.stream.restore_payload(&extra_bytes).await {
+                return Err(FormFieldError::Others(None, error.to_string(), true));
+            }
+        }
+
+        self.body_read.store(true, Ordering::Relaxed);
+        Ok(body)
+    }
 }
 
 impl Clone for Request {
@@ -180,6 +419,9 @@ impl Clone for Request {
             body_read: self.body_read.clone(),
             form_constraints: self.form_constraints.clone(),
             response_headers: self.response_headers.clone(),
+            extensions: self.extensions.clone(),
+            state: self.state.clone(),
+            middleware_chain: self.middleware_chain.clone(),
         }
     }
 }
@@ -187,5 +429,7 @@ impl Clone for Request {
 #[derive(Debug)]
 pub enum RequestError {
     HeaderSizeExceed,
+    HeaderReadTimeout,
+    Http2Preface,
     Others(String),
 }
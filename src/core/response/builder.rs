@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::core::cookie;
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::response::cache_control::CacheControl;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse, Response};
+use crate::racoon_error;
+
+///
+/// Fluent builder for composing status, headers, cookies and body in a single chain.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use serde_json::json;
+///
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+/// use racoon::core::response::builder::ResponseBuilder;
+/// use racoon::core::response::status::ResponseStatus;
+///
+/// async fn login(request: Request) -> Response {
+///     ResponseBuilder::created()
+///         .header("X-Request-Source", "api")
+///         .cookie("theme", "dark", Duration::from_secs(3600))
+///         .json(&json!({"status": "logged in"}))
+/// }
+/// ```
+///
+pub struct ResponseBuilder {
+    status_code: u32,
+    status_text: String,
+    headers: Headers,
+    keep_alive: bool,
+}
+
+impl ResponseStatus for ResponseBuilder {
+    fn with_status(status_code: u32, status_text: &str) -> Self {
+        Self {
+            status_code,
+            status_text: status_text.to_string(),
+            headers: Headers::new(),
+            keep_alive: true,
+        }
+    }
+}
+
+impl ResponseBuilder {
+    ///
+    /// Sets header replacing existing header with the same name.
+    ///
+    pub fn header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.headers.set(name, value);
+        self
+    }
+
+    ///
+    /// Adds header without replacing existing headers with the same name.
+    ///
+    pub fn append_header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.headers.set_multiple(name, value);
+        self
+    }
+
+    pub fn content_type<S: AsRef<str>>(self, value: S) -> Self {
+        self.header("Content-Type", value.as_ref())
+    }
+
+    ///
+    /// Sets cookie in max age from "/" path.
+    ///
+    pub fn cookie<S: AsRef<str>>(mut self, name: S, value: S, max_age: Duration) -> Self {
+        cookie::set_cookie(&mut self.headers, name, value, max_age);
+        self
+    }
+
+    ///
+    /// Removes cookie from "/" path.
+    ///
+    pub fn remove_cookie<S: AsRef<str>>(mut self, name: S) -> Self {
+        let expire_header_value = format!(
+            "{}=;Expires=Sun, 06 Nov 1994 08:49:37 GMT; Path=/",
+            name.as_ref()
+        );
+        self.headers.set_multiple("Set-Cookie", expire_header_value);
+        self
+    }
+
+    pub fn cache_control(mut self, cache_control: &CacheControl) -> Self {
+        cache_control.apply(&mut self.headers);
+        self
+    }
+
+    pub fn keep_alive(mut self, is_alive: bool) -> Self {
+        self.keep_alive = is_alive;
+        self
+    }
+
+    ///
+    /// Closes the connection after the response is served.
+    ///
+    pub fn close(self) -> Self {
+        self.keep_alive(false)
+    }
+
+    ///
+    /// Sets raw bytes as response body. `Content-Type` header is not modified.
+    ///
+    pub fn bytes<B: AsRef<[u8]>>(self, data: B) -> Response {
+        let mut http_response = HttpResponse::with_status(self.status_code, &self.status_text)
+            .keep_alive(self.keep_alive);
+
+        let headers = http_response.get_headers();
+        for (name, values) in self.headers {
+            for value in values {
+                headers.set_multiple(&name, value);
+            }
+        }
+
+        http_response.bytes(data)
+    }
+
+    ///
+    /// Sets text body. Uses `text/plain` content type if not already specified.
+    ///
+    pub fn body<S: AsRef<str>>(self, text: S) -> Response {
+        self.default_content_type("text/plain; charset=utf-8")
+            .bytes(text.as_ref())
+    }
+
+    ///
+    /// Sets html body. The html is not escaped.
+    ///
+    pub fn html<S: AsRef<str>>(self, html: S) -> Response {
+        self.default_content_type("text/html; charset=utf-8")
+            .bytes(html.as_ref())
+    }
+
+    ///
+    /// Serializes value to JSON and sets as body. If serialization fails, responds with
+    /// `500 Internal Server Error`.
+    ///
+    pub fn json<T: Serialize + ?Sized>(self, value: &T) -> Response {
+        match serde_json::to_vec(value) {
+            Ok(json_bytes) => self
+                .default_content_type("application/json")
+                .bytes(json_bytes),
+            Err(error) => {
+                racoon_error!("Failed to serialize JSON response. Error: {}", error);
+                HttpResponse::internal_server_error().body("Internal Server Error")
+            }
+        }
+    }
+
+    pub fn empty(self) -> Response {
+        self.bytes([])
+    }
+
+    fn default_content_type(self, content_type: &str) -> Self {
+        if self.headers.value("Content-Type").is_some() {
+            return self;
+        }
+
+        self.content_type(content_type)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json::json;
+
+    use crate::core::headers::HeaderValue;
+    use crate::core::response::status::ResponseStatus;
+
+    use super::ResponseBuilder;
+
+    #[test]
+    fn test_response_builder() {
+        let mut response = ResponseBuilder::created()
+            .header("X-Custom", "value")
+            .append_header("X-Custom", "value2")
+            .json(&json!({"id": 1}));
+
+        assert_eq!((201, "Created".to_string()), response.status());
+
+        let headers = response.get_headers();
+        assert_eq!(
+            Some("application/json".to_string()),
+            headers.value("Content-Type")
+        );
+        assert_eq!(Some("8".to_string()), headers.value("Content-Length"));
+        assert_eq!(2, headers.get("X-Custom").unwrap().len());
+        assert_eq!(b"{\"id\":1}".to_vec(), *response.get_body());
+    }
+
+    #[test]
+    fn test_response_builder_close() {
+        let mut response = ResponseBuilder::ok()
+            .content_type("text/csv")
+            .close()
+            .body("a,b");
+
+        assert!(response.should_close());
+        assert_eq!(
+            Some("text/csv".to_string()),
+            response.get_headers().value("Content-Type")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::response::AbstractResponse;
+
+///
+/// Typed builder for `Cache-Control` response header.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::cache_control::{CacheControl, SetCacheControl};
+/// use racoon::core::response::status::ResponseStatus;
+///
+/// async fn logo(request: Request) -> Response {
+///     let cache_control = CacheControl::new()
+///         .public()
+///         .max_age(Duration::from_secs(86400))
+///         .immutable();
+///
+///     HttpResponse::ok().body("...").cache_control(&cache_control)
+/// }
+/// ```
+///
+/// More information: <https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Cache-Control>
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheControl {
+    public: bool,
+    private: bool,
+    no_cache: bool,
+    no_store: bool,
+    no_transform: bool,
+    must_revalidate: bool,
+    proxy_revalidate: bool,
+    immutable: bool,
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    stale_if_error: Option<Duration>,
+}
+
+impl CacheControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Response must not be stored by any cache. Useful for sensitive data.
+    ///
+    pub fn no_store_policy() -> Self {
+        Self::new().no_store()
+    }
+
+    ///
+    /// Response may be cached forever. Useful for fingerprinted static files.
+    ///
+    pub fn immutable_policy() -> Self {
+        Self::new()
+            .public()
+            .max_age(Duration::from_secs(31536000))
+            .immutable()
+    }
+
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    ///
+    /// Response may be stored but must be validated with the server before each reuse.
+    ///
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    pub fn no_store(mut self) -> Self {
+        self.no_store = true;
+        self
+    }
+
+    pub fn no_transform(mut self) -> Self {
+        self.no_transform = true;
+        self
+    }
+
+    pub fn must_revalidate(mut self) -> Self {
+        self.must_revalidate = true;
+        self
+    }
+
+    pub fn proxy_revalidate(mut self) -> Self {
+        self.proxy_revalidate = true;
+        self
+    }
+
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    ///
+    /// Overrides `max-age` for shared caches such as CDNs and proxies.
+    ///
+    pub fn s_maxage(mut self, s_maxage: Duration) -> Self {
+        self.s_maxage = Some(s_maxage);
+        self
+    }
+
+    pub fn stale_while_revalidate(mut self, duration: Duration) -> Self {
+        self.stale_while_revalidate = Some(duration);
+        self
+    }
+
+    pub fn stale_if_error(mut self, duration: Duration) -> Self {
+        self.stale_if_error = Some(duration);
+        self
+    }
+
+    ///
+    /// Sets `Cache-Control` header replacing the existing one.
+    ///
+    pub fn apply(&self, headers: &mut Headers) {
+        headers.set("Cache-Control", self.to_string());
+    }
+}
+
+impl Display for CacheControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let flags = [
+            (self.public, "public"),
+            (self.private, "private"),
+            (self.no_cache, "no-cache"),
+            (self.no_store, "no-store"),
+            (self.no_transform, "no-transform"),
+            (self.must_revalidate, "must-revalidate"),
+            (self.proxy_revalidate, "proxy-revalidate"),
+            (self.immutable, "immutable"),
+        ];
+
+        let durations = [
+            (self.max_age, "max-age"),
+            (self.s_maxage, "s-maxage"),
+            (self.stale_while_revalidate, "stale-while-revalidate"),
+            (self.stale_if_error, "stale-if-error"),
+        ];
+
+        let mut directives: Vec<String> = flags
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, name)| name.to_string())
+            .collect();
+
+        for (duration, name) in durations {
+            if let Some(duration) = duration {
+                directives.push(format!("{}={}", name, duration.as_secs()));
+            }
+        }
+
+        write!(f, "{}", directives.join(", "))
+    }
+}
+
+///
+/// Attaches `Cache-Control` header to any boxed response.
+///
+pub trait SetCacheControl {
+    fn cache_control(self, cache_control: &CacheControl) -> Self;
+}
+
+impl<R: AbstractResponse + ?Sized> SetCacheControl for Box<R> {
+    fn cache_control(mut self, cache_control: &CacheControl) -> Self {
+        cache_control.apply(self.get_headers());
+        self
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use crate::core::headers::HeaderValue;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+
+    use super::{CacheControl, SetCacheControl};
+
+    #[test]
+    fn test_cache_control() {
+        let cache_control = CacheControl::new()
+            .public()
+            .max_age(Duration::from_secs(60))
+            .s_maxage(Duration::from_secs(120))
+            .stale_while_revalidate(Duration::from_secs(30));
+
+        assert_eq!(
+            "public, max-age=60, s-maxage=120, stale-while-revalidate=30",
+            cache_control.to_string()
+        );
+        assert_eq!("no-store", CacheControl::no_store_policy().to_string());
+
+        let mut response: Response = HttpResponse::ok().body("Hello");
+        response = response.cache_control(&CacheControl::immutable_policy());
+        assert_eq!(
+            Some("public, immutable, max-age=31536000".to_string()),
+            response.get_headers().value("Cache-Control")
+        );
+    }
+}
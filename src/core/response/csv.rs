@@ -0,0 +1,255 @@
+use serde::Serialize;
+use tokio_stream::{Stream as AsyncStream, StreamExt};
+
+use crate::core::headers::Headers;
+use crate::core::request::Request;
+use crate::core::response::writer::ResponseWriter;
+use crate::core::response::AbstractResponse;
+use crate::racoon_error;
+
+///
+/// Streams rows as CSV to the client using chunked transfer encoding. Rows are serialized one by
+/// one and flushed whenever the buffered bytes reach the stream buffer size, so large exports are
+/// never held in memory completely.
+///
+/// # Examples
+///
+/// ```
+/// use serde::Serialize;
+///
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+/// use racoon::core::response::csv::CsvResponse;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     id: u64,
+///     name: String,
+/// }
+///
+/// async fn export(request: Request) -> Response {
+///     let rows = tokio_stream::iter((0..1000).map(|id| User {
+///         id,
+///         name: format!("User {}", id),
+///     }));
+///
+///     CsvResponse::from(&request)
+///         .filename("users.csv")
+///         .send(rows)
+///         .await
+/// }
+/// ```
+///
+pub struct CsvResponse {
+    writer: ResponseWriter,
+    delimiter: u8,
+    has_headers: bool,
+}
+
+impl AbstractResponse for CsvResponse {
+    fn status(&self) -> (u32, String) {
+        self.writer.status()
+    }
+
+    fn serve_default(&mut self) -> bool {
+        false
+    }
+
+    fn get_headers(&mut self) -> &mut Headers {
+        self.writer.get_headers()
+    }
+
+    fn get_body(&mut self) -> &mut Vec<u8> {
+        self.writer.get_body()
+    }
+
+    fn should_close(&mut self) -> bool {
+        self.writer.should_close()
+    }
+}
+
+impl CsvResponse {
+    pub fn from(request: &Request) -> Self {
+        let writer =
+            ResponseWriter::from(request).header("Content-Type", "text/csv; charset=utf-8");
+
+        Self {
+            writer,
+            delimiter: b',',
+            has_headers: true,
+        }
+    }
+
+    ///
+    /// Asks browser to download the response as attachment with the given file name.
+    ///
+    pub fn filename<S: AsRef<str>>(mut self, filename: S) -> Self {
+        let filename = filename.as_ref().replace('"', "");
+        let value = format!("attachment; filename=\"{}\"", filename);
+        self.writer = self.writer.header("Content-Disposition", value);
+        self
+    }
+
+    pub fn header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.writer = self.writer.header(name, value);
+        self
+    }
+
+    ///
+    /// Field delimiter. Default is `,`.
+    ///
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    ///
+    /// Whether to write header row from the field names of the first row. Default is `true`.
+    ///
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    ///
+    /// Writes response headers and rows to the client.
+    ///
+    /// HTTP/1.0 clients do not support chunked transfer encoding, so raw bytes are written and the
+    /// connection is closed after the last row.
+    ///
+    pub async fn send<T, S>(mut self, mut rows: S) -> Box<Self>
+    where
+        T: Serialize,
+        S: AsyncStream<Item = T> + Unpin,
+    {
+        if self.writer.send_head().await.is_err() {
+            return Box::new(self);
+        }
+
+        let flush_size = self.writer.buffer_size().await;
+
+        // Header row is written only by the first writer.
+        let mut csv_writer = self.csv_writer(self.has_headers);
+
+        while let Some(row) = rows.next().await {
+            if let Err(error) = csv_writer.serialize(row) {
+                // Response head is already sent, so the only option left is to end the
+                // connection without terminating chunk.
+                racoon_error!("Failed to serialize CSV row. Error: {}", error);
+                self.writer.abort();
+                return Box::new(self);
+            }
+
+            let _ = csv_writer.flush();
+            if csv_writer.get_ref().len() >= flush_size {
+                let full_writer = std::mem::replace(&mut csv_writer, self.csv_writer(false));
+                let buffer = full_writer.into_inner().unwrap_or_default();
+
+                if self.writer.write_chunk(&buffer).await.is_err() {
+                    return Box::new(self);
+                }
+            }
+        }
+
+        let buffer = csv_writer.into_inner().unwrap_or_default();
+        if self.writer.write_chunk(&buffer).await.is_err() {
+            return Box::new(self);
+        }
+
+        self.writer = *self.writer.finish().await;
+        Box::new(self)
+    }
+
+    fn csv_writer(&self, has_headers: bool) -> ::csv::Writer<Vec<u8>> {
+        ::csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(has_headers)
+            .from_writer(vec![])
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use serde::Serialize;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::Headers;
+    use crate::core::stream::{Stream, TcpStreamWrapper};
+
+    use super::CsvResponse;
+
+    #[derive(Serialize)]
+    struct User {
+        id: u64,
+        name: &'static str,
+    }
+
+    async fn export(method: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        // Buffered rows are flushed once they reach the buffer size of 32 bytes.
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server_stream, 32).unwrap());
+
+        let mut request = request("/users.csv", Headers::new(), b"").await;
+        request.method = method.to_string();
+        request.stream = Arc::new(stream);
+
+        let rows = tokio_stream::iter(vec![
+            User {
+                id: 1,
+                name: "Doe; John",
+            },
+            User {
+                id: 2,
+                name: "Say \"hi\"",
+            },
+            User {
+                id: 3,
+                name: "Plain",
+            },
+        ]);
+        let response = CsvResponse::from(&request)
+            .delimiter(b';')
+            .filename("users.csv")
+            .send(rows)
+            .await;
+        drop(response);
+        drop(request);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        String::from_utf8(received).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_csv_response() {
+        let received = export("GET").await;
+        let (head, body) = received.split_once("\r\n\r\n").unwrap();
+        // Header order is not fixed, so keep the line ending of the last header.
+        let head = format!("{}\r\n", head.to_lowercase());
+        assert!(head.starts_with("http/1.1 200 ok\r\n"));
+        assert!(head.contains("content-type: text/csv; charset=utf-8\r\n"));
+        assert!(head.contains("content-disposition: attachment; filename=\"users.csv\""));
+        assert!(head.contains("transfer-encoding: chunked"));
+
+        // First chunk is flushed after the second row crosses the buffer size.
+        assert_eq!(
+            "25\r\nid;name\n1;\"Doe; John\"\n2;\"Say \"\"hi\"\"\"\n\r\n\
+            8\r\n3;Plain\n\r\n\
+            0\r\n\r\n",
+            body
+        );
+
+        let received = export("HEAD").await;
+        let (head, body) = received.split_once("\r\n\r\n").unwrap();
+        assert!(head.to_lowercase().contains("content-type: text/csv"));
+        assert!(body.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::request::Request;
+use crate::racoon_debug;
+
+///
+/// Informational `103 Early Hints` response sent before the final response so that browsers can
+/// start preloading resources while the handler is still working.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::early_hints::EarlyHints;
+/// use racoon::core::response::status::ResponseStatus;
+///
+/// async fn home(request: Request) -> Response {
+///     let _ = EarlyHints::new()
+///         .preload("/static/style.css", "style")
+///         .preload("/static/app.js", "script")
+///         .send(&request)
+///         .await;
+///
+///     // Slow work here
+///     HttpResponse::ok().body("Home")
+/// }
+/// ```
+///
+/// More information: <https://developer.mozilla.org/en-US/docs/Web/HTTP/Status/103>
+///
+pub struct EarlyHints {
+    headers: Headers,
+}
+
+impl Default for EarlyHints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EarlyHints {
+    pub fn new() -> Self {
+        Self {
+            headers: Headers::new(),
+        }
+    }
+
+    ///
+    /// Adds raw `Link` header value. For example: `</style.css>; rel=preload; as=style`.
+    ///
+    pub fn link<S: AsRef<str>>(mut self, value: S) -> Self {
+        self.headers.set_multiple("Link", value.as_ref());
+        self
+    }
+
+    ///
+    /// Adds `Link` header with `rel=preload` for the given url and destination type.
+    ///
+    pub fn preload<S: AsRef<str>>(self, url: S, as_type: S) -> Self {
+        let value = format!("<{}>; rel=preload; as={}", url.as_ref(), as_type.as_ref());
+        self.link(value)
+    }
+
+    ///
+    /// Adds `Link` header with `rel=preconnect` for the given origin.
+    ///
+    pub fn preconnect<S: AsRef<str>>(self, origin: S) -> Self {
+        let value = format!("<{}>; rel=preconnect", origin.as_ref());
+        self.link(value)
+    }
+
+    ///
+    /// Adds additional header to the informational response.
+    ///
+    pub fn header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.headers.set_multiple(name, value);
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = b"HTTP/1.1 103 Early Hints\r\n".to_vec();
+
+        for (name, values) in self.headers.iter() {
+            for value in values {
+                bytes.extend(name.as_bytes());
+                bytes.extend(b": ");
+                bytes.extend(value);
+                bytes.extend(b"\r\n");
+            }
+        }
+
+        bytes.extend(b"\r\n");
+        bytes
+    }
+
+    ///
+    /// Writes informational response to the client. The final response must still be returned
+    /// from the view.
+    ///
+    /// HTTP/1.0 clients do not understand informational responses, so nothing is written for them.
+    ///
+    pub async fn send(self, request: &Request) -> std::io::Result<()> {
+        if request.http_version == 0 {
+            racoon_debug!("Skipping early hints for HTTP/1.0 client.");
+            return Ok(());
+        }
+
+        let bytes = self.to_bytes();
+        request.stream.write_chunk(&bytes).await
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::EarlyHints;
+
+    #[test]
+    fn test_early_hints_bytes() {
+        let early_hints = EarlyHints::new().preload("/style.css", "style");
+        let bytes = early_hints.to_bytes();
+
+        assert_eq!(
+            b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n".to_vec(),
+            bytes
+        );
+    }
+}
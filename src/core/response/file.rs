@@ -0,0 +1,665 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+use crate::core::etag::{etag_matches, NOT_MODIFIED_HEADERS};
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::path::merge_headers;
+use crate::core::request::Request;
+use crate::core::response::{head_to_bytes, AbstractResponse};
+use crate::core::stream::Stream;
+use crate::racoon_debug;
+
+///
+/// Sends file from the disk to the client. On Linux, plain TCP connections use `sendfile` so the
+/// file content is transferred by the kernel without copying through userspace buffers. Other
+/// streams fall back to buffered copy.
+///
+/// `ETag` header is generated from the modification time and the size of the file, and
+/// `304 Not Modified` is sent without the content if the `If-None-Match` header of the request
+/// matches. Small files are served from the memory if [`FileCache`] is registered with
+/// `Server::with_state` or set with `cache`. Headers set on the request such as the session
+/// cookies are sent with the head.
+///
+/// The path is used as it is, so values received from the client must be validated to prevent
+/// directory traversal.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+/// use racoon::core::response::file::FileResponse;
+///
+/// async fn download(request: Request) -> Response {
+///     FileResponse::from(&request)
+///         .filename("report.pdf")
+///         .send("/var/reports/report.pdf")
+///         .await
+/// }
+/// ```
+///
+pub struct FileResponse {
+    stream: Arc<Stream>,
+    method: String,
+    status_code: u32,
+    status_text: String,
+    headers: Headers,
+    request_headers: Arc<tokio::sync::Mutex<Headers>>,
+    body: Vec<u8>,
+    serve_default: bool,
+    keep_alive: bool,
+    if_none_match: Option<String>,
+    cache: Option<FileCache>,
+}
+
+impl AbstractResponse for FileResponse {
+    fn status(&self) -> (u32, String) {
+        (self.status_code, self.status_text.to_owned())
+    }
+
+    fn serve_default(&mut self) -> bool {
+        self.serve_default
+    }
+
+    fn get_headers(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    fn get_body(&mut self) -> &mut Vec<u8> {
+        &mut self.body
+    }
+
+    fn should_close(&mut self) -> bool {
+        !self.keep_alive
+    }
+}
+
+impl FileResponse {
+    pub fn from(request: &Request) -> Self {
+        Self {
+            stream: request.stream.clone(),
+            method: request.method.clone(),
+            status_code: 200,
+            status_text: "OK".to_string(),
+            headers: Headers::new(),
+            request_headers: request.response_headers.clone(),
+            body: vec![],
+            serve_default: false,
+            keep_alive: true,
+            if_none_match: request.headers.value("If-None-Match"),
+            cache: request.state::<FileCache>().cloned(),
+        }
+    }
+
+    ///
+    /// Serves the file from the given cache instead of the one registered with the server state.
+    ///
+    pub fn cache(mut self, cache: FileCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    ///
+    /// Asks browser to download the response as attachment with the given file name.
+    ///
+    pub fn filename<S: AsRef<str>>(mut self, filename: S) -> Self {
+        let filename = filename.as_ref().replace('"', "");
+        let value = format!("attachment; filename=\"{}\"", filename);
+        self.headers.set("Content-Disposition", value);
+        self
+    }
+
+    ///
+    /// Overrides the content type guessed from the file extension.
+    ///
+    pub fn content_type(mut self, value: &str) -> Self {
+        self.headers.set("Content-Type", value);
+        self
+    }
+
+    pub fn header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.headers.set(name, value);
+        self
+    }
+
+    ///
+    /// Writes response headers and file content to the client. Responds with `404 Not Found` if
+    /// the file does not exist or cannot be read.
+    ///
+    pub async fn send<P: AsRef<Path>>(mut self, path: P) -> Box<Self> {
+        let path = path.as_ref();
+
+        let metadata = match tokio::fs::metadata(path).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => return self.not_found(),
+            Err(error) => {
+                racoon_debug!(
+                    "Failed to read metadata of file {:?}. Error: {}",
+                    path,
+                    error
+                );
+                return self.not_found();
+            }
+        };
+        let length = metadata.len();
+        let modified = metadata.modified().ok();
+
+        if let Some(modified) = modified {
+            if self.headers.value("ETag").is_none() {
+                self.headers.set("ETag", file_etag(modified, length));
+            }
+        }
+
+        let etag = self.headers.value("ETag");
+        if let (Some(if_none_match), Some(etag)) = (&self.if_none_match, etag) {
+            let is_cacheable_method = self.method == "GET" || self.method == "HEAD";
+            if is_cacheable_method && etag_matches(if_none_match, &etag) {
+                return self.not_modified();
+            }
+        }
+
+        if self.headers.value("Content-Type").is_none() {
+            let content_type = content_type_from_path(path);
+            self.headers.set("Content-Type", content_type);
+        }
+
+        let cache = match (self.cache.clone(), modified) {
+            (Some(cache), Some(modified)) if length <= cache.max_file_size => {
+                Some((cache, modified))
+            }
+            _ => None,
+        };
+
+        if let Some((cache, modified)) = cache {
+            let content = match cache.get(path, modified, length) {
+                Some(content) => content,
+                None => match read_file(path).await {
+                    Ok(content) => cache.insert(path, modified, length, content),
+                    Err(error) => {
+                        racoon_debug!("Failed to read file {:?}. Error: {}", path, error);
+                        return self.not_found();
+                    }
+                },
+            };
+            return self.send_content(&content).await;
+        }
+
+        let mut file = match File::open(path).await {
+            Ok(file) => file,
+            Err(error) => {
+                racoon_debug!("Failed to open file {:?}. Error: {}", path, error);
+                return self.not_found();
+            }
+        };
+
+        self.headers.set("Content-Length", length.to_string());
+        merge_headers(&self.request_headers, &mut self.headers).await;
+
+        let head_bytes = head_to_bytes(self.status_code, &self.status_text, &self.headers);
+        if let Err(error) = self.stream.write_chunk(&head_bytes).await {
+            racoon_debug!("Failed to write file response head. Error: {}", error);
+            self.keep_alive = false;
+            return Box::new(self);
+        }
+
+        if self.method == "HEAD" {
+            return Box::new(self);
+        }
+
+        if let Err(error) = self.stream.send_file(&mut file, 0, length).await {
+            // Content-Length is already sent, so the connection cannot be reused.
+            racoon_debug!("Failed to send file {:?}. Error: {}", path, error);
+            self.keep_alive = false;
+        }
+
+        Box::new(self)
+    }
+
+    ///
+    /// Writes the file content read from the cache with the head in a single write.
+    ///
+    async fn send_content(mut self, content: &[u8]) -> Box<Self> {
+        self.headers
+            .set("Content-Length", content.len().to_string());
+        merge_headers(&self.request_headers, &mut self.headers).await;
+
+        let mut response_bytes = head_to_bytes(self.status_code, &self.status_text, &self.headers);
+        if self.method != "HEAD" {
+            response_bytes.extend_from_slice(content);
+        }
+
+        if let Err(error) = self.stream.write_chunk(&response_bytes).await {
+            racoon_debug!("Failed to write cached file response. Error: {}", error);
+            self.keep_alive = false;
+        }
+        Box::new(self)
+    }
+
+    fn not_modified(mut self) -> Box<Self> {
+        let mut headers = Headers::new();
+        for name in NOT_MODIFIED_HEADERS.iter().chain(&["ETag"]) {
+            for value in self.headers.multiple_values(name) {
+                headers.set_multiple(name, value);
+            }
+        }
+
+        self.status_code = 304;
+        self.status_text = "Not Modified".to_string();
+        self.headers = headers;
+        self.serve_default = true;
+        Box::new(self)
+    }
+
+    fn not_found(mut self) -> Box<Self> {
+        let body = b"Not Found".to_vec();
+
+        self.status_code = 404;
+        self.status_text = "Not Found".to_string();
+        self.headers = Headers::new();
+        self.headers
+            .set("Content-Type", "text/plain; charset=utf-8");
+        self.headers.set("Content-Length", body.len().to_string());
+        self.body = body;
+        self.serve_default = true;
+        Box::new(self)
+    }
+}
+
+///
+/// Weak ETag of the file from the modification time and the size, so the content is not hashed.
+///
+fn file_etag(modified: SystemTime, length: u64) -> String {
+    let modified = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("W/\"{:x}-{:x}\"", modified, length)
+}
+
+async fn read_file(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path).await?;
+    let mut content = vec![];
+    file.read_to_end(&mut content).await?;
+    Ok(content)
+}
+
+struct CachedFile {
+    content: Arc<Vec<u8>>,
+    modified: SystemTime,
+    length: u64,
+    last_used: u64,
+}
+
+struct FileCacheState {
+    entries: HashMap<PathBuf, CachedFile>,
+    size: u64,
+    counter: u64,
+}
+
+///
+/// In-memory cache of the small files sent frequently such as CSS and JS, so they are not read
+/// from the disk for every request. Least recently used files are evicted when the total size of
+/// the cached content exceeds the max size.
+///
+/// Cached file is read again when its modification time or size changes. Modification time is
+/// checked with the file metadata on each request, so the file system must update it on write.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::response::file::FileCache;
+/// use racoon::core::server::Server;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.with_state(FileCache::new(32 * 1024 * 1024).max_file_size(256 * 1024));
+/// ```
+///
+#[derive(Clone)]
+pub struct FileCache {
+    max_size: u64,
+    max_file_size: u64,
+    state: Arc<Mutex<FileCacheState>>,
+}
+
+impl FileCache {
+    ///
+    /// Creates cache holding at most `max_size` bytes of the file content.
+    ///
+    pub fn new(max_size: u64) -> Self {
+        Self {
+            max_size,
+            max_file_size: max_size.min(64 * 1024),
+            state: Arc::new(Mutex::new(FileCacheState {
+                entries: HashMap::new(),
+                size: 0,
+                counter: 0,
+            })),
+        }
+    }
+
+    ///
+    /// Larger files are sent from the disk without caching. Default is 64 KiB.
+    ///
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size.min(self.max_size);
+        self
+    }
+
+    ///
+    /// Total size of the cached content in bytes.
+    ///
+    pub fn size(&self) -> u64 {
+        self.state
+            .lock()
+            .map(|state| state.size)
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .map(|state| state.entries.len())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+            state.size = 0;
+        }
+    }
+
+    ///
+    /// Returns the cached content if the file is not modified since it was cached. Stale content
+    /// is removed.
+    ///
+    fn get(&self, path: &Path, modified: SystemTime, length: u64) -> Option<Arc<Vec<u8>>> {
+        let mut state = self.state.lock().ok()?;
+        state.counter += 1;
+        let counter = state.counter;
+
+        let entry = state.entries.get_mut(path)?;
+        if entry.modified == modified && entry.length == length {
+            entry.last_used = counter;
+            return Some(entry.content.clone());
+        }
+
+        if let Some(stale) = state.entries.remove(path) {
+            state.size -= stale.content.len() as u64;
+        }
+        None
+    }
+
+    ///
+    /// Caches the content read from the disk. Content whose size differs from the metadata was
+    /// modified while reading, so it is returned without caching.
+    ///
+    fn insert(
+        &self,
+        path: &Path,
+        modified: SystemTime,
+        length: u64,
+        content: Vec<u8>,
+    ) -> Arc<Vec<u8>> {
+        let content = Arc::new(content);
+        let size = content.len() as u64;
+        if size != length || size > self.max_file_size {
+            return content;
+        }
+
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return content,
+        };
+        state.counter += 1;
+        let counter = state.counter;
+
+        if let Some(previous) = state.entries.remove(path) {
+            state.size -= previous.content.len() as u64;
+        }
+
+        while state.size + size > self.max_size {
+            let lru_path = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.to_owned());
+
+            match lru_path.and_then(|lru_path| state.entries.remove(&lru_path)) {
+                Some(evicted) => state.size -= evicted.content.len() as u64,
+                None => break,
+            }
+        }
+
+        state.size += size;
+        state.entries.insert(
+            path.to_path_buf(),
+            CachedFile {
+                content: content.clone(),
+                modified,
+                length,
+                last_used: counter,
+            },
+        );
+        content
+    }
+}
+
+///
+/// Guesses content type from the file extension. Returns `application/octet-stream` for unknown
+/// extensions.
+///
+pub fn content_type_from_path(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "wasm" => "application/wasm",
+        "mp4" => "video/mp4",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::path::{Path as UrlPath, View};
+    use crate::core::response::Response;
+    use crate::core::server::Server;
+    use crate::core::stream::{AbstractStream, Stream, TcpStreamWrapper};
+    use crate::core::testing::request::TestRequest;
+    use crate::core::testing::TestServer;
+
+    use super::{content_type_from_path, FileCache, FileResponse};
+
+    #[test]
+    fn test_content_type_from_path() {
+        assert_eq!("image/png", content_type_from_path(Path::new("a/logo.PNG")));
+        assert_eq!(
+            "application/octet-stream",
+            content_type_from_path(Path::new("archive"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_file() {
+        let content: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        let file_path = std::env::temp_dir().join(format!("racoon-{}.bin", uuid::Uuid::new_v4()));
+        tokio::fs::write(&file_path, &content).await.unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let stream = TcpStreamWrapper::from(server_stream, 1024).unwrap();
+
+        let reader = tokio::spawn(async move {
+            let mut received: Vec<u8> = vec![];
+            let mut buffer = vec![0u8; 8096];
+            while received.len() < 199_900 {
+                let read_size = client.read(&mut buffer).await.unwrap();
+                if read_size == 0 {
+                    break;
+                }
+                received.extend(&buffer[..read_size]);
+            }
+            received
+        });
+
+        let mut file = tokio::fs::File::open(&file_path).await.unwrap();
+        stream.send_file(&mut file, 100, 199_900).await.unwrap();
+
+        let received = reader.await.unwrap();
+        assert_eq!(content[100..], received[..]);
+
+        let _ = tokio::fs::remove_file(&file_path).await;
+    }
+
+    #[tokio::test]
+    async fn test_file_response_request_headers() {
+        let file_path = std::env::temp_dir().join(format!("racoon-{}.css", uuid::Uuid::new_v4()));
+        tokio::fs::write(&file_path, "body {}").await.unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server_stream, 1024).unwrap());
+
+        let mut request = request("/style.css", Headers::new(), b"").await;
+        request.method = "GET".to_string();
+        request.stream = Arc::new(stream);
+
+        // Session cookie is set on the request before the head is sent.
+        request
+            .response_headers
+            .lock()
+            .await
+            .set("Set-Cookie", "sessionid=1");
+
+        let response = FileResponse::from(&request).send(&file_path).await;
+        drop(response);
+        drop(request);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        let received = String::from_utf8(received).unwrap();
+        let (head, body) = received.split_once("\r\n\r\n").unwrap();
+        assert!(head.to_lowercase().contains("set-cookie: sessionid=1"));
+        assert_eq!("body {}", body);
+
+        let _ = tokio::fs::remove_file(&file_path).await;
+    }
+
+    #[tokio::test]
+    async fn test_file_cache() {
+        let view: View = |request| {
+            Box::pin(async move {
+                let path = request.query_params.get("path").unwrap()[0].clone();
+                request.session.set("visited", "1").await.unwrap();
+                let response: Response = FileResponse::from(&request).send(path).await;
+                response
+            })
+        };
+
+        let cache = FileCache::new(1024).max_file_size(100);
+        let mut server = Server::bind("127.0.0.1:0");
+        server
+            .with_state(cache.clone())
+            .urls(vec![UrlPath::new("/", view)]);
+        let client = TestServer::new(server).client();
+
+        let file_path = std::env::temp_dir().join(format!("racoon-{}.css", uuid::Uuid::new_v4()));
+        tokio::fs::write(&file_path, "body {}").await.unwrap();
+        let url = format!("/?path={}", file_path.display());
+
+        let mut response = client.get(&url).await;
+        assert_eq!(b"body {}".to_vec(), *response.get_body());
+        assert_eq!(1, cache.len());
+        assert_eq!(7, cache.size());
+        let cookie = response.get_headers().value("Set-Cookie").unwrap();
+        assert!(cookie.starts_with("sessionid="));
+        let etag = response.get_headers().value("ETag").unwrap();
+
+        let mut response = client
+            .send(TestRequest::get(&url).header("If-None-Match", &etag))
+            .await;
+        assert_eq!(304, response.status().0);
+        assert!(response.get_body().is_empty());
+
+        // Modified file is read again.
+        tokio::fs::write(&file_path, "body { margin: 0 }")
+            .await
+            .unwrap();
+        let file = std::fs::File::options()
+            .write(true)
+            .open(&file_path)
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        let mut response = client.get(&url).await;
+        assert_eq!(b"body { margin: 0 }".to_vec(), *response.get_body());
+        assert_ne!(Some(etag), response.get_headers().value("ETag"));
+        assert_eq!(18, cache.size());
+
+        // Least recently used file is evicted when the max size is exceeded.
+        let lru = FileCache::new(1000).max_file_size(500);
+        let modified = SystemTime::now();
+        for name in ["a.js", "b.js"] {
+            lru.insert(Path::new(name), modified, 500, vec![0; 500]);
+        }
+        assert!(lru.get(Path::new("a.js"), modified, 500).is_some());
+        lru.insert(Path::new("c.js"), modified, 500, vec![0; 500]);
+        assert!(lru.get(Path::new("b.js"), modified, 500).is_none());
+        assert!(lru.get(Path::new("a.js"), modified, 500).is_some());
+        assert_eq!(1000, lru.size());
+
+        // Larger files and stale content are not cached.
+        lru.insert(Path::new("d.js"), modified, 501, vec![0; 501]);
+        lru.insert(Path::new("e.js"), modified, 400, vec![0; 300]);
+        assert_eq!(2, lru.len());
+
+        lru.clear();
+        assert!(lru.is_empty());
+        let _ = tokio::fs::remove_file(&file_path).await;
+    }
+}
@@ -1,13 +1,23 @@
+pub mod builder;
+pub mod cache_control;
+pub mod csv;
+pub mod early_hints;
+pub mod file;
+pub mod sse;
 pub mod status;
+pub mod writer;
+pub mod zip;
 
 use std::collections::HashMap;
+use std::io::Write;
 use std::time::Duration;
 
 use serde_json::json;
 
 use crate::core::cookie;
+use crate::core::html;
 use crate::core::headers::{HeaderValue, Headers};
-use crate::core::response::status::ResponseStatus;
+use crate::core::response::status::{ResponseStatus, StatusCode};
 
 pub trait AbstractResponse: Send {
     fn status(&self) -> (u32, String);
@@ -15,6 +25,14 @@ pub trait AbstractResponse: Send {
     fn get_headers(&mut self) -> &mut Headers;
     fn get_body(&mut self) -> &mut Vec<u8>;
     fn should_close(&mut self) -> bool;
+
+    ///
+    /// Returns status code of the response for classification in middleware and logs.
+    ///
+    fn status_code(&self) -> StatusCode {
+        let (status_code, _) = self.status();
+        StatusCode::from(status_code as u16)
+    }
 }
 
 pub type Response = Box<dyn AbstractResponse>;
@@ -56,8 +74,46 @@ impl HttpResponse {
         self
     }
 
+    ///
+    /// Specifies whether the connection can be reused for next request after this response is
+    /// served. If `false`, the server sends `Connection: close` and closes the connection.
+    ///
     pub fn keep_alive(mut self, is_alive: bool) -> Self {
-        self.keep_alive = !is_alive;
+        self.keep_alive = is_alive;
+        self
+    }
+
+    ///
+    /// Closes the connection after this response is served. Useful after authentication failures
+    /// or while draining connections.
+    ///
+    pub fn close(self) -> Self {
+        self.keep_alive(false)
+    }
+
+    ///
+    /// Advertises keep-alive parameters to the client with `Keep-Alive` header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use racoon::core::response::HttpResponse;
+    /// use racoon::core::response::status::ResponseStatus;
+    ///
+    /// let response = HttpResponse::ok()
+    ///     .keep_alive_params(Duration::from_secs(5), Some(100))
+    ///     .body("Hello World");
+    /// ```
+    ///
+    pub fn keep_alive_params(mut self, timeout: Duration, max_requests: Option<usize>) -> Self {
+        let mut value = format!("timeout={}", timeout.as_secs());
+        if let Some(max_requests) = max_requests {
+            value.push_str(&format!(", max={}", max_requests));
+        }
+
+        self.headers.set("Keep-Alive", value);
         self
     }
 
@@ -71,7 +127,14 @@ impl HttpResponse {
         Box::new(self)
     }
 
-    pub fn body<S: AsRef<str>>(mut self, data: S) -> Box<Self> {
+    pub fn body<S: AsRef<str>>(self, data: S) -> Box<Self> {
+        self.bytes(data.as_ref())
+    }
+
+    ///
+    /// Sets raw bytes as response body.
+    ///
+    pub fn bytes<B: AsRef<[u8]>>(mut self, data: B) -> Box<Self> {
         let data = data.as_ref();
 
         self.headers
@@ -85,7 +148,7 @@ impl HttpResponse {
             }
         }
 
-        self.body = data.as_bytes().to_vec();
+        self.body = data.to_vec();
 
         Box::new(self)
     }
@@ -123,27 +186,63 @@ impl ResponseStatus for HttpResponse {
 }
 
 pub fn response_to_bytes(response: &mut Box<dyn AbstractResponse>) -> Vec<u8> {
-    let mut response_bytes: Vec<u8> = Vec::with_capacity(response.get_body().len());
     let (status_code, status_text) = response.status();
+    let body_length = response.get_body().len();
+    let mut response_bytes =
+        write_head(status_code, &status_text, response.get_headers(), body_length);
+
+    // Body start
+    response_bytes.extend_from_slice(response.get_body());
+    response_bytes
+}
+
+///
+/// Returns status line and headers terminated by empty line without the response body.
+///
+pub fn response_head_to_bytes(response: &mut Box<dyn AbstractResponse>) -> Vec<u8> {
+    let (status_code, status_text) = response.status();
+    head_to_bytes(status_code, &status_text, response.get_headers())
+}
+
+pub fn head_to_bytes(status_code: u32, status_text: &str, headers: &Headers) -> Vec<u8> {
+    write_head(status_code, status_text, headers, 0)
+}
+
+///
+/// Writes status line and headers to the buffer allocated once with the space for `extra_capacity`
+/// bytes of the body.
+///
+fn write_head(
+    status_code: u32,
+    status_text: &str,
+    headers: &Headers,
+    extra_capacity: usize,
+) -> Vec<u8> {
+    // "HTTP/1.1 " + code + " " + text + "\r\n" and final "\r\n"
+    let mut capacity = 9 + 10 + 1 + status_text.len() + 2 + 2 + extra_capacity;
+    for (name, values) in headers.iter() {
+        for value in values {
+            capacity += name.len() + 2 + value.len() + 2;
+        }
+    }
+
+    let mut response_bytes: Vec<u8> = Vec::with_capacity(capacity);
 
     // Append header response start line
-    let response_header_begin = format!("HTTP/1.1 {} {}\r\n", status_code, status_text);
-    response_bytes.extend(response_header_begin.as_bytes());
+    // Writing to vector does not fail.
+    let _ = write!(response_bytes, "HTTP/1.1 {} {}\r\n", status_code, status_text);
 
     // Append headers
-    response.get_headers().iter().for_each(|(name, values)| {
+    headers.iter().for_each(|(name, values)| {
         for value in values {
-            response_bytes.extend(name.as_bytes());
-            response_bytes.extend(b": ");
-            response_bytes.extend(value);
-            response_bytes.extend(b"\r\n");
+            response_bytes.extend_from_slice(name.as_bytes());
+            response_bytes.extend_from_slice(b": ");
+            response_bytes.extend_from_slice(value);
+            response_bytes.extend_from_slice(b"\r\n");
         }
     });
 
-    response_bytes.extend(b"\r\n");
-
-    // Body start
-    response_bytes.extend(response.get_body().as_slice());
+    response_bytes.extend_from_slice(b"\r\n");
     response_bytes
 }
 
@@ -175,6 +274,21 @@ impl JsonResponse {
         Box::new(self)
     }
 
+    ///
+    /// Specifies whether the connection can be reused after this response is served.
+    ///
+    pub fn keep_alive(mut self, is_alive: bool) -> Self {
+        self.http_response = self.http_response.keep_alive(is_alive);
+        self
+    }
+
+    ///
+    /// Closes the connection after this response is served.
+    ///
+    pub fn close(self) -> Self {
+        self.keep_alive(false)
+    }
+
     ///
     /// Creates empty JSON object response.
     ///
@@ -228,3 +342,116 @@ impl ResponseStatus for JsonResponse {
         Self { http_response }
     }
 }
+
+///
+/// Response with `text/html` content type. Values received from the client must be escaped with
+/// `escape_html` or `format_html!` before they are placed in the body.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HtmlResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::shortcuts::SingleText;
+/// use racoon::format_html;
+///
+/// async fn hello(request: Request) -> Response {
+///     let name = request.query_params.value("name").cloned().unwrap_or_default();
+///     HtmlResponse::ok().body(format_html!("<h1>Hello {}</h1>", name))
+/// }
+/// ```
+///
+pub struct HtmlResponse {
+    http_response: HttpResponse,
+}
+
+impl HtmlResponse {
+    ///
+    /// Sets trusted html as response body. The html is not escaped.
+    ///
+    pub fn body<S: AsRef<str>>(mut self, html: S) -> Box<Self> {
+        self.http_response = *self.http_response.body(html);
+        Box::new(self)
+    }
+
+    ///
+    /// Renders minimal html page with escaped title and message. Useful for error pages.
+    ///
+    pub fn page<S: AsRef<str>>(self, title: S, message: S) -> Box<Self> {
+        let title = html::escape_html(title);
+        let message = html::escape_html(message);
+
+        let page = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n\
+            <body>\n<h1>{}</h1>\n<p>{}</p>\n</body>\n</html>\n",
+            title, title, message
+        );
+        self.body(page)
+    }
+
+    pub fn empty(self) -> Box<Self> {
+        self.body("")
+    }
+
+    ///
+    /// Specifies whether the connection can be reused after this response is served.
+    ///
+    pub fn keep_alive(mut self, is_alive: bool) -> Self {
+        self.http_response = self.http_response.keep_alive(is_alive);
+        self
+    }
+
+    ///
+    /// Closes the connection after this response is served.
+    ///
+    pub fn close(self) -> Self {
+        self.keep_alive(false)
+    }
+
+    ///
+    /// Sets cookie in max age from "/" path.
+    ///
+    pub fn set_cookie<S: AsRef<str>>(&mut self, name: S, value: S, max_age: Duration) {
+        self.http_response.set_cookie(name, value, max_age);
+    }
+
+    ///
+    /// Removes cookie from "/" path.
+    ///
+    pub fn remove_cookie<S: AsRef<str>>(&mut self, name: S) {
+        self.http_response.remove_cookie(name)
+    }
+}
+
+impl AbstractResponse for HtmlResponse {
+    fn status(&self) -> (u32, String) {
+        self.http_response.status()
+    }
+
+    fn serve_default(&mut self) -> bool {
+        self.http_response.serve_default
+    }
+
+    fn get_headers(&mut self) -> &mut Headers {
+        self.http_response.get_headers()
+    }
+
+    fn get_body(&mut self) -> &mut Vec<u8> {
+        self.http_response.get_body()
+    }
+
+    fn should_close(&mut self) -> bool {
+        self.http_response.should_close()
+    }
+}
+
+impl ResponseStatus for HtmlResponse {
+    fn with_status(status_code: u32, status_text: &str) -> Self {
+        let mut http_response = HttpResponse::with_status(status_code, status_text);
+        let headers = http_response.get_headers();
+        headers.set("Content-Type", "text/html; charset=utf-8");
+
+        Self { http_response }
+    }
+}
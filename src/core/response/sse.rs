@@ -0,0 +1,277 @@
+use std::time::Duration;
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::request::Request;
+use crate::core::response::writer::ResponseWriter;
+use crate::core::response::AbstractResponse;
+use crate::core::server::ShutdownSignal;
+
+///
+/// Server-sent event. Multiline data is sent as multiple `data` fields.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: String,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    pub fn data<S: AsRef<str>>(data: S) -> Self {
+        Self {
+            data: data.as_ref().to_string(),
+            ..Self::default()
+        }
+    }
+
+    ///
+    /// Event ID sent back by the browser in the `Last-Event-ID` header on reconnect.
+    ///
+    pub fn id<S: AsRef<str>>(mut self, id: S) -> Self {
+        self.id = Some(single_line(id.as_ref()));
+        self
+    }
+
+    ///
+    /// Event type dispatched to the listeners added with `addEventListener`.
+    ///
+    pub fn event<S: AsRef<str>>(mut self, event: S) -> Self {
+        self.event = Some(single_line(event.as_ref()));
+        self
+    }
+
+    ///
+    /// Delay after which the browser reconnects if the stream is disconnected.
+    ///
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    ///
+    /// More information: <https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation>
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+
+        if let Some(id) = &self.id {
+            bytes.extend(format!("id: {}\n", id).as_bytes());
+        }
+
+        if let Some(event) = &self.event {
+            bytes.extend(format!("event: {}\n", event).as_bytes());
+        }
+
+        if let Some(retry) = self.retry {
+            bytes.extend(format!("retry: {}\n", retry.as_millis()).as_bytes());
+        }
+
+        for line in self.data.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            bytes.extend(format!("data: {}\n", line).as_bytes());
+        }
+
+        bytes.extend(b"\n");
+        bytes
+    }
+}
+
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], "")
+}
+
+///
+/// Streams server-sent events to the client. When the browser reconnects, the ID of the last
+/// received event is available from `last_event_id`, so the handler can resume the stream from
+/// the next event.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+/// use racoon::core::response::sse::{Event, SseResponse};
+///
+/// async fn notifications(request: Request) -> Response {
+///     let mut sse = SseResponse::from(&request).retry(Duration::from_secs(5));
+///
+///     // Resumes after the last event received by the client.
+///     let start: u64 = match sse.last_event_id() {
+///         Some(id) => id.parse().unwrap_or(0) + 1,
+///         None => 0,
+///     };
+///
+///     for id in start..start + 10 {
+///         let event = Event::data(format!("Notification {}", id)).id(id.to_string());
+///         if sse.send(event).await.is_err() {
+///             // Client is disconnected.
+///             break;
+///         }
+///         tokio::time::sleep(Duration::from_secs(1)).await;
+///     }
+///
+///     sse.finish().await
+/// }
+/// ```
+///
+pub struct SseResponse {
+    writer: ResponseWriter,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+    shutdown: Option<ShutdownSignal>,
+}
+
+impl AbstractResponse for SseResponse {
+    fn status(&self) -> (u32, String) {
+        self.writer.status()
+    }
+
+    fn serve_default(&mut self) -> bool {
+        false
+    }
+
+    fn get_headers(&mut self) -> &mut Headers {
+        self.writer.get_headers()
+    }
+
+    fn get_body(&mut self) -> &mut Vec<u8> {
+        self.writer.get_body()
+    }
+
+    fn should_close(&mut self) -> bool {
+        self.writer.should_close()
+    }
+}
+
+impl SseResponse {
+    pub fn from(request: &Request) -> Self {
+        // Disables buffering by the reverse proxies, so events are delivered immediately.
+        let writer = ResponseWriter::from(request)
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .header("X-Accel-Buffering", "no");
+
+        Self {
+            writer,
+            last_event_id: request.headers.value("Last-Event-ID"),
+            retry: None,
+            shutdown: request.extensions.get::<ShutdownSignal>().cloned(),
+        }
+    }
+
+    pub fn header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.writer = self.writer.header(name, value);
+        self
+    }
+
+    ///
+    /// Reconnection delay of the stream sent to the client before the first event.
+    ///
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    ///
+    /// ID of the last event received by the client before reconnecting.
+    ///
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    ///
+    /// Writes response head and the reconnection delay if not already sent. Called automatically
+    /// by the first `send`.
+    ///
+    pub async fn send_head(&mut self) -> std::io::Result<()> {
+        self.writer.send_head().await?;
+
+        if let Some(retry) = self.retry.take() {
+            let retry = format!("retry: {}\n\n", retry.as_millis());
+            self.writer.write_chunk(retry).await?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Sends the event and waits until the socket accepts it. Returns error if the client is
+    /// disconnected or the server is shutting down.
+    ///
+    pub async fn send(&mut self, event: Event) -> std::io::Result<()> {
+        self.check_shutdown()?;
+        self.send_head().await?;
+        self.writer.write_chunk(event.to_bytes()).await
+    }
+
+    ///
+    /// Sends the comment ignored by the client. Useful to keep the idle connection open through
+    /// the proxies.
+    ///
+    pub async fn comment<S: AsRef<str>>(&mut self, comment: S) -> std::io::Result<()> {
+        self.check_shutdown()?;
+        self.send_head().await?;
+        let comment = format!(": {}\n\n", single_line(comment.as_ref()));
+        self.writer.write_chunk(comment).await
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.writer.is_disconnected()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown
+            .as_ref()
+            .is_some_and(|shutdown| shutdown.is_shutting_down())
+    }
+
+    ///
+    /// Resolves when the server starts shutting down, so the handler waiting for the next event
+    /// can finish the stream within the drain timeout.
+    ///
+    pub async fn wait_shutdown(&self) {
+        match &self.shutdown {
+            Some(shutdown) => shutdown.wait().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn check_shutdown(&self) -> std::io::Result<()> {
+        if self.is_shutting_down() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "Server is shutting down.",
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn finish(mut self) -> Box<Self> {
+        if self.send_head().await.is_ok() {
+            self.writer = *self.writer.finish().await;
+        }
+        Box::new(self)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use super::Event;
+
+    #[test]
+    fn test_event_to_bytes() {
+        let event = Event::data("first\r\nsecond")
+            .id("4\n2")
+            .event("update")
+            .retry(Duration::from_secs(3));
+
+        assert_eq!(
+            "id: 42\nevent: update\nretry: 3000\ndata: first\ndata: second\n\n",
+            String::from_utf8(event.to_bytes()).unwrap()
+        );
+    }
+}
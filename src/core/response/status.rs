@@ -4,6 +4,14 @@
 pub trait ResponseStatus: Sized {
     fn with_status(status_code: u32, status_text: &str) -> Self;
 
+    ///
+    /// Creates response with standard reason phrase of the status code.
+    ///
+    fn from_code(status_code: u16) -> Self {
+        let status = StatusCode::from(status_code);
+        Self::with_status(status.as_u32(), status.reason_phrase())
+    }
+
     fn r#continue() -> Self {
         Self::with_status(100, "Continue")
     }
@@ -114,7 +122,7 @@ pub trait ResponseStatus: Sized {
     /// Experimental. Expect behaviour to change in the future.
     ///
     fn payment_required() -> Self {
-        Self::with_status(403, "Payment Required")
+        Self::with_status(402, "Payment Required")
     }
 
     fn forbidden() -> Self {
@@ -158,7 +166,7 @@ pub trait ResponseStatus: Sized {
     }
 
     fn payload_too_large() -> Self {
-        Self::with_status(412, "Payload Too Large")
+        Self::with_status(413, "Payload Too Large")
     }
 
     fn uri_too_long() -> Self {
@@ -178,7 +186,7 @@ pub trait ResponseStatus: Sized {
     }
 
     fn im_a_teapot() -> Self {
-        Self::with_status(418, "I',m a teapot")
+        Self::with_status(418, "I'm a teapot")
     }
 
     fn misdirected_request() -> Self {
@@ -268,4 +276,168 @@ pub trait ResponseStatus: Sized {
     fn network_authentication_required() -> Self {
         Self::with_status(511, "Network Authentication Required")
     }
-}
\ No newline at end of file
+}
+///
+/// HTTP status code with helpers for classification.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::response::status::StatusCode;
+///
+/// let status = StatusCode::from(404);
+/// assert_eq!(404, status.as_u16());
+/// assert_eq!("Not Found", status.reason_phrase());
+/// assert!(status.is_client_error());
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    pub fn as_u32(&self) -> u32 {
+        self.0 as u32
+    }
+
+    ///
+    /// Returns standard reason phrase. Returns empty text for unknown status code.
+    ///
+    pub fn reason_phrase(&self) -> &'static str {
+        match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            102 => "Processing",
+            103 => "Early Hints",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            207 => "Multi-Status",
+            208 => "Already Reported",
+            226 => "IM Used",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            305 => "Use Proxy",
+            306 => "Unused",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Payload Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            418 => "I'm a teapot",
+            421 => "Misdirected Request",
+            422 => "Unprocessable Content",
+            423 => "Locked",
+            424 => "Failed Dependency",
+            425 => "Too Early",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            451 => "Unavailable For Legal Reasons",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            506 => "Variant Also Negotiates",
+            507 => "Insufficient Storage",
+            508 => "Loop Detected",
+            510 => "Not Extended",
+            511 => "Network Authentication Required",
+            _ => "",
+        }
+    }
+
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    pub fn is_redirection(&self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+
+    ///
+    /// Returns true for both client and server errors.
+    ///
+    pub fn is_error(&self) -> bool {
+        self.is_client_error() || self.is_server_error()
+    }
+}
+
+impl From<u16> for StatusCode {
+    fn from(status_code: u16) -> Self {
+        Self(status_code)
+    }
+}
+
+impl From<StatusCode> for u16 {
+    fn from(status_code: StatusCode) -> Self {
+        status_code.0
+    }
+}
+
+impl std::fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.0, self.reason_phrase())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::response::{AbstractResponse, HttpResponse};
+
+    use super::{ResponseStatus, StatusCode};
+
+    #[test]
+    fn test_status_code() {
+        let status = StatusCode::from(503);
+        assert!(status.is_server_error());
+        assert!(status.is_error());
+        assert!(!status.is_success());
+        assert_eq!("503 Service Unavailable", status.to_string());
+        assert_eq!("", StatusCode::from(599).reason_phrase());
+
+        let response = HttpResponse::from_code(413);
+        assert_eq!((413, "Payload Too Large".to_string()), response.status());
+        assert!(response.status_code().is_client_error());
+    }
+}
@@ -0,0 +1,307 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::path::merge_headers;
+use crate::core::request::Request;
+use crate::core::response::{head_to_bytes, AbstractResponse};
+use crate::core::stream::Stream;
+use crate::racoon_debug;
+
+///
+/// Low-level writer for streaming response body in chunks. Each `write_chunk` waits until the
+/// bytes are accepted by the socket, so a slow client slows down the producer instead of
+/// buffering unlimited data in memory. Returns error when the client is disconnected, so the
+/// handler can stop the work early.
+///
+/// Headers set on the request such as the session cookies are added to the head, since the
+/// middlewares cannot change the response after the head is sent.
+///
+/// Chunked transfer encoding is used for HTTP/1.1 clients. For HTTP/1.0 clients, raw bytes are
+/// written and the connection is closed after the response.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+/// use racoon::core::response::writer::ResponseWriter;
+///
+/// async fn progress(request: Request) -> Response {
+///     let mut writer = ResponseWriter::from(&request).header("Content-Type", "text/plain");
+///
+///     for i in 0..10 {
+///         if writer.write_chunk(format!("Step {}\n", i)).await.is_err() {
+///             // Client is disconnected.
+///             break;
+///         }
+///         tokio::time::sleep(Duration::from_millis(100)).await;
+///     }
+///
+///     writer.finish().await
+/// }
+/// ```
+///
+pub struct ResponseWriter {
+    stream: Arc<Stream>,
+    is_chunked: bool,
+    status_code: u32,
+    status_text: String,
+    headers: Headers,
+    request_headers: Option<Arc<Mutex<Headers>>>,
+    body: Vec<u8>,
+    head_sent: bool,
+    disconnected: bool,
+    keep_alive: bool,
+    skip_body: bool,
+}
+
+impl AbstractResponse for ResponseWriter {
+    fn status(&self) -> (u32, String) {
+        (self.status_code, self.status_text.to_owned())
+    }
+
+    fn serve_default(&mut self) -> bool {
+        false
+    }
+
+    fn get_headers(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    fn get_body(&mut self) -> &mut Vec<u8> {
+        &mut self.body
+    }
+
+    fn should_close(&mut self) -> bool {
+        !self.keep_alive
+    }
+}
+
+impl ResponseWriter {
+    ///
+    /// Creates writer for the request. For `HEAD` requests, only the head is written and the
+    /// body chunks are discarded.
+    ///
+    pub fn from(request: &Request) -> Self {
+        let mut writer = Self::new(request.stream.clone(), request.http_version);
+        writer.skip_body = request.method == "HEAD";
+        writer.request_headers = Some(request.response_headers.clone());
+        writer
+    }
+
+    pub(crate) fn new(stream: Arc<Stream>, http_version: u8) -> Self {
+        Self {
+            stream,
+            is_chunked: http_version != 0,
+            status_code: 200,
+            status_text: "OK".to_string(),
+            headers: Headers::new(),
+            request_headers: None,
+            body: vec![],
+            head_sent: false,
+            disconnected: false,
+            keep_alive: true,
+            skip_body: false,
+        }
+    }
+
+    ///
+    /// Sets response status. Default is `200 OK`. Has no effect after the head is sent.
+    ///
+    pub fn set_status(mut self, status_code: u32, status_text: &str) -> Self {
+        self.status_code = status_code;
+        self.status_text = status_text.to_string();
+        self
+    }
+
+    ///
+    /// Sets header. Has no effect after the head is sent.
+    ///
+    pub fn header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.headers.set(name, value);
+        self
+    }
+
+    ///
+    /// Stream buffer size. Useful for batching small writes.
+    ///
+    pub async fn buffer_size(&self) -> usize {
+        self.stream.buffer_size().await
+    }
+
+    ///
+    /// Returns true if writing to the client failed.
+    ///
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    ///
+    /// Writes status line and headers if not already sent. Called automatically by the first
+    /// `write_chunk`.
+    ///
+    pub async fn send_head(&mut self) -> std::io::Result<()> {
+        if self.head_sent {
+            return Ok(());
+        }
+
+        if let Some(request_headers) = &self.request_headers {
+            merge_headers(request_headers, &mut self.headers).await;
+        }
+
+        if self.is_chunked {
+            self.headers.set("Transfer-Encoding", "chunked");
+        } else {
+            self.headers.set("Connection", "close");
+            self.keep_alive = false;
+        }
+
+        let head_bytes = head_to_bytes(self.status_code, &self.status_text, &self.headers);
+        self.head_sent = true;
+        self.write_raw(&head_bytes).await
+    }
+
+    ///
+    /// Writes bytes to the client and waits until the socket accepts them. Empty data is ignored
+    /// because empty chunk marks the end of the body.
+    ///
+    pub async fn write_chunk<B: AsRef<[u8]>>(&mut self, data: B) -> std::io::Result<()> {
+        self.send_head().await?;
+
+        let data = data.as_ref();
+        if data.is_empty() || self.skip_body {
+            return Ok(());
+        }
+
+        if self.is_chunked {
+            let mut chunk = format!("{:X}\r\n", data.len()).into_bytes();
+            chunk.extend(data);
+            chunk.extend(b"\r\n");
+            self.write_raw(&chunk).await
+        } else {
+            self.write_raw(data).await
+        }
+    }
+
+    ///
+    /// Ends the response body. If nothing is written yet, sends response with empty body.
+    ///
+    pub async fn finish(mut self) -> Box<Self> {
+        if self.send_head().await.is_err() {
+            return Box::new(self);
+        }
+
+        // Body is not terminated if the response is aborted, so the client can detect the failure.
+        if self.is_chunked && self.keep_alive && !self.skip_body {
+            let _ = self.write_raw(b"0\r\n\r\n").await;
+        }
+
+        Box::new(self)
+    }
+
+    ///
+    /// Marks the connection to be closed after an error, since the response cannot be completed.
+    ///
+    pub fn abort(&mut self) {
+        self.keep_alive = false;
+    }
+
+    async fn write_raw(&mut self, data: &[u8]) -> std::io::Result<()> {
+        if let Err(error) = self.stream.write_chunk(data).await {
+            racoon_debug!(
+                "Client disconnected while writing response. Error: {}",
+                error
+            );
+            self.disconnected = true;
+            self.keep_alive = false;
+            return Err(error);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::stream::{Stream, TcpStreamWrapper, TestStreamWrapper};
+
+    use super::ResponseWriter;
+
+    #[tokio::test]
+    async fn test_response_writer_chunks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server_stream, 1024).unwrap());
+
+        let mut writer = ResponseWriter::new(Arc::new(stream), 1).header("X-Test", "1");
+        writer.write_chunk("Hello").await.unwrap();
+        writer.write_chunk("").await.unwrap();
+        writer.write_chunk(" World").await.unwrap();
+        let writer = writer.finish().await;
+        assert!(!writer.is_disconnected());
+        drop(writer);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        let received = String::from_utf8(received).unwrap();
+
+        assert!(received.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(received.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(received.ends_with("\r\n\r\n5\r\nHello\r\n6\r\n World\r\n0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_response_writer_disconnected() {
+        let stream: Stream = Box::new(TestStreamWrapper::new(vec![], 1024));
+        let stream = Arc::new(stream);
+        stream.shutdown().await.unwrap();
+
+        let mut writer = ResponseWriter::new(stream, 1);
+        assert!(writer.write_chunk("data").await.is_err());
+        assert!(writer.is_disconnected());
+    }
+
+    #[tokio::test]
+    async fn test_response_writer_request_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server_stream, 1024).unwrap());
+
+        let mut request = request("/", Headers::new(), b"").await;
+        request.stream = Arc::new(stream);
+
+        // Session cookie is set on the request before the head is sent.
+        request
+            .response_headers
+            .lock()
+            .await
+            .set("Set-Cookie", "sessionid=1");
+
+        let mut writer = ResponseWriter::from(&request);
+        writer.write_chunk("Hello").await.unwrap();
+        drop(writer.finish().await);
+        drop(request);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        let received = String::from_utf8(received).unwrap();
+        assert!(received.contains("Set-Cookie: sessionid=1\r\n"));
+        assert!(received.ends_with("\r\n\r\n5\r\nHello\r\n0\r\n\r\n"));
+    }
+}
@@ -0,0 +1,469 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{Datelike, Local, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::Crc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::core::headers::Headers;
+use crate::core::request::Request;
+use crate::core::response::writer::ResponseWriter;
+use crate::core::response::AbstractResponse;
+use crate::{racoon_debug, racoon_error};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+// Sizes are written in data descriptor after the content and file name is UTF-8.
+const GENERAL_PURPOSE_FLAGS: u16 = 0x0008 | 0x0800;
+const VERSION_NEEDED: u16 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZipCompression {
+    /// Files are stored without compression. Useful for already compressed files like images.
+    Stored,
+    /// Files are compressed with deflate.
+    Deflated,
+}
+
+impl ZipCompression {
+    fn method(&self) -> u16 {
+        match self {
+            ZipCompression::Stored => 0,
+            ZipCompression::Deflated => 8,
+        }
+    }
+}
+
+enum ZipSource {
+    Path(PathBuf),
+    Bytes(Vec<u8>),
+    Reader(Box<dyn AsyncRead + Send + Unpin>),
+}
+
+struct ZipEntry {
+    name: String,
+    source: ZipSource,
+}
+
+struct CentralDirectoryRecord {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    offset: u64,
+}
+
+///
+/// Builds ZIP archive on the fly while it is being sent to the client, so the archive is never
+/// written to the disk or held in memory completely.
+///
+/// The archive uses classic ZIP format, so total size of the archive must not exceed 4 GiB and
+/// number of entries must not exceed 65535.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+/// use racoon::core::response::zip::{ZipCompression, ZipStreamResponse};
+///
+/// async fn download_attachments(request: Request) -> Response {
+///     ZipStreamResponse::from(&request)
+///         .filename("attachments.zip")
+///         .compression(ZipCompression::Deflated)
+///         .add_file("invoice.pdf", "/var/attachments/invoice.pdf")
+///         .add_bytes("README.txt", "Attachments of order #120")
+///         .send()
+///         .await
+/// }
+/// ```
+///
+pub struct ZipStreamResponse {
+    writer: ResponseWriter,
+    compression: ZipCompression,
+    entries: Vec<ZipEntry>,
+    pending: Vec<u8>,
+    written: u64,
+}
+
+impl AbstractResponse for ZipStreamResponse {
+    fn status(&self) -> (u32, String) {
+        self.writer.status()
+    }
+
+    fn serve_default(&mut self) -> bool {
+        false
+    }
+
+    fn get_headers(&mut self) -> &mut Headers {
+        self.writer.get_headers()
+    }
+
+    fn get_body(&mut self) -> &mut Vec<u8> {
+        self.writer.get_body()
+    }
+
+    fn should_close(&mut self) -> bool {
+        self.writer.should_close()
+    }
+}
+
+impl ZipStreamResponse {
+    pub fn from(request: &Request) -> Self {
+        Self::new(ResponseWriter::from(request))
+    }
+
+    fn new(writer: ResponseWriter) -> Self {
+        Self {
+            writer: writer.header("Content-Type", "application/zip"),
+            compression: ZipCompression::Deflated,
+            entries: vec![],
+            pending: vec![],
+            written: 0,
+        }
+    }
+
+    ///
+    /// Asks browser to download the archive with the given file name.
+    ///
+    pub fn filename<S: AsRef<str>>(mut self, filename: S) -> Self {
+        let filename = filename.as_ref().replace('"', "");
+        let value = format!("attachment; filename=\"{}\"", filename);
+        self.writer = self.writer.header("Content-Disposition", value);
+        self
+    }
+
+    pub fn header<B: AsRef<[u8]>>(mut self, name: &str, value: B) -> Self {
+        self.writer = self.writer.header(name, value);
+        self
+    }
+
+    ///
+    /// Compression method for all the entries. Default is `ZipCompression::Deflated`.
+    ///
+    pub fn compression(mut self, compression: ZipCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    ///
+    /// Adds file from the disk. The file is opened only when the entry is being written and
+    /// skipped if it cannot be opened.
+    ///
+    pub fn add_file<S: AsRef<str>, P: Into<PathBuf>>(mut self, name: S, path: P) -> Self {
+        self.entries.push(ZipEntry {
+            name: name.as_ref().to_string(),
+            source: ZipSource::Path(path.into()),
+        });
+        self
+    }
+
+    pub fn add_bytes<S: AsRef<str>, B: AsRef<[u8]>>(mut self, name: S, data: B) -> Self {
+        self.entries.push(ZipEntry {
+            name: name.as_ref().to_string(),
+            source: ZipSource::Bytes(data.as_ref().to_vec()),
+        });
+        self
+    }
+
+    ///
+    /// Adds entry whose content is read from the async reader until EOF.
+    ///
+    pub fn add_reader<S: AsRef<str>, R: AsyncRead + Send + Unpin + 'static>(
+        mut self,
+        name: S,
+        reader: R,
+    ) -> Self {
+        self.entries.push(ZipEntry {
+            name: name.as_ref().to_string(),
+            source: ZipSource::Reader(Box::new(reader)),
+        });
+        self
+    }
+
+    ///
+    /// Writes response headers and the archive to the client.
+    ///
+    /// HTTP/1.0 clients do not support chunked transfer encoding, so raw bytes are written and the
+    /// connection is closed after the archive.
+    ///
+    pub async fn send(mut self) -> Box<Self> {
+        if self.writer.send_head().await.is_err() {
+            return Box::new(self);
+        }
+
+        if let Err(error) = self.write_archive().await {
+            // Response head is already sent, so the only option left is to end the connection
+            // without completing the archive.
+            racoon_debug!("Failed to stream ZIP archive. Error: {}", error);
+            self.writer.abort();
+            return Box::new(self);
+        }
+
+        self.writer = *self.writer.finish().await;
+        Box::new(self)
+    }
+
+    async fn write_archive(&mut self) -> std::io::Result<()> {
+        let flush_size = self.writer.buffer_size().await;
+        let (time, date) = dos_datetime();
+        let method = self.compression.method();
+
+        let entries = std::mem::take(&mut self.entries);
+        let mut records = vec![];
+
+        for entry in entries {
+            let mut reader: Box<dyn AsyncRead + Send + Unpin> = match entry.source {
+                ZipSource::Path(path) => match tokio::fs::File::open(&path).await {
+                    Ok(file) => Box::new(file),
+                    Err(error) => {
+                        racoon_error!("Skipping ZIP entry {:?}. Error: {}", path, error);
+                        continue;
+                    }
+                },
+                ZipSource::Bytes(data) => Box::new(std::io::Cursor::new(data)),
+                ZipSource::Reader(reader) => reader,
+            };
+
+            let offset = self.written;
+            let name = entry.name.as_bytes();
+
+            let mut header = vec![];
+            header.extend(LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+            header.extend(VERSION_NEEDED.to_le_bytes());
+            header.extend(GENERAL_PURPOSE_FLAGS.to_le_bytes());
+            header.extend(method.to_le_bytes());
+            header.extend(time.to_le_bytes());
+            header.extend(date.to_le_bytes());
+            // CRC and sizes are written in data descriptor.
+            header.extend([0u8; 12]);
+            header.extend((name.len() as u16).to_le_bytes());
+            header.extend(0u16.to_le_bytes());
+            header.extend(name);
+            self.push(&header, flush_size).await?;
+
+            let mut crc = Crc::new();
+            let mut encoder = match self.compression {
+                ZipCompression::Deflated => {
+                    Some(DeflateEncoder::new(vec![], flate2::Compression::default()))
+                }
+                ZipCompression::Stored => None,
+            };
+
+            let mut compressed_size = 0u64;
+            let mut uncompressed_size = 0u64;
+            let mut buffer = vec![0u8; flush_size];
+
+            loop {
+                let read_size = reader.read(&mut buffer).await?;
+                if read_size == 0 {
+                    break;
+                }
+
+                let chunk = &buffer[..read_size];
+                crc.update(chunk);
+                uncompressed_size += read_size as u64;
+
+                if let Some(encoder) = encoder.as_mut() {
+                    encoder.write_all(chunk)?;
+                    let compressed = std::mem::take(encoder.get_mut());
+                    compressed_size += compressed.len() as u64;
+                    self.push(&compressed, flush_size).await?;
+                } else {
+                    compressed_size += read_size as u64;
+                    self.push(chunk, flush_size).await?;
+                }
+            }
+
+            if let Some(encoder) = encoder {
+                let compressed = encoder.finish()?;
+                compressed_size += compressed.len() as u64;
+                self.push(&compressed, flush_size).await?;
+            }
+
+            if compressed_size > u32::MAX as u64
+                || uncompressed_size > u32::MAX as u64
+                || self.written > u32::MAX as u64
+            {
+                return Err(std::io::Error::other("ZIP archive exceeds 4 GiB limit."));
+            }
+
+            let mut descriptor = vec![];
+            descriptor.extend(DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+            descriptor.extend(crc.sum().to_le_bytes());
+            descriptor.extend((compressed_size as u32).to_le_bytes());
+            descriptor.extend((uncompressed_size as u32).to_le_bytes());
+            self.push(&descriptor, flush_size).await?;
+
+            records.push(CentralDirectoryRecord {
+                name: entry.name,
+                method,
+                crc: crc.sum(),
+                compressed_size,
+                uncompressed_size,
+                offset,
+            });
+        }
+
+        if records.len() > u16::MAX as usize {
+            return Err(std::io::Error::other("ZIP archive exceeds 65535 entries."));
+        }
+
+        let central_directory_offset = self.written;
+        for record in &records {
+            let name = record.name.as_bytes();
+
+            let mut header = vec![];
+            header.extend(CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+            header.extend(VERSION_NEEDED.to_le_bytes()); // Version made by
+            header.extend(VERSION_NEEDED.to_le_bytes());
+            header.extend(GENERAL_PURPOSE_FLAGS.to_le_bytes());
+            header.extend(record.method.to_le_bytes());
+            header.extend(time.to_le_bytes());
+            header.extend(date.to_le_bytes());
+            header.extend(record.crc.to_le_bytes());
+            header.extend((record.compressed_size as u32).to_le_bytes());
+            header.extend((record.uncompressed_size as u32).to_le_bytes());
+            header.extend((name.len() as u16).to_le_bytes());
+            // Extra field length, comment length, disk number, internal and external attributes
+            header.extend([0u8; 12]);
+            header.extend((record.offset as u32).to_le_bytes());
+            header.extend(name);
+            self.push(&header, flush_size).await?;
+        }
+
+        let central_directory_size = self.written - central_directory_offset;
+        if self.written > u32::MAX as u64 {
+            return Err(std::io::Error::other("ZIP archive exceeds 4 GiB limit."));
+        }
+
+        let mut end_record = vec![];
+        end_record.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        // Disk numbers
+        end_record.extend([0u8; 4]);
+        end_record.extend((records.len() as u16).to_le_bytes());
+        end_record.extend((records.len() as u16).to_le_bytes());
+        end_record.extend((central_directory_size as u32).to_le_bytes());
+        end_record.extend((central_directory_offset as u32).to_le_bytes());
+        // Comment length
+        end_record.extend(0u16.to_le_bytes());
+        self.push(&end_record, flush_size).await?;
+
+        let pending = std::mem::take(&mut self.pending);
+        self.writer.write_chunk(&pending).await
+    }
+
+    ///
+    /// Buffers archive bytes and writes them to the client when buffer reaches flush size.
+    ///
+    async fn push(&mut self, data: &[u8], flush_size: usize) -> std::io::Result<()> {
+        self.written += data.len() as u64;
+        self.pending.extend(data);
+
+        if self.pending.len() >= flush_size {
+            let pending = std::mem::take(&mut self.pending);
+            self.writer.write_chunk(&pending).await?;
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// Returns current local time and date in MS-DOS format used by ZIP.
+///
+fn dos_datetime() -> (u16, u16) {
+    let now = Local::now();
+    let year = now.year().clamp(1980, 2107) as u16;
+
+    let time =
+        ((now.hour() as u16) << 11) | ((now.minute() as u16) << 5) | (now.second() as u16 / 2);
+    let date = ((year - 1980) << 9) | ((now.month() as u16) << 5) | now.day() as u16;
+    (time, date)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Read;
+    use std::sync::Arc;
+
+    use flate2::read::DeflateDecoder;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::response::writer::ResponseWriter;
+    use crate::core::stream::{Stream, TcpStreamWrapper};
+
+    use super::{ZipCompression, ZipStreamResponse};
+
+    fn u16_at(data: &[u8], position: usize) -> usize {
+        u16::from_le_bytes([data[position], data[position + 1]]) as usize
+    }
+
+    fn u32_at(data: &[u8], position: usize) -> usize {
+        u32::from_le_bytes(data[position..position + 4].try_into().unwrap()) as usize
+    }
+
+    #[tokio::test]
+    async fn test_zip_stream_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let (server_stream, _) = listener.accept().await.unwrap();
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server_stream, 64).unwrap());
+
+        let large_text = "racoon ".repeat(1000);
+        let response = ZipStreamResponse::new(ResponseWriter::new(Arc::new(stream), 0))
+            .compression(ZipCompression::Deflated)
+            .add_bytes("hello.txt", "Hello World")
+            .add_reader(
+                "large.txt",
+                std::io::Cursor::new(large_text.clone().into_bytes()),
+            )
+            .add_file("missing.txt", "/path/does/not/exist")
+            .send()
+            .await;
+        drop(response);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+
+        let head_end = received.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let archive = &received[head_end..];
+        assert_eq!(b"PK\x03\x04", &archive[..4]);
+
+        // Reads entries from central directory.
+        let end_record = &archive[archive.len() - 22..];
+        assert_eq!(2, u16_at(end_record, 10));
+        let mut position = u32_at(end_record, 16);
+
+        let mut entries = vec![];
+        for _ in 0..2 {
+            let compressed_size = u32_at(archive, position + 20);
+            let name_length = u16_at(archive, position + 28);
+            let offset = u32_at(archive, position + 42);
+            let name =
+                String::from_utf8(archive[position + 46..position + 46 + name_length].to_vec());
+
+            let data_start = offset + 30 + u16_at(archive, offset + 26);
+            let mut decoder =
+                DeflateDecoder::new(&archive[data_start..data_start + compressed_size]);
+            let mut content = String::new();
+            decoder.read_to_string(&mut content).unwrap();
+
+            entries.push((name.unwrap(), content));
+            position += 46 + name_length;
+        }
+
+        assert_eq!(
+            ("hello.txt".to_string(), "Hello World".to_string()),
+            entries[0]
+        );
+        assert_eq!(("large.txt".to_string(), large_text), entries[1]);
+    }
+}
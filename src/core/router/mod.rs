@@ -0,0 +1,1089 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::core::cors::Cors;
+use crate::core::headers::Headers;
+use crate::core::path::{self, Path, PathParams, Paths, RouteMatch, View};
+use crate::core::request::Request;
+
+///
+/// Policy for requests whose path differs from the registered route only by trailing slash.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TrailingSlash {
+    /// `/users` and `/users/` are different routes.
+    #[default]
+    Strict,
+    /// `/users` and `/users/` are handled by the same route.
+    Ignore,
+    /// Redirects to the registered form with `301 Moved Permanently`.
+    MovedPermanently,
+    /// Redirects to the registered form with `308 Permanent Redirect`. Unlike 301, clients keep
+    /// the request method and body.
+    PermanentRedirect,
+}
+
+///
+/// Name of the matched route like `/users/{id:int}` inserted into the request extensions.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedPath(pub String);
+
+///
+/// Metadata and tags of the matched route inserted into the request extensions. Middlewares can
+/// read them to change the behavior for the routes, like skipping authentication of the routes
+/// tagged as `public`, instead of matching the path prefixes.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::path::Path;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::router::RouteMetadata;
+/// use racoon::view;
+///
+/// async fn home(request: Request) -> Response {
+///     let metadata = RouteMetadata::of(&request).cloned().unwrap_or_default();
+///     assert!(metadata.has_tag("public"));
+///     assert_eq!(Some("home"), metadata.get("page"));
+///     HttpResponse::ok().body("Home")
+/// }
+///
+/// let paths = vec![
+///     Path::get("/", view!(home)).tag("public").metadata("page", "home"),
+/// ];
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteMetadata {
+    values: Arc<HashMap<String, String>>,
+    tags: Arc<Vec<String>>,
+}
+
+impl RouteMetadata {
+    ///
+    /// Returns the metadata of the route matched by the request.
+    ///
+    pub fn of(request: &Request) -> Option<&RouteMetadata> {
+        request.extensions.get::<RouteMetadata>()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|value| value == tag)
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.tags.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, key: String, value: String) {
+        Arc::make_mut(&mut self.values).insert(key, value);
+    }
+
+    pub(crate) fn add_tag(&mut self, tag: String) {
+        if !self.has_tag(&tag) {
+            Arc::make_mut(&mut self.tags).push(tag);
+        }
+    }
+
+    ///
+    /// Adds the values and tags of the other metadata. Existing values are kept.
+    ///
+    pub(crate) fn inherit(&mut self, other: &RouteMetadata) {
+        for (key, value) in other.values.iter() {
+            if !self.values.contains_key(key) {
+                self.insert(key.clone(), value.clone());
+            }
+        }
+
+        for tag in other.tags.iter() {
+            self.add_tag(tag.clone());
+        }
+    }
+}
+
+pub enum RouteResult<'a> {
+    Found {
+        path: &'a Path,
+        params: PathParams,
+    },
+    /// Path exists but does not accept the request method. Contains the allowed methods.
+    MethodNotAllowed(Vec<String>),
+    /// Request should be redirected to the location. Query string of the request is appended by
+    /// the server.
+    Redirect {
+        status_code: u16,
+        location: String,
+    },
+    /// CORS preflight request for the registered route. Contains the response headers.
+    Preflight(Headers),
+    NotFound,
+}
+
+///
+/// Fallback view for unmatched `GET` and `HEAD` requests under the prefix. Useful for single page
+/// applications using history mode routing where the client side router handles the path, so
+/// the view usually serves `index.html`.
+///
+/// Requests whose last path segment has a file extension like `/app/main.js` are not handled, so
+/// missing assets still receive `404 Not Found`. Excluded prefixes such as `/api` also respond
+/// normally.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+/// use racoon::core::response::Response;
+/// use racoon::core::response::file::FileResponse;
+/// use racoon::core::router::SpaFallback;
+/// use racoon::core::server::Server;
+/// use racoon::view;
+///
+/// async fn index(request: Request) -> Response {
+///     FileResponse::from(&request)
+///         .send("dist/index.html")
+///         .await
+/// }
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.spa_fallback(SpaFallback::new("/", view!(index)).exclude("/api"));
+/// ```
+///
+#[derive(Clone)]
+pub struct SpaFallback {
+    prefix: String,
+    excludes: Vec<String>,
+    path: Path,
+}
+
+impl SpaFallback {
+    pub fn new<S: AsRef<str>>(prefix: S, view: View) -> Self {
+        let prefix = normalize_prefix(prefix.as_ref());
+
+        Self {
+            path: Path::new(&prefix, view),
+            prefix,
+            excludes: vec![],
+        }
+    }
+
+    ///
+    /// Requests under the excluded prefix are not handled by the fallback.
+    ///
+    pub fn exclude<S: AsRef<str>>(mut self, prefix: S) -> Self {
+        self.excludes.push(normalize_prefix(prefix.as_ref()));
+        self
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    ///
+    /// Returns true if the fallback should handle the request.
+    ///
+    pub fn is_match(&self, method: &str, request_path: &str) -> bool {
+        if method != "GET" && method != "HEAD" {
+            return false;
+        }
+
+        if !has_prefix(request_path, &self.prefix) {
+            return false;
+        }
+
+        if self
+            .excludes
+            .iter()
+            .any(|exclude| has_prefix(request_path, exclude))
+        {
+            return false;
+        }
+
+        // Paths with file extension are treated as assets.
+        let last_segment = request_path.rsplit('/').next().unwrap_or_default();
+        !last_segment.contains('.')
+    }
+}
+
+fn normalize_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim().trim_end_matches('/');
+    if prefix.starts_with('/') {
+        prefix.to_string()
+    } else {
+        format!("/{}", prefix)
+    }
+}
+
+fn has_prefix(request_path: &str, prefix: &str) -> bool {
+    if prefix == "/" {
+        return true;
+    }
+
+    match request_path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+///
+/// Lowercases the static parts of the route pattern leaving the param names unchanged.
+///
+fn lowercase_pattern(pattern: &str) -> String {
+    let mut lowercased = String::with_capacity(pattern.len());
+    let mut depth = 0;
+
+    for c in pattern.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+
+        if depth == 0 {
+            lowercased.push(c.to_ascii_lowercase());
+        } else {
+            lowercased.push(c);
+        }
+    }
+    lowercased
+}
+
+///
+/// Decodes percent-encoded unreserved characters and removes dot-segments as described in
+/// RFC 3986 section 6.2.2.
+///
+fn normalize_path(request_path: &str) -> String {
+    // Such as asterisk form used by OPTIONS request.
+    if !request_path.starts_with('/') {
+        return request_path.to_string();
+    }
+
+    let bytes = request_path.as_bytes();
+    let mut decoded = String::with_capacity(request_path.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                    decoded.push(byte as char);
+                    i += 3;
+                    continue;
+                }
+            }
+        }
+
+        // Multibyte characters are copied as it is.
+        let c = request_path[i..].chars().next().unwrap();
+        decoded.push(c);
+        i += c.len_utf8();
+    }
+
+    let mut segments: Vec<&str> = vec![];
+    for segment in decoded.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    // Path ending with dot-segment refers to the directory.
+    if decoded.ends_with("/.") || decoded.ends_with("/..") {
+        segments.push("");
+    }
+
+    if segments.is_empty() {
+        return "/".to_string();
+    }
+    format!("/{}", segments.join("/"))
+}
+
+///
+/// Route table which resolves request path and method to the registered path.
+///
+pub struct Router {
+    paths: Paths,
+    routes: matchit::Router<Paths>,
+    trailing_slash: TrailingSlash,
+    case_insensitive: bool,
+    normalize_paths: bool,
+    spa_fallbacks: Vec<SpaFallback>,
+    cors: Option<Cors>,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self {
+            paths: vec![],
+            routes: matchit::Router::new(),
+            trailing_slash: TrailingSlash::default(),
+            case_insensitive: false,
+            normalize_paths: false,
+            spa_fallbacks: vec![],
+            cors: None,
+        }
+    }
+
+    ///
+    /// Builds route table from the paths. Trailing slash policy is used for the paths which do not
+    /// specify their own policy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path pattern is invalid or the same path is registered twice for the same
+    /// request method. Also panics if the path conflicts with another path, or would never be
+    /// reached since the path registered earlier with the same pattern accepts all its requests.
+    /// The message names both paths.
+    ///
+    pub fn from_paths(paths: &[Path], trailing_slash: TrailingSlash) -> Self {
+        Self {
+            paths: paths.to_vec(),
+            routes: Self::build_routes(paths, false),
+            trailing_slash,
+            ..Self::new()
+        }
+    }
+
+    ///
+    /// Matches the static parts of the path case-insensitively, so `/About` and `/ABOUT` are
+    /// handled by `/about` route. Path params keep the case sent by the client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the paths differing only by case are registered for the same request method.
+    ///
+    pub fn case_insensitive(mut self, enabled: bool) -> Self {
+        if self.case_insensitive != enabled {
+            self.routes = Self::build_routes(&self.paths, enabled);
+            self.case_insensitive = enabled;
+        }
+        self
+    }
+
+    ///
+    /// Decodes percent-encoded unreserved characters like `%7E` and removes dot-segments like
+    /// `/a/./b/../c` from the request path before matching.
+    ///
+    pub fn normalize_paths(mut self, enabled: bool) -> Self {
+        self.normalize_paths = enabled;
+        self
+    }
+
+    fn build_routes(paths: &[Path], case_insensitive: bool) -> matchit::Router<Paths> {
+        let mut routes = matchit::Router::new();
+
+        // Paths with the same pattern are grouped, so they can be selected by request method.
+        let mut groups: Vec<(String, Paths)> = vec![];
+        let mut catch_all_groups: Vec<(String, Paths)> = vec![];
+
+        for path in paths {
+            let pattern = if case_insensitive {
+                lowercase_pattern(path.pattern())
+            } else {
+                path.pattern().to_string()
+            };
+            let pattern = pattern.as_str();
+
+            if let Some((_, paths)) = groups.iter_mut().find(|(name, _)| name == pattern) {
+                // Paths with different parameter constraints may share the pattern, but the path
+                // accepted entirely by the earlier path would never be reached.
+                if let Some(existing) = paths.iter().find(|existing| path.is_shadowed_by(existing))
+                {
+                    if existing.name == path.name {
+                        panic!(
+                            "Duplicate path \"{}\" for the same request method.",
+                            path.name
+                        );
+                    }
+
+                    panic!(
+                        "Path \"{}\" is shadowed by \"{}\" registered earlier for the same request \
+                        method. Add parameter constraints to the earlier path or register the more \
+                        specific path first.",
+                        path.name, existing.name
+                    );
+                }
+
+                paths.push(path.clone());
+            } else {
+                groups.push((pattern.to_string(), vec![path.clone()]));
+            }
+
+            // Catch-all routes also match their prefix with empty remaining path.
+            if let Some(prefix) = path.catch_all_prefix() {
+                let prefix = if case_insensitive {
+                    lowercase_pattern(prefix)
+                } else {
+                    prefix.to_string()
+                };
+
+                if let Some((_, paths)) = catch_all_groups
+                    .iter_mut()
+                    .find(|(name, _)| *name == prefix)
+                {
+                    paths.push(path.clone());
+                } else {
+                    catch_all_groups.push((prefix, vec![path.clone()]));
+                }
+            }
+        }
+
+        let mut inserted: Vec<(String, String)> = vec![];
+
+        for (pattern, mut paths) in groups {
+            // Routes with host are more specific, so they are tried first.
+            paths.sort_by_key(|path| path.host_pattern().is_none());
+            let path_name = paths[0].name.to_string();
+
+            match routes.insert(pattern.clone(), paths) {
+                Ok(()) => inserted.push((pattern, path_name)),
+                Err(matchit::InsertError::Conflict { with }) => {
+                    // Conflicting route is reported as the common prefix if it was not inserted
+                    // as it is.
+                    let existing = inserted
+                        .iter()
+                        .find(|(pattern, _)| *pattern == with)
+                        .or_else(|| {
+                            inserted
+                                .iter()
+                                .find(|(pattern, _)| pattern.starts_with(&with))
+                        })
+                        .map(|(_, name)| name.as_str())
+                        .unwrap_or(&with);
+
+                    panic!(
+                        "Path \"{}\" conflicts with \"{}\" since both match the same requests. \
+                        Use the same parameter names and select between them with the parameter \
+                        constraints.",
+                        path_name, existing
+                    );
+                }
+                Err(error) => {
+                    panic!("Invalid path \"{}\" pattern. Error: {}", path_name, error);
+                }
+            }
+        }
+
+        // Ignored if the prefix is registered explicitly.
+        for (prefix, mut paths) in catch_all_groups {
+            paths.sort_by_key(|path| path.host_pattern().is_none());
+            let _ = routes.insert(prefix, paths);
+        }
+        routes
+    }
+
+    ///
+    /// Answers CORS preflight requests for the registered routes using the configuration.
+    ///
+    pub fn cors(mut self, cors: Cors) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    ///
+    /// Adds fallback for unmatched requests. Fallback with the longest prefix is used if multiple
+    /// fallbacks match.
+    ///
+    pub fn spa_fallback(mut self, fallback: SpaFallback) -> Self {
+        self.spa_fallbacks.push(fallback);
+        self.spa_fallbacks
+            .sort_by_key(|fallback| std::cmp::Reverse(fallback.prefix.len()));
+        self
+    }
+
+    ///
+    /// Resolves the route like `resolve` and additionally answers CORS preflight requests if CORS
+    /// is configured. Routes explicitly accepting `OPTIONS` method handle the preflight
+    /// themselves.
+    ///
+    pub fn route<'a>(
+        &'a self,
+        method: &str,
+        host: Option<&str>,
+        request_path: &str,
+        request_headers: &Headers,
+    ) -> RouteResult<'a> {
+        let cors = match &self.cors {
+            Some(cors) if Cors::is_preflight(method, request_headers) => cors,
+            _ => return self.resolve(method, host, request_path),
+        };
+
+        // Route accepting all the methods is also matched for OPTIONS method. Otherwise, methods
+        // registered for the path are received as allowed methods.
+        let result = self.resolve(method, host, request_path);
+        let route_methods = match &result {
+            RouteResult::Found { path, .. } if !path.allowed_methods().is_empty() => return result,
+            RouteResult::Found { .. } => vec![],
+            RouteResult::MethodNotAllowed(allowed_methods) => allowed_methods.clone(),
+            _ => return result,
+        };
+
+        RouteResult::Preflight(cors.preflight_headers(request_headers, &route_methods))
+    }
+
+    ///
+    /// Resolves the route for the request method, `Host` header value and request path.
+    ///
+    pub fn resolve<'a>(
+        &'a self,
+        method: &str,
+        host: Option<&str>,
+        request_path: &str,
+    ) -> RouteResult<'a> {
+        let request_path = self.normalized(request_path);
+        let request_path = request_path.as_ref();
+
+        match self.resolve_route(method, host, request_path) {
+            RouteResult::NotFound => {}
+            result => return result,
+        }
+
+        for fallback in &self.spa_fallbacks {
+            if fallback.is_match(method, request_path) {
+                return RouteResult::Found {
+                    path: &fallback.path,
+                    params: PathParams::new(),
+                };
+            }
+        }
+        RouteResult::NotFound
+    }
+
+    ///
+    /// Returns the first path registered with the pattern matching the request path regardless of
+    /// the request method.
+    ///
+    pub fn matched_path(&self, host: Option<&str>, request_path: &str) -> Option<&Path> {
+        let request_path = self.normalized(request_path);
+        let (_, _, path) = self.lookup("OPTIONS", host, &request_path)?;
+        Some(path)
+    }
+
+    fn normalized<'b>(&self, request_path: &'b str) -> Cow<'b, str> {
+        if self.normalize_paths {
+            Cow::Owned(normalize_path(request_path))
+        } else {
+            Cow::Borrowed(request_path)
+        }
+    }
+
+    fn resolve_route<'a>(
+        &'a self,
+        method: &str,
+        host: Option<&str>,
+        request_path: &str,
+    ) -> RouteResult<'a> {
+        if let Some((route_match, params, _)) = self.lookup(method, host, request_path) {
+            return Self::to_result(route_match, host, params);
+        }
+
+        if request_path == "/" || request_path.is_empty() {
+            return RouteResult::NotFound;
+        }
+
+        // Tries the same path with or without trailing slash.
+        let alternate_path = match request_path.strip_suffix('/') {
+            Some(stripped) => stripped.to_string(),
+            None => format!("{}/", request_path),
+        };
+
+        let (route_match, params, first_path) = match self.lookup(method, host, &alternate_path) {
+            Some(found) => found,
+            None => return RouteResult::NotFound,
+        };
+
+        let policy = first_path
+            .trailing_slash_policy()
+            .unwrap_or(self.trailing_slash);
+        match policy {
+            TrailingSlash::Strict => RouteResult::NotFound,
+            TrailingSlash::Ignore => Self::to_result(route_match, host, params),
+            TrailingSlash::MovedPermanently => RouteResult::Redirect {
+                status_code: 301,
+                location: alternate_path,
+            },
+            TrailingSlash::PermanentRedirect => RouteResult::Redirect {
+                status_code: 308,
+                location: alternate_path,
+            },
+        }
+    }
+
+    ///
+    /// Returns matched route, path params and the first path registered with the matched pattern.
+    /// Returns `None` if no route is found.
+    ///
+    fn lookup<'a>(
+        &'a self,
+        method: &str,
+        host: Option<&str>,
+        request_path: &str,
+    ) -> Option<(RouteMatch<'a>, PathParams, &'a Path)> {
+        let lowercase_path;
+        let match_path = if self.case_insensitive {
+            lowercase_path = request_path.to_ascii_lowercase();
+            lowercase_path.as_str()
+        } else {
+            request_path
+        };
+
+        let matched = self.routes.at(match_path).ok()?;
+
+        // ASCII lowercasing keeps the byte offsets, so param values are taken from the original
+        // path to preserve their case.
+        let matched_params: Vec<(&str, &str)> = matched
+            .params
+            .iter()
+            .map(|(key, value)| {
+                let start = value.as_ptr() as usize - match_path.as_ptr() as usize;
+                (key, &request_path[start..start + value.len()])
+            })
+            .collect();
+
+        // Routes with unsatisfied parameter constraints are treated as not found.
+        let route_match = path::select_route(matched.value, method, host, &matched_params);
+        if let RouteMatch::NotFound = route_match {
+            return None;
+        }
+
+        let mut params = PathParams::new();
+        for (key, value) in matched_params {
+            params.insert(key, value);
+        }
+
+        Some((route_match, params, &matched.value[0]))
+    }
+
+    fn to_result<'a>(
+        route_match: RouteMatch<'a>,
+        host: Option<&str>,
+        mut params: PathParams,
+    ) -> RouteResult<'a> {
+        match route_match {
+            RouteMatch::Found(path) => {
+                // Subdomains captured from the host are available as path params.
+                for (key, value) in path.match_host(host).unwrap_or_default() {
+                    params.insert(&key, &value);
+                }
+
+                if let Some(catch_all) = path.catch_all() {
+                    params.map().entry(catch_all.to_string()).or_default();
+                }
+
+                if let Some((location, status_code)) = path.redirect_location(&params) {
+                    return RouteResult::Redirect {
+                        status_code,
+                        location,
+                    };
+                }
+
+                RouteResult::Found { path, params }
+            }
+            RouteMatch::MethodNotAllowed(allowed_methods) => {
+                RouteResult::MethodNotAllowed(allowed_methods)
+            }
+            RouteMatch::NotFound => RouteResult::NotFound,
+        }
+    }
+}
+
+///
+/// Summary of the registered route for debugging which route handles the request.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteInfo {
+    /// Allowed request methods. Empty if all the methods are allowed.
+    pub methods: Vec<String>,
+    /// Route as registered like `/posts/{id:int}`.
+    pub name: String,
+    /// Pattern used for matching with the constraints removed like `/posts/{id}`.
+    pub pattern: String,
+    pub host: Option<String>,
+    pub middleware_count: usize,
+    pub guard_count: usize,
+    pub timeout: Option<Duration>,
+}
+
+impl From<&Path> for RouteInfo {
+    fn from(path: &Path) -> Self {
+        Self {
+            methods: path.allowed_methods().to_vec(),
+            name: path.name.clone(),
+            pattern: path.pattern().to_string(),
+            host: path.host_pattern().map(|host| host.to_string()),
+            middleware_count: path.middlewares().len(),
+            guard_count: path.guards().len(),
+            timeout: path.timeout_duration(),
+        }
+    }
+}
+
+///
+/// Formats routes as a plain text table.
+///
+pub struct RouteTable<'a>(pub &'a [RouteInfo]);
+
+impl Display for RouteTable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut rows = vec![[
+            "METHODS".to_string(),
+            "PATH".to_string(),
+            "HOST".to_string(),
+            "MIDDLEWARES".to_string(),
+            "GUARDS".to_string(),
+            "TIMEOUT".to_string(),
+        ]];
+
+        for route in self.0 {
+            let methods = if route.methods.is_empty() {
+                "*".to_string()
+            } else {
+                route.methods.join(",")
+            };
+
+            rows.push([
+                methods,
+                route.name.clone(),
+                route.host.clone().unwrap_or_else(|| "*".to_string()),
+                route.middleware_count.to_string(),
+                route.guard_count.to_string(),
+                route
+                    .timeout
+                    .map(|timeout| format!("{:?}", timeout))
+                    .unwrap_or_else(|| "-".to_string()),
+            ]);
+        }
+
+        let mut widths = [0; 6];
+        for row in &rows {
+            for (index, column) in row.iter().enumerate() {
+                widths[index] = widths[index].max(column.len());
+            }
+        }
+
+        for row in &rows {
+            let columns: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(index, column)| format!("{:width$}", column, width = widths[index]))
+                .collect();
+            writeln!(f, "{}", columns.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::path::{Path, Scope};
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+    use crate::core::shortcuts::SingleText;
+
+    use crate::core::cors::Cors;
+    use crate::core::headers::{HeaderValue, Headers};
+
+    use super::{
+        normalize_path, RouteInfo, RouteResult, RouteTable, Router, SpaFallback, TrailingSlash,
+    };
+
+    async fn view(_: Request) -> Response {
+        HttpResponse::ok().empty()
+    }
+
+    fn matched_name(result: RouteResult) -> Option<String> {
+        match result {
+            RouteResult::Found { path, .. } => Some(path.name.clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_catch_all_routes() {
+        let router = Router::from_paths(
+            &[
+                Path::new("/static/{rest:*}", |request| Box::pin(view(request))),
+                Path::new("/{rest:*}", |request| Box::pin(view(request))),
+                Path::new("/", |request| Box::pin(view(request))),
+            ],
+            TrailingSlash::Strict,
+        );
+
+        match router.resolve("GET", None, "/static/css/style.css") {
+            RouteResult::Found { params, .. } => {
+                assert_eq!(Some(&"css/style.css".to_string()), params.value("rest"));
+            }
+            _ => panic!("Expected static route."),
+        }
+
+        let static_root = router.resolve("GET", None, "/static/");
+        assert_eq!(
+            Some("/static/{rest:*}".to_string()),
+            matched_name(static_root)
+        );
+        let about = router.resolve("GET", None, "/about/team");
+        assert_eq!(Some("/{rest:*}".to_string()), matched_name(about));
+        assert_eq!(
+            Some("/".to_string()),
+            matched_name(router.resolve("GET", None, "/"))
+        );
+    }
+
+    #[test]
+    fn test_host_routes() {
+        let tenant = Scope::new()
+            .path("/", |request| Box::pin(view(request)))
+            .host("{tenant}.example.com");
+
+        let mut paths = vec![Path::new("/", |request| Box::pin(view(request)))];
+        paths.push(Path::new("/", |request| Box::pin(view(request))).host("admin.example.com"));
+        paths.extend(tenant.into_paths());
+        let router = Router::from_paths(&paths, TrailingSlash::Strict);
+
+        let host_of = |result: RouteResult| match result {
+            RouteResult::Found { path, params } => (
+                path.host_pattern().map(|host| host.to_string()),
+                params.value("tenant").cloned(),
+            ),
+            _ => panic!("Expected route."),
+        };
+
+        assert_eq!(
+            (Some("admin.example.com".to_string()), None),
+            host_of(router.resolve("GET", Some("admin.example.com"), "/"))
+        );
+        assert_eq!(
+            (
+                Some("{tenant}.example.com".to_string()),
+                Some("acme".to_string())
+            ),
+            host_of(router.resolve("GET", Some("acme.example.com:8080"), "/"))
+        );
+        assert_eq!(
+            (None, None),
+            host_of(router.resolve("GET", Some("localhost"), "/"))
+        );
+    }
+
+    #[test]
+    fn test_spa_fallback() {
+        let paths = vec![Path::new("/api/users", |request| Box::pin(view(request)))];
+        let router = Router::from_paths(&paths, TrailingSlash::Strict).spa_fallback(
+            SpaFallback::new("/", |request| Box::pin(view(request))).exclude("/api/"),
+        );
+
+        assert_eq!(
+            Some("/api/users".to_string()),
+            matched_name(router.resolve("GET", None, "/api/users"))
+        );
+        assert_eq!(
+            Some("/".to_string()),
+            matched_name(router.resolve("GET", None, "/dashboard/settings"))
+        );
+        assert_eq!(
+            Some("/".to_string()),
+            matched_name(router.resolve("GET", None, "/apis"))
+        );
+        assert!(matches!(
+            router.resolve("GET", None, "/api/posts"),
+            RouteResult::NotFound
+        ));
+        assert!(matches!(
+            router.resolve("POST", None, "/dashboard"),
+            RouteResult::NotFound
+        ));
+        assert!(matches!(
+            router.resolve("GET", None, "/assets/main.js"),
+            RouteResult::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_cors_preflight() {
+        let paths = vec![
+            Path::get("/users", |request| Box::pin(view(request))),
+            Path::post("/users", |request| Box::pin(view(request))),
+            Path::new("/custom", |request| Box::pin(view(request))).methods(&["PUT", "OPTIONS"]),
+        ];
+        let router = Router::from_paths(&paths, TrailingSlash::Strict)
+            .cors(Cors::new().allow_origin("https://example.com"));
+
+        let mut headers = Headers::new();
+        headers.set("Origin", "https://example.com");
+        headers.set("Access-Control-Request-Method", "POST");
+
+        match router.route("OPTIONS", None, "/users", &headers) {
+            RouteResult::Preflight(response_headers) => {
+                assert_eq!(
+                    Some("GET, POST, HEAD".to_string()),
+                    response_headers.value("Access-Control-Allow-Methods")
+                );
+            }
+            _ => panic!("Expected preflight."),
+        }
+
+        assert!(matches!(
+            router.route("OPTIONS", None, "/custom", &headers),
+            RouteResult::Found { .. }
+        ));
+        assert!(matches!(
+            router.route("OPTIONS", None, "/missing", &headers),
+            RouteResult::NotFound
+        ));
+        assert!(matches!(
+            router.route("OPTIONS", None, "/users", &Headers::new()),
+            RouteResult::MethodNotAllowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_route_table() {
+        let paths = [
+            Path::get("/users/{id:int}", |request| Box::pin(view(request))),
+            Path::new("/", |request| Box::pin(view(request))).host("admin.example.com"),
+        ];
+        let routes: Vec<RouteInfo> = paths.iter().map(RouteInfo::from).collect();
+
+        assert_eq!("/users/{id}", routes[0].pattern);
+        assert_eq!(
+            "METHODS  PATH             HOST               MIDDLEWARES  GUARDS  TIMEOUT\n\
+             GET      /users/{id:int}  *                  0            0       -\n\
+             *        /                admin.example.com  0            0       -\n",
+            RouteTable(&routes).to_string()
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let paths = vec![
+            Path::get("/About", |request| Box::pin(view(request))),
+            Path::get("/Users/{Name}", |request| Box::pin(view(request))),
+        ];
+
+        let router = Router::from_paths(&paths, TrailingSlash::Strict);
+        assert!(matches!(
+            router.resolve("GET", None, "/about"),
+            RouteResult::NotFound
+        ));
+
+        let router = router.case_insensitive(true);
+        let about = router.resolve("GET", None, "/ABOUT");
+        assert_eq!(Some("/About".to_string()), matched_name(about));
+
+        match router.resolve("GET", None, "/users/JohnDoe") {
+            RouteResult::Found { params, .. } => {
+                assert_eq!(Some(&"JohnDoe".to_string()), params.value("Name"));
+            }
+            _ => panic!("Expected route."),
+        }
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!("/a/c", normalize_path("/a/./b/../c"));
+        assert_eq!("/~user/", normalize_path("/%7Euser/."));
+        assert_eq!("/a%2Fb", normalize_path("/a%2Fb"));
+        assert_eq!("/", normalize_path("/../.."));
+        assert_eq!("*", normalize_path("*"));
+
+        let paths = vec![Path::get("/users/~admin", |request| {
+            Box::pin(view(request))
+        })];
+        let router = Router::from_paths(&paths, TrailingSlash::Strict).normalize_paths(true);
+        let admin = router.resolve("GET", None, "/static/../users/%7eadmin");
+        assert_eq!(Some("/users/~admin".to_string()), matched_name(admin));
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        let api = Scope::new()
+            .path("/users/", |request| Box::pin(view(request)))
+            .trailing_slash(TrailingSlash::PermanentRedirect);
+
+        let paths = Scope::new()
+            .path("/about", |request| Box::pin(view(request)))
+            .mount("/api", api)
+            .into_paths();
+
+        let router = Router::from_paths(&paths, TrailingSlash::Strict);
+        assert!(matches!(
+            router.resolve("GET", None, "/about/"),
+            RouteResult::NotFound
+        ));
+
+        match router.resolve("POST", None, "/api/users") {
+            RouteResult::Redirect {
+                status_code,
+                location,
+            } => {
+                assert_eq!(308, status_code);
+                assert_eq!("/api/users/", location);
+            }
+            _ => panic!("Expected redirect."),
+        }
+
+        let router = Router::from_paths(&paths, TrailingSlash::Ignore);
+        let about = router.resolve("GET", None, "/about/");
+        assert_eq!(Some("/about".to_string()), matched_name(about));
+    }
+
+    #[test]
+    fn test_route_conflicts() {
+        let conflict = |paths: Vec<Path>| {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                Router::from_paths(&paths, TrailingSlash::Strict);
+            }));
+
+            match result {
+                Ok(()) => None,
+                Err(error) => error.downcast_ref::<String>().cloned(),
+            }
+        };
+
+        // Constrained path registered first is tried before the fallback.
+        assert!(conflict(vec![
+            Path::get("/posts/{id:int}", |request| Box::pin(view(request))),
+            Path::get("/posts/{id}", |request| Box::pin(view(request))),
+            Path::post("/posts/{id:int}", |request| Box::pin(view(request))),
+        ])
+        .is_none());
+
+        let error = conflict(vec![
+            Path::get("/posts/{id}", |request| Box::pin(view(request))),
+            Path::get("/posts/{id:int}", |request| Box::pin(view(request))),
+        ])
+        .unwrap();
+        assert!(error.contains("\"/posts/{id:int}\" is shadowed by \"/posts/{id}\""));
+
+        let error = conflict(vec![
+            Path::get("/posts/{id}/edit", |request| Box::pin(view(request))),
+            Path::get("/posts/{slug}/edit", |request| Box::pin(view(request))),
+        ])
+        .unwrap();
+        assert!(error.contains("\"/posts/{slug}/edit\" conflicts with \"/posts/{id}/edit\""));
+    }
+}
@@ -0,0 +1,226 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::task::JoinSet;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::{racoon_debug, racoon_error};
+
+pub type JobResult = Pin<Box<dyn Future<Output = std::io::Result<()>> + Send>>;
+
+///
+/// Async callback run by the job.
+///
+pub type JobTask = Arc<dyn Fn() -> JobResult + Send + Sync>;
+
+///
+/// Clears the running flag even if the job panics, so the next runs are not skipped forever.
+///
+struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+///
+/// Recurring background job registered with `Server::schedule`. Jobs start after the server is
+/// ready and are stopped when the server shuts down.
+///
+/// The run is skipped if the previous run is still in progress, unless `allow_overlap` is set.
+/// Failed runs are logged and do not stop the next runs. Set `jitter` to spread the runs of the
+/// same job across the server instances.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::scheduler::Job;
+/// use racoon::core::server::Server;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.schedule(
+///     Job::every("purge-sessions", Duration::from_secs(3600), || async {
+///         // Delete the expired sessions from the database.
+///         Ok(())
+///     })
+///     .jitter(Duration::from_secs(60)),
+/// );
+/// ```
+///
+#[derive(Clone)]
+pub struct Job {
+    name: String,
+    interval: Duration,
+    jitter: Duration,
+    run_on_start: bool,
+    allow_overlap: bool,
+    task: JobTask,
+}
+
+impl Job {
+    ///
+    /// Runs the task repeatedly with the interval. First run starts after the interval unless
+    /// `run_on_start` is set.
+    ///
+    pub fn every<S, F, Fut>(name: S, interval: Duration, task: F) -> Self
+    where
+        S: AsRef<str>,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<()>> + Send + 'static,
+    {
+        Self {
+            name: name.as_ref().to_string(),
+            // Interval of zero would run the job in a busy loop.
+            interval: interval.max(Duration::from_millis(1)),
+            jitter: Duration::ZERO,
+            run_on_start: false,
+            allow_overlap: false,
+            task: Arc::new(move || Box::pin(task())),
+        }
+    }
+
+    ///
+    /// Delays each run by the random duration up to `jitter`.
+    ///
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    ///
+    /// Runs the task as soon as the server is ready instead of waiting for the first interval.
+    ///
+    pub fn run_on_start(mut self) -> Self {
+        self.run_on_start = true;
+        self
+    }
+
+    ///
+    /// Starts the run even if the previous run is still in progress.
+    ///
+    pub fn allow_overlap(mut self) -> Self {
+        self.allow_overlap = true;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///
+    /// Runs the task once without the schedule, for example from the tests or the admin
+    /// endpoint.
+    ///
+    pub async fn run_now(&self) -> std::io::Result<()> {
+        (self.task)().await
+    }
+
+    fn random_jitter(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        rand::thread_rng().gen_range(Duration::ZERO..=self.jitter)
+    }
+
+    ///
+    /// Runs the job until the returned future is dropped. Runs in progress are cancelled when
+    /// it is dropped.
+    ///
+    pub async fn run(self) {
+        let start = if self.run_on_start {
+            Instant::now()
+        } else {
+            Instant::now() + self.interval
+        };
+
+        let mut interval = tokio::time::interval_at(start, self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let running = Arc::new(AtomicBool::new(false));
+        let mut runs = JoinSet::new();
+
+        loop {
+            interval.tick().await;
+            while runs.try_join_next().is_some() {}
+
+            if !self.allow_overlap && running.swap(true, Ordering::AcqRel) {
+                racoon_debug!(
+                    "Skipped job {}. Previous run is still in progress.",
+                    self.name
+                );
+                continue;
+            }
+
+            let guard = RunningGuard(running.clone());
+            let jitter = self.random_jitter();
+            let task = self.task.clone();
+            let name = self.name.clone();
+
+            runs.spawn(async move {
+                let _guard = guard;
+                tokio::time::sleep(jitter).await;
+
+                if let Err(error) = task().await {
+                    racoon_error!("Job {} failed. Error: {}", name, error);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::Job;
+
+    #[tokio::test]
+    async fn test_job_overlap() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let active = Arc::new(AtomicUsize::new(0));
+        let max_active = Arc::new(AtomicUsize::new(0));
+
+        let task = {
+            let (runs, active, max_active) = (runs.clone(), active.clone(), max_active.clone());
+            move || {
+                let (runs, active, max_active) = (runs.clone(), active.clone(), max_active.clone());
+                async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    let current = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_active.fetch_max(current, Ordering::SeqCst);
+
+                    tokio::time::sleep(Duration::from_millis(70)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    Err(std::io::Error::other("Failed runs do not stop the job."))
+                }
+            }
+        };
+
+        let job = Job::every("slow", Duration::from_millis(20), task.clone()).run_on_start();
+        let handle = tokio::spawn(job.run());
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        handle.abort();
+        let _ = handle.await;
+
+        // Ticks during the run are skipped.
+        assert_eq!(1, max_active.load(Ordering::SeqCst));
+        assert!((2..=4).contains(&runs.load(Ordering::SeqCst)));
+
+        active.store(0, Ordering::SeqCst);
+        max_active.store(0, Ordering::SeqCst);
+        let job = Job::every("overlap", Duration::from_millis(20), task).allow_overlap();
+        let handle = tokio::spawn(job.run());
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        handle.abort();
+        assert!(max_active.load(Ordering::SeqCst) > 1);
+    }
+}
@@ -0,0 +1,275 @@
+use std::time::Duration;
+
+use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::core::client::{ClientRequest, ClientResponse, HttpClient};
+use crate::core::headers::HeaderValue;
+
+///
+/// ECDSA P-256 account key signing the ACME requests.
+///
+pub(crate) struct AccountKey {
+    key_pair: EcdsaKeyPair,
+    pkcs8: Vec<u8>,
+}
+
+impl AccountKey {
+    pub fn generate() -> std::io::Result<Self> {
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .map_err(|_| std::io::Error::other("Failed to generate account key."))?;
+        Self::from_pkcs8(pkcs8.as_ref())
+    }
+
+    pub fn from_pkcs8(pkcs8: &[u8]) -> std::io::Result<Self> {
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8)
+            .map_err(|_| std::io::Error::other("Invalid account key."))?;
+
+        Ok(Self {
+            key_pair,
+            pkcs8: pkcs8.to_vec(),
+        })
+    }
+
+    pub fn pkcs8(&self) -> &[u8] {
+        &self.pkcs8
+    }
+
+    ///
+    /// Public key in JSON Web Key format. Members are in lexicographic order as required for the
+    /// thumbprint.
+    ///
+    pub fn jwk(&self) -> String {
+        // Uncompressed point: 0x04 || x || y
+        let public_key = self.key_pair.public_key().as_ref();
+        format!(
+            r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+            URL_SAFE_NO_PAD.encode(&public_key[1..33]),
+            URL_SAFE_NO_PAD.encode(&public_key[33..65])
+        )
+    }
+
+    ///
+    /// Key authorization of the challenge token.
+    ///
+    pub fn key_authorization(&self, token: &str) -> String {
+        let thumbprint = Sha256::digest(self.jwk().as_bytes());
+        format!("{}.{}", token, URL_SAFE_NO_PAD.encode(thumbprint))
+    }
+
+    ///
+    /// Signs the payload in flattened JWS JSON serialization. Account URL is used as key id once
+    /// the account is registered, else the public key is embedded.
+    ///
+    pub fn sign(
+        &self,
+        url: &str,
+        nonce: &str,
+        kid: Option<&str>,
+        payload: Option<&Value>,
+    ) -> std::io::Result<Vec<u8>> {
+        let mut protected = json!({
+            "alg": "ES256",
+            "nonce": nonce,
+            "url": url,
+        });
+
+        match kid {
+            Some(kid) => protected["kid"] = json!(kid),
+            None => {
+                protected["jwk"] =
+                    serde_json::from_str(&self.jwk()).map_err(std::io::Error::other)?;
+            }
+        }
+
+        let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+        // Empty payload is used for POST-as-GET requests.
+        let payload = match payload {
+            Some(payload) => URL_SAFE_NO_PAD.encode(payload.to_string()),
+            None => String::new(),
+        };
+
+        let signing_input = format!("{}.{}", protected, payload);
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), signing_input.as_bytes())
+            .map_err(|_| std::io::Error::other("Failed to sign ACME request."))?;
+
+        let body = json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+        });
+        Ok(body.to_string().into_bytes())
+    }
+}
+
+///
+/// ACME protocol client as described in RFC 8555.
+///
+pub(crate) struct AcmeClient {
+    http_client: HttpClient,
+    account_key: AccountKey,
+    directory: Value,
+    kid: Option<String>,
+    nonce: Option<String>,
+}
+
+impl AcmeClient {
+    pub async fn new(
+        http_client: HttpClient,
+        directory_url: &str,
+        account_key: AccountKey,
+    ) -> std::io::Result<Self> {
+        let response = http_client.send(ClientRequest::get(directory_url)).await?;
+        if response.status != 200 {
+            return Err(std::io::Error::other(format!(
+                "Failed to fetch ACME directory. Status: {}",
+                response.status
+            )));
+        }
+
+        Ok(Self {
+            http_client,
+            account_key,
+            directory: response.json()?,
+            kid: None,
+            nonce: None,
+        })
+    }
+
+    pub fn account_key(&self) -> &AccountKey {
+        &self.account_key
+    }
+
+    fn directory_url(&self, name: &str) -> std::io::Result<String> {
+        match self.directory[name].as_str() {
+            Some(url) => Ok(url.to_string()),
+            None => Err(std::io::Error::other(format!(
+                "ACME directory has no {} URL.",
+                name
+            ))),
+        }
+    }
+
+    async fn nonce(&mut self) -> std::io::Result<String> {
+        if let Some(nonce) = self.nonce.take() {
+            return Ok(nonce);
+        }
+
+        let new_nonce_url = self.directory_url("newNonce")?;
+        let response = self
+            .http_client
+            .send(ClientRequest::new("HEAD", &new_nonce_url))
+            .await?;
+
+        match response.headers.value("Replay-Nonce") {
+            Some(nonce) => Ok(nonce),
+            None => Err(std::io::Error::other("ACME server did not return nonce.")),
+        }
+    }
+
+    ///
+    /// Sends signed request. Request is retried once if the server rejects the nonce.
+    ///
+    pub async fn post(
+        &mut self,
+        url: &str,
+        payload: Option<&Value>,
+    ) -> std::io::Result<ClientResponse> {
+        let mut retried = false;
+
+        loop {
+            let nonce = self.nonce().await?;
+            let body = self
+                .account_key
+                .sign(url, &nonce, self.kid.as_deref(), payload)?;
+
+            let response = self
+                .http_client
+                .send(
+                    ClientRequest::post(url)
+                        .header("Content-Type", "application/jose+json")
+                        .body(body),
+                )
+                .await?;
+            self.nonce = response.headers.value("Replay-Nonce");
+
+            if response.status >= 400 {
+                let problem = response.json().unwrap_or_default();
+                let problem_type = problem["type"].as_str().unwrap_or_default();
+
+                if problem_type == "urn:ietf:params:acme:error:badNonce" && !retried {
+                    retried = true;
+                    continue;
+                }
+
+                return Err(std::io::Error::other(format!(
+                    "ACME request to {} failed. Status: {} Error: {}",
+                    url, response.status, problem
+                )));
+            }
+            return Ok(response);
+        }
+    }
+
+    ///
+    /// Registers the account or finds the existing account of the key.
+    ///
+    pub async fn register(&mut self, contacts: &[String]) -> std::io::Result<()> {
+        let new_account_url = self.directory_url("newAccount")?;
+        let payload = json!({
+            "termsOfServiceAgreed": true,
+            "contact": contacts,
+        });
+
+        let response = self.post(&new_account_url, Some(&payload)).await?;
+        self.kid = Some(response.location()?);
+        Ok(())
+    }
+
+    pub async fn new_order(&mut self, domains: &[String]) -> std::io::Result<(String, Value)> {
+        let new_order_url = self.directory_url("newOrder")?;
+        let identifiers: Vec<Value> = domains
+            .iter()
+            .map(|domain| json!({"type": "dns", "value": domain}))
+            .collect();
+
+        let response = self
+            .post(&new_order_url, Some(&json!({ "identifiers": identifiers })))
+            .await?;
+        Ok((response.location()?, response.json()?))
+    }
+
+    ///
+    /// Fetches the resource with POST-as-GET request.
+    ///
+    pub async fn fetch(&mut self, url: &str) -> std::io::Result<ClientResponse> {
+        self.post(url, None).await
+    }
+
+    ///
+    /// Polls the resource until its status is not pending or processing.
+    ///
+    pub async fn poll(&mut self, url: &str) -> std::io::Result<Value> {
+        for _ in 0..30 {
+            let resource = self.fetch(url).await?.json()?;
+            match resource["status"].as_str() {
+                Some("pending") | Some("processing") => {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                }
+                _ => return Ok(resource),
+            }
+        }
+
+        Err(std::io::Error::other(format!(
+            "ACME resource {} is still pending.",
+            url
+        )))
+    }
+}
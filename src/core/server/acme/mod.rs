@@ -0,0 +1,692 @@
+mod client;
+
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rcgen::{CertificateParams, CustomExtension, KeyPair};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio_rustls::TlsAcceptor;
+
+use crate::core::client::HttpClient;
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse};
+use crate::{racoon_debug, racoon_error, racoon_info, racoon_warn};
+
+use self::client::{AccountKey, AcmeClient};
+
+pub const LETS_ENCRYPT_PRODUCTION: &str = "https://acme-v02.api.letsencrypt.org/directory";
+pub const LETS_ENCRYPT_STAGING: &str = "https://acme-staging-v02.api.letsencrypt.org/directory";
+
+/// ALPN protocol used by the ACME server for validating TLS-ALPN-01 challenge.
+pub const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+const HTTP_CHALLENGE_PREFIX: &str = "/.well-known/acme-challenge/";
+
+///
+/// Challenge used to prove the control of the domains.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AcmeChallenge {
+    /// Token is served over plain HTTP on port 80, so the server needs a listener on port 80.
+    #[default]
+    Http01,
+    /// Challenge certificate is served on the TLS listener itself on port 443.
+    TlsAlpn01,
+}
+
+#[derive(Debug, Default)]
+struct AcmeResolver {
+    certificate: RwLock<Option<Arc<CertifiedKey>>>,
+    /// TLS-ALPN-01 challenge certificates by domain.
+    challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl ResolvesServerCert for AcmeResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let is_challenge = match client_hello.alpn() {
+            Some(mut protocols) => protocols.any(|protocol| protocol == ACME_TLS_ALPN),
+            None => false,
+        };
+
+        if is_challenge {
+            let domain = client_hello.server_name()?;
+            let challenges = self.challenges.read().ok()?;
+            return challenges.get(domain).cloned();
+        }
+
+        self.certificate.read().ok()?.clone()
+    }
+}
+
+#[derive(Debug, Default)]
+struct AcmeState {
+    resolver: Arc<AcmeResolver>,
+    /// HTTP-01 key authorizations by token.
+    http_tokens: RwLock<HashMap<String, String>>,
+}
+
+///
+/// Obtains and renews the TLS certificate automatically from the ACME certificate authority
+/// like Let's Encrypt. Certificates and the account key are stored in the cache directory and
+/// reused on restart.
+///
+/// # Examples
+///
+/// ```no_run
+/// use racoon::core::server::acme::AcmeConfig;
+/// use racoon::core::server::Server;
+///
+/// let acme = AcmeConfig::new(&["example.com", "www.example.com"])
+///     .contact("admin@example.com")
+///     .cache_dir("/var/lib/app/acme");
+///
+/// // Port 80 serves the HTTP-01 challenges.
+/// let mut server = Server::bind("0.0.0.0:80");
+/// server.acme("0.0.0.0:443", acme);
+/// ```
+///
+#[derive(Clone)]
+pub struct AcmeConfig {
+    domains: Vec<String>,
+    contacts: Vec<String>,
+    directory_url: String,
+    cache_dir: PathBuf,
+    challenge: AcmeChallenge,
+    ca_bundle: Option<String>,
+    renew_before: Duration,
+    state: Arc<AcmeState>,
+}
+
+impl AcmeConfig {
+    pub fn new<S: AsRef<str>>(domains: &[S]) -> Self {
+        Self {
+            domains: domains
+                .iter()
+                .map(|domain| domain.as_ref().to_lowercase())
+                .collect(),
+            contacts: vec![],
+            directory_url: LETS_ENCRYPT_PRODUCTION.to_string(),
+            cache_dir: PathBuf::from("acme"),
+            challenge: AcmeChallenge::default(),
+            ca_bundle: None,
+            renew_before: Duration::from_secs(30 * 24 * 3600),
+            state: Arc::new(AcmeState::default()),
+        }
+    }
+
+    ///
+    /// Email address notified by the certificate authority about the expiring certificates.
+    ///
+    pub fn contact<S: AsRef<str>>(mut self, email: S) -> Self {
+        self.contacts.push(format!("mailto:{}", email.as_ref()));
+        self
+    }
+
+    ///
+    /// Directory URL of the ACME server. Defaults to Let's Encrypt production.
+    ///
+    pub fn directory<S: AsRef<str>>(mut self, directory_url: S) -> Self {
+        self.directory_url = directory_url.as_ref().to_string();
+        self
+    }
+
+    ///
+    /// Uses Let's Encrypt staging environment, which has higher rate limits but issues untrusted
+    /// certificates.
+    ///
+    pub fn staging(self) -> Self {
+        self.directory(LETS_ENCRYPT_STAGING)
+    }
+
+    pub fn cache_dir<P: Into<PathBuf>>(mut self, cache_dir: P) -> Self {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+
+    pub fn challenge(mut self, challenge: AcmeChallenge) -> Self {
+        self.challenge = challenge;
+        self
+    }
+
+    ///
+    /// PEM file with the CA certificates trusted for connecting to the ACME server. Defaults to
+    /// the CA certificates of the platform.
+    ///
+    pub fn ca_bundle<S: AsRef<str>>(mut self, ca_bundle: S) -> Self {
+        self.ca_bundle = Some(ca_bundle.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Renews the certificate when it expires within the duration. Defaults to 30 days.
+    ///
+    pub fn renew_before(mut self, renew_before: Duration) -> Self {
+        self.renew_before = renew_before;
+        self
+    }
+
+    ///
+    /// TLS acceptor serving the obtained certificate. Handshakes fail until the first
+    /// certificate is available.
+    ///
+    pub(crate) fn tls_acceptor(&self) -> TlsAcceptor {
+        let mut server_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(self.state.resolver.clone());
+
+        if self.challenge == AcmeChallenge::TlsAlpn01 {
+            server_config.alpn_protocols = vec![b"http/1.1".to_vec(), ACME_TLS_ALPN.to_vec()];
+        }
+        TlsAcceptor::from(Arc::new(server_config))
+    }
+
+    ///
+    /// Middleware answering the HTTP-01 challenges. Returns `None` for other challenge types.
+    ///
+    pub(crate) fn http_challenge(&self) -> Option<AcmeHttpChallenge> {
+        match self.challenge {
+            AcmeChallenge::Http01 => Some(AcmeHttpChallenge(self.state.clone())),
+            AcmeChallenge::TlsAlpn01 => None,
+        }
+    }
+
+    fn certificate_path(&self) -> PathBuf {
+        self.cache_dir.join(format!("{}.crt", self.domains[0]))
+    }
+
+    fn private_key_path(&self) -> PathBuf {
+        self.cache_dir.join(format!("{}.key", self.domains[0]))
+    }
+
+    ///
+    /// Loads the cached certificate and returns its expiry time.
+    ///
+    fn load_cached(&self) -> std::io::Result<Option<DateTime<Utc>>> {
+        let certificate_pem = match std::fs::read(self.certificate_path()) {
+            Ok(certificate_pem) => certificate_pem,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let private_key_pem = std::fs::read(self.private_key_path())?;
+
+        let (certified_key, expires_at) = certified_key(&certificate_pem, &private_key_pem)?;
+        if let Ok(mut certificate) = self.state.resolver.certificate.write() {
+            *certificate = Some(Arc::new(certified_key));
+        }
+        Ok(Some(expires_at))
+    }
+
+    fn account_key(&self) -> std::io::Result<AccountKey> {
+        let account_key_path = self.cache_dir.join("account.der");
+
+        match std::fs::read(&account_key_path) {
+            Ok(pkcs8) => AccountKey::from_pkcs8(&pkcs8),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                let account_key = AccountKey::generate()?;
+                write_private(&account_key_path, account_key.pkcs8())?;
+                Ok(account_key)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    ///
+    /// Keeps the certificate valid. Cached certificate is used if it is not due for renewal.
+    /// Failed attempts are retried with increasing delay.
+    ///
+    pub(crate) async fn maintain(self) {
+        if self.domains.is_empty() {
+            racoon_error!("No domains to request certificate for.");
+            return;
+        }
+
+        let mut retry_delay = Duration::from_secs(60);
+
+        loop {
+            let expires_at = match self.load_cached() {
+                Ok(expires_at) => expires_at,
+                Err(error) => {
+                    racoon_warn!("Failed to load cached certificate. Error: {}", error);
+                    None
+                }
+            };
+
+            let renew_in = match expires_at {
+                Some(expires_at) => (expires_at - Utc::now())
+                    .to_std()
+                    .unwrap_or_default()
+                    .saturating_sub(self.renew_before),
+                None => Duration::ZERO,
+            };
+
+            if !renew_in.is_zero() {
+                // Checked daily, so the certificate replaced in the cache directory is picked up.
+                tokio::time::sleep(renew_in.min(Duration::from_secs(24 * 3600))).await;
+                continue;
+            }
+
+            racoon_info!("Requesting certificate for {}", self.domains.join(", "));
+            match self.provision().await {
+                Ok(()) => {
+                    racoon_info!("Certificate issued for {}", self.domains.join(", "));
+                    retry_delay = Duration::from_secs(60);
+                }
+                Err(error) => {
+                    racoon_error!(
+                        "Failed to obtain certificate. Retrying in {:?}. Error: {}",
+                        retry_delay,
+                        error
+                    );
+                    tokio::time::sleep(retry_delay).await;
+                    retry_delay = (retry_delay * 2).min(Duration::from_secs(6 * 3600));
+                }
+            }
+        }
+    }
+
+    async fn provision(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.cache_dir)?;
+        let http_client = match &self.ca_bundle {
+            Some(ca_bundle) => HttpClient::with_ca_bundle(ca_bundle)?,
+            None => HttpClient::new()?,
+        };
+        let mut client =
+            AcmeClient::new(http_client, &self.directory_url, self.account_key()?).await?;
+        client.register(&self.contacts).await?;
+
+        let (order_url, order) = client.new_order(&self.domains).await?;
+        let authorizations = order["authorizations"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        for authorization_url in authorizations {
+            let authorization_url = authorization_url.as_str().unwrap_or_default();
+            self.authorize(&mut client, authorization_url).await?;
+        }
+
+        let key_pair = KeyPair::generate().map_err(std::io::Error::other)?;
+        let csr = CertificateParams::new(self.domains.clone())
+            .and_then(|params| params.serialize_request(&key_pair))
+            .map_err(std::io::Error::other)?;
+
+        let finalize_url = json_str(&order, "finalize")?;
+        let payload = json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) });
+        client.post(&finalize_url, Some(&payload)).await?;
+
+        let order = client.poll(&order_url).await?;
+        if order["status"] != "valid" {
+            return Err(std::io::Error::other(format!(
+                "Order is not valid. Order: {}",
+                order
+            )));
+        }
+
+        let certificate_url = json_str(&order, "certificate")?;
+        let certificate_pem = client.fetch(&certificate_url).await?.body;
+
+        // Certificate is validated before replacing the cached one.
+        certified_key(&certificate_pem, key_pair.serialize_pem().as_bytes())?;
+        write_private(
+            &self.private_key_path(),
+            key_pair.serialize_pem().as_bytes(),
+        )?;
+        std::fs::write(self.certificate_path(), certificate_pem)?;
+        Ok(())
+    }
+
+    async fn authorize(
+        &self,
+        client: &mut AcmeClient,
+        authorization_url: &str,
+    ) -> std::io::Result<()> {
+        let authorization = client.fetch(authorization_url).await?.json()?;
+        if authorization["status"] == "valid" {
+            return Ok(());
+        }
+
+        let domain = authorization["identifier"]["value"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let challenge_type = match self.challenge {
+            AcmeChallenge::Http01 => "http-01",
+            AcmeChallenge::TlsAlpn01 => "tls-alpn-01",
+        };
+
+        let challenges = authorization["challenges"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let challenge = match challenges
+            .iter()
+            .find(|challenge| challenge["type"] == challenge_type)
+        {
+            Some(challenge) => challenge,
+            None => {
+                return Err(std::io::Error::other(format!(
+                    "ACME server does not offer {} challenge for {}",
+                    challenge_type, domain
+                )));
+            }
+        };
+
+        let token = json_str(challenge, "token")?;
+        let key_authorization = client.account_key().key_authorization(&token);
+
+        match self.challenge {
+            AcmeChallenge::Http01 => {
+                if let Ok(mut http_tokens) = self.state.http_tokens.write() {
+                    http_tokens.insert(token.clone(), key_authorization);
+                }
+            }
+            AcmeChallenge::TlsAlpn01 => {
+                let certified_key = challenge_certificate(&domain, &key_authorization)?;
+                if let Ok(mut challenges) = self.state.resolver.challenges.write() {
+                    challenges.insert(domain.clone(), Arc::new(certified_key));
+                }
+            }
+        }
+
+        racoon_debug!("Responding to {} challenge for {}", challenge_type, domain);
+        let result = match client
+            .post(&json_str(challenge, "url")?, Some(&json!({})))
+            .await
+        {
+            Ok(_) => client.poll(authorization_url).await,
+            Err(error) => Err(error),
+        };
+
+        if let Ok(mut http_tokens) = self.state.http_tokens.write() {
+            http_tokens.remove(&token);
+        }
+
+        if let Ok(mut challenges) = self.state.resolver.challenges.write() {
+            challenges.remove(&domain);
+        }
+
+        let authorization = result?;
+        if authorization["status"] != "valid" {
+            return Err(std::io::Error::other(format!(
+                "Authorization failed for {}. Authorization: {}",
+                domain, authorization
+            )));
+        }
+        Ok(())
+    }
+}
+
+///
+/// Middleware answering the HTTP-01 challenge requests of the ACME server.
+///
+#[derive(Clone)]
+pub(crate) struct AcmeHttpChallenge(Arc<AcmeState>);
+
+impl AbstractMiddleware for AcmeHttpChallenge {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let state = self.0.clone();
+
+        Box::new(Box::pin(async move {
+            let token = match request.path.strip_prefix(HTTP_CHALLENGE_PREFIX) {
+                Some(token) => token.to_string(),
+                None => return next.run(request).await,
+            };
+
+            let key_authorization = match state.http_tokens.read() {
+                Ok(http_tokens) => http_tokens.get(&token).cloned(),
+                Err(_) => None,
+            };
+
+            match key_authorization {
+                Some(key_authorization) => {
+                    let mut response = HttpResponse::ok().body(key_authorization);
+                    response
+                        .get_headers()
+                        .set("Content-Type", "application/octet-stream");
+                    response
+                }
+                None => HttpResponse::not_found().body("404 Not Found"),
+            }
+        }))
+    }
+}
+
+fn json_str(value: &Value, name: &str) -> std::io::Result<String> {
+    match value[name].as_str() {
+        Some(value) => Ok(value.to_string()),
+        None => Err(std::io::Error::other(format!(
+            "ACME response has no {}.",
+            name
+        ))),
+    }
+}
+
+fn write_private(path: &std::path::Path, content: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, content)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+///
+/// Creates signing certificate from PEM encoded certificate chain and PKCS#8 private key.
+/// Returns expiry time of the leaf certificate.
+///
+fn certified_key(
+    certificate_pem: &[u8],
+    private_key_pem: &[u8],
+) -> std::io::Result<(CertifiedKey, DateTime<Utc>)> {
+    let mut certificates = vec![];
+    for certificate in rustls_pemfile::certs(&mut &certificate_pem[..]) {
+        certificates.push(certificate?);
+    }
+
+    let private_key = match rustls_pemfile::pkcs8_private_keys(&mut &private_key_pem[..]).next() {
+        Some(private_key) => PrivateKeyDer::Pkcs8(private_key?),
+        None => return Err(std::io::Error::other("Private key not found.")),
+    };
+
+    let expires_at = match certificates.first().and_then(|leaf| not_after(leaf)) {
+        Some(expires_at) => expires_at,
+        None => return Err(std::io::Error::other("Invalid certificate.")),
+    };
+
+    let signing_key = rustls::crypto::aws_lc_rs::sign::any_supported_type(&private_key)
+        .map_err(std::io::Error::other)?;
+    Ok((CertifiedKey::new(certificates, signing_key), expires_at))
+}
+
+///
+/// Self-signed certificate with the key authorization digest for TLS-ALPN-01 challenge as
+/// described in RFC 8737.
+///
+fn challenge_certificate(domain: &str, key_authorization: &str) -> std::io::Result<CertifiedKey> {
+    let key_pair = KeyPair::generate().map_err(std::io::Error::other)?;
+    let mut params =
+        CertificateParams::new(vec![domain.to_string()]).map_err(std::io::Error::other)?;
+    params.custom_extensions = vec![CustomExtension::new_acme_identifier(&Sha256::digest(
+        key_authorization.as_bytes(),
+    ))];
+
+    let certificate = params
+        .self_signed(&key_pair)
+        .map_err(std::io::Error::other)?;
+    let private_key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_pair.serialize_der()));
+    let signing_key = rustls::crypto::aws_lc_rs::sign::any_supported_type(&private_key)
+        .map_err(std::io::Error::other)?;
+
+    Ok(CertifiedKey::new(
+        vec![certificate.der().clone()],
+        signing_key,
+    ))
+}
+
+///
+/// Reads the DER element and returns its tag, content and the remaining bytes.
+///
+fn read_der(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let first_length = *data.get(1)? as usize;
+
+    let (length, header_size) = if first_length < 0x80 {
+        (first_length, 2)
+    } else {
+        let length_size = first_length & 0x7f;
+        if length_size == 0 || length_size > 4 {
+            return None;
+        }
+
+        let mut length = 0;
+        for byte in data.get(2..2 + length_size)? {
+            length = (length << 8) | *byte as usize;
+        }
+        (length, 2 + length_size)
+    };
+
+    let content = data.get(header_size..header_size + length)?;
+    Some((tag, content, &data[header_size + length..]))
+}
+
+///
+/// Expiry time of the X.509 certificate.
+///
+fn not_after(certificate: &CertificateDer) -> Option<DateTime<Utc>> {
+    let (_, certificate, _) = read_der(certificate)?;
+    let (_, tbs_certificate, _) = read_der(certificate)?;
+
+    // Optional version is tagged with [0].
+    let mut fields = tbs_certificate;
+    let (tag, _, rest) = read_der(fields)?;
+    if tag == 0xa0 {
+        fields = rest;
+    }
+
+    // Serial number, signature algorithm and issuer precede validity.
+    for _ in 0..3 {
+        fields = read_der(fields)?.2;
+    }
+
+    let (_, validity, _) = read_der(fields)?;
+    let (_, _, rest) = read_der(validity)?;
+    let (tag, not_after, _) = read_der(rest)?;
+    let not_after = std::str::from_utf8(not_after).ok()?;
+
+    let not_after = match tag {
+        // UTCTime with two digit year
+        0x17 => {
+            let year: u32 = not_after.get(..2)?.parse().ok()?;
+            let century = if year >= 50 { "19" } else { "20" };
+            format!("{}{}", century, not_after)
+        }
+        // GeneralizedTime
+        0x18 => not_after.to_string(),
+        _ => return None,
+    };
+
+    let not_after = NaiveDateTime::parse_from_str(&not_after, "%Y%m%d%H%M%SZ").ok()?;
+    Some(not_after.and_utc())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Datelike, Timelike};
+    use rcgen::{CertificateParams, KeyPair};
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::Headers;
+    use crate::core::middleware::Next;
+
+    use crate::core::client::{parse_response, parse_url};
+
+    use super::client::AccountKey;
+    use super::{certified_key, AcmeConfig};
+
+    #[test]
+    fn test_certificate_expiry() {
+        let key_pair = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["example.com".to_string()]).unwrap();
+        params.not_after = rcgen::date_time_ymd(2031, 5, 17);
+        let certificate = params.self_signed(&key_pair).unwrap();
+
+        let (_, expires_at) = certified_key(
+            certificate.pem().as_bytes(),
+            key_pair.serialize_pem().as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            (2031, 5, 17, 0),
+            (
+                expires_at.year(),
+                expires_at.month(),
+                expires_at.day(),
+                expires_at.hour()
+            )
+        );
+    }
+
+    #[test]
+    fn test_client_helpers() {
+        assert_eq!(
+            (true, "acme.test".to_string(), 14000, "/dir".to_string()),
+            parse_url("https://acme.test:14000/dir").unwrap()
+        );
+
+        let response = parse_response(
+            b"HTTP/1.1 201 Created\r\nLocation: /acct/1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\n{}\r\n0\r\n\r\n",
+            false,
+        )
+        .unwrap();
+        assert_eq!(201, response.status);
+        assert_eq!("/acct/1", response.location().unwrap());
+        assert_eq!(b"{}".to_vec(), response.body);
+
+        let account_key = AccountKey::generate().unwrap();
+        let key_authorization = account_key.key_authorization("token");
+        assert!(key_authorization.starts_with("token."));
+        // Base64url encoded SHA-256 digest
+        assert_eq!("token.".len() + 43, key_authorization.len());
+
+        let body = account_key
+            .sign("https://acme.test/new-acct", "nonce", None, None)
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("", body["payload"]);
+        assert_eq!(86, body["signature"].as_str().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn test_http_challenge() {
+        let acme = AcmeConfig::new(&["example.com"]);
+        acme.state
+            .http_tokens
+            .write()
+            .unwrap()
+            .insert("abc".to_string(), "abc.thumbprint".to_string());
+
+        let middleware = acme.http_challenge().unwrap();
+        let next = Next::new(Arc::new(vec![Arc::new(middleware)]), None);
+        let response = next
+            .clone()
+            .run(request("/.well-known/acme-challenge/abc", Headers::new(), b"").await)
+            .await;
+        assert_eq!(200, response.status().0);
+
+        let response = next
+            .run(request("/.well-known/acme-challenge/unknown", Headers::new(), b"").await)
+            .await;
+        assert_eq!(404, response.status().0);
+    }
+}
@@ -0,0 +1,332 @@
+use tokio::sync::{mpsc, Mutex};
+
+use crate::core::stream::{AbstractStream, StreamResult};
+use crate::racoon_debug;
+
+///
+/// Response headers which are specific to the HTTP/1 connection and not allowed in HTTP/2 and
+/// HTTP/3.
+///
+pub(crate) const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+///
+/// Body of the HTTP/2 or HTTP/3 request.
+///
+pub(crate) trait RequestBody {
+    async fn next_chunk(&mut self) -> Option<Result<Vec<u8>, String>>;
+}
+
+///
+/// Response of the HTTP/2 or HTTP/3 request.
+///
+pub(crate) trait ResponseSink {
+    async fn send_response(&mut self, response: http::Response<()>) -> Result<(), String>;
+    async fn send_data(&mut self, data: Vec<u8>) -> Result<(), String>;
+    async fn finish(&mut self) -> Result<(), String>;
+}
+
+///
+/// Serializes the request as HTTP/1.0 request to be handled by the same parser and handler as
+/// the TCP connections. HTTP/1.0 is used since the response ends with the stream, so neither
+/// keep-alive nor chunked transfer encoding is used.
+///
+pub(crate) fn request_head(request: &http::Request<()>, content_length: Option<usize>) -> Vec<u8> {
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    let mut head = format!("{} {} HTTP/1.0\r\n", request.method(), path).into_bytes();
+    if let Some(authority) = request.uri().authority() {
+        if !request.headers().contains_key(http::header::HOST) {
+            head.extend(format!("Host: {}\r\n", authority).as_bytes());
+        }
+    }
+
+    for (name, value) in request.headers() {
+        if name == http::header::CONTENT_LENGTH && content_length.is_some() {
+            continue;
+        }
+
+        head.extend(name.as_str().as_bytes());
+        head.extend(b": ");
+        head.extend(value.as_bytes());
+        head.extend(b"\r\n");
+    }
+
+    if let Some(content_length) = content_length {
+        head.extend(format!("Content-Length: {}\r\n", content_length).as_bytes());
+    }
+    head.extend(b"\r\n");
+    head
+}
+
+///
+/// Forwards request head and body to the stream read by the server. Body without
+/// `Content-Length` is buffered to calculate its length.
+///
+pub(crate) async fn forward_request<B: RequestBody>(
+    request: http::Request<()>,
+    mut body: B,
+    request_sender: mpsc::Sender<Vec<u8>>,
+    max_body_size: usize,
+) {
+    if request.headers().contains_key(http::header::CONTENT_LENGTH) {
+        if request_sender
+            .send(request_head(&request, None))
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        while let Some(Ok(chunk)) = body.next_chunk().await {
+            if request_sender.send(chunk).await.is_err() {
+                return;
+            }
+        }
+        return;
+    }
+
+    let mut buffered_body = vec![];
+    loop {
+        match body.next_chunk().await {
+            Some(Ok(chunk)) => buffered_body.extend(chunk),
+            Some(Err(error)) => {
+                racoon_debug!("Failed to read request body. Error: {}", error);
+                return;
+            }
+            None => break,
+        }
+
+        // Length is sent as it is and rejected by the server without reading the body.
+        if buffered_body.len() > max_body_size {
+            let _ = request_sender
+                .send(request_head(&request, Some(buffered_body.len())))
+                .await;
+            return;
+        }
+    }
+
+    let content_length = if buffered_body.is_empty() {
+        None
+    } else {
+        Some(buffered_body.len())
+    };
+
+    if request_sender
+        .send(request_head(&request, content_length))
+        .await
+        .is_ok()
+        && !buffered_body.is_empty()
+    {
+        let _ = request_sender.send(buffered_body).await;
+    }
+}
+
+///
+/// Parses the HTTP/1 response written by the server and sends it to the response sink.
+///
+pub(crate) async fn forward_response<S: ResponseSink>(
+    mut sink: S,
+    mut response_receiver: mpsc::Receiver<Vec<u8>>,
+) -> Result<(), String> {
+    let mut buffer = vec![];
+
+    let body = loop {
+        match response_receiver.recv().await {
+            Some(bytes) => buffer.extend(bytes),
+            None => return Ok(()),
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; 100];
+        let mut parsed = httparse::Response::new(&mut headers);
+        let head_length = match parsed.parse(&buffer) {
+            Ok(httparse::Status::Complete(head_length)) => head_length,
+            Ok(httparse::Status::Partial) => continue,
+            Err(error) => return Err(error.to_string()),
+        };
+
+        let status_code = parsed.code.unwrap_or(500);
+        let mut builder = http::Response::builder().status(status_code);
+        for header in parsed.headers.iter() {
+            if CONNECTION_HEADERS.contains(&header.name.to_lowercase().as_str()) {
+                continue;
+            }
+            builder = builder.header(header.name, header.value);
+        }
+        let response = builder.body(()).map_err(|error| error.to_string())?;
+
+        let body = buffer.split_off(head_length);
+        buffer.clear();
+        sink.send_response(response).await?;
+
+        // Informational responses such as early hints are followed by the final response.
+        if status_code < 200 {
+            buffer = body;
+            continue;
+        }
+        break body;
+    };
+
+    if !body.is_empty() {
+        sink.send_data(body).await?;
+    }
+
+    while let Some(bytes) = response_receiver.recv().await {
+        sink.send_data(bytes).await?;
+    }
+
+    sink.finish().await
+}
+
+///
+/// Stream of a single HTTP/2 or HTTP/3 request. Request bytes are received from the protocol
+/// stream and response bytes written by the server are forwarded back to it. Response channel is
+/// bounded, so writing waits until the client accepts the data like the TCP connections.
+///
+pub(crate) struct RequestStreamWrapper {
+    peer_addr: Option<String>,
+    buffer_size: usize,
+    request_bytes: Mutex<mpsc::Receiver<Vec<u8>>>,
+    response_bytes: Mutex<Option<mpsc::Sender<Vec<u8>>>>,
+    restored_payload: Mutex<Option<Vec<u8>>>,
+}
+
+impl RequestStreamWrapper {
+    pub fn new(
+        peer_addr: Option<String>,
+        buffer_size: usize,
+        request_bytes: mpsc::Receiver<Vec<u8>>,
+        response_bytes: mpsc::Sender<Vec<u8>>,
+    ) -> Self {
+        Self {
+            peer_addr,
+            buffer_size,
+            request_bytes: Mutex::new(request_bytes),
+            response_bytes: Mutex::new(Some(response_bytes)),
+            restored_payload: Mutex::new(None),
+        }
+    }
+}
+
+impl AbstractStream for RequestStreamWrapper {
+    fn buffer_size(&self) -> StreamResult<'_, usize> {
+        Box::new(Box::pin(async move { self.buffer_size }))
+    }
+
+    fn peer_addr(&self) -> StreamResult<'_, Option<String>> {
+        Box::new(Box::pin(async move { self.peer_addr.clone() }))
+    }
+
+    fn restore_payload(&self, bytes: &[u8]) -> StreamResult<'_, std::io::Result<()>> {
+        let bytes = bytes.to_vec();
+
+        Box::new(Box::pin(async move {
+            *self.restored_payload.lock().await = Some(bytes);
+            Ok(())
+        }))
+    }
+
+    fn restored_len(&self) -> StreamResult<'_, usize> {
+        Box::new(Box::pin(async move {
+            match self.restored_payload.lock().await.as_ref() {
+                Some(restored_payload) => restored_payload.len(),
+                None => 0,
+            }
+        }))
+    }
+
+    fn read_chunk(&self) -> StreamResult<'_, std::io::Result<Vec<u8>>> {
+        Box::new(Box::pin(async move {
+            if let Some(payload) = self.restored_payload.lock().await.take() {
+                return Ok(payload);
+            }
+
+            match self.request_bytes.lock().await.recv().await {
+                Some(bytes) => Ok(bytes),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "Request stream is finished.",
+                )),
+            }
+        }))
+    }
+
+    fn write_chunk<'a>(&'a self, bytes: &'a [u8]) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            // Sender is cloned, so the shutdown is not blocked while waiting for the capacity.
+            let sender = self.response_bytes.lock().await.clone();
+            match sender {
+                Some(sender) => sender
+                    .send(bytes.to_vec())
+                    .await
+                    .map_err(|_| std::io::Error::other("Response stream is closed.")),
+                None => Err(std::io::Error::other("Request stream is already shutdown.")),
+            }
+        }))
+    }
+
+    fn shutdown(&self) -> StreamResult<'_, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            // Dropping the sender finishes the response.
+            self.response_bytes.lock().await.take();
+            Ok(())
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc;
+
+    use crate::core::stream::AbstractStream;
+
+    use super::{request_head, RequestStreamWrapper};
+
+    #[test]
+    fn test_request_head() {
+        let request = http::Request::builder()
+            .method("POST")
+            .uri("https://example.com/users?page=2")
+            .header("content-type", "application/json")
+            .body(())
+            .unwrap();
+
+        let head = String::from_utf8(request_head(&request, Some(2))).unwrap();
+        assert_eq!(
+            "POST /users?page=2 HTTP/1.0\r\nHost: example.com\r\ncontent-type: application/json\r\nContent-Length: 2\r\n\r\n",
+            head
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_chunk_waits_for_capacity() {
+        let (_request_sender, request_receiver) = mpsc::channel(1);
+        let (response_sender, mut response_receiver) = mpsc::channel(1);
+        let stream = RequestStreamWrapper::new(None, 1024, request_receiver, response_sender);
+
+        stream.write_chunk(b"first").await.unwrap();
+
+        // Second chunk waits until the first one is forwarded to the client.
+        let second_chunk =
+            tokio::time::timeout(Duration::from_millis(50), stream.write_chunk(b"second"));
+        assert!(second_chunk.await.is_err());
+
+        assert_eq!(Some(b"first".to_vec()), response_receiver.recv().await);
+        stream.write_chunk(b"second").await.unwrap();
+        assert_eq!(Some(b"second".to_vec()), response_receiver.recv().await);
+
+        drop(response_receiver);
+        assert!(stream.write_chunk(b"third").await.is_err());
+    }
+}
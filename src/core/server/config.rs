@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::core::forms::FormConstraints;
+use crate::core::health::Health;
+use crate::core::server::{ConnectionLimitBehavior, Server};
+
+///
+/// Prefix of the environment variables read by `ServerConfig::from_env`.
+///
+pub const ENV_PREFIX: &str = "RACOON_";
+
+fn invalid<S: AsRef<str>>(message: S) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        message.as_ref().to_string(),
+    )
+}
+
+///
+/// Parses the duration like `30s`, `500ms`, `5m` or `1h`. Numbers without the unit are seconds.
+///
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    let number: u64 = number.parse().ok()?;
+
+    match unit.trim() {
+        "" | "s" => Some(Duration::from_secs(number)),
+        "ms" => Some(Duration::from_millis(number)),
+        "m" => Some(Duration::from_secs(number.checked_mul(60)?)),
+        "h" => Some(Duration::from_secs(number.checked_mul(3600)?)),
+        _ => None,
+    }
+}
+
+///
+/// Parses the size like `512`, `64KB`, `10MiB` or `1GB` in bytes. Units are case-insensitive.
+///
+pub fn parse_size(value: &str) -> Option<usize> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    let number: usize = number.parse().ok()?;
+
+    let multiplier = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "kib" | "k" => 1024,
+        "mb" => 1000 * 1000,
+        "mib" | "m" => 1024 * 1024,
+        "gb" => 1000 * 1000 * 1000,
+        "gib" | "g" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+///
+/// Numbers and strings are both accepted for the durations and sizes in the config file.
+///
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(u64),
+    String(String),
+}
+
+impl NumberOrString {
+    fn into_string(self) -> String {
+        match self {
+            NumberOrString::Number(number) => number.to_string(),
+            NumberOrString::String(value) => value,
+        }
+    }
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let value = NumberOrString::deserialize(deserializer)?.into_string();
+    parse_size(&value).ok_or_else(|| serde::de::Error::custom(format!("invalid size {}", value)))
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let value = match Option::<NumberOrString>::deserialize(deserializer)? {
+        Some(value) => value.into_string(),
+        None => return Ok(None),
+    };
+
+    parse_duration(&value)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid duration {}", value)))
+}
+
+///
+/// Settings of the server loaded from the config file and the environment variables, so the
+/// deployments can change them without rebuilding. Fields not present keep their defaults.
+///
+/// Durations are written like `30s`, `500ms` or `5m` and sizes like `64KB` or `10MiB`. Config
+/// files are read with the `toml` or `yaml` feature.
+///
+/// Environment variables are named after the fields with the `RACOON_` prefix, like
+/// `RACOON_BIND` or `RACOON_MAX_BODY_SIZE`, and override the values of the config file. Empty
+/// values unset the optional fields.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::server::config::ServerConfig;
+/// use racoon::core::server::Server;
+///
+/// # fn main() -> std::io::Result<()> {
+/// std::env::set_var("RACOON_BIND", "0.0.0.0:9000");
+/// std::env::set_var("RACOON_KEEP_ALIVE_TIMEOUT", "15s");
+///
+/// // Reads the file at `RACOON_CONFIG` if set and applies the environment variables.
+/// let config = ServerConfig::load()?;
+/// assert_eq!("0.0.0.0:9000", config.bind);
+/// assert_eq!(Some(Duration::from_secs(15)), config.keep_alive_timeout);
+///
+/// let mut server = Server::from_config(&config)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address of the listener. Defaults to `127.0.0.1:8080`.
+    pub bind: String,
+    /// Certificate chain of the TLS listener. Requires `tls_key`.
+    pub tls_cert: Option<PathBuf>,
+    /// Private key of the TLS listener. Requires `tls_cert`.
+    pub tls_key: Option<PathBuf>,
+    /// Number of worker threads used by `run_blocking`.
+    pub workers: Option<usize>,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub buffer_size: usize,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub max_request_header_size: usize,
+    pub max_header_count: usize,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub max_body_size: usize,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub max_file_size: usize,
+    /// Connections over the limit are rejected with `503 Service Unavailable`.
+    pub max_connections: Option<usize>,
+    pub max_requests_per_connection: Option<usize>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub header_read_timeout: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub keep_alive_timeout: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub body_read_timeout: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub response_write_timeout: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub drain_timeout: Option<Duration>,
+    /// Enables the racoon logs.
+    pub logging: bool,
+    /// Logs the registered routes on startup.
+    pub log_routes: bool,
+    /// Serves the `/healthz` and `/readyz` endpoints.
+    pub health: bool,
+    /// Path of the Prometheus metrics endpoint.
+    pub metrics_path: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        let form_constraints = FormConstraints::default();
+
+        Self {
+            bind: "127.0.0.1:8080".to_string(),
+            tls_cert: None,
+            tls_key: None,
+            workers: None,
+            buffer_size: 8096,
+            max_request_header_size: 5 * 1024 * 1024,
+            max_header_count: 100,
+            max_body_size: form_constraints.max_body_size(0),
+            max_file_size: form_constraints.max_size_for_file(&String::new(), 0),
+            max_connections: None,
+            max_requests_per_connection: None,
+            header_read_timeout: None,
+            keep_alive_timeout: None,
+            body_read_timeout: None,
+            response_write_timeout: None,
+            drain_timeout: None,
+            logging: false,
+            log_routes: false,
+            health: false,
+            metrics_path: None,
+        }
+    }
+}
+
+///
+/// Sets the field from the environment variable if it is present.
+///
+trait FromEnv: Sized {
+    fn from_env(value: &str) -> Option<Self>;
+}
+
+impl FromEnv for String {
+    fn from_env(value: &str) -> Option<Self> {
+        Some(value.to_string())
+    }
+}
+
+impl FromEnv for PathBuf {
+    fn from_env(value: &str) -> Option<Self> {
+        Some(PathBuf::from(value))
+    }
+}
+
+impl FromEnv for bool {
+    fn from_env(value: &str) -> Option<Self> {
+        parse_bool(value)
+    }
+}
+
+impl FromEnv for usize {
+    fn from_env(value: &str) -> Option<Self> {
+        parse_size(value)
+    }
+}
+
+impl FromEnv for Duration {
+    fn from_env(value: &str) -> Option<Self> {
+        parse_duration(value)
+    }
+}
+
+impl<T: FromEnv> FromEnv for Option<T> {
+    fn from_env(value: &str) -> Option<Self> {
+        if value.trim().is_empty() {
+            return Some(None);
+        }
+        T::from_env(value).map(Some)
+    }
+}
+
+macro_rules! apply_env {
+    ($config: ident, $prefix: ident, $($field: ident),*) => {
+        $(
+            let name = format!("{}{}", $prefix, stringify!($field).to_uppercase());
+            if let Ok(value) = std::env::var(&name) {
+                $config.$field = FromEnv::from_env(&value)
+                    .ok_or_else(|| invalid(format!("Invalid value of {}: {}", name, value)))?;
+            }
+        )*
+    };
+}
+
+impl ServerConfig {
+    ///
+    /// Reads the config from the environment variables with the `RACOON_` prefix.
+    ///
+    pub fn from_env() -> std::io::Result<Self> {
+        Self::default().merge_env(ENV_PREFIX)
+    }
+
+    ///
+    /// Reads the config file. The format is detected from the `.toml`, `.yaml` or `.yml`
+    /// extension.
+    ///
+    pub fn from_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let config = match extension.as_str() {
+            "toml" => Self::from_toml(&content)?,
+            "yaml" | "yml" => Self::from_yaml(&content)?,
+            _ => {
+                return Err(invalid(format!(
+                    "Unsupported config file {}. Expected .toml, .yaml or .yml file.",
+                    path.display()
+                )));
+            }
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    ///
+    /// Reads the config file at `RACOON_CONFIG` if set, then applies the environment variables.
+    ///
+    pub fn load() -> std::io::Result<Self> {
+        let config = match std::env::var(format!("{}CONFIG", ENV_PREFIX)) {
+            Ok(path) if !path.is_empty() => Self::from_file(path)?,
+            _ => Self::default(),
+        };
+
+        config.merge_env(ENV_PREFIX)
+    }
+
+    #[cfg(feature = "toml")]
+    fn from_toml(content: &str) -> std::io::Result<Self> {
+        toml::from_str(content).map_err(|error| invalid(error.to_string()))
+    }
+
+    #[cfg(not(feature = "toml"))]
+    fn from_toml(_: &str) -> std::io::Result<Self> {
+        Err(invalid("TOML config files require the `toml` feature."))
+    }
+
+    #[cfg(feature = "yaml")]
+    fn from_yaml(content: &str) -> std::io::Result<Self> {
+        serde_yaml::from_str(content).map_err(|error| invalid(error.to_string()))
+    }
+
+    #[cfg(not(feature = "yaml"))]
+    fn from_yaml(_: &str) -> std::io::Result<Self> {
+        Err(invalid("YAML config files require the `yaml` feature."))
+    }
+
+    ///
+    /// Overrides the fields with the environment variables named with the prefix.
+    ///
+    pub fn merge_env<S: AsRef<str>>(mut self, prefix: S) -> std::io::Result<Self> {
+        let prefix = prefix.as_ref();
+        let config = &mut self;
+
+        apply_env!(
+            config,
+            prefix,
+            bind,
+            tls_cert,
+            tls_key,
+            workers,
+            buffer_size,
+            max_request_header_size,
+            max_header_count,
+            max_body_size,
+            max_file_size,
+            max_connections,
+            max_requests_per_connection,
+            header_read_timeout,
+            keep_alive_timeout,
+            body_read_timeout,
+            response_write_timeout,
+            drain_timeout,
+            logging,
+            log_routes,
+            health,
+            metrics_path
+        );
+
+        self.validate()?;
+        Ok(self)
+    }
+
+    ///
+    /// Checks that the settings can be applied, so the misconfigured deployment fails on startup
+    /// instead of on the first request.
+    ///
+    pub fn validate(&self) -> std::io::Result<()> {
+        match self.bind.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {}
+            _ => {
+                return Err(invalid(format!(
+                    "Invalid bind address {}. Expected host:port.",
+                    self.bind
+                )));
+            }
+        }
+
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(cert), Some(key)) => {
+                for path in [cert, key] {
+                    if !path.is_file() {
+                        return Err(invalid(format!("TLS file {} not found.", path.display())));
+                    }
+                }
+            }
+            (None, None) => {}
+            _ => return Err(invalid("Both tls_cert and tls_key are required for TLS.")),
+        }
+
+        let non_zero = [
+            ("workers", self.workers.unwrap_or(1)),
+            ("buffer_size", self.buffer_size),
+            ("max_header_count", self.max_header_count),
+            ("max_connections", self.max_connections.unwrap_or(1)),
+            (
+                "max_requests_per_connection",
+                self.max_requests_per_connection.unwrap_or(1),
+            ),
+        ];
+
+        for (name, value) in non_zero {
+            if value == 0 {
+                return Err(invalid(format!("{} must be greater than 0.", name)));
+            }
+        }
+
+        if let Some(metrics_path) = &self.metrics_path {
+            if !metrics_path.starts_with('/') {
+                return Err(invalid("metrics_path must start with /."));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Server {
+    ///
+    /// Creates the server listening at the address of the config with its limits, timeouts and
+    /// middlewares applied. Settings not covered by the config can be changed afterwards.
+    ///
+    pub fn from_config(config: &ServerConfig) -> std::io::Result<Self> {
+        config.validate()?;
+
+        if config.logging {
+            Server::enable_logging();
+        }
+
+        let mut server = match (&config.tls_cert, &config.tls_key) {
+            (Some(cert), Some(key)) => Server::bind_tls(&config.bind, cert, key)?,
+            _ => Server::bind(&config.bind),
+        };
+
+        let defaults = FormConstraints::default();
+        let form_constraints = FormConstraints::new(
+            config.max_body_size,
+            defaults.max_header_size(0),
+            config.max_file_size,
+            defaults.max_value_size(0),
+            HashMap::new(),
+        );
+
+        server
+            .buffer_size(config.buffer_size)
+            .max_request_header_size(config.max_request_header_size)
+            .form_constraints(form_constraints)
+            .log_routes(config.log_routes);
+        Arc::make_mut(&mut server.request_constraints).max_header_count = config.max_header_count;
+
+        if let Some(workers) = config.workers {
+            server.worker_threads(workers);
+        }
+
+        if let Some(max_connections) = config.max_connections {
+            server.max_connections(max_connections, ConnectionLimitBehavior::Reject);
+        }
+
+        if let Some(max_requests) = config.max_requests_per_connection {
+            server.max_requests_per_connection(max_requests);
+        }
+
+        if let Some(timeout) = config.header_read_timeout {
+            server.header_read_timeout(timeout);
+        }
+
+        if let Some(timeout) = config.keep_alive_timeout {
+            server.keep_alive_timeout(timeout);
+        }
+
+        if let Some(timeout) = config.body_read_timeout {
+            server.body_read_timeout(timeout);
+        }
+
+        if let Some(timeout) = config.response_write_timeout {
+            server.response_write_timeout(timeout);
+        }
+
+        if let Some(timeout) = config.drain_timeout {
+            server.drain_timeout(timeout);
+        }
+
+        if config.health {
+            server.health(Health::new());
+        }
+
+        if let Some(metrics_path) = &config.metrics_path {
+            server.metrics(metrics_path);
+        }
+        Ok(server)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use super::{parse_duration, parse_size, ServerConfig};
+
+    #[test]
+    fn test_server_config() {
+        assert_eq!(Some(Duration::from_millis(500)), parse_duration("500ms"));
+        assert_eq!(Some(Duration::from_secs(300)), parse_duration("5m"));
+        assert_eq!(Some(Duration::from_secs(30)), parse_duration("30"));
+        assert_eq!(None, parse_duration("5 days"));
+        assert_eq!(Some(10 * 1024 * 1024), parse_size("10MiB"));
+        assert_eq!(Some(64_000), parse_size("64kb"));
+        assert_eq!(None, parse_size("-1"));
+
+        std::env::set_var("TEST_CONFIG_BIND", "0.0.0.0:9000");
+        std::env::set_var("TEST_CONFIG_MAX_BODY_SIZE", "1MiB");
+        std::env::set_var("TEST_CONFIG_BODY_READ_TIMEOUT", "10s");
+        std::env::set_var("TEST_CONFIG_HEALTH", "on");
+
+        let config = ServerConfig::default().merge_env("TEST_CONFIG_").unwrap();
+        assert_eq!("0.0.0.0:9000", config.bind);
+        assert_eq!(1024 * 1024, config.max_body_size);
+        assert_eq!(Some(Duration::from_secs(10)), config.body_read_timeout);
+        assert!(config.health);
+        assert_eq!(100, config.max_header_count);
+
+        std::env::set_var("TEST_CONFIG_MAX_CONNECTIONS", "many");
+        let error = ServerConfig::default()
+            .merge_env("TEST_CONFIG_")
+            .unwrap_err();
+        assert_eq!(
+            "Invalid value of TEST_CONFIG_MAX_CONNECTIONS: many",
+            error.to_string()
+        );
+
+        let config = ServerConfig {
+            tls_cert: Some("cert.pem".into()),
+            ..ServerConfig::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = ServerConfig {
+            bind: "8080".to_string(),
+            ..ServerConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_config() {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            bind = "0.0.0.0:80"
+            max_file_size = "20MB"
+            buffer_size = 16384
+            keep_alive_timeout = "1m"
+            metrics_path = "/metrics"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(20_000_000, config.max_file_size);
+        assert_eq!(16384, config.buffer_size);
+        assert_eq!(Some(Duration::from_secs(60)), config.keep_alive_timeout);
+        assert!(toml::from_str::<ServerConfig>("port = 80").is_err());
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::HashMap;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::core::headers::HeaderValue;
+use crate::core::html::escape_html;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::recovery::Panic;
+use crate::core::request::Request;
+use crate::core::response::sse::{Event, SseResponse};
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HtmlResponse, Response};
+use crate::core::router::RouteInfo;
+use crate::{racoon_error, racoon_info, racoon_warn};
+
+use super::{ConnectionTracker, ShutdownLock};
+
+///
+/// Endpoint streaming the reload events to the script injected into the html pages.
+///
+pub const RELOAD_PATH: &str = "/__racoon/reload";
+
+///
+/// Reloads the page when the server sends different data than the first event, such as after
+/// the restart or the template change. Browser reconnects automatically while the server restarts.
+///
+const RELOAD_SCRIPT: &str = "<script>(function(){var version;\
+var events=new EventSource(\"/__racoon/reload\");\
+events.onmessage=function(event){if(version&&version!==event.data){location.reload();}\
+version=event.data;};})();</script>";
+
+struct DevState {
+    /// Changes on every restart, so the browsers reload the page after reconnecting.
+    instance_id: String,
+    reload_version: watch::Sender<u64>,
+    build_error: Mutex<Option<String>>,
+    restart_requested: AtomicBool,
+    /// Executable resolved before rebuilding, since it is replaced by the build.
+    executable: Mutex<Option<PathBuf>>,
+}
+
+///
+/// Development mode which rebuilds and restarts the server when the source files change and
+/// reloads the open pages when the templates or static files change. Panics are rendered as
+/// detailed error pages with the backtrace, and unmatched paths list the registered routes.
+///
+/// Files are polled for changes, so no file system notification service is required. Dev mode is
+/// ignored in release builds.
+///
+/// # Examples
+///
+/// ```no_run
+/// use racoon::core::server::dev::DevMode;
+/// use racoon::core::server::Server;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.dev_mode(
+///     DevMode::new()
+///         .watch_source("src")
+///         .watch_reload("templates")
+///         .build_command("cargo", &["build", "--bin", "app"]),
+/// );
+///
+/// server.run().await.unwrap();
+/// # }
+/// ```
+///
+#[derive(Clone)]
+pub struct DevMode {
+    source_paths: Vec<PathBuf>,
+    reload_paths: Vec<PathBuf>,
+    build_program: String,
+    build_args: Vec<String>,
+    poll_interval: Duration,
+    routes: Arc<Vec<RouteInfo>>,
+    custom_not_found: bool,
+    state: Arc<DevState>,
+}
+
+impl Default for DevMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DevMode {
+    pub fn new() -> Self {
+        Self {
+            source_paths: vec![],
+            reload_paths: vec![],
+            build_program: "cargo".to_string(),
+            build_args: vec!["build".to_string()],
+            poll_interval: Duration::from_millis(500),
+            routes: Arc::new(vec![]),
+            custom_not_found: false,
+            state: Arc::new(DevState {
+                instance_id: Uuid::new_v4().simple().to_string(),
+                reload_version: watch::channel(0).0,
+                build_error: Mutex::new(None),
+                restart_requested: AtomicBool::new(false),
+                executable: Mutex::new(None),
+            }),
+        }
+    }
+
+    ///
+    /// Adds the file or directory which rebuilds and restarts the server when changed. Defaults
+    /// to `src` and `Cargo.toml` if no path is added.
+    ///
+    pub fn watch_source<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.source_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    ///
+    /// Adds the file or directory which reloads the open pages when changed. Defaults to
+    /// `templates` and `static` if no path is added.
+    ///
+    pub fn watch_reload<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.reload_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    ///
+    /// Command building the server binary before the restart. Defaults to `cargo build`.
+    ///
+    pub fn build_command<S: AsRef<str>>(mut self, program: S, args: &[S]) -> Self {
+        self.build_program = program.as_ref().to_string();
+        self.build_args = args.iter().map(|arg| arg.as_ref().to_string()).collect();
+        self
+    }
+
+    ///
+    /// Interval at which the watched files are checked for changes. Defaults to 500 milliseconds.
+    ///
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    ///
+    /// Sets the routes listed on the not found page. The page is not rendered if the custom
+    /// `404` error handler is registered.
+    ///
+    pub(crate) fn routes(mut self, routes: Vec<RouteInfo>, custom_not_found: bool) -> Self {
+        self.routes = Arc::new(routes);
+        self.custom_not_found = custom_not_found;
+        self
+    }
+
+    fn source_paths(&self) -> Vec<PathBuf> {
+        if self.source_paths.is_empty() {
+            return vec![PathBuf::from("src"), PathBuf::from("Cargo.toml")];
+        }
+        self.source_paths.clone()
+    }
+
+    fn reload_paths(&self) -> Vec<PathBuf> {
+        if self.reload_paths.is_empty() {
+            return vec![PathBuf::from("templates"), PathBuf::from("static")];
+        }
+        self.reload_paths.clone()
+    }
+
+    fn build_error(&self) -> Option<String> {
+        match self.state.build_error.lock() {
+            Ok(build_error) => build_error.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn set_build_error(&self, error: Option<String>) {
+        match self.state.build_error.lock() {
+            Ok(mut build_error) => *build_error = error,
+            Err(poisoned) => *poisoned.into_inner() = error,
+        }
+    }
+
+    fn reload(&self) {
+        self.state
+            .reload_version
+            .send_modify(|version| *version += 1);
+    }
+
+    ///
+    /// Polls the watched files until the server starts draining. Source changes are built with the
+    /// build command and the server is shut down for the restart if the build succeeds.
+    ///
+    pub(crate) fn spawn_watcher(
+        &self,
+        connections: ConnectionTracker,
+        shutdown_lock: ShutdownLock,
+    ) {
+        match std::env::current_exe() {
+            Ok(executable) => {
+                if let Ok(mut current) = self.state.executable.lock() {
+                    *current = Some(executable);
+                }
+            }
+            Err(error) => racoon_warn!("Dev mode cannot restart the server: {}", error),
+        }
+
+        let dev_mode = self.clone();
+        tokio::spawn(async move {
+            let source_paths = dev_mode.source_paths();
+            let reload_paths = dev_mode.reload_paths();
+            let mut sources = snapshot(source_paths.clone()).await;
+            let mut reloads = snapshot(reload_paths.clone()).await;
+
+            racoon_info!(
+                "Dev mode watching {:?} for restart and {:?} for reload.",
+                source_paths,
+                reload_paths
+            );
+
+            loop {
+                tokio::time::sleep(dev_mode.poll_interval).await;
+                if connections.is_draining() {
+                    break;
+                }
+
+                let current_sources = snapshot(source_paths.clone()).await;
+                if current_sources != sources {
+                    sources = current_sources;
+                    if dev_mode.rebuild().await {
+                        dev_mode
+                            .state
+                            .restart_requested
+                            .store(true, Ordering::Relaxed);
+                        racoon_info!("Restarting server.");
+
+                        let (mutex, condvar) = &*shutdown_lock;
+                        let _lock = mutex.lock();
+                        condvar.notify_all();
+                        break;
+                    }
+                    continue;
+                }
+
+                let current_reloads = snapshot(reload_paths.clone()).await;
+                if current_reloads != reloads {
+                    reloads = current_reloads;
+                    racoon_info!("Reloading pages.");
+                    dev_mode.reload();
+                }
+            }
+        });
+    }
+
+    ///
+    /// Runs the build command. Build errors are logged and rendered to the html requests until
+    /// the next successful build.
+    ///
+    async fn rebuild(&self) -> bool {
+        racoon_info!(
+            "Source changed. Running {} {}",
+            self.build_program,
+            self.build_args.join(" ")
+        );
+
+        let output = tokio::process::Command::new(&self.build_program)
+            .args(&self.build_args)
+            .output()
+            .await;
+
+        let error = match output {
+            Ok(output) if output.status.success() => {
+                self.set_build_error(None);
+                return true;
+            }
+            Ok(output) => String::from_utf8_lossy(&output.stderr).to_string(),
+            Err(error) => format!("Failed to run {}: {}", self.build_program, error),
+        };
+
+        racoon_error!("Build failed:\n{}", error);
+        self.set_build_error(Some(error));
+        self.reload();
+        false
+    }
+
+    pub(crate) fn restart_requested(&self) -> bool {
+        self.state.restart_requested.load(Ordering::Relaxed)
+    }
+
+    ///
+    /// Replaces the current process with the rebuilt executable. Returns only if the executable
+    /// cannot be started.
+    ///
+    pub(crate) fn restart(&self) -> std::io::Error {
+        let executable = match self.state.executable.lock() {
+            Ok(executable) => executable.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+
+        match executable {
+            Some(executable) => std::process::Command::new(executable)
+                .args(std::env::args_os().skip(1))
+                .exec(),
+            None => std::io::Error::other("Executable of the server is unknown."),
+        }
+    }
+
+    ///
+    /// Sends the instance ID and reload version to the injected script until the server shuts
+    /// down.
+    ///
+    async fn reload_events(&self, request: &Request) -> Response {
+        let mut receiver = self.state.reload_version.subscribe();
+        let mut sse = SseResponse::from(request).retry(Duration::from_millis(500));
+
+        loop {
+            let version = *receiver.borrow_and_update();
+            let data = format!("{}-{}", self.state.instance_id, version);
+            if sse.send(Event::data(data)).await.is_err() {
+                break;
+            }
+
+            tokio::select! {
+                changed = receiver.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+                _ = sse.wait_shutdown() => break,
+            }
+        }
+
+        sse.finish().await
+    }
+
+    fn not_found_page(&self, method: &str, path: &str) -> Response {
+        let mut rows = String::new();
+        for route in self.routes.iter() {
+            let methods = if route.methods.is_empty() {
+                "*".to_string()
+            } else {
+                route.methods.join(", ")
+            };
+
+            rows.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(methods),
+                escape_html(&route.name),
+                escape_html(route.host.as_deref().unwrap_or("*"))
+            ));
+        }
+
+        let content = format!(
+            "<p>No route matched <code>{} {}</code>.</p>\n<table>\n\
+            <tr><th>Methods</th><th>Path</th><th>Host</th></tr>\n{}</table>",
+            escape_html(method),
+            escape_html(path),
+            rows
+        );
+        HtmlResponse::not_found().body(error_page("404 Page not found", &content))
+    }
+}
+
+impl AbstractMiddleware for DevMode {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let dev_mode = self.clone();
+
+        Box::new(Box::pin(async move {
+            let path = request.path.split('?').next().unwrap_or_default();
+            if path == RELOAD_PATH {
+                return dev_mode.reload_events(&request).await;
+            }
+
+            let accepts_html = request
+                .headers
+                .value("Accept")
+                .is_some_and(|accept| accept.contains("text/html"));
+
+            if accepts_html {
+                if let Some(build_error) = dev_mode.build_error() {
+                    let content = format!("<pre>{}</pre>", escape_html(build_error));
+                    let mut response: Response = HtmlResponse::internal_server_error()
+                        .body(error_page("Build failed", &content));
+                    inject_reload_script(&mut response);
+                    return response;
+                }
+            }
+
+            let method = request.method.clone();
+            let path = request.path.clone();
+            let mut response = next.run(request).await;
+
+            if !dev_mode.custom_not_found
+                && response.status().0 == 404
+                && response.get_body().as_slice() == b"404 Page not found"
+            {
+                response = dev_mode.not_found_page(&method, &path);
+            }
+
+            inject_reload_script(&mut response);
+            response
+        }))
+    }
+}
+
+///
+/// Returns the modification times of the files in the paths. Hidden entries and the `target`
+/// directories are skipped.
+///
+async fn snapshot(paths: Vec<PathBuf>) -> HashMap<PathBuf, SystemTime> {
+    tokio::task::spawn_blocking(move || {
+        let mut files = HashMap::new();
+        let mut pending = paths;
+
+        while let Some(path) = pending.pop() {
+            let metadata = match std::fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if metadata.is_dir() {
+                let entries = match std::fs::read_dir(&path) {
+                    Ok(entries) => entries,
+                    Err(_) => continue,
+                };
+
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+                    if !name.starts_with('.') && name != "target" {
+                        pending.push(entry.path());
+                    }
+                }
+            } else if let Ok(modified) = metadata.modified() {
+                files.insert(path, modified);
+            }
+        }
+        files
+    })
+    .await
+    .unwrap_or_default()
+}
+
+///
+/// Inserts the reload script before the closing body tag of the html responses. Streamed and
+/// encoded responses are left unchanged.
+///
+fn inject_reload_script(response: &mut Response) {
+    if !response.serve_default() {
+        return;
+    }
+
+    let headers = response.get_headers();
+    let is_html = headers
+        .value("Content-Type")
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    if !is_html || headers.value("Content-Encoding").is_some() {
+        return;
+    }
+
+    let body = response.get_body();
+    let position = match body
+        .windows(7)
+        .rposition(|window| window.eq_ignore_ascii_case(b"</body>"))
+    {
+        Some(position) => position,
+        None => return,
+    };
+
+    body.splice(position..position, RELOAD_SCRIPT.bytes());
+    let length = body.len();
+
+    let headers = response.get_headers();
+    if headers.value("Content-Length").is_some() {
+        headers.set("Content-Length", length.to_string());
+    }
+}
+
+fn error_page(title: &str, content: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+        body {{ font-family: sans-serif; margin: 2em; }}\n\
+        pre {{ background: #f4f4f4; padding: 1em; overflow: auto; }}\n\
+        td, th {{ padding: 0.25em 1em; text-align: left; }}\n\
+        </style>\n</head>\n<body>\n<h1>{}</h1>\n{}\n</body>\n</html>\n",
+        escape_html(title),
+        escape_html(title),
+        content
+    )
+}
+
+///
+/// Renders the panic with the location, backtrace and request line of the panicked request.
+///
+pub(crate) fn panic_page(panic: &Panic, request_line: &str) -> Response {
+    let content = format!(
+        "<p><code>{}</code></p>\n<p>Panicked at <code>{}</code></p>\n<pre>{}</pre>\n\
+        <h2>Backtrace</h2>\n<pre>{}</pre>",
+        escape_html(request_line),
+        escape_html(panic.location.as_deref().unwrap_or("unknown location")),
+        escape_html(&panic.message),
+        escape_html(
+            panic
+                .backtrace
+                .as_deref()
+                .unwrap_or("Backtrace is not available.")
+        )
+    );
+
+    let mut response: Response = HtmlResponse::internal_server_error()
+        .body(error_page("500 Internal Server Error", &content));
+    inject_reload_script(&mut response);
+    response
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::recovery::Panic;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+
+    use super::{inject_reload_script, panic_page, RELOAD_SCRIPT};
+
+    #[test]
+    fn test_error_pages() {
+        let panic = Panic {
+            message: "Index <5> out of range".to_string(),
+            location: Some("main.rs:10".to_string()),
+            backtrace: Some("0: main".to_string()),
+        };
+
+        let mut response = panic_page(&panic, "GET /posts");
+        assert_eq!(500, response.status().0);
+
+        let body = String::from_utf8(response.get_body().clone()).unwrap();
+        assert!(body.contains("Index &lt;5&gt; out of range"));
+        assert!(body.contains("main.rs:10"));
+        assert!(body.contains(&format!("{}</body>", RELOAD_SCRIPT)));
+
+        // Script is not injected to the non-html responses.
+        let mut response: Response = HttpResponse::ok().body("</body>");
+        inject_reload_script(&mut response);
+        assert_eq!(b"</body>".to_vec(), *response.get_body());
+    }
+}
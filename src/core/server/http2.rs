@@ -0,0 +1,639 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context as TaskContext, Poll};
+
+use bytes::Bytes;
+use h2::server::SendResponse;
+use h2::{RecvStream, SendStream};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+
+use crate::core::forms::FormConstraints;
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::middleware::Next;
+use crate::core::parser::headers::HTTP2_PREFACE;
+use crate::core::router::Router;
+use crate::core::session::SessionManager;
+use crate::core::stream::Stream;
+use crate::racoon_debug;
+
+use super::bridge::{
+    forward_request, forward_response, RequestBody, RequestStreamWrapper, ResponseSink,
+    CONNECTION_HEADERS,
+};
+use super::{
+    ConnectionConstraints, ConnectionTracker, Context, ErrorHandlers, RequestConstraints, Server,
+};
+
+/// Number of streams each connection can have open at once unless configured.
+pub(crate) const DEFAULT_MAX_CONCURRENT_STREAMS: u32 = 100;
+
+/// Frame size every HTTP/2 peer accepts before the settings are exchanged.
+const DEFAULT_MAX_FRAME_SIZE: usize = 16384;
+
+const FRAME_HEADER_SIZE: usize = 9;
+const HEADERS_FRAME: u8 = 0x1;
+const SETTINGS_FRAME: u8 = 0x4;
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+
+type IoFuture<T> = Pin<Box<dyn Future<Output = std::io::Result<T>> + Send>>;
+
+///
+/// Adapts the server stream to the `AsyncRead` and `AsyncWrite` traits required by `h2`, so the
+/// HTTP/2 connections work over every stream type.
+///
+struct StreamIo {
+    stream: Arc<Stream>,
+    read: Option<IoFuture<Vec<u8>>>,
+    unread: Vec<u8>,
+    write: Option<IoFuture<()>>,
+    shutdown: Option<IoFuture<()>>,
+}
+
+impl StreamIo {
+    fn new(stream: Arc<Stream>) -> Self {
+        Self {
+            stream,
+            read: None,
+            unread: vec![],
+            write: None,
+            shutdown: None,
+        }
+    }
+
+    fn poll_pending_write(&mut self, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        let result = match self.write.as_mut() {
+            Some(write) => ready!(write.as_mut().poll(cx)),
+            None => Ok(()),
+        };
+        self.write = None;
+        Poll::Ready(result)
+    }
+}
+
+impl AsyncRead for StreamIo {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.unread.is_empty() {
+            let stream = this.stream.clone();
+            let read = this
+                .read
+                .get_or_insert_with(|| Box::pin(async move { stream.read_chunk().await }));
+            let result = ready!(read.as_mut().poll(cx));
+            this.read = None;
+
+            match result {
+                Ok(chunk) => this.unread = chunk,
+                // Streams report the closed connection as broken pipe, which is the end of file.
+                Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => {
+                    return Poll::Ready(Ok(()));
+                }
+                Err(error) => return Poll::Ready(Err(error)),
+            }
+        }
+
+        let size = std::cmp::min(buf.remaining(), this.unread.len());
+        buf.put_slice(&this.unread[..size]);
+        this.unread.drain(..size);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for StreamIo {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending_write(cx))?;
+
+        // Bytes are written in the background and the result is returned by the next write or
+        // flush.
+        let stream = this.stream.clone();
+        let bytes = buf.to_vec();
+        this.write = Some(Box::pin(async move { stream.write_chunk(&bytes).await }));
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        self.get_mut().poll_pending_write(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending_write(cx))?;
+
+        let stream = this.stream.clone();
+        let shutdown = this
+            .shutdown
+            .get_or_insert_with(|| Box::pin(async move { stream.shutdown().await }));
+        shutdown.as_mut().poll(cx)
+    }
+}
+
+struct Http2Body(RecvStream);
+
+impl RequestBody for Http2Body {
+    async fn next_chunk(&mut self) -> Option<Result<Vec<u8>, String>> {
+        match self.0.data().await {
+            Some(Ok(chunk)) => {
+                // Client is allowed to send more data once the chunk is consumed.
+                let _ = self.0.flow_control().release_capacity(chunk.len());
+                Some(Ok(chunk.to_vec()))
+            }
+            Some(Err(error)) => Some(Err(error.to_string())),
+            None => None,
+        }
+    }
+}
+
+struct Http2Response {
+    respond: SendResponse<Bytes>,
+    send_stream: Option<SendStream<Bytes>>,
+}
+
+impl ResponseSink for Http2Response {
+    async fn send_response(&mut self, response: http::Response<()>) -> Result<(), String> {
+        // h2 does not support sending the informational responses, so they are skipped.
+        if response.status().is_informational() {
+            return Ok(());
+        }
+
+        let send_stream = self
+            .respond
+            .send_response(response, false)
+            .map_err(|error| error.to_string())?;
+        self.send_stream = Some(send_stream);
+        Ok(())
+    }
+
+    async fn send_data(&mut self, data: Vec<u8>) -> Result<(), String> {
+        let send_stream = match self.send_stream.as_mut() {
+            Some(send_stream) => send_stream,
+            None => return Err("HTTP/2 response is not sent.".to_string()),
+        };
+
+        // Data is sent as the client grants the flow control window.
+        let mut data = Bytes::from(data);
+        while !data.is_empty() {
+            send_stream.reserve_capacity(data.len());
+
+            let capacity = match std::future::poll_fn(|cx| send_stream.poll_capacity(cx)).await {
+                Some(Ok(capacity)) => capacity,
+                Some(Err(error)) => return Err(error.to_string()),
+                None => return Err("HTTP/2 stream is closed.".to_string()),
+            };
+
+            if capacity == 0 {
+                continue;
+            }
+
+            let chunk = data.split_to(std::cmp::min(capacity, data.len()));
+            send_stream
+                .send_data(chunk, false)
+                .map_err(|error| error.to_string())?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), String> {
+        match self.send_stream.as_mut() {
+            Some(send_stream) => send_stream
+                .send_data(Bytes::new(), true)
+                .map_err(|error| error.to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn encode_integer(value: usize, prefix_bits: u32, block: &mut Vec<u8>) {
+    let max_prefix = (1 << prefix_bits) - 1;
+    if value < max_prefix {
+        block.push(value as u8);
+        return;
+    }
+
+    block.push(max_prefix as u8);
+    let mut value = value - max_prefix;
+    while value >= 128 {
+        block.push((value % 128 + 128) as u8);
+        value /= 128;
+    }
+    block.push(value as u8);
+}
+
+///
+/// Encodes the header as HPACK literal without indexing, so the header table of the connection is
+/// not changed.
+///
+fn encode_header(name: &[u8], value: &[u8], block: &mut Vec<u8>) {
+    block.push(0);
+    encode_integer(name.len(), 7, block);
+    block.extend_from_slice(name);
+    encode_integer(value.len(), 7, block);
+    block.extend_from_slice(value);
+}
+
+///
+/// Returns HTTP/2 `HEADERS` frame of the request if the HTTP/1.1 request asks to upgrade to h2c.
+/// Request with body is not upgraded, since the body is already sent over HTTP/1.1.
+///
+pub(crate) fn upgrade_frame(
+    method: &str,
+    raw_path: &str,
+    http_version: u8,
+    headers: &Headers,
+) -> Option<Vec<u8>> {
+    let has_token = |name: &str, token: &str| {
+        headers.multiple_values(name).iter().any(|value| {
+            value
+                .split(',')
+                .any(|value_token| value_token.trim().eq_ignore_ascii_case(token))
+        })
+    };
+
+    if http_version != 1
+        || !has_token("upgrade", "h2c")
+        || !has_token("connection", "upgrade")
+        || headers.value("http2-settings").is_none()
+    {
+        return None;
+    }
+
+    let content_length = headers.value("content-length");
+    if headers.value("transfer-encoding").is_some()
+        || content_length.is_some_and(|content_length| content_length.trim() != "0")
+    {
+        return None;
+    }
+
+    let mut block = vec![];
+    encode_header(b":method", method.as_bytes(), &mut block);
+    encode_header(b":scheme", b"http", &mut block);
+    encode_header(b":path", raw_path.as_bytes(), &mut block);
+    if let Some(host) = headers.value("host") {
+        encode_header(b":authority", host.as_bytes(), &mut block);
+    }
+
+    for (name, values) in headers {
+        let name = name.to_ascii_lowercase();
+        if name == "host"
+            || name == "http2-settings"
+            || name == "te"
+            || CONNECTION_HEADERS.contains(&name.as_str())
+        {
+            continue;
+        }
+
+        for value in values {
+            encode_header(name.as_bytes(), value, &mut block);
+        }
+    }
+
+    // Header block is not split into continuation frames.
+    if block.len() > DEFAULT_MAX_FRAME_SIZE {
+        return None;
+    }
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + block.len());
+    frame.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    frame.push(HEADERS_FRAME);
+    frame.push(END_HEADERS | END_STREAM);
+    frame.extend_from_slice(&1u32.to_be_bytes());
+    frame.extend(block);
+    Some(frame)
+}
+
+///
+/// Reads the connection preface and the settings sent by the client after the upgrade. The
+/// upgraded request is placed after them as stream 1, so it is answered like other HTTP/2
+/// requests.
+///
+async fn restore_with_upgrade_request(
+    stream: &Stream,
+    headers_frame: Vec<u8>,
+) -> std::io::Result<()> {
+    let settings_start = HTTP2_PREFACE.len();
+    let mut buffer: Vec<u8> = vec![];
+
+    let settings_end = loop {
+        if buffer.len() >= settings_start + FRAME_HEADER_SIZE {
+            if !buffer.starts_with(HTTP2_PREFACE) || buffer[settings_start + 3] != SETTINGS_FRAME {
+                return Err(std::io::Error::other(
+                    "Client did not send HTTP/2 connection preface after the upgrade.",
+                ));
+            }
+
+            let length = u32::from_be_bytes([
+                0,
+                buffer[settings_start],
+                buffer[settings_start + 1],
+                buffer[settings_start + 2],
+            ]) as usize;
+
+            if length > DEFAULT_MAX_FRAME_SIZE {
+                return Err(std::io::Error::other("HTTP/2 settings frame is too large."));
+            }
+
+            let settings_end = settings_start + FRAME_HEADER_SIZE + length;
+            if buffer.len() >= settings_end {
+                break settings_end;
+            }
+        }
+
+        buffer.extend(stream.read_chunk().await?);
+    };
+
+    let remaining = buffer.split_off(settings_end);
+    buffer.extend(headers_frame);
+    buffer.extend(remaining);
+    stream.restore_payload(&buffer).await
+}
+
+///
+/// Switches the HTTP/1.1 connection to HTTP/2 and serves the upgraded request.
+///
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn serve_upgraded(
+    stream: Arc<Stream>,
+    headers_frame: Vec<u8>,
+    scheme: String,
+    context: Arc<Context>,
+    router: Arc<Router>,
+    next: Next,
+    error_handlers: Arc<ErrorHandlers>,
+    request_constraints: Arc<RequestConstraints>,
+    connection_constraints: Arc<ConnectionConstraints>,
+    form_constraints: Arc<FormConstraints>,
+    session_manager: Arc<SessionManager>,
+) {
+    let switching_protocols =
+        b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n";
+    if let Err(error) = stream.write_chunk(switching_protocols).await {
+        racoon_debug!("Failed to upgrade to HTTP/2. Error: {}", error);
+        return;
+    }
+
+    let restore = restore_with_upgrade_request(&stream, headers_frame);
+    let result = match request_constraints.header_read_timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, restore).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::other(
+                "Timed out waiting for HTTP/2 preface.",
+            )),
+        },
+        None => restore.await,
+    };
+
+    if let Err(error) = result {
+        racoon_debug!("Failed to upgrade to HTTP/2. Error: {}", error);
+        let _ = stream.shutdown().await;
+        return;
+    }
+
+    serve(
+        stream,
+        scheme,
+        context,
+        router,
+        next,
+        error_handlers,
+        request_constraints,
+        connection_constraints,
+        form_constraints,
+        session_manager,
+    )
+    .await;
+}
+
+///
+/// Handles the request stream with the same handler as the HTTP/1 connections. Future is boxed
+/// with the explicit type, since the handler also serves the HTTP/2 connections.
+///
+#[allow(clippy::too_many_arguments)]
+fn handle_stream(
+    stream: Stream,
+    scheme: String,
+    context: Arc<Context>,
+    router: Arc<Router>,
+    next: Next,
+    error_handlers: Arc<ErrorHandlers>,
+    request_constraints: Arc<RequestConstraints>,
+    connection_constraints: Arc<ConnectionConstraints>,
+    form_constraints: Arc<FormConstraints>,
+    session_manager: Arc<SessionManager>,
+) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(Server::handle_stream(
+        stream,
+        scheme,
+        context,
+        router,
+        next,
+        error_handlers,
+        request_constraints,
+        connection_constraints,
+        form_constraints,
+        session_manager,
+    ))
+}
+
+///
+/// Serves the HTTP/2 connection. Each request is handled by the same handler as HTTP/1
+/// connections in its own task.
+///
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn serve(
+    stream: Arc<Stream>,
+    scheme: String,
+    context: Arc<Context>,
+    router: Arc<Router>,
+    next: Next,
+    error_handlers: Arc<ErrorHandlers>,
+    request_constraints: Arc<RequestConstraints>,
+    connection_constraints: Arc<ConnectionConstraints>,
+    form_constraints: Arc<FormConstraints>,
+    session_manager: Arc<SessionManager>,
+) {
+    let buffer_size = stream.buffer_size().await;
+    let peer_addr = stream.peer_addr().await;
+
+    let max_concurrent_streams = connection_constraints
+        .http2_max_concurrent_streams
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_STREAMS);
+    let handshake = h2::server::Builder::new()
+        .max_concurrent_streams(max_concurrent_streams)
+        .handshake::<_, Bytes>(StreamIo::new(stream));
+
+    let mut connection = match handshake.await {
+        Ok(connection) => connection,
+        Err(error) => {
+            racoon_debug!("Failed to establish HTTP/2 connection. Error: {}", error);
+            return;
+        }
+    };
+
+    let mut draining = connection_constraints.connections.track();
+    let mut shutting_down = false;
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = connection.accept() => Some(accepted),
+            _ = ConnectionTracker::wait_draining(&mut draining), if !shutting_down => None,
+        };
+
+        let (request, respond) = match accepted {
+            Some(Some(Ok(accepted))) => accepted,
+            Some(Some(Err(error))) => {
+                racoon_debug!("HTTP/2 connection closed. Error: {}", error);
+                break;
+            }
+            Some(None) => break,
+            None => {
+                // Open streams are completed before the connection is closed.
+                connection.graceful_shutdown();
+                shutting_down = true;
+                continue;
+            }
+        };
+
+        let peer_addr = peer_addr.clone();
+        let scheme = scheme.clone();
+        let context = context.clone();
+        let router = router.clone();
+        let next = next.clone();
+        let error_handlers = error_handlers.clone();
+        let request_constraints = request_constraints.clone();
+        let connection_constraints = connection_constraints.clone();
+        let form_constraints = form_constraints.clone();
+        let session_manager = session_manager.clone();
+
+        tokio::spawn(async move {
+            let (parts, body) = request.into_parts();
+            let request = http::Request::from_parts(parts, ());
+
+            let (request_sender, request_receiver) = mpsc::channel(8);
+            let (response_sender, response_receiver) = mpsc::channel(8);
+
+            let max_body_size = form_constraints.max_body_size(buffer_size);
+            let forward_body =
+                forward_request(request, Http2Body(body), request_sender, max_body_size);
+
+            let stream = Box::new(RequestStreamWrapper::new(
+                peer_addr,
+                buffer_size,
+                request_receiver,
+                response_sender,
+            ));
+
+            let handle_stream = handle_stream(
+                stream,
+                scheme,
+                context,
+                router,
+                next,
+                error_handlers,
+                request_constraints,
+                connection_constraints,
+                form_constraints,
+                session_manager,
+            );
+
+            let response = Http2Response {
+                respond,
+                send_stream: None,
+            };
+
+            let (_, _, result) = tokio::join!(
+                forward_body,
+                handle_stream,
+                forward_response(response, response_receiver)
+            );
+            if let Err(error) = result {
+                racoon_debug!("Failed to send HTTP/2 response. Error: {}", error);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use tokio::net::TcpStream;
+
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::path::{Path, View};
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+    use crate::core::server::tests::serve;
+
+    use super::upgrade_frame;
+
+    #[test]
+    fn test_upgrade_frame() {
+        let mut headers = Headers::new();
+        headers.set("Host", "example.com");
+        headers.set("Connection", "Upgrade, HTTP2-Settings");
+        headers.set("Upgrade", "h2c");
+        headers.set("HTTP2-Settings", "AAMAAABkAARAAAAAAAIAAAAA");
+
+        let frame = upgrade_frame("GET", "/", 1, &headers).unwrap();
+        let mut block = vec![];
+        for (name, value) in [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "example.com"),
+        ] {
+            block.push(0);
+            block.push(name.len() as u8);
+            block.extend(name.as_bytes());
+            block.push(value.len() as u8);
+            block.extend(value.as_bytes());
+        }
+
+        assert_eq!(
+            &[0, 0, block.len() as u8, 0x1, 0x5, 0, 0, 0, 1],
+            &frame[..9]
+        );
+        assert_eq!(block, frame[9..]);
+
+        // Request with body is served over HTTP/1.1.
+        headers.set("Content-Length", "5");
+        assert!(upgrade_frame("POST", "/", 1, &headers).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_streams() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let response: Response = HttpResponse::ok().body("OK");
+                response
+            })
+        };
+
+        let address = serve(move |server| {
+            server
+                .h2c(true)
+                .http2_max_concurrent_streams(2)
+                .urls(vec![Path::get("/", view)]);
+        });
+
+        let stream = TcpStream::connect(address).await.unwrap();
+        let (mut client, connection) = h2::client::handshake(stream).await.unwrap();
+        tokio::spawn(connection);
+
+        let request = http::Request::get("http://localhost/").body(()).unwrap();
+        let (response, _) = client.send_request(request, true).unwrap();
+        assert_eq!(200, response.await.unwrap().status());
+
+        // Limit is advertised in the settings received before the response.
+        assert_eq!(2, client.current_max_send_streams());
+    }
+}
@@ -0,0 +1,292 @@
+use std::sync::Arc;
+
+use bytes::{Buf, Bytes};
+use h3::server::RequestStream;
+use tokio::sync::mpsc;
+
+use crate::core::forms::FormConstraints;
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::router::Router;
+use crate::core::session::SessionManager;
+use crate::core::telemetry;
+use crate::racoon_debug;
+
+use super::bridge::{
+    forward_request, forward_response, RequestBody, RequestStreamWrapper, ResponseSink,
+};
+use super::{
+    ConnectionConstraints, Context, ErrorHandlers, RequestConstraints, Server, ShutdownLock,
+};
+
+///
+/// Address and TLS configuration of the experimental HTTP/3 listener.
+///
+#[derive(Clone)]
+pub struct Http3Config {
+    address: String,
+    server_config: Arc<rustls::ServerConfig>,
+}
+
+impl Http3Config {
+    ///
+    /// HTTP/3 requires TLS 1.3, so the server configuration must allow it. ALPN protocol is set to
+    /// `h3`.
+    ///
+    pub fn new<S: AsRef<str>>(address: S, mut server_config: rustls::ServerConfig) -> Self {
+        server_config.alpn_protocols = vec![b"h3".to_vec()];
+
+        Self {
+            address: address.as_ref().to_string(),
+            server_config: Arc::new(server_config),
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    ///
+    /// Value of `Alt-Svc` header advertising the HTTP/3 listener to the clients connected over
+    /// TCP.
+    ///
+    pub fn alt_svc(&self) -> String {
+        let port = self.address.rsplit(':').next().unwrap_or("443");
+        format!("h3=\":{}\"; ma=86400", port)
+    }
+}
+
+///
+/// Adds `Alt-Svc` header to the responses, so browsers can switch to HTTP/3.
+///
+pub(crate) struct AltSvc(pub(crate) String);
+
+impl AbstractMiddleware for AltSvc {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let alt_svc = self.0.clone();
+
+        Box::new(Box::pin(async move {
+            let mut response = next.run(request).await;
+            let headers = response.get_headers();
+            if headers.value("Alt-Svc").is_none() {
+                headers.set("Alt-Svc", alt_svc);
+            }
+            response
+        }))
+    }
+}
+
+///
+/// Binds UDP socket for receiving QUIC connections.
+///
+pub(crate) async fn bind(config: &Http3Config) -> std::io::Result<quinn::Endpoint> {
+    let quic_config =
+        quinn::crypto::rustls::QuicServerConfig::try_from(config.server_config.clone())
+            .map_err(std::io::Error::other)?;
+    let server_config = quinn::ServerConfig::with_crypto(Arc::new(quic_config));
+
+    let address = match tokio::net::lookup_host(&config.address).await?.next() {
+        Some(address) => address,
+        None => {
+            return Err(std::io::Error::other(format!(
+                "Failed to resolve address: {}",
+                config.address
+            )));
+        }
+    };
+
+    quinn::Endpoint::server(server_config, address)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn listen(
+    endpoint: quinn::Endpoint,
+    context: Arc<Context>,
+    router: Arc<Router>,
+    buffer_size: usize,
+    next: Next,
+    error_handlers: Arc<ErrorHandlers>,
+    request_constraints: Arc<RequestConstraints>,
+    connection_constraints: Arc<ConnectionConstraints>,
+    form_constraints: Arc<FormConstraints>,
+    session_manager: Arc<SessionManager>,
+    shutdown_lock: ShutdownLock,
+) {
+    loop {
+        let incoming;
+        tokio::select! {
+            result = endpoint.accept() => {
+                incoming = result;
+            }
+
+            _ = Server::wait_shutdown(shutdown_lock.clone()) => {
+                racoon_debug!("Shutting down HTTP/3 listener");
+                endpoint.close(0u32.into(), b"shutdown");
+                return;
+            }
+        }
+
+        let incoming = match incoming {
+            Some(incoming) => incoming,
+            None => return,
+        };
+
+        let context = context.clone();
+        let router = router.clone();
+        let next = next.clone();
+        let error_handlers = error_handlers.clone();
+        let request_constraints = request_constraints.clone();
+        let connection_constraints = connection_constraints.clone();
+        let form_constraints = form_constraints.clone();
+        let session_manager = session_manager.clone();
+
+        tokio::spawn(async move {
+            let connection = match incoming.await {
+                Ok(connection) => connection,
+                Err(error) => {
+                    racoon_debug!("Failed to establish QUIC connection. Error: {}", error);
+                    return;
+                }
+            };
+
+            let peer_addr = connection.remote_address();
+            let connection_span = telemetry::connection_span(peer_addr);
+
+            let handle_connection = async move {
+                let mut h3_connection = match h3::server::Connection::new(
+                    h3_quinn::Connection::new(connection),
+                )
+                .await
+                {
+                    Ok(h3_connection) => h3_connection,
+                    Err(error) => {
+                        racoon_debug!("Failed to establish HTTP/3 connection. Error: {}", error);
+                        return;
+                    }
+                };
+
+                loop {
+                    let resolver = match h3_connection.accept().await {
+                        Ok(Some(resolver)) => resolver,
+                        Ok(None) => break,
+                        Err(error) => {
+                            racoon_debug!("HTTP/3 connection closed. Error: {}", error);
+                            break;
+                        }
+                    };
+
+                    let context = context.clone();
+                    let router = router.clone();
+                    let next = next.clone();
+                    let error_handlers = error_handlers.clone();
+                    let request_constraints = request_constraints.clone();
+                    let connection_constraints = connection_constraints.clone();
+                    let form_constraints = form_constraints.clone();
+                    let session_manager = session_manager.clone();
+
+                    tokio::spawn(async move {
+                        let (request, stream) = match resolver.resolve_request().await {
+                            Ok(resolved) => resolved,
+                            Err(error) => {
+                                racoon_debug!("Failed to read HTTP/3 request. Error: {}", error);
+                                return;
+                            }
+                        };
+
+                        let (send_stream, recv_stream) = stream.split();
+                        let (request_sender, request_receiver) = mpsc::channel(8);
+                        let (response_sender, response_receiver) = mpsc::channel(8);
+
+                        let max_body_size = form_constraints.max_body_size(buffer_size);
+                        let forward_body = forward_request(
+                            request,
+                            Http3Body(recv_stream),
+                            request_sender,
+                            max_body_size,
+                        );
+
+                        let stream = Box::new(RequestStreamWrapper::new(
+                            Some(peer_addr.to_string()),
+                            buffer_size,
+                            request_receiver,
+                            response_sender,
+                        ));
+                        let handle_stream = Server::handle_stream(
+                            stream,
+                            "https".to_string(),
+                            context,
+                            router,
+                            next,
+                            error_handlers,
+                            request_constraints,
+                            connection_constraints,
+                            form_constraints,
+                            session_manager,
+                        );
+
+                        let (_, _, result) = tokio::join!(
+                            forward_body,
+                            handle_stream,
+                            forward_response(Http3Response(send_stream), response_receiver)
+                        );
+                        if let Err(error) = result {
+                            racoon_debug!("Failed to send HTTP/3 response. Error: {}", error);
+                        }
+                    });
+                }
+            };
+            telemetry::in_span(connection_span, handle_connection).await;
+        });
+    }
+}
+
+struct Http3Body<S: h3::quic::RecvStream>(RequestStream<S, Bytes>);
+
+impl<S: h3::quic::RecvStream> RequestBody for Http3Body<S> {
+    async fn next_chunk(&mut self) -> Option<Result<Vec<u8>, String>> {
+        match self.0.recv_data().await {
+            Ok(Some(mut chunk)) => Some(Ok(chunk.copy_to_bytes(chunk.remaining()).to_vec())),
+            Ok(None) => None,
+            Err(error) => Some(Err(error.to_string())),
+        }
+    }
+}
+
+struct Http3Response<S: h3::quic::SendStream<Bytes>>(RequestStream<S, Bytes>);
+
+impl<S: h3::quic::SendStream<Bytes>> ResponseSink for Http3Response<S> {
+    async fn send_response(&mut self, response: http::Response<()>) -> Result<(), String> {
+        self.0
+            .send_response(response)
+            .await
+            .map_err(|error| error.to_string())
+    }
+
+    async fn send_data(&mut self, data: Vec<u8>) -> Result<(), String> {
+        self.0
+            .send_data(Bytes::from(data))
+            .await
+            .map_err(|error| error.to_string())
+    }
+
+    async fn finish(&mut self) -> Result<(), String> {
+        self.0.finish().await.map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::Http3Config;
+
+    #[test]
+    fn test_alt_svc() {
+        let server_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(std::sync::Arc::new(
+                rustls::server::ResolvesServerCertUsingSni::new(),
+            ));
+        let config = Http3Config::new("0.0.0.0:8443", server_config);
+        assert_eq!("h3=\":8443\"; ma=86400", config.alt_svc());
+    }
+}
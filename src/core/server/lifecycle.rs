@@ -0,0 +1,126 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+use crate::racoon_error;
+
+pub type LifecycleResult = Pin<Box<dyn Future<Output = std::io::Result<()>> + Send>>;
+
+///
+/// Async callback run at the server lifecycle stage.
+///
+pub type LifecycleHook = Arc<dyn Fn() -> LifecycleResult + Send + Sync>;
+
+///
+/// Callbacks registered for the server lifecycle stages.
+///
+#[derive(Clone, Default)]
+pub(crate) struct Lifecycle {
+    pub on_start: Vec<LifecycleHook>,
+    pub on_ready: Vec<LifecycleHook>,
+    pub on_shutdown: Vec<LifecycleHook>,
+}
+
+impl Lifecycle {
+    pub fn hook<F, Fut>(hook: F) -> LifecycleHook
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<()>> + Send + 'static,
+    {
+        Arc::new(move || Box::pin(hook()))
+    }
+
+    ///
+    /// Runs the hooks in the order they are registered. Stops at the first failed hook.
+    ///
+    pub async fn run_until_error(hooks: &[LifecycleHook]) -> std::io::Result<()> {
+        for hook in hooks {
+            hook().await?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Runs all the shutdown hooks, so the failed hook does not prevent the cleanup of others.
+    ///
+    pub async fn shutdown(&self) {
+        for hook in &self.on_shutdown {
+            if let Err(error) = hook().await {
+                racoon_error!("Shutdown hook failed. Error: {}", error);
+            }
+        }
+    }
+}
+
+///
+/// Runs the listener once the startup completes. Listener is not run if the startup fails.
+///
+pub(crate) async fn after_startup<F>(
+    mut startup: watch::Receiver<bool>,
+    listener: F,
+) -> std::io::Result<()>
+where
+    F: Future<Output = std::io::Result<()>>,
+{
+    if startup.wait_for(|started| *started).await.is_err() {
+        return Ok(());
+    }
+    listener.await
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::core::server::Server;
+
+    #[tokio::test]
+    async fn test_failed_startup() {
+        let calls = Arc::new(Mutex::new(vec![]));
+
+        let mut server = Server::bind("127.0.0.1:0");
+        let start_calls = calls.clone();
+        let failing_calls = calls.clone();
+        let ready_calls = calls.clone();
+        let shutdown_calls = calls.clone();
+
+        server
+            .on_start(move || {
+                let calls = start_calls.clone();
+                async move {
+                    calls.lock().unwrap().push("start");
+                    Ok(())
+                }
+            })
+            .on_start(move || {
+                let calls = failing_calls.clone();
+                async move {
+                    calls.lock().unwrap().push("failing start");
+                    Err(std::io::Error::other("Database is not reachable."))
+                }
+            })
+            .on_ready(move || {
+                let calls = ready_calls.clone();
+                async move {
+                    calls.lock().unwrap().push("ready");
+                    Ok(())
+                }
+            })
+            .on_shutdown(move || {
+                let calls = shutdown_calls.clone();
+                async move {
+                    calls.lock().unwrap().push("shutdown");
+                    Ok(())
+                }
+            });
+
+        let error = server.run().await.unwrap_err();
+        assert_eq!("Database is not reachable.", error.to_string());
+        assert_eq!(
+            vec!["start", "failing start", "shutdown"],
+            *calls.lock().unwrap()
+        );
+    }
+}
@@ -1,32 +1,73 @@
+#[cfg(feature = "acme")]
+pub mod acme;
+#[cfg(any(feature = "http2", feature = "http3"))]
+mod bridge;
+pub mod config;
+pub mod dev;
+#[cfg(feature = "http2")]
+mod http2;
+#[cfg(feature = "http3")]
+pub mod http3;
+pub mod lifecycle;
+pub mod socket;
+pub mod systemd;
+pub mod tls;
+pub mod upgrade;
 pub mod utils;
 
 use std::any::Any;
 use std::collections::HashMap;
-use std::env;
 use std::ffi::OsStr;
+use std::future::Future;
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex as StdMutex};
+use std::time::Duration;
 
-use matchit::Router;
-
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::net::{TcpListener, UnixListener};
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex, OwnedSemaphorePermit, Semaphore};
 use tokio_rustls::TlsAcceptor;
 
+use crate::core::cors::Cors;
 use crate::core::forms::FormConstraints;
 use crate::core::headers::HeaderValue;
-use crate::core::middleware::Middleware;
+use crate::core::health::Health;
+use crate::core::logging::{self, LevelFilter};
+use crate::core::metrics::{self, Metrics};
+use crate::core::middleware::{
+    self, AbstractMiddleware, Middleware, MiddlewareChain, Middlewares, Next,
+};
+use crate::core::openapi::OpenApi;
 use crate::core::parser::headers::read_request_headers;
 use crate::core::parser::{params, path};
-use crate::core::path::{Path, PathParams, Paths};
-use crate::core::request::{Request, RequestError};
+use crate::core::path::{Path, PathParams, Paths, Scope, View};
+use crate::core::ratelimit::connections::ConnectionRateLimit;
+use crate::core::recovery;
+use crate::core::request::{Extensions, Request, RequestError};
 use crate::core::response::status::ResponseStatus;
 use crate::core::response::{AbstractResponse, HttpResponse};
+use crate::core::router::{
+    MatchedPath, RouteInfo, RouteResult, RouteTable, Router, SpaFallback, TrailingSlash,
+};
+use crate::core::scheduler::Job;
+use crate::core::stream::timeout::{ProtocolSwitch, TimeoutStreamWrapper};
+#[cfg(feature = "io-uring")]
+use crate::core::stream::uring::UringDriver;
 use crate::core::stream::{Stream, TcpStreamWrapper, UnixStreamWrapper};
+use crate::core::telemetry;
+use crate::core::transfer::{ByteCounters, CountingStreamWrapper, TransferSize};
+use crate::core::websocket::WebSocketConfig;
+use dev::DevMode;
+use lifecycle::{after_startup, Lifecycle};
+use socket::SocketOptions;
+use systemd::ActivatedListener;
+use upgrade::UpgradeHandle;
 
-use crate::{racoon_debug, racoon_error};
+use crate::{racoon_debug, racoon_error, racoon_info, racoon_warn};
 
 use crate::core::headers::Headers;
 use crate::core::response;
@@ -34,9 +75,16 @@ use crate::core::session::managers::FileSessionManager;
 use crate::core::session::{AbstractSessionManager, SessionManager};
 use crate::core::stream::TlsTcpStreamWrapper;
 
+#[derive(Debug, Clone)]
 pub struct RequestConstraints {
+    /// Size of the request line and headers. Clients sending larger headers receive
+    /// `431 Request Header Fields Too Large`.
     pub max_request_header_size: usize,
+    /// Number of headers allowed in the request.
     pub max_header_count: usize,
+    /// Duration within which the client needs to send the complete request line and headers.
+    /// Clients sending slower receive `408 Request Timeout`.
+    pub header_read_timeout: Option<Duration>,
 }
 
 impl RequestConstraints {
@@ -49,6 +97,194 @@ impl RequestConstraints {
     }
 }
 
+///
+/// Limits applied to the client connections.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionConstraints {
+    /// Duration for which the keep-alive connection can stay idle waiting for the next request.
+    pub keep_alive_timeout: Option<Duration>,
+    /// Duration within which each chunk of the request body needs to be received.
+    pub body_read_timeout: Option<Duration>,
+    /// Duration within which each chunk of the response needs to be written.
+    pub response_write_timeout: Option<Duration>,
+    /// Number of requests served by a connection before it is closed.
+    pub max_requests_per_connection: Option<usize>,
+    /// Number of connections handled concurrently across all the listeners.
+    pub max_connections: Option<usize>,
+    /// Behavior when the number of connections reaches `max_connections`.
+    pub connection_limit_behavior: ConnectionLimitBehavior,
+    /// Size of the queue holding the connections not yet accepted by the TCP listeners.
+    pub backlog: Option<u32>,
+    /// Duration for which the open connections are waited to complete on shutdown.
+    pub drain_timeout: Option<Duration>,
+    /// Limits of the connections per client IP address.
+    pub rate_limit: Option<ConnectionRateLimit>,
+    /// Heartbeat and idle limits of the WebSocket connections upgraded from the requests.
+    pub websocket: Option<WebSocketConfig>,
+    connection_slots: Option<Arc<Semaphore>>,
+    #[cfg(feature = "io-uring")]
+    uring: Option<Arc<UringDriver>>,
+    #[cfg(feature = "http2")]
+    h2c: bool,
+    #[cfg(feature = "http2")]
+    http2_max_concurrent_streams: Option<u32>,
+    connections: ConnectionTracker,
+    dev_error_pages: bool,
+    state: Arc<Extensions>,
+}
+
+impl ConnectionConstraints {
+    ///
+    /// Reserves a slot for the accepted connection. The slot is released when the returned permit
+    /// is dropped. Returns `None` if the connection needs to be rejected.
+    ///
+    async fn acquire_slot(&self) -> Option<Option<OwnedSemaphorePermit>> {
+        let connection_slots = match &self.connection_slots {
+            Some(connection_slots) => connection_slots.clone(),
+            None => return Some(None),
+        };
+
+        match self.connection_limit_behavior {
+            ConnectionLimitBehavior::Reject => match connection_slots.try_acquire_owned() {
+                Ok(permit) => Some(Some(permit)),
+                Err(_) => None,
+            },
+            // Accept loop waits here, so the new connections stay in the listener backlog.
+            ConnectionLimitBehavior::Pause => connection_slots.acquire_owned().await.ok().map(Some),
+        }
+    }
+}
+
+///
+/// Signals the server shutdown to the long-lived responses such as WebSocket and server-sent
+/// events, so they can be closed before the drain timeout elapses. Available from the request
+/// extensions.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::server::ShutdownSignal;
+///
+/// async fn report(request: Request) -> Response {
+///     if let Some(shutdown) = request.extensions.get::<ShutdownSignal>() {
+///         tokio::select! {
+///             _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => {}
+///             _ = shutdown.wait() => {
+///                 return HttpResponse::service_unavailable().body("Shutting down.");
+///             }
+///         }
+///     }
+///     HttpResponse::ok().body("Report")
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal(pub(crate) ConnectionTracker);
+
+impl ShutdownSignal {
+    pub fn is_shutting_down(&self) -> bool {
+        self.0.is_draining()
+    }
+
+    ///
+    /// Resolves when the server starts shutting down.
+    ///
+    pub async fn wait(&self) {
+        // Subscribed only while waiting, so the drained server does not wait for the signal held
+        // after the connection is closed.
+        let mut draining = self.0.track();
+        ConnectionTracker::wait_draining(&mut draining).await;
+    }
+}
+
+///
+/// Tracks the open connections, so they can be drained on shutdown.
+///
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionTracker(Arc<watch::Sender<bool>>);
+
+impl Default for ConnectionTracker {
+    fn default() -> Self {
+        Self(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl ConnectionTracker {
+    ///
+    /// Receiver held by the connection while it is open. Value is changed to true when the server
+    /// starts draining.
+    ///
+    fn track(&self) -> watch::Receiver<bool> {
+        self.0.subscribe()
+    }
+
+    fn is_draining(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    ///
+    /// Resolves when the server starts draining.
+    ///
+    async fn wait_draining(draining: &mut watch::Receiver<bool>) {
+        let _ = draining.wait_for(|draining| *draining).await;
+    }
+
+    ///
+    /// Closes the idle keep-alive connections and waits for the other connections to complete
+    /// their current request.
+    ///
+    pub(crate) async fn drain(&self, timeout: Option<Duration>) {
+        self.0.send_replace(true);
+
+        if let Some(timeout) = timeout {
+            racoon_debug!("Draining {} connections.", self.0.receiver_count());
+
+            if tokio::time::timeout(timeout, self.0.closed())
+                .await
+                .is_err()
+            {
+                racoon_warn!(
+                    "Drain timeout elapsed with {} open connections.",
+                    self.0.receiver_count()
+                );
+            }
+        }
+    }
+}
+
+///
+/// Behavior when the maximum number of concurrent connections is reached.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ConnectionLimitBehavior {
+    /// Responds `503 Service Unavailable` and closes the new connection.
+    #[default]
+    Reject,
+    /// Stops accepting new connections until one of the active connections is closed.
+    Pause,
+}
+
+///
+/// How the TCP listeners bound by the server use the Tokio runtime. Custom listeners and Unix
+/// Domain Sockets always use the shared runtime.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RuntimeTopology {
+    /// Single accept loop per listener on the runtime running the server.
+    #[default]
+    Shared,
+    /// Number of accept loops per listener on the runtime running the server. Each loop has its
+    /// own socket bound with `SO_REUSEPORT`, so the kernel distributes the connections.
+    AcceptPerCore(usize),
+    /// Number of single-threaded runtimes per listener, each with its own `SO_REUSEPORT` accept
+    /// loop. Connections are handled on the runtime which accepted them.
+    RuntimePerCore(usize),
+}
+
 pub type Context = Pin<Box<dyn Any + Send + Sync>>;
 
 #[derive(Debug)]
@@ -59,21 +295,65 @@ pub enum RequestScheme {
 
 pub type ShutdownLock = Arc<(StdMutex<()>, Condvar)>;
 
+///
+/// Listener bound when the server runs.
+///
+enum Listener {
+    Tcp {
+        address: String,
+        tls_acceptor: Option<TlsAcceptor>,
+    },
+    CustomTcp {
+        listener: TcpListener,
+        tls_acceptor: Option<TlsAcceptor>,
+    },
+    Uds(String),
+    CustomUnix(UnixListener),
+    Activated(ActivatedListener),
+}
+
+///
+/// Custom views for rendering errors generated by the server, mapped by status code.
+///
+pub type ErrorHandlers = HashMap<u32, View>;
+
 pub struct Server {
-    scheme: String,
-    bind_address: Option<String>,
-    sock_path: Option<String>,
-    custom_tcp_listener: Option<TcpListener>,
-    custom_unix_listener: Option<UnixListener>,
-    tls_acceptor: Option<TlsAcceptor>,
-    router: Arc<Router<Path>>,
+    scheme: Option<String>,
+    listeners: Vec<Listener>,
+    listener_fds: Arc<StdMutex<Vec<RawFd>>>,
+    sock_permissions: Option<u32>,
+    paths: Paths,
+    trailing_slash: TrailingSlash,
+    case_insensitive_paths: bool,
+    normalize_paths: bool,
+    spa_fallbacks: Vec<SpaFallback>,
+    cors: Option<Cors>,
+    log_routes: bool,
+    router: Arc<Router>,
     context: Arc<Context>,
     buffer_size: usize,
-    nodelay: Arc<AtomicBool>,
+    socket_options: Arc<SocketOptions>,
+    topology: RuntimeTopology,
+    worker_threads: Option<usize>,
+    #[cfg(feature = "io-uring")]
+    uring_workers: Option<usize>,
     middleware: Option<Middleware>,
+    middlewares: Middlewares,
+    error_handlers: Arc<ErrorHandlers>,
     request_constraints: Arc<RequestConstraints>,
+    connection_constraints: Arc<ConnectionConstraints>,
     form_constraints: Arc<FormConstraints>,
     session_manager: Option<Arc<SessionManager>>,
+    #[cfg(feature = "http3")]
+    http3: Option<http3::Http3Config>,
+    #[cfg(feature = "acme")]
+    acme: Option<acme::AcmeConfig>,
+    health: Option<Health>,
+    openapi: Option<OpenApi>,
+    dev_mode: Option<DevMode>,
+    certificate_reloaders: Vec<tls::CertificateReloader>,
+    lifecycle: Lifecycle,
+    jobs: Vec<Job>,
     shutdown_lock: ShutdownLock,
 }
 
@@ -82,31 +362,48 @@ impl Server {
         let default_request_constraint = RequestConstraints {
             max_request_header_size: 5 * 1024 * 1024, // 5 MiB
             max_header_count: 100,
+            header_read_timeout: None,
         };
 
-        let default_form_constraint = FormConstraints::new(
-            512 * 1024 * 1024, // 512 MiB
-            2 * 1024,          // 2 KiB
-            512 * 1024 * 1024, // 512 MiB
-            2 * 1024 * 1024,   // 2 MiB
-            HashMap::new(),
-        );
+        let default_form_constraint = FormConstraints::default();
 
         Self {
-            scheme: "http".to_string(),
-            bind_address: None,
-            sock_path: None,
-            custom_tcp_listener: None,
-            custom_unix_listener: None,
-            tls_acceptor: None,
+            scheme: None,
+            listeners: vec![],
+            listener_fds: Arc::new(StdMutex::new(vec![])),
+            sock_permissions: None,
+            paths: Paths::new(),
+            trailing_slash: TrailingSlash::default(),
+            case_insensitive_paths: false,
+            normalize_paths: false,
+            spa_fallbacks: vec![],
+            cors: None,
+            log_routes: false,
             router: Arc::new(Router::new()),
             context: Arc::new(Box::pin(None::<String>)),
             buffer_size: 8096,
-            nodelay: Arc::new(AtomicBool::new(false)),
+            socket_options: Arc::new(SocketOptions::default()),
+            topology: RuntimeTopology::default(),
+            worker_threads: None,
+            #[cfg(feature = "io-uring")]
+            uring_workers: None,
             middleware: None,
+            middlewares: vec![],
+            error_handlers: Arc::new(ErrorHandlers::new()),
             request_constraints: Arc::from(default_request_constraint),
+            connection_constraints: Arc::new(ConnectionConstraints::default()),
             form_constraints: Arc::from(default_form_constraint),
             session_manager: None,
+            #[cfg(feature = "http3")]
+            http3: None,
+            #[cfg(feature = "acme")]
+            acme: None,
+            health: None,
+            openapi: None,
+            dev_mode: None,
+            certificate_reloaders: vec![],
+            lifecycle: Lifecycle::default(),
+            jobs: vec![],
             shutdown_lock: Arc::new((StdMutex::new(()), Condvar::new())),
         }
     }
@@ -114,42 +411,94 @@ impl Server {
     /// Binds server to given port.
     pub fn bind<S: AsRef<str>>(address: S) -> Self {
         let mut instance = Self::initialize_default();
-        instance.bind_address = Some(address.as_ref().to_string());
+        instance.add_listener(address);
         instance
     }
 
-    /// Binds server to Unix Domain Socket.
+    ///
+    /// Binds server to Unix Domain Socket. Existing socket file is replaced and the socket file is
+    /// removed when the server shuts down.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind_uds("/run/app.sock");
+    /// server.uds_permissions(0o660);
+    /// ```
+    ///
     pub fn bind_uds<S: AsRef<str>>(path: S) -> Self {
-        let path = path.as_ref();
-
-        // If sock file exists, removes sock file.
-        let path_buf = PathBuf::from(path);
-        if path_buf.exists() {
-            let _ = std::fs::remove_file(path);
-        }
-
         let mut instance = Self::initialize_default();
-        instance.sock_path = Some(path.to_string());
+        instance.add_uds_listener(path);
         instance
     }
 
+    ///
+    /// Sets permissions of the socket files created by `Server::bind_uds`, such as `0o660` to allow
+    /// the reverse proxy running in the same group to connect.
+    ///
+    pub fn uds_permissions(&mut self, mode: u32) -> &mut Self {
+        self.sock_permissions = Some(mode);
+        self
+    }
+
     pub fn from_tcp_listener(tcp_listener: TcpListener) -> Self {
         let mut instance = Self::initialize_default();
-        instance.custom_tcp_listener = Some(tcp_listener);
+        instance.listeners.push(Listener::CustomTcp {
+            listener: tcp_listener,
+            tls_acceptor: None,
+        });
         instance
     }
 
     pub fn from_unix_listener(unix_listener: UnixListener) -> Self {
         let mut instance = Self::initialize_default();
-        instance.custom_unix_listener = Some(unix_listener);
+        instance.listeners.push(Listener::CustomUnix(unix_listener));
         instance
     }
 
+    ///
+    /// Uses the listening sockets passed by systemd socket activation. Returns error if the
+    /// process is not socket activated.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use racoon::core::server::Server;
+    ///
+    /// // app.socket: ListenStream=80
+    /// let mut server = Server::from_systemd().unwrap();
+    /// ```
+    ///
+    pub fn from_systemd() -> std::io::Result<Self> {
+        let mut instance = Self::initialize_default();
+        instance.add_systemd_listeners()?;
+
+        if instance.listeners.is_empty() {
+            return Err(std::io::Error::other(
+                "No listening sockets are passed by systemd.",
+            ));
+        }
+        Ok(instance)
+    }
+
+    ///
+    /// Adds the listening sockets passed by systemd socket activation, if any.
+    ///
+    pub fn add_systemd_listeners(&mut self) -> std::io::Result<&mut Self> {
+        for listener in systemd::listeners()? {
+            self.listeners.push(Listener::Activated(listener));
+        }
+        Ok(self)
+    }
+
     pub fn bind_tls_custom(tcp_listener: TcpListener, tls_acceptor: TlsAcceptor) -> Self {
         let mut instance = Self::initialize_default();
-        instance.custom_tcp_listener = Some(tcp_listener);
-        instance.tls_acceptor = Some(tls_acceptor);
-        instance.scheme = "https".to_string();
+        instance.listeners.push(Listener::CustomTcp {
+            listener: tcp_listener,
+            tls_acceptor: Some(tls_acceptor),
+        });
         instance
     }
 
@@ -158,14 +507,139 @@ impl Server {
         certificate_path: P,
         private_key_path: P,
     ) -> std::io::Result<Self> {
-        let acceptor = utils::tls_acceptor_from_path(certificate_path, private_key_path)?;
         let mut instance = Server::initialize_default();
-        instance.scheme = "https".to_string();
-        instance.bind_address = Some(address.as_ref().to_string());
-        instance.tls_acceptor = Some(acceptor);
+        instance.add_tls_listener(address, certificate_path, private_key_path)?;
         Ok(instance)
     }
 
+    ///
+    /// Additionally listens on the address. All the listeners share the same routes, middlewares
+    /// and settings, and are run together.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use racoon::core::server::Server;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut server = Server::bind("0.0.0.0:80");
+    /// server
+    ///     .add_tls_listener("0.0.0.0:443", "cert.pem", "key.pem")?
+    ///     .add_uds_listener("/run/app.sock");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    pub fn add_listener<S: AsRef<str>>(&mut self, address: S) -> &mut Self {
+        self.listeners.push(Listener::Tcp {
+            address: address.as_ref().to_string(),
+            tls_acceptor: None,
+        });
+        self
+    }
+
+    ///
+    /// Additionally listens for HTTPS requests on the address. TLS is used only for this listener.
+    ///
+    pub fn add_tls_listener<S: AsRef<str>, P: AsRef<OsStr>>(
+        &mut self,
+        address: S,
+        certificate_path: P,
+        private_key_path: P,
+    ) -> std::io::Result<&mut Self> {
+        let acceptor = utils::tls_acceptor_from_path(certificate_path, private_key_path)?;
+        self.listeners.push(Listener::Tcp {
+            address: address.as_ref().to_string(),
+            tls_acceptor: Some(acceptor),
+        });
+        Ok(self)
+    }
+
+    ///
+    /// Additionally listens for HTTPS requests on the address with the certificate which can be
+    /// reloaded while the server is running. See [`tls::CertificateReloader`].
+    ///
+    pub fn add_reloadable_tls_listener<S: AsRef<str>>(
+        &mut self,
+        address: S,
+        reloader: tls::CertificateReloader,
+    ) -> &mut Self {
+        self.listeners.push(Listener::Tcp {
+            address: address.as_ref().to_string(),
+            tls_acceptor: Some(reloader.tls_acceptor()),
+        });
+        self.certificate_reloaders.push(reloader);
+        self
+    }
+
+    ///
+    /// Additionally listens on the Unix Domain Socket. Existing socket file is replaced.
+    ///
+    pub fn add_uds_listener<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+        let path = path.as_ref();
+
+        // If sock file exists, removes sock file.
+        let path_buf = PathBuf::from(path);
+        if path_buf.exists() {
+            let _ = std::fs::remove_file(path);
+        }
+
+        self.listeners.push(Listener::Uds(path.to_string()));
+        self
+    }
+
+    ///
+    /// Additionally listens for HTTP/3 requests over QUIC on the UDP address. Requests are handled
+    /// by the same routes and middlewares as the TCP listener, which advertises HTTP/3 to the
+    /// clients with `Alt-Svc` header. Experimental and available with `http3` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use racoon::core::server::Server;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut server = Server::bind_tls("0.0.0.0:443", "cert.pem", "key.pem")?;
+    /// server.http3("0.0.0.0:443", "cert.pem", "key.pem")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    #[cfg(feature = "http3")]
+    pub fn http3<S: AsRef<str>, P: AsRef<OsStr>>(
+        &mut self,
+        address: S,
+        certificate_path: P,
+        private_key_path: P,
+    ) -> std::io::Result<&mut Self> {
+        let server_config = utils::server_config_from_path(certificate_path, private_key_path)?;
+        self.http3 = Some(http3::Http3Config::new(address, server_config));
+        Ok(self)
+    }
+
+    ///
+    /// Listens for HTTP/3 requests with the custom TLS configuration.
+    ///
+    #[cfg(feature = "http3")]
+    pub fn http3_custom(&mut self, config: http3::Http3Config) -> &mut Self {
+        self.http3 = Some(config);
+        self
+    }
+
+    ///
+    /// Adds HTTPS listener serving the certificate obtained and renewed with ACME. Requests are
+    /// answered with TLS handshake error until the first certificate is issued.
+    ///
+    #[cfg(feature = "acme")]
+    pub fn acme<S: AsRef<str>>(&mut self, address: S, config: acme::AcmeConfig) -> &mut Self {
+        self.listeners.push(Listener::Tcp {
+            address: address.as_ref().to_string(),
+            tls_acceptor: Some(config.tls_acceptor()),
+        });
+        self.acme = Some(config);
+        self
+    }
+
     /// Force provided scheme in all the requests
     ///
     /// # Examples
@@ -190,28 +664,132 @@ impl Server {
     pub fn set_scheme(&mut self, scheme: RequestScheme) -> &mut Self {
         match scheme {
             RequestScheme::HTTP => {
-                self.scheme = "http".to_string();
+                self.scheme = Some("http".to_string());
             }
 
             RequestScheme::HTTPS => {
-                self.scheme = "https".to_string();
+                self.scheme = Some("https".to_string());
             }
         }
 
         self
     }
 
-    /// Enables logging for internal debug
+    ///
+    /// Emits all the levels of the framework logs, same as `RACOON_LOGGING=trace`. Use
+    /// `logging::set_max_level` for the other levels.
+    ///
     pub fn enable_logging() {
-        env::set_var("RACOON_LOGGING", "true");
+        logging::set_max_level(LevelFilter::Trace);
     }
 
-    /// 
+    ///
     /// Sets nodelay to client stream.
     /// It is not available for Unix Domain Socket.
     ///
-    pub fn nodelay(self) -> Self {
-        self.nodelay.store(true, Ordering::Relaxed);
+    pub fn nodelay(mut self) -> Self {
+        self.socket_options = Arc::new(self.socket_options.as_ref().clone().nodelay(true));
+        self
+    }
+
+    ///
+    /// Socket options for the TCP listeners and the accepted connections.
+    ///
+    pub fn socket_options(&mut self, socket_options: SocketOptions) -> &mut Self {
+        self.socket_options = Arc::new(socket_options);
+        self
+    }
+
+    ///
+    /// Runs the accept loops of the TCP listeners as specified by the topology.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use racoon::core::server::{RuntimeTopology, Server};
+    ///
+    /// let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server
+    ///     .topology(RuntimeTopology::RuntimePerCore(cores))
+    ///     .worker_threads(2);
+    /// server.run_blocking().unwrap();
+    /// ```
+    ///
+    pub fn topology(&mut self, topology: RuntimeTopology) -> &mut Self {
+        self.topology = topology;
+        self
+    }
+
+    ///
+    /// Number of worker threads of the runtime built by `run_blocking`. Defaults to the number of
+    /// cores.
+    ///
+    pub fn worker_threads(&mut self, worker_threads: usize) -> &mut Self {
+        self.worker_threads = Some(worker_threads);
+        self
+    }
+
+    ///
+    /// Performs the socket reads, writes and file transfers of plain TCP connections with
+    /// io_uring on the number of worker threads. Accepting connections, TLS and Unix sockets
+    /// stay on the Tokio runtime. Available on Linux with `io-uring` feature. `run` fails if
+    /// io_uring is not permitted by the kernel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.io_uring(2);
+    /// ```
+    ///
+    #[cfg(feature = "io-uring")]
+    pub fn io_uring(&mut self, workers: usize) -> &mut Self {
+        self.uring_workers = Some(workers);
+        self
+    }
+
+    ///
+    /// Accepts cleartext HTTP/2 connections on the listeners, both with prior knowledge and by
+    /// upgrading HTTP/1.1 requests with `Upgrade: h2c` header. Meant for trusted networks, such as
+    /// the traffic from the reverse proxy or between the internal services. Available with
+    /// `http2` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.h2c(true);
+    /// ```
+    ///
+    #[cfg(feature = "http2")]
+    pub fn h2c(&mut self, enabled: bool) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).h2c = enabled;
+        self
+    }
+
+    ///
+    /// Number of streams each HTTP/2 connection can have open at once, so a single client cannot
+    /// start unlimited concurrent requests. Default is 100. Available with `http2` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.h2c(true).http2_max_concurrent_streams(32);
+    /// ```
+    ///
+    #[cfg(feature = "http2")]
+    pub fn http2_max_concurrent_streams(&mut self, max_streams: u32) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).http2_max_concurrent_streams =
+            Some(max_streams);
         self
     }
 
@@ -229,6 +807,33 @@ impl Server {
         self
     }
 
+    ///
+    /// Shares the value such as the database pool, config or HTTP client with all the handlers.
+    /// Can be called multiple times with the values of different types. The value is read with
+    /// `request.state::<T>()` or the `State<T>` handler argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::server::Server;
+    ///
+    /// #[derive(Clone)]
+    /// struct Config {
+    ///     site_name: String,
+    /// }
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.with_state(Config {
+    ///     site_name: "Racoon".to_string(),
+    /// });
+    /// ```
+    ///
+    pub fn with_state<T: Send + Sync + 'static>(&mut self, state: T) -> &mut Self {
+        let connection_constraints = Arc::make_mut(&mut self.connection_constraints);
+        Arc::make_mut(&mut connection_constraints.state).insert(state);
+        self
+    }
+
     /// Buffer size for reading and writing stream.
     pub fn buffer_size(&mut self, size: usize) -> &mut Self {
         self.buffer_size = size;
@@ -241,150 +846,962 @@ impl Server {
         self
     }
 
-    /// Constraints for parsing request body.
-    pub fn form_constraints(&mut self, form_constraints: FormConstraints) -> &mut Self {
-        self.form_constraints = Arc::from(form_constraints);
+    ///
+    /// Maximum size of the request line and headers. Requests with larger headers are answered
+    /// with `431 Request Header Fields Too Large` and the connection is closed.
+    ///
+    pub fn max_request_header_size(&mut self, size: usize) -> &mut Self {
+        Arc::make_mut(&mut self.request_constraints).max_request_header_size = size;
         self
     }
 
-    /// Pass vec of paths.
-    pub fn urls(&mut self, paths: Paths) -> &mut Self {
-        let mut router = Router::new();
-
-        for path in paths {
-            let path_name = path.name.to_string();
+    ///
+    /// Closes the connection with `408 Request Timeout` if the client does not send the complete
+    /// request headers within the duration. For the first request the time is counted from the
+    /// connection accept, for the next requests on keep-alive connection from the first received
+    /// byte. Protects the server from slowloris clients holding the connections open by sending
+    /// the headers slowly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server
+    ///     .header_read_timeout(Duration::from_secs(10))
+    ///     .max_request_header_size(16 * 1024);
+    /// ```
+    ///
+    pub fn header_read_timeout(&mut self, timeout: Duration) -> &mut Self {
+        Arc::make_mut(&mut self.request_constraints).header_read_timeout = Some(timeout);
+        self
+    }
 
-            match router.insert(&path_name, path) {
-                Ok(()) => {}
-                Err(error) => {
-                    panic!("Invalid path \"{}\" pattern. Error: {}", path_name, error);
-                }
-            }
+    ///
+    /// Closes the keep-alive connection if the next request is not received within the duration.
+    /// By default, idle connections are kept open until the client closes them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server
+    ///     .keep_alive_timeout(Duration::from_secs(60))
+    ///     .max_requests_per_connection(1000);
+    /// ```
+    ///
+    pub fn keep_alive_timeout(&mut self, timeout: Duration) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    ///
+    /// Fails reading the request body if the client does not send the next chunk within the
+    /// duration. Unlike the keep-alive timeout, applies while the request is being handled.
+    /// WebSocket and other upgraded connections are not limited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server
+    ///     .body_read_timeout(Duration::from_secs(30))
+    ///     .response_write_timeout(Duration::from_secs(30));
+    /// ```
+    ///
+    pub fn body_read_timeout(&mut self, timeout: Duration) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).body_read_timeout = Some(timeout);
+        self
+    }
+
+    ///
+    /// Closes the connection if the client does not receive the next chunk of the response
+    /// within the duration. Files are sent in parts, so large downloads are not limited by the
+    /// total transfer time.
+    ///
+    pub fn response_write_timeout(&mut self, timeout: Duration) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).response_write_timeout = Some(timeout);
+        self
+    }
+
+    ///
+    /// Sets the heartbeat and idle limits of the WebSocket connections. Used by `WebSocket::from`
+    /// and the `WebSocket` handler argument.
+    ///
+    pub fn websocket_config(&mut self, config: WebSocketConfig) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).websocket = Some(config);
+        self
+    }
+
+    ///
+    /// Closes the connection after serving the number of requests, so the clients reconnect and
+    /// the load is spread again across the instances.
+    ///
+    pub fn max_requests_per_connection(&mut self, max_requests: usize) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).max_requests_per_connection =
+            Some(max_requests);
+        self
+    }
+
+    ///
+    /// Limits the number of connections handled concurrently to keep the memory usage bounded
+    /// under connection floods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::server::{ConnectionLimitBehavior, Server};
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server
+    ///     .max_connections(10000, ConnectionLimitBehavior::Reject)
+    ///     .backlog(1024);
+    /// ```
+    ///
+    pub fn max_connections(
+        &mut self,
+        max_connections: usize,
+        behavior: ConnectionLimitBehavior,
+    ) -> &mut Self {
+        let connection_constraints = Arc::make_mut(&mut self.connection_constraints);
+        connection_constraints.max_connections = Some(max_connections);
+        connection_constraints.connection_limit_behavior = behavior;
+        self
+    }
+
+    ///
+    /// Limits the new and the open connections per client IP address. Connections over the limit
+    /// are closed before the request is read. Not applied to Unix Domain Sockets.
+    ///
+    pub fn connection_rate_limit(&mut self, rate_limit: ConnectionRateLimit) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).rate_limit = Some(rate_limit);
+        self
+    }
+
+    ///
+    /// Waits for the open connections to complete their current request on shutdown before
+    /// `run` returns. Idle keep-alive connections are closed immediately. WebSocket connections
+    /// are closed with the `1001 Going Away` close frame and the server-sent event streams are
+    /// ended, so they can finish within the timeout.
+    ///
+    pub fn drain_timeout(&mut self, timeout: Duration) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).drain_timeout = Some(timeout);
+        self
+    }
+
+    ///
+    /// Size of the pending connections queue for the TCP listeners bound by the server. Has no
+    /// effect on the custom listeners.
+    ///
+    pub fn backlog(&mut self, backlog: u32) -> &mut Self {
+        Arc::make_mut(&mut self.connection_constraints).backlog = Some(backlog);
+        self
+    }
+
+    /// Constraints for parsing request body.
+    pub fn form_constraints(&mut self, form_constraints: FormConstraints) -> &mut Self {
+        self.form_constraints = Arc::from(form_constraints);
+        self
+    }
+
+    /// Pass vec of paths. Can be called multiple times to add more paths.
+    pub fn urls(&mut self, paths: Paths) -> &mut Self {
+        self.paths.extend(paths);
+        self.build_router();
+        self
+    }
+
+    ///
+    /// Adds all the paths of the scope under the prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::Scope;
+    /// use racoon::core::server::Server;
+    ///
+    /// fn api_v1() -> Scope {
+    ///     Scope::new()
+    /// }
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.mount("/api/v1", api_v1());
+    /// ```
+    ///
+    pub fn mount<S: AsRef<str>>(&mut self, prefix: S, scope: Scope) -> &mut Self {
+        let paths = Scope::new().mount(prefix, scope).into_paths();
+        self.urls(paths)
+    }
+
+    ///
+    /// Policy for requests whose path differs from the registered route only by trailing slash.
+    /// Default is `TrailingSlash::Strict`. Scopes and paths can override it.
+    ///
+    pub fn trailing_slash(&mut self, policy: TrailingSlash) -> &mut Self {
+        self.trailing_slash = policy;
+        self.build_router();
+        self
+    }
+
+    ///
+    /// Matches the request path case-insensitively. Useful when migrating from servers like IIS
+    /// where `/About.aspx` and `/about.aspx` are the same resource. Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.case_insensitive_paths(true).normalize_paths(true);
+    /// ```
+    ///
+    pub fn case_insensitive_paths(&mut self, enabled: bool) -> &mut Self {
+        self.case_insensitive_paths = enabled;
+        self.build_router();
+        self
+    }
+
+    ///
+    /// Decodes percent-encoded unreserved characters and removes dot-segments from the request
+    /// path before matching the route. `Request.path` still contains the original path.
+    /// Disabled by default.
+    ///
+    pub fn normalize_paths(&mut self, enabled: bool) -> &mut Self {
+        self.normalize_paths = enabled;
+        self.build_router();
+        self
+    }
+
+    ///
+    /// Serves the fallback view for unmatched `GET` requests under the prefix, while other
+    /// requests still receive `404 Not Found`. See [`SpaFallback`] for details.
+    ///
+    pub fn spa_fallback(&mut self, fallback: SpaFallback) -> &mut Self {
+        self.spa_fallbacks.push(fallback);
+        self.build_router();
+        self
+    }
+
+    ///
+    /// Configures CORS. `OPTIONS` preflight requests for the registered routes are answered
+    /// automatically, so explicit `OPTIONS` views are not required.
+    ///
+    pub fn cors(&mut self, cors: Cors) -> &mut Self {
+        self.cors = Some(cors);
+        self.build_router();
+        self
+    }
+
+    ///
+    /// Redirects requests matching the path to the target. See [`Path::redirect`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::server::Server;
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server
+    ///     .redirect("/old", "/new", 301)
+    ///     .redirect("/blog/{slug}", "/posts/{slug}", 308);
+    /// ```
+    ///
+    pub fn redirect<S: AsRef<str>, T: AsRef<str>>(
+        &mut self,
+        name: S,
+        target: T,
+        status_code: u16,
+    ) -> &mut Self {
+        self.urls(vec![Path::redirect(name, target, status_code)])
+    }
+
+    ///
+    /// Returns the registered routes in the order they are registered. Useful for debugging why
+    /// the route is not matching.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::path::Path;
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HttpResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::core::server::Server;
+    /// use racoon::view;
+    ///
+    /// async fn home(request: Request) -> Response {
+    ///     HttpResponse::ok().body("Home")
+    /// }
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.urls(vec![Path::get("/", view!(home))]);
+    ///
+    /// for route in server.routes() {
+    ///     println!("{:?} {}", route.methods, route.name);
+    /// }
+    /// ```
+    ///
+    pub fn routes(&self) -> Vec<RouteInfo> {
+        self.paths.iter().map(RouteInfo::from).collect()
+    }
+
+    ///
+    /// Logs the table of registered routes when the server starts.
+    ///
+    pub fn log_routes(&mut self, enabled: bool) -> &mut Self {
+        self.log_routes = enabled;
+        self
+    }
+
+    fn build_router(&mut self) {
+        let mut router = Router::from_paths(&self.paths, self.trailing_slash)
+            .case_insensitive(self.case_insensitive_paths)
+            .normalize_paths(self.normalize_paths);
+        for fallback in &self.spa_fallbacks {
+            router = router.spa_fallback(fallback.clone());
+        }
+
+        if let Some(cors) = &self.cors {
+            router = router.cors(cors.clone());
+        }
+        self.router = Arc::new(router);
+    }
+
+    /// Pass middleware view to capture request and response.
+    pub fn wrap(&mut self, middleware: Middleware) -> &mut Self {
+        self.middleware = Some(middleware);
+        self
+    }
+
+    ///
+    /// Registers middleware for all the requests. Middlewares are called in the order they are
+    /// registered and before the middleware passed to `wrap`. See [`AbstractMiddleware`] for
+    /// examples.
+    ///
+    pub fn middleware<M: AbstractMiddleware + 'static>(&mut self, middleware: M) -> &mut Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    ///
+    /// Records request and connection metrics and serves them in Prometheus text format at the
+    /// path. Call it before registering other middlewares to include their time in the request
+    /// duration.
+    ///
+    pub fn metrics<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+        self.middleware(Metrics::new());
+        self.urls(vec![Path::get(path, |request| {
+            Box::pin(metrics::metrics_view(request))
+        })])
+    }
+
+    ///
+    /// Serves the liveness and readiness endpoints. Readiness endpoint reports the server as not
+    /// ready until it starts accepting connections and while it drains connections on shutdown.
+    /// See [`Health`] for examples.
+    ///
+    pub fn health(&mut self, health: Health) -> &mut Self {
+        self.health = Some(health);
+        self
+    }
+
+    ///
+    /// Serves the OpenAPI document generated from the registered routes. See [`OpenApi`] for
+    /// examples.
+    ///
+    pub fn openapi(&mut self, openapi: OpenApi) -> &mut Self {
+        self.openapi = Some(openapi);
+        self
+    }
+
+    ///
+    /// Enables the development mode which restarts the server on source changes, reloads the
+    /// pages on template changes and renders the panics with the backtrace. Ignored in release
+    /// builds. See [`DevMode`] for examples.
+    ///
+    pub fn dev_mode(&mut self, dev_mode: DevMode) -> &mut Self {
+        if !cfg!(debug_assertions) {
+            racoon_warn!("Dev mode is ignored in release builds.");
+            return self;
+        }
+
+        Arc::make_mut(&mut self.connection_constraints).dev_error_pages = true;
+        self.dev_mode = Some(dev_mode);
+        self
+    }
+
+    ///
+    /// Registers custom view for rendering errors generated by the server such as `404 Not Found`
+    /// when no route matches. The view is responsible for setting the same status code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use racoon::core::request::Request;
+    /// use racoon::core::response::{HtmlResponse, JsonResponse, Response};
+    /// use racoon::core::response::status::ResponseStatus;
+    /// use racoon::core::server::Server;
+    /// use racoon::view;
+    ///
+    /// async fn not_found(request: Request) -> Response {
+    ///     if request.path.starts_with("/api/") {
+    ///         return JsonResponse::not_found().body(serde_json::json!({"error": "Not found"}));
+    ///     }
+    ///
+    ///     HtmlResponse::not_found().page("Not Found", "The requested page does not exist.")
+    /// }
+    ///
+    /// let mut server = Server::bind("127.0.0.1:8080");
+    /// server.on_error(404, view!(not_found));
+    /// ```
+    ///
+    pub fn on_error(&mut self, status_code: u32, view: View) -> &mut Self {
+        Arc::make_mut(&mut self.error_handlers).insert(status_code, view);
+        self
+    }
+
+    ///
+    /// Registers async callback run before the listeners are bound, for example to open database
+    /// pools or warm caches. If the callback fails, the server does not start and `run` returns
+    /// the error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use racoon::core::server::Server;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut server = Server::bind("127.0.0.1:8080");
+    ///     server
+    ///         .on_start(|| async {
+    ///             println!("Opening database pool.");
+    ///             Ok(())
+    ///         })
+    ///         .on_ready(|| async {
+    ///             println!("Registering with service discovery.");
+    ///             Ok(())
+    ///         })
+    ///         .on_shutdown(|| async {
+    ///             println!("Closing database pool.");
+    ///             Ok(())
+    ///         });
+    ///
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    ///
+    pub fn on_start<F, Fut>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<()>> + Send + 'static,
+    {
+        self.lifecycle.on_start.push(Lifecycle::hook(hook));
+        self
+    }
+
+    ///
+    /// Registers async callback run after all the listeners are bound. Connections are accepted
+    /// once all the ready callbacks complete. If the callback fails, the listeners are closed and
+    /// `run` returns the error.
+    ///
+    pub fn on_ready<F, Fut>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<()>> + Send + 'static,
+    {
+        self.lifecycle.on_ready.push(Lifecycle::hook(hook));
+        self
+    }
+
+    ///
+    /// Registers async callback run after the server stops and the connections are drained. The
+    /// callbacks also run when the startup fails, so the resources opened by the start callbacks
+    /// are released. Failed callbacks are logged and do not stop the others.
+    ///
+    pub fn on_shutdown<F, Fut>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<()>> + Send + 'static,
+    {
+        self.lifecycle.on_shutdown.push(Lifecycle::hook(hook));
+        self
+    }
+
+    ///
+    /// Runs the job in the background while the server is running. Jobs start after the ready
+    /// callbacks complete and are cancelled before the shutdown callbacks run. See [`Job`] for
+    /// examples.
+    ///
+    pub fn schedule(&mut self, job: Job) -> &mut Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// Runs server in blocking thread.
+    pub async fn run(&mut self) -> std::io::Result<()> {
+        let lifecycle = self.lifecycle.clone();
+
+        let mut result = Lifecycle::run_until_error(&lifecycle.on_start).await;
+        if result.is_ok() {
+            result = self.serve().await;
+        }
+
+        lifecycle.shutdown().await;
+
+        if let Some(dev_mode) = &self.dev_mode {
+            if dev_mode.restart_requested() {
+                return Err(dev_mode.restart());
+            }
+        }
+        result
+    }
+
+    ///
+    /// Middlewares of the server with the built-in ones such as the health probes and the
+    /// OpenAPI documents. Used by both the listeners and the test server, so the requests are
+    /// dispatched through the same chain.
+    ///
+    fn build_middlewares(&self, startup: watch::Receiver<bool>) -> Middlewares {
+        #[allow(unused_mut)]
+        let mut middlewares = self.middlewares.clone();
+
+        // Documents are served after the other middlewares, so they can be protected.
+        if let Some(openapi) = &self.openapi {
+            middlewares.push(Arc::new(openapi.docs(&self.paths)));
+        }
+
+        #[cfg(feature = "http3")]
+        if let Some(http3) = &self.http3 {
+            middlewares.insert(0, Arc::new(http3::AltSvc(http3.alt_svc())));
+        }
+
+        // Challenge requests are answered before the other middlewares can reject them.
+        #[cfg(feature = "acme")]
+        if let Some(http_challenge) = self.acme.as_ref().and_then(|acme| acme.http_challenge()) {
+            middlewares.insert(0, Arc::new(http_challenge));
+        }
+
+        // Probes are answered before the other middlewares can reject them.
+        if let Some(health) = &self.health {
+            let connections = self.connection_constraints.connections.clone();
+            let health = health
+                .clone()
+                .server_ready(move || *startup.borrow() && !connections.is_draining());
+            middlewares.insert(0, Arc::new(health));
+        }
+
+        if let Some(dev_mode) = &self.dev_mode {
+            let dev_mode = dev_mode
+                .clone()
+                .routes(self.routes(), self.error_handlers.contains_key(&404));
+            middlewares.insert(0, Arc::new(dev_mode));
+        }
+        middlewares
+    }
+
+    async fn serve(&mut self) -> std::io::Result<()> {
+        if self.log_routes {
+            racoon_info!("Registered routes:\n{}", RouteTable(&self.routes()));
+        }
+
+        // Listeners start accepting connections after the ready hooks complete.
+        let (startup_sender, startup) = watch::channel(false);
+
+        if let Some(dev_mode) = &self.dev_mode {
+            dev_mode.spawn_watcher(
+                self.connection_constraints.connections.clone(),
+                self.shutdown_lock.clone(),
+            );
+        }
+
+        let next = Next::new(
+            Arc::new(self.build_middlewares(startup.clone())),
+            self.middleware,
+        );
+
+        // Slots are shared, so the limit applies to the connections of all the listeners.
+        if let Some(max_connections) = self.connection_constraints.max_connections {
+            Arc::make_mut(&mut self.connection_constraints).connection_slots =
+                Some(Arc::new(Semaphore::new(max_connections)));
+        }
+
+        #[cfg(feature = "io-uring")]
+        if let Some(workers) = self.uring_workers {
+            Arc::make_mut(&mut self.connection_constraints).uring =
+                Some(Arc::new(UringDriver::start(workers)?));
+        }
+
+        let session_manager: Arc<SessionManager>;
+        if let Some(custom_session_manager) = &self.session_manager {
+            session_manager = custom_session_manager.clone();
+        } else {
+            session_manager = Arc::new(Box::new(FileSessionManager::new().await?));
+        }
+
+        #[cfg(feature = "http3")]
+        let http3_endpoint = match &self.http3 {
+            Some(http3) => Some(http3::bind(http3).await?),
+            None => None,
+        };
+
+        // Listeners are run concurrently and share the routes, middlewares and session manager.
+        let mut listeners = tokio::task::JoinSet::new();
+
+        for listener in std::mem::take(&mut self.listeners) {
+            let context = self.context.clone();
+            let router = self.router.clone();
+            let error_handlers = self.error_handlers.clone();
+            let request_constraints = self.request_constraints.clone();
+            let connection_constraints = self.connection_constraints.clone();
+            let form_constraints = self.form_constraints.clone();
+            let session_manager = session_manager.clone();
+            let shutdown_lock = self.shutdown_lock.clone();
+            let next = next.clone();
+
+            let (tcp_listener, tls_acceptor) = match listener {
+                Listener::Tcp {
+                    address,
+                    tls_acceptor,
+                } => {
+                    let scheme = if tls_acceptor.is_some() {
+                        "https"
+                    } else {
+                        "http"
+                    };
+                    racoon_info!("Server listening at {}://{}", scheme, address);
+
+                    match self.topology {
+                        RuntimeTopology::Shared => {
+                            let listener = self.bind_tcp(&address, false).await?;
+                            (TcpListener::from_std(listener)?, tls_acceptor)
+                        }
+                        RuntimeTopology::AcceptPerCore(cores) => {
+                            for _ in 0..cores.max(1) {
+                                let listener = self.bind_tcp(&address, true).await?;
+                                listeners.spawn(after_startup(
+                                    startup.clone(),
+                                    self.accept_loop(
+                                        listener,
+                                        tls_acceptor.clone(),
+                                        next.clone(),
+                                        session_manager.clone(),
+                                    ),
+                                ));
+                            }
+                            continue;
+                        }
+                        RuntimeTopology::RuntimePerCore(cores) => {
+                            for _ in 0..cores.max(1) {
+                                let listener = self.bind_tcp(&address, true).await?;
+                                let accept_loop = self.accept_loop(
+                                    listener,
+                                    tls_acceptor.clone(),
+                                    next.clone(),
+                                    session_manager.clone(),
+                                );
+
+                                let startup = startup.clone();
+                                listeners.spawn_blocking(move || {
+                                    let runtime = tokio::runtime::Builder::new_current_thread()
+                                        .enable_all()
+                                        .build()?;
+                                    runtime.block_on(after_startup(startup, accept_loop))
+                                });
+                            }
+                            continue;
+                        }
+                    }
+                }
+                Listener::CustomTcp {
+                    listener,
+                    tls_acceptor,
+                } => (listener, tls_acceptor),
+                Listener::Activated(ActivatedListener::Tcp(listener)) => {
+                    racoon_info!(
+                        "Server listening at {:?} (socket activated)",
+                        listener.local_addr()?
+                    );
+                    (TcpListener::from_std(listener)?, None)
+                }
+                Listener::Activated(ActivatedListener::Unix(listener)) => {
+                    racoon_info!("Server listening at unix socket (socket activated)");
+                    listeners.spawn(after_startup(
+                        startup.clone(),
+                        Self::listen_uds(
+                            self.listener_scheme(false),
+                            UnixListener::from_std(listener)?,
+                            context,
+                            router,
+                            self.buffer_size,
+                            next,
+                            error_handlers,
+                            request_constraints,
+                            connection_constraints,
+                            form_constraints,
+                            session_manager,
+                            shutdown_lock,
+                        ),
+                    ));
+                    continue;
+                }
+                Listener::Uds(path) => {
+                    racoon_info!("Server listening at unix:{}", path);
+                    let listener = UnixListener::bind(&path)?;
+
+                    if let Some(mode) = self.sock_permissions {
+                        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+                    }
+
+                    let scheme = self.listener_scheme(false);
+                    let buffer_size = self.buffer_size;
+                    let startup = startup.clone();
+                    listeners.spawn(async move {
+                        let result = after_startup(
+                            startup,
+                            Self::listen_uds(
+                                scheme,
+                                listener,
+                                context,
+                                router,
+                                buffer_size,
+                                next,
+                                error_handlers,
+                                request_constraints,
+                                connection_constraints,
+                                form_constraints,
+                                session_manager,
+                                shutdown_lock,
+                            ),
+                        )
+                        .await;
+
+                        // Stale socket file would prevent other processes from binding to the
+                        // same path.
+                        let _ = std::fs::remove_file(&path);
+                        result
+                    });
+                    continue;
+                }
+                Listener::CustomUnix(listener) => {
+                    listeners.spawn(after_startup(
+                        startup.clone(),
+                        Self::listen_uds(
+                            self.listener_scheme(false),
+                            listener,
+                            context,
+                            router,
+                            self.buffer_size,
+                            next,
+                            error_handlers,
+                            request_constraints,
+                            connection_constraints,
+                            form_constraints,
+                            session_manager,
+                            shutdown_lock,
+                        ),
+                    ));
+                    continue;
+                }
+            };
+
+            // If TLS acceptor is set, listener will receive on HTTPS else HTTP
+            listeners.spawn(after_startup(
+                startup.clone(),
+                Self::listen_port(
+                    self.listener_scheme(tls_acceptor.is_some()),
+                    tcp_listener,
+                    tls_acceptor,
+                    context,
+                    router,
+                    self.buffer_size,
+                    self.socket_options.clone(),
+                    next,
+                    error_handlers,
+                    request_constraints,
+                    connection_constraints,
+                    form_constraints,
+                    session_manager,
+                    shutdown_lock,
+                ),
+            ));
+        }
+
+        if let Err(error) = Lifecycle::run_until_error(&self.lifecycle.on_ready).await {
+            // Listeners return without accepting connections when the sender is dropped.
+            drop(startup_sender);
+            while listeners.join_next().await.is_some() {}
+            return Err(error);
         }
-        self.router = Arc::from(router);
-        self
-    }
+        startup_sender.send_replace(true);
 
-    /// Pass middleware view to capture request and response.
-    pub fn wrap(&mut self, middleware: Middleware) -> &mut Self {
-        self.middleware = Some(middleware);
-        self
-    }
+        for reloader in &self.certificate_reloaders {
+            tokio::spawn(reloader.clone().watch_files());
+        }
 
-    /// Runs server in blocking thread.
-    pub async fn run(&mut self) -> std::io::Result<()> {
-        let session_manager: Arc<SessionManager>;
-        if let Some(custom_session_manager) = &self.session_manager {
-            session_manager = custom_session_manager.clone();
-        } else {
-            session_manager = Arc::new(Box::new(FileSessionManager::new().await?));
+        // Jobs are cancelled when the set is dropped on return.
+        let mut jobs = tokio::task::JoinSet::new();
+        for job in &self.jobs {
+            jobs.spawn(job.clone().run());
         }
 
-        if let Some(bind_address) = &self.bind_address {
-            if self.tls_acceptor.is_some() {
-                log::info!("Server listening at https://{}", bind_address);
-            } else {
-                log::info!("Server listening at at http://{}", bind_address);
-            }
+        #[cfg(feature = "acme")]
+        if let Some(acme) = &self.acme {
+            tokio::spawn(acme.clone().maintain());
+        }
 
-            let mut listener = TcpListener::bind(bind_address).await?;
+        #[cfg(feature = "http3")]
+        if let (Some(http3), Some(endpoint)) = (&self.http3, http3_endpoint) {
+            racoon_info!("HTTP/3 listening at https://{}", http3.address());
 
-            // If TLS acceptor is set, server will receive on HTTPS else HTTP
-            Self::listen_port(
-                &self.scheme,
-                &mut listener,
-                self.tls_acceptor.clone(),
+            tokio::spawn(http3::listen(
+                endpoint,
                 self.context.clone(),
                 self.router.clone(),
-                self.buffer_size.clone(),
-                self.nodelay.clone(),
-                self.middleware,
+                self.buffer_size,
+                next.clone(),
+                self.error_handlers.clone(),
                 self.request_constraints.clone(),
+                self.connection_constraints.clone(),
                 self.form_constraints.clone(),
                 session_manager.clone(),
                 self.shutdown_lock.clone(),
-            )
-            .await?;
+            ));
         }
 
-        if let Some(sock_path) = &self.sock_path {
-            log::info!("Running is server at {}", sock_path);
+        while let Some(result) = listeners.join_next().await {
+            match result {
+                Ok(result) => result?,
+                Err(error) => return Err(std::io::Error::other(error)),
+            }
+        }
 
-            let mut listener = UnixListener::bind(sock_path)?;
+        let connection_constraints = self.connection_constraints.clone();
+        connection_constraints
+            .connections
+            .drain(connection_constraints.drain_timeout)
+            .await;
 
-            Self::listen_uds(
-                &self.scheme,
-                &mut listener,
-                self.context.clone(),
-                self.router.clone(),
-                self.buffer_size.clone(),
-                self.middleware,
-                self.request_constraints.clone(),
-                self.form_constraints.clone(),
-                session_manager.clone(),
-                self.shutdown_lock.clone(),
-            )
-            .await?;
-        }
+        jobs.shutdown().await;
+        Ok(())
+    }
 
-        if let Some(tls_acceptor) = &self.tls_acceptor {
-            let listener = self
-                .custom_tcp_listener
-                .as_mut()
-                .expect("Tcp Listener not set.");
+    ///
+    /// Binds the TCP listener or takes the listener inherited from the parent process.
+    ///
+    async fn bind_tcp(
+        &self,
+        address: &str,
+        reuseport: bool,
+    ) -> std::io::Result<std::net::TcpListener> {
+        let listener = match upgrade::take_inherited(address).await {
+            Some(listener) => {
+                racoon_debug!("Using inherited listener for {}", address);
+                listener
+            }
+            None => {
+                let backlog = self
+                    .connection_constraints
+                    .backlog
+                    .unwrap_or(socket::DEFAULT_BACKLOG);
+                socket::bind(address, backlog, reuseport, &self.socket_options)
+                    .await?
+                    .into_std()?
+            }
+        };
 
-            Self::listen_port(
-                &self.scheme,
-                listener,
-                Some(tls_acceptor.clone()),
-                self.context.clone(),
-                self.router.clone(),
-                self.buffer_size.clone(),
-                self.nodelay.clone(),
-                self.middleware,
-                self.request_constraints.clone(),
-                self.form_constraints.clone(),
-                session_manager.clone(),
-                self.shutdown_lock.clone(),
-            )
-            .await?;
+        if let Ok(mut listener_fds) = self.listener_fds.lock() {
+            listener_fds.push(listener.as_raw_fd());
         }
+        Ok(listener)
+    }
 
-        if let Some(listener) = self.custom_tcp_listener.as_mut() {
-            Self::listen_port(
-                &self.scheme,
-                listener,
-                None,
-                self.context.clone(),
-                self.router.clone(),
-                self.buffer_size.clone(),
-                self.nodelay.clone(),
-                self.middleware,
-                self.request_constraints.clone(),
-                self.form_constraints.clone(),
-                session_manager.clone(),
-                self.shutdown_lock.clone(),
-            )
-            .await?;
+    ///
+    /// Builds multi-threaded Tokio runtime with the configured worker threads and runs the server
+    /// on it. Must not be called from the async context.
+    ///
+    pub fn run_blocking(&mut self) -> std::io::Result<()> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
         }
 
-        if let Some(listener) = self.custom_unix_listener.as_mut() {
-            Self::listen_uds(
-                &self.scheme,
-                listener,
-                self.context.clone(),
-                self.router.clone(),
-                self.buffer_size.clone(),
-                self.middleware,
-                self.request_constraints.clone(),
-                self.form_constraints.clone(),
-                session_manager.clone(),
-                self.shutdown_lock.clone(),
+        let runtime = builder.enable_all().build()?;
+        runtime.block_on(self.run())
+    }
+
+    ///
+    /// Accept loop for the TCP listener bound by the server. Listener is registered to the
+    /// runtime which polls the returned future.
+    ///
+    fn accept_loop(
+        &self,
+        listener: std::net::TcpListener,
+        tls_acceptor: Option<TlsAcceptor>,
+        next: Next,
+        session_manager: Arc<SessionManager>,
+    ) -> impl Future<Output = std::io::Result<()>> + Send + 'static {
+        let scheme = self.listener_scheme(tls_acceptor.is_some());
+        let context = self.context.clone();
+        let router = self.router.clone();
+        let buffer_size = self.buffer_size;
+        let socket_options = self.socket_options.clone();
+        let error_handlers = self.error_handlers.clone();
+        let request_constraints = self.request_constraints.clone();
+        let connection_constraints = self.connection_constraints.clone();
+        let form_constraints = self.form_constraints.clone();
+        let shutdown_lock = self.shutdown_lock.clone();
+
+        async move {
+            let result = Self::listen_port(
+                scheme,
+                TcpListener::from_std(listener)?,
+                tls_acceptor,
+                context,
+                router,
+                buffer_size,
+                socket_options,
+                next,
+                error_handlers,
+                request_constraints,
+                connection_constraints.clone(),
+                form_constraints,
+                session_manager,
+                shutdown_lock,
             )
-            .await?;
+            .await;
+
+            // Connections are handled on the runtime polling this loop, so they are drained
+            // before the runtime is dropped.
+            connection_constraints
+                .connections
+                .drain(connection_constraints.drain_timeout)
+                .await;
+            result
         }
+    }
 
-        Ok(())
+    ///
+    /// Scheme of the requests received by the listener unless it is forced with `set_scheme`.
+    ///
+    fn listener_scheme(&self, is_tls: bool) -> String {
+        match &self.scheme {
+            Some(scheme) => scheme.clone(),
+            None if is_tls => "https".to_string(),
+            None => "http".to_string(),
+        }
     }
 
     async fn wait_shutdown(shutdown_lock: ShutdownLock) {
@@ -395,7 +1812,7 @@ impl Server {
                     drop(condvar.wait(lock));
                 }
                 Err(error) => {
-                    log::error!("{}", error);
+                    racoon_error!("{}", error);
                 }
             };
         })
@@ -403,15 +1820,17 @@ impl Server {
     }
 
     async fn listen_port(
-        scheme: &String,
-        listener: &mut TcpListener,
+        scheme: String,
+        listener: TcpListener,
         tls_acceptor: Option<TlsAcceptor>,
         context: Arc<Context>,
-        router: Arc<Router<Path>>,
+        router: Arc<Router>,
         buffer_size: usize,
-        nodelay: Arc<AtomicBool>,
-        middleware: Option<Middleware>,
+        socket_options: Arc<SocketOptions>,
+        next: Next,
+        error_handlers: Arc<ErrorHandlers>,
         request_constraints: Arc<RequestConstraints>,
+        connection_constraints: Arc<ConnectionConstraints>,
         form_constraints: Arc<FormConstraints>,
         session_manager: Arc<SessionManager>,
         shutdown_lock: ShutdownLock,
@@ -419,6 +1838,7 @@ impl Server {
         loop {
             let router = router.clone();
             let context = context.clone();
+            let error_handlers = error_handlers.clone();
             let tls_acceptor = tls_acceptor.clone();
 
             let accept_result;
@@ -433,24 +1853,54 @@ impl Server {
                 }
             }
 
-            let tcp_stream = match accept_result {
-                Ok((tcp_stream, _)) => tcp_stream,
+            let (tcp_stream, peer_addr) = match accept_result {
+                Ok((tcp_stream, peer_addr)) => (tcp_stream, peer_addr),
                 Err(error) => {
-                    log::error!("Failed to accept connection. Error: {:?}", error);
+                    racoon_error!("Failed to accept connection. Error: {:?}", error);
+                    continue;
+                }
+            };
+
+            let rate_limit_guard = match &connection_constraints.rate_limit {
+                Some(rate_limit) => match rate_limit.acquire(peer_addr.ip()) {
+                    Some(rate_limit_guard) => Some(rate_limit_guard),
+                    None => {
+                        racoon_debug!("Connection rate limit exceeded by {}", peer_addr.ip());
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            let connection_slot = match connection_constraints.acquire_slot().await {
+                Some(connection_slot) => connection_slot,
+                None => {
+                    racoon_warn!("Connection limit reached. Rejecting {}", peer_addr);
+
+                    // Plain text response can't be read by the TLS client.
+                    if tls_acceptor.is_none() {
+                        tokio::spawn(reject_connection(tcp_stream));
+                    }
                     continue;
                 }
             };
 
-            if nodelay.load(Ordering::Relaxed) {
-                let _ = tcp_stream.set_nodelay(true);
+            if let Err(error) = socket_options.apply(&tcp_stream) {
+                racoon_warn!("Failed to set socket options. Error: {:?}", error);
             }
 
             let request_constraints = request_constraints.clone();
+            let connection_constraints = connection_constraints.clone();
             let form_constraints = form_constraints.clone();
+            let next = next.clone();
             let scheme = scheme.clone();
             let session_type = session_manager.clone();
 
-            let _ = tokio::spawn(async move {
+            let connection_span = telemetry::connection_span(peer_addr);
+            let connection = async move {
+                let _connection_slot = connection_slot;
+                let _rate_limit_guard = rate_limit_guard;
+
                 if let Some(tls_acceptor) = tls_acceptor.clone() {
                     // With TLS
                     match TlsTcpStreamWrapper::from(tcp_stream, &tls_acceptor, buffer_size.clone())
@@ -463,8 +1913,10 @@ impl Server {
                                 scheme.clone(),
                                 context,
                                 router,
-                                middleware,
+                                next,
+                                error_handlers,
                                 request_constraints,
+                                connection_constraints,
                                 form_constraints,
                                 session_type,
                             )
@@ -477,17 +1929,17 @@ impl Server {
                     }
                 } else {
                     // Without TLS
-                    match TcpStreamWrapper::from(tcp_stream, buffer_size.clone()) {
-                        Ok(tcp_stream_wrapper) => {
-                            let stream = Box::new(tcp_stream_wrapper);
-
+                    match Self::plain_stream(tcp_stream, buffer_size, &connection_constraints) {
+                        Ok(stream) => {
                             Self::handle_stream(
                                 stream,
                                 scheme,
                                 context,
                                 router,
-                                middleware,
+                                next,
+                                error_handlers,
                                 request_constraints,
+                                connection_constraints,
                                 form_constraints,
                                 session_type,
                             )
@@ -495,22 +1947,25 @@ impl Server {
                         }
 
                         Err(error) => {
-                            log::error!("Failed to handle accepted connection: Error: {}", error);
+                            racoon_error!("Failed to handle accepted connection: Error: {}", error);
                         }
                     }
                 }
-            });
+            };
+            tokio::spawn(telemetry::in_span(connection_span, connection));
         }
     }
 
     async fn listen_uds(
-        scheme: &String,
-        listener: &mut UnixListener,
+        scheme: String,
+        listener: UnixListener,
         context: Arc<Context>,
-        router: Arc<Router<Path>>,
+        router: Arc<Router>,
         buffer_size: usize,
-        middleware: Option<Middleware>,
+        next: Next,
+        error_handlers: Arc<ErrorHandlers>,
         request_constraints: Arc<RequestConstraints>,
+        connection_constraints: Arc<ConnectionConstraints>,
         form_constraints: Arc<FormConstraints>,
         session_type: Arc<SessionManager>,
         shutdown_lock: ShutdownLock,
@@ -518,6 +1973,7 @@ impl Server {
         loop {
             let router = router.clone();
             let context = context.clone();
+            let error_handlers = error_handlers.clone();
 
             let accept_result;
             tokio::select! {
@@ -534,17 +1990,31 @@ impl Server {
             let unix_stream = match accept_result {
                 Ok((unix_stream, _)) => unix_stream,
                 Err(error) => {
-                    log::error!("Failed to accept connection. Error: {:?}", error);
+                    racoon_error!("Failed to accept connection. Error: {:?}", error);
+                    continue;
+                }
+            };
+
+            let connection_slot = match connection_constraints.acquire_slot().await {
+                Some(connection_slot) => connection_slot,
+                None => {
+                    racoon_warn!("Connection limit reached. Rejecting unix connection.");
+                    tokio::spawn(reject_connection(unix_stream));
                     continue;
                 }
             };
 
             let request_constraints = request_constraints.clone();
+            let connection_constraints = connection_constraints.clone();
             let form_constraints = form_constraints.clone();
+            let next = next.clone();
             let scheme = scheme.clone();
             let session_type = session_type.clone();
 
-            let _ = tokio::spawn(async move {
+            let connection_span = telemetry::connection_span("unix");
+            let connection = async move {
+                let _connection_slot = connection_slot;
+
                 match UnixStreamWrapper::from(unix_stream, buffer_size.clone()) {
                     Ok(unix_stream_wrapper) => {
                         let stream = Box::new(unix_stream_wrapper);
@@ -554,8 +2024,10 @@ impl Server {
                             scheme,
                             context,
                             router,
-                            middleware,
+                            next,
+                            error_handlers,
                             request_constraints,
+                            connection_constraints,
                             form_constraints,
                             session_type,
                         )
@@ -563,47 +2035,163 @@ impl Server {
                     }
 
                     Err(error) => {
-                        log::error!("Failed to handle accepted connection: Error: {}", error);
+                        racoon_error!("Failed to handle accepted connection: Error: {}", error);
                     }
                 }
-            });
+            };
+            tokio::spawn(telemetry::in_span(connection_span, connection));
+        }
+    }
+
+    ///
+    /// Wraps the plain TCP connection in the stream using io_uring if enabled.
+    ///
+    #[cfg_attr(not(feature = "io-uring"), allow(unused_variables))]
+    fn plain_stream(
+        tcp_stream: tokio::net::TcpStream,
+        buffer_size: usize,
+        connection_constraints: &ConnectionConstraints,
+    ) -> std::io::Result<Stream> {
+        #[cfg(feature = "io-uring")]
+        if let Some(uring) = &connection_constraints.uring {
+            return Ok(Box::new(uring.register(tcp_stream, buffer_size)?));
         }
+
+        Ok(Box::new(TcpStreamWrapper::from(tcp_stream, buffer_size)?))
     }
 
     async fn handle_stream(
         stream: Stream,
         scheme: String,
         context: Arc<Context>,
-        router: Arc<Router<Path>>,
-        middleware: Option<Middleware>,
+        router: Arc<Router>,
+        next: Next,
+        error_handlers: Arc<ErrorHandlers>,
         request_constraints: Arc<RequestConstraints>,
+        connection_constraints: Arc<ConnectionConstraints>,
         form_constraints: Arc<FormConstraints>,
         session_type: Arc<SessionManager>,
     ) {
+        // Body read timeout is not applied while waiting for the next request.
+        let waiting_request = Arc::new(AtomicBool::new(true));
+        let stream: Stream = if connection_constraints.body_read_timeout.is_some()
+            || connection_constraints.response_write_timeout.is_some()
+        {
+            Box::new(TimeoutStreamWrapper::new(
+                stream,
+                connection_constraints.body_read_timeout,
+                connection_constraints.response_write_timeout,
+                waiting_request.clone(),
+            ))
+        } else {
+            stream
+        };
+
+        let byte_counters = Arc::new(ByteCounters::default());
+        let stream: Stream = Box::new(CountingStreamWrapper::new(stream, byte_counters.clone()));
+
         let stream = Arc::new(stream);
+        let _connection = metrics::ConnectionGuard::new();
+        let mut draining = connection_constraints.connections.track();
+        let mut served_requests: usize = 0;
 
         loop {
-            let request_result =
-                match read_request_headers(stream.clone(), request_constraints.clone()).await {
-                    Ok(result) => result,
-                    Err(error) => {
-                        racoon_debug!("Failed to parse request. Error: {:?}", error);
+            waiting_request.store(true, Ordering::Relaxed);
+            let transfer = TransferSize::start(byte_counters.clone());
+            let read_request = telemetry::in_span(
+                telemetry::parse_span(),
+                read_request_headers(stream.clone(), request_constraints.clone()),
+            );
+
+            // Idle connection waiting for the next request is closed after the keep-alive timeout
+            // or when the server starts draining.
+            let read_result = if served_requests > 0 {
+                let timeout = connection_constraints
+                    .keep_alive_timeout
+                    .unwrap_or(Duration::MAX);
+
+                tokio::select! {
+                    result = tokio::time::timeout(timeout, read_request) => match result {
+                        Ok(read_result) => read_result,
+                        Err(_) => {
+                            racoon_debug!("Keep-alive connection is idle for {:?}.", timeout);
+                            let _ = stream.shutdown().await;
+                            break;
+                        }
+                    },
+
+                    _ = ConnectionTracker::wait_draining(&mut draining) => {
+                        racoon_debug!("Closing idle connection for shutdown.");
+                        let _ = stream.shutdown().await;
+                        break;
+                    }
+                }
+            } else if let Some(timeout) = request_constraints.header_read_timeout {
+                // Connection which does not send the first request in time is not kept open.
+                match tokio::time::timeout(timeout, read_request).await {
+                    Ok(read_result) => read_result,
+                    Err(_) => Err(RequestError::HeaderReadTimeout),
+                }
+            } else {
+                read_request.await
+            };
 
-                        match error {
-                            RequestError::HeaderSizeExceed => {
-                                let mut bad_request: Box<dyn AbstractResponse> =
-                                    HttpResponse::request_header_fields_too_large()
-                                        .body("Request header too large.");
+            let request_result = match read_result {
+                Ok(result) => result,
+                Err(error) => {
+                    #[cfg(feature = "http2")]
+                    if matches!(error, RequestError::Http2Preface)
+                        && connection_constraints.h2c
+                        && served_requests == 0
+                    {
+                        http2::serve(
+                            stream.clone(),
+                            scheme.clone(),
+                            context.clone(),
+                            router.clone(),
+                            next.clone(),
+                            error_handlers.clone(),
+                            request_constraints.clone(),
+                            connection_constraints.clone(),
+                            form_constraints.clone(),
+                            session_type.clone(),
+                        )
+                        .await;
+                        break;
+                    }
 
-                                let response_bytes = response::response_to_bytes(&mut bad_request);
-                                let _ = stream.write_chunk(&response_bytes).await;
-                                let _ = stream.shutdown().await;
-                            }
-                            _ => {}
+                    match error {
+                        // Closed or broken connections are common and not actionable.
+                        RequestError::Http2Preface | RequestError::Others(_) => {
+                            racoon_debug!("Failed to parse request. Error: {:?}", error);
                         }
-                        break;
+                        _ => racoon_info!("Rejected request. Error: {:?}", error),
                     }
-                };
+
+                    let error_response = match error {
+                        RequestError::HeaderSizeExceed => Some(
+                            HttpResponse::request_header_fields_too_large()
+                                .body("Request header too large."),
+                        ),
+                        RequestError::HeaderReadTimeout => {
+                            Some(HttpResponse::request_timeout().body("408 Request Timeout"))
+                        }
+                        RequestError::Http2Preface | RequestError::Others(_) => None,
+                    };
+
+                    if let Some(error_response) = error_response {
+                        let mut error_response: Box<dyn AbstractResponse> = error_response;
+                        error_response.get_headers().set("Connection", "close");
+
+                        let response_bytes = response::response_to_bytes(&mut error_response);
+                        let _ = stream.write_chunk(&response_bytes).await;
+                        let _ = stream.shutdown().await;
+                    }
+                    break;
+                }
+            };
+
+            waiting_request.store(false, Ordering::Relaxed);
 
             let request_method;
             if let Some(method) = request_result.method {
@@ -635,21 +2223,127 @@ impl Server {
                 break;
             }
 
-            let route = router.clone();
-            let matched_route = match route.at(&path) {
-                Ok(matched) => Some(matched),
-                Err(_) => None,
-            };
+            #[cfg(feature = "http2")]
+            if connection_constraints.h2c {
+                let headers_frame = http2::upgrade_frame(
+                    &request_method,
+                    &raw_path,
+                    http_version,
+                    &request_result.headers,
+                );
+
+                if let Some(headers_frame) = headers_frame {
+                    ProtocolSwitch::new(waiting_request.clone()).switched();
+                    http2::serve_upgraded(
+                        stream.clone(),
+                        headers_frame,
+                        scheme.clone(),
+                        context.clone(),
+                        router.clone(),
+                        next.clone(),
+                        error_handlers.clone(),
+                        request_constraints.clone(),
+                        connection_constraints.clone(),
+                        form_constraints.clone(),
+                        session_type.clone(),
+                    )
+                    .await;
+                    break;
+                }
+            }
 
             let mut params = PathParams::new();
-            let view;
-            if let Some(route) = matched_route {
-                view = Some(route.value.view);
-                route.params.iter().for_each(|(key, value)| {
-                    params.insert(key, value);
-                });
-            } else {
-                view = None;
+            let mut route_chain = None;
+            let mut route_timeout = None;
+            let mut route_max_body_size = None;
+            let mut matched_path = None;
+            let mut route_metadata = None;
+            let mut view;
+
+            let extra_headers = Arc::new(Mutex::new(Headers::new()));
+
+            let host = request_result.headers.value("host");
+            let route_span = telemetry::route_span(&request_method, &path);
+            let route_result = telemetry::in_scope(&route_span, || {
+                router.route(
+                    &request_method,
+                    host.as_deref(),
+                    &path,
+                    &request_result.headers,
+                )
+            });
+
+            match route_result {
+                RouteResult::Found {
+                    path: route,
+                    params: route_params,
+                } => {
+                    view = Some(route.view);
+                    params = route_params;
+                    route_timeout = route.timeout_duration();
+                    route_max_body_size = route.body_size_limit();
+                    matched_path = Some(MatchedPath(route.name.clone()));
+                    route_metadata = Some(route.route_metadata().clone());
+
+                    if !route.middlewares().is_empty() || !route.guards().is_empty() {
+                        route_chain = Some((route.middlewares().clone(), route.guards().clone()));
+                    }
+                }
+                RouteResult::MethodNotAllowed(allowed_methods) => {
+                    extra_headers
+                        .lock()
+                        .await
+                        .set("Allow", allowed_methods.join(", "));
+
+                    let default_view: View = |_| {
+                        Box::pin(async move {
+                            let response: Box<dyn AbstractResponse> =
+                                HttpResponse::method_not_allowed().body("405 Method Not Allowed");
+                            response
+                        })
+                    };
+                    view = Some(*error_handlers.get(&405).unwrap_or(&default_view));
+
+                    // Route middlewares such as CORS can still answer requests like preflight.
+                    if let Some(route) = router.matched_path(host.as_deref(), &path) {
+                        if !route.middlewares().is_empty() {
+                            route_chain = Some((route.middlewares().clone(), Arc::default()));
+                        }
+                    }
+                }
+                RouteResult::Redirect {
+                    status_code,
+                    mut location,
+                } => {
+                    // Keeps the query string while redirecting.
+                    if let Some((_, query)) = raw_path.split_once('?') {
+                        let separator = if location.contains('?') { '&' } else { '?' };
+                        location = format!("{}{}{}", location, separator, query);
+                    }
+                    extra_headers.lock().await.set("Location", location);
+                    view = Some(redirect_view(status_code));
+                }
+                RouteResult::Preflight(preflight_headers) => {
+                    let mut extra_headers = extra_headers.lock().await;
+                    for (name, values) in preflight_headers {
+                        for value in values {
+                            extra_headers.set_multiple(&name, value);
+                        }
+                    }
+
+                    let preflight_view: View = |_| {
+                        Box::pin(async move {
+                            let response: Box<dyn AbstractResponse> =
+                                HttpResponse::no_content().empty();
+                            response
+                        })
+                    };
+                    view = Some(preflight_view);
+                }
+                RouteResult::NotFound => {
+                    // Custom 404 handler is used as view if registered.
+                    view = error_handlers.get(&404).copied();
+                }
             }
 
             let mut is_keep_alive;
@@ -667,6 +2361,18 @@ impl Server {
                 is_keep_alive = value.to_lowercase() == "keep-alive";
             }
 
+            served_requests += 1;
+            if let Some(max_requests) = connection_constraints.max_requests_per_connection {
+                if served_requests >= max_requests {
+                    racoon_debug!("Connection served {} requests.", served_requests);
+                    is_keep_alive = false;
+                }
+            }
+
+            if *draining.borrow() {
+                is_keep_alive = false;
+            }
+
             // Shutdowns next request on the current connection, if the request body is not read
             // completely.
 
@@ -678,14 +2384,44 @@ impl Server {
                 };
             }
 
+            let is_head_request = request_method == "HEAD";
+
+            // Route can override the maximum body size. Larger body is rejected before reading.
+            let mut request_form_constraints = form_constraints.clone();
+            if let Some(max_body_size) = route_max_body_size {
+                request_form_constraints =
+                    Arc::new(form_constraints.with_max_body_size(max_body_size));
+
+                let content_length = request_result
+                    .headers
+                    .value("content-length")
+                    .and_then(|value| value.parse::<usize>().ok());
+
+                if content_length.is_some_and(|content_length| content_length > max_body_size) {
+                    racoon_debug!(
+                        "Request body exceeds the route limit of {} bytes.",
+                        max_body_size
+                    );
+
+                    let default_view: View = |_| {
+                        Box::pin(async move {
+                            let response: Box<dyn AbstractResponse> =
+                                HttpResponse::payload_too_large().body("413 Payload Too Large");
+                            response
+                        })
+                    };
+                    view = Some(*error_handlers.get(&413).unwrap_or(&default_view));
+                    route_chain = None;
+                    route_timeout = None;
+                }
+            }
+
             let body_read = Arc::new(AtomicBool::from(true));
             if request_result.headers.value("content-length").is_some() {
                 body_read.store(false, Ordering::Relaxed);
             }
 
-            let extra_headers = Arc::new(Mutex::new(Headers::new()));
-
-            let request = Request::from(
+            let mut request = Request::from(
                 stream.clone(),
                 context.clone(),
                 scheme.clone(),
@@ -697,24 +2433,125 @@ impl Server {
                 query_params,
                 session_type.clone(),
                 body_read.clone(),
-                form_constraints.clone(),
+                request_form_constraints,
                 extra_headers.clone(),
             )
             .await;
 
+            // Route middlewares and guards are called through the chain before the route view.
+            if let Some((route_middlewares, route_guards)) = route_chain {
+                request.middleware_chain =
+                    MiddlewareChain::new(route_middlewares, route_guards, view);
+                view = Some(middleware::next_view);
+            }
+
+            request.state = connection_constraints.state.clone();
+
+            if let Some(matched_path) = matched_path {
+                request.extensions.insert(matched_path);
+            }
+
+            if let Some(route_metadata) = route_metadata {
+                request.extensions.insert(route_metadata);
+            }
+
+            if let Some(websocket_config) = &connection_constraints.websocket {
+                request.extensions.insert(websocket_config.clone());
+            }
+
+            request
+                .extensions
+                .insert(ShutdownSignal(connection_constraints.connections.clone()));
+            request.extensions.insert(transfer.clone());
+            request
+                .extensions
+                .insert(ProtocolSwitch::new(waiting_request.clone()));
+
+            let request_span = telemetry::request_span(&mut request);
+
+            // Request is kept for rendering timeout or panic error since the original is moved to
+            // the handler.
+            let fallback_request = (route_timeout.is_some() || error_handlers.contains_key(&500))
+                .then(|| request.clone());
+            let request_line = connection_constraints
+                .dev_error_pages
+                .then(|| format!("{} {}", request.method, request.path));
+
+            // Panics in the handler are converted to 500 response instead of dropping the
+            // connection without response.
+            let handle_request = async {
+                match recovery::catch_unwind(telemetry::in_span(
+                    request_span.clone(),
+                    next.clone().with_view(view).run(request),
+                ))
+                .await
+                {
+                    Ok(response) => response,
+                    Err(panic) => {
+                        racoon_error!(
+                            "Request handler panicked at {}: {}\n{}",
+                            panic.location.as_deref().unwrap_or("unknown location"),
+                            panic.message,
+                            panic.backtrace.as_deref().unwrap_or_default()
+                        );
+
+                        let mut response: Box<dyn AbstractResponse> =
+                            match (error_handlers.get(&500), fallback_request.clone()) {
+                                _ if request_line.is_some() => dev::panic_page(
+                                    &panic,
+                                    request_line.as_deref().unwrap_or_default(),
+                                ),
+                                (Some(panic_view), Some(panic_request)) => {
+                                    Path::resolve(panic_request, Some(*panic_view)).await
+                                }
+                                _ => HttpResponse::internal_server_error()
+                                    .body("500 Internal Server Error"),
+                            };
+                        // State of the request body is unknown after the panic.
+                        response.get_headers().set("Connection", "close");
+                        response
+                    }
+                }
+            };
+
             let mut response;
-            if let Some(middleware) = middleware {
-                racoon_debug!("Middleware found. Passing request to middleware.");
-                response = middleware(request, view).await;
-            } else {
-                response = Path::resolve(request, view).await;
+            match (route_timeout, fallback_request.clone()) {
+                (Some(duration), Some(timeout_request)) => {
+                    match tokio::time::timeout(duration, handle_request).await {
+                        Ok(handler_response) => response = handler_response,
+                        Err(_) => {
+                            racoon_debug!("Request handler timed out after {:?}.", duration);
+                            is_keep_alive = false;
+
+                            let default_view: View = |_| {
+                                Box::pin(async move {
+                                    let response: Box<dyn AbstractResponse> =
+                                        HttpResponse::gateway_timeout().body("504 Gateway Timeout");
+                                    response
+                                })
+                            };
+                            let timeout_view = *error_handlers.get(&504).unwrap_or(&default_view);
+                            response = Path::resolve(timeout_request, Some(timeout_view)).await;
+                        }
+                    }
+                }
+                _ => response = handle_request.await,
             }
+            telemetry::record_status(&request_span, response.status().0);
 
             if !body_read.load(Ordering::Relaxed) {
                 racoon_debug!("Request body is not parsed completely. So keep-alive is disabled.");
                 is_keep_alive = false;
             }
 
+            // Response can also ask to close connection by setting header manually.
+            if let Some(connection) = response.get_headers().value("Connection") {
+                if connection.to_lowercase() == "close" {
+                    racoon_debug!("Response requested to close connection.");
+                    is_keep_alive = false;
+                }
+            }
+
             // Serves bytes to client
             if response.serve_default() {
                 if response.should_close() || !is_keep_alive {
@@ -722,15 +2559,27 @@ impl Server {
                     headers.set("Connection", "close");
                 }
 
-                let response_bytes = response::response_to_bytes(&mut response);
+                // Response to HEAD request has the same headers as GET but without the body.
+                let response_bytes = if is_head_request {
+                    let body_length = response.get_body().len();
+                    let headers = response.get_headers();
+                    if headers.value("Content-Length").is_none() {
+                        headers.set("Content-Length", body_length.to_string());
+                    }
+                    response::response_head_to_bytes(&mut response)
+                } else {
+                    response::response_to_bytes(&mut response)
+                };
                 match stream.write_chunk(response_bytes.as_slice()).await {
                     Ok(()) => {}
                     Err(error) => {
                         racoon_debug!("Failed to write response: Error: {}", error);
+                        transfer.complete();
                         break;
                     }
                 }
             }
+            transfer.complete();
 
             // Close connection if response explicitly specifies to close or HTTP client does not support
             // keep alive connection.
@@ -745,4 +2594,373 @@ impl Server {
     pub fn shutdown_lock(&self) -> ShutdownLock {
         self.shutdown_lock.clone()
     }
+
+    pub(crate) fn custom_session_manager(&self) -> Option<Arc<SessionManager>> {
+        self.session_manager.clone()
+    }
+
+    ///
+    /// Handles the connection with the routes, middlewares and constraints of the server without
+    /// binding the listeners. Used by the in-process test server.
+    ///
+    pub(crate) async fn serve_connection(
+        &self,
+        stream: Stream,
+        session_manager: Arc<SessionManager>,
+    ) {
+        // Test server is ready as soon as it is created.
+        let (_, startup) = watch::channel(true);
+        let next = Next::new(Arc::new(self.build_middlewares(startup)), self.middleware);
+
+        Self::handle_stream(
+            stream,
+            self.listener_scheme(false),
+            self.context.clone(),
+            self.router.clone(),
+            next,
+            self.error_handlers.clone(),
+            self.request_constraints.clone(),
+            self.connection_constraints.clone(),
+            self.form_constraints.clone(),
+            session_manager,
+        )
+        .await;
+    }
+
+    ///
+    /// Handle for passing the listeners of the running server to the new process.
+    ///
+    pub fn upgrade_handle(&self) -> UpgradeHandle {
+        UpgradeHandle::new(self.listener_fds.clone(), self.shutdown_lock.clone())
+    }
+}
+
+///
+/// View responding with the redirect status. Location header is set separately.
+///
+fn redirect_view(status_code: u16) -> View {
+    match status_code {
+        302 => |_| {
+            Box::pin(async move {
+                let response: Box<dyn AbstractResponse> = HttpResponse::found().empty();
+                response
+            })
+        },
+        303 => |_| {
+            Box::pin(async move {
+                let response: Box<dyn AbstractResponse> = HttpResponse::see_other().empty();
+                response
+            })
+        },
+        307 => |_| {
+            Box::pin(async move {
+                let response: Box<dyn AbstractResponse> =
+                    HttpResponse::temporary_redirect().empty();
+                response
+            })
+        },
+        308 => |_| {
+            Box::pin(async move {
+                let response: Box<dyn AbstractResponse> =
+                    HttpResponse::permanent_redirect().empty();
+                response
+            })
+        },
+        _ => |_| {
+            Box::pin(async move {
+                let response: Box<dyn AbstractResponse> = HttpResponse::moved_permanently().empty();
+                response
+            })
+        },
+    }
+}
+
+///
+/// Responds `503 Service Unavailable` to the connection accepted over the limit and closes it.
+///
+async fn reject_connection<S: AsyncWrite + Unpin>(mut stream: S) {
+    let mut response: Box<dyn AbstractResponse> =
+        HttpResponse::service_unavailable().body("503 Service Unavailable");
+    response.get_headers().set("Connection", "close");
+
+    let response_bytes = response::response_to_bytes(&mut response);
+    let _ = stream.write_all(&response_bytes).await;
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::headers::HeaderValue;
+    use crate::core::path::{Path, Scope, View};
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{HttpResponse, Response};
+
+    use super::Server;
+
+    ///
+    /// Runs the server configured by the closure on a random port and returns its address. The
+    /// server has its own runtime in a separate thread and runs until the tests exit.
+    ///
+    pub fn serve<F: FnOnce(&mut Server) + Send + 'static>(configure: F) -> SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            runtime.block_on(async move {
+                let listener = TcpListener::from_std(listener).unwrap();
+                let mut server = Server::from_tcp_listener(listener);
+                configure(&mut server);
+                server.run().await
+            })
+        });
+        address
+    }
+
+    ///
+    /// Writes the raw request and returns the response read until the connection is closed.
+    ///
+    pub async fn send(address: SocketAddr, request: &str) -> String {
+        let mut client = TcpStream::connect(address).await.unwrap();
+        client.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = vec![];
+        tokio::time::timeout(Duration::from_secs(3), client.read_to_end(&mut response))
+            .await
+            .expect("Connection is not closed.")
+            .unwrap();
+        String::from_utf8(response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_error_handlers() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let response: Response = HttpResponse::ok().body("OK");
+                response
+            })
+        };
+        let panic_view: View = |_| Box::pin(async move { panic!("Handler failed.") });
+
+        let address = serve(move |server| {
+            server
+                .urls(vec![
+                    Path::get("/users", view),
+                    Path::post("/upload", view).max_body_size(4),
+                    Path::get("/panic", panic_view),
+                ])
+                .on_error(404, |_| {
+                    Box::pin(async move {
+                        let response: Response = HttpResponse::not_found().body("Custom 404");
+                        response
+                    })
+                })
+                .on_error(405, |_| {
+                    Box::pin(async move {
+                        let response: Response =
+                            HttpResponse::method_not_allowed().body("Custom 405");
+                        response
+                    })
+                })
+                .on_error(413, |_| {
+                    Box::pin(async move {
+                        let response: Response =
+                            HttpResponse::payload_too_large().body("Custom 413");
+                        response
+                    })
+                })
+                .on_error(500, |_| {
+                    Box::pin(async move {
+                        let response: Response =
+                            HttpResponse::internal_server_error().body("Custom 500");
+                        response
+                    })
+                });
+        });
+
+        let requests = [
+            (404, "GET /missing HTTP/1.1\r\nConnection: close\r\n\r\n"),
+            (
+                405,
+                "POST /users HTTP/1.1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ),
+            (
+                413,
+                "POST /upload HTTP/1.1\r\nContent-Length: 9\r\nConnection: close\r\n\r\nToo large",
+            ),
+            (500, "GET /panic HTTP/1.1\r\nConnection: close\r\n\r\n"),
+        ];
+        for (status_code, request) in requests {
+            let response = send(address, request).await;
+            assert!(response.starts_with(&format!("HTTP/1.1 {} ", status_code)));
+            assert!(response.ends_with(&format!("\r\n\r\nCustom {}", status_code)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_timeout() {
+        let slow: View = |_| {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                let response: Response = HttpResponse::ok().body("Done");
+                response
+            })
+        };
+
+        let paths = move || {
+            vec![
+                Path::get("/slow", slow).timeout(Duration::from_millis(100)),
+                Path::get("/fast", |_| {
+                    Box::pin(async move {
+                        let response: Response = HttpResponse::ok().body("Done");
+                        response
+                    })
+                })
+                .timeout(Duration::from_secs(10)),
+            ]
+        };
+
+        // Connection is closed after the response, since the handler is abandoned.
+        let address = serve(move |server| {
+            server.urls(paths());
+        });
+        let response = send(address, "GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 504 Gateway Timeout\r\n"));
+        assert!(response.to_lowercase().contains("connection: close\r\n"));
+
+        let address = serve(move |server| {
+            server.urls(paths()).on_error(504, |_| {
+                Box::pin(async move {
+                    let response: Response =
+                        HttpResponse::gateway_timeout().body("Try again later");
+                    response
+                })
+            });
+        });
+
+        let response = send(address, "GET /slow HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 504 "));
+        assert!(response.ends_with("\r\n\r\nTry again later"));
+
+        let response = send(address, "GET /fast HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 "));
+    }
+
+    #[tokio::test]
+    async fn test_guards() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let response: Response = HttpResponse::ok().body("Dashboard");
+                response
+            })
+        };
+
+        let address = serve(move |server| {
+            let admin = Scope::new()
+                .guard(|request| match request.headers.value("Authorization") {
+                    Some(_) => Ok(()),
+                    None => Err(HttpResponse::unauthorized().body("Login required")),
+                })
+                .urls(vec![Path::new("/", view).guard(|request| {
+                    match request.headers.value("X-Role").as_deref() {
+                        Some("admin") => Ok(()),
+                        _ => Err(HttpResponse::forbidden().body("Admins only")),
+                    }
+                })]);
+            server.mount("/admin", admin);
+        });
+
+        // Scope guard is evaluated first and the first rejection is sent.
+        let requests = [
+            ("", "HTTP/1.1 401 ", "Login required"),
+            ("Authorization: Token a\r\n", "HTTP/1.1 403 ", "Admins only"),
+            (
+                "Authorization: Token a\r\nX-Role: admin\r\n",
+                "HTTP/1.1 200 ",
+                "Dashboard",
+            ),
+        ];
+        for (headers, status_line, body) in requests {
+            let request = format!(
+                "GET /admin/ HTTP/1.1\r\n{}Connection: close\r\n\r\n",
+                headers
+            );
+            let response = send(address, &request).await;
+            assert!(response.starts_with(status_line));
+            assert!(response.ends_with(&format!("\r\n\r\n{}", body)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_from_get_route() {
+        let view: View = |_| {
+            Box::pin(async move {
+                let response: Response = HttpResponse::ok().body("Hello");
+                response
+            })
+        };
+
+        let address = serve(move |server| {
+            server.urls(vec![Path::get("/", view)]);
+        });
+        let get_response = send(address, "GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+        let head_response = send(address, "HEAD / HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+
+        // Head is the same as the head of the GET response, without the body.
+        let (get_head, _) = get_response.split_once("\r\n\r\n").unwrap();
+        let (head, body) = head_response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(body.is_empty());
+
+        let mut get_lines: Vec<&str> = get_head.lines().collect();
+        let mut head_lines: Vec<&str> = head.lines().collect();
+        get_lines.sort();
+        head_lines.sort();
+        assert_eq!(get_lines, head_lines);
+        assert!(head.to_lowercase().contains("content-length: 5"));
+    }
+
+    #[tokio::test]
+    async fn test_body_read_timeout_with_upgrade_header() {
+        let view: View = |request| {
+            Box::pin(async move {
+                let response: Response = match request.body().await {
+                    Ok(_) => HttpResponse::ok().body("Read"),
+                    Err(_) => HttpResponse::request_timeout().body("Timed out"),
+                };
+                response
+            })
+        };
+
+        let address = serve(move |server| {
+            server
+                .body_read_timeout(Duration::from_millis(100))
+                .urls(vec![Path::new("/", view)]);
+        });
+
+        // Upgrade header alone does not lift the timeout of the stalled body.
+        let mut client = TcpStream::connect(address).await.unwrap();
+        client
+            .write_all(b"POST / HTTP/1.1\r\nUpgrade: x\r\nContent-Length: 100\r\n\r\nabc")
+            .await
+            .unwrap();
+
+        let mut response = vec![0; 1024];
+        let read_size = tokio::time::timeout(Duration::from_secs(3), client.read(&mut response))
+            .await
+            .expect("Body read is not timed out.")
+            .unwrap();
+        assert!(response[..read_size].starts_with(b"HTTP/1.1 408"));
+    }
 }
@@ -0,0 +1,217 @@
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+
+/// Same as the default backlog of `TcpListener::bind`.
+pub(crate) const DEFAULT_BACKLOG: u32 = 1024;
+
+///
+/// Options applied to the TCP sockets bound by the server and the connections accepted from
+/// them. `nodelay` and keep-alive options are also applied to the connections of the custom TCP
+/// listeners.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::server::socket::SocketOptions;
+/// use racoon::core::server::Server;
+///
+/// let socket_options = SocketOptions::new()
+///     .nodelay(true)
+///     .reuseport(true)
+///     .keepalive(Duration::from_secs(60))
+///     .keepalive_interval(Duration::from_secs(10))
+///     .keepalive_retries(5);
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.socket_options(socket_options);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct SocketOptions {
+    nodelay: bool,
+    reuseaddr: bool,
+    reuseport: bool,
+    keepalive_time: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    keepalive_retries: Option<u32>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: false,
+            reuseaddr: true,
+            reuseport: false,
+            keepalive_time: None,
+            keepalive_interval: None,
+            keepalive_retries: None,
+        }
+    }
+}
+
+impl SocketOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Sets `TCP_NODELAY` to the accepted connections, so the small responses are sent without
+    /// waiting to be coalesced.
+    ///
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    ///
+    /// Sets `SO_REUSEADDR` to the listening socket. Enabled by default, so the server can be
+    /// restarted while the old connections are in `TIME_WAIT` state.
+    ///
+    pub fn reuseaddr(mut self, reuseaddr: bool) -> Self {
+        self.reuseaddr = reuseaddr;
+        self
+    }
+
+    ///
+    /// Sets `SO_REUSEPORT` to the listening socket, so multiple processes can bind to the same
+    /// address.
+    ///
+    pub fn reuseport(mut self, reuseport: bool) -> Self {
+        self.reuseport = reuseport;
+        self
+    }
+
+    ///
+    /// Enables TCP keepalive. Probes are sent after the connection is idle for the duration.
+    ///
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        self.keepalive_time = Some(time);
+        self
+    }
+
+    ///
+    /// Duration between the TCP keepalive probes.
+    ///
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = Some(interval);
+        self
+    }
+
+    ///
+    /// Number of unanswered TCP keepalive probes before the connection is dropped.
+    ///
+    pub fn keepalive_retries(mut self, retries: u32) -> Self {
+        self.keepalive_retries = Some(retries);
+        self
+    }
+
+    ///
+    /// Applies the connection level options to the accepted stream.
+    ///
+    pub(crate) fn apply(&self, tcp_stream: &TcpStream) -> std::io::Result<()> {
+        if self.nodelay {
+            tcp_stream.set_nodelay(true)?;
+        }
+
+        if let Some(time) = self.keepalive_time {
+            let mut keepalive = TcpKeepalive::new().with_time(time);
+            if let Some(interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(interval);
+            }
+
+            if let Some(retries) = self.keepalive_retries {
+                keepalive = keepalive.with_retries(retries);
+            }
+
+            SockRef::from(tcp_stream).set_tcp_keepalive(&keepalive)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Binds TCP listener with the socket options and the given size of the pending connections
+/// queue. Resolved addresses are tried in order until one of them is bound.
+///
+pub(crate) async fn bind(
+    address: &str,
+    backlog: u32,
+    reuseport: bool,
+    socket_options: &SocketOptions,
+) -> std::io::Result<TcpListener> {
+    let mut last_error = None;
+
+    for socket_address in tokio::net::lookup_host(address).await? {
+        let socket = if socket_address.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+
+        let result = socket
+            .set_reuseaddr(socket_options.reuseaddr)
+            .and_then(|_| socket.set_reuseport(reuseport || socket_options.reuseport))
+            .and_then(|_| socket.bind(socket_address))
+            .and_then(|_| socket.listen(backlog));
+
+        match result {
+            Ok(listener) => return Ok(listener),
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    match last_error {
+        Some(error) => Err(error),
+        None => Err(std::io::Error::other(format!(
+            "Failed to resolve address {}",
+            address
+        ))),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use socket2::SockRef;
+    use tokio::net::TcpStream;
+
+    use super::{bind, SocketOptions};
+
+    #[tokio::test]
+    async fn test_socket_options() {
+        let socket_options = SocketOptions::new()
+            .nodelay(true)
+            .reuseport(true)
+            .keepalive(Duration::from_secs(60))
+            .keepalive_interval(Duration::from_secs(10));
+
+        let listener = bind("127.0.0.1:0", 16, false, &socket_options)
+            .await
+            .unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // Second socket can bind to the same address with SO_REUSEPORT.
+        assert!(bind(&address.to_string(), 16, false, &socket_options)
+            .await
+            .is_ok());
+
+        let client = TcpStream::connect(address).await.unwrap();
+        let (tcp_stream, _) = listener.accept().await.unwrap();
+        socket_options.apply(&tcp_stream).unwrap();
+
+        assert!(tcp_stream.nodelay().unwrap());
+        let socket = SockRef::from(&tcp_stream);
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(Duration::from_secs(60), socket.keepalive_time().unwrap());
+        assert_eq!(
+            Duration::from_secs(10),
+            socket.keepalive_interval().unwrap()
+        );
+        drop(client);
+    }
+}
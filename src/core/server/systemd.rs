@@ -0,0 +1,103 @@
+use std::env;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixListener;
+
+use socket2::{Domain, Socket, Type};
+
+use crate::racoon_warn;
+
+/// First file descriptor passed by systemd.
+pub const SD_LISTEN_FDS_START: RawFd = 3;
+
+///
+/// Listening socket passed by systemd.
+///
+#[derive(Debug)]
+pub enum ActivatedListener {
+    Tcp(std::net::TcpListener),
+    Unix(UnixListener),
+}
+
+///
+/// Returns the number of file descriptors passed to this process. File descriptors are meant for
+/// the process with `LISTEN_PID`.
+///
+fn listen_fds_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> usize {
+    let listen_pid: u32 = match listen_pid.and_then(|value| value.trim().parse().ok()) {
+        Some(listen_pid) => listen_pid,
+        None => return 0,
+    };
+
+    if listen_pid != pid {
+        return 0;
+    }
+
+    listen_fds
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+///
+/// Takes the listening sockets passed with systemd socket activation protocol. Environment
+/// variables are removed, so the child processes don't take the same sockets. Returns empty list
+/// if the process is not socket activated.
+///
+/// # Examples
+///
+/// ```no_run
+/// use racoon::core::server::systemd::{self, ActivatedListener};
+///
+/// for listener in systemd::listeners().unwrap() {
+///     if let ActivatedListener::Tcp(listener) = listener {
+///         println!("{:?}", listener.local_addr());
+///     }
+/// }
+/// ```
+///
+pub fn listeners() -> std::io::Result<Vec<ActivatedListener>> {
+    let count = listen_fds_count(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    let mut listeners = vec![];
+    for fd in SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count as RawFd {
+        // Safety: systemd passes the file descriptors in sequence starting from 3.
+        let socket = unsafe { Socket::from_raw_fd(fd) };
+        socket.set_cloexec(true)?;
+        socket.set_nonblocking(true)?;
+
+        if socket.r#type()? != Type::STREAM {
+            racoon_warn!(
+                "Ignoring socket activated file descriptor {} of non stream type.",
+                fd
+            );
+            continue;
+        }
+
+        if socket.domain()? == Domain::UNIX {
+            listeners.push(ActivatedListener::Unix(OwnedFd::from(socket).into()));
+        } else {
+            listeners.push(ActivatedListener::Tcp(socket.into()));
+        }
+    }
+    Ok(listeners)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::listen_fds_count;
+
+    #[test]
+    fn test_listen_fds_count() {
+        assert_eq!(2, listen_fds_count(Some("100"), Some("2"), 100));
+        assert_eq!(0, listen_fds_count(Some("101"), Some("2"), 100));
+        assert_eq!(0, listen_fds_count(None, Some("2"), 100));
+        assert_eq!(0, listen_fds_count(Some("100"), Some("invalid"), 100));
+    }
+}
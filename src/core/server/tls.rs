@@ -0,0 +1,258 @@
+use std::ffi::OsString;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use tokio_rustls::TlsAcceptor;
+
+use crate::{racoon_error, racoon_info};
+
+///
+/// Serves the certificate currently loaded by the reloader.
+///
+#[derive(Debug)]
+struct ReloadableResolver(RwLock<Arc<CertifiedKey>>);
+
+impl ResolvesServerCert for ReloadableResolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        match self.0.read() {
+            Ok(certified_key) => Some(certified_key.clone()),
+            Err(poisoned) => Some(poisoned.into_inner().clone()),
+        }
+    }
+}
+
+///
+/// TLS certificate and private key which can be replaced while the server is running. New
+/// handshakes use the reloaded certificate, while the existing connections are not affected.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use racoon::core::server::tls::CertificateReloader;
+/// use racoon::core::server::Server;
+///
+/// # fn main() -> std::io::Result<()> {
+/// // Files are checked for changes every minute.
+/// let reloader = CertificateReloader::from_path("cert.pem", "key.pem")?
+///     .watch(Duration::from_secs(60));
+///
+/// let mut server = Server::bind("0.0.0.0:80");
+/// server.add_reloadable_tls_listener("0.0.0.0:443", reloader.clone());
+///
+/// // Or reload explicitly, for example on SIGHUP.
+/// reloader.reload()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct CertificateReloader {
+    certificate_path: OsString,
+    private_key_path: OsString,
+    resolver: Arc<ReloadableResolver>,
+    modified: Arc<Mutex<Option<(SystemTime, SystemTime)>>>,
+    watch_interval: Option<Duration>,
+}
+
+impl CertificateReloader {
+    ///
+    /// Loads PEM encoded certificate chain and PKCS#8 private key.
+    ///
+    pub fn from_path<P: Into<OsString>>(
+        certificate_path: P,
+        private_key_path: P,
+    ) -> std::io::Result<Self> {
+        let certificate_path = certificate_path.into();
+        let private_key_path = private_key_path.into();
+
+        let modified = modified_times(&certificate_path, &private_key_path).ok();
+        let certified_key = load_certified_key(&certificate_path, &private_key_path)?;
+
+        Ok(Self {
+            certificate_path,
+            private_key_path,
+            resolver: Arc::new(ReloadableResolver(RwLock::new(Arc::new(certified_key)))),
+            modified: Arc::new(Mutex::new(modified)),
+            watch_interval: None,
+        })
+    }
+
+    ///
+    /// Reloads the certificate when the files are modified. Files are checked at the interval
+    /// while the server is running.
+    ///
+    pub fn watch(mut self, interval: Duration) -> Self {
+        self.watch_interval = Some(interval);
+        self
+    }
+
+    ///
+    /// Reads the certificate and private key files again. If the files are invalid, the error is
+    /// returned and the previous certificate is kept.
+    ///
+    pub fn reload(&self) -> std::io::Result<()> {
+        let modified = modified_times(&self.certificate_path, &self.private_key_path).ok();
+        let certified_key = load_certified_key(&self.certificate_path, &self.private_key_path)?;
+
+        match self.resolver.0.write() {
+            Ok(mut current) => *current = Arc::new(certified_key),
+            Err(poisoned) => *poisoned.into_inner() = Arc::new(certified_key),
+        }
+
+        if let Ok(mut current_modified) = self.modified.lock() {
+            *current_modified = modified;
+        }
+
+        racoon_info!("Reloaded TLS certificate from {:?}", self.certificate_path);
+        Ok(())
+    }
+
+    ///
+    /// Reloads the certificate if any of the files is modified since the last load.
+    ///
+    fn reload_if_modified(&self) -> std::io::Result<()> {
+        let modified = modified_times(&self.certificate_path, &self.private_key_path)?;
+
+        let is_modified = match self.modified.lock() {
+            Ok(current_modified) => *current_modified != Some(modified),
+            Err(_) => true,
+        };
+
+        if is_modified {
+            self.reload()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn tls_acceptor(&self) -> TlsAcceptor {
+        let server_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_cert_resolver(self.resolver.clone());
+        TlsAcceptor::from(Arc::new(server_config))
+    }
+
+    ///
+    /// Checks the files for changes at the watch interval. Returns immediately if watching is not
+    /// enabled.
+    ///
+    pub(crate) async fn watch_files(self) {
+        let interval = match self.watch_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            // Files may be partially written by the renewal tool, so the failed reload is
+            // retried at the next check.
+            if let Err(error) = self.reload_if_modified() {
+                racoon_error!(
+                    "Failed to reload TLS certificate from {:?}. Error: {}",
+                    self.certificate_path,
+                    error
+                );
+            }
+        }
+    }
+}
+
+fn modified_times(
+    certificate_path: &OsString,
+    private_key_path: &OsString,
+) -> std::io::Result<(SystemTime, SystemTime)> {
+    let certificate_modified = std::fs::metadata(certificate_path)?.modified()?;
+    let private_key_modified = std::fs::metadata(private_key_path)?.modified()?;
+    Ok((certificate_modified, private_key_modified))
+}
+
+fn load_certified_key(
+    certificate_path: &OsString,
+    private_key_path: &OsString,
+) -> std::io::Result<CertifiedKey> {
+    let certificate_file = std::fs::File::open(certificate_path).map_err(|error| {
+        std::io::Error::other(format!("Failed to open certificate file. Error: {}", error))
+    })?;
+
+    let mut certificates = vec![];
+    for certificate in rustls_pemfile::certs(&mut BufReader::new(certificate_file)) {
+        certificates.push(certificate?);
+    }
+
+    if certificates.is_empty() {
+        return Err(std::io::Error::other("Certificate not found."));
+    }
+
+    let private_key_file = std::fs::File::open(private_key_path).map_err(|error| {
+        std::io::Error::other(format!("Failed to open private key file. Error: {}", error))
+    })?;
+
+    let private_key =
+        match rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(private_key_file)).next() {
+            Some(private_key) => rustls::pki_types::PrivateKeyDer::Pkcs8(private_key?),
+            None => return Err(std::io::Error::other("Private key not found.")),
+        };
+
+    let signing_key = rustls::crypto::aws_lc_rs::sign::any_supported_type(&private_key)
+        .map_err(std::io::Error::other)?;
+    let certified_key = CertifiedKey::new(certificates, signing_key);
+
+    // Mismatched certificate and key would fail every handshake after the reload.
+    certified_key.keys_match().map_err(std::io::Error::other)?;
+    Ok(certified_key)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use rcgen::{CertificateParams, KeyPair};
+
+    use super::CertificateReloader;
+
+    fn write_certificate(directory: &std::path::Path, domain: &str) {
+        let key_pair = KeyPair::generate().unwrap();
+        let params = CertificateParams::new(vec![domain.to_string()]).unwrap();
+        let certificate = params.self_signed(&key_pair).unwrap();
+
+        std::fs::write(directory.join("cert.pem"), certificate.pem()).unwrap();
+        std::fs::write(directory.join("key.pem"), key_pair.serialize_pem()).unwrap();
+    }
+
+    fn serving_certificate(reloader: &CertificateReloader) -> Vec<u8> {
+        let certified_key = reloader.resolver.0.read().unwrap().clone();
+        certified_key.cert[0].to_vec()
+    }
+
+    #[test]
+    fn test_certificate_reload() {
+        let directory = std::env::temp_dir().join(format!("racoon-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        write_certificate(&directory, "old.example.com");
+
+        let reloader =
+            CertificateReloader::from_path(directory.join("cert.pem"), directory.join("key.pem"))
+                .unwrap();
+        let old_certificate = serving_certificate(&reloader);
+
+        // Invalid files keep the previous certificate.
+        std::fs::write(directory.join("key.pem"), "invalid").unwrap();
+        assert!(reloader.reload().is_err());
+        assert_eq!(old_certificate, serving_certificate(&reloader));
+
+        write_certificate(&directory, "new.example.com");
+        reloader.reload().unwrap();
+        assert_ne!(old_certificate, serving_certificate(&reloader));
+
+        // Acceptor created before the reload uses the same resolver.
+        let _acceptor = reloader.tls_acceptor();
+        assert_eq!(2, Arc::strong_count(&reloader.resolver));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use std::env;
+use std::net::{SocketAddr, TcpListener};
+use std::os::fd::{BorrowedFd, FromRawFd, RawFd};
+use std::process::{Child, Command};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use socket2::SockRef;
+
+use crate::core::server::ShutdownLock;
+use crate::racoon_error;
+
+/// Environment variable with the comma separated listener file descriptors passed to the new
+/// process.
+pub const LISTEN_FDS_ENV: &str = "RACOON_LISTEN_FDS";
+
+///
+/// Listeners inherited from the parent process. Environment variable is read only once, so the
+/// processes started later by this process don't take the same file descriptors.
+///
+fn inherited() -> &'static Mutex<Vec<TcpListener>> {
+    static INHERITED: OnceLock<Mutex<Vec<TcpListener>>> = OnceLock::new();
+
+    INHERITED.get_or_init(|| {
+        let mut listeners = vec![];
+
+        if let Ok(listen_fds) = env::var(LISTEN_FDS_ENV) {
+            env::remove_var(LISTEN_FDS_ENV);
+
+            for fd in listen_fds.split(',') {
+                let fd: RawFd = match fd.trim().parse() {
+                    Ok(fd) => fd,
+                    Err(_) => {
+                        racoon_error!("Invalid file descriptor in {}: {}", LISTEN_FDS_ENV, fd);
+                        continue;
+                    }
+                };
+
+                // Safety: the parent process passes the file descriptors of its listeners.
+                let listener = unsafe { TcpListener::from_raw_fd(fd) };
+                let _ = SockRef::from(&listener).set_cloexec(true);
+                listeners.push(listener);
+            }
+        }
+        Mutex::new(listeners)
+    })
+}
+
+///
+/// Takes the inherited listener bound to the address.
+///
+pub(crate) async fn take_inherited(address: &str) -> Option<TcpListener> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host(address).await.ok()?.collect();
+
+    let mut inherited = match inherited().lock() {
+        Ok(inherited) => inherited,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    let position = inherited
+        .iter()
+        .position(|listener| match listener.local_addr() {
+            Ok(local_address) => addresses.contains(&local_address),
+            Err(_) => false,
+        })?;
+
+    let listener = inherited.remove(position);
+    listener.set_nonblocking(true).ok()?;
+    Some(listener)
+}
+
+///
+/// Handle for upgrading the running server binary without dropping the connections.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use racoon::core::server::Server;
+/// use tokio::signal::unix::{signal, SignalKind};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mut server = Server::bind("127.0.0.1:8080");
+///     server.drain_timeout(Duration::from_secs(30));
+///
+///     let upgrade_handle = server.upgrade_handle();
+///     tokio::spawn(async move {
+///         let mut signal = signal(SignalKind::user_defined2()).unwrap();
+///         signal.recv().await;
+///
+///         // New binary at the same path takes over the listeners.
+///         upgrade_handle.upgrade().unwrap();
+///     });
+///
+///     server.run().await.unwrap();
+/// }
+/// ```
+///
+#[derive(Clone)]
+pub struct UpgradeHandle {
+    listener_fds: Arc<Mutex<Vec<RawFd>>>,
+    shutdown_lock: ShutdownLock,
+}
+
+impl UpgradeHandle {
+    pub(crate) fn new(listener_fds: Arc<Mutex<Vec<RawFd>>>, shutdown_lock: ShutdownLock) -> Self {
+        Self {
+            listener_fds,
+            shutdown_lock,
+        }
+    }
+
+    ///
+    /// Starts the current executable with the same arguments and passes the TCP listeners bound
+    /// by the server to it with `RACOON_LISTEN_FDS` environment variable. The server then stops
+    /// accepting connections and drains the open connections.
+    ///
+    pub fn upgrade(&self) -> std::io::Result<Child> {
+        let listener_fds = match self.listener_fds.lock() {
+            Ok(listener_fds) => listener_fds.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+
+        if listener_fds.is_empty() {
+            return Err(std::io::Error::other(
+                "Server has no listeners to pass to the new process.",
+            ));
+        }
+
+        let listen_fds: Vec<String> = listener_fds.iter().map(|fd| fd.to_string()).collect();
+        let mut command = Command::new(env::current_exe()?);
+        command
+            .args(env::args_os().skip(1))
+            .env(LISTEN_FDS_ENV, listen_fds.join(","));
+
+        // Listeners are created with close-on-exec flag, so it is cleared only while spawning.
+        set_cloexec(&listener_fds, false)?;
+        let child = command.spawn();
+        set_cloexec(&listener_fds, true)?;
+        let child = child?;
+
+        let (mutex, condvar) = &*self.shutdown_lock;
+        let _lock = mutex.lock();
+        condvar.notify_all();
+        Ok(child)
+    }
+}
+
+fn set_cloexec(fds: &[RawFd], cloexec: bool) -> std::io::Result<()> {
+    for fd in fds {
+        // Safety: file descriptors belong to the listeners of the running server.
+        let fd = unsafe { BorrowedFd::borrow_raw(*fd) };
+        SockRef::from(&fd).set_cloexec(cloexec)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::TcpListener;
+    use std::os::fd::IntoRawFd;
+
+    use super::{take_inherited, LISTEN_FDS_ENV};
+
+    #[tokio::test]
+    async fn test_take_inherited() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let fd = listener.try_clone().unwrap().into_raw_fd();
+        std::env::set_var(LISTEN_FDS_ENV, fd.to_string());
+
+        let inherited = take_inherited(&address).await.unwrap();
+        assert_eq!(address, inherited.local_addr().unwrap().to_string());
+        assert!(std::env::var(LISTEN_FDS_ENV).is_err());
+        assert!(take_inherited(&address).await.is_none());
+    }
+}
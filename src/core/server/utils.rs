@@ -10,6 +10,17 @@ pub fn tls_acceptor_from_path<S: AsRef<OsStr>>(
     certificate_path: S,
     private_key_path: S,
 ) -> std::io::Result<TlsAcceptor> {
+    let server_config = server_config_from_path(certificate_path, private_key_path)?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+///
+/// Creates TLS server configuration from PEM encoded certificate chain and PKCS#8 private key.
+///
+pub fn server_config_from_path<S: AsRef<OsStr>>(
+    certificate_path: S,
+    private_key_path: S,
+) -> std::io::Result<rustls::ServerConfig> {
     // Tries to read certificate file
     let certificate_file = match std::fs::File::open(certificate_path.as_ref()) {
         Ok(file) => file,
@@ -62,8 +73,8 @@ pub fn tls_acceptor_from_path<S: AsRef<OsStr>>(
             }
         };
 
-        return Ok(TlsAcceptor::from(Arc::new(server_config)));
+        Ok(server_config)
     } else {
-        return Err(std::io::Error::other("Private key not found."));
+        Err(std::io::Error::other("Private key not found."))
     }
 }
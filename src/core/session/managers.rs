@@ -1,14 +1,18 @@
+use std::collections::HashMap;
 use std::env;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::ConnectOptions;
 use sqlx::Executor;
 use sqlx::Pool;
 use sqlx::Sqlite;
+use tokio::sync::Mutex;
 
+use crate::core::clock::{self, Clock};
 use crate::core::session::AbstractSessionManager;
 use crate::core::session::SessionResult;
 use crate::racoon_debug;
@@ -311,15 +315,312 @@ impl AbstractSessionManager for FileSessionManager {
     }
 }
 
+///
+/// Session manager storing the session values in memory. Values are lost when the server
+/// restarts, so it is useful for development, tests and single instance deployments.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::server::Server;
+/// use racoon::core::session::managers::MemorySessionManager;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.set_session_manager(MemorySessionManager::new());
+/// ```
+///
+#[derive(Clone)]
+pub struct MemorySessionManager {
+    sessions: Arc<Mutex<MemorySessions>>,
+    ttl: Option<Duration>,
+    clock: Clock,
+}
+
+struct MemorySession {
+    values: HashMap<String, String>,
+    expires_at: Option<Instant>,
+}
+
+struct MemorySessions {
+    sessions: HashMap<String, MemorySession>,
+    last_cleanup: Instant,
+}
+
+impl MemorySessions {
+    ///
+    /// Returns the session if it has not expired. Expired sessions are removed.
+    ///
+    fn active(&mut self, session_id: &str, now: Instant) -> Option<&mut MemorySession> {
+        let is_expired = self
+            .sessions
+            .get(session_id)?
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now);
+
+        if is_expired {
+            self.sessions.remove(session_id);
+            return None;
+        }
+        self.sessions.get_mut(session_id)
+    }
+}
+
+impl Default for MemorySessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemorySessionManager {
+    pub fn new() -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(MemorySessions {
+                sessions: HashMap::new(),
+                last_cleanup: Instant::now(),
+            })),
+            ttl: None,
+            clock: clock::system(),
+        }
+    }
+
+    ///
+    /// Removes the session after the duration since its last write. Sessions do not expire by
+    /// default.
+    ///
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    ///
+    /// Clock used for expiring the sessions. Defaults to the system clock.
+    ///
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl AbstractSessionManager for MemorySessionManager {
+    fn set(
+        &self,
+        session_id: &String,
+        name: &str,
+        value: &str,
+    ) -> SessionResult<std::io::Result<()>> {
+        let sessions = self.sessions.clone();
+        let session_id = session_id.to_owned();
+        let name = name.to_owned();
+        let value = value.to_owned();
+        let now = self.clock.now();
+        let expires_at = self.ttl.map(|ttl| now + ttl);
+
+        Box::new(Box::pin(async move {
+            let mut sessions = sessions.lock().await;
+
+            // Expired sessions nobody reads again are removed periodically.
+            if now.saturating_duration_since(sessions.last_cleanup) > Duration::from_secs(60) {
+                sessions.sessions.retain(|_, session| {
+                    session.expires_at.is_none_or(|expires_at| expires_at > now)
+                });
+                sessions.last_cleanup = now;
+            }
+
+            if sessions.active(&session_id, now).is_none() {
+                sessions.sessions.insert(
+                    session_id.clone(),
+                    MemorySession {
+                        values: HashMap::new(),
+                        expires_at,
+                    },
+                );
+            }
+
+            if let Some(session) = sessions.sessions.get_mut(&session_id) {
+                session.values.insert(name, value);
+                session.expires_at = expires_at;
+            }
+            Ok(())
+        }))
+    }
+
+    fn get(&self, session_id: &String, name: &str) -> SessionResult<Option<String>> {
+        let sessions = self.sessions.clone();
+        let session_id = session_id.to_owned();
+        let name = name.to_owned();
+        let now = self.clock.now();
+
+        Box::new(Box::pin(async move {
+            let mut sessions = sessions.lock().await;
+            sessions
+                .active(&session_id, now)?
+                .values
+                .get(&name)
+                .cloned()
+        }))
+    }
+
+    fn remove(&self, session_id: &String, name: &str) -> SessionResult<std::io::Result<()>> {
+        let sessions = self.sessions.clone();
+        let session_id = session_id.to_owned();
+        let name = name.to_owned();
+
+        Box::new(Box::pin(async move {
+            let mut sessions = sessions.lock().await;
+            if let Some(session) = sessions.sessions.get_mut(&session_id) {
+                session.values.remove(&name);
+            }
+            Ok(())
+        }))
+    }
+
+    fn destroy(&self, session_id: &String) -> SessionResult<std::io::Result<()>> {
+        let sessions = self.sessions.clone();
+        let session_id = session_id.to_owned();
+
+        Box::new(Box::pin(async move {
+            sessions.lock().await.sessions.remove(&session_id);
+            Ok(())
+        }))
+    }
+}
+
+///
+/// Session manager storing the session values in Redis hash `racoon:session:<session_id>`.
+/// Available with the `redis` feature. Keys expire after the specified time to live which is
+/// refreshed on every write.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use racoon::core::server::Server;
+/// use racoon::core::session::managers::RedisSessionManager;
+///
+/// #[tokio::main]
+/// async fn main() -> std::io::Result<()> {
+///     let session_manager =
+///         RedisSessionManager::new("redis://127.0.0.1/", Duration::from_secs(7 * 86400)).await?;
+///
+///     let mut server = Server::bind("127.0.0.1:8080");
+///     server.set_session_manager(session_manager);
+///     server.run().await
+/// }
+/// ```
+///
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisSessionManager {
+    connection: redis::aio::ConnectionManager,
+    ttl: std::time::Duration,
+}
+
+#[cfg(feature = "redis")]
+impl RedisSessionManager {
+    pub async fn new<S: AsRef<str>>(url: S, ttl: std::time::Duration) -> std::io::Result<Self> {
+        let client = redis::Client::open(url.as_ref()).map_err(std::io::Error::other)?;
+        let connection = redis::aio::ConnectionManager::new(client)
+            .await
+            .map_err(std::io::Error::other)?;
+        Ok(Self { connection, ttl })
+    }
+
+    fn key(session_id: &str) -> String {
+        format!("racoon:session:{}", session_id)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl AbstractSessionManager for RedisSessionManager {
+    fn set(
+        &self,
+        session_id: &String,
+        name: &str,
+        value: &str,
+    ) -> SessionResult<std::io::Result<()>> {
+        let mut connection = self.connection.clone();
+        let key = Self::key(session_id);
+        let ttl = self.ttl.as_secs() as i64;
+        let name = name.to_owned();
+        let value = value.to_owned();
+
+        Box::new(Box::pin(async move {
+            redis::pipe()
+                .atomic()
+                .hset(&key, name, value)
+                .ignore()
+                .expire(&key, ttl)
+                .ignore()
+                .query_async::<_, ()>(&mut connection)
+                .await
+                .map_err(std::io::Error::other)
+        }))
+    }
+
+    fn get(&self, session_id: &String, name: &str) -> SessionResult<Option<String>> {
+        let mut connection = self.connection.clone();
+        let key = Self::key(session_id);
+        let name = name.to_owned();
+
+        Box::new(Box::pin(async move {
+            let result: redis::RedisResult<Option<String>> = redis::cmd("HGET")
+                .arg(&key)
+                .arg(name)
+                .query_async(&mut connection)
+                .await;
+
+            match result {
+                Ok(value) => value,
+                Err(error) => {
+                    racoon_error!("Failed to read session value. Error: {}", error);
+                    None
+                }
+            }
+        }))
+    }
+
+    fn remove(&self, session_id: &String, name: &str) -> SessionResult<std::io::Result<()>> {
+        let mut connection = self.connection.clone();
+        let key = Self::key(session_id);
+        let name = name.to_owned();
+
+        Box::new(Box::pin(async move {
+            redis::cmd("HDEL")
+                .arg(&key)
+                .arg(name)
+                .query_async::<_, ()>(&mut connection)
+                .await
+                .map_err(std::io::Error::other)
+        }))
+    }
+
+    fn destroy(&self, session_id: &String) -> SessionResult<std::io::Result<()>> {
+        let mut connection = self.connection.clone();
+        let key = Self::key(session_id);
+
+        Box::new(Box::pin(async move {
+            redis::cmd("DEL")
+                .arg(&key)
+                .query_async::<_, ()>(&mut connection)
+                .await
+                .map_err(std::io::Error::other)
+        }))
+    }
+}
+
 #[cfg(test)]
 pub mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
     use std::{env, path::PathBuf, str::FromStr};
 
     use uuid::Uuid;
 
+    use crate::core::clock::ManualClock;
     use crate::core::session::AbstractSessionManager;
 
-    use super::FileSessionManager;
+    use super::{FileSessionManager, MemorySessionManager};
 
     #[tokio::test]
     async fn test_file_session() {
@@ -372,4 +673,60 @@ pub mod test {
         let delete_db_result = tokio::fs::remove_file(db_path).await;
         assert_eq!(true, delete_db_result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_memory_session() {
+        let session_manager = MemorySessionManager::new();
+        let session_id = Uuid::new_v4().to_string();
+
+        assert!(session_manager
+            .set(&session_id, "name", "John")
+            .await
+            .is_ok());
+        assert!(session_manager
+            .set(&session_id, "location", "ktm")
+            .await
+            .is_ok());
+        assert_eq!(
+            Some("John".to_string()),
+            session_manager.get(&session_id, "name").await
+        );
+
+        assert!(session_manager.remove(&session_id, "name").await.is_ok());
+        assert_eq!(None, session_manager.get(&session_id, "name").await);
+
+        assert!(session_manager.destroy(&session_id).await.is_ok());
+        assert_eq!(None, session_manager.get(&session_id, "location").await);
+    }
+
+    #[tokio::test]
+    async fn test_memory_session_expiry() {
+        let clock = ManualClock::new();
+        let session_manager = MemorySessionManager::new()
+            .ttl(Duration::from_secs(60))
+            .clock(Arc::new(clock.clone()));
+        let session_id = Uuid::new_v4().to_string();
+
+        assert!(session_manager
+            .set(&session_id, "name", "John")
+            .await
+            .is_ok());
+
+        // Writes refresh the expiry.
+        clock.advance(Duration::from_secs(50));
+        assert!(session_manager
+            .set(&session_id, "location", "ktm")
+            .await
+            .is_ok());
+
+        clock.advance(Duration::from_secs(50));
+        assert_eq!(
+            Some("John".to_string()),
+            session_manager.get(&session_id, "name").await
+        );
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(None, session_manager.get(&session_id, "name").await);
+        assert!(session_manager.sessions.lock().await.sessions.is_empty());
+    }
 }
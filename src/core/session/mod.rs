@@ -8,6 +8,9 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::core::headers::Headers;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::shortcuts::SingleText;
 
 use super::cookie;
 
@@ -215,3 +218,47 @@ impl Session {
         Ok(())
     }
 }
+
+///
+/// Middleware using different session manager for the wrapped routes than the one set on the
+/// server. Session id is still read from the `sessionid` cookie and new session id is sent with
+/// the response when the value is set for the first time.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::path::Scope;
+/// use racoon::core::session::SessionMiddleware;
+/// use racoon::core::session::managers::MemorySessionManager;
+///
+/// let admin = Scope::new().middleware(SessionMiddleware::new(MemorySessionManager::new()));
+/// ```
+///
+#[derive(Clone)]
+pub struct SessionMiddleware {
+    session_manager: Arc<SessionManager>,
+}
+
+impl SessionMiddleware {
+    pub fn new<T: AbstractSessionManager + 'static>(session_manager: T) -> Self {
+        Self {
+            session_manager: Arc::new(Box::new(session_manager)),
+        }
+    }
+}
+
+impl AbstractMiddleware for SessionMiddleware {
+    fn handle(&self, mut request: Request, next: Next) -> MiddlewareResult {
+        let session_manager = self.session_manager.clone();
+
+        Box::new(Box::pin(async move {
+            let session_id = request.cookies.value("sessionid");
+            request.session = Session::from(
+                session_manager,
+                session_id,
+                request.response_headers.clone(),
+            );
+            next.run(request).await
+        }))
+    }
+}
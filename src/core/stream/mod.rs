@@ -1,10 +1,19 @@
+mod pool;
+pub(crate) mod timeout;
+#[cfg(feature = "io-uring")]
+pub mod uring;
+
+#[cfg(all(feature = "io-uring", not(target_os = "linux")))]
+compile_error!("io-uring feature is only supported on Linux.");
+
 use std::future::Future;
 use std::io::ErrorKind;
 use std::net::Shutdown;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::io::{ReadHalf, WriteHalf};
 use tokio::net::{TcpStream, UnixStream};
 use tokio::sync::Mutex;
@@ -13,6 +22,7 @@ use tokio_rustls::server::TlsStream;
 use tokio_rustls::TlsAcceptor;
 
 use crate::{racoon_debug, racoon_error};
+use pool::PooledBuffer;
 
 pub type StreamResult<'a, T> = Box<dyn Future<Output = T> + Sync + Send + Unpin + 'a>;
 pub type Stream = Box<dyn AbstractStream>;
@@ -25,6 +35,105 @@ pub trait AbstractStream: Sync + Send {
     fn read_chunk(&self) -> StreamResult<std::io::Result<Vec<u8>>>;
     fn write_chunk<'a>(&'a self, bytes: &'a [u8]) -> StreamResult<std::io::Result<()>>;
     fn shutdown(&self) -> StreamResult<std::io::Result<()>>;
+
+    ///
+    /// Writes `length` bytes of the file starting from `offset` to the stream. By default, file is
+    /// copied through userspace buffers. Streams supporting zero-copy transmission override this.
+    ///
+    fn send_file<'a>(
+        &'a self,
+        file: &'a mut File,
+        offset: u64,
+        length: u64,
+    ) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            copy_file(self, file, offset, length).await
+        }))
+    }
+}
+
+///
+/// Copies file to the stream in chunks of stream buffer size.
+///
+pub async fn copy_file<S: AbstractStream + ?Sized>(
+    stream: &S,
+    file: &mut File,
+    offset: u64,
+    length: u64,
+) -> std::io::Result<()> {
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+
+    let buffer_size = stream.buffer_size().await;
+    let mut buffer = PooledBuffer::take(buffer_size);
+    let mut remaining = length;
+
+    while remaining > 0 {
+        let read_limit = std::cmp::min(remaining, buffer_size as u64) as usize;
+        let read_size = file.read(&mut buffer[..read_limit]).await?;
+
+        if read_size == 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "File ended before the specified length.",
+            ));
+        }
+
+        stream.write_chunk(&buffer[..read_size]).await?;
+        remaining -= read_size as u64;
+    }
+
+    Ok(())
+}
+
+///
+/// Transfers file to the socket with `sendfile` system call, so the file content never enters
+/// userspace buffers.
+///
+#[cfg(target_os = "linux")]
+async fn sendfile(
+    stream: &TcpStream,
+    file: &File,
+    offset: u64,
+    length: u64,
+) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+    use tokio::io::Interest;
+
+    // Maximum bytes transferred by single sendfile call on Linux.
+    const MAX_SENDFILE_SIZE: u64 = 0x7fff_f000;
+
+    let socket_fd = stream.as_raw_fd();
+    let file_fd = file.as_raw_fd();
+    let mut offset = offset as libc::off_t;
+    let mut remaining = length;
+
+    while remaining > 0 {
+        stream.writable().await?;
+
+        let result = stream.try_io(Interest::WRITABLE, || {
+            let count = std::cmp::min(remaining, MAX_SENDFILE_SIZE) as usize;
+            let sent = unsafe { libc::sendfile(socket_fd, file_fd, &mut offset, count) };
+
+            if sent < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(sent as u64)
+        });
+
+        match result {
+            Ok(0) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "File ended before the specified length.",
+                ));
+            }
+            Ok(sent) => remaining -= sent,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => continue,
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -129,7 +238,7 @@ impl AbstractStream for TcpStreamWrapper {
                 return Ok(payload);
             }
 
-            let mut buffer = vec![0u8; buffer_size];
+            let mut buffer = PooledBuffer::take(buffer_size);
             let mut reader = reader_ref.lock().await;
 
             return match reader.read(&mut buffer).await {
@@ -141,8 +250,7 @@ impl AbstractStream for TcpStreamWrapper {
                         ));
                     }
 
-                    let chunk: Vec<u8> = buffer.drain(0..read_size).collect();
-                    Ok(chunk)
+                    Ok(buffer[..read_size].to_vec())
                 }
                 Err(error) => Err(std::io::Error::other(error)),
             };
@@ -168,6 +276,24 @@ impl AbstractStream for TcpStreamWrapper {
             Ok(())
         }))
     }
+
+    #[cfg(target_os = "linux")]
+    fn send_file<'a>(
+        &'a self,
+        file: &'a mut File,
+        offset: u64,
+        length: u64,
+    ) -> StreamResult<'a, std::io::Result<()>> {
+        let stream_ref = self.stream.clone();
+        let writer_ref = self.writer.clone();
+
+        Box::new(Box::pin(async move {
+            // Writer is locked so that other writes are not interleaved with the file content.
+            let _writer = writer_ref.lock().await;
+            let stream = stream_ref.lock().await;
+            sendfile(&stream, file, offset, length).await
+        }))
+    }
 }
 
 #[derive(Debug)]
@@ -263,7 +389,7 @@ impl AbstractStream for UnixStreamWrapper {
                 return Ok(buffer);
             }
 
-            let mut buffer = vec![0u8; buffer_size];
+            let mut buffer = PooledBuffer::take(buffer_size);
 
             let reader_ref = reader.clone();
             let mut reader = reader_ref.lock().await;
@@ -399,7 +525,7 @@ impl AbstractStream for TlsTcpStreamWrapper {
                 return Ok(buffer);
             }
 
-            let mut buffer = vec![0u8; buffer_size];
+            let mut buffer = PooledBuffer::take(buffer_size);
             let mut reader = reader.lock().await;
 
             return match reader.read(&mut buffer).await {
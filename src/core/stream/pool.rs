@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+
+/// Number of buffers kept for reuse by each thread.
+const MAX_POOLED_BUFFERS: usize = 64;
+
+thread_local! {
+    static BUFFERS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+///
+/// Read buffer taken from the pool of the current thread. Buffer is returned to the pool of the
+/// thread dropping it, so the next read does not allocate and zero it again.
+///
+pub(crate) struct PooledBuffer(Vec<u8>);
+
+impl PooledBuffer {
+    ///
+    /// Takes buffer of `size` bytes. Content of the reused buffer is not cleared, so only the
+    /// bytes written by the read are meant to be used.
+    ///
+    pub fn take(size: usize) -> Self {
+        let mut buffer = BUFFERS
+            .with(|buffers| buffers.borrow_mut().pop())
+            .unwrap_or_default();
+        buffer.resize(size, 0);
+        Self(buffer)
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let buffer = std::mem::take(&mut self.0);
+
+        // Thread local storage may already be destroyed when the thread exits.
+        let _ = BUFFERS.try_with(|buffers| {
+            let mut buffers = buffers.borrow_mut();
+            if buffers.len() < MAX_POOLED_BUFFERS {
+                buffers.push(buffer);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::PooledBuffer;
+
+    #[test]
+    fn test_pooled_buffer() {
+        let mut buffer = PooledBuffer::take(16);
+        buffer[..4].copy_from_slice(b"test");
+        let address = buffer.as_ptr();
+        drop(buffer);
+
+        // Same allocation is reused by the next read on this thread.
+        let buffer = PooledBuffer::take(8);
+        assert_eq!(8, buffer.len());
+        assert_eq!(address, buffer.as_ptr());
+    }
+}
@@ -0,0 +1,196 @@
+use std::future::Future;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::fs::File;
+
+use crate::core::stream::{AbstractStream, Stream, StreamResult};
+
+/// Size of the file part which needs to be sent within the write timeout.
+const SEND_FILE_PART_SIZE: u64 = 64 * 1024;
+
+async fn with_timeout<T, F>(
+    timeout: Option<Duration>,
+    future: F,
+    message: &'static str,
+) -> std::io::Result<T>
+where
+    F: Future<Output = std::io::Result<T>>,
+{
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, future).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::new(ErrorKind::TimedOut, message)),
+        },
+        None => future.await,
+    }
+}
+
+///
+/// Lifts the body read timeout of the connection after the protocol is switched, since the
+/// upgraded connections such as WebSocket stay idle between the messages. The timeout is not
+/// lifted by the `Upgrade` header alone, so the request body is still read with the timeout.
+///
+#[derive(Clone)]
+pub(crate) struct ProtocolSwitch(Arc<AtomicBool>);
+
+impl ProtocolSwitch {
+    pub fn new(waiting_request: Arc<AtomicBool>) -> Self {
+        Self(waiting_request)
+    }
+
+    pub fn switched(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+///
+/// Fails the reads and writes which don't complete within the timeouts, so stalled clients
+/// don't hold the connection and its buffers. Reads are not limited while the connection waits
+/// for the next request, since the idle time is limited by the keep-alive and header read
+/// timeouts.
+///
+pub(crate) struct TimeoutStreamWrapper {
+    stream: Stream,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    waiting_request: Arc<AtomicBool>,
+}
+
+impl TimeoutStreamWrapper {
+    pub fn new(
+        stream: Stream,
+        read_timeout: Option<Duration>,
+        write_timeout: Option<Duration>,
+        waiting_request: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            stream,
+            read_timeout,
+            write_timeout,
+            waiting_request,
+        }
+    }
+}
+
+impl AbstractStream for TimeoutStreamWrapper {
+    fn buffer_size(&self) -> StreamResult<'_, usize> {
+        self.stream.buffer_size()
+    }
+
+    fn peer_addr(&self) -> StreamResult<'_, Option<String>> {
+        self.stream.peer_addr()
+    }
+
+    fn restore_payload(&self, bytes: &[u8]) -> StreamResult<'_, std::io::Result<()>> {
+        self.stream.restore_payload(bytes)
+    }
+
+    fn restored_len(&self) -> StreamResult<'_, usize> {
+        self.stream.restored_len()
+    }
+
+    fn read_chunk(&self) -> StreamResult<'_, std::io::Result<Vec<u8>>> {
+        let read_timeout = if self.waiting_request.load(Ordering::Relaxed) {
+            None
+        } else {
+            self.read_timeout
+        };
+
+        Box::new(Box::pin(with_timeout(
+            read_timeout,
+            self.stream.read_chunk(),
+            "Timed out reading request body.",
+        )))
+    }
+
+    fn write_chunk<'a>(&'a self, bytes: &'a [u8]) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(with_timeout(
+            self.write_timeout,
+            self.stream.write_chunk(bytes),
+            "Timed out writing response.",
+        )))
+    }
+
+    fn shutdown(&self) -> StreamResult<'_, std::io::Result<()>> {
+        Box::new(Box::pin(with_timeout(
+            self.write_timeout,
+            self.stream.shutdown(),
+            "Timed out shutting down connection.",
+        )))
+    }
+
+    fn send_file<'a>(
+        &'a self,
+        file: &'a mut File,
+        offset: u64,
+        length: u64,
+    ) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            if self.write_timeout.is_none() {
+                return self.stream.send_file(file, offset, length).await;
+            }
+
+            // Timeout is applied to each part, so large files are not limited by the total
+            // transfer time.
+            let mut sent = 0;
+            while sent < length {
+                let part_size = std::cmp::min(length - sent, SEND_FILE_PART_SIZE);
+                with_timeout(
+                    self.write_timeout,
+                    self.stream.send_file(file, offset + sent, part_size),
+                    "Timed out writing response.",
+                )
+                .await?;
+                sent += part_size;
+            }
+            Ok(())
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::ErrorKind;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::stream::{AbstractStream, TcpStreamWrapper};
+
+    use super::TimeoutStreamWrapper;
+
+    #[tokio::test]
+    async fn test_stream_timeouts() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let waiting_request = Arc::new(AtomicBool::new(true));
+        let stream = TimeoutStreamWrapper::new(
+            Box::new(TcpStreamWrapper::from(server, 8096).unwrap()),
+            Some(Duration::from_millis(100)),
+            Some(Duration::from_millis(100)),
+            waiting_request.clone(),
+        );
+
+        // Idle connection waiting for the request is not timed out.
+        let idle = tokio::time::timeout(Duration::from_millis(300), stream.read_chunk()).await;
+        assert!(idle.is_err());
+
+        waiting_request.store(false, Ordering::Relaxed);
+        let error = stream.read_chunk().await.unwrap_err();
+        assert_eq!(ErrorKind::TimedOut, error.kind());
+
+        // Client does not read, so the write blocks once the socket buffers are full.
+        let response = vec![0; 64 * 1024 * 1024];
+        let error = stream.write_chunk(&response).await.unwrap_err();
+        assert_eq!(ErrorKind::TimedOut, error.kind());
+    }
+}
@@ -0,0 +1,407 @@
+use std::net::Shutdown;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::fs::File;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_uring::buf::IoBuf;
+
+use crate::core::stream::{AbstractStream, StreamResult};
+use crate::racoon_debug;
+
+struct ReadOperation {
+    size: usize,
+    reply: oneshot::Sender<std::io::Result<Vec<u8>>>,
+}
+
+enum WriteOperation {
+    Write {
+        data: Vec<u8>,
+        reply: oneshot::Sender<std::io::Result<()>>,
+    },
+    SendFile {
+        file: std::fs::File,
+        offset: u64,
+        length: u64,
+        buffer_size: usize,
+        reply: oneshot::Sender<std::io::Result<()>>,
+    },
+    Shutdown {
+        reply: oneshot::Sender<std::io::Result<()>>,
+    },
+}
+
+///
+/// Connection handed over to the io_uring worker thread.
+///
+struct Registration {
+    stream: std::net::TcpStream,
+    reads: mpsc::UnboundedReceiver<ReadOperation>,
+    writes: mpsc::UnboundedReceiver<WriteOperation>,
+}
+
+///
+/// Threads running io_uring runtimes which perform the socket reads, writes and file transfers
+/// of the registered connections. Requests are still handled on the Tokio runtime running the
+/// server, so the handlers don't need to be aware of io_uring.
+///
+#[derive(Debug)]
+pub struct UringDriver {
+    workers: Vec<mpsc::UnboundedSender<Registration>>,
+    next_worker: AtomicUsize,
+}
+
+impl UringDriver {
+    ///
+    /// Starts the worker threads. Returns error if io_uring is not supported by the kernel or
+    /// blocked by the seccomp policy.
+    ///
+    pub fn start(workers: usize) -> std::io::Result<Self> {
+        let mut senders = vec![];
+
+        for index in 0..workers.max(1) {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let (started_sender, started) = std::sync::mpsc::channel();
+
+            std::thread::Builder::new()
+                .name(format!("racoon-uring-{}", index))
+                .spawn(move || {
+                    let runtime = match tokio_uring::Runtime::new(&tokio_uring::builder()) {
+                        Ok(runtime) => runtime,
+                        Err(error) => {
+                            let _ = started_sender.send(Err(error));
+                            return;
+                        }
+                    };
+
+                    let _ = started_sender.send(Ok(()));
+                    runtime.block_on(run_worker(receiver));
+                })?;
+
+            match started.recv() {
+                Ok(result) => result?,
+                Err(error) => return Err(std::io::Error::other(error)),
+            }
+            senders.push(sender);
+        }
+
+        Ok(Self {
+            workers: senders,
+            next_worker: AtomicUsize::new(0),
+        })
+    }
+
+    ///
+    /// Moves the connection to one of the workers. Workers are picked in round-robin order.
+    ///
+    pub fn register(
+        &self,
+        tcp_stream: tokio::net::TcpStream,
+        buffer_size: usize,
+    ) -> std::io::Result<UringStreamWrapper> {
+        let peer_addr = tcp_stream.peer_addr().ok().map(|addr| addr.to_string());
+
+        // Deregisters the socket from the Tokio reactor.
+        let stream = tcp_stream.into_std()?;
+
+        let (read_sender, reads) = mpsc::unbounded_channel();
+        let (write_sender, writes) = mpsc::unbounded_channel();
+
+        let index = self.next_worker.fetch_add(1, Ordering::Relaxed) % self.workers.len();
+        let registration = Registration {
+            stream,
+            reads,
+            writes,
+        };
+
+        if self.workers[index].send(registration).is_err() {
+            return Err(std::io::Error::other("io_uring worker is not running."));
+        }
+
+        Ok(UringStreamWrapper {
+            reads: read_sender,
+            writes: write_sender,
+            buffer_size,
+            peer_addr,
+            restored_payload: Arc::new(Mutex::new(None)),
+        })
+    }
+}
+
+async fn run_worker(mut registrations: mpsc::UnboundedReceiver<Registration>) {
+    while let Some(registration) = registrations.recv().await {
+        let stream = Rc::new(tokio_uring::net::TcpStream::from_std(registration.stream));
+        tokio_uring::spawn(serve_reads(stream.clone(), registration.reads));
+        tokio_uring::spawn(serve_writes(stream, registration.writes));
+    }
+}
+
+async fn serve_reads(
+    stream: Rc<tokio_uring::net::TcpStream>,
+    mut reads: mpsc::UnboundedReceiver<ReadOperation>,
+) {
+    let mut buffer: Vec<u8> = vec![];
+
+    // Chunk read for the reader which stopped waiting is kept for the next read.
+    let mut unclaimed: Option<std::io::Result<Vec<u8>>> = None;
+
+    while let Some(read) = reads.recv().await {
+        if let Some(chunk) = unclaimed.take() {
+            if let Err(chunk) = read.reply.send(chunk) {
+                unclaimed = Some(chunk);
+            }
+            continue;
+        }
+
+        buffer.clear();
+        buffer.reserve(read.size);
+
+        let (result, read_buffer) = stream.read(buffer).await;
+        buffer = read_buffer;
+
+        let chunk = match result {
+            Ok(0) => Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "Read size is 0. Probably connection broken.",
+            )),
+            Ok(read_size) => Ok(buffer[..read_size].to_vec()),
+            Err(error) => Err(error),
+        };
+
+        if let Err(chunk) = read.reply.send(chunk) {
+            unclaimed = Some(chunk);
+        }
+    }
+}
+
+async fn serve_writes(
+    stream: Rc<tokio_uring::net::TcpStream>,
+    mut writes: mpsc::UnboundedReceiver<WriteOperation>,
+) {
+    while let Some(write) = writes.recv().await {
+        match write {
+            WriteOperation::Write { data, reply } => {
+                let (result, _) = stream.write_all(data).await;
+                let _ = reply.send(result);
+            }
+            WriteOperation::SendFile {
+                file,
+                offset,
+                length,
+                buffer_size,
+                reply,
+            } => {
+                let result = send_file(&stream, file, offset, length, buffer_size).await;
+                let _ = reply.send(result);
+            }
+            WriteOperation::Shutdown { reply } => {
+                if let Err(error) = stream.shutdown(Shutdown::Both) {
+                    racoon_debug!("Failed to shutdown io_uring stream. Error: {}", error);
+                }
+                let _ = reply.send(Ok(()));
+            }
+        }
+    }
+}
+
+///
+/// Copies the file to the socket with io_uring reads and writes.
+///
+async fn send_file(
+    stream: &tokio_uring::net::TcpStream,
+    file: std::fs::File,
+    offset: u64,
+    length: u64,
+    buffer_size: usize,
+) -> std::io::Result<()> {
+    let file = tokio_uring::fs::File::from_std(file);
+    let mut buffer: Vec<u8> = Vec::with_capacity(buffer_size.max(1));
+    let mut position = offset;
+    let mut remaining = length;
+
+    let result = async {
+        while remaining > 0 {
+            let read_limit = std::cmp::min(remaining, buffer.capacity() as u64) as usize;
+            buffer.clear();
+
+            let (result, slice) = file.read_at(buffer.slice(..read_limit), position).await;
+            buffer = slice.into_inner();
+            let read_size = result?;
+
+            if read_size == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "File ended before the specified length.",
+                ));
+            }
+
+            let (result, slice) = stream.write_all(buffer.slice(..read_size)).await;
+            buffer = slice.into_inner();
+            result?;
+
+            position += read_size as u64;
+            remaining -= read_size as u64;
+        }
+        Ok(())
+    }
+    .await;
+
+    let _ = file.close().await;
+    result
+}
+
+///
+/// Stream whose reads and writes are performed by the io_uring worker thread.
+///
+pub struct UringStreamWrapper {
+    reads: mpsc::UnboundedSender<ReadOperation>,
+    writes: mpsc::UnboundedSender<WriteOperation>,
+    buffer_size: usize,
+    peer_addr: Option<String>,
+    restored_payload: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+fn worker_stopped<T>(_: T) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "io_uring worker stopped handling the connection.",
+    )
+}
+
+impl UringStreamWrapper {
+    async fn write_operation<F>(&self, operation: F) -> std::io::Result<()>
+    where
+        F: FnOnce(oneshot::Sender<std::io::Result<()>>) -> WriteOperation,
+    {
+        let (reply, result) = oneshot::channel();
+        self.writes.send(operation(reply)).map_err(worker_stopped)?;
+        result.await.map_err(worker_stopped)?
+    }
+}
+
+impl AbstractStream for UringStreamWrapper {
+    fn buffer_size(&self) -> StreamResult<'_, usize> {
+        let buffer_size = self.buffer_size;
+        Box::new(Box::pin(async move { buffer_size }))
+    }
+
+    fn peer_addr(&self) -> StreamResult<'_, Option<String>> {
+        let peer_addr = self.peer_addr.clone();
+        Box::new(Box::pin(async move { peer_addr }))
+    }
+
+    fn restore_payload(&self, bytes: &[u8]) -> StreamResult<'_, std::io::Result<()>> {
+        let restored_payload_ref = self.restored_payload.clone();
+        let bytes = bytes.to_vec();
+
+        Box::new(Box::pin(async move {
+            let mut restored_payload = restored_payload_ref.lock().await;
+            *restored_payload = Some(bytes);
+            Ok(())
+        }))
+    }
+
+    fn restored_len(&self) -> StreamResult<'_, usize> {
+        let restored_payload_ref = self.restored_payload.clone();
+
+        Box::new(Box::pin(async move {
+            let restored_payload = restored_payload_ref.lock().await;
+
+            match restored_payload.as_ref() {
+                Some(restored) => restored.len(),
+                None => 0,
+            }
+        }))
+    }
+
+    fn read_chunk(&self) -> StreamResult<'_, std::io::Result<Vec<u8>>> {
+        Box::new(Box::pin(async move {
+            if let Some(payload) = self.restored_payload.lock().await.take() {
+                return Ok(payload);
+            }
+
+            let (reply, result) = oneshot::channel();
+            self.reads
+                .send(ReadOperation {
+                    size: self.buffer_size,
+                    reply,
+                })
+                .map_err(worker_stopped)?;
+            result.await.map_err(worker_stopped)?
+        }))
+    }
+
+    fn write_chunk<'a>(&'a self, bytes: &'a [u8]) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            self.write_operation(|reply| WriteOperation::Write {
+                data: bytes.to_vec(),
+                reply,
+            })
+            .await
+        }))
+    }
+
+    fn shutdown(&self) -> StreamResult<'_, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            self.write_operation(|reply| WriteOperation::Shutdown { reply })
+                .await
+        }))
+    }
+
+    fn send_file<'a>(
+        &'a self,
+        file: &'a mut File,
+        offset: u64,
+        length: u64,
+    ) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            // Worker reads from its own file descriptor, so the position of the caller's file is
+            // not changed.
+            let file = file.try_clone().await?.into_std().await;
+            let buffer_size = self.buffer_size;
+
+            self.write_operation(|reply| WriteOperation::SendFile {
+                file,
+                offset,
+                length,
+                buffer_size,
+                reply,
+            })
+            .await
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::stream::AbstractStream;
+
+    use super::UringDriver;
+
+    #[tokio::test]
+    #[ignore = "io_uring is often blocked in sandboxes and containers, run with --ignored"]
+    async fn test_uring_stream() {
+        let driver = UringDriver::start(1).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let stream = driver.register(server, 1024).unwrap();
+
+        client.write_all(b"ping").await.unwrap();
+        assert_eq!(b"ping".to_vec(), stream.read_chunk().await.unwrap());
+
+        stream.write_chunk(b"pong").await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut response = vec![];
+        client.read_to_end(&mut response).await.unwrap();
+        assert_eq!(b"pong".to_vec(), response);
+    }
+}
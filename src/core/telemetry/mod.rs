@@ -0,0 +1,266 @@
+//!
+//! Tracing instrumentation of the server available with the `tracing` feature. Spans are created
+//! for accepted connections, request parsing, routing and the request handler. Without the
+//! feature, the spans are no-op.
+//!
+//! Incoming W3C `traceparent` header is continued, so the spans of the request belong to the
+//! trace started by the caller. The trace context is available as [`TraceContext`] in the request
+//! extensions for propagating it to other services.
+//!
+
+use std::fmt::Display;
+use std::future::Future;
+
+use rand::Rng;
+
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::request::Request;
+
+///
+/// W3C trace context of the request.
+///
+/// More information: <https://www.w3.org/TR/trace-context/>
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::request::Request;
+/// use racoon::core::telemetry::TraceContext;
+///
+/// async fn home(request: Request) {
+///     if let Some(trace_context) = request.extensions.get::<TraceContext>() {
+///         // Sent as `traceparent` header to the downstream service.
+///         let traceparent = trace_context.traceparent();
+///     }
+/// }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    /// 32 lowercase hex characters shared by all the spans of the trace.
+    pub trace_id: String,
+    /// Span id of the caller. `None` if this server started the trace.
+    pub parent_id: Option<String>,
+    /// 16 lowercase hex characters identifying the span of this request.
+    pub span_id: String,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    ///
+    /// Starts new trace.
+    ///
+    pub fn new() -> Self {
+        Self {
+            trace_id: random_hex(16),
+            parent_id: None,
+            span_id: random_hex(8),
+            sampled: true,
+        }
+    }
+
+    ///
+    /// Continues the trace from the `traceparent` header. Returns `None` if the header is missing
+    /// or invalid.
+    ///
+    pub fn from_headers(headers: &Headers) -> Option<Self> {
+        let traceparent = headers.value("traceparent")?;
+        let parts: Vec<&str> = traceparent.trim().split('-').collect();
+        if parts.len() < 4 {
+            return None;
+        }
+
+        let (version, trace_id, parent_id, flags) = (parts[0], parts[1], parts[2], parts[3]);
+        let is_valid = is_hex(version, 2)
+            && version != "ff"
+            && (version != "00" || parts.len() == 4)
+            && is_hex(trace_id, 32)
+            && is_hex(parent_id, 16)
+            && is_hex(flags, 2)
+            && trace_id.chars().any(|c| c != '0')
+            && parent_id.chars().any(|c| c != '0');
+
+        if !is_valid {
+            return None;
+        }
+
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            parent_id: Some(parent_id.to_string()),
+            span_id: random_hex(8),
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    ///
+    /// Value of `traceparent` header for the requests sent while handling this request.
+    ///
+    pub fn traceparent(&self) -> String {
+        let flags = if self.sampled { "01" } else { "00" };
+        format!("00-{}-{}-{}", self.trace_id, self.span_id, flags)
+    }
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_hex(value: &str, length: usize) -> bool {
+    value.len() == length
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes)
+        .map(|_| format!("{:02x}", rng.gen::<u8>()))
+        .collect()
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) type Span = tracing::Span;
+
+///
+/// Placeholder span used when the `tracing` feature is disabled.
+///
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone)]
+pub(crate) struct Span;
+
+pub(crate) fn connection_span<D: Display>(peer_addr: D) -> Span {
+    #[cfg(feature = "tracing")]
+    return tracing::info_span!("racoon.connection", "client.address" = %peer_addr);
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = peer_addr;
+        Span
+    }
+}
+
+pub(crate) fn parse_span() -> Span {
+    #[cfg(feature = "tracing")]
+    return tracing::debug_span!("racoon.parse");
+
+    #[cfg(not(feature = "tracing"))]
+    Span
+}
+
+pub(crate) fn route_span(method: &str, path: &str) -> Span {
+    #[cfg(feature = "tracing")]
+    return tracing::debug_span!(
+        "racoon.route",
+        "http.request.method" = method,
+        "url.path" = path
+    );
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (method, path);
+        Span
+    }
+}
+
+///
+/// Creates span for handling the request. The trace context is continued from the request
+/// headers or started and inserted into the request extensions.
+///
+pub(crate) fn request_span(request: &mut Request) -> Span {
+    #[cfg(feature = "tracing")]
+    {
+        let trace_context = TraceContext::from_headers(&request.headers).unwrap_or_default();
+        let span = tracing::info_span!(
+            "racoon.request",
+            "http.request.method" = %request.method,
+            "url.path" = %request.path,
+            "http.response.status_code" = tracing::field::Empty,
+            "trace_id" = %trace_context.trace_id,
+            "span_id" = %trace_context.span_id,
+            "parent_id" = trace_context.parent_id.as_deref().unwrap_or_default(),
+        );
+        request.extensions.insert(trace_context);
+        span
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = request;
+        Span
+    }
+}
+
+pub(crate) fn record_status(span: &Span, status_code: u32) {
+    #[cfg(feature = "tracing")]
+    span.record("http.response.status_code", status_code);
+
+    #[cfg(not(feature = "tracing"))]
+    let _ = (span, status_code);
+}
+
+///
+/// Calls the function inside the span.
+///
+pub(crate) fn in_scope<T, F: FnOnce() -> T>(span: &Span, f: F) -> T {
+    #[cfg(feature = "tracing")]
+    return span.in_scope(f);
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = span;
+        f()
+    }
+}
+
+///
+/// Runs the future inside the span.
+///
+pub(crate) async fn in_span<F: Future>(span: Span, future: F) -> F::Output {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+        future.instrument(span).await
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = span;
+        future.await
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::headers::{HeaderValue, Headers};
+
+    use super::TraceContext;
+
+    #[test]
+    fn test_trace_context() {
+        let mut headers = Headers::new();
+        headers.set(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        );
+
+        let trace_context = TraceContext::from_headers(&headers).unwrap();
+        assert_eq!("4bf92f3577b34da6a3ce929d0e0e4736", trace_context.trace_id);
+        assert_eq!(Some("00f067aa0ba902b7"), trace_context.parent_id.as_deref());
+        assert!(trace_context.sampled);
+
+        let traceparent = trace_context.traceparent();
+        assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(!traceparent.contains("00f067aa0ba902b7"));
+        assert_eq!(55, traceparent.len());
+
+        headers.set(
+            "traceparent",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        );
+        assert!(TraceContext::from_headers(&headers).is_none());
+    }
+}
@@ -0,0 +1,420 @@
+//!
+//! Partitioning of the requests by the tenants for the multi-tenant applications.
+//!
+//! The [`Tenancy`] middleware asks the resolvers for the tenant ID of the request and inserts the
+//! [`Tenant`] into the request extensions. Per-tenant config and state registered on the
+//! middleware is available from the tenant, so the handlers do not need to look them up.
+//!
+//! # Examples
+//!
+//! ```
+//! use racoon::core::request::Request;
+//! use racoon::core::response::{HttpResponse, Response};
+//! use racoon::core::response::status::ResponseStatus;
+//! use racoon::core::server::Server;
+//! use racoon::core::tenancy::{HeaderResolver, SubdomainResolver, Tenancy, Tenant};
+//!
+//! struct Plan {
+//!     max_users: u32,
+//! }
+//!
+//! async fn home(tenant: Tenant) -> Response {
+//!     let max_users = tenant.state::<Plan>().map(|plan| plan.max_users).unwrap_or(0);
+//!     HttpResponse::ok().body(format!("{} can have {} users", tenant.id(), max_users))
+//! }
+//!
+//! let mut server = Server::bind("127.0.0.1:8080");
+//! server.middleware(
+//!     Tenancy::new()
+//!         .resolver(SubdomainResolver::new("example.com"))
+//!         .resolver(HeaderResolver::new("X-Tenant"))
+//!         .tenant_state("acme", Plan { max_users: 10 })
+//!         .required(),
+//! );
+//! ```
+//!
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::core::extract::{ExtractResult, FromRequest};
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::{Extensions, Request};
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+use crate::racoon_debug;
+
+/// Maximum length of the tenant ID. Same as the DNS label, so it fits in the subdomain.
+const MAX_TENANT_ID_LENGTH: usize = 63;
+
+///
+/// Resolves the tenant ID of the request. Returns `None` if the request does not belong to any
+/// tenant.
+///
+pub trait AbstractTenantResolver: Send + Sync {
+    fn resolve(&self, request: &Request) -> Option<String>;
+}
+
+pub type TenantResolver = Arc<dyn AbstractTenantResolver>;
+
+impl<F> AbstractTenantResolver for F
+where
+    F: Fn(&Request) -> Option<String> + Send + Sync,
+{
+    fn resolve(&self, request: &Request) -> Option<String> {
+        self(request)
+    }
+}
+
+///
+/// Resolves the tenant from the subdomain of the base domain like `acme` from
+/// `acme.example.com`. Nested subdomains and the base domain itself are not resolved.
+///
+pub struct SubdomainResolver {
+    base_domain: String,
+}
+
+impl SubdomainResolver {
+    pub fn new<S: AsRef<str>>(base_domain: S) -> Self {
+        Self {
+            base_domain: base_domain.as_ref().trim_matches('.').to_lowercase(),
+        }
+    }
+}
+
+impl AbstractTenantResolver for SubdomainResolver {
+    fn resolve(&self, request: &Request) -> Option<String> {
+        let host = request.headers.value("Host")?.to_lowercase();
+
+        // Removes the port.
+        let host = match host.rsplit_once(':') {
+            Some((host, port)) if port.bytes().all(|byte| byte.is_ascii_digit()) => host,
+            _ => host.as_str(),
+        };
+
+        let subdomain = host.strip_suffix(&self.base_domain)?.strip_suffix('.')?;
+        if subdomain.is_empty() || subdomain.contains('.') {
+            return None;
+        }
+        Some(subdomain.to_string())
+    }
+}
+
+///
+/// Resolves the tenant from the header value like `X-Tenant: acme`.
+///
+pub struct HeaderResolver {
+    name: String,
+}
+
+impl HeaderResolver {
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+        }
+    }
+}
+
+impl AbstractTenantResolver for HeaderResolver {
+    fn resolve(&self, request: &Request) -> Option<String> {
+        let value = request.headers.value(&self.name)?;
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        Some(value.to_string())
+    }
+}
+
+///
+/// Resolves the tenant from the first path segment after the prefix like `acme` from
+/// `/t/acme/dashboard` with the prefix `/t`. The path is not rewritten, so the routes must
+/// include the tenant segment like `/t/{tenant}/dashboard`.
+///
+pub struct PathPrefixResolver {
+    prefix: String,
+}
+
+impl PathPrefixResolver {
+    pub fn new<S: AsRef<str>>(prefix: S) -> Self {
+        Self {
+            prefix: prefix.as_ref().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl AbstractTenantResolver for PathPrefixResolver {
+    fn resolve(&self, request: &Request) -> Option<String> {
+        let path = request.path.split('?').next().unwrap_or_default();
+        let remaining = path.strip_prefix(&self.prefix)?.strip_prefix('/')?;
+        let segment = remaining.split('/').next().unwrap_or_default();
+        if segment.is_empty() {
+            return None;
+        }
+        Some(segment.to_string())
+    }
+}
+
+///
+/// Tenant of the request inserted into the request extensions by the [`Tenancy`] middleware.
+///
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    id: String,
+    state: Arc<Extensions>,
+}
+
+impl Tenant {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    ///
+    /// Returns the config or state registered for this tenant with `Tenancy::tenant_state`.
+    ///
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state.get::<T>()
+    }
+
+    ///
+    /// Returns the tenant of the request if resolved.
+    ///
+    pub fn of(request: &Request) -> Option<&Tenant> {
+        request.extensions.get::<Tenant>()
+    }
+}
+
+///
+/// Extracts the tenant of the request. Responds with `404 Not Found` if the tenant is not
+/// resolved.
+///
+impl FromRequest for Tenant {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            match Tenant::of(&request) {
+                Some(tenant) => Ok(tenant.clone()),
+                None => Err(HttpResponse::not_found().body("404 Not Found") as Response),
+            }
+        }))
+    }
+}
+
+///
+/// Returns true if the tenant ID is safe to use in the hostnames, paths, cache keys and schema
+/// names. Only ASCII letters, digits, `-` and `_` are allowed.
+///
+pub fn is_valid_tenant_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_TENANT_ID_LENGTH
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+///
+/// Middleware resolving the tenant of the request. Resolvers are tried in the order they are
+/// added and the first resolved ID is used.
+///
+/// If the tenants are registered with `tenant_state`, other IDs are treated as unresolved unless
+/// `allow_unknown` is set.
+///
+#[derive(Clone)]
+pub struct Tenancy {
+    resolvers: Vec<TenantResolver>,
+    tenants: HashMap<String, Arc<Extensions>>,
+    required: bool,
+    allow_unknown: bool,
+}
+
+impl Tenancy {
+    pub fn new() -> Self {
+        Self {
+            resolvers: vec![],
+            tenants: HashMap::new(),
+            required: false,
+            allow_unknown: false,
+        }
+    }
+
+    pub fn resolver<R: AbstractTenantResolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolvers.push(Arc::new(resolver));
+        self
+    }
+
+    ///
+    /// Registers the tenant with its config or state. Can be called multiple times for the same
+    /// tenant with the values of different types.
+    ///
+    pub fn tenant_state<S: AsRef<str>, T: Send + Sync + 'static>(
+        mut self,
+        id: S,
+        value: T,
+    ) -> Self {
+        let state = self.tenants.entry(id.as_ref().to_string()).or_default();
+        Arc::make_mut(state).insert(value);
+        self
+    }
+
+    ///
+    /// Rejects the requests without the tenant with `404 Not Found`.
+    ///
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    ///
+    /// Resolves the tenants which are not registered with `tenant_state`.
+    ///
+    pub fn allow_unknown(mut self) -> Self {
+        self.allow_unknown = true;
+        self
+    }
+
+    ///
+    /// Resolves the tenant of the request.
+    ///
+    pub fn resolve(&self, request: &Request) -> Option<Tenant> {
+        let id = self
+            .resolvers
+            .iter()
+            .find_map(|resolver| resolver.resolve(request))?;
+
+        if !is_valid_tenant_id(&id) {
+            racoon_debug!("Invalid tenant ID: {:?}", id);
+            return None;
+        }
+
+        let state = match self.tenants.get(&id) {
+            Some(state) => state.clone(),
+            None if self.tenants.is_empty() || self.allow_unknown => Arc::new(Extensions::new()),
+            None => {
+                racoon_debug!("Unknown tenant: {}", id);
+                return None;
+            }
+        };
+        Some(Tenant { id, state })
+    }
+}
+
+impl Default for Tenancy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AbstractMiddleware for Tenancy {
+    fn handle(&self, mut request: Request, next: Next) -> MiddlewareResult {
+        match self.resolve(&request) {
+            Some(tenant) => request.extensions.insert(tenant),
+            None if self.required => {
+                return Box::new(Box::pin(async move {
+                    HttpResponse::not_found().body("404 Not Found") as Response
+                }));
+            }
+            None => request.extensions.remove::<Tenant>(),
+        }
+
+        Box::new(Box::pin(next.run(request)))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::path::{Path, View};
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::HttpResponse;
+    use crate::core::server::Server;
+    use crate::core::testing::request::TestRequest;
+    use crate::core::testing::TestServer;
+
+    use super::{is_valid_tenant_id, HeaderResolver, PathPrefixResolver, SubdomainResolver};
+    use super::{Tenancy, Tenant};
+
+    struct Plan(&'static str);
+
+    #[test]
+    fn test_is_valid_tenant_id() {
+        assert!(is_valid_tenant_id("acme-corp_1"));
+        assert!(!is_valid_tenant_id(""));
+        assert!(!is_valid_tenant_id("../acme"));
+        assert!(!is_valid_tenant_id(&"a".repeat(64)));
+    }
+
+    #[tokio::test]
+    async fn test_tenancy_middleware() {
+        let view: View = |request| {
+            Box::pin(async move {
+                let body = match Tenant::of(&request) {
+                    Some(tenant) => {
+                        let plan = tenant.state::<Plan>().map(|plan| plan.0).unwrap_or("none");
+                        format!("{}:{}", tenant.id(), plan)
+                    }
+                    None => "public".to_string(),
+                };
+                let response: crate::core::response::Response = HttpResponse::ok().body(body);
+                response
+            })
+        };
+
+        let mut server = Server::bind("127.0.0.1:0");
+        server
+            .middleware(
+                Tenancy::new()
+                    .resolver(SubdomainResolver::new("example.com"))
+                    .resolver(HeaderResolver::new("X-Tenant"))
+                    .resolver(PathPrefixResolver::new("/t"))
+                    .tenant_state("acme", Plan("pro"))
+                    .tenant_state("globex", Plan("free")),
+            )
+            .urls(vec![
+                Path::new("/", view),
+                Path::new("/t/{tenant}/home", view),
+            ]);
+
+        let client = TestServer::new(server).client();
+        let cases = [
+            (
+                TestRequest::get("/").header("Host", "acme.example.com:8080"),
+                "acme:pro",
+            ),
+            (
+                TestRequest::get("/").header("X-Tenant", "globex"),
+                "globex:free",
+            ),
+            (TestRequest::get("/t/acme/home?tab=1"), "acme:pro"),
+            (
+                TestRequest::get("/").header("Host", "a.b.example.com"),
+                "public",
+            ),
+            (
+                TestRequest::get("/").header("X-Tenant", "unknown"),
+                "public",
+            ),
+            (TestRequest::get("/"), "public"),
+        ];
+        for (request, expected) in cases {
+            let mut response = client.send(request).await;
+            assert_eq!(expected.as_bytes().to_vec(), *response.get_body());
+        }
+
+        let mut server = Server::bind("127.0.0.1:0");
+        server
+            .middleware(
+                Tenancy::new()
+                    .resolver(HeaderResolver::new("X-Tenant"))
+                    .required(),
+            )
+            .urls(vec![Path::new("/", view)]);
+
+        let client = TestServer::new(server).client();
+        let mut response = client
+            .send(TestRequest::get("/").header("X-Tenant", "initech"))
+            .await;
+        assert_eq!(b"initech:none".to_vec(), *response.get_body());
+
+        let response = client.send(TestRequest::get("/")).await;
+        assert_eq!(404, response.status().0);
+    }
+}
@@ -0,0 +1,145 @@
+use async_tempfile::TempFile;
+use tokio::io::AsyncWriteExt;
+
+use crate::core::forms::{FileField, Files, FormData};
+
+///
+/// Returns the file field backed by the temporary file with the given content, as received by
+/// the multipart parser. The temporary file is removed when the field is dropped.
+///
+pub async fn file_field<S: AsRef<str>, B: AsRef<[u8]>>(
+    filename: S,
+    content_type: S,
+    content: B,
+) -> std::io::Result<FileField> {
+    let temp_file = TempFile::new().await.map_err(std::io::Error::other)?;
+    let mut writer = temp_file.open_rw().await.map_err(std::io::Error::other)?;
+    writer.write_all(content.as_ref()).await?;
+    writer.flush().await?;
+
+    Ok(FileField::from(filename, temp_file).with_content_type(content_type))
+}
+
+///
+/// Builds the `FormData` and `Files` passed to the form fields while testing their validation.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::forms::FileFieldShortcut;
+/// use racoon::core::shortcuts::SingleText;
+/// use racoon::core::testing::forms::FormFixture;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let (form_data, files) = FormFixture::new()
+///     .value("title", "Logo")
+///     .file("image", "logo.png", "image/png", b"PNG")
+///     .build()
+///     .await
+///     .unwrap();
+///
+/// assert_eq!(Some(&"Logo".to_string()), form_data.value("title"));
+///
+/// let image = files.value("image").unwrap();
+/// assert_eq!(Some("image/png".to_string()), image.content_type);
+/// assert_eq!(b"PNG".to_vec(), std::fs::read(&image.temp_path).unwrap());
+/// # }
+/// ```
+///
+#[derive(Default)]
+pub struct FormFixture {
+    form_data: FormData,
+    files: Vec<(String, String, String, Vec<u8>)>,
+}
+
+impl FormFixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Adds the value to the field. Values added to the same field are kept in order.
+    ///
+    pub fn value<S: AsRef<str>>(mut self, name: S, value: S) -> Self {
+        self.form_data
+            .entry(name.as_ref().to_string())
+            .or_default()
+            .push(value.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Adds the file to the field. The temporary file is created by `build`.
+    ///
+    pub fn file<S: AsRef<str>, B: AsRef<[u8]>>(
+        mut self,
+        name: S,
+        filename: S,
+        content_type: S,
+        content: B,
+    ) -> Self {
+        self.files.push((
+            name.as_ref().to_string(),
+            filename.as_ref().to_string(),
+            content_type.as_ref().to_string(),
+            content.as_ref().to_vec(),
+        ));
+        self
+    }
+
+    pub async fn build(self) -> std::io::Result<(FormData, Files)> {
+        let mut files = Files::new();
+
+        for (name, filename, content_type, content) in self.files {
+            let file = file_field(filename, content_type, content).await?;
+            files.entry(name).or_default().push(file);
+        }
+
+        Ok((self.form_data, files))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::shortcuts::SingleText;
+    use crate::forms::fields::file_field::{FileField, UploadedFile};
+    use crate::forms::fields::input_field::InputField;
+    use crate::forms::fields::AbstractFields;
+
+    use super::FormFixture;
+
+    #[tokio::test]
+    async fn test_form_fixture() {
+        let (mut form_data, mut files) = FormFixture::new()
+            .value("tags", "rust")
+            .value("tags", "web")
+            .file("files", "a.txt", "text/plain", "First")
+            .file("files", "b.txt", "text/plain", "Second")
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(Some(&"rust".to_string()), form_data.value("tags"));
+        assert_eq!(2, form_data["tags"].len());
+
+        let mut tags_field: InputField<Vec<String>> = InputField::new("tags");
+        assert!(tags_field
+            .validate(&mut form_data, &mut files)
+            .await
+            .is_ok());
+
+        let mut files_field: FileField<Vec<UploadedFile>> = FileField::new("files");
+        assert!(files_field
+            .validate(&mut form_data, &mut files)
+            .await
+            .is_ok());
+
+        let uploaded_files = files_field.value().await;
+        assert_eq!("a.txt", uploaded_files[0].filename);
+        assert_eq!(
+            "Second",
+            std::fs::read_to_string(&uploaded_files[1].temp_path).unwrap()
+        );
+    }
+}
@@ -0,0 +1,454 @@
+pub mod forms;
+pub mod request;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use chrono::{NaiveDateTime, Utc};
+use tokio::sync::Mutex;
+
+use crate::core::headers::HeaderValue;
+use crate::core::parser::chunked::decode_chunked;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{AbstractResponse, HttpResponse, Response};
+use crate::core::server::Server;
+use crate::core::session::managers::MemorySessionManager;
+use crate::core::session::SessionManager;
+use crate::core::stream::{AbstractStream, Stream, StreamResult};
+use crate::core::testing::request::TestRequest;
+
+const BUFFER_SIZE: usize = 8096;
+
+///
+/// In-memory connection of the test client. The request bytes are read by the server and the
+/// response bytes written by the server are collected.
+///
+struct TestStream {
+    request_bytes: Mutex<Vec<u8>>,
+    restored_payload: Mutex<Option<Vec<u8>>>,
+    response_bytes: Arc<StdMutex<Vec<u8>>>,
+}
+
+impl TestStream {
+    ///
+    /// Returns the stream and the response bytes written to it.
+    ///
+    fn new(request_bytes: Vec<u8>) -> (Self, Arc<StdMutex<Vec<u8>>>) {
+        let response_bytes = Arc::new(StdMutex::new(vec![]));
+
+        let stream = Self {
+            request_bytes: Mutex::new(request_bytes),
+            restored_payload: Mutex::new(None),
+            response_bytes: response_bytes.clone(),
+        };
+        (stream, response_bytes)
+    }
+}
+
+impl AbstractStream for TestStream {
+    fn buffer_size(&self) -> StreamResult<'_, usize> {
+        Box::new(Box::pin(async move { BUFFER_SIZE }))
+    }
+
+    fn peer_addr(&self) -> StreamResult<'_, Option<String>> {
+        Box::new(Box::pin(async move { Some("127.0.0.1:0".to_string()) }))
+    }
+
+    fn restore_payload(&self, bytes: &[u8]) -> StreamResult<'_, std::io::Result<()>> {
+        let bytes = bytes.to_vec();
+
+        Box::new(Box::pin(async move {
+            *self.restored_payload.lock().await = Some(bytes);
+            Ok(())
+        }))
+    }
+
+    fn restored_len(&self) -> StreamResult<'_, usize> {
+        Box::new(Box::pin(async move {
+            match self.restored_payload.lock().await.as_ref() {
+                Some(restored_payload) => restored_payload.len(),
+                None => 0,
+            }
+        }))
+    }
+
+    fn read_chunk(&self) -> StreamResult<'_, std::io::Result<Vec<u8>>> {
+        Box::new(Box::pin(async move {
+            if let Some(payload) = self.restored_payload.lock().await.take() {
+                return Ok(payload);
+            }
+
+            let mut request_bytes = self.request_bytes.lock().await;
+            if request_bytes.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "Request is finished.",
+                ));
+            }
+
+            let length = std::cmp::min(request_bytes.len(), BUFFER_SIZE);
+            Ok(request_bytes.drain(..length).collect())
+        }))
+    }
+
+    fn write_chunk<'a>(&'a self, bytes: &'a [u8]) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            match self.response_bytes.lock() {
+                Ok(mut response_bytes) => response_bytes.extend_from_slice(bytes),
+                Err(poisoned) => poisoned.into_inner().extend_from_slice(bytes),
+            }
+            Ok(())
+        }))
+    }
+
+    fn shutdown(&self) -> StreamResult<'_, std::io::Result<()>> {
+        Box::new(Box::pin(async move { Ok(()) }))
+    }
+}
+
+///
+/// Parses the HTTP/1.1 response written by the server. Informational responses are skipped.
+///
+fn parse_response(mut bytes: Vec<u8>) -> std::io::Result<(Response, Vec<(String, String)>)> {
+    loop {
+        let mut headers = [httparse::EMPTY_HEADER; 100];
+        let mut parsed = httparse::Response::new(&mut headers);
+        let head_length = match parsed.parse(&bytes) {
+            Ok(httparse::Status::Complete(head_length)) => head_length,
+            Ok(httparse::Status::Partial) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Server closed the connection without complete response.",
+                ));
+            }
+            Err(error) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    error.to_string(),
+                ));
+            }
+        };
+
+        let status_code = parsed.code.unwrap_or(500);
+        let status_text = parsed.reason.unwrap_or_default().to_string();
+        let header_list: Vec<(String, String)> = parsed
+            .headers
+            .iter()
+            .map(|header| {
+                let value = String::from_utf8_lossy(header.value).to_string();
+                (header.name.to_string(), value)
+            })
+            .collect();
+
+        let mut body = bytes.split_off(head_length);
+        if status_code < 200 {
+            bytes = body;
+            continue;
+        }
+
+        let is_chunked = header_list.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("Transfer-Encoding") && value.eq_ignore_ascii_case("chunked")
+        });
+        if is_chunked {
+            body = decode_chunked(&body)?;
+        }
+
+        let mut response = HttpResponse::with_status(status_code as u32, &status_text).bytes(body);
+        let headers = response.get_headers();
+        headers.clear();
+        for (name, value) in &header_list {
+            headers.set_multiple(name, value);
+        }
+
+        let response: Box<dyn AbstractResponse> = response;
+        return Ok((response, header_list));
+    }
+}
+
+///
+/// Serves the requests of the test clients in-process with the routes, middlewares, error
+/// handlers and constraints of the server. The listeners of the server are not bound. Session
+/// manager set on the server is used, otherwise sessions are stored in memory.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::path::Path;
+/// use racoon::core::request::Request;
+/// use racoon::core::response::status::ResponseStatus;
+/// use racoon::core::response::{HttpResponse, Response};
+/// use racoon::core::server::Server;
+/// use racoon::core::testing::TestServer;
+/// use racoon::view;
+///
+/// async fn home(request: Request) -> Response {
+///     HttpResponse::ok().body("Home")
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.urls(vec![Path::get("/", view!(home))]);
+///
+/// let client = TestServer::new(server).client();
+/// let mut response = client.get("/").await;
+///
+/// assert_eq!(200, response.status().0);
+/// assert_eq!(b"Home".to_vec(), *response.get_body());
+/// # }
+/// ```
+///
+#[derive(Clone)]
+pub struct TestServer {
+    server: Arc<Server>,
+    session_manager: Arc<SessionManager>,
+}
+
+impl TestServer {
+    pub fn new(server: Server) -> Self {
+        let session_manager: Arc<SessionManager> = match server.custom_session_manager() {
+            Some(session_manager) => session_manager,
+            None => Arc::new(Box::new(MemorySessionManager::new())),
+        };
+
+        Self {
+            server: Arc::new(server),
+            session_manager,
+        }
+    }
+
+    ///
+    /// Client with its own cookies. Clients of the same server share the sessions.
+    ///
+    pub fn client(&self) -> TestClient {
+        TestClient {
+            server: self.clone(),
+            cookies: Arc::new(StdMutex::new(HashMap::new())),
+        }
+    }
+
+    ///
+    /// Serves the raw HTTP/1.1 request and returns the response with its header lines. The
+    /// connection is closed after the response.
+    ///
+    pub async fn dispatch(
+        &self,
+        request_bytes: Vec<u8>,
+    ) -> std::io::Result<(Response, Vec<(String, String)>)> {
+        let (stream, response_bytes) = TestStream::new(request_bytes);
+        let stream: Stream = Box::new(stream);
+
+        self.server
+            .serve_connection(stream, self.session_manager.clone())
+            .await;
+
+        let response_bytes = match response_bytes.lock() {
+            Ok(mut response_bytes) => std::mem::take(&mut *response_bytes),
+            Err(poisoned) => std::mem::take(&mut *poisoned.into_inner()),
+        };
+        parse_response(response_bytes)
+    }
+}
+
+///
+/// Sends the requests to the test server and keeps the cookies set by the responses for the next
+/// requests.
+///
+#[derive(Clone)]
+pub struct TestClient {
+    server: TestServer,
+    cookies: Arc<StdMutex<HashMap<String, String>>>,
+}
+
+impl TestClient {
+    fn cookies(&self) -> std::sync::MutexGuard<'_, HashMap<String, String>> {
+        match self.cookies.lock() {
+            Ok(cookies) => cookies,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    ///
+    /// Returns the cookie set by the previous responses.
+    ///
+    pub fn cookie<S: AsRef<str>>(&self, name: S) -> Option<String> {
+        self.cookies().get(name.as_ref()).cloned()
+    }
+
+    pub async fn get<S: AsRef<str>>(&self, path: S) -> Response {
+        self.send(TestRequest::get(path)).await
+    }
+
+    pub async fn post<S: AsRef<str>, B: AsRef<[u8]>>(
+        &self,
+        path: S,
+        content_type: &str,
+        body: B,
+    ) -> Response {
+        let request = TestRequest::post(path)
+            .header("Content-Type", content_type)
+            .body(body);
+        self.send(request).await
+    }
+
+    ///
+    /// Sends the request through the routes and middlewares of the server. Cookies stored by the
+    /// client are sent with the cookies of the request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server closes the connection without complete response.
+    ///
+    pub async fn send(&self, mut request: TestRequest) -> Response {
+        let stored_cookies: Vec<(String, String)> = self
+            .cookies()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        for (name, value) in stored_cookies {
+            request = request.cookie(name, value);
+        }
+
+        let request_bytes = request.header("Connection", "close").to_bytes();
+        let (response, header_list) = match self.server.dispatch(request_bytes).await {
+            Ok(result) => result,
+            Err(error) => panic!("Failed to receive response. Error: {}", error),
+        };
+
+        for (name, value) in header_list {
+            if name.eq_ignore_ascii_case("Set-Cookie") {
+                self.store_cookie(&value);
+            }
+        }
+        response
+    }
+
+    fn store_cookie(&self, header_value: &str) {
+        let mut parts = header_value.split(';');
+        let (name, value) = match parts.next().and_then(|cookie| cookie.split_once('=')) {
+            Some((name, value)) => (decode(name.trim()), decode(value.trim())),
+            None => return,
+        };
+
+        // Cookie is removed by setting the expiry time in the past.
+        let expired = parts.any(|attribute| {
+            let (key, attribute_value) = match attribute.split_once('=') {
+                Some((key, attribute_value)) => (key.trim(), attribute_value.trim()),
+                None => return false,
+            };
+
+            if key.eq_ignore_ascii_case("Max-Age") {
+                return attribute_value
+                    .parse::<i64>()
+                    .is_ok_and(|max_age| max_age <= 0);
+            }
+
+            if key.eq_ignore_ascii_case("Expires") {
+                return NaiveDateTime::parse_from_str(attribute_value, "%a, %d-%b-%Y %H:%M:%S GMT")
+                    .is_ok_and(|expires| expires <= Utc::now().naive_utc());
+            }
+            false
+        });
+
+        let mut cookies = self.cookies();
+        if expired {
+            cookies.remove(&name);
+        } else {
+            cookies.insert(name, value);
+        }
+    }
+}
+
+///
+/// Decodes the cookie name or value encoded by `set_cookie`. Raw value is used if decoding fails.
+///
+fn decode(value: &str) -> String {
+    match urlencoding::decode(value) {
+        Ok(decoded) => decoded.to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::cookie::set_cookie;
+    use crate::core::headers::HeaderValue;
+    use crate::core::health::Health;
+    use crate::core::openapi::OpenApi;
+    use crate::core::path::Path;
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse, Response};
+    use crate::core::server::Server;
+    use crate::core::shortcuts::SingleText;
+
+    use super::TestServer;
+
+    async fn login(request: Request) -> Response {
+        let (form_data, _) = request.parse().await;
+        let name = form_data.value("name").cloned().unwrap_or_default();
+
+        let mut response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Logged in");
+        set_cookie(
+            response.get_headers(),
+            "name",
+            &name,
+            std::time::Duration::from_secs(60),
+        );
+        response
+    }
+
+    async fn profile(request: Request) -> Response {
+        match request.cookies.value("name") {
+            Some(name) => HttpResponse::ok().body(format!("Hello {}", name)),
+            None => HttpResponse::unauthorized().body("Unauthorized"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client() {
+        let mut server = Server::bind("127.0.0.1:0");
+        server.urls(vec![
+            Path::post("/login", |request| Box::pin(login(request))),
+            Path::get("/profile", |request| Box::pin(profile(request))),
+        ]);
+
+        let client = TestServer::new(server).client();
+        assert_eq!(401, client.get("/profile").await.status().0);
+        assert_eq!(404, client.get("/missing").await.status().0);
+
+        let response = client
+            .post("/login", "application/x-www-form-urlencoded", "name=John")
+            .await;
+        assert_eq!(200, response.status().0);
+        assert_eq!(Some("John".to_string()), client.cookie("name"));
+
+        let mut response = client.get("/profile").await;
+        assert_eq!(b"Hello John".to_vec(), *response.get_body());
+        assert_eq!(
+            Some("close".to_string()),
+            response.get_headers().value("Connection")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_built_in_middlewares() {
+        let mut server = Server::bind("127.0.0.1:0");
+        server
+            .health(Health::new())
+            .openapi(OpenApi::new("Users", "1.0"))
+            .urls(vec![Path::get("/profile", |request| {
+                Box::pin(profile(request))
+            })]);
+
+        // Requests are dispatched through the same middlewares as the running server.
+        let client = TestServer::new(server).client();
+        assert_eq!(200, client.get("/healthz").await.status().0);
+        assert_eq!(200, client.get("/readyz").await.status().0);
+
+        let mut response = client.get("/openapi.json").await;
+        assert_eq!(200, response.status().0);
+        let document = String::from_utf8(response.get_body().clone()).unwrap();
+        assert!(document.contains("\"Users\""));
+    }
+}
@@ -0,0 +1,396 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::core::forms::FormConstraints;
+use crate::core::headers::{HeaderValue, Headers};
+use crate::core::parser::params;
+use crate::core::path::PathParams;
+use crate::core::request::{Extensions, Request};
+use crate::core::server::Context;
+use crate::core::session::managers::MemorySessionManager;
+use crate::core::session::SessionManager;
+use crate::core::stream::Stream;
+
+use super::TestStream;
+
+struct FilePart {
+    name: String,
+    filename: String,
+    content_type: String,
+    content: Vec<u8>,
+}
+
+///
+/// Percent-encodes the pairs as decoded by the query string and urlencoded form parsers.
+///
+fn encode_pairs(pairs: &[(String, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                urlencoding::encode(name),
+                urlencoding::encode(value)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+enum Body {
+    Empty,
+    Raw(Vec<u8>),
+    Form(Vec<(String, String)>),
+    Multipart(Vec<(String, String)>, Vec<FilePart>),
+}
+
+///
+/// Builds the request for testing the handlers. Body is encoded from the JSON value, form fields
+/// or files, so the handlers parsing the body can be tested without writing the raw payloads.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::forms::FileFieldShortcut;
+/// use racoon::core::shortcuts::SingleText;
+/// use racoon::core::testing::request::TestRequest;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let request = TestRequest::post("/upload?folder=images")
+///     .cookie("theme", "dark")
+///     .field("title", "Logo")
+///     .file("image", "logo.png", "image/png", b"PNG")
+///     .build()
+///     .await;
+///
+/// assert_eq!(Some(&"images".to_string()), request.query_params.value("folder"));
+/// assert_eq!(Some(&"dark".to_string()), request.cookies.value("theme"));
+///
+/// let (form_data, files) = request.parse().await;
+/// assert_eq!(Some(&"Logo".to_string()), form_data.value("title"));
+/// assert!(files.value("image").is_some());
+/// # }
+/// ```
+///
+pub struct TestRequest {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Headers,
+    cookies: Vec<(String, String)>,
+    path_params: PathParams,
+    state: Extensions,
+    body: Body,
+}
+
+impl TestRequest {
+    pub fn new<M: AsRef<str>, S: AsRef<str>>(method: M, path: S) -> Self {
+        Self {
+            method: method.as_ref().to_uppercase(),
+            path: path.as_ref().to_string(),
+            query: vec![],
+            headers: Headers::new(),
+            cookies: vec![],
+            path_params: PathParams::new(),
+            state: Extensions::new(),
+            body: Body::Empty,
+        }
+    }
+
+    pub fn get<S: AsRef<str>>(path: S) -> Self {
+        Self::new("GET", path)
+    }
+
+    pub fn post<S: AsRef<str>>(path: S) -> Self {
+        Self::new("POST", path)
+    }
+
+    pub fn put<S: AsRef<str>>(path: S) -> Self {
+        Self::new("PUT", path)
+    }
+
+    pub fn patch<S: AsRef<str>>(path: S) -> Self {
+        Self::new("PATCH", path)
+    }
+
+    pub fn delete<S: AsRef<str>>(path: S) -> Self {
+        Self::new("DELETE", path)
+    }
+
+    pub fn header<S: AsRef<str>>(mut self, name: &str, value: S) -> Self {
+        self.headers.set_multiple(name, value.as_ref());
+        self
+    }
+
+    pub fn cookie<S: AsRef<str>>(mut self, name: S, value: S) -> Self {
+        self.cookies
+            .push((name.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    ///
+    /// Appends the query parameter to the query string of the path.
+    ///
+    pub fn query<S: AsRef<str>>(mut self, name: S, value: S) -> Self {
+        self.query
+            .push((name.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    ///
+    /// Path parameter of the request built with `build`. The path is not matched with the routes
+    /// when the request is built without the test server.
+    ///
+    pub fn path_param<S: AsRef<str>>(mut self, name: S, value: S) -> Self {
+        self.path_params.insert(name.as_ref(), value.as_ref());
+        self
+    }
+
+    ///
+    /// Application state of the request built with `build`, as registered with
+    /// `Server::with_state`.
+    ///
+    pub fn state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.state.insert(state);
+        self
+    }
+
+    ///
+    /// Raw request body. `Content-Type` header needs to be set separately.
+    ///
+    pub fn body<B: AsRef<[u8]>>(mut self, body: B) -> Self {
+        self.body = Body::Raw(body.as_ref().to_vec());
+        self
+    }
+
+    pub fn json(self, json: serde_json::Value) -> Self {
+        self.header("Content-Type", "application/json")
+            .body(json.to_string())
+    }
+
+    ///
+    /// Adds the form field. The form is sent as `application/x-www-form-urlencoded` unless files
+    /// are added.
+    ///
+    pub fn field<S: AsRef<str>>(mut self, name: S, value: S) -> Self {
+        let field = (name.as_ref().to_string(), value.as_ref().to_string());
+
+        match &mut self.body {
+            Body::Form(fields) | Body::Multipart(fields, _) => fields.push(field),
+            _ => self.body = Body::Form(vec![field]),
+        }
+        self
+    }
+
+    ///
+    /// Adds the file field. The form is sent as `multipart/form-data`.
+    ///
+    pub fn file<S: AsRef<str>, B: AsRef<[u8]>>(
+        mut self,
+        name: S,
+        filename: S,
+        content_type: S,
+        content: B,
+    ) -> Self {
+        let file = FilePart {
+            name: name.as_ref().to_string(),
+            filename: filename.as_ref().to_string(),
+            content_type: content_type.as_ref().to_string(),
+            content: content.as_ref().to_vec(),
+        };
+
+        self.body = match std::mem::replace(&mut self.body, Body::Empty) {
+            Body::Form(fields) => Body::Multipart(fields, vec![file]),
+            Body::Multipart(fields, mut files) => {
+                files.push(file);
+                Body::Multipart(fields, files)
+            }
+            _ => Body::Multipart(vec![], vec![file]),
+        };
+        self
+    }
+
+    ///
+    /// Path with the query string added by `query`.
+    ///
+    fn raw_path(&self) -> String {
+        if self.query.is_empty() {
+            return self.path.clone();
+        }
+
+        let query = encode_pairs(&self.query);
+        let separator = if self.path.contains('?') { '&' } else { '?' };
+        format!("{}{}{}", self.path, separator, query)
+    }
+
+    ///
+    /// Returns the headers with the content type of the encoded body and the body.
+    ///
+    fn encode_body(&self) -> (Headers, Vec<u8>) {
+        let mut headers = self.headers.clone();
+
+        let body = match &self.body {
+            Body::Empty => vec![],
+            Body::Raw(body) => body.clone(),
+            Body::Form(fields) => {
+                headers.set("Content-Type", "application/x-www-form-urlencoded");
+                encode_pairs(fields).into_bytes()
+            }
+            Body::Multipart(fields, files) => {
+                let boundary = format!("RacoonTestBoundary{}", Uuid::new_v4().simple());
+                headers.set(
+                    "Content-Type",
+                    format!("multipart/form-data; boundary={}", boundary),
+                );
+
+                let mut body = vec![];
+                for (name, value) in fields {
+                    body.extend(format!("--{}\r\n", boundary).as_bytes());
+                    body.extend(
+                        format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name)
+                            .as_bytes(),
+                    );
+                    body.extend(value.as_bytes());
+                    body.extend(b"\r\n");
+                }
+
+                for file in files {
+                    body.extend(format!("--{}\r\n", boundary).as_bytes());
+                    body.extend(
+                        format!(
+                            "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n",
+                            file.name, file.filename
+                        )
+                        .as_bytes(),
+                    );
+                    body.extend(format!("Content-Type: {}\r\n\r\n", file.content_type).as_bytes());
+                    body.extend(&file.content);
+                    body.extend(b"\r\n");
+                }
+
+                body.extend(format!("--{}--\r\n", boundary).as_bytes());
+                body
+            }
+        };
+
+        if !body.is_empty() && headers.value("Content-Length").is_none() {
+            headers.set("Content-Length", body.len().to_string());
+        }
+
+        if !self.cookies.is_empty() {
+            let cookies = self
+                .cookies
+                .iter()
+                .map(|(name, value)| {
+                    format!(
+                        "{}={}",
+                        urlencoding::encode(name),
+                        urlencoding::encode(value)
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("; ");
+            headers.set_multiple("Cookie", cookies);
+        }
+
+        if headers.value("Host").is_none() {
+            headers.set("Host", "testserver");
+        }
+        (headers, body)
+    }
+
+    ///
+    /// Returns the HTTP/1.1 request with the encoded body.
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (headers, body) = self.encode_body();
+        let mut request_bytes =
+            format!("{} {} HTTP/1.1\r\n", self.method, self.raw_path()).into_bytes();
+
+        for (name, values) in &headers {
+            for value in values {
+                request_bytes.extend(format!("{}: ", name).as_bytes());
+                request_bytes.extend(value);
+                request_bytes.extend(b"\r\n");
+            }
+        }
+
+        request_bytes.extend(b"\r\n");
+        request_bytes.extend(body);
+        request_bytes
+    }
+
+    ///
+    /// Builds the request which can be passed to the handler directly. The body is read from
+    /// memory and the sessions are stored in memory.
+    ///
+    pub async fn build(self) -> Request {
+        let (headers, body) = self.encode_body();
+        let raw_path = self.raw_path();
+        let query_params = params::query_params_from_raw(&raw_path);
+        let body_read = Arc::new(AtomicBool::new(body.is_empty()));
+
+        let (stream, _) = TestStream::new(body);
+        let stream: Stream = Box::new(stream);
+        let context: Context = Box::pin(None::<String>);
+        let session_manager: SessionManager = Box::<MemorySessionManager>::default();
+
+        let mut request = Request::from(
+            Arc::new(stream),
+            Arc::new(context),
+            "http".to_string(),
+            self.method,
+            raw_path,
+            1,
+            headers,
+            self.path_params,
+            query_params,
+            Arc::new(session_manager),
+            body_read,
+            Arc::new(FormConstraints::default()),
+            Arc::new(Mutex::new(Headers::new())),
+        )
+        .await;
+        request.state = Arc::new(self.state);
+        request
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::core::shortcuts::SingleText;
+
+    use super::TestRequest;
+
+    #[tokio::test]
+    async fn test_request_body() {
+        let request = TestRequest::put("/users/{id}")
+            .query("page", "2 of 3")
+            .path_param("id", "7")
+            .json(serde_json::json!({"name": "John"}))
+            .build()
+            .await;
+
+        assert_eq!("/users/{id}?page=2%20of%203", request.path);
+        assert_eq!(
+            Some(&"2 of 3".to_string()),
+            request.query_params.value("page")
+        );
+        assert_eq!(Some(&"7".to_string()), request.path_params.value("id"));
+        assert_eq!(
+            br#"{"name":"John"}"#.to_vec(),
+            request.body().await.unwrap()
+        );
+
+        let request = TestRequest::post("/login")
+            .field("name", "John & Jane")
+            .build()
+            .await;
+        let (form_data, _) = request.parse().await;
+        assert_eq!(Some(&"John & Jane".to_string()), form_data.value("name"));
+    }
+}
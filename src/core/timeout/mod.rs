@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use crate::core::headers::HeaderValue;
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+use crate::racoon_debug;
+
+///
+/// Cancels the downstream middlewares and view if they do not respond within the duration, so
+/// slow calls made by the handler can not hold the connection forever. Unlike socket timeouts,
+/// the time is counted while the handler is running.
+///
+/// Client receives `504 Gateway Timeout` by default or `503 Service Unavailable` if configured.
+/// Connection is closed after the timeout since the request body may be partially read.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::server::Server;
+/// use racoon::core::timeout::Timeout;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(Timeout::new(Duration::from_secs(30)));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Timeout {
+    duration: Duration,
+    service_unavailable: bool,
+    retry_after: Option<Duration>,
+}
+
+impl Timeout {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            service_unavailable: false,
+            retry_after: None,
+        }
+    }
+
+    ///
+    /// Responds with `503 Service Unavailable` instead of `504 Gateway Timeout`.
+    ///
+    pub fn service_unavailable(mut self) -> Self {
+        self.service_unavailable = true;
+        self
+    }
+
+    ///
+    /// Sends `Retry-After` header with the timeout response.
+    ///
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+}
+
+impl AbstractMiddleware for Timeout {
+    fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+        let timeout = self.clone();
+
+        Box::new(Box::pin(async move {
+            if let Ok(response) = tokio::time::timeout(timeout.duration, next.run(request)).await {
+                return response;
+            }
+
+            racoon_debug!("Request handler timed out after {:?}.", timeout.duration);
+            let mut response: Response = if timeout.service_unavailable {
+                HttpResponse::service_unavailable().body("503 Service Unavailable")
+            } else {
+                HttpResponse::gateway_timeout().body("504 Gateway Timeout")
+            };
+
+            let headers = response.get_headers();
+            headers.set("Connection", "close");
+            if let Some(retry_after) = timeout.retry_after {
+                headers.set("Retry-After", retry_after.as_secs().max(1).to_string());
+            }
+            response
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::middleware::Next;
+    use crate::core::path::View;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse};
+
+    use super::Timeout;
+
+    #[tokio::test]
+    async fn test_timeout_middleware() {
+        let view: View = |request| {
+            Box::pin(async move {
+                if request.path == "/slow" {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                let response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Done");
+                response
+            })
+        };
+
+        let timeout = Timeout::new(Duration::from_millis(50));
+        let next = Next::new(Arc::new(vec![Arc::new(timeout)]), None);
+        let response = next
+            .clone()
+            .with_view(Some(view))
+            .run(request("/fast", Headers::new(), b"").await)
+            .await;
+        assert_eq!(200, response.status().0);
+
+        let response = next
+            .with_view(Some(view))
+            .run(request("/slow", Headers::new(), b"").await)
+            .await;
+        assert_eq!(504, response.status().0);
+
+        let timeout = Timeout::new(Duration::from_millis(50))
+            .service_unavailable()
+            .retry_after(Duration::from_secs(10));
+        let next = Next::new(Arc::new(vec![Arc::new(timeout)]), None);
+        let mut response = next
+            .with_view(Some(view))
+            .run(request("/slow", Headers::new(), b"").await)
+            .await;
+        assert_eq!(503, response.status().0);
+        assert_eq!(
+            Some("10".to_string()),
+            response.get_headers().value("Retry-After")
+        );
+    }
+}
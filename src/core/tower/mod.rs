@@ -0,0 +1,221 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+use crate::core::request::Request;
+use crate::core::response::status::ResponseStatus;
+use crate::core::response::{HttpResponse, Response};
+use crate::racoon_error;
+
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+pub type ResponseFuture = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+///
+/// Rest of the middleware chain and the view as `tower::Service`, so the tower layers can wrap
+/// the racoon handlers. Never fails, errors are already converted to the responses.
+///
+impl Service<Request> for Next {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = ResponseFuture;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let next = self.clone();
+        Box::pin(async move { Ok(next.run(request).await) })
+    }
+}
+
+///
+/// Inner service of the layers applied with `TowerMiddleware::layer`. Continues the middleware
+/// chain which the request was received from.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NextService;
+
+impl Service<Request> for NextService {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = ResponseFuture;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        let mut next = request
+            .extensions
+            .get::<Next>()
+            .cloned()
+            .unwrap_or_default();
+        request.extensions.remove::<Next>();
+        next.call(request)
+    }
+}
+
+fn default_error_response(error: BoxError) -> Response {
+    racoon_error!("Tower service failed: {}", error);
+    HttpResponse::service_unavailable().body("503 Service Unavailable")
+}
+
+///
+/// Runs the `tower::Service` as racoon middleware. Layers from the tower ecosystem such as
+/// timeout, concurrency limit, buffer or load shedding wrap the rest of the chain with
+/// `TowerMiddleware::layer`. Services handling the request themselves are added with
+/// `TowerMiddleware::service`.
+///
+/// Service is built once and cloned for each request, so the state of the layers such as the
+/// buffer worker or the concurrency limit is shared by all the requests. Service errors are
+/// returned as `503 Service Unavailable` unless changed with `error_response`.
+///
+/// Requires the `tower` feature.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::server::Server;
+/// use racoon::core::tower::TowerMiddleware;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.middleware(TowerMiddleware::layer(
+///     tower::timeout::TimeoutLayer::new(Duration::from_secs(30)),
+/// ));
+/// ```
+///
+#[derive(Clone)]
+pub struct TowerMiddleware<S> {
+    service: S,
+    error_response: fn(BoxError) -> Response,
+}
+
+impl<S> TowerMiddleware<S> {
+    ///
+    /// Responds with the service. Rest of the chain can still be called through `NextService`.
+    ///
+    pub fn service(service: S) -> Self {
+        Self {
+            service,
+            error_response: default_error_response,
+        }
+    }
+
+    ///
+    /// Wraps the rest of the middleware chain and the view with the layer.
+    ///
+    pub fn layer<L: Layer<NextService, Service = S>>(layer: L) -> Self {
+        Self::service(layer.layer(NextService))
+    }
+
+    ///
+    /// Sets the function converting the service error to the response.
+    ///
+    pub fn error_response(mut self, error_response: fn(BoxError) -> Response) -> Self {
+        self.error_response = error_response;
+        self
+    }
+}
+
+impl<S> AbstractMiddleware for TowerMiddleware<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + Sync + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+{
+    fn handle(&self, mut request: Request, next: Next) -> MiddlewareResult {
+        request.extensions.insert(next);
+        let mut service = self.service.clone();
+        let error_response = self.error_response;
+
+        Box::new(Box::pin(async move {
+            if let Err(error) = poll_fn(|cx| service.poll_ready(cx)).await {
+                return error_response(error.into());
+            }
+
+            match service.call(request).await {
+                Ok(response) => response,
+                Err(error) => error_response(error.into()),
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tower::ServiceExt;
+
+    use crate::core::extract::tests::request;
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::middleware::Next;
+    use crate::core::path::View;
+    use crate::core::request::Request;
+    use crate::core::response::status::ResponseStatus;
+    use crate::core::response::{AbstractResponse, HttpResponse, Response};
+
+    use super::{NextService, TowerMiddleware};
+
+    #[tokio::test]
+    async fn test_tower_middleware() {
+        let view: View = |request| {
+            Box::pin(async move {
+                if request.path == "/slow" {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+                let response: Box<dyn AbstractResponse> = HttpResponse::ok().body("Done");
+                response
+            })
+        };
+
+        let timeout =
+            TowerMiddleware::layer(tower::timeout::TimeoutLayer::new(Duration::from_millis(50)));
+        let next = Next::new(Arc::new(vec![Arc::new(timeout)]), None).with_view(Some(view));
+
+        let response = next
+            .clone()
+            .run(request("/", Headers::new(), b"").await)
+            .await;
+        assert_eq!(200, response.status().0);
+
+        let response = next
+            .clone()
+            .run(request("/slow", Headers::new(), b"").await)
+            .await;
+        assert_eq!(503, response.status().0);
+
+        // Racoon chain is a tower service.
+        let mut response = next
+            .oneshot(request("/", Headers::new(), b"").await)
+            .await
+            .unwrap();
+        assert_eq!(b"Done".to_vec(), *response.get_body());
+
+        let service = tower::service_fn(|request: Request| async move {
+            let mut response = NextService.oneshot(request).await?;
+            response.get_headers().set("X-Service", "tower");
+            Ok::<Response, Infallible>(response)
+        });
+        let middleware = TowerMiddleware::service(service);
+        let next = Next::new(Arc::new(vec![Arc::new(middleware)]), None).with_view(Some(view));
+
+        let mut response = next.run(request("/", Headers::new(), b"").await).await;
+        assert_eq!(
+            Some("tower".to_string()),
+            response.get_headers().value("X-Service")
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//!
+//! Accounting of the bytes read and written for each request on HTTP/1 connections.
+//!
+//! [`TransferSize`] is inserted into the request extensions. Bytes include the request and
+//! response heads and the chunked encoding, and are counted as they pass the socket, so streamed
+//! bodies and file responses are included. Since the default responses are written after the
+//! middlewares return, metering middlewares should read the final sizes with `on_complete`.
+//!
+//! # Examples
+//!
+//! ```
+//! use racoon::core::middleware::{AbstractMiddleware, MiddlewareResult, Next};
+//! use racoon::core::request::Request;
+//! use racoon::core::transfer::TransferSize;
+//!
+//! struct Metering;
+//!
+//! impl AbstractMiddleware for Metering {
+//!     fn handle(&self, request: Request, next: Next) -> MiddlewareResult {
+//!         if let Some(transfer) = TransferSize::of(&request) {
+//!             let path = request.path.clone();
+//!             transfer.on_complete(move |stats| {
+//!                 println!("{} used {} bytes", path, stats.bytes_read + stats.bytes_written);
+//!             });
+//!         }
+//!         Box::new(Box::pin(next.run(request)))
+//!     }
+//! }
+//! ```
+//!
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::fs::File;
+
+use crate::core::request::Request;
+use crate::core::stream::{AbstractStream, Stream, StreamResult};
+
+///
+/// Bytes read and written for the request.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferStats {
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+///
+/// Bytes read and written on the connection.
+///
+#[derive(Debug, Default)]
+pub(crate) struct ByteCounters {
+    read: AtomicU64,
+    written: AtomicU64,
+}
+
+impl ByteCounters {
+    fn stats(&self) -> TransferStats {
+        TransferStats {
+            bytes_read: self.read.load(Ordering::Relaxed),
+            bytes_written: self.written.load(Ordering::Relaxed),
+        }
+    }
+}
+
+type CompleteCallback = Box<dyn FnOnce(TransferStats) + Send>;
+
+///
+/// Size of the request and the response transferred on the connection.
+///
+#[derive(Clone)]
+pub struct TransferSize {
+    counters: Arc<ByteCounters>,
+    start: TransferStats,
+    callbacks: Arc<Mutex<Vec<CompleteCallback>>>,
+}
+
+impl std::fmt::Debug for TransferSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransferSize")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl TransferSize {
+    ///
+    /// Starts counting the request from the current position of the connection.
+    ///
+    pub(crate) fn start(counters: Arc<ByteCounters>) -> Self {
+        let start = counters.stats();
+        Self {
+            counters,
+            start,
+            callbacks: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    ///
+    /// Returns the transfer size of the request if counted.
+    ///
+    pub fn of(request: &Request) -> Option<&TransferSize> {
+        request.extensions.get::<TransferSize>()
+    }
+
+    ///
+    /// Bytes transferred for the request so far.
+    ///
+    pub fn stats(&self) -> TransferStats {
+        let current = self.counters.stats();
+        TransferStats {
+            bytes_read: current.bytes_read.saturating_sub(self.start.bytes_read),
+            bytes_written: current
+                .bytes_written
+                .saturating_sub(self.start.bytes_written),
+        }
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.stats().bytes_read
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.stats().bytes_written
+    }
+
+    ///
+    /// Calls the callback with the final sizes after the response is written or the connection
+    /// fails.
+    ///
+    pub fn on_complete<F: FnOnce(TransferStats) + Send + 'static>(&self, callback: F) {
+        if let Ok(mut callbacks) = self.callbacks.lock() {
+            callbacks.push(Box::new(callback));
+        }
+    }
+
+    pub(crate) fn complete(&self) {
+        let callbacks = match self.callbacks.lock() {
+            Ok(mut callbacks) => std::mem::take(&mut *callbacks),
+            Err(_) => return,
+        };
+
+        let stats = self.stats();
+        for callback in callbacks {
+            callback(stats);
+        }
+    }
+}
+
+///
+/// Counts the bytes passing the stream. Bytes restored to the stream are not counted as read,
+/// so the bytes of the pipelined requests are counted once.
+///
+pub(crate) struct CountingStreamWrapper {
+    stream: Stream,
+    counters: Arc<ByteCounters>,
+}
+
+impl CountingStreamWrapper {
+    pub fn new(stream: Stream, counters: Arc<ByteCounters>) -> Self {
+        Self { stream, counters }
+    }
+}
+
+impl AbstractStream for CountingStreamWrapper {
+    fn buffer_size(&self) -> StreamResult<'_, usize> {
+        self.stream.buffer_size()
+    }
+
+    fn peer_addr(&self) -> StreamResult<'_, Option<String>> {
+        self.stream.peer_addr()
+    }
+
+    fn restore_payload(&self, bytes: &[u8]) -> StreamResult<'_, std::io::Result<()>> {
+        let length = bytes.len() as u64;
+        let _ = self
+            .counters
+            .read
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |read| {
+                Some(read.saturating_sub(length))
+            });
+        self.stream.restore_payload(bytes)
+    }
+
+    fn restored_len(&self) -> StreamResult<'_, usize> {
+        self.stream.restored_len()
+    }
+
+    fn read_chunk(&self) -> StreamResult<'_, std::io::Result<Vec<u8>>> {
+        Box::new(Box::pin(async move {
+            let chunk = self.stream.read_chunk().await?;
+            self.counters
+                .read
+                .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            Ok(chunk)
+        }))
+    }
+
+    fn write_chunk<'a>(&'a self, bytes: &'a [u8]) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            self.stream.write_chunk(bytes).await?;
+            self.counters
+                .written
+                .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+            Ok(())
+        }))
+    }
+
+    fn shutdown(&self) -> StreamResult<'_, std::io::Result<()>> {
+        self.stream.shutdown()
+    }
+
+    fn send_file<'a>(
+        &'a self,
+        file: &'a mut File,
+        offset: u64,
+        length: u64,
+    ) -> StreamResult<'a, std::io::Result<()>> {
+        Box::new(Box::pin(async move {
+            self.stream.send_file(file, offset, length).await?;
+            self.counters.written.fetch_add(length, Ordering::Relaxed);
+            Ok(())
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Mutex;
+
+    use crate::core::path::{Path, View};
+    use crate::core::response::writer::ResponseWriter;
+    use crate::core::server::Server;
+    use crate::core::testing::TestServer;
+
+    use super::{TransferSize, TransferStats};
+
+    static COMPLETED: Mutex<Vec<TransferStats>> = Mutex::new(vec![]);
+
+    #[tokio::test]
+    async fn test_transfer_size() {
+        let view: View = |request| {
+            Box::pin(async move {
+                let transfer = TransferSize::of(&request).unwrap().clone();
+                transfer.on_complete(|stats| COMPLETED.lock().unwrap().push(stats));
+
+                let body = request.body().await.unwrap();
+                assert!(transfer.bytes_read() > body.len() as u64);
+
+                let mut writer = ResponseWriter::from(&request);
+                let _ = writer.write_chunk(vec![b'a'; 1000]).await;
+                let streamed = transfer.bytes_written();
+                assert!(streamed > 1000);
+
+                let response: crate::core::response::Response = writer.finish().await;
+                response
+            })
+        };
+
+        let mut server = Server::bind("127.0.0.1:0");
+        server.urls(vec![Path::new("/", view)]);
+
+        let client = TestServer::new(server).client();
+        for _ in 0..2 {
+            let response = client.post("/", "text/plain", vec![b'b'; 500]).await;
+            assert_eq!(200, response.status().0);
+        }
+
+        let completed = COMPLETED.lock().unwrap().clone();
+        assert_eq!(2, completed.len());
+        // Sizes of the second request on the keep-alive connection do not include the first.
+        assert_eq!(completed[0], completed[1]);
+        assert!(completed[0].bytes_read > 500);
+        assert!(completed[0].bytes_written > 1000);
+    }
+}
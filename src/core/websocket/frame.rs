@@ -30,6 +30,7 @@ pub struct Frame {
 }
 
 pub mod reader {
+    use std::io::ErrorKind;
     use std::sync::Arc;
 
     use crate::core::stream::Stream;
@@ -38,6 +39,19 @@ pub mod reader {
     use crate::racoon_debug;
 
     pub async fn read_frame(stream: Arc<Stream>, max_payload_size: u64) -> std::io::Result<Frame> {
+        read_frame_opt(stream, max_payload_size, false).await
+    }
+
+    ///
+    /// Reads the frame. Server must set `require_mask` since the client frames are always
+    /// masked. Protocol violations are returned as `ErrorKind::InvalidData` and the payload
+    /// larger than `max_payload_size` as `ErrorKind::InvalidInput`.
+    ///
+    pub async fn read_frame_opt(
+        stream: Arc<Stream>,
+        max_payload_size: u64,
+        require_mask: bool,
+    ) -> std::io::Result<Frame> {
         let mut buffer = vec![];
 
         // Reads first 16 bits including FIN, RSV(1, 2, 3), OPCODE and Payload length
@@ -56,6 +70,30 @@ pub mod reader {
 
         let payload_length = payload_length_to_u8(&second_byte);
 
+        // No extensions are negotiated, so reserved bits must be 0.
+        if first_byte & 0b01110000 != 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "Reserved bits must not be set.",
+            ));
+        }
+
+        // Control frames must not be fragmented and can have at most 125 bytes payload.
+        // More information: https://datatracker.ietf.org/doc/html/rfc6455#section-5.5
+        if op_code >= 8 && (fin != 1 || payload_length > 125) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid control frame.",
+            ));
+        }
+
+        if require_mask && mask_bit != 1 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "Client frame is not masked.",
+            ));
+        }
+
         // Removes two bytes read from the buffer
         buffer.drain(0..2);
 
@@ -92,7 +130,7 @@ pub mod reader {
 
         if mask_bit == 1 {
             // Bit mask bit is set to 1, so extracts masking key of 4 bytes.
-            if buffer.len() < 4 {
+            while buffer.len() < 4 {
                 let chunk = stream.read_chunk().await?;
                 buffer.extend(chunk);
             }
@@ -110,7 +148,8 @@ pub mod reader {
         }
 
         if actual_payload_length > max_payload_size {
-            return Err(std::io::Error::other(
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
                 "Payload length is more than the maximum allowed size.",
             ));
         }
@@ -310,14 +349,16 @@ pub mod builder {
             buffer.push(second_byte);
         } else if actual_payload_length < (2_usize.pow(16)) {
             // Payload length is between 126 and 65535 bytes
-            buffer.push(126); // Indicates length is in next 2 bytes
+            // Indicates length is in next 2 bytes
+            buffer.push(if mask { 126 | 0b10000000 } else { 126 });
 
             // Convert the length to 2 bytes and push them
             let length_bytes: [u8; 2] = (actual_payload_length as u16).to_be_bytes();
             buffer.extend_from_slice(&length_bytes);
         } else {
             // Payload length is greater than or equal to 65536 bytes
-            buffer.push(127); // Indicates length is in next 8 bytes
+            // Indicates length is in next 8 bytes
+            buffer.push(if mask { 127 | 0b10000000 } else { 127 });
 
             // Convert the length to 8 bytes and push them
             let length_bytes: [u8; 8] = (actual_payload_length as u64).to_be_bytes();
@@ -352,7 +393,7 @@ pub mod builder {
         use std::sync::Arc;
 
         use crate::core::stream::{AbstractStream, TestStreamWrapper};
-        use crate::core::websocket::frame::reader::read_frame;
+        use crate::core::websocket::frame::reader::{read_frame, read_frame_opt};
         use crate::core::websocket::frame::Frame;
 
         use super::build_opt;
@@ -379,5 +420,27 @@ pub mod builder {
             assert_eq!(frame.op_code, 1);
             assert_eq!(frame.payload, "Hello World".as_bytes().to_vec());
         }
+
+        #[tokio::test]
+        async fn test_frame_build_masked_extended_length() {
+            let frame = Frame {
+                fin: 1,
+                op_code: 2,
+                payload: vec![7; 300],
+            };
+
+            let test_stream_wrapper = TestStreamWrapper::new(build_opt(&frame, true), 1024);
+            let stream: Arc<Box<dyn AbstractStream + 'static>> =
+                Arc::new(Box::new(test_stream_wrapper));
+
+            let frame = read_frame_opt(stream, 1000, true).await.unwrap();
+            assert_eq!(frame.payload, vec![7; 300]);
+
+            // Server requires the client frames to be masked.
+            let test_stream_wrapper = TestStreamWrapper::new(build_opt(&frame, false), 1024);
+            let stream: Arc<Box<dyn AbstractStream + 'static>> =
+                Arc::new(Box::new(test_stream_wrapper));
+            assert!(read_frame_opt(stream, 1000, true).await.is_err());
+        }
     }
 }
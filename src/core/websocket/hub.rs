@@ -0,0 +1,739 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Weak};
+
+use base64::Engine;
+use serde_json::{json, Value};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Mutex, OnceCell, RwLock};
+
+use crate::core::websocket::{Message, WebSocket};
+use crate::{racoon_debug, racoon_error};
+
+pub type BackplaneResult<T> = Box<dyn Future<Output = T> + Send + Unpin>;
+
+/// Messages queued for a connection before it is disconnected for not keeping up.
+const MAX_QUEUED_MESSAGES: usize = 256;
+
+///
+/// Connections receiving the hub message.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    All,
+    Room(String),
+    /// Connection with the WebSocket `uid`.
+    Connection(String),
+}
+
+///
+/// Message published to the hubs through the backplane.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct HubEvent {
+    pub target: Target,
+    pub message: Message,
+}
+
+impl HubEvent {
+    ///
+    /// Encodes the event as JSON to be sent through the message broker. Binary payloads are
+    /// base64 encoded.
+    ///
+    pub fn to_json(&self) -> String {
+        let (target, name) = match &self.target {
+            Target::All => ("all", ""),
+            Target::Room(room) => ("room", room.as_str()),
+            Target::Connection(connection_id) => ("connection", connection_id.as_str()),
+        };
+
+        let base64 = |bytes: &[u8]| base64::engine::general_purpose::STANDARD.encode(bytes);
+        let (message_type, data, code) = match &self.message {
+            Message::Text(text) => ("text", text.clone(), 0),
+            Message::Binary(bytes) => ("binary", base64(bytes), 0),
+            Message::Ping(payload) => ("ping", base64(payload), 0),
+            Message::Pong(payload) => ("pong", base64(payload), 0),
+            Message::Close(code, reason) => ("close", reason.clone(), *code),
+        };
+
+        json!({
+            "target": target,
+            "name": name,
+            "type": message_type,
+            "data": data,
+            "code": code,
+        })
+        .to_string()
+    }
+
+    pub fn from_json(json: &str) -> std::io::Result<Self> {
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid hub event.");
+
+        let value: Value = serde_json::from_str(json).map_err(|_| invalid())?;
+        let field = |name: &str| value.get(name).and_then(Value::as_str).ok_or_else(invalid);
+        let name = field("name")?.to_string();
+        let data = field("data")?;
+
+        let target = match field("target")? {
+            "all" => Target::All,
+            "room" => Target::Room(name),
+            "connection" => Target::Connection(name),
+            _ => return Err(invalid()),
+        };
+
+        let base64 = |data: &str| {
+            base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|_| invalid())
+        };
+        let message = match field("type")? {
+            "text" => Message::Text(data.to_string()),
+            "binary" => Message::Binary(base64(data)?),
+            "ping" => Message::Ping(base64(data)?),
+            "pong" => Message::Pong(base64(data)?),
+            "close" => {
+                let code = value
+                    .get("code")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(invalid)?;
+                Message::Close(code as u16, data.to_string())
+            }
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self { target, message })
+    }
+}
+
+///
+/// Delivers the hub events between the server instances. Each hub subscribes once and delivers
+/// the received events to its local connections, so events published by the hub are received
+/// back by its own subscription.
+///
+pub trait AbstractBackplane: Sync + Send {
+    fn publish(&self, event: HubEvent) -> BackplaneResult<std::io::Result<()>>;
+    fn subscribe(&self) -> BackplaneResult<std::io::Result<mpsc::UnboundedReceiver<HubEvent>>>;
+}
+
+pub type Backplane = Box<dyn AbstractBackplane>;
+
+///
+/// In-process backplane. Hubs sharing the cloned backplane receive each other's events, but
+/// events are not shared between the server instances.
+///
+#[derive(Clone)]
+pub struct MemoryBackplane {
+    subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<HubEvent>>>>,
+}
+
+impl Default for MemoryBackplane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryBackplane {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(vec![])),
+        }
+    }
+}
+
+impl AbstractBackplane for MemoryBackplane {
+    fn publish(&self, event: HubEvent) -> BackplaneResult<std::io::Result<()>> {
+        let subscribers_ref = self.subscribers.clone();
+
+        Box::new(Box::pin(async move {
+            let mut subscribers = subscribers_ref.lock().await;
+            subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+            Ok(())
+        }))
+    }
+
+    fn subscribe(&self) -> BackplaneResult<std::io::Result<mpsc::UnboundedReceiver<HubEvent>>> {
+        let subscribers_ref = self.subscribers.clone();
+
+        Box::new(Box::pin(async move {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            subscribers_ref.lock().await.push(sender);
+            Ok(receiver)
+        }))
+    }
+}
+
+///
+/// Backplane delivering the events through Redis pub/sub, so the broadcasts reach the
+/// connections of all the server instances subscribed to the channel. Available with the `redis`
+/// feature.
+///
+/// Events published while the subscription is reconnecting are not received.
+///
+/// # Examples
+///
+/// ```no_run
+/// use racoon::core::websocket::hub::{Hub, RedisBackplane};
+///
+/// # async fn hub() -> std::io::Result<Hub> {
+/// let backplane = RedisBackplane::new("redis://127.0.0.1/", "racoon:hub").await?;
+/// let hub = Hub::with_backplane(backplane);
+/// # Ok(hub)
+/// # }
+/// ```
+///
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisBackplane {
+    client: redis::Client,
+    connection: redis::aio::ConnectionManager,
+    channel: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisBackplane {
+    pub async fn new<S: AsRef<str>>(url: S, channel: S) -> std::io::Result<Self> {
+        let client = redis::Client::open(url.as_ref()).map_err(std::io::Error::other)?;
+        let connection = redis::aio::ConnectionManager::new(client.clone())
+            .await
+            .map_err(std::io::Error::other)?;
+
+        Ok(Self {
+            client,
+            connection,
+            channel: channel.as_ref().to_string(),
+        })
+    }
+
+    async fn pubsub(
+        client: &redis::Client,
+        channel: &str,
+    ) -> redis::RedisResult<redis::aio::PubSub> {
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.subscribe(channel).await?;
+        Ok(pubsub)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl AbstractBackplane for RedisBackplane {
+    fn publish(&self, event: HubEvent) -> BackplaneResult<std::io::Result<()>> {
+        let mut connection = self.connection.clone();
+        let channel = self.channel.clone();
+
+        Box::new(Box::pin(async move {
+            redis::cmd("PUBLISH")
+                .arg(&channel)
+                .arg(event.to_json())
+                .query_async::<_, ()>(&mut connection)
+                .await
+                .map_err(std::io::Error::other)
+        }))
+    }
+
+    fn subscribe(&self) -> BackplaneResult<std::io::Result<mpsc::UnboundedReceiver<HubEvent>>> {
+        let client = self.client.clone();
+        let channel = self.channel.clone();
+
+        Box::new(Box::pin(async move {
+            use tokio_stream::StreamExt;
+
+            let pubsub = Self::pubsub(&client, &channel)
+                .await
+                .map_err(std::io::Error::other)?;
+            let (sender, receiver) = mpsc::unbounded_channel();
+
+            tokio::spawn(async move {
+                let mut messages = Box::pin(pubsub.into_on_message());
+
+                loop {
+                    while let Some(message) = messages.next().await {
+                        let payload: String = match message.get_payload() {
+                            Ok(payload) => payload,
+                            Err(error) => {
+                                racoon_error!("Invalid hub event payload. Error: {}", error);
+                                continue;
+                            }
+                        };
+
+                        match HubEvent::from_json(&payload) {
+                            Ok(event) => {
+                                if sender.send(event).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(error) => racoon_error!("{}", error),
+                        }
+                    }
+
+                    // Connection is lost, so subscribes again until the hub is dropped.
+                    loop {
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        if sender.is_closed() {
+                            return;
+                        }
+
+                        match Self::pubsub(&client, &channel).await {
+                            Ok(pubsub) => {
+                                messages = Box::pin(pubsub.into_on_message());
+                                break;
+                            }
+                            Err(error) => {
+                                racoon_error!("Failed to subscribe to Redis. Error: {}", error)
+                            }
+                        }
+                    }
+                }
+            });
+
+            Ok(receiver)
+        }))
+    }
+}
+
+struct HubConnection {
+    /// Outgoing messages written by the writer task.
+    sender: mpsc::Sender<Message>,
+    websocket: WebSocket,
+}
+
+struct HubState {
+    /// Connections by their `uid`.
+    connections: HashMap<String, HubConnection>,
+    rooms: HashMap<String, HashSet<String>>,
+}
+
+struct HubInner {
+    backplane: Backplane,
+    state: RwLock<HubState>,
+    subscribed: OnceCell<()>,
+}
+
+///
+/// Registry of the WebSocket connections and rooms. Messages are queued for each connection, so
+/// slow clients don't delay the broadcast to others. Connections failing to receive are removed,
+/// and the connections with 256 queued messages are closed with `1008` code.
+///
+/// Hub delivers messages to the connections of this server only. Use `with_backplane` to reach
+/// the connections of other server instances, for example through the message broker.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::extract::State;
+/// use racoon::core::path::Path;
+/// use racoon::core::server::Server;
+/// use racoon::core::websocket::hub::Hub;
+/// use racoon::core::websocket::{Message, WebSocket};
+/// use racoon::handler;
+///
+/// async fn chat(State(hub): State<Hub>, websocket: WebSocket) -> WebSocket {
+///     hub.join("lobby", &websocket).await;
+///
+///     while let Some(message) = websocket.recv().await {
+///         if let Message::Text(text) = message {
+///             let _ = hub.broadcast("lobby", Message::Text(text)).await;
+///         }
+///     }
+///
+///     // Removes the connection from all the rooms.
+///     hub.unregister(&websocket.uid).await;
+///     websocket
+/// }
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server
+///     .with_state(Hub::new())
+///     .urls(vec![Path::new("/chat/", handler!(chat))]);
+/// ```
+///
+#[derive(Clone)]
+pub struct Hub {
+    inner: Arc<HubInner>,
+}
+
+impl Default for Hub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hub {
+    pub fn new() -> Self {
+        Self::with_backplane(MemoryBackplane::new())
+    }
+
+    pub fn with_backplane<B: AbstractBackplane + 'static>(backplane: B) -> Self {
+        Self {
+            inner: Arc::new(HubInner {
+                backplane: Box::new(backplane),
+                state: RwLock::new(HubState {
+                    connections: HashMap::new(),
+                    rooms: HashMap::new(),
+                }),
+                subscribed: OnceCell::new(),
+            }),
+        }
+    }
+
+    ///
+    /// Adds the connection, so it receives the messages sent to all and directly to its `uid`.
+    /// Does nothing if the connection is already registered.
+    ///
+    pub async fn register(&self, websocket: &WebSocket) {
+        self.subscribe().await;
+
+        let mut state = self.inner.state.write().await;
+        if state.connections.contains_key(&websocket.uid) {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel(MAX_QUEUED_MESSAGES);
+        let connection = HubConnection {
+            sender,
+            websocket: websocket.clone(),
+        };
+        state.connections.insert(websocket.uid.clone(), connection);
+        tokio::spawn(write_messages(
+            Arc::downgrade(&self.inner),
+            websocket.clone(),
+            receiver,
+        ));
+    }
+
+    ///
+    /// Removes the connection from the hub and all of its rooms.
+    ///
+    pub async fn unregister(&self, connection_id: &str) {
+        remove_connection(&self.inner, connection_id).await;
+    }
+
+    ///
+    /// Adds the connection to the room. The connection is registered if not registered yet.
+    ///
+    pub async fn join<S: AsRef<str>>(&self, room: S, websocket: &WebSocket) {
+        self.register(websocket).await;
+
+        let mut state = self.inner.state.write().await;
+        state
+            .rooms
+            .entry(room.as_ref().to_string())
+            .or_default()
+            .insert(websocket.uid.clone());
+    }
+
+    pub async fn leave<S: AsRef<str>>(&self, room: S, connection_id: &str) {
+        let mut state = self.inner.state.write().await;
+        let room = room.as_ref();
+
+        if let Some(members) = state.rooms.get_mut(room) {
+            members.remove(connection_id);
+            if members.is_empty() {
+                state.rooms.remove(room);
+            }
+        }
+    }
+
+    ///
+    /// Returns the `uid` of the connections in the room on this server.
+    ///
+    pub async fn members<S: AsRef<str>>(&self, room: S) -> Vec<String> {
+        let state = self.inner.state.read().await;
+        match state.rooms.get(room.as_ref()) {
+            Some(members) => members.iter().cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    pub async fn broadcast<S: AsRef<str>>(&self, room: S, message: Message) -> std::io::Result<()> {
+        self.publish(Target::Room(room.as_ref().to_string()), message)
+            .await
+    }
+
+    pub async fn broadcast_all(&self, message: Message) -> std::io::Result<()> {
+        self.publish(Target::All, message).await
+    }
+
+    pub async fn send_to(&self, connection_id: &str, message: Message) -> std::io::Result<()> {
+        self.publish(Target::Connection(connection_id.to_string()), message)
+            .await
+    }
+
+    async fn publish(&self, target: Target, message: Message) -> std::io::Result<()> {
+        self.subscribe().await;
+        self.inner
+            .backplane
+            .publish(HubEvent { target, message })
+            .await
+    }
+
+    ///
+    /// Subscribes to the backplane once and delivers the received events until the hub is
+    /// dropped. Failed subscription is retried by the next call.
+    ///
+    async fn subscribe(&self) {
+        let subscribed = self
+            .inner
+            .subscribed
+            .get_or_try_init(|| async {
+                let mut receiver = self.inner.backplane.subscribe().await?;
+
+                let hub = Arc::downgrade(&self.inner);
+                tokio::spawn(async move {
+                    while let Some(event) = receiver.recv().await {
+                        match hub.upgrade() {
+                            Some(hub) => deliver(&hub, event).await,
+                            None => break,
+                        }
+                    }
+                });
+                Ok::<(), std::io::Error>(())
+            })
+            .await;
+
+        if let Err(error) = subscribed {
+            racoon_error!("Failed to subscribe to the hub backplane. Error: {}", error);
+        }
+    }
+}
+
+async fn deliver(hub: &HubInner, event: HubEvent) {
+    let mut lagging = vec![];
+
+    {
+        let state = hub.state.read().await;
+
+        let mut send = |connection_id: &String| {
+            if let Some(connection) = state.connections.get(connection_id) {
+                if let Err(TrySendError::Full(_)) =
+                    connection.sender.try_send(event.message.clone())
+                {
+                    lagging.push(connection_id.clone());
+                }
+            }
+        };
+
+        match &event.target {
+            Target::All => state.connections.keys().for_each(&mut send),
+            Target::Room(room) => {
+                if let Some(members) = state.rooms.get(room) {
+                    members.iter().for_each(&mut send);
+                }
+            }
+            Target::Connection(connection_id) => send(connection_id),
+        }
+    }
+
+    // Slow connections are closed instead of queueing unlimited messages in memory.
+    for connection_id in lagging {
+        if let Some(connection) = remove_connection(hub, &connection_id).await {
+            racoon_debug!(
+                "Closing hub connection {} for not keeping up.",
+                connection_id
+            );
+            tokio::spawn(async move {
+                let _ = connection
+                    .websocket
+                    .close(1008, "Too many queued messages.")
+                    .await;
+            });
+        }
+    }
+}
+
+async fn remove_connection(hub: &HubInner, connection_id: &str) -> Option<HubConnection> {
+    let mut state = hub.state.write().await;
+
+    // Dropping the sender stops the writer task.
+    let connection = state.connections.remove(connection_id);
+    state.rooms.retain(|_, members| {
+        members.remove(connection_id);
+        !members.is_empty()
+    });
+    connection
+}
+
+///
+/// Sends the queued messages to the connection in order.
+///
+async fn write_messages(
+    hub: Weak<HubInner>,
+    websocket: WebSocket,
+    mut receiver: mpsc::Receiver<Message>,
+) {
+    while let Some(message) = receiver.recv().await {
+        if let Err(error) = websocket.send(message).await {
+            racoon_debug!("Failed to send hub message. Error: {}", error);
+
+            if let Some(hub) = hub.upgrade() {
+                remove_connection(&hub, &websocket.uid).await;
+            }
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    use crate::core::stream::{Stream, TcpStreamWrapper};
+    use crate::core::websocket::frame::reader;
+    use crate::core::websocket::{Message, WebSocket, WebSocketConfig};
+
+    use super::{
+        deliver, AbstractBackplane, BackplaneResult, Hub, HubConnection, HubEvent, MemoryBackplane,
+        Target, MAX_QUEUED_MESSAGES,
+    };
+
+    ///
+    /// Backplane failing the first subscription.
+    ///
+    struct FlakyBackplane {
+        backplane: MemoryBackplane,
+        attempts: Arc<AtomicUsize>,
+    }
+
+    impl AbstractBackplane for FlakyBackplane {
+        fn publish(&self, event: HubEvent) -> BackplaneResult<std::io::Result<()>> {
+            self.backplane.publish(event)
+        }
+
+        fn subscribe(&self) -> BackplaneResult<std::io::Result<mpsc::UnboundedReceiver<HubEvent>>> {
+            if self.attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                return Box::new(Box::pin(async {
+                    Err(std::io::Error::other("Broker is unavailable."))
+                }));
+            }
+            self.backplane.subscribe()
+        }
+    }
+
+    async fn connect() -> (WebSocket, Arc<Stream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let server: Stream = Box::new(TcpStreamWrapper::from(server, 8096).unwrap());
+        let client: Stream = Box::new(TcpStreamWrapper::from(client, 8096).unwrap());
+        let websocket = WebSocket::new(Arc::new(server), WebSocketConfig::new());
+        (websocket, Arc::new(client))
+    }
+
+    async fn receive_text(client: &Arc<Stream>) -> String {
+        let frame = reader::read_frame(client.clone(), 1024).await.unwrap();
+        String::from_utf8(frame.payload).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hub() {
+        let hub = Hub::new();
+        let (first, first_client) = connect().await;
+        let (second, second_client) = connect().await;
+
+        hub.join("chat", &first).await;
+        hub.register(&second).await;
+        assert_eq!(vec![first.uid.clone()], hub.members("chat").await);
+
+        hub.broadcast("chat", Message::Text("room".to_string()))
+            .await
+            .unwrap();
+        hub.send_to(&second.uid, Message::Text("direct".to_string()))
+            .await
+            .unwrap();
+        hub.broadcast_all(Message::Text("all".to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!("room", receive_text(&first_client).await);
+        assert_eq!("all", receive_text(&first_client).await);
+        assert_eq!("direct", receive_text(&second_client).await);
+        assert_eq!("all", receive_text(&second_client).await);
+
+        hub.unregister(&first.uid).await;
+        assert!(hub.members("chat").await.is_empty());
+    }
+    #[test]
+    fn test_hub_event_json() {
+        let events = [
+            HubEvent {
+                target: Target::All,
+                message: Message::Text("Hello".to_string()),
+            },
+            HubEvent {
+                target: Target::Room("chat".to_string()),
+                message: Message::Binary(vec![0, 255]),
+            },
+            HubEvent {
+                target: Target::Connection("uid".to_string()),
+                message: Message::Close(1000, "Bye".to_string()),
+            },
+        ];
+
+        for event in events {
+            assert_eq!(event, HubEvent::from_json(&event.to_json()).unwrap());
+        }
+        assert!(HubEvent::from_json("{}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hub_subscribe_retry() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let hub = Hub::with_backplane(FlakyBackplane {
+            backplane: MemoryBackplane::new(),
+            attempts: attempts.clone(),
+        });
+        let (websocket, client) = connect().await;
+
+        hub.register(&websocket).await;
+        assert_eq!(1, attempts.load(Ordering::Relaxed));
+
+        // Failed subscription is not cached, so the events are delivered after the retry.
+        for text in ["first", "second"] {
+            hub.broadcast_all(Message::Text(text.to_string()))
+                .await
+                .unwrap();
+            let received = tokio::time::timeout(Duration::from_secs(3), receive_text(&client));
+            assert_eq!(text, received.await.expect("Event is not delivered."));
+        }
+        assert_eq!(2, attempts.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn test_hub_slow_connection() {
+        let hub = Hub::new();
+        let (websocket, client) = connect().await;
+
+        // Queue is not read, like the connection of the client not receiving the messages.
+        let (sender, _receiver) = mpsc::channel(MAX_QUEUED_MESSAGES);
+        let connection = HubConnection {
+            sender,
+            websocket: websocket.clone(),
+        };
+        let mut state = hub.inner.state.write().await;
+        state.connections.insert(websocket.uid.clone(), connection);
+        drop(state);
+
+        for _ in 0..=MAX_QUEUED_MESSAGES {
+            let event = HubEvent {
+                target: Target::All,
+                message: Message::Text("update".to_string()),
+            };
+            deliver(&hub.inner, event).await;
+        }
+
+        let state = hub.inner.state.read().await;
+        assert!(!state.connections.contains_key(&websocket.uid));
+        drop(state);
+
+        let frame = reader::read_frame(client, 1024).await.unwrap();
+        assert_eq!(8, frame.op_code);
+        assert_eq!(1008u16.to_be_bytes(), frame.payload[..2]);
+    }
+}
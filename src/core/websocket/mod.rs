@@ -1,39 +1,206 @@
 pub mod frame;
+pub mod hub;
 
+use std::io::ErrorKind;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
 
 use base64::Engine;
 use serde_json::Value;
 use sha1::{Digest, Sha1};
+use tokio::sync::{watch, Mutex};
 use uuid::Uuid;
 
+use crate::core::extract::{ExtractResult, FromRequest, IntoResponse};
 use crate::core::headers::{HeaderValue, Headers};
 use crate::core::request::Request;
 use crate::core::response::status::ResponseStatus;
-use crate::core::response::{response_to_bytes, AbstractResponse, HttpResponse};
+use crate::core::response::{response_to_bytes, AbstractResponse, HttpResponse, Response};
+use crate::core::server::ShutdownSignal;
+use crate::core::stream::timeout::ProtocolSwitch;
 use crate::core::stream::Stream;
 use crate::core::websocket::frame::{reader, Frame};
 use crate::{racoon_debug, racoon_error};
 
 const DEFAULT_MAX_PAYLOAD_SIZE: u64 = 5 * 1024 * 1024; // 5 MiB
 
+/// Version of the protocol defined in RFC 6455, which is the only supported version.
+const WEBSOCKET_VERSION: &str = "13";
+
+/// Duration for which the client's close frame is waited after closing the connection on shutdown.
+const SHUTDOWN_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Opcode and payload of the fragmented message received so far.
+type Fragments = Option<(u8, Vec<u8>)>;
+
+///
+/// Heartbeat, idle and size limits of the WebSocket connections. By default, the server pings
+/// every 10 seconds and closes the connection if the client doesn't respond within 10 seconds.
+/// Frames and messages are limited to 5 MiB.
+///
+/// Frames are received by `recv()`, so the connection needs to be read to receive the pongs.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use racoon::core::server::Server;
+/// use racoon::core::websocket::WebSocketConfig;
+///
+/// let mut server = Server::bind("127.0.0.1:8080");
+/// server.websocket_config(
+///     WebSocketConfig::new()
+///         .heartbeat(Duration::from_secs(30), Duration::from_secs(10))
+///         .idle_timeout(Duration::from_secs(300))
+///         .max_frame_size(64 * 1024)
+///         .max_message_size(1024 * 1024),
+/// );
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct WebSocketConfig {
+    ping_interval: Option<Duration>,
+    pong_timeout: Duration,
+    idle_timeout: Option<Duration>,
+    max_frame_size: u64,
+    max_message_size: u64,
+    protocols: Vec<String>,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebSocketConfig {
+    pub fn new() -> Self {
+        Self {
+            ping_interval: Some(Duration::from_secs(10)),
+            pong_timeout: Duration::from_secs(10),
+            idle_timeout: None,
+            max_frame_size: DEFAULT_MAX_PAYLOAD_SIZE,
+            max_message_size: DEFAULT_MAX_PAYLOAD_SIZE,
+            protocols: vec![],
+        }
+    }
+
+    ///
+    /// Sends the ping at the interval. Connection is closed if no frame is received from the
+    /// client within `pong_timeout` after the ping.
+    ///
+    pub fn heartbeat(mut self, ping_interval: Duration, pong_timeout: Duration) -> Self {
+        self.ping_interval = Some(ping_interval);
+        self.pong_timeout = pong_timeout;
+        self
+    }
+
+    pub fn disable_heartbeat(mut self) -> Self {
+        self.ping_interval = None;
+        self
+    }
+
+    ///
+    /// Closes the connection with code `1001` if the client sends no text or binary message
+    /// within the duration. Heartbeat frames don't keep the connection from being idle.
+    ///
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    ///
+    /// Closes the connection with code `1009` if the client sends the larger frame. The frame
+    /// is rejected from its header, so the payload is not read to the memory.
+    ///
+    pub fn max_frame_size(mut self, size: u64) -> Self {
+        self.max_frame_size = size;
+        self
+    }
+
+    ///
+    /// Closes the connection with code `1009` if the text or binary message assembled from the
+    /// fragments exceeds the size.
+    ///
+    pub fn max_message_size(mut self, size: u64) -> Self {
+        self.max_message_size = size;
+        self
+    }
+
+    ///
+    /// Subprotocols supported by the server in the order of preference. The first one offered by
+    /// the client in the `Sec-WebSocket-Protocol` header is selected and returned by
+    /// `WebSocket::protocol()`.
+    ///
+    pub fn protocols<S: AsRef<str>>(mut self, protocols: &[S]) -> Self {
+        self.protocols = protocols
+            .iter()
+            .map(|protocol| protocol.as_ref().to_string())
+            .collect();
+        self
+    }
+}
+
+///
+/// WebSocket message. Fragmented text and binary messages are received as a single message.
+///
+#[derive(Debug, Clone, PartialEq)]
 pub enum Message {
-    Continue(Vec<u8>),
     Text(String),
     Binary(Vec<u8>),
+    /// Close code and reason. Code `1005` is received if the close frame has no code and `1006`
+    /// if the connection is lost without the close frame.
     Close(u16, String),
-    Ping(),
-    Pong(),
-    Others(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
 }
 
+///
+/// WebSocket connection upgraded from the request. Can be used as the handler argument, in which
+/// case the handshake is done before calling the handler and the invalid upgrade requests are
+/// rejected.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::core::path::Path;
+/// use racoon::core::websocket::{Message, WebSocket};
+/// use racoon::handler;
+///
+/// async fn echo(websocket: WebSocket) -> WebSocket {
+///     while let Some(message) = websocket.recv().await {
+///         match message {
+///             Message::Text(_) | Message::Binary(_) => {
+///                 let _ = websocket.send(message).await;
+///             }
+///             _ => {}
+///         }
+///     }
+///     websocket
+/// }
+///
+/// let paths = vec![
+///     Path::new("/ws/", handler!(echo)),
+/// ];
+/// ```
+///
 pub struct WebSocket {
     pub uid: String,
     stream: Arc<Stream>,
     request_validated: bool,
     receive_next: Arc<AtomicBool>,
+    close_sent: Arc<AtomicBool>,
+    fragments: Arc<Mutex<Fragments>>,
+    config: WebSocketConfig,
+    /// Time when the last frame and the last message frame are received.
+    last_received: Arc<StdMutex<(Instant, Instant)>>,
+    /// Set when the connection is closed by the heartbeat.
+    closed: Arc<watch::Sender<bool>>,
+    /// Connection is closed with `1001 Going Away` when the server shuts down.
+    shutdown: Option<ShutdownSignal>,
+    protocol: Option<String>,
     headers: Headers,
     body: Vec<u8>,
 }
@@ -43,8 +210,15 @@ impl Clone for WebSocket {
         Self {
             uid: self.uid.clone(),
             stream: self.stream.clone(),
-            request_validated: self.request_validated.clone(),
+            request_validated: self.request_validated,
             receive_next: self.receive_next.clone(),
+            close_sent: self.close_sent.clone(),
+            fragments: self.fragments.clone(),
+            config: self.config.clone(),
+            last_received: self.last_received.clone(),
+            closed: self.closed.clone(),
+            shutdown: self.shutdown.clone(),
+            protocol: self.protocol.clone(),
             headers: self.headers.clone(),
             body: self.body.clone(),
         }
@@ -73,59 +247,181 @@ impl AbstractResponse for WebSocket {
     }
 }
 
+impl FromRequest for WebSocket {
+    fn from_request(request: Request) -> ExtractResult<Self> {
+        Box::new(Box::pin(async move {
+            // Client is told the supported version, so it can retry the handshake.
+            // More information: https://datatracker.ietf.org/doc/html/rfc6455#section-4.4
+            if let Some(version) = request.headers.value("Sec-WebSocket-Version") {
+                if version.trim() != WEBSOCKET_VERSION {
+                    let mut response = HttpResponse::upgrade_required();
+                    response
+                        .get_headers()
+                        .set("Sec-WebSocket-Version", WEBSOCKET_VERSION);
+                    return Err(response.body("Unsupported WebSocket version.") as Response);
+                }
+            }
+
+            let (websocket, connected) = WebSocket::from(&request).await;
+            if !connected {
+                return Err(HttpResponse::bad_request().body("Bad Request") as Response);
+            }
+            Ok(websocket)
+        }))
+    }
+}
+
+impl IntoResponse for WebSocket {
+    fn into_response(self) -> Response {
+        Box::new(self)
+    }
+}
+
 impl WebSocket {
+    fn new(stream: Arc<Stream>, config: WebSocketConfig) -> Self {
+        Self {
+            uid: Uuid::new_v4().to_string(),
+            stream,
+            request_validated: false,
+            receive_next: Arc::new(AtomicBool::new(false)),
+            close_sent: Arc::new(AtomicBool::new(false)),
+            fragments: Arc::new(Mutex::new(None)),
+            config,
+            last_received: Arc::new(StdMutex::new((Instant::now(), Instant::now()))),
+            closed: Arc::new(watch::Sender::new(false)),
+            shutdown: None,
+            protocol: None,
+            headers: Headers::new(),
+            body: Vec::new(),
+        }
+    }
+
+    ///
+    /// Upgrades the request using the config set with `Server::websocket_config` or the default
+    /// config.
+    ///
     pub async fn from(request: &Request) -> (Self, bool) {
-        Self::from_opt(request, true).await
+        let config = match request.extensions.get::<WebSocketConfig>() {
+            Some(config) => config.clone(),
+            None => WebSocketConfig::new(),
+        };
+        Self::from_config(request, config).await
     }
 
     pub async fn from_opt(request: &Request, periodic_ping: bool) -> (Self, bool) {
-        let instance = match WebSocket::validate(request).await {
-            Ok(instance) => instance,
+        if periodic_ping {
+            return Self::from(request).await;
+        }
+        Self::from_config(request, WebSocketConfig::new().disable_heartbeat()).await
+    }
+
+    pub async fn from_config(request: &Request, config: WebSocketConfig) -> (Self, bool) {
+        let mut instance = Self::new(request.stream.clone(), config);
+        instance.shutdown = request.extensions.get::<ShutdownSignal>().cloned();
+
+        if let Err(error) = instance.accept(request).await {
+            racoon_error!("WS Error: {}", error);
+            return (instance, false);
+        }
+
+        if let Some(ping_interval) = instance.config.ping_interval {
+            tokio::spawn(instance.clone().heartbeat(ping_interval));
+        }
+
+        (instance, true)
+    }
+
+    ///
+    /// Subprotocol selected during the handshake.
+    ///
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    async fn accept(&mut self, request: &Request) -> Result<(), String> {
+        let sec_websocket_key = Self::validate(request)?;
+        let protocol = Self::select_protocol(&request.headers, &self.config.protocols);
+
+        match Self::handshake(
+            request.stream.clone(),
+            &sec_websocket_key,
+            protocol.as_deref(),
+        )
+        .await
+        {
+            Ok(()) => {}
             Err(error) => {
-                racoon_error!("WS Error: {}", error);
-
-                let failed = Self {
-                    uid: Uuid::new_v4().to_string(),
-                    stream: request.stream.clone(),
-                    request_validated: false,
-                    receive_next: Arc::new(AtomicBool::new(true)),
-                    headers: Headers::new(),
-                    body: Vec::new(),
-                };
-                return (failed, false);
+                return Err(format!("Failed to handshake. {}", error));
             }
         };
 
-        if periodic_ping {
-            instance.ping_with_interval(Duration::from_secs(10)).await;
+        // Connection stays idle between the messages after the handshake.
+        if let Some(protocol_switch) = request.extensions.get::<ProtocolSwitch>() {
+            protocol_switch.switched();
         }
 
-        (instance, true)
+        self.protocol = protocol;
+        self.request_validated = true;
+        self.receive_next.store(true, Ordering::Relaxed);
+        Ok(())
     }
 
-    async fn validate(request: &Request) -> Result<Self, String> {
+    ///
+    /// More information: <https://datatracker.ietf.org/doc/html/rfc6455#section-4.2.2>
+    ///
+    fn select_protocol(headers: &Headers, supported: &[String]) -> Option<String> {
+        // Protocols can be offered in the multiple headers and the comma separated values.
+        let offered: Vec<String> = headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Sec-WebSocket-Protocol"))
+            .flat_map(|(_, values)| values.iter())
+            .flat_map(|value| {
+                String::from_utf8_lossy(value)
+                    .split(',')
+                    .map(|protocol| protocol.trim().to_string())
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+
+        supported
+            .iter()
+            .find(|protocol| offered.contains(protocol))
+            .cloned()
+    }
+
+    ///
+    /// Validates the upgrade request and returns the `Sec-WebSocket-Key` header value.
+    ///
+    /// More information: <https://datatracker.ietf.org/doc/html/rfc6455#section-4.2.1>
+    ///
+    fn validate(request: &Request) -> Result<String, String> {
         if request.method != "GET" {
             return Err("Invalid request method.".to_owned());
         }
 
-        // Validate connection header
+        // Connection header can contain multiple values seperated by comma.
         if let Some(value) = request.headers.value("Connection") {
-            // Connection header can contain multiple values seperated by comma.
-            // Checks if 'upgrade' is specified or not. If not returns error.
-            if !value.to_lowercase().contains("upgrade") {
+            if !has_token(&value, "upgrade") {
                 return Err("Connection header does not specify to upgrade".to_string());
             }
         } else {
             return Err("Connection header is missing.".to_string());
         }
 
-        let upgrade;
         if let Some(value) = request.headers.value("Upgrade") {
-            upgrade = value;
+            if !has_token(&value, "websocket") {
+                return Err("Upgrade header is not set to websocket.".to_string());
+            }
         } else {
             return Err("Upgrade header is missing.".to_string());
         };
 
+        match request.headers.value("Sec-WebSocket-Version") {
+            Some(value) if value.trim() == WEBSOCKET_VERSION => {}
+            Some(value) => return Err(format!("Unsupported WebSocket version: {}", value)),
+            None => return Err("Sec-WebSocket-Version header is missing.".to_string()),
+        }
+
         let sec_websocket_key;
         if let Some(value) = request.headers.value("Sec-WebSocket-Key") {
             // According to RFC, any leading or trailing spaces must be removed.
@@ -134,35 +430,23 @@ impl WebSocket {
             return Err("Sec-WebSocket-Key header is missing".to_string());
         }
 
-        if upgrade.to_lowercase() == "websocket" {
-        } else {
-            return Err("Upgrade header is not set to websocket.".to_string());
+        // Key is the base64 encoded random 16 bytes.
+        match base64::engine::general_purpose::STANDARD.decode(&sec_websocket_key) {
+            Ok(key) if key.len() == 16 => {}
+            _ => return Err("Sec-WebSocket-Key header is invalid.".to_string()),
         }
 
-        let instance = Self {
-            uid: Uuid::new_v4().to_string(),
-            stream: request.stream.clone(),
-            request_validated: true,
-            receive_next: Arc::new(AtomicBool::new(false)),
-            headers: Headers::new(),
-            body: Vec::new(),
-        };
-
-        match Self::handshake(request.stream.clone(), &sec_websocket_key).await {
-            Ok(()) => {}
-            Err(error) => {
-                return Err(format!("Failed to handshake. {}", error));
-            }
-        };
-
-        instance.receive_next.store(true, Ordering::Relaxed);
-        Ok(instance)
+        Ok(sec_websocket_key)
     }
 
     ///
     /// More information: <https://datatracker.ietf.org/doc/html/rfc6455#section-1.3>
     ///
-    async fn handshake(stream: Arc<Stream>, sec_websocket_key: &str) -> std::io::Result<()> {
+    async fn handshake(
+        stream: Arc<Stream>,
+        sec_websocket_key: &str,
+        protocol: Option<&str>,
+    ) -> std::io::Result<()> {
         let base64_hash = Self::handshake_key_base64(sec_websocket_key);
 
         let mut http_response = HttpResponse::switching_protocols();
@@ -170,10 +454,13 @@ impl WebSocket {
         headers.set("Connection", "upgrade");
         headers.set("Upgrade", "websocket");
         headers.set("Sec-WebSocket-Accept", base64_hash.as_bytes());
+        if let Some(protocol) = protocol {
+            headers.set("Sec-WebSocket-Protocol", protocol);
+        }
 
         let mut response: Box<dyn AbstractResponse> = http_response.empty();
         let response_bytes = response_to_bytes(&mut response);
-        Ok(stream.write_chunk(&response_bytes).await?)
+        stream.write_chunk(&response_bytes).await
     }
 
     fn handshake_key_base64(sec_websocket_key: &str) -> String {
@@ -190,154 +477,366 @@ impl WebSocket {
         base64::engine::general_purpose::STANDARD.encode(hash_result)
     }
 
-    async fn ping_with_interval(&self, duration: Duration) {
-        let stream = self.stream.clone();
-        let receive_next = self.receive_next.clone();
+    ///
+    /// Sends the periodic pings and closes the connection if the client stops responding.
+    ///
+    async fn heartbeat(self, ping_interval: Duration) {
+        racoon_debug!("Sending periodic ping frames...");
 
-        tokio::spawn(async move {
-            racoon_debug!("Sending periodic ping frames...");
+        loop {
+            tokio::time::sleep(ping_interval).await;
 
-            let mut interval = tokio::time::interval(duration);
+            // No frames are sent after the close frame.
+            if self.close_sent.load(Ordering::Relaxed) || !self.receive_next.load(Ordering::Relaxed)
+            {
+                break;
+            }
 
-            // More information: https://datatracker.ietf.org/doc/html/rfc6455#section-5.5.2
-            let frame = Frame {
-                fin: 1,
-                op_code: 9,
-                payload: vec![],
-            };
+            racoon_debug!("Sending ping...");
+            let ping_sent_at = Instant::now();
 
-            let bytes = frame::builder::build(&frame);
-            interval.tick().await;
+            // More information: https://datatracker.ietf.org/doc/html/rfc6455#section-5.5.2
+            if let Err(error) = self.write_frame(9, vec![]).await {
+                racoon_debug!("Ping failed. Error: {}", error);
+                self.reap("Ping failed.");
+                break;
+            }
 
-            loop {
-                interval.tick().await;
-                racoon_debug!("Sending ping...");
+            tokio::time::sleep(self.config.pong_timeout).await;
 
-                match stream.write_chunk(&bytes).await {
-                    Ok(()) => {}
-                    Err(error) => {
-                        // Ping failed, so if messages are waiting, stops waiting new messages.
-                        receive_next.store(false, Ordering::Relaxed);
-                        racoon_debug!("Ping failed. Error: {}", error);
-                        break;
-                    }
-                }
+            // Any frame received after the ping shows the client is alive.
+            if self.last_received().0 < ping_sent_at {
+                racoon_debug!("Pong not received within the timeout.");
+                self.reap("Pong not received.");
+                break;
             }
-        });
+        }
+    }
+
+    ///
+    /// Stops receiving from the unresponsive client. Waiting `recv()` returns the close message
+    /// with code `1006`.
+    ///
+    fn reap(&self, reason: &str) {
+        self.receive_next.store(false, Ordering::Relaxed);
+        self.close_sent.store(true, Ordering::Relaxed);
+        self.closed.send_replace(true);
+        racoon_debug!("Closing WebSocket. Reason: {}", reason);
+    }
+
+    fn last_received(&self) -> (Instant, Instant) {
+        match self.last_received.lock() {
+            Ok(last_received) => *last_received,
+            Err(poisoned) => *poisoned.into_inner(),
+        }
+    }
+
+    fn set_last_received(&self, is_message: bool) {
+        let mut last_received = match self.last_received.lock() {
+            Ok(last_received) => last_received,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let now = Instant::now();
+        last_received.0 = now;
+        if is_message {
+            last_received.1 = now;
+        }
     }
 
-    async fn send_pong(&self) {
-        racoon_debug!("Sending pong frame.");
+    ///
+    /// Reads the next frame. Returns the close message if the connection is closed by the
+    /// heartbeat or stays idle.
+    ///
+    async fn next_frame(&self, max_frame_size: u64) -> Result<Frame, Message> {
+        let mut closed = self.closed.subscribe();
+        let closed = async move {
+            let _ = closed.wait_for(|closed| *closed).await;
+        };
+        let idle_deadline = self
+            .config
+            .idle_timeout
+            .map(|idle_timeout| self.last_received().1 + idle_timeout);
+
+        let idle = async move {
+            match idle_deadline {
+                Some(idle_deadline) => tokio::time::sleep_until(idle_deadline.into()).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let shutdown = self.shutdown.clone();
+        let shutdown = async move {
+            match shutdown {
+                Some(shutdown) => shutdown.wait().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        let result = tokio::select! {
+            result = reader::read_frame_opt(self.stream.clone(), max_frame_size, true) => result,
+            _ = closed => {
+                return Err(Message::Close(1006, "Client is not responding.".to_string()));
+            }
+            _ = idle => return Err(self.fail(1001, "Idle timeout.").await),
+            _ = shutdown => return Err(self.going_away().await),
+        };
+
+        match result {
+            Ok(frame) => {
+                self.set_last_received(frame.op_code < 8);
+                Ok(frame)
+            }
+            Err(error) => Err(match error.kind() {
+                ErrorKind::InvalidData => self.fail(1002, error.to_string()).await,
+                ErrorKind::InvalidInput => self.fail(1009, error.to_string()).await,
+                _ => {
+                    // Connection is lost, so stops waiting for new messages.
+                    self.receive_next.store(false, Ordering::Relaxed);
+                    Message::Close(1006, error.to_string())
+                }
+            }),
+        }
+    }
 
-        // More information: https://datatracker.ietf.org/doc/html/rfc6455#section-5.5.2
+    async fn write_frame(&self, op_code: u8, payload: Vec<u8>) -> std::io::Result<()> {
         let frame = Frame {
             fin: 1,
-            op_code: 10,
-            payload: vec![],
+            op_code,
+            payload,
         };
 
+        // Frame is written at once, so frames sent concurrently are not interleaved.
         let bytes = frame::builder::build(&frame);
-        match self.stream.write_chunk(&bytes).await {
-            Ok(()) => {}
-            Err(error) => {
-                // Pong failed, so stops receiving messages.
-                self.receive_next.store(false, Ordering::Relaxed);
-                racoon_debug!("Pong failed. Error: {}", error);
-            }
-        }
+        self.stream.write_chunk(&bytes).await
     }
 
+    ///
+    /// Receives the next message with the payload size limit. The limit applies to both the
+    /// frames and the message, but not above the configured frame size.
+    ///
     pub async fn receive_message_with_limit(&self, max_payload_size: u64) -> Option<Message> {
-        if !self.receive_next.load(Ordering::Relaxed) {
-            return None;
-        };
+        let max_frame_size = std::cmp::min(max_payload_size, self.config.max_frame_size);
+        self.receive(max_frame_size, max_payload_size).await
+    }
 
-        let mut response: Vec<u8> = vec![];
+    ///
+    /// Pings are answered with the pong and the close frame is echoed before returning them.
+    /// Returns `None` once the connection is closed.
+    ///
+    async fn receive(&self, max_frame_size: u64, max_message_size: u64) -> Option<Message> {
+        // Fragments received before the control frame are kept for the next call.
+        let mut fragments = self.fragments.lock().await;
 
         loop {
-            let frame = match reader::read_frame(self.stream.clone(), max_payload_size).await {
+            if !self.receive_next.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let frame = match self.next_frame(max_frame_size).await {
                 Ok(frame) => frame,
-                Err(error) => {
-                    // Stops waiting for new messages
-                    self.receive_next.store(false, Ordering::Relaxed);
-                    return Some(Message::Close(1000, error.to_string()));
-                }
+                Err(message) => return Some(message),
             };
 
-            response.extend(&frame.payload);
-
-            // Checks response size
-            if response.len() > DEFAULT_MAX_PAYLOAD_SIZE as usize {
-                return Some(Message::Close(0, "Max payload size exceed.".to_string()));
+            let fin = frame.fin;
+            match frame.op_code {
+                0 => match fragments.as_mut() {
+                    Some((_, payload)) => {
+                        if (payload.len() + frame.payload.len()) as u64 > max_message_size {
+                            *fragments = None;
+                            return Some(self.fail(1009, "Max message size exceed.").await);
+                        }
+                        payload.extend(frame.payload);
+                    }
+                    None => return Some(self.fail(1002, "Unexpected continuation frame.").await),
+                },
+                1 | 2 => {
+                    if fragments.is_some() {
+                        return Some(self.fail(1002, "Expected continuation frame.").await);
+                    }
+                    if frame.payload.len() as u64 > max_message_size {
+                        return Some(self.fail(1009, "Max message size exceed.").await);
+                    }
+                    *fragments = Some((frame.op_code, frame.payload));
+                }
+                8 => return Some(self.receive_close(&frame.payload).await),
+                9 => {
+                    // Pong must contain the same payload as the ping.
+                    if let Err(error) = self.send(Message::Pong(frame.payload.clone())).await {
+                        self.receive_next.store(false, Ordering::Relaxed);
+                        racoon_debug!("Pong failed. Error: {}", error);
+                    }
+                    return Some(Message::Ping(frame.payload));
+                }
+                10 => return Some(Message::Pong(frame.payload)),
+                _ => return Some(self.fail(1002, "Unknown opcode.").await),
             }
 
             // If fin is 1, the complete message is received.
-            if frame.fin == 1 {
-                return if frame.op_code == 0 {
-                    Some(Message::Continue(frame.payload))
-                } else if frame.op_code == 1 {
-                    // Text Frame
-                    let payload_text = String::from_utf8_lossy(frame.payload.as_slice());
-                    Some(Message::Text(payload_text.to_string()))
-                } else if frame.op_code == 2 {
-                    // Binary frame
-                    Some(Message::Binary(frame.payload))
-                } else if frame.op_code == 8 {
-                    // Connection close frame
-                    self.receive_next.store(false, Ordering::Relaxed);
-                    let close_code = self.close_code_from_payload(&frame.payload);
-                    let close_message = self.close_message_from_payload(&frame.payload);
-                    Some(Message::Close(close_code, close_message))
-                } else if frame.op_code == 9 {
-                    // Ping frame
-                    self.send_pong().await;
-                    Some(Message::Ping())
-                } else if frame.op_code == 10 {
-                    // Pong frame
-                    Some(Message::Pong())
-                } else {
-                    Some(Message::Others(frame.payload))
-                };
+            if fin == 1 {
+                if let Some((op_code, payload)) = fragments.take() {
+                    if op_code == 2 {
+                        return Some(Message::Binary(payload));
+                    }
+
+                    return Some(match String::from_utf8(payload) {
+                        Ok(text) => Message::Text(text),
+                        Err(_) => self.fail(1007, "Text message is not valid UTF-8.").await,
+                    });
+                }
             }
         }
     }
 
-    pub async fn message(&self) -> Option<Message> {
-        self.receive_message_with_limit(DEFAULT_MAX_PAYLOAD_SIZE)
+    ///
+    /// Receives the next message within the configured size limits. Returns `None` once the
+    /// connection is closed.
+    ///
+    pub async fn recv(&self) -> Option<Message> {
+        self.receive(self.config.max_frame_size, self.config.max_message_size)
             .await
     }
 
-    pub async fn send_text<S: AsRef<str>>(&self, message: S) -> std::io::Result<()> {
-        let message = message.as_ref();
+    pub async fn message(&self) -> Option<Message> {
+        self.recv().await
+    }
 
-        let frame = Frame {
-            fin: 1,
-            op_code: 1,
-            payload: message.as_bytes().to_vec(),
+    ///
+    /// Sends the message. Sending `Message::Close` is same as calling `close()`.
+    ///
+    pub async fn send(&self, message: Message) -> std::io::Result<()> {
+        let (op_code, payload) = match message {
+            Message::Text(text) => (1, text.into_bytes()),
+            Message::Binary(bytes) => (2, bytes),
+            Message::Ping(payload) => (9, payload),
+            Message::Pong(payload) => (10, payload),
+            Message::Close(code, reason) => return self.close(code, reason).await,
         };
 
-        let bytes = frame::builder::build(&frame);
-        self.stream.write_chunk(&bytes).await?;
-        Ok(())
+        // More information: https://datatracker.ietf.org/doc/html/rfc6455#section-5.5
+        if op_code >= 8 && payload.len() > 125 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Control frame payload must not be more than 125 bytes.",
+            ));
+        }
+
+        if self.close_sent.load(Ordering::Relaxed) {
+            return Err(std::io::Error::new(
+                ErrorKind::NotConnected,
+                "WebSocket is already closed.",
+            ));
+        }
+
+        self.write_frame(op_code, payload).await
+    }
+
+    pub async fn send_text<S: AsRef<str>>(&self, message: S) -> std::io::Result<()> {
+        self.send(Message::Text(message.as_ref().to_string())).await
     }
 
     pub async fn send_bytes<B: AsRef<[u8]>>(&self, bytes: B) -> std::io::Result<()> {
-        let payload = Vec::from(bytes.as_ref());
+        self.send(Message::Binary(bytes.as_ref().to_vec())).await
+    }
 
-        let frame = Frame {
-            fin: 1,
-            op_code: 2,
-            payload,
+    pub async fn send_json(&self, json: &Value) -> std::io::Result<()> {
+        self.send_text(json.to_string().as_str()).await
+    }
+
+    ///
+    /// Sends the close frame. The client responds with its close frame, which is returned by
+    /// `recv()`. Reason longer than 123 bytes is truncated to fit the control frame.
+    ///
+    pub async fn close<S: AsRef<str>>(&self, code: u16, reason: S) -> std::io::Result<()> {
+        let reason = reason.as_ref();
+        let mut reason_length = std::cmp::min(reason.len(), 123);
+        while !reason.is_char_boundary(reason_length) {
+            reason_length -= 1;
+        }
+
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend(&reason.as_bytes()[..reason_length]);
+        self.send_close(payload).await
+    }
+
+    async fn send_close(&self, payload: Vec<u8>) -> std::io::Result<()> {
+        // Close frame is sent only once.
+        if self.close_sent.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        self.write_frame(8, payload).await
+    }
+
+    ///
+    /// Echoes the close frame received from the client.
+    ///
+    /// More information: <https://datatracker.ietf.org/doc/html/rfc6455#section-5.5.1>
+    ///
+    async fn receive_close(&self, payload: &[u8]) -> Message {
+        self.receive_next.store(false, Ordering::Relaxed);
+
+        let close_code = self.close_code_from_payload(payload);
+        let close_message = self.close_message_from_payload(payload);
+
+        let echo_payload = if payload.len() >= 2 {
+            payload[..2].to_vec()
+        } else {
+            vec![]
         };
 
-        let bytes = frame::builder::build(&frame);
-        self.stream.write_chunk(&bytes).await?;
+        if let Err(error) = self.send_close(echo_payload).await {
+            racoon_debug!("Failed to send close frame. Error: {}", error);
+        }
+        Message::Close(close_code, close_message)
+    }
 
-        Ok(())
+    ///
+    /// Closes the connection due to the client error. Returned close message is passed to the
+    /// handler.
+    ///
+    async fn fail<S: Into<String>>(&self, code: u16, reason: S) -> Message {
+        let reason = reason.into();
+        racoon_debug!("Closing WebSocket. Code: {} Reason: {}", code, reason);
+
+        self.receive_next.store(false, Ordering::Relaxed);
+        if let Err(error) = self.close(code, &reason).await {
+            racoon_debug!("Failed to send close frame. Error: {}", error);
+        }
+        Message::Close(code, reason)
     }
 
-    pub async fn send_json(&self, json: &Value) -> std::io::Result<()> {
-        self.send_text(json.to_string().as_str()).await
+    ///
+    /// Closes the connection on server shutdown and waits for the client to echo the close frame
+    /// before returning the close message to the handler.
+    ///
+    async fn going_away(&self) -> Message {
+        let reason = "Server is shutting down.";
+        self.receive_next.store(false, Ordering::Relaxed);
+
+        match self.close(1001, reason).await {
+            Ok(()) => {
+                // Messages received after sending the close frame are discarded.
+                let wait_close = async {
+                    while let Ok(frame) = reader::read_frame_opt(
+                        self.stream.clone(),
+                        self.config.max_frame_size,
+                        true,
+                    )
+                    .await
+                    {
+                        if frame.op_code == 8 {
+                            break;
+                        }
+                    }
+                };
+                let _ = tokio::time::timeout(SHUTDOWN_CLOSE_TIMEOUT, wait_close).await;
+            }
+            Err(error) => {
+                racoon_debug!("Failed to send close frame. Error: {}", error);
+            }
+        }
+        Message::Close(1001, reason.to_string())
     }
 
     pub async fn bad_request(self) -> Box<Self> {
@@ -353,9 +852,9 @@ impl WebSocket {
     }
 
     fn close_code_from_payload(&self, response: &[u8]) -> u16 {
-        if response.len() == 2 {
+        if response.len() >= 2 {
             let mut tmp_bytes = [0u8; 2];
-            tmp_bytes.copy_from_slice(response);
+            tmp_bytes.copy_from_slice(&response[..2]);
             return u16::from_be_bytes(tmp_bytes);
         }
 
@@ -363,7 +862,9 @@ impl WebSocket {
             "Close payload length expected more than 2. But found: {}",
             response.len()
         );
-        return 0;
+
+        // No status code is present.
+        1005
     }
 
     fn close_message_from_payload(&self, response: &[u8]) -> String {
@@ -372,6 +873,226 @@ impl WebSocket {
         }
 
         let message_bytes = &response[2..];
-        String::from_utf8_lossy(&message_bytes).to_string()
+        String::from_utf8_lossy(message_bytes).to_string()
+    }
+}
+
+///
+/// Checks if the comma separated header value contains the token, ignoring case.
+///
+pub(crate) fn has_token(value: &str, token: &str) -> bool {
+    value
+        .split(',')
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::core::headers::{HeaderValue, Headers};
+    use crate::core::server::{ConnectionTracker, ShutdownSignal};
+    use crate::core::stream::{Stream, TcpStreamWrapper};
+    use crate::core::websocket::frame::{builder, reader, Frame};
+
+    use super::{Message, WebSocket, WebSocketConfig};
+
+    #[test]
+    fn test_handshake_key() {
+        // Example from https://datatracker.ietf.org/doc/html/rfc6455#section-1.3
+        assert_eq!(
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+            WebSocket::handshake_key_base64("dGhlIHNhbXBsZSBub25jZQ==")
+        );
+    }
+
+    #[test]
+    fn test_select_protocol() {
+        let supported = vec!["graphql-transport-ws".to_string(), "graphql-ws".to_string()];
+        let mut headers = Headers::new();
+        assert_eq!(None, WebSocket::select_protocol(&headers, &supported));
+
+        headers.set("Sec-WebSocket-Protocol", "chat, graphql-ws");
+        assert_eq!(
+            Some("graphql-ws".to_string()),
+            WebSocket::select_protocol(&headers, &supported)
+        );
+
+        headers.set_multiple("Sec-WebSocket-Protocol", "graphql-transport-ws");
+        assert_eq!(
+            Some("graphql-transport-ws".to_string()),
+            WebSocket::select_protocol(&headers, &supported)
+        );
+    }
+
+    fn client_frame(fin: u8, op_code: u8, payload: &[u8]) -> Vec<u8> {
+        let frame = Frame {
+            fin,
+            op_code,
+            payload: payload.to_vec(),
+        };
+        builder::build_opt(&frame, true)
+    }
+
+    #[tokio::test]
+    async fn test_receive_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server, 8096).unwrap());
+        let websocket = WebSocket::new(Arc::new(stream), WebSocketConfig::new());
+        websocket.receive_next.store(true, Ordering::Relaxed);
+
+        // Ping is received between the fragments of the text message.
+        let mut bytes = client_frame(0, 1, b"Hel");
+        bytes.extend(client_frame(1, 9, b"ping"));
+        bytes.extend(client_frame(1, 0, b"lo"));
+        let mut close_payload = 1000u16.to_be_bytes().to_vec();
+        close_payload.extend(b"Bye");
+        bytes.extend(client_frame(1, 8, &close_payload));
+        client.write_all(&bytes).await.unwrap();
+
+        assert_eq!(
+            Some(Message::Ping(b"ping".to_vec())),
+            websocket.recv().await
+        );
+        assert_eq!(
+            Some(Message::Text("Hello".to_string())),
+            websocket.recv().await
+        );
+        assert_eq!(
+            Some(Message::Close(1000, "Bye".to_string())),
+            websocket.recv().await
+        );
+        assert_eq!(None, websocket.recv().await);
+
+        // Server responds with the pong and echoes the close frame.
+        let client: Stream = Box::new(TcpStreamWrapper::from(client, 8096).unwrap());
+        let client: Arc<Stream> = Arc::new(client);
+        let pong = reader::read_frame(client.clone(), 125).await.unwrap();
+        assert_eq!((10, b"ping".to_vec()), (pong.op_code, pong.payload));
+        let close = reader::read_frame(client.clone(), 125).await.unwrap();
+        assert_eq!(
+            (8, 1000u16.to_be_bytes().to_vec()),
+            (close.op_code, close.payload)
+        );
+
+        assert!(websocket.send_text("Hello").await.is_err());
+        let _ = client.shutdown().await;
+    }
+    #[tokio::test]
+    async fn test_heartbeat_and_idle_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let stream: Arc<Stream> = Arc::new(Box::new(TcpStreamWrapper::from(server, 8096).unwrap()));
+
+        // Client does not respond to the ping.
+        let config =
+            WebSocketConfig::new().heartbeat(Duration::from_millis(50), Duration::from_millis(50));
+        let websocket = WebSocket::new(stream.clone(), config);
+        websocket.receive_next.store(true, Ordering::Relaxed);
+        tokio::spawn(websocket.clone().heartbeat(Duration::from_millis(50)));
+
+        match websocket.recv().await {
+            Some(Message::Close(code, _)) => assert_eq!(1006, code),
+            _ => panic!("Expected close message."),
+        }
+        assert_eq!(None, websocket.recv().await);
+
+        let config = WebSocketConfig::new()
+            .disable_heartbeat()
+            .idle_timeout(Duration::from_millis(50));
+        let websocket = WebSocket::new(stream, config);
+        websocket.receive_next.store(true, Ordering::Relaxed);
+        assert_eq!(
+            Some(Message::Close(1001, "Idle timeout.".to_string())),
+            websocket.recv().await
+        );
+    }
+    #[tokio::test]
+    async fn test_size_limits() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = WebSocketConfig::new()
+            .disable_heartbeat()
+            .max_frame_size(10)
+            .max_message_size(15);
+
+        // Single frame is larger than the frame limit, while the fragments are within the limit
+        // but the assembled message is not.
+        let messages = [
+            client_frame(1, 2, &[0; 11]),
+            [client_frame(0, 2, &[0; 10]), client_frame(1, 0, &[0; 10])].concat(),
+        ];
+
+        for bytes in messages {
+            let mut client = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (server, _) = listener.accept().await.unwrap();
+            let stream: Stream = Box::new(TcpStreamWrapper::from(server, 8096).unwrap());
+            let websocket = WebSocket::new(Arc::new(stream), config.clone());
+            websocket.receive_next.store(true, Ordering::Relaxed);
+
+            client.write_all(&bytes).await.unwrap();
+            match websocket.recv().await {
+                Some(Message::Close(code, _)) => assert_eq!(1009, code),
+                _ => panic!("Expected close message."),
+            }
+
+            let client: Stream = Box::new(TcpStreamWrapper::from(client, 8096).unwrap());
+            let close = reader::read_frame(Arc::new(client), 125).await.unwrap();
+            assert_eq!(8, close.op_code);
+            assert_eq!(1009u16.to_be_bytes(), close.payload[..2]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_on_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let stream: Stream = Box::new(TcpStreamWrapper::from(server, 8096).unwrap());
+        let config = WebSocketConfig::new().disable_heartbeat();
+        let mut websocket = WebSocket::new(Arc::new(stream), config);
+        websocket.receive_next.store(true, Ordering::Relaxed);
+
+        let connections = ConnectionTracker::default();
+        websocket.shutdown = Some(ShutdownSignal(connections.clone()));
+
+        let client: Stream = Box::new(TcpStreamWrapper::from(client, 8096).unwrap());
+        let client: Arc<Stream> = Arc::new(client);
+        let client_task = client.clone();
+        let client_task = tokio::spawn(async move {
+            let close = reader::read_frame(client_task.clone(), 125).await.unwrap();
+            let close_echo = client_frame(1, 8, &close.payload[..2]);
+            client_task.write_chunk(&close_echo).await.unwrap();
+            close
+        });
+
+        let receiving = tokio::spawn(async move { websocket.recv().await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        connections.drain(None).await;
+
+        assert_eq!(
+            Some(Message::Close(1001, "Server is shutting down.".to_string())),
+            receiving.await.unwrap()
+        );
+        let close = client_task.await.unwrap();
+        assert_eq!(8, close.op_code);
+        assert_eq!(1001u16.to_be_bytes(), close.payload[..2]);
     }
 }
@@ -1,17 +1,39 @@
 use std::any::Any;
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use async_tempfile::TempFile;
 use tokio::sync::Mutex;
+use uuid::Uuid;
 
 use crate::core::forms::{Files, FormData};
-use crate::forms::AbstractFields;
-
-use crate::forms::fields::FieldResult;
-
+use crate::forms::{error_message, AbstractFields};
+
+use crate::forms::fields::{FieldResult, FieldSchema};
+
+///
+/// File uploaded with the form. The temp file is deleted when the request completes unless the
+/// handler takes its ownership:
+///
+/// - `persist` moves the file to the given path.
+/// - `keep` moves the file out of the upload cleanup and returns its path. The handler is
+///   responsible for deleting it.
+/// - `into_temp_file` returns the temp file, which is deleted when it is dropped. Use it to
+///   process the file after the response is sent.
+///
+/// # Examples
+///
+/// ```
+/// use racoon::forms::fields::file_field::UploadedFile;
+///
+/// async fn save_avatar(file: UploadedFile) -> std::io::Result<()> {
+///     file.persist("/var/lib/app/avatars/1.png").await?;
+///     Ok(())
+/// }
+/// ```
+///
 pub struct UploadedFile {
     pub filename: String,
     core_file_field: crate::core::forms::FileField,
@@ -45,6 +67,37 @@ impl UploadedFile {
             temp_path,
         }
     }
+
+    ///
+    /// Moves the file to the path, replacing the existing file. Falls back to copying if the path
+    /// is on another filesystem. Returns the new path.
+    ///
+    pub async fn persist<P: AsRef<Path>>(self, path: P) -> std::io::Result<PathBuf> {
+        let path = path.as_ref().to_path_buf();
+        if tokio::fs::rename(&self.temp_path, &path).await.is_err() {
+            // Temp file is deleted when self is dropped.
+            tokio::fs::copy(&self.temp_path, &path).await?;
+        }
+        Ok(path)
+    }
+
+    ///
+    /// Keeps the file after the request completes by moving it to another path in the same
+    /// directory. Returns the new path.
+    ///
+    pub async fn keep(self) -> std::io::Result<PathBuf> {
+        let path = self
+            .temp_path
+            .with_file_name(format!("racoon-kept-{}", Uuid::new_v4().simple()));
+        self.persist(path).await
+    }
+
+    ///
+    /// Returns the temp file which is deleted when dropped instead of when the request completes.
+    ///
+    pub fn into_temp_file(self) -> TempFile {
+        self.core_file_field.into_temp_file()
+    }
 }
 
 pub type PostValidator<T> = Box<fn(T) -> Result<T, Vec<String>>>;
@@ -76,6 +129,11 @@ pub trait ToOptionT {
         Self: Sized;
 
     fn is_optional() -> bool;
+
+    /// True if all the files of the field are kept.
+    fn is_multiple() -> bool {
+        false
+    }
 }
 
 impl ToOptionT for UploadedFile {
@@ -133,6 +191,10 @@ impl ToOptionT for Vec<UploadedFile> {
     fn is_optional() -> bool {
         false
     }
+
+    fn is_multiple() -> bool {
+        true
+    }
 }
 
 impl ToOptionT for Option<Vec<UploadedFile>> {
@@ -158,6 +220,10 @@ impl ToOptionT for Option<Vec<UploadedFile>> {
     fn is_optional() -> bool {
         true
     }
+
+    fn is_multiple() -> bool {
+        true
+    }
 }
 
 impl<T: Sync + Send + 'static> FileField<T> {
@@ -245,7 +311,11 @@ impl<T: ToOptionT + Sync + Send + 'static> AbstractFields for FileField<T> {
             }
 
             if !is_optional && is_empty {
-                errors.push("This field is required.".to_string());
+                errors.push(error_message(
+                    "form-field-required",
+                    "This field is required.".to_string(),
+                    &[],
+                ));
             }
 
             if errors.len() > 0 {
@@ -268,13 +338,22 @@ impl<T: ToOptionT + Sync + Send + 'static> AbstractFields for FileField<T> {
     fn wrap(&self) -> Box<dyn AbstractFields> {
         Box::new(self.clone())
     }
+
+    fn schema(&self) -> Option<FieldSchema> {
+        let schema = serde_json::json!({"type": "string", "format": "binary"});
+        let mut field_schema =
+            FieldSchema::new(&self.field_name, schema, T::is_multiple(), !T::is_optional());
+        field_schema.is_file = true;
+        Some(field_schema)
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use async_tempfile::TempFile;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 
+    use crate::core::forms::uploads::create_temp_file;
     use crate::core::forms::{Files, FormData};
     use crate::forms::fields::AbstractFields;
 
@@ -402,4 +481,54 @@ pub mod tests {
         let result = file_field.validate(&mut form_data, &mut files).await;
         assert_eq!(false, result.is_ok());
     }
+
+    async fn uploaded_file() -> UploadedFile {
+        let (mut temp_file, upload) = create_temp_file().await.unwrap();
+        temp_file.write_all(b"Hello World").await.unwrap();
+        temp_file.flush().await.unwrap();
+
+        let core_file_field =
+            crate::core::forms::FileField::from("file.txt", temp_file).with_upload(Some(upload));
+        UploadedFile::from_core_file_field(core_file_field)
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_policy() {
+        // Deleted when dropped by default.
+        let file = uploaded_file().await;
+        let temp_path = file.temp_path.clone();
+        drop(file);
+        assert!(!temp_path.exists());
+
+        let file = uploaded_file().await;
+        let temp_path = file.temp_path.clone();
+        let destination = temp_path.with_file_name(format!(
+            "{}.persisted",
+            temp_path.file_name().unwrap().to_string_lossy()
+        ));
+        assert_eq!(destination, file.persist(&destination).await.unwrap());
+        assert!(!temp_path.exists());
+        assert_eq!(
+            "Hello World",
+            tokio::fs::read_to_string(&destination).await.unwrap()
+        );
+        tokio::fs::remove_file(destination).await.unwrap();
+
+        let file = uploaded_file().await;
+        let kept_path = file.keep().await.unwrap();
+        assert!(kept_path.exists());
+        tokio::fs::remove_file(kept_path).await.unwrap();
+
+        // Owned by the returned temp file after the upload is dropped.
+        let file = uploaded_file().await;
+        let temp_path = file.temp_path.clone();
+        let mut temp_file = file.into_temp_file();
+        assert!(temp_path.exists());
+        let mut content = String::new();
+        temp_file.rewind().await.unwrap();
+        temp_file.read_to_string(&mut content).await.unwrap();
+        assert_eq!("Hello World", content);
+        drop(temp_file);
+        let _ = tokio::fs::remove_file(temp_path).await;
+    }
 }
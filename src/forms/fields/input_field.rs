@@ -8,8 +8,8 @@ use tokio::sync::Mutex;
 
 use crate::core::forms::{Files, FormData};
 
-use crate::forms::fields::FieldResult;
-use crate::forms::AbstractFields;
+use crate::forms::fields::{FieldResult, FieldSchema};
+use crate::forms::{error_message, AbstractFields};
 
 pub enum InputFieldError<'a> {
     MissingField(&'a String),
@@ -27,6 +27,11 @@ pub trait ToOptionT {
     where
         Self: Sized;
     fn is_optional() -> bool;
+
+    /// True if all the values of the field are kept.
+    fn is_multiple() -> bool {
+        false
+    }
 }
 
 impl ToOptionT for String {
@@ -81,6 +86,10 @@ impl ToOptionT for Vec<String> {
     fn is_optional() -> bool {
         false
     }
+
+    fn is_multiple() -> bool {
+        true
+    }
 }
 
 impl ToOptionT for Option<Vec<String>> {
@@ -104,6 +113,10 @@ impl ToOptionT for Option<Vec<String>> {
     fn is_optional() -> bool {
         true
     }
+
+    fn is_multiple() -> bool {
+        true
+    }
 }
 
 type BoxResult = Box<dyn Any + Send + Sync + 'static>;
@@ -214,8 +227,11 @@ fn validate_input_length(
     if let Some(max_length) = max_length {
         // Checks maximum value length constraints
         if value.len() > *max_length {
-            let default_max_length_exceed_messsage =
-                format!("Character length exceeds maximum size of {}", *max_length);
+            let default_max_length_exceed_messsage = error_message(
+                "form-max-length",
+                format!("Character length exceeds maximum size of {}", *max_length),
+                &[("max", *max_length)],
+            );
 
             if let Some(error_handler) = error_handler.clone() {
                 let max_length_exceed_error =
@@ -235,8 +251,11 @@ fn validate_input_length(
     if let Some(min_length) = min_length {
         // Checks maximum value length constraints
         if value.len() < *min_length {
-            let default_max_length_exceed_messsage =
-                format!("Text length is less then {}", *min_length);
+            let default_max_length_exceed_messsage = error_message(
+                "form-min-length",
+                format!("Text length is less then {}", *min_length),
+                &[("min", *min_length)],
+            );
 
             if let Some(error_handler) = error_handler.clone() {
                 let max_length_exceed_error =
@@ -330,7 +349,11 @@ impl<T: ToOptionT + Sync + Send + 'static> AbstractFields for InputField<T> {
                         form_values = Some(vec![default_value]);
                     }
                 } else {
-                    let default_field_missing_error = "This field is missing.".to_string();
+                    let default_field_missing_error = error_message(
+                        "form-field-missing",
+                        "This field is missing.".to_string(),
+                        &[],
+                    );
 
                     if let Some(error_handler) = error_handler {
                         let field_missing_error = InputFieldError::MissingField(&field_name);
@@ -384,6 +407,24 @@ impl<T: ToOptionT + Sync + Send + 'static> AbstractFields for InputField<T> {
     fn wrap(&self) -> Box<dyn AbstractFields> {
         Box::new(self.clone())
     }
+
+    fn schema(&self) -> Option<FieldSchema> {
+        let mut schema = serde_json::json!({"type": "string"});
+        if let Some(min_length) = &self.min_length {
+            schema["minLength"] = (**min_length).into();
+        }
+        if let Some(max_length) = &self.max_length {
+            schema["maxLength"] = (**max_length).into();
+        }
+
+        let required = !T::is_optional() && self.default_value.is_none();
+        Some(FieldSchema::new(
+            &self.field_name,
+            schema,
+            T::is_multiple(),
+            required,
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -4,6 +4,8 @@ pub mod uuid_field;
 
 use std::future::Future;
 
+use serde_json::Value;
+
 use crate::core::forms::{Files, FormData};
 
 type FieldResult<T> = Box<dyn Future<Output = T> + Send + Sync + Unpin>;
@@ -16,6 +18,47 @@ pub trait AbstractFields: Sync + Send {
         files: &mut Files,
     ) -> FieldResult<Result<(), Vec<String>>>;
     fn wrap(&self) -> Box<dyn AbstractFields>;
+
+    ///
+    /// Describes the field for the generated API documentation. Fields returning `None` are not
+    /// documented.
+    ///
+    fn schema(&self) -> Option<FieldSchema> {
+        None
+    }
+}
+
+///
+/// Name, JSON schema and requirement of the form field used in the OpenAPI document.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSchema {
+    pub name: String,
+    pub schema: Value,
+    pub required: bool,
+    /// True for the file fields, which are sent as `multipart/form-data`.
+    pub is_file: bool,
+}
+
+impl FieldSchema {
+    ///
+    /// Wraps the schema of a single value in an array schema if the field accepts multiple
+    /// values.
+    ///
+    pub fn new<S: AsRef<str>>(name: S, schema: Value, multiple: bool, required: bool) -> Self {
+        let schema = if multiple {
+            serde_json::json!({"type": "array", "items": schema})
+        } else {
+            schema
+        };
+
+        Self {
+            name: name.as_ref().to_string(),
+            schema,
+            required,
+            is_file: false,
+        }
+    }
 }
 
 pub type FormFields = Vec<Box<dyn AbstractFields + Sync + Send>>;
@@ -8,7 +8,7 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use crate::core::forms::{Files, FormData};
-use crate::forms::fields::{AbstractFields, FieldResult};
+use crate::forms::fields::{AbstractFields, FieldResult, FieldSchema};
 
 pub trait ToTypeT {
     fn from_vec(values: &mut Vec<String>) -> Option<Self>
@@ -277,6 +277,16 @@ impl<T: ToTypeT + Sync + Send + 'static> AbstractFields for UuidField<T> {
     fn wrap(&self) -> Box<dyn AbstractFields> {
         Box::new(self.clone())
     }
+
+    fn schema(&self) -> Option<FieldSchema> {
+        let schema = serde_json::json!({"type": "string", "format": "uuid"});
+        Some(FieldSchema::new(
+            &self.field_name,
+            schema,
+            false,
+            !T::is_optional(),
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,526 @@
+//!
+//! Rendering of the forms as html widgets, so the templates do not repeat the field definitions.
+//!
+//! Widgets are chosen from the schema of the fields and can be overridden for each field. The
+//! submitted values and the errors of [`ValidationError`] are rendered again, so the invalid form
+//! can be displayed without losing the input. Values of the password widgets are never rendered.
+//!
+//! # Examples
+//!
+//! ```
+//! use racoon::core::request::Request;
+//! use racoon::core::response::status::ResponseStatus;
+//! use racoon::core::response::{HttpResponse, Response};
+//! use racoon::forms::fields::input_field::InputField;
+//! use racoon::forms::html::Widget;
+//! use racoon::forms::{FormFields, FormValidator};
+//!
+//! struct SignupForm {
+//!     email: InputField<String>,
+//!     password: InputField<String>,
+//! }
+//!
+//! impl FormValidator for SignupForm {
+//!     fn new() -> Self {
+//!         Self {
+//!             email: InputField::new("email").max_length(100),
+//!             password: InputField::new("password").min_length(8),
+//!         }
+//!     }
+//!
+//!     fn form_fields(&mut self) -> FormFields {
+//!         vec![Box::new(self.email.clone()), Box::new(self.password.clone())]
+//!     }
+//! }
+//!
+//! async fn signup(request: Request) -> Response {
+//!     let mut form_html = SignupForm::new().as_html();
+//!     if request.method == "POST" {
+//!         match SignupForm::new().validate(&request).await {
+//!             Ok(_) => return HttpResponse::ok().body("Welcome"),
+//!             Err(error) => form_html = form_html.errors(&error),
+//!         }
+//!     }
+//!
+//!     let form_html = form_html
+//!         .widget("email", Widget::Email)
+//!         .widget("password", Widget::Password)
+//!         .csrf_token("csrf_token", "token-from-session");
+//!     HttpResponse::ok().body(format!(
+//!         "<form method=\"post\">{}<button>Sign up</button></form>",
+//!         form_html
+//!     ))
+//! }
+//! ```
+//!
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use serde_json::Value;
+
+use crate::core::forms::FormData;
+use crate::core::html::escape_html;
+use crate::forms::fields::FieldSchema;
+use crate::forms::{FormFields, ValidationError};
+
+///
+/// Html control used to render the field.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Widget {
+    Text,
+    Email,
+    /// Password input which never renders the value.
+    Password,
+    Number,
+    /// Checkbox sending `true` when checked.
+    Checkbox,
+    TextArea,
+    Hidden,
+    File,
+    /// Select with the options of value and label.
+    Select(Vec<(String, String)>),
+}
+
+impl Widget {
+    ///
+    /// Chooses the widget from the JSON schema of the field.
+    ///
+    pub fn from_schema(field_schema: &FieldSchema) -> Self {
+        if field_schema.is_file {
+            return Widget::File;
+        }
+
+        let schema = item_schema(field_schema);
+        if let Some(values) = schema["enum"].as_array() {
+            let options = values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::String(value) => Some(value.clone()),
+                    Value::Number(value) => Some(value.to_string()),
+                    _ => None,
+                })
+                .map(|value| (value.clone(), value))
+                .collect();
+            return Widget::Select(options);
+        }
+
+        match (schema["type"].as_str(), schema["format"].as_str()) {
+            (_, Some("email")) => Widget::Email,
+            (_, Some("password")) => Widget::Password,
+            (Some("integer" | "number"), _) => Widget::Number,
+            (Some("boolean"), _) => Widget::Checkbox,
+            _ => Widget::Text,
+        }
+    }
+}
+
+///
+/// Returns the schema of a single value of the field.
+///
+fn item_schema(field_schema: &FieldSchema) -> &Value {
+    match field_schema.schema["type"].as_str() {
+        Some("array") => &field_schema.schema["items"],
+        _ => &field_schema.schema,
+    }
+}
+
+///
+/// Label of the field derived from its name like `First name` from `first_name`.
+///
+fn default_label(name: &str) -> String {
+    let label = name.replace(['_', '-'], " ");
+    let mut characters = label.chars();
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => label,
+    }
+}
+
+fn errors_html(errors: &[String]) -> String {
+    let items: Vec<String> = errors
+        .iter()
+        .map(|error| format!("<li>{}</li>", escape_html(error)))
+        .collect();
+    format!("<ul class=\"errors\">{}</ul>", items.join(""))
+}
+
+///
+/// Html of the form fields, non-field errors and the CSRF token. The `<form>` element and the
+/// submit button are left to the template.
+///
+/// Each field is wrapped in `<div class="field">` with the label and the errors in
+/// `<ul class="errors">`. Fields with errors have the `error` class.
+///
+#[derive(Debug, Clone)]
+pub struct FormHtml {
+    fields: Vec<FieldSchema>,
+    widgets: HashMap<String, Widget>,
+    labels: HashMap<String, String>,
+    values: FormData,
+    field_errors: HashMap<String, Vec<String>>,
+    others: Vec<String>,
+    csrf_token: Option<(String, String)>,
+}
+
+impl FormHtml {
+    ///
+    /// Renders the fields with the schema. Fields without the schema are skipped.
+    ///
+    pub fn new(form_fields: FormFields) -> Self {
+        Self {
+            fields: form_fields
+                .iter()
+                .filter_map(|field| field.schema())
+                .collect(),
+            widgets: HashMap::new(),
+            labels: HashMap::new(),
+            values: FormData::new(),
+            field_errors: HashMap::new(),
+            others: vec![],
+            csrf_token: None,
+        }
+    }
+
+    ///
+    /// Renders the errors and the submitted values of the failed validation.
+    ///
+    pub fn errors(mut self, error: &ValidationError) -> Self {
+        self.field_errors = error.field_errors.clone();
+        self.others = error.others.clone();
+        self.values.extend(error.values.clone());
+        self
+    }
+
+    ///
+    /// Initial values of the fields such as the saved values of the edit form.
+    ///
+    pub fn values(mut self, values: FormData) -> Self {
+        self.values = values;
+        self
+    }
+
+    pub fn value<S: AsRef<str>, V: AsRef<str>>(mut self, name: S, value: V) -> Self {
+        self.values
+            .insert(name.as_ref().to_string(), vec![value.as_ref().to_string()]);
+        self
+    }
+
+    pub fn widget<S: AsRef<str>>(mut self, name: S, widget: Widget) -> Self {
+        self.widgets.insert(name.as_ref().to_string(), widget);
+        self
+    }
+
+    pub fn label<S: AsRef<str>, L: AsRef<str>>(mut self, name: S, label: L) -> Self {
+        self.labels
+            .insert(name.as_ref().to_string(), label.as_ref().to_string());
+        self
+    }
+
+    ///
+    /// Renders the token as hidden input with the name.
+    ///
+    pub fn csrf_token<S: AsRef<str>, T: AsRef<str>>(mut self, name: S, token: T) -> Self {
+        self.csrf_token = Some((name.as_ref().to_string(), token.as_ref().to_string()));
+        self
+    }
+
+    ///
+    /// Renders single field, so the template can place the fields separately.
+    ///
+    pub fn field<S: AsRef<str>>(&self, name: S) -> Option<String> {
+        let name = name.as_ref();
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| self.render_field(field))
+    }
+
+    pub fn render(&self) -> String {
+        let mut parts = vec![];
+
+        if let Some((name, token)) = &self.csrf_token {
+            parts.push(format!(
+                "<input type=\"hidden\" name=\"{}\" value=\"{}\">",
+                escape_html(name),
+                escape_html(token)
+            ));
+        }
+
+        if !self.others.is_empty() {
+            parts.push(errors_html(&self.others));
+        }
+
+        for field in &self.fields {
+            parts.push(self.render_field(field));
+        }
+        parts.join("\n")
+    }
+
+    fn render_field(&self, field: &FieldSchema) -> String {
+        let name = &field.name;
+        let id = format!("id_{}", name);
+        let widget = match self.widgets.get(name) {
+            Some(widget) => widget.clone(),
+            None => Widget::from_schema(field),
+        };
+        let values = self.values.get(name).map(Vec::as_slice).unwrap_or_default();
+        let errors = self
+            .field_errors
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let multiple = field.schema["type"].as_str() == Some("array");
+
+        let mut attributes = format!(" name=\"{}\"", escape_html(name));
+        if field.required && widget != Widget::Checkbox {
+            attributes.push_str(" required");
+        }
+
+        let schema = item_schema(field);
+        if let Some(min_length) = schema["minLength"].as_u64() {
+            attributes.push_str(&format!(" minlength=\"{}\"", min_length));
+        }
+        if let Some(max_length) = schema["maxLength"].as_u64() {
+            attributes.push_str(&format!(" maxlength=\"{}\"", max_length));
+        }
+        if !errors.is_empty() {
+            attributes.push_str(" aria-invalid=\"true\"");
+        }
+
+        let id_attribute = format!(" id=\"{}\"", escape_html(&id));
+        let input = |input_type: &str, value: Option<&String>, id_attribute: &str| {
+            let value = match value {
+                Some(value) => format!(" value=\"{}\"", escape_html(value)),
+                None => String::new(),
+            };
+            format!(
+                "<input type=\"{}\"{}{}{}>",
+                input_type, id_attribute, attributes, value
+            )
+        };
+
+        let control = match &widget {
+            Widget::Text | Widget::Email | Widget::Number | Widget::Hidden => {
+                let input_type = match widget {
+                    Widget::Email => "email",
+                    Widget::Number => "number",
+                    Widget::Hidden => "hidden",
+                    _ => "text",
+                };
+
+                if multiple && values.len() > 1 {
+                    // Only the first input is targeted by the label.
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(index, value)| {
+                            let id_attribute = if index == 0 {
+                                id_attribute.as_str()
+                            } else {
+                                ""
+                            };
+                            input(input_type, Some(value), id_attribute)
+                        })
+                        .collect::<Vec<String>>()
+                        .join("")
+                } else {
+                    input(input_type, values.first(), &id_attribute)
+                }
+            }
+            Widget::Password => input("password", None, &id_attribute),
+            Widget::File => {
+                let multiple = if multiple { " multiple" } else { "" };
+                format!(
+                    "<input type=\"file\"{}{}{}>",
+                    id_attribute, attributes, multiple
+                )
+            }
+            Widget::Checkbox => {
+                let checked = values
+                    .first()
+                    .is_some_and(|value| matches!(value.as_str(), "true" | "on" | "1"));
+                let checked = if checked { " checked" } else { "" };
+                format!(
+                    "<input type=\"checkbox\"{}{} value=\"true\"{}>",
+                    id_attribute, attributes, checked
+                )
+            }
+            Widget::TextArea => {
+                let value = values.first().map(escape_html).unwrap_or_default();
+                format!(
+                    "<textarea{}{}>{}</textarea>",
+                    id_attribute, attributes, value
+                )
+            }
+            Widget::Select(options) => {
+                let mut option_tags = vec![];
+                if !field.required && !multiple {
+                    option_tags.push("<option value=\"\"></option>".to_string());
+                }
+
+                for (value, label) in options {
+                    let selected = if values.contains(value) {
+                        " selected"
+                    } else {
+                        ""
+                    };
+                    option_tags.push(format!(
+                        "<option value=\"{}\"{}>{}</option>",
+                        escape_html(value),
+                        selected,
+                        escape_html(label)
+                    ));
+                }
+
+                let multiple = if multiple { " multiple" } else { "" };
+                format!(
+                    "<select{}{}{}>{}</select>",
+                    id_attribute,
+                    attributes,
+                    multiple,
+                    option_tags.join("")
+                )
+            }
+        };
+
+        if widget == Widget::Hidden {
+            return control;
+        }
+
+        let label = match self.labels.get(name) {
+            Some(label) => label.clone(),
+            None => default_label(name),
+        };
+        let class = if errors.is_empty() {
+            "field"
+        } else {
+            "field error"
+        };
+        let mut html = format!(
+            "<div class=\"{}\"><label for=\"{}\">{}</label>{}",
+            class,
+            escape_html(&id),
+            escape_html(label),
+            control
+        );
+        if !errors.is_empty() {
+            html.push_str(&errors_html(errors));
+        }
+        html.push_str("</div>");
+        html
+    }
+}
+
+impl Display for FormHtml {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+
+    use crate::core::forms::FormData;
+    use crate::forms::fields::file_field::{FileField, UploadedFile};
+    use crate::forms::fields::input_field::InputField;
+    use crate::forms::{FormFields, FormValidator, ValidationError};
+
+    use super::{default_label, Widget};
+
+    struct ProfileForm {
+        first_name: InputField<String>,
+        password: InputField<String>,
+        bio: InputField<Option<String>>,
+        tags: InputField<Vec<String>>,
+        avatar: FileField<Option<UploadedFile>>,
+    }
+
+    impl FormValidator for ProfileForm {
+        fn new() -> Self {
+            Self {
+                first_name: InputField::new("first_name").max_length(20),
+                password: InputField::new("password").min_length(8),
+                bio: InputField::new("bio"),
+                tags: InputField::new("tags"),
+                avatar: FileField::new("avatar"),
+            }
+        }
+
+        fn form_fields(&mut self) -> FormFields {
+            vec![
+                Box::new(self.first_name.clone()),
+                Box::new(self.password.clone()),
+                Box::new(self.bio.clone()),
+                Box::new(self.tags.clone()),
+                Box::new(self.avatar.clone()),
+            ]
+        }
+    }
+
+    #[test]
+    fn test_form_html() {
+        assert_eq!("First name", default_label("first_name"));
+
+        let mut values = FormData::new();
+        values.insert("first_name".to_string(), vec!["<John>".to_string()]);
+        values.insert("password".to_string(), vec!["secret".to_string()]);
+        values.insert(
+            "tags".to_string(),
+            vec!["rust".to_string(), "web".to_string()],
+        );
+
+        let mut field_errors = HashMap::new();
+        field_errors.insert(
+            "password".to_string(),
+            vec!["Minimum 8 characters are required.".to_string()],
+        );
+        let error = ValidationError {
+            field_errors,
+            others: vec!["Try again.".to_string()],
+            critical_errors: vec![],
+            values,
+        };
+
+        let form_html = ProfileForm::new()
+            .as_html()
+            .errors(&error)
+            .widget("password", Widget::Password)
+            .widget("bio", Widget::TextArea)
+            .label("bio", "About you")
+            .csrf_token("csrf_token", "abc\"");
+
+        assert_eq!(
+            Some(
+                "<div class=\"field\"><label for=\"id_first_name\">First name</label>\
+                <input type=\"text\" id=\"id_first_name\" name=\"first_name\" required \
+                maxlength=\"20\" value=\"&lt;John&gt;\"></div>"
+                    .to_string()
+            ),
+            form_html.field("first_name")
+        );
+        assert_eq!(
+            Some(
+                "<div class=\"field error\"><label for=\"id_password\">Password</label>\
+                <input type=\"password\" id=\"id_password\" name=\"password\" required \
+                minlength=\"8\" aria-invalid=\"true\">\
+                <ul class=\"errors\"><li>Minimum 8 characters are required.</li></ul></div>"
+                    .to_string()
+            ),
+            form_html.field("password")
+        );
+
+        let html = form_html.to_string();
+        assert!(html.starts_with(
+            "<input type=\"hidden\" name=\"csrf_token\" value=\"abc&quot;\">\n\
+            <ul class=\"errors\"><li>Try again.</li></ul>"
+        ));
+        assert!(html.contains("<label for=\"id_bio\">About you</label><textarea id=\"id_bio\" name=\"bio\"></textarea>"));
+        assert!(html
+            .contains("value=\"rust\"><input type=\"text\" name=\"tags\" required value=\"web\">"));
+        assert!(html.contains("<input type=\"file\" id=\"id_avatar\" name=\"avatar\">"));
+        assert!(!html.contains("secret"));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod fields;
+pub mod html;
 
 use std::collections::HashMap;
 use std::future::Future;
@@ -6,20 +7,44 @@ use std::vec;
 
 use serde::{Deserialize, Serialize};
 
-use crate::core::forms::FormFieldError;
+use crate::core::forms::{FormData, FormFieldError};
 use crate::core::request::Request;
 
 use crate::forms::fields::AbstractFields;
+use crate::forms::html::FormHtml;
 use crate::racoon_error;
 
 pub type FormFields = Vec<Box<dyn AbstractFields + Sync + Send>>;
 
+///
+/// Translates the default error message with the translator of the current request if the
+/// `i18n` feature is enabled. See [`crate::core::i18n`] for the message IDs.
+///
+#[allow(unused_variables)]
+pub(crate) fn error_message(id: &str, default: String, args: &[(&str, usize)]) -> String {
+    #[cfg(feature = "i18n")]
+    if let Some(translator) = crate::core::i18n::current() {
+        let mut fluent_args = crate::core::i18n::FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, *value);
+        }
+
+        if let Some(message) = translator.translate(id, Some(&fluent_args)) {
+            return message;
+        }
+    }
+    default
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ValidationError {
     pub field_errors: HashMap<String, Vec<String>>,
     pub others: Vec<String>,
     #[serde(skip_serializing)]
     pub critical_errors: Vec<String>,
+    /// Submitted values of the form, used to display the form again with the errors.
+    #[serde(skip)]
+    pub values: FormData,
 }
 
 pub trait FormValidator: Sized + Send {
@@ -46,16 +71,27 @@ pub trait FormValidator: Sized + Send {
                     Err(error) => {
                         match error {
                             FormFieldError::MaxBodySizeExceed => {
-                                other_errors.push("Max body size exceed.".to_string());
+                                other_errors.push(error_message(
+                                    "form-max-body-size",
+                                    "Max body size exceed.".to_string(),
+                                    &[],
+                                ));
                             }
 
                             FormFieldError::MaxHeaderSizeExceed => {
-                                other_errors.push("Max header size exceed.".to_string());
+                                other_errors.push(error_message(
+                                    "form-max-header-size",
+                                    "Max header size exceed.".to_string(),
+                                    &[],
+                                ));
                             }
 
                             FormFieldError::MaxFileSizeExceed(field_name) => {
-                                let file_size_exceed_error =
-                                    vec!["Max file size exceed.".to_string()];
+                                let file_size_exceed_error = vec![error_message(
+                                    "form-max-file-size",
+                                    "Max file size exceed.".to_string(),
+                                    &[],
+                                )];
                                 if let Some(errors) = field_errors.get_mut(&field_name) {
                                     errors.extend_from_slice(&file_size_exceed_error);
                                 } else {
@@ -64,8 +100,11 @@ pub trait FormValidator: Sized + Send {
                             }
 
                             FormFieldError::MaxValueSizeExceed(field_name) => {
-                                let value_length_exceed_error =
-                                    vec!["Max value length exceed.".to_string()];
+                                let value_length_exceed_error = vec![error_message(
+                                    "form-max-value-length",
+                                    "Max value length exceed.".to_string(),
+                                    &[],
+                                )];
                                 if let Some(errors) = field_errors.get_mut(&field_name) {
                                     errors.extend_from_slice(&value_length_exceed_error);
                                 } else {
@@ -93,10 +132,13 @@ pub trait FormValidator: Sized + Send {
                             field_errors,
                             others: other_errors,
                             critical_errors,
+                            values: FormData::new(),
                         };
                         return Err(validation_error);
                     }
                 };
+            // Fields take their values while validating.
+            let values = form_data.clone();
 
             for mut field in self.form_fields() {
                 let field_name = field.field_name().await;
@@ -123,6 +165,7 @@ pub trait FormValidator: Sized + Send {
                     field_errors,
                     others: vec![],
                     critical_errors,
+                    values,
                 };
                 return Err(validation_error);
             }
@@ -140,4 +183,11 @@ pub trait FormValidator: Sized + Send {
     {
         Box::new(Box::pin(async move { None }))
     }
+
+    ///
+    /// Renders the fields of the form as html widgets. See [`FormHtml`].
+    ///
+    fn as_html(&mut self) -> FormHtml {
+        FormHtml::new(self.form_fields())
+    }
 }
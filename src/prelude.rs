@@ -4,8 +4,11 @@ pub use crate::core::response::Response;
 pub use crate::core::response::status::ResponseStatus;
 pub use crate::core::response::HttpResponse;
 pub use crate::core::response::JsonResponse;
+pub use crate::core::response::HtmlResponse;
 pub use crate::core::path::Path;
+pub use crate::core::middleware::Next;
 pub use crate::core::shortcuts::SingleText;
 pub use crate::core::server::Server;
 pub use crate::view;
 pub use crate::wrap_view;
+pub use crate::handler;